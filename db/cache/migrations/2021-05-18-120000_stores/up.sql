@@ -0,0 +1,7 @@
+-- Node data kept next to the asset cache, like pending spends ledger, which
+-- are stored in their strict encoding under the name of the store
+
+create table sql_stores(
+    name text PRIMARY KEY not null,
+    data blob not null
+);
@@ -0,0 +1,7 @@
+-- Audit log is append-only, so each record is kept in its own row in its
+-- strict encoding
+
+create table sql_audit_records(
+    seq bigint PRIMARY KEY not null,
+    record blob not null
+);
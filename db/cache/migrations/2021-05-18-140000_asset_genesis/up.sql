@@ -0,0 +1,4 @@
+-- Asset genesis is cached together with the rest of the asset data, since
+-- the base schema was missing it
+
+alter table sql_assets add column genesis text not null default '';
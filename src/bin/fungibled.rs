@@ -11,33 +11,46 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use clap::Clap;
-use log::*;
-use std::env;
-
 use rgb_node::error::BootstrapError;
 use rgb_node::fungibled::{main_with_config, Config, Opts};
+use rgb_node::util::config::{self, LayeredOpts};
+use rgb_node::util::daemon::{self, PidFile};
+use rgb_node::util::logging;
 
 fn main() -> Result<(), BootstrapError> {
-    // TODO #60: Parse config file as well
-    let opts: Opts = Opts::parse();
-    let config: Config = opts.into();
+    let mut opts: Opts = config::parse()?;
+    if opts.dump_config {
+        let config: Config = opts.into();
+        print!("{}", config::dump(Opts::DAEMON, config.dump()));
+        return Ok(());
+    }
+
+    // Process has to be detached before any of the threads are started
+    if opts.daemon {
+        daemon::detach()?;
+    }
+    let _pid_file = opts.pid_file.as_ref().map(PidFile::create).transpose()?;
+
+    // Verbosity and log filter are applied by the logger itself, so they
+    // can be changed by configuration reload
+    let log_filter = opts.log_filter.clone().unwrap_or_default();
+    logging::init("fungibled", &opts.log, opts.verbose, &log_filter)?;
 
-    if env::var("RUST_LOG").is_err() {
-        env::set_var(
-            "RUST_LOG",
-            match config.verbose {
-                0 => "error",
-                1 => "warn",
-                2 => "info",
-                3 => "debug",
-                4 => "trace",
-                _ => "trace",
-            },
-        );
+    // User database is not reachable once the daemon is confined
+    let credentials = opts
+        .user
+        .as_deref()
+        .map(daemon::Credentials::resolve)
+        .transpose()?;
+    if opts.chroot {
+        daemon::confine(&opts.data_dir)?;
+        let data_dir = std::mem::replace(&mut opts.data_dir, "/".to_string());
+        opts.config_file = opts
+            .config_file
+            .map(|path| daemon::confined_path(&path, &data_dir));
     }
-    env_logger::init();
-    log::set_max_level(LevelFilter::Trace);
 
+    let mut config: Config = opts.into();
+    config.credentials = credentials;
     main_with_config(config)
 }
@@ -0,0 +1,224 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Human-readable rendering of the assets known to the fungible daemon
+
+use std::collections::BTreeMap;
+
+use lnpbp::strict_encoding::strict_deserialize;
+use rgb::{ContractId, FromBech32};
+use rgb20::Asset;
+use serde_json::json;
+
+use super::{Error, OutputFormat, OutputMode, Runtime};
+use crate::rpc::fungible::AllocationsReq;
+use crate::rpc::{reply, Reply};
+use crate::util::format_amount;
+
+#[derive(Clap, Clone, Debug, Display)]
+#[display(Debug)]
+pub enum Command {
+    /// Lists known assets with their supply and own balance
+    List,
+
+    /// Shows asset metadata and its allocations grouped by outpoint
+    Show {
+        /// Asset contract id
+        #[clap(parse(try_from_str = ContractId::from_bech32_str))]
+        asset: ContractId,
+
+        /// Show only allocations having all of the given labels
+        #[clap(long = "label")]
+        labels: Vec<String>,
+    },
+}
+
+/// Requests all known assets from the daemon, printing server error if
+/// the request has failed
+pub(super) fn known_assets(
+    runtime: &mut Runtime,
+) -> Result<Option<Vec<Asset>>, Error> {
+    match &*runtime.list(OutputFormat::StrictEncode)? {
+        Reply::Failure(failure) => {
+            eprintln!("Server returned error: {}", failure);
+            Ok(None)
+        }
+        Reply::Sync(reply::AssetSync { data, .. }) => {
+            Ok(Some(strict_deserialize(data)?))
+        }
+        _ => {
+            eprintln!(
+                "Unexpected server error; probably you connecting with outdated client version"
+            );
+            Ok(None)
+        }
+    }
+}
+
+impl Command {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        match self {
+            Command::List => {
+                if let Some(assets) = known_assets(&mut runtime)? {
+                    Self::exec_list(assets, runtime.output())?;
+                }
+            }
+            Command::Show { asset, labels } => {
+                let assets = match known_assets(&mut runtime)? {
+                    Some(assets) => assets,
+                    None => return Ok(()),
+                };
+                let asset =
+                    match assets.into_iter().find(|known| *known.id() == asset)
+                    {
+                        Some(asset) => asset,
+                        None => {
+                            eprintln!("Asset {} is not known", asset);
+                            return Ok(());
+                        }
+                    };
+                let req = AllocationsReq {
+                    contract_id: *asset.id(),
+                    labels: labels.into_iter().collect(),
+                };
+                match &*runtime.allocations(req)? {
+                    Reply::Failure(failure) => {
+                        eprintln!("Server returned error: {}", failure);
+                    }
+                    Reply::AssetAllocations(allocations) => {
+                        Self::exec_show(asset, allocations, runtime.output())?;
+                    }
+                    _ => {
+                        eprintln!(
+                            "Unexpected server error; probably you connecting with outdated client version"
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_list(assets: Vec<Asset>, output: OutputMode) -> Result<(), Error> {
+        let list = assets
+            .iter()
+            .map(|asset| {
+                json!({
+                    "id": asset.id().to_string(),
+                    "ticker": asset.ticker(),
+                    "name": asset.name(),
+                    "precision": asset.decimal_precision(),
+                    "supply": asset.supply().known_circulating(),
+                    "balance": asset.known_atomic_value(),
+                })
+            })
+            .collect::<Vec<_>>();
+        output.print(&list, || {
+            if assets.is_empty() {
+                eprintln!("No assets are known");
+                return;
+            }
+            println!(
+                "{:<8} {:<24} {:>24} {:>24}  {}",
+                "TICKER", "NAME", "SUPPLY", "BALANCE", "ID"
+            );
+            for asset in &assets {
+                let precision = *asset.decimal_precision();
+                println!(
+                    "{:<8} {:<24} {:>24} {:>24}  {}",
+                    asset.ticker(),
+                    asset.name(),
+                    format_amount(
+                        *asset.supply().known_circulating(),
+                        precision
+                    ),
+                    format_amount(asset.known_atomic_value(), precision),
+                    asset.id()
+                );
+            }
+        })
+    }
+
+    fn exec_show(
+        asset: Asset,
+        allocations: &BTreeMap<bitcoin::OutPoint, reply::AllocatedAmounts>,
+        output: OutputMode,
+    ) -> Result<(), Error> {
+        let capabilities = reply::AssetCapabilities::with_asset(&asset);
+        let details = json!({
+            "asset": asset,
+            "capabilities": capabilities,
+            "balance": asset.known_atomic_value(),
+            "allocations": allocations
+                .iter()
+                .map(|(outpoint, allocated)| {
+                    (
+                        outpoint.to_string(),
+                        json!({
+                            "status": allocated.status.to_string(),
+                            "amounts": allocated.amounts,
+                            "labels": allocated.labels,
+                        }),
+                    )
+                })
+                .collect::<serde_json::Map<_, _>>(),
+        });
+        output.print(&details, || {
+            let precision = *asset.decimal_precision();
+            println!("Id:          {}", asset.id());
+            println!("Ticker:      {}", asset.ticker());
+            println!("Name:        {}", asset.name());
+            if let Some(description) = asset.description() {
+                println!("Description: {}", description);
+            }
+            println!("Network:     {}", asset.chain());
+            println!("Issued:      {}", asset.date());
+            println!("Precision:   {}", precision);
+            if let Some(capabilities) = capabilities {
+                println!("Capabilities: {}", capabilities);
+            }
+            println!(
+                "Supply:      {}",
+                format_amount(*asset.supply().known_circulating(), precision)
+            );
+            println!(
+                "Issue limit: {}",
+                format_amount(*asset.supply().issue_limit(), precision)
+            );
+            println!(
+                "Balance:     {}",
+                format_amount(asset.known_atomic_value(), precision)
+            );
+            if allocations.is_empty() {
+                println!("Allocations: none");
+                return;
+            }
+            println!("Allocations:");
+            for (outpoint, allocated) in allocations {
+                println!("  {} ({})", outpoint, allocated.status);
+                if !allocated.labels.is_empty() {
+                    let labels = allocated
+                        .labels
+                        .iter()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    println!("    [{}]", labels);
+                }
+                for value in &allocated.amounts {
+                    println!("    {}", format_amount(*value, precision));
+                }
+            }
+        })
+    }
+}
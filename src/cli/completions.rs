@@ -0,0 +1,166 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Shell completion scripts generated from the command-line definitions
+
+use clap::{App, IntoApp};
+
+use super::Opts;
+
+#[derive(Clap, Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display(Debug)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Completion data for a (sub)command
+struct Node {
+    /// Names of the command and its parent commands, space-separated
+    path: String,
+    subcommands: Vec<String>,
+    /// Short and long names of the command flags and options, together
+    /// with their description
+    flags: Vec<(Option<char>, Option<String>, Option<String>)>,
+}
+
+/// Collects completion data for the command and all of its subcommands
+fn collect(app: &App, path: String, nodes: &mut Vec<Node>) {
+    let flags = app
+        .get_arguments()
+        .filter(|arg| arg.get_short().is_some() || arg.get_long().is_some())
+        .map(|arg| {
+            (
+                arg.get_short(),
+                arg.get_long().map(str::to_owned),
+                arg.get_about().map(str::to_owned),
+            )
+        })
+        .collect();
+    nodes.push(Node {
+        path: path.clone(),
+        subcommands: app
+            .get_subcommands()
+            .map(|sub| sub.get_name().to_owned())
+            .collect(),
+        flags,
+    });
+    for sub in app.get_subcommands() {
+        collect(sub, format!("{} {}", path, sub.get_name()), nodes);
+    }
+}
+
+fn bash(bin: &str, nodes: &[Node]) -> String {
+    let func = format!("_{}", bin.replace('-', "_"));
+    let mut script = format!(
+        "{}() {{\n    local cur path word\n    \
+        cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    path=\"{}\"\n    \
+        for word in \"${{COMP_WORDS[@]:1:COMP_CWORD-1}}\"; do\n        \
+        case \"${{path}}:${{word}}\" in\n",
+        func, bin
+    );
+    for node in nodes {
+        for sub in &node.subcommands {
+            script += &format!(
+                "            \"{}:{}\") path=\"{} {}\" ;;\n",
+                node.path, sub, node.path, sub
+            );
+        }
+    }
+    script += "        esac\n    done\n    case \"${path}\" in\n";
+    for node in nodes {
+        let words = node
+            .subcommands
+            .iter()
+            .cloned()
+            .chain(node.flags.iter().flat_map(|(short, long, _)| {
+                short
+                    .map(|short| format!("-{}", short))
+                    .into_iter()
+                    .chain(long.as_ref().map(|long| format!("--{}", long)))
+            }))
+            .collect::<Vec<_>>()
+            .join(" ");
+        script += &format!(
+            "        \"{}\") COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\")) ;;\n",
+            node.path, words
+        );
+    }
+    script += &format!("    esac\n}}\ncomplete -F {} {}\n", func, bin);
+    script
+}
+
+fn fish(bin: &str, nodes: &[Node]) -> String {
+    let func = format!("__{}_path", bin.replace('-', "_"));
+    let mut script = format!(
+        "function {}\n    set -l path {}\n    \
+        for word in (commandline -opc)[2..-1]\n        \
+        switch \"$path:$word\"\n",
+        func, bin
+    );
+    for node in nodes {
+        for sub in &node.subcommands {
+            script += &format!(
+                "            case '{}:{}'\n                set path '{} {}'\n",
+                node.path, sub, node.path, sub
+            );
+        }
+    }
+    script += "        end\n    end\n    echo $path\nend\n";
+    for node in nodes {
+        let condition = format!("test ({}) = '{}'", func, node.path);
+        if !node.subcommands.is_empty() {
+            script += &format!(
+                "complete -c {} -f -n \"{}\" -a '{}'\n",
+                bin,
+                condition,
+                node.subcommands.join(" ")
+            );
+        }
+        for (short, long, about) in &node.flags {
+            script += &format!("complete -c {} -n \"{}\"", bin, condition);
+            if let Some(short) = short {
+                script += &format!(" -s {}", short);
+            }
+            if let Some(long) = long {
+                script += &format!(" -l {}", long);
+            }
+            if let Some(about) = about {
+                let about = about.lines().next().unwrap_or_default();
+                script += &format!(" -d '{}'", about.replace('\'', "\\'"));
+            }
+            script += "\n";
+        }
+    }
+    script
+}
+
+/// Generates completion script for the command-line tool
+pub fn generate(shell: Shell) -> String {
+    let mut app = Opts::into_app();
+    // Propagates global arguments and adds help arguments to all subcommands
+    app._build();
+    let bin = app.get_name().to_owned();
+    let mut nodes = vec![];
+    collect(&app, bin.clone(), &mut nodes);
+    match shell {
+        Shell::Bash => bash(&bin, &nodes),
+        // Zsh is able to use bash completions with `bashcompinit`
+        Shell::Zsh => format!(
+            "autoload -U +X bashcompinit && bashcompinit\n{}",
+            bash(&bin, &nodes)
+        ),
+        Shell::Fish => fish(&bin, &nodes),
+    }
+}
@@ -15,10 +15,14 @@ use core::fmt::Display;
 use core::str::FromStr;
 use std::path::PathBuf;
 
+use bitcoin::OutPoint;
 use internet2::zmqsocket::ZmqSocketAddr;
 use lnpbp::Chain;
 
-use super::{fungible, stash, Error, Runtime};
+use super::{
+    asset, completions, consignment, fungible, stash, supervisor, Error,
+    OutputMode, Runtime,
+};
 use crate::constants::*;
 
 #[derive(Clap, Clone, Debug, Display)]
@@ -34,6 +38,18 @@ pub struct Opts {
     #[clap(short, long, global = true, parse(from_occurrences))]
     pub verbose: u8,
 
+    /// Presentation of the command results: human-readable tables or
+    /// machine-readable data
+    #[clap(
+        short,
+        long,
+        global = true,
+        arg_enum,
+        default_value = "table",
+        env = "RGB_CLI_OUTPUT"
+    )]
+    pub output: OutputMode,
+
     /// Data directory path
     #[clap(short, long, default_value = RGB_DATA_DIR, env = "RGB_DATA_DIR")]
     pub data_dir: String,
@@ -46,11 +62,17 @@ pub struct Opts {
     #[clap(short, long, default_value = STASHD_RPC_ENDPOINT)]
     pub stash_endpoint: String,
 
+    /// RPC endpoint of `rgbd` supervisor
+    #[clap(long, default_value = RGBD_RPC_ENDPOINT, env = "RGB_RGBD_RPC")]
+    pub rgbd_endpoint: String,
+
     /// Command to execute
     #[clap(subcommand)]
     pub command: Command,
 
-    /// Bitcoin network to use
+    /// Bitcoin network to use (`bitcoin`, `testnet`, `signet` or `regtest`);
+    /// private signet and regtest networks are given as
+    /// `signet:<genesis hash>` and `regtest:<genesis hash>`
     #[clap(short, long, default_value = RGB_NETWORK, env = "RGB_NETWORK")]
     pub network: Chain,
 }
@@ -70,12 +92,52 @@ pub enum Command {
         subcommand: stash::GenesisCommand,
     },
 
+    /// Creates blinded outpoint for receiving assets, keeping its blinding
+    /// factor in the node
+    Blind {
+        /// Original outpoint in `txid:vout` format
+        outpoint: OutPoint,
+    },
+
+    /// Validation, acceptance and inspection of consignments read from files
+    /// or standard input
+    Consignment {
+        /// Subcommand specifying particular operation
+        #[clap(subcommand)]
+        subcommand: consignment::Command,
+    },
+
+    /// Prints shell completion script; install it with e.g.
+    /// `rgb-cli completions bash > /etc/bash_completion.d/rgb-cli`
+    Completions {
+        #[clap(arg_enum)]
+        shell: completions::Shell,
+    },
+
+    /// Human-readable listing and details of the known RGB-20 assets
+    Asset {
+        /// Subcommand specifying particular operation
+        #[clap(subcommand)]
+        subcommand: asset::Command,
+    },
+
     /// Operations on fungible RGB assets (RGB-20 standard)
     Fungible {
         /// Subcommand specifying particular operation
         #[clap(subcommand)]
         subcommand: fungible::Command,
     },
+
+    /// Status of the daemons launched and restarted by `rgbd` supervisor
+    Daemons,
+
+    /// Imports schemata and contract geneses from the checkpoint file signed
+    /// by one of the trusted checkpoint keys of the stash daemon; history of
+    /// the imported contracts is backfilled by the daemon from its peers
+    Checkpoint {
+        /// Checkpoint file; must be readable by the stash daemon
+        file: PathBuf,
+    },
 }
 
 // We need config structure since not all of the parameters can be specified
@@ -85,9 +147,11 @@ pub enum Command {
 #[display(Debug)]
 pub struct Config {
     pub verbose: u8,
+    pub output: OutputMode,
     pub data_dir: PathBuf,
     pub fungible_endpoint: ZmqSocketAddr,
     pub stash_endpoint: ZmqSocketAddr,
+    pub rgbd_endpoint: ZmqSocketAddr,
     pub network: Chain,
 }
 
@@ -95,12 +159,14 @@ impl From<Opts> for Config {
     fn from(opts: Opts) -> Self {
         let mut me = Self {
             verbose: opts.verbose,
+            output: opts.output,
             network: opts.network,
             ..Config::default()
         };
         me.data_dir = me.parse_param(opts.data_dir);
         me.fungible_endpoint = me.parse_param(opts.fungible_endpoint);
         me.stash_endpoint = me.parse_param(opts.stash_endpoint);
+        me.rgbd_endpoint = me.parse_param(opts.rgbd_endpoint);
         me
     }
 }
@@ -109,6 +175,7 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             verbose: 0,
+            output: OutputMode::default(),
             data_dir: RGB_DATA_DIR
                 .parse()
                 .expect("Error in RGB_DATA_DIR constant value"),
@@ -118,6 +185,9 @@ impl Default for Config {
             stash_endpoint: STASHD_RPC_ENDPOINT
                 .parse()
                 .expect("Broken STASHD_RPC_ENDPOINT value"),
+            rgbd_endpoint: RGBD_RPC_ENDPOINT
+                .parse()
+                .expect("Broken RGBD_RPC_ENDPOINT value"),
             network: RGB_NETWORK
                 .parse()
                 .expect("Error in RGB_NETWORK constant value"),
@@ -128,9 +198,18 @@ impl Default for Config {
 impl Command {
     pub fn exec(self, runtime: Runtime) -> Result<(), Error> {
         match self {
+            Command::Blind { outpoint } => fungible::blind(runtime, outpoint),
+            Command::Completions { shell } => {
+                print!("{}", completions::generate(shell));
+                Ok(())
+            }
+            Command::Consignment { subcommand } => subcommand.exec(runtime),
+            Command::Asset { subcommand } => subcommand.exec(runtime),
             Command::Fungible { subcommand } => subcommand.exec(runtime),
             Command::Schema { subcommand } => subcommand.exec(runtime),
             Command::Genesis { subcommand } => subcommand.exec(runtime),
+            Command::Daemons => supervisor::status(runtime),
+            Command::Checkpoint { file } => stash::checkpoint(runtime, file),
         }
     }
 }
@@ -0,0 +1,393 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Consignment commands reading consignments from files or standard input.
+//! Their exit codes reflect the result of the operation, so they can be used
+//! from scripts.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process;
+
+use bitcoin::OutPoint;
+use lnpbp::seals::OutpointReveal;
+use lnpbp::strict_encoding::strict_deserialize;
+use rgb::validation::{Status, Validity};
+use rgb::Consignment;
+use serde_json::json;
+
+use super::{Error, OutputMode, Runtime};
+use crate::rpc::fungible::{AcceptReq, ValidateReq};
+use crate::rpc::stash::AcceptRequest;
+use crate::rpc::Reply;
+use crate::util::MagicNumber;
+
+/// Operation succeeded; consignment is valid
+pub const EXIT_OK: i32 = 0;
+/// Consignment is invalid
+pub const EXIT_INVALID: i32 = 1;
+/// Consignment is valid, but some of its witness transactions are not known
+/// or do not have enough confirmations yet
+pub const EXIT_UNRESOLVED: i32 = 2;
+/// Node has rejected or failed to process the request
+pub const EXIT_FAILURE: i32 = 3;
+
+#[derive(Clap, Clone, Debug, Display)]
+#[display(Debug)]
+pub enum Command {
+    /// Validates consignment without accepting it
+    Validate {
+        /// Consignment file, binary or Bech32-encoded; if omitted or `-`,
+        /// consignment is read from the standard input
+        consignment: Option<PathBuf>,
+
+        /// Number of confirmations required for the consignment witness
+        /// transactions; overrides node configuration
+        #[clap(long)]
+        min_confirmations: Option<u32>,
+
+        /// Validates binary consignment file by the stash daemon without
+        /// loading it into memory, which is suitable for very large
+        /// consignments. The file must be accessible to the stash daemon;
+        /// confirmations of the witness transactions are not checked.
+        #[clap(long)]
+        streaming: bool,
+    },
+
+    /// Validates and accepts consignment
+    Accept {
+        /// Consignment file, binary or Bech32-encoded; if omitted or `-`,
+        /// consignment is read from the standard input
+        consignment: Option<PathBuf>,
+
+        #[clap(flatten)]
+        reveal: RevealOpts,
+
+        /// Number of confirmations required for the consignment witness
+        /// transactions; overrides node configuration
+        #[clap(long)]
+        min_confirmations: Option<u32>,
+    },
+
+    /// Shows consignment summary without validating it
+    Inspect {
+        /// Consignment file, binary or Bech32-encoded; if omitted or `-`,
+        /// consignment is read from the standard input
+        consignment: Option<PathBuf>,
+
+        #[clap(flatten)]
+        reveal: RevealOpts,
+    },
+
+    /// Re-validates consignment and explains each of the validation steps,
+    /// showing which ancestor, anchor, witness transaction or schema rule
+    /// the failures are caused by
+    Explain {
+        /// Consignment file, binary or Bech32-encoded; if omitted or `-`,
+        /// consignment is read from the standard input
+        consignment: Option<PathBuf>,
+    },
+}
+
+#[derive(Clap, Clone, PartialEq, Eq, Debug, Display)]
+#[display(Debug)]
+pub struct RevealOpts {
+    /// Locally-controlled outpoint receiving assets; may be omitted if the
+    /// outpoint was blinded by the node with the `blind` command
+    #[clap(long)]
+    pub outpoint: Option<OutPoint>,
+
+    /// Outpoint blinding factor (generated when the invoice was created)
+    #[clap(long)]
+    pub blinding_factor: Option<u64>,
+}
+
+impl RevealOpts {
+    fn reveal_outpoints(&self) -> Result<Vec<OutpointReveal>, Error> {
+        match (self.outpoint, self.blinding_factor) {
+            (Some(outpoint), Some(blinding)) => Ok(vec![OutpointReveal {
+                blinding,
+                txid: outpoint.txid,
+                vout: outpoint.vout,
+            }]),
+            (None, None) => Ok(vec![]),
+            _ => {
+                eprintln!(
+                    "Both outpoint and its blinding factor must be provided"
+                );
+                Err(Error::DataInconsistency)
+            }
+        }
+    }
+}
+
+/// Reads consignment from the file or standard input. Consignment may be
+/// given as a binary file with magic number, Bech32 string or raw strict
+/// encoded data.
+fn read_consignment(path: Option<PathBuf>) -> Result<Consignment, Error> {
+    let (name, data) = match path {
+        Some(path) if path.to_str() != Some("-") => {
+            let name = format!("{:?}", path);
+            let data = fs::read(&path)
+                .map_err(|_| Error::InputFileIoError(name.clone()))?;
+            (name, data)
+        }
+        _ => {
+            let mut data = vec![];
+            io::stdin().read_to_end(&mut data)?;
+            (s!("<stdin>"), data)
+        }
+    };
+    debug!(
+        "Read {} bytes of consignment data from {}",
+        data.len(),
+        name
+    );
+
+    let magic = MagicNumber::Consignment.to_u32().to_be_bytes();
+    let result = if data.starts_with(&magic) {
+        strict_deserialize(&data[magic.len()..].to_vec())
+            .map_err(|err| err.to_string())
+    } else if let Ok(text) = std::str::from_utf8(&data) {
+        text.trim()
+            .parse::<Consignment>()
+            .map_err(|err| err.to_string())
+    } else {
+        strict_deserialize(&data).map_err(|err| err.to_string())
+    };
+    result.map_err(|err| Error::InputFileFormatError(name, err))
+}
+
+/// Machine-readable representation of the validation status
+pub(super) fn status_json(status: &Status) -> serde_json::Value {
+    let strings = |items: Vec<String>| json!(items);
+    json!({
+        "validity": format!("{:?}", status.validity()),
+        "failures": strings(status.failures.iter().map(|f| f.to_string()).collect()),
+        "warnings": strings(status.warnings.iter().map(|w| w.to_string()).collect()),
+        "info": strings(status.info.iter().map(|i| i.to_string()).collect()),
+        "unresolved-txids": strings(
+            status.unresolved_txids.iter().map(|t| t.to_string()).collect()
+        ),
+    })
+}
+
+fn print_status(status: &Status) {
+    println!("Validity: {:?}", status.validity());
+    for failure in &status.failures {
+        println!("Failure: {}", failure);
+    }
+    for warning in &status.warnings {
+        println!("Warning: {}", warning);
+    }
+    for info in &status.info {
+        println!("Info: {}", info);
+    }
+    for txid in &status.unresolved_txids {
+        println!("Unresolved transaction: {}", txid);
+    }
+}
+
+/// Prints the reply common for validation and acceptance and returns the
+/// corresponding exit code
+fn report(output: OutputMode, reply: &Reply) -> Result<i32, Error> {
+    Ok(match reply {
+        Reply::Failure(failure) => {
+            eprintln!("Server returned error: {}", failure);
+            EXIT_FAILURE
+        }
+        Reply::ValidationStatus(status) => {
+            output.print(&status_json(status), || print_status(status))?;
+            match status.validity() {
+                Validity::Valid => EXIT_OK,
+                Validity::UnresolvedTransactions => EXIT_UNRESOLVED,
+                Validity::Invalid => EXIT_INVALID,
+            }
+        }
+        Reply::Pending(pending) => {
+            let data = json!({
+                "validity": "Pending",
+                "consignment-id": pending.consignment_id.to_string(),
+                "required": pending.required,
+                "confirmations": pending
+                    .confirmations
+                    .iter()
+                    .map(|(txid, confirmations)| {
+                        (txid.to_string(), json!(confirmations))
+                    })
+                    .collect::<serde_json::Map<_, _>>(),
+            });
+            output.print(&data, || {
+                println!("Validity: Pending");
+                println!("Required confirmations: {}", pending.required);
+                for (txid, confirmations) in &pending.confirmations {
+                    println!(
+                        "Unconfirmed transaction: {} ({} confirmations)",
+                        txid, confirmations
+                    );
+                }
+            })?;
+            EXIT_UNRESOLVED
+        }
+        Reply::Success => {
+            eprintln!("Consignment successfully accepted");
+            EXIT_OK
+        }
+        Reply::AlreadyAccepted(consignment_id) => {
+            eprintln!("Consignment {} was already accepted", consignment_id);
+            EXIT_OK
+        }
+        _ => {
+            eprintln!(
+                "Unexpected server error; probably you connecting with outdated client version"
+            );
+            EXIT_FAILURE
+        }
+    })
+}
+
+impl Command {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        let code = match self {
+            Command::Validate {
+                consignment: Some(path),
+                streaming: true,
+                ..
+            } => {
+                info!("Validating consignment file...");
+                let path = fs::canonicalize(&path).map_err(|_| {
+                    Error::InputFileIoError(format!("{:?}", path))
+                })?;
+                let reply = runtime
+                    .validate_file(path.to_string_lossy().to_string())?;
+                report(runtime.output(), &reply)?
+            }
+            Command::Validate {
+                streaming: true, ..
+            } => {
+                eprintln!("Streaming validation requires consignment file");
+                Err(Error::DataInconsistency)?
+            }
+            Command::Validate {
+                consignment,
+                min_confirmations,
+                ..
+            } => {
+                info!("Validating consignment...");
+                let consignment = read_consignment(consignment)?;
+                let reply = runtime.validate(ValidateReq {
+                    consignment: consignment.into(),
+                    min_confirmations,
+                })?;
+                report(runtime.output(), &reply)?
+            }
+            Command::Accept {
+                consignment,
+                reveal,
+                min_confirmations,
+            } => {
+                info!("Accepting consignment...");
+                let consignment = read_consignment(consignment)?;
+                let reply = runtime.accept(AcceptReq {
+                    consignment: consignment.into(),
+                    reveal_outpoints: reveal.reveal_outpoints()?,
+                    min_confirmations,
+                })?;
+                report(runtime.output(), &reply)?
+            }
+            Command::Inspect {
+                consignment,
+                reveal,
+            } => {
+                info!("Inspecting consignment...");
+                let consignment = read_consignment(consignment)?;
+                let reply = runtime.inspect(AcceptRequest {
+                    consignment: consignment.into(),
+                    reveal_outpoints: reveal.reveal_outpoints()?,
+                })?;
+                match &*reply {
+                    Reply::ConsignmentSummary(summary) => {
+                        let to_strings = |items: Vec<String>| json!(items);
+                        let data = json!({
+                            "contract-ids": to_strings(
+                                summary.contract_ids.iter().map(ToString::to_string).collect()
+                            ),
+                            "schema-id": summary.schema_id.to_string(),
+                            "transitions": summary.transitions,
+                            "extensions": summary.extensions,
+                            "endpoints": to_strings(
+                                summary.endpoints.iter().map(|(_, endpoint)| endpoint.to_string()).collect()
+                            ),
+                            "txids": to_strings(
+                                summary.txids.iter().map(ToString::to_string).collect()
+                            ),
+                            "received-value": summary.received_value,
+                        });
+                        runtime.output().print(&data, || {
+                            for contract_id in &summary.contract_ids {
+                                println!("Contract: {}", contract_id);
+                            }
+                            println!("Schema: {}", summary.schema_id);
+                            println!("Transitions: {}", summary.transitions);
+                            println!("Extensions: {}", summary.extensions);
+                            for (node_id, endpoint) in &summary.endpoints {
+                                println!(
+                                    "Endpoint: {} in {}",
+                                    endpoint, node_id
+                                );
+                            }
+                            for txid in &summary.txids {
+                                println!("Witness transaction: {}", txid);
+                            }
+                            println!(
+                                "Received value: {}",
+                                summary.received_value
+                            );
+                        })?;
+                        EXIT_OK
+                    }
+                    reply => report(runtime.output(), reply)?,
+                }
+            }
+            Command::Explain { consignment } => {
+                info!("Explaining consignment validation...");
+                let consignment = read_consignment(consignment)?;
+                let reply = runtime.explain_failure(consignment)?;
+                match &*reply {
+                    Reply::ValidationReport(validation) => {
+                        let mut data = status_json(&validation.status);
+                        data["steps"] = json!(validation.steps);
+                        runtime.output().print(&data, || {
+                            for (no, step) in
+                                validation.steps.iter().enumerate()
+                            {
+                                println!("{:>4}. {}", no + 1, step);
+                            }
+                        })?;
+                        match validation.status.validity() {
+                            Validity::Valid => EXIT_OK,
+                            Validity::UnresolvedTransactions => EXIT_UNRESOLVED,
+                            Validity::Invalid => EXIT_INVALID,
+                        }
+                    }
+                    reply => report(runtime.output(), reply)?,
+                }
+            }
+        };
+        if code != EXIT_OK {
+            process::exit(code);
+        }
+        Ok(())
+    }
+}
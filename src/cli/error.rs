@@ -20,6 +20,9 @@ pub enum Error {
 
     InputFileFormatError(String, String),
 
+    #[from(std::io::Error)]
+    TerminalIoError,
+
     #[from]
     ServiceError(ServiceErrorDomain),
 
@@ -33,6 +36,12 @@ pub enum Error {
     #[from(toml::ser::Error)]
     TomlError,
 
+    #[from]
+    CborError(serde_cbor::Error),
+
+    #[from(rmp_serde::decode::Error)]
+    MsgPackError,
+
     #[from]
     StrictEncoding(lnpbp::strict_encoding::Error),
 
@@ -41,6 +50,9 @@ pub enum Error {
 
     DataInconsistency,
 
+    /// Node rate limit is exceeded; retry after the given number of seconds
+    RateLimited(u32),
+
     UnsupportedFunctionality,
 
     FormatNotSupported,
@@ -16,20 +16,27 @@ use std::fs;
 use std::path::PathBuf;
 
 use bitcoin::consensus::{Decodable, Encodable};
+use bitcoin::hashes::sha256;
 use bitcoin::util::psbt::raw::ProprietaryKey;
 use bitcoin::util::psbt::PartiallySignedTransaction;
-use bitcoin::OutPoint;
+use bitcoin::{OutPoint, Txid};
 use lnpbp::client_side_validation::CommitConceal;
 use lnpbp::seals::{OutpointHash, OutpointReveal};
 use lnpbp::strict_encoding::strict_deserialize;
-use microservices::FileFormat;
 use rgb::prelude::*;
 use rgb20::{Asset, SealCoins};
+use serde_json::json;
 
-use super::{Error, OutputFormat, Runtime};
-use crate::rpc::fungible::{AcceptReq, IssueReq, TransferReq};
+use super::consignment::status_json;
+use super::{Error, OutputFormat, OutputMode, Runtime, TransferWizard};
+use crate::rpc::fungible::{
+    AcceptReq, ComposeWitnessReq, IssueReq, LabelTarget, LedgerFormat,
+    OpenEpochReq, ReceiveAssetReq, Request, ReserveOutpointReq,
+    SetAssetMetaReq, SetLabelReq, TransferReq, ValidateReq, VoutCoins,
+};
 use crate::rpc::{reply, Reply};
 use crate::util::file::ReadWrite;
+use crate::util::{AnchorContainer, DataFormat};
 
 #[derive(Clap, Clone, Debug, Display)]
 #[display(Debug)]
@@ -50,6 +57,13 @@ pub enum Command {
         asset: Genesis,
     },
 
+    /// Registers asset from the consignment genesis without accepting the
+    /// consignment; the asset is known with zero balance
+    ImportConsignment {
+        /// Consignment file
+        consignment: PathBuf,
+    },
+
     Export {
         /// Bech32 representation of the asset ID (contract id of the asset
         /// genesis)
@@ -60,7 +74,12 @@ pub enum Command {
     /// Creates a new asset
     Issue(IssueReq),
 
+    /// Shows asset which would be created by the issue command, without
+    /// creating it
+    PreviewIssue(IssueReq),
+
     /// Creates a blinded version of a given bitcoin transaction outpoint
+    /// locally, without storing the blinding factor in the node
     Blind {
         /// Original outpoint in `txid:vout` format
         outpoint: OutPoint,
@@ -69,10 +88,24 @@ pub enum Command {
     /// Do a transfer of some requested asset to another party
     Transfer(TransferCli),
 
+    /// Guides through the transfer: lists spendable allocations to pick
+    /// inputs from, computes the change and writes the transfer files
+    TransferWizard(TransferWizard),
+
     /// Do a transfer of some requested asset to another party
     Validate {
         /// Consignment file
         consignment: PathBuf,
+
+        /// Number of confirmations required for the consignment witness
+        /// transactions; overrides node configuration
+        #[clap(long)]
+        min_confirmations: Option<u32>,
+
+        /// Executes the request in background, printing the job id which
+        /// can be checked with `job` command
+        #[clap(long)]
+        background: bool,
     },
 
     /// Accepts an incoming payment
@@ -81,11 +114,22 @@ pub enum Command {
         consignment: PathBuf,
 
         /// Locally-controlled outpoint (specified when the invoice was
-        /// created)
-        outpoint: OutPoint,
+        /// created); may be omitted if the outpoint was blinded by the node
+        /// with the `blind` command
+        outpoint: Option<OutPoint>,
 
         /// Outpoint blinding factor (generated when the invoice was created)
-        blinding_factor: u64,
+        blinding_factor: Option<u64>,
+
+        /// Number of confirmations required for the consignment witness
+        /// transactions; overrides node configuration
+        #[clap(long)]
+        min_confirmations: Option<u32>,
+
+        /// Executes the request in background, printing the job id which
+        /// can be checked with `job` command
+        #[clap(long)]
+        background: bool,
     },
 
     /// Adds data from some disclosure to the stash & asset information cache
@@ -99,6 +143,269 @@ pub enum Command {
         /// has to be forgotten
         outpoint: OutPoint,
     },
+
+    /// Restores assets allocated to the outpoint which were forgotten before
+    Restore {
+        /// Bitcoin transaction output which data has to be restored
+        outpoint: OutPoint,
+    },
+
+    /// Blinds the receiving outpoint and creates invoice for the payer
+    Receive(ReceiveAssetReq),
+
+    /// Lists receives which are neither fulfilled nor expired
+    Receives,
+
+    /// Cancels pending receive, releasing the funding output reserved for it
+    CancelReceive {
+        /// Blinded outpoint of the receive
+        blinded: OutpointHash,
+    },
+
+    /// Reserves outpoint for a protocol run outside of the node (like
+    /// channel funding or a swap), so transfers do not spend it
+    Reserve(ReserveOutpointReq),
+
+    /// Releases outpoint reserved with `reserve` command
+    Release {
+        /// Reserved bitcoin transaction output
+        outpoint: OutPoint,
+    },
+
+    /// Removes all data about the asset from the cache and the stash
+    ForgetContract {
+        /// Only list allocations which would be removed
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Asset contract id
+        contract_id: ContractId,
+    },
+
+    /// Removes all known assets from the cache and the stash
+    ForgetAll {
+        /// Only list allocations which would be removed
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Prints client-provided metadata of the asset
+    Meta {
+        /// Asset contract id
+        contract_id: ContractId,
+    },
+
+    /// Sets asset metadata entry (like `label` or `icon`), or removes it if
+    /// the value is not given
+    SetMeta {
+        /// Asset contract id
+        contract_id: ContractId,
+
+        /// Metadata key
+        key: String,
+
+        /// New value of the key
+        value: Option<String>,
+    },
+
+    /// Recomputes asset supply from the contract data kept by the stash and
+    /// reports the cached values which do not match it
+    SupplyReport {
+        /// Asset contract id
+        contract_id: ContractId,
+    },
+
+    /// Reports operations permitted by the contract schema and genesis
+    Capabilities {
+        /// Asset contract id
+        contract_id: ContractId,
+    },
+
+    /// Lists unspent epoch and burn-and-replace seals of the asset
+    EpochSeals {
+        /// Asset contract id
+        contract_id: ContractId,
+    },
+
+    /// Opens new epoch of the burn and replacement procedure by spending the
+    /// current epoch seal
+    OpenEpoch {
+        /// Outpoint receiving the right to open the next epoch
+        #[clap(long)]
+        next_epoch: Option<OutPoint>,
+
+        /// Outpoint receiving the right to burn and replace assets within
+        /// the opened epoch
+        #[clap(long)]
+        burn_replace: Option<OutPoint>,
+
+        /// Asset contract id
+        contract_id: ContractId,
+
+        /// Outpoint of the current epoch seal
+        epoch: OutPoint,
+
+        /// Read partially-signed transaction prototype
+        prototype: PathBuf,
+
+        /// File to save consignment to
+        consignment: PathBuf,
+
+        /// File to save disclosure to
+        disclosure: PathBuf,
+
+        /// File to save updated partially-signed bitcoin transaction to
+        transaction: PathBuf,
+    },
+
+    /// Composes witness transaction spending the asset inputs, with the fee
+    /// paid from the outputs of the registered descriptors and the change
+    /// sent back to them; the resulting PSBT can be used for the transfer
+    ComposeWitness {
+        /// Fee rate in sats per virtual byte
+        #[clap(short, long, default_value = "1")]
+        fee_rate: u64,
+
+        /// Witness transaction output holding the anchor commitment: `tweak`
+        /// or `op-return` [default: depends on the node configuration]
+        #[clap(long)]
+        container: Option<AnchorContainer>,
+
+        /// File to save partially-signed witness transaction to
+        transaction: PathBuf,
+
+        /// Outpoints holding the assets which will be transferred
+        inputs: Vec<OutPoint>,
+    },
+
+    /// Lists outgoing transfers with the stage each of them has reached
+    Transfers,
+
+    /// Reports stage of the outgoing transfer
+    TransferStatus {
+        /// Witness transaction id of the transfer
+        txid: Txid,
+    },
+
+    /// Exports consignment accepted by the node, so it can be presented to
+    /// third parties as a proof of the payment
+    ExportConsignment {
+        /// Id of the accepted consignment, as reported by the
+        /// `consignment_accepted` event
+        consignment_id: sha256::Hash,
+
+        /// File to save consignment to
+        consignment: PathBuf,
+    },
+
+    /// Rebuilds consignment of the earlier transfer for the recipient who
+    /// has lost it
+    Reconsign {
+        /// Witness transaction id of the transfer
+        txid: Txid,
+
+        /// File to save consignment to
+        consignment: PathBuf,
+    },
+
+    /// Exports bundle with everything required to claim the owned
+    /// allocations from scratch on a fresh node: reveal secrets, contract
+    /// histories and funding descriptors
+    ExportClaims {
+        /// Passphrase encrypting the bundle
+        #[clap(long, env = "RGB_CLAIMS_PASSPHRASE")]
+        passphrase: String,
+
+        /// File to save the bundle to
+        output: PathBuf,
+    },
+
+    /// Imports claims bundle exported from another node
+    ImportClaims {
+        /// Passphrase the bundle was encrypted with
+        #[clap(long, env = "RGB_CLAIMS_PASSPHRASE")]
+        passphrase: String,
+
+        /// File with the bundle
+        input: PathBuf,
+    },
+
+    /// Backs up cache and stash into the configured backup target right
+    /// away, rotating the older backups
+    Backup,
+
+    /// Reports bitcoin balance of the registered descriptors which pays
+    /// the witness transaction fees
+    FundingBalance,
+
+    /// Returns unused bitcoin address for topping up the funds paying the
+    /// witness transaction fees
+    FundingAddress,
+
+    /// Labels outpoint or asset allocation for bookkeeping
+    Label {
+        /// Remove the label instead of adding it
+        #[clap(long)]
+        remove: bool,
+
+        /// Outpoint or asset allocation as `<contract_id>@<outpoint>`
+        target: LabelTarget,
+
+        /// Label text
+        label: String,
+    },
+
+    /// Reports readiness of the daemon and the services it depends on
+    Status,
+
+    /// Exports ledger of the allocation changes mined at or below the given
+    /// block height, for accounting and tax reporting
+    ExportLedger {
+        /// Block height of the ledger snapshot
+        height: u32,
+
+        /// Ledger format (`csv` or `json`)
+        #[clap(short, long, default_value = "csv")]
+        format: LedgerFormat,
+
+        /// File to save the ledger to; if not given, the ledger is printed
+        /// to the standard output
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Imports allocations of the known assets from the snapshot file with
+    /// `contract_id,outpoint,node_id,index,amount` records
+    BulkImport {
+        /// Snapshot file; it is read by the daemon, so it must be accessible
+        /// from the daemon host
+        snapshot: PathBuf,
+
+        /// Number of records applied to the cache at once; defaults to the
+        /// daemon setting
+        #[clap(long)]
+        batch_size: Option<u32>,
+    },
+
+    /// Exports records of the audit log made within the given time range
+    AuditLog {
+        /// Unix timestamp of the earliest exported record
+        #[clap(long, default_value = "0")]
+        from: i64,
+
+        /// Unix timestamp following the latest exported record
+        #[clap(long, default_value = "9223372036854775807")]
+        to: i64,
+    },
+
+    /// Makes the daemon re-read tunable settings from its configuration file
+    ReloadConfig,
+
+    /// Reports status of the request executed in background
+    Job {
+        /// Job id printed when the request was queued
+        id: u64,
+    },
 }
 
 #[derive(Clap, Clone, PartialEq, Debug, Display)]
@@ -113,6 +420,22 @@ pub struct TransferCli {
     #[clap(short, long)]
     pub allocate: Vec<SealCoins>,
 
+    /// Spend only inputs which are already confirmed
+    #[clap(long)]
+    pub confirmed_only: bool,
+
+    /// Witness transaction output holding the anchor commitment: `tweak`
+    /// for the pay-to-contract tweak of an existing output key or
+    /// `op-return` for an OP_RETURN output added to the transaction
+    /// [default: depends on the node configuration]
+    #[clap(long)]
+    pub container: Option<AnchorContainer>,
+
+    /// Pays to outputs of the witness transaction itself, in form of
+    /// <amount>@<vout>, in addition to the blinded receiver
+    #[clap(long)]
+    pub theirs_witness: Vec<VoutCoins>,
+
     /// Whom to pay
     pub receiver: OutpointHash,
 
@@ -140,6 +463,101 @@ pub struct TransferCli {
     pub transaction: PathBuf,
 }
 
+/// Reads partially-signed witness transaction prototype, marking the keys
+/// of its outputs for the LNPBP1/2 commitments
+fn read_prototype(
+    prototype: &PathBuf,
+) -> Result<PartiallySignedTransaction, Error> {
+    debug!(
+        "Reading partially-signed transaction from file {:?}",
+        prototype
+    );
+    let filepath = format!("{:?}", &prototype);
+    let file = fs::File::open(prototype)
+        .map_err(|_| Error::InputFileIoError(format!("{:?}", filepath)))?;
+    let mut psbt =
+        PartiallySignedTransaction::consensus_decode(file).map_err(|err| {
+            Error::InputFileFormatError(
+                format!("{:?}", filepath),
+                format!("{}", err),
+            )
+        })?;
+
+    for (index, output) in &mut psbt.outputs.iter_mut().enumerate() {
+        if let Some(key) = output.bip32_derivation.keys().next() {
+            let key = key.clone();
+            output.proprietary.insert(
+                ProprietaryKey {
+                    prefix: b"RGB".to_vec(),
+                    subtype: PSBT_OUT_PUBKEY,
+                    key: vec![],
+                },
+                key.key.serialize().to_vec(),
+            );
+            debug!("Output #{} commitment key will be {}", index, key);
+        } else {
+            warn!(
+                "No public key information found for output #{}; \
+                LNPBP1/2 commitment will be impossible.\
+                In order to allow commitment pls add known keys derivation \
+                information to PSBT output map",
+                index
+            );
+        }
+    }
+    trace!("{:?}", psbt);
+    Ok(psbt)
+}
+
+fn print_reveal(
+    output: OutputMode,
+    reveal: &OutpointReveal,
+) -> Result<(), Error> {
+    let data = json!({
+        "blinded": reveal.commit_conceal().to_string(),
+        "blinding": reveal.blinding,
+    });
+    output.print(&data, || {
+        eprint!("Blinded outpoint: ");
+        println!("{}", reveal.commit_conceal());
+        eprint!("Outpoint blinding secret: ");
+        println!("{}", reveal.blinding);
+    })
+}
+
+fn transfer_json(status: &reply::TransferStatus) -> serde_json::Value {
+    json!({
+        "txid": status.txid.to_string(),
+        "contract_id": status.contract_id.to_string(),
+        "amount": status.amount,
+        "state": status.state.to_string(),
+        "created": status.created,
+        "updated": status.updated,
+        "reason": status.reason,
+    })
+}
+
+/// Asks the node to blind the outpoint, keeping the blinding factor in the
+/// node for accepting consignments sent to the blinded outpoint
+pub fn blind(mut runtime: Runtime, outpoint: OutPoint) -> Result<(), Error> {
+    info!("Blinding outpoint ...");
+
+    match &*runtime.blind(outpoint)? {
+        Reply::Failure(failure) => {
+            eprintln!("Server returned error: {}", failure);
+        }
+        Reply::OutpointReveal(reveal) => {
+            print_reveal(runtime.output(), reveal)?;
+        }
+        _ => {
+            eprintln!(
+                "Unexpected server error; probably you connecting with outdated client version"
+            );
+        }
+    }
+    Ok(())
+}
+
 impl Command {
     pub fn exec(self, runtime: Runtime) -> Result<(), Error> {
         match self {
@@ -149,36 +567,1010 @@ impl Command {
             Command::Import { ref asset } => {
                 self.exec_import(runtime, asset.clone())
             }
+            Command::ImportConsignment { ref consignment } => {
+                self.exec_import_consignment(runtime, consignment.clone())
+            }
             Command::Export { asset } => self.exec_export(runtime, asset),
             Command::Blind { outpoint } => {
                 info!("Blinding outpoint ...");
                 let outpoint_reveal = OutpointReveal::from(outpoint);
-                eprint!("Blinded outpoint: ");
-                println!("{}", outpoint_reveal.commit_conceal());
-                eprint!("Outpoint blinding secret: ");
-                println!("{}", outpoint_reveal.blinding);
-                Ok(())
+                print_reveal(runtime.output(), &outpoint_reveal)
             }
             Command::Issue(issue) => issue.exec(runtime),
+            Command::PreviewIssue(issue) => issue.exec_preview(runtime),
             Command::Transfer(transfer) => transfer.exec(runtime),
-            Command::Validate { ref consignment } => {
-                self.exec_validate(runtime, consignment.clone())
-            }
+            Command::TransferWizard(wizard) => wizard.exec(runtime),
+            Command::Validate {
+                ref consignment,
+                min_confirmations,
+                background,
+            } => self.exec_validate(
+                runtime,
+                consignment.clone(),
+                min_confirmations,
+                background,
+            ),
             Command::Accept {
                 ref consignment,
                 outpoint,
                 blinding_factor,
+                min_confirmations,
+                background,
             } => self.exec_accept(
                 runtime,
                 consignment.clone(),
                 outpoint,
                 blinding_factor,
+                min_confirmations,
+                background,
             ),
             Command::Enclose { ref disclosure } => {
                 self.exec_enclose(runtime, disclosure.clone())
             }
-            Command::Forget { outpoint } => self.exec_forget(runtime, outpoint),
+            Command::Forget { outpoint } => self.exec_forget(runtime, outpoint),
+            Command::Restore { outpoint } => {
+                self.exec_restore(runtime, outpoint)
+            }
+            Command::Receive(ref req) => {
+                self.exec_receive(runtime, req.clone())
+            }
+            Command::Receives => self.exec_receives(runtime),
+            Command::CancelReceive { blinded } => {
+                self.exec_cancel_receive(runtime, blinded)
+            }
+            Command::Reserve(ref req) => {
+                self.exec_reserve(runtime, req.clone())
+            }
+            Command::Release { outpoint } => {
+                self.exec_release(runtime, outpoint)
+            }
+            Command::ForgetContract {
+                dry_run,
+                contract_id,
+            } => self.exec_forget_contract(runtime, Some(contract_id), dry_run),
+            Command::ForgetAll { dry_run } => {
+                self.exec_forget_contract(runtime, None, dry_run)
+            }
+            Command::Meta { contract_id } => {
+                self.exec_meta(runtime, contract_id)
+            }
+            Command::SetMeta {
+                contract_id,
+                ref key,
+                ref value,
+            } => self.exec_set_meta(
+                runtime,
+                SetAssetMetaReq {
+                    contract_id,
+                    key: key.clone(),
+                    value: value.clone(),
+                },
+            ),
+            Command::SupplyReport { contract_id } => {
+                self.exec_supply_report(runtime, contract_id)
+            }
+            Command::Capabilities { contract_id } => {
+                self.exec_capabilities(runtime, contract_id)
+            }
+            Command::EpochSeals { contract_id } => {
+                self.exec_epoch_seals(runtime, contract_id)
+            }
+            Command::OpenEpoch {
+                next_epoch,
+                burn_replace,
+                contract_id,
+                epoch,
+                ref prototype,
+                ref consignment,
+                ref disclosure,
+                ref transaction,
+            } => {
+                let seal = |outpoint: OutPoint| {
+                    SealDefinition::TxOutpoint(OutpointReveal::from(outpoint))
+                };
+                let req = OpenEpochReq {
+                    contract_id,
+                    witness: read_prototype(prototype)?,
+                    epoch,
+                    next_epoch: next_epoch.map(seal),
+                    burn_replace: burn_replace.map(seal),
+                };
+                self.exec_open_epoch(
+                    runtime,
+                    req,
+                    consignment,
+                    disclosure,
+                    transaction,
+                )
+            }
+            Command::ComposeWitness {
+                fee_rate,
+                container,
+                ref transaction,
+                ref inputs,
+            } => self.exec_compose_witness(
+                runtime,
+                ComposeWitnessReq {
+                    inputs: inputs.iter().copied().collect(),
+                    fee_rate,
+                    container,
+                },
+                transaction,
+            ),
+            Command::Transfers => self.exec_transfers(runtime),
+            Command::TransferStatus { txid } => {
+                self.exec_transfer_status(runtime, txid)
+            }
+            Command::ExportConsignment {
+                consignment_id,
+                ref consignment,
+            } => self.exec_export_consignment(
+                runtime,
+                consignment_id,
+                consignment,
+            ),
+            Command::Reconsign {
+                txid,
+                ref consignment,
+            } => self.exec_reconsign(runtime, txid, consignment),
+            Command::ExportClaims {
+                ref passphrase,
+                ref output,
+            } => self.exec_export_claims(runtime, passphrase, output),
+            Command::ImportClaims {
+                ref passphrase,
+                ref input,
+            } => self.exec_import_claims(runtime, passphrase, input),
+            Command::Backup => self.exec_backup(runtime),
+            Command::FundingBalance => self.exec_funding_balance(runtime),
+            Command::FundingAddress => self.exec_funding_address(runtime),
+            Command::Label {
+                remove,
+                target,
+                ref label,
+            } => self.exec_label(
+                runtime,
+                SetLabelReq {
+                    target,
+                    label: label.clone(),
+                    set: !remove,
+                },
+            ),
+            Command::Status => self.exec_status(runtime),
+            Command::ExportLedger {
+                height,
+                format,
+                ref output,
+            } => self.exec_export_ledger(runtime, height, format, output),
+            Command::BulkImport {
+                ref snapshot,
+                batch_size,
+            } => self.exec_bulk_import(runtime, snapshot, batch_size),
+            Command::AuditLog { from, to } => {
+                self.exec_audit_log(runtime, from, to)
+            }
+            Command::ReloadConfig => self.exec_reload_config(runtime),
+            Command::Job { id } => self.exec_job(runtime, id),
+        }
+    }
+
+    fn exec_status(&self, mut runtime: Runtime) -> Result<(), Error> {
+        match &*runtime.status()? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Health(status) => {
+                let data = json!({
+                    "ready": status.is_ready(),
+                    "stash-connected": status.stash_connected,
+                    "cache-available": status.cache_available,
+                    "schema-registered": status.schema_registered,
+                    "chain-height": status.chain_height,
+                    "chain-lag": status.chain_lag,
+                    "cache": {
+                        "hot": status.cache.hot,
+                        "cold": status.cache.cold,
+                        "memory-used": status.cache.memory_used,
+                        "memory-budget": status.cache.memory_budget,
+                        "hits": status.cache.hits,
+                        "misses": status.cache.misses,
+                        "evictions": status.cache.evictions,
+                        "hit-rate": status.cache.hit_rate(),
+                    },
+                });
+                runtime.output().print(&data, || {
+                    println!("Stash connected: {}", status.stash_connected);
+                    println!("Cache available: {}", status.cache_available);
+                    println!("Schema registered: {}", status.schema_registered);
+                    match (status.chain_height, status.chain_lag) {
+                        (Some(height), Some(lag)) => println!(
+                            "Chain backend: height {}, last block {} s ago",
+                            height, lag
+                        ),
+                        _ => println!("Chain backend: unavailable"),
+                    }
+                    println!("Cache: {}", status.cache);
+                    if let Some(rate) = status.cache.hit_rate() {
+                        println!(
+                            "Cache hit rate: {:.1}% ({} evictions)",
+                            rate * 100.0,
+                            status.cache.evictions
+                        );
+                    }
+                    if status.is_ready() {
+                        eprintln!("Daemon is ready");
+                    } else {
+                        eprintln!("Daemon is not ready");
+                    }
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_export_ledger(
+        &self,
+        mut runtime: Runtime,
+        height: u32,
+        format: LedgerFormat,
+        output: &Option<PathBuf>,
+    ) -> Result<(), Error> {
+        info!("Exporting allocation ledger ...");
+
+        match &*runtime.export_ledger(height, format)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Ledger(ledger) => match output {
+                Some(filename) => {
+                    fs::write(filename, &ledger.data)?;
+                    eprintln!(
+                        "Ledger at height {} is saved to {}",
+                        ledger.height,
+                        filename.display()
+                    );
+                }
+                None => println!("{}", String::from_utf8_lossy(&ledger.data)),
+            },
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_bulk_import(
+        &self,
+        mut runtime: Runtime,
+        snapshot: &PathBuf,
+        batch_size: Option<u32>,
+    ) -> Result<(), Error> {
+        info!("Importing allocations from {} ...", snapshot.display());
+        let path = fs::canonicalize(snapshot).map_err(|_| {
+            Error::InputFileIoError(snapshot.display().to_string())
+        })?;
+        match &*runtime.bulk_import(
+            path.to_string_lossy().to_string(),
+            batch_size.unwrap_or_default(),
+        )? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::BulkImport(report) => {
+                let data = json!({
+                    "records": report.records,
+                    "imported": report.imported,
+                    "duplicates": report.duplicates,
+                });
+                runtime.output().print(&data, || println!("{}", report))?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_audit_log(
+        &self,
+        mut runtime: Runtime,
+        from: i64,
+        to: i64,
+    ) -> Result<(), Error> {
+        match &*runtime.audit_log(from, to)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::AuditLog(records) => {
+                let data = records
+                    .iter()
+                    .map(|record| {
+                        json!({
+                            "seq": record.seq,
+                            "timestamp": record.timestamp,
+                            "caller": record.caller,
+                            "request": record.request,
+                            "params-digest": record.params_digest.to_string(),
+                            "outcome": record.outcome.to_string(),
+                            "prev-hash": record.prev_hash.to_string(),
+                            "hash": record.hash().to_string(),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                runtime.output().print(&data, || {
+                    for record in records {
+                        println!(
+                            "#{} {} {} {}: {}",
+                            record.seq,
+                            record.timestamp,
+                            record.caller,
+                            record.request,
+                            record.outcome
+                        );
+                    }
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_meta(
+        &self,
+        mut runtime: Runtime,
+        contract_id: ContractId,
+    ) -> Result<(), Error> {
+        match &*runtime.asset_meta(contract_id)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::AssetMeta(meta) => {
+                runtime.output().print(meta, || {
+                    for (key, value) in meta {
+                        println!("{}: {}", key, value);
+                    }
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_set_meta(
+        &self,
+        mut runtime: Runtime,
+        req: SetAssetMetaReq,
+    ) -> Result<(), Error> {
+        let key = req.key.clone();
+        match &*runtime.set_asset_meta(req)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Success => {
+                eprintln!("Asset metadata `{}` updated", key);
+            }
+            Reply::Nothing => {
+                eprintln!("Asset metadata `{}` is unchanged", key);
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_supply_report(
+        &self,
+        mut runtime: Runtime,
+        contract_id: ContractId,
+    ) -> Result<(), Error> {
+        match &*runtime.supply_report(contract_id)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::SupplyReport(report) => {
+                let data = json!({
+                    "contract_id": report.contract_id.to_string(),
+                    "issued": report.issued,
+                    "secondary_issues": report.secondary_issues,
+                    "burned": report.burned,
+                    "replaced": report.replaced,
+                    "confidential_replacements":
+                        report.confidential_replacements,
+                    "circulating": report.circulating,
+                    "mismatches": report
+                        .mismatches
+                        .iter()
+                        .map(|mismatch| json!({
+                            "value": mismatch.value,
+                            "cached": mismatch.cached,
+                            "computed": mismatch.computed,
+                        }))
+                        .collect::<Vec<_>>(),
+                });
+                runtime.output().print(&data, || {
+                    println!("Issued: {}", report.issued);
+                    println!("Secondary issues: {}", report.secondary_issues);
+                    println!("Burned: {}", report.burned);
+                    println!("Replaced: {}", report.replaced);
+                    if report.confidential_replacements > 0 {
+                        println!(
+                            "Confidential replacements: {}",
+                            report.confidential_replacements
+                        );
+                    }
+                    println!("Circulating: {}", report.circulating);
+                    for mismatch in &report.mismatches {
+                        println!("Mismatch: {}", mismatch);
+                    }
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_capabilities(
+        &self,
+        mut runtime: Runtime,
+        contract_id: ContractId,
+    ) -> Result<(), Error> {
+        match &*runtime.contract_capabilities(contract_id)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::ContractCapabilities(report) => {
+                let data = json!({
+                    "contract_id": report.contract_id.to_string(),
+                    "schema_id": report.schema_id.to_string(),
+                    "transitions": report.transitions,
+                    "inflatable": report.capabilities.inflation,
+                    "renominatable": report.capabilities.renomination,
+                    "burnable": report.capabilities.burn,
+                    "issued": report.issued,
+                    "max_supply": report.max_supply,
+                });
+                runtime.output().print(&data, || {
+                    println!("Schema:        {}", report.schema_id);
+                    println!("Capabilities:  {}", report.capabilities);
+                    println!("Issued:        {}", report.issued);
+                    println!("Max supply:    {}", report.max_supply);
+                    let transitions = report
+                        .transitions
+                        .iter()
+                        .map(u16::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    println!("Transitions:   {}", transitions);
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_epoch_seals(
+        &self,
+        mut runtime: Runtime,
+        contract_id: ContractId,
+    ) -> Result<(), Error> {
+        match &*runtime.epoch_seals(contract_id)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::EpochSeals(seals) => {
+                let data = seals
+                    .iter()
+                    .map(|seal| {
+                        json!({
+                            "right": seal.right.to_string(),
+                            "node_id": seal.node_id.to_string(),
+                            "index": seal.index,
+                            "outpoint": seal
+                                .outpoint
+                                .map(|outpoint| outpoint.to_string()),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                runtime.output().print(&data, || {
+                    for seal in seals {
+                        match seal.outpoint {
+                            Some(outpoint) => {
+                                println!("{}: {}", seal, outpoint)
+                            }
+                            None => println!("{}: <confidential>", seal),
+                        }
+                    }
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_open_epoch(
+        &self,
+        mut runtime: Runtime,
+        req: OpenEpochReq,
+        consignment: &PathBuf,
+        disclosure: &PathBuf,
+        transaction: &PathBuf,
+    ) -> Result<(), Error> {
+        match &*runtime.open_epoch(req)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Transfer(transfer) => {
+                transfer.disclosure.write_file(disclosure)?;
+                transfer.consignment.write_file(consignment)?;
+
+                let out_file = fs::File::create(transaction)
+                    .expect("can't create output transaction file");
+                transfer.witness.consensus_encode(out_file).map_err(|err| {
+                    bitcoin::consensus::encode::Error::Io(err)
+                })?;
+
+                eprintln!(
+                    "Epoch transition is created, consignment and disclosure \
+                     are written to {:?} and {:?}, partially signed witness \
+                     transaction to {:?}",
+                    consignment, disclosure, transaction
+                );
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_compose_witness(
+        &self,
+        mut runtime: Runtime,
+        req: ComposeWitnessReq,
+        transaction: &PathBuf,
+    ) -> Result<(), Error> {
+        match &*runtime.compose_witness(req)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::WitnessTemplate(template) => {
+                let out_file = fs::File::create(transaction)
+                    .expect("can't create output transaction file");
+                template.witness.consensus_encode(out_file).map_err(|err| {
+                    bitcoin::consensus::encode::Error::Io(err)
+                })?;
+
+                let funding = template
+                    .funding
+                    .iter()
+                    .map(OutPoint::to_string)
+                    .collect::<Vec<_>>();
+                let txid = template.witness.global.unsigned_tx.txid();
+                let data = json!({
+                    "txid": txid.to_string(),
+                    "funding": funding,
+                    "fee": template.fee,
+                    "change_vout": template.change_vout,
+                });
+                runtime.output().print(&data, || {
+                    eprintln!(
+                        "Witness transaction with fee of {} sats is written \
+                         to {:?}; change goes to output #{}",
+                        template.fee, transaction, template.change_vout
+                    );
+                    for outpoint in &funding {
+                        println!("Funding input: {}", outpoint);
+                    }
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_transfers(&self, mut runtime: Runtime) -> Result<(), Error> {
+        match &*runtime.list_transfers()? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Transfers(transfers) => {
+                let data =
+                    transfers.iter().map(transfer_json).collect::<Vec<_>>();
+                runtime.output().print(&data, || {
+                    for status in transfers {
+                        println!(
+                            "{}\t{}\t{}\t{}",
+                            status.txid,
+                            status.contract_id,
+                            status.amount,
+                            status.state
+                        );
+                    }
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_transfer_status(
+        &self,
+        mut runtime: Runtime,
+        txid: Txid,
+    ) -> Result<(), Error> {
+        match &*runtime.transfer_status(txid)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::TransferStatus(status) => {
+                runtime.output().print(
+                    &transfer_json(status),
+                    || match status.reason {
+                        Some(ref reason) => {
+                            println!("{} ({})", status.state, reason)
+                        }
+                        None => println!("{}", status.state),
+                    },
+                )?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_export_consignment(
+        &self,
+        mut runtime: Runtime,
+        consignment_id: sha256::Hash,
+        filename: &PathBuf,
+    ) -> Result<(), Error> {
+        match &*runtime.export_accepted_consignment(consignment_id)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Consignment(consignment) => {
+                consignment.write_file(filename)?;
+                eprintln!("Consignment is written to {:?}", filename);
+            }
+            Reply::Nothing => {
+                eprintln!(
+                    "Consignment {} is unknown or has expired",
+                    consignment_id
+                );
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_reconsign(
+        &self,
+        mut runtime: Runtime,
+        txid: Txid,
+        filename: &PathBuf,
+    ) -> Result<(), Error> {
+        match &*runtime.reconsign(txid)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Consignment(consignment) => {
+                consignment.write_file(filename)?;
+                eprintln!(
+                    "Consignment of transfer {} is written to {:?}",
+                    txid, filename
+                );
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_export_claims(
+        &self,
+        mut runtime: Runtime,
+        passphrase: &str,
+        filename: &PathBuf,
+    ) -> Result<(), Error> {
+        info!("Exporting claims ...");
+        match &*runtime.export_claims(passphrase.to_string())? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Claims(data) => {
+                fs::write(filename, data)?;
+                eprintln!("Claims are written to {:?}", filename);
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_import_claims(
+        &self,
+        mut runtime: Runtime,
+        passphrase: &str,
+        filename: &PathBuf,
+    ) -> Result<(), Error> {
+        info!("Importing claims from {} ...", filename.display());
+        let data = fs::read(filename).map_err(|_| {
+            Error::InputFileIoError(filename.display().to_string())
+        })?;
+        match &*runtime.import_claims(data, passphrase.to_string())? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::ClaimsImport(report) => {
+                let data = json!({
+                    "contracts": report.contracts,
+                    "reveals": report.reveals,
+                    "descriptors": report.descriptors,
+                });
+                runtime.output().print(&data, || {
+                    println!("{}", report);
+                    if !report.descriptors.is_empty() {
+                        eprintln!(
+                            "Add the funding descriptors to the node \
+                             configuration:"
+                        );
+                        for descriptor in &report.descriptors {
+                            println!("{}", descriptor);
+                        }
+                    }
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_backup(&self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Backing up node data ...");
+        match &*runtime.backup()? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Backup(backup) => {
+                let data = json!({
+                    "name": backup.name,
+                    "cache-files": backup.cache_files,
+                    "removed": backup.removed,
+                });
+                runtime.output().print(&data, || println!("{}", backup))?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_funding_balance(&self, mut runtime: Runtime) -> Result<(), Error> {
+        match &*runtime.funding_balance()? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::FundingBalance(balance) => {
+                let data = json!({
+                    "confirmed": balance.confirmed,
+                    "unconfirmed": balance.unconfirmed,
+                    "locked": balance.locked,
+                    "outputs": balance.outputs,
+                });
+                runtime.output().print(&data, || {
+                    println!(
+                        "Confirmed:    {} sats in {} outputs",
+                        balance.confirmed, balance.outputs
+                    );
+                    println!("Unconfirmed:  {} sats", balance.unconfirmed);
+                    println!("Locked:       {} sats", balance.locked);
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_funding_address(&self, mut runtime: Runtime) -> Result<(), Error> {
+        match &*runtime.new_funding_address()? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::FundingAddress(address) => {
+                let data = json!({ "address": address });
+                runtime.output().print(&data, || println!("{}", address))?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_label(
+        &self,
+        mut runtime: Runtime,
+        req: SetLabelReq,
+    ) -> Result<(), Error> {
+        let target = req.target;
+        match &*runtime.set_label(req)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Success => {
+                eprintln!("Labels of {} updated", target);
+            }
+            Reply::Nothing => {
+                eprintln!("Labels of {} are unchanged", target);
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_reload_config(&self, mut runtime: Runtime) -> Result<(), Error> {
+        match &*runtime.reload_config()? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Success => {
+                eprintln!("Configuration reloaded");
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_submit(
+        &self,
+        mut runtime: Runtime,
+        request: Request,
+    ) -> Result<(), Error> {
+        match &*runtime.submit(request)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::JobQueued(id) => {
+                eprintln!("Request is queued as job #{}", id);
+                println!("{}", id);
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_job(&self, mut runtime: Runtime, id: u64) -> Result<(), Error> {
+        match &*runtime.job_status(id)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Job(status) => {
+                let result =
+                    status.result.as_ref().map(|reply| reply.to_string());
+                let data = json!({
+                    "id": status.id,
+                    "request": status.request,
+                    "queued": status.queued,
+                    "completed": status.completed,
+                    "result": result,
+                });
+                runtime.output().print(&data, || {
+                    println!("Job #{}: {}", status.id, status.request);
+                    match status.result {
+                        Some(ref reply) => println!("Completed: {}", reply),
+                        None => println!("Queued, not completed yet"),
+                    }
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
         }
+
+        Ok(())
     }
 
     fn exec_list(
@@ -191,13 +1583,21 @@ impl Command {
             Reply::Failure(failure) => {
                 eprintln!("Server returned error: {}", failure);
             }
-            Reply::Sync(reply::SyncFormat(input_format, data)) => {
+            Reply::Sync(reply::AssetSync {
+                format: input_format,
+                data,
+                ..
+            }) => {
                 let assets: Vec<Asset> = match input_format {
-                    FileFormat::Yaml => serde_yaml::from_slice(&data)?,
-                    FileFormat::Json => serde_json::from_slice(&data)?,
-                    FileFormat::Toml => toml::from_slice(&data)?,
-                    FileFormat::StrictEncode => strict_deserialize(&data)?,
-                    _ => unimplemented!(),
+                    DataFormat::Yaml => serde_yaml::from_slice(&data)?,
+                    DataFormat::Json => serde_json::from_slice(&data)?,
+                    DataFormat::Toml => toml::from_slice(&data)?,
+                    DataFormat::Cbor => serde_cbor::from_slice(&data)?,
+                    DataFormat::MsgPack => rmp_serde::from_slice(&data)?,
+                    // Protobuf encoding is one-way: it is intended for
+                    // non-Rust clients and can't be decoded back into `Asset`
+                    DataFormat::Protobuf => Err(Error::FormatNotSupported)?,
+                    DataFormat::StrictEncode => strict_deserialize(&data)?,
                 };
                 let short: Vec<HashMap<&str, String>> = assets
                     .iter()
@@ -224,6 +1624,13 @@ impl Command {
                         long_str = toml::to_string(&assets)?;
                         short_str = toml::to_string(&short)?;
                     }
+                    // Binary formats are used only for transferring data
+                    // between the node and the tool, so we render their
+                    // content as YAML
+                    OutputFormat::Cbor | OutputFormat::MsgPack => {
+                        long_str = serde_yaml::to_string(&assets)?;
+                        short_str = serde_yaml::to_string(&short)?;
+                    }
                     _ => unimplemented!(),
                 }
                 if long {
@@ -241,6 +1648,43 @@ impl Command {
         Ok(())
     }
 
+    fn exec_import_consignment(
+        &self,
+        mut runtime: Runtime,
+        filename: PathBuf,
+    ) -> Result<(), Error> {
+        info!("Importing asset from consignment ...");
+
+        debug!("Reading consignment from file {:?}", &filename);
+        let consignment =
+            Consignment::read_file(filename.clone()).map_err(|err| {
+                Error::InputFileFormatError(
+                    format!("{:?}", filename),
+                    format!("{}", err),
+                )
+            })?;
+        trace!("{:#?}", consignment);
+
+        match &*runtime.import_from_consignment(consignment)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Asset(asset) => {
+                eprintln!(
+                    "Asset {} ({}) successfully imported",
+                    asset.id(),
+                    asset.ticker()
+                );
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn exec_import(
         &self,
         mut runtime: Runtime,
@@ -292,6 +1736,8 @@ impl Command {
         &self,
         mut runtime: Runtime,
         filename: PathBuf,
+        min_confirmations: Option<u32>,
+        background: bool,
     ) -> Result<(), Error> {
         info!("Validating asset transfer...");
 
@@ -305,13 +1751,27 @@ impl Command {
             })?;
         trace!("{:#?}", consignment);
 
-        match &*runtime.validate(consignment)? {
+        let api = ValidateReq {
+            consignment,
+            min_confirmations,
+        };
+        if background {
+            return self.exec_submit(runtime, Request::Validate(api));
+        }
+        match &*runtime.validate(api)? {
             Reply::Failure(failure) => {
                 eprintln!("Server returned error: {}", failure);
             }
             Reply::ValidationStatus(status) => {
                 eprintln!("Asset transfer validation report:\n{:#?}", status);
             }
+            Reply::Pending(pending) => {
+                eprintln!(
+                    "Asset transfer is valid, but its witness transactions \
+                    require {} confirmations:\n{:#?}",
+                    pending.required, pending.confirmations
+                );
+            }
             _ => {
                 eprintln!(
                     "Unexpected server error; probably you connecting with outdated client version"
@@ -323,10 +1783,12 @@ impl Command {
 
     fn exec_accept(
         &self,
-        mut runtime: Runtime,
+        runtime: Runtime,
         filename: PathBuf,
-        outpoint: OutPoint,
-        blinding_factor: u64,
+        outpoint: Option<OutPoint>,
+        blinding_factor: Option<u64>,
+        min_confirmations: Option<u32>,
+        background: bool,
     ) -> Result<(), Error> {
         info!("Accepting asset transfer...");
 
@@ -340,6 +1802,28 @@ impl Command {
             })?;
         trace!("{:#?}", consignment);
 
+        let (outpoint, blinding_factor) = match (outpoint, blinding_factor) {
+            (Some(outpoint), Some(blinding_factor)) => {
+                (outpoint, blinding_factor)
+            }
+            (None, None) => {
+                // The node uses blinding factors of the outpoints it has
+                // blinded itself
+                let api = AcceptReq {
+                    consignment,
+                    reveal_outpoints: vec![],
+                    min_confirmations,
+                };
+                return self.exec_accept_req(runtime, api, background);
+            }
+            _ => {
+                eprintln!(
+                    "Both outpoint and its blinding factor must be provided"
+                );
+                Err(Error::DataInconsistency)?
+            }
+        };
+
         let api = if let Some((_, seal_endpoint)) = consignment.endpoints.get(0)
         {
             let outpoint_reveal = OutpointReveal {
@@ -359,6 +1843,7 @@ impl Command {
             AcceptReq {
                 consignment,
                 reveal_outpoints: vec![outpoint_reveal],
+                min_confirmations,
             }
         } else {
             eprintln!(
@@ -368,7 +1853,18 @@ impl Command {
             );
             Err(Error::UnsupportedFunctionality)?
         };
+        self.exec_accept_req(runtime, api, background)
+    }
 
+    fn exec_accept_req(
+        &self,
+        mut runtime: Runtime,
+        api: AcceptReq,
+        background: bool,
+    ) -> Result<(), Error> {
+        if background {
+            return self.exec_submit(runtime, Request::Accept(api));
+        }
         match &*runtime.accept(api)? {
             Reply::Failure(failure) => {
                 eprintln!("Server returned error: {}", failure);
@@ -376,6 +1872,21 @@ impl Command {
             Reply::Success => {
                 eprintln!("Asset transfer successfully accepted.");
             }
+            Reply::AlreadyAccepted(consignment_id) => {
+                eprintln!(
+                    "Consignment {} was already accepted before.",
+                    consignment_id
+                );
+            }
+            Reply::Pending(pending) => {
+                eprintln!(
+                    "Consignment {} will be accepted once its witness \
+                    transactions get {} confirmations:\n{:#?}",
+                    pending.consignment_id,
+                    pending.required,
+                    pending.confirmations
+                );
+            }
             _ => {
                 eprintln!(
                     "Unexpected server error; probably you connecting with outdated client version"
@@ -446,6 +1957,249 @@ impl Command {
 
         Ok(())
     }
+
+    fn exec_receive(
+        &self,
+        mut runtime: Runtime,
+        req: ReceiveAssetReq,
+    ) -> Result<(), Error> {
+        info!("Preparing to receive asset ...");
+
+        match &*runtime.receive_asset(req)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Receive(receive) => {
+                let data = json!({
+                    "outpoint": OutPoint::from(receive.reveal).to_string(),
+                    "blinded": receive.invoice.endpoint.to_string(),
+                    "blinding": receive.reveal.blinding,
+                    "invoice": receive.invoice.to_string(),
+                });
+                runtime.output().print(&data, || {
+                    eprint!("Receiving outpoint: ");
+                    println!("{}", OutPoint::from(receive.reveal));
+                    eprint!("Blinded outpoint: ");
+                    println!("{}", receive.invoice.endpoint);
+                    eprint!("Invoice: ");
+                    println!("{}", receive.invoice);
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_receives(&self, mut runtime: Runtime) -> Result<(), Error> {
+        match &*runtime.list_pending_receives()? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::PendingReceives(receives) => {
+                let data = receives
+                    .iter()
+                    .map(|receive| {
+                        json!({
+                            "blinded": receive.invoice.endpoint.to_string(),
+                            "outpoint": receive.outpoint.to_string(),
+                            "invoice": receive.invoice.to_string(),
+                            "created": receive.created,
+                            "expires": receive.expires,
+                            "reserved": receive.reserved,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                runtime.output().print(&data, || {
+                    for receive in receives {
+                        println!(
+                            "{}\t{}\texpires {}",
+                            receive.invoice.endpoint,
+                            receive.outpoint,
+                            receive.expires
+                        );
+                    }
+                })?;
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_cancel_receive(
+        &self,
+        mut runtime: Runtime,
+        blinded: OutpointHash,
+    ) -> Result<(), Error> {
+        match &*runtime.cancel_receive(blinded)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Success => {
+                eprintln!("Receive to {} is cancelled", blinded);
+            }
+            Reply::Nothing => {
+                eprintln!("There is no pending receive to {}", blinded);
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_reserve(
+        &self,
+        mut runtime: Runtime,
+        req: ReserveOutpointReq,
+    ) -> Result<(), Error> {
+        info!("Reserving outpoint ...");
+
+        let outpoint = req.outpoint;
+        match &*runtime.reserve_outpoint(req)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Success => {
+                eprintln!("Outpoint {} is reserved", outpoint);
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_release(
+        &self,
+        mut runtime: Runtime,
+        outpoint: OutPoint,
+    ) -> Result<(), Error> {
+        info!("Releasing outpoint ...");
+
+        match &*runtime.release_outpoint(outpoint)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Success => {
+                eprintln!("Outpoint {} is released", outpoint);
+            }
+            Reply::Nothing => {
+                eprintln!("Outpoint {} was not reserved", outpoint);
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_restore(
+        &self,
+        mut runtime: Runtime,
+        outpoint: OutPoint,
+    ) -> Result<(), Error> {
+        info!("Restoring assets allocated to {}...", outpoint);
+
+        match &*runtime.restore(outpoint)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Success => {
+                eprintln!("Assets are restored.");
+            }
+            Reply::Nothing => {
+                eprintln!("No forgotten assets are known for {}.", outpoint);
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_forget_contract(
+        &self,
+        mut runtime: Runtime,
+        contract_id: Option<ContractId>,
+        dry_run: bool,
+    ) -> Result<(), Error> {
+        let reply = match contract_id {
+            Some(contract_id) => {
+                info!("Forgetting asset {}...", contract_id);
+                runtime.forget_contract(contract_id, dry_run)?
+            }
+            None => {
+                info!("Forgetting all known assets...");
+                runtime.forget_all(dry_run)?
+            }
+        };
+
+        match &*reply {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Forgotten(report) => {
+                let data = report
+                    .assets
+                    .iter()
+                    .map(|(contract_id, allocations)| {
+                        (
+                            contract_id.to_string(),
+                            allocations
+                                .iter()
+                                .map(|(outpoint, amounts)| {
+                                    (outpoint.to_string(), json!(amounts))
+                                })
+                                .collect::<serde_json::Map<_, _>>()
+                                .into(),
+                        )
+                    })
+                    .collect::<serde_json::Map<_, _>>();
+                runtime.output().print(&data, || {
+                    for (contract_id, allocations) in &report.assets {
+                        println!("{}:", contract_id);
+                        for (outpoint, amounts) in allocations {
+                            println!("  {}: {:?}", outpoint, amounts);
+                        }
+                    }
+                })?;
+                if report.dry_run {
+                    eprintln!("Dry run: no data were removed.");
+                } else {
+                    eprintln!("Assets are removed from the cache and stash.");
+                }
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl IssueReq {
@@ -481,6 +2235,58 @@ impl IssueReq {
 
         Ok(())
     }
+
+    pub fn exec_preview(self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Previewing asset issue ...");
+        debug!("{}", self.clone());
+
+        let reply = runtime.preview_issue(self)?;
+        info!("Reply: {}", reply);
+
+        match &*reply {
+            Reply::Failure(failure) => {
+                eprintln!("Issue preview failed: {}", failure);
+            }
+            Reply::IssuePreview(preview) => {
+                let data = json!({
+                    "contract-id": preview.contract_id.to_string(),
+                    "genesis-size": preview.genesis_size,
+                    "issued": preview.issued,
+                    "inflation": preview.inflation,
+                    "allocations": preview
+                        .allocations
+                        .iter()
+                        .map(|(seal, amount)| {
+                            (seal.to_string(), json!(amount))
+                        })
+                        .collect::<serde_json::Map<_, _>>(),
+                    "status": status_json(&preview.status),
+                });
+                runtime.output().print(&data, || {
+                    eprintln!("Contract id: {}", preview.contract_id);
+                    eprintln!("Genesis size: {} bytes", preview.genesis_size);
+                    eprintln!("Issued: {} atomic units", preview.issued);
+                    eprintln!(
+                        "Inflation cap: {} atomic units",
+                        preview.inflation
+                    );
+                    for (seal, amount) in &preview.allocations {
+                        eprintln!("  {} @ {}", amount, seal);
+                    }
+                    eprintln!(
+                        "Schema validation report:\n{:#?}",
+                        preview.status
+                    );
+                })?;
+            }
+            _ => {
+                eprintln!("Unrecognized RGB node reply");
+                Err(Error::DataInconsistency)?
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl TransferCli {
@@ -489,44 +2295,7 @@ impl TransferCli {
         info!("Transferring asset ...");
         debug!("{}", self.clone());
 
-        debug!(
-            "Reading partially-signed transaction from file {:?}",
-            self.prototype
-        );
-        let filepath = format!("{:?}", &self.prototype);
-        let file = fs::File::open(self.prototype)
-            .map_err(|_| Error::InputFileIoError(format!("{:?}", filepath)))?;
-        let mut psbt = PartiallySignedTransaction::consensus_decode(file)
-            .map_err(|err| {
-                Error::InputFileFormatError(
-                    format!("{:?}", filepath),
-                    format!("{}", err),
-                )
-            })?;
-
-        for (index, output) in &mut psbt.outputs.iter_mut().enumerate() {
-            if let Some(key) = output.bip32_derivation.keys().next() {
-                let key = key.clone();
-                output.proprietary.insert(
-                    ProprietaryKey {
-                        prefix: b"RGB".to_vec(),
-                        subtype: PSBT_OUT_PUBKEY,
-                        key: vec![],
-                    },
-                    key.key.serialize().to_vec(),
-                );
-                debug!("Output #{} commitment key will be {}", index, key);
-            } else {
-                warn!(
-                    "No public key information found for output #{}; \
-                    LNPBP1/2 commitment will be impossible.\
-                    In order to allow commitment pls add known keys derivation \
-                    information to PSBT output map",
-                    index
-                );
-            }
-        }
-        trace!("{:?}", psbt);
+        let psbt = read_prototype(&self.prototype)?;
 
         let api = TransferReq {
             witness: psbt,
@@ -540,6 +2309,13 @@ impl TransferCli {
                 })
                 .collect(),
             payment: bmap! { SealEndpoint::TxOutpoint(self.receiver) => self.amount },
+            theirs_witness: self
+                .theirs_witness
+                .into_iter()
+                .map(|vout_coins| (vout_coins.vout, vout_coins.coins))
+                .collect(),
+            confirmed_only: self.confirmed_only,
+            container: self.container,
         };
 
         let reply = runtime.transfer(api)?;
@@ -566,6 +2342,13 @@ impl TransferCli {
                 eprint!("Consignment data to share:");
                 println!("{}", transfer.consignment);
             }
+            Reply::ApprovalRequired(txid) => {
+                eprintln!(
+                    "Transfer {} exceeds approval threshold; consignment will \
+                     be released once approved by a second operator",
+                    txid
+                );
+            }
             _ => (),
         }
 
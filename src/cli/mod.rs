@@ -11,15 +11,23 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+pub mod asset;
+pub mod completions;
 mod config;
+pub mod consignment;
 mod error;
 pub mod fungible;
 mod runtime;
 pub mod stash;
+pub mod supervisor;
+mod wizard;
 
 pub use config::{Config, Opts};
 pub use error::Error;
 pub use runtime::Runtime;
+pub use wizard::TransferWizard;
+
+use serde::Serialize;
 
 #[derive(Clap, Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
 #[display(Debug)]
@@ -30,6 +38,44 @@ pub enum OutputFormat {
     Csv,
     Tsv,
     Bech32,
+    Cbor,
+    MsgPack,
     PrettyPrint,
     StrictEncode,
 }
+
+/// Presentation of the command results, selected with the global `--output`
+/// argument
+#[derive(Clap, Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display(Debug)]
+pub enum OutputMode {
+    /// Human-readable text and tables
+    Table,
+    Json,
+    Yaml,
+}
+
+impl Default for OutputMode {
+    #[inline]
+    fn default() -> Self {
+        OutputMode::Table
+    }
+}
+
+impl OutputMode {
+    /// Prints the data in a machine-readable format, or, in the table mode,
+    /// calls `table` which prints human-readable representation of the data
+    pub fn print<T>(self, data: &T, table: impl FnOnce()) -> Result<(), Error>
+    where
+        T: Serialize,
+    {
+        match self {
+            OutputMode::Table => table(),
+            OutputMode::Json => {
+                println!("{}", serde_json::to_string_pretty(data)?)
+            }
+            OutputMode::Yaml => println!("{}", serde_yaml::to_string(data)?),
+        }
+        Ok(())
+    }
+}
@@ -13,27 +13,35 @@
 
 use std::sync::Arc;
 
-use bitcoin::OutPoint;
+use bitcoin::hashes::sha256;
+use bitcoin::{OutPoint, Txid};
 use internet2::zmqsocket::ZmqType;
 use internet2::{
-    session, transport, CreateUnmarshaller, PlainTranscoder, Session,
-    TypedEnum, Unmarshall, Unmarshaller,
+    session, transport, CreateUnmarshaller, PlainTranscoder, TypedEnum,
+    Unmarshall, Unmarshaller,
 };
+use lnpbp::seals::OutpointHash;
 use rgb::{Consignment, ContractId, Disclosure, Genesis, SchemaId};
 
 use super::{Config, Error};
-use crate::cli::OutputFormat;
+use crate::cli::{OutputFormat, OutputMode};
 use crate::error::{BootstrapError, ServiceErrorDomain};
-use crate::rpc::fungible::{self, AcceptReq, IssueReq, TransferReq};
-use crate::rpc::stash;
+use crate::rpc::fungible::{
+    self, AcceptReq, AsyncReq, AuditLogReq, BulkImportReq, ExportLedgerReq,
+    IssueReq, LedgerFormat, ReserveOutpointReq, TransferReq, ValidateReq,
+};
 use crate::rpc::Reply;
-use microservices::FileFormat;
+use crate::rpc::{stash, supervisor};
+use crate::util::compression::req_rep;
+use crate::util::DataFormat;
 
 pub struct Runtime {
     stash_rpc: session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
     fungible_rpc:
         session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
+    rgbd_rpc: session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
     unmarshaller: Unmarshaller<Reply>,
+    output: OutputMode,
 }
 
 impl Runtime {
@@ -50,20 +58,32 @@ impl Runtime {
             None,
             None,
         )?;
+        let rgbd_rpc = session::Raw::with_zmq_unencrypted(
+            ZmqType::Req,
+            &config.rgbd_endpoint,
+            None,
+            None,
+        )?;
         Ok(Self {
             stash_rpc,
             fungible_rpc,
+            rgbd_rpc,
             unmarshaller: Reply::create_unmarshaller(),
+            output: config.output,
         })
     }
 
+    /// Presentation of the command results requested by the user
+    #[inline]
+    pub fn output(&self) -> OutputMode {
+        self.output
+    }
+
     fn stash_command(
         &mut self,
         command: stash::Request,
     ) -> Result<Arc<Reply>, ServiceErrorDomain> {
-        let data = command.serialize();
-        self.stash_rpc.send_raw_message(&data)?;
-        let raw = self.stash_rpc.recv_raw_message()?;
+        let raw = req_rep(&mut self.stash_rpc, command.serialize())?;
         let reply = self.unmarshaller.unmarshall(&raw)?;
         Ok(reply)
     }
@@ -71,14 +91,33 @@ impl Runtime {
     fn fungible_command(
         &mut self,
         command: fungible::Request,
+    ) -> Result<Arc<Reply>, Error> {
+        let raw = req_rep(&mut self.fungible_rpc, command.serialize())
+            .map_err(ServiceErrorDomain::from)?;
+        let reply = self
+            .unmarshaller
+            .unmarshall(&raw)
+            .map_err(ServiceErrorDomain::from)?;
+        if let Reply::RateLimited(retry_after) = *reply {
+            Err(Error::RateLimited(retry_after))?
+        }
+        Ok(reply)
+    }
+
+    fn rgbd_command(
+        &mut self,
+        command: supervisor::Request,
     ) -> Result<Arc<Reply>, ServiceErrorDomain> {
-        let data = command.serialize();
-        self.fungible_rpc.send_raw_message(&data)?;
-        let raw = self.fungible_rpc.recv_raw_message()?;
+        let raw = req_rep(&mut self.rgbd_rpc, command.serialize())?;
         let reply = self.unmarshaller.unmarshall(&raw)?;
         Ok(reply)
     }
 
+    #[inline]
+    pub fn daemons(&mut self) -> Result<Arc<Reply>, Error> {
+        Ok(self.rgbd_command(supervisor::Request::Status)?)
+    }
+
     #[inline]
     pub fn list_schemata(&mut self) -> Result<Arc<Reply>, Error> {
         Ok(self.stash_command(stash::Request::ListSchemata())?)
@@ -102,26 +141,83 @@ impl Runtime {
         Ok(self.stash_command(stash::Request::ReadGenesis(contract_id))?)
     }
 
+    #[inline]
+    pub fn contract_graph(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::ExportGraph(contract_id))?)
+    }
+
+    #[inline]
+    pub fn contract_state(
+        &mut self,
+        contract_id: ContractId,
+        output_format: OutputFormat,
+    ) -> Result<Arc<Reply>, Error> {
+        let format = match output_format {
+            OutputFormat::Yaml => DataFormat::Yaml,
+            OutputFormat::Json => DataFormat::Json,
+            _ => Err(Error::FormatNotSupported)?,
+        };
+        Ok(self.stash_command(stash::Request::ContractState(
+            stash::ContractStateRequest {
+                contract_id,
+                format,
+            },
+        ))?)
+    }
+
     #[inline]
     pub fn list(
         &mut self,
         output_format: OutputFormat,
     ) -> Result<Arc<Reply>, Error> {
         let data_format = match output_format {
-            OutputFormat::Yaml => FileFormat::Yaml,
-            OutputFormat::Json => FileFormat::Json,
-            OutputFormat::Toml => FileFormat::Toml,
-            OutputFormat::StrictEncode => FileFormat::StrictEncode,
+            OutputFormat::Yaml => DataFormat::Yaml,
+            OutputFormat::Json => DataFormat::Json,
+            OutputFormat::Toml => DataFormat::Toml,
+            OutputFormat::Cbor => DataFormat::Cbor,
+            OutputFormat::MsgPack => DataFormat::MsgPack,
+            OutputFormat::StrictEncode => DataFormat::StrictEncode,
             _ => unimplemented!("The provided output format is not supported for this operation")
         };
         Ok(self.fungible_command(fungible::Request::Sync(data_format))?)
     }
 
+    #[inline]
+    pub fn allocations(
+        &mut self,
+        req: fungible::AllocationsReq,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Allocations(req))?)
+    }
+
+    #[inline]
+    pub fn set_label(
+        &mut self,
+        req: fungible::SetLabelReq,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::SetLabel(req))?)
+    }
+
     #[inline]
     pub fn import(&mut self, genesis: Genesis) -> Result<Arc<Reply>, Error> {
         Ok(self.fungible_command(fungible::Request::ImportAsset(genesis))?)
     }
 
+    #[inline]
+    pub fn import_from_consignment(
+        &mut self,
+        consignment: Consignment,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(
+            self.fungible_command(fungible::Request::ImportFromConsignment(
+                consignment,
+            ))?,
+        )
+    }
+
     #[inline]
     pub fn export(
         &mut self,
@@ -135,6 +231,14 @@ impl Runtime {
         Ok(self.fungible_command(fungible::Request::Issue(issue))?)
     }
 
+    #[inline]
+    pub fn preview_issue(
+        &mut self,
+        issue: IssueReq,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::PreviewIssue(issue))?)
+    }
+
     #[inline]
     pub fn transfer(
         &mut self,
@@ -146,9 +250,9 @@ impl Runtime {
     #[inline]
     pub fn validate(
         &mut self,
-        consignment: Consignment,
+        validate: ValidateReq,
     ) -> Result<Arc<Reply>, Error> {
-        Ok(self.fungible_command(fungible::Request::Validate(consignment))?)
+        Ok(self.fungible_command(fungible::Request::Validate(validate))?)
     }
 
     #[inline]
@@ -156,6 +260,55 @@ impl Runtime {
         Ok(self.fungible_command(fungible::Request::Accept(accept))?)
     }
 
+    #[inline]
+    pub fn submit(
+        &mut self,
+        request: fungible::Request,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Async(AsyncReq {
+            request: Box::new(request),
+        }))?)
+    }
+
+    #[inline]
+    pub fn job_status(&mut self, id: u64) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::JobStatus(id))?)
+    }
+
+    #[inline]
+    pub fn inspect(
+        &mut self,
+        request: stash::AcceptRequest,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::InspectConsignment(request))?)
+    }
+
+    #[inline]
+    pub fn explain_failure(
+        &mut self,
+        consignment: Consignment,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::ExplainFailure(
+            consignment.into(),
+        ))?)
+    }
+
+    #[inline]
+    pub fn validate_file(
+        &mut self,
+        filename: String,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::ValidateFile(filename))?)
+    }
+
+    #[inline]
+    pub fn import_checkpoint(
+        &mut self,
+        filename: String,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::ImportCheckpoint(filename))?)
+    }
+
     #[inline]
     pub fn enclose(
         &mut self,
@@ -164,8 +317,250 @@ impl Runtime {
         Ok(self.fungible_command(fungible::Request::Enclose(disclosure))?)
     }
 
+    #[inline]
+    pub fn blind(&mut self, outpoint: OutPoint) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Blind(outpoint))?)
+    }
+
     #[inline]
     pub fn forget(&mut self, outpoint: OutPoint) -> Result<Arc<Reply>, Error> {
         Ok(self.fungible_command(fungible::Request::Forget(outpoint))?)
     }
+
+    #[inline]
+    pub fn restore(&mut self, outpoint: OutPoint) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Restore(outpoint))?)
+    }
+
+    #[inline]
+    pub fn reserve_outpoint(
+        &mut self,
+        req: ReserveOutpointReq,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::ReserveOutpoint(req))?)
+    }
+
+    #[inline]
+    pub fn release_outpoint(
+        &mut self,
+        outpoint: OutPoint,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self
+            .fungible_command(fungible::Request::ReleaseOutpoint(outpoint))?)
+    }
+
+    #[inline]
+    pub fn forget_contract(
+        &mut self,
+        contract_id: ContractId,
+        dry_run: bool,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::ForgetContract(
+            fungible::ForgetContractReq {
+                contract_id,
+                dry_run,
+            },
+        ))?)
+    }
+
+    #[inline]
+    pub fn forget_all(&mut self, dry_run: bool) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::ForgetAll(dry_run))?)
+    }
+
+    #[inline]
+    pub fn supply_report(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self
+            .fungible_command(fungible::Request::SupplyReport(contract_id))?)
+    }
+
+    #[inline]
+    pub fn contract_capabilities(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(
+            self.fungible_command(fungible::Request::ContractCapabilities(
+                contract_id,
+            ))?,
+        )
+    }
+
+    #[inline]
+    pub fn epoch_seals(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::EpochSeals(contract_id))?)
+    }
+
+    #[inline]
+    pub fn open_epoch(
+        &mut self,
+        req: fungible::OpenEpochReq,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::OpenEpoch(req))?)
+    }
+
+    #[inline]
+    pub fn compose_witness(
+        &mut self,
+        req: fungible::ComposeWitnessReq,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::ComposeWitness(req))?)
+    }
+
+    #[inline]
+    pub fn receive_asset(
+        &mut self,
+        req: fungible::ReceiveAssetReq,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::ReceiveAsset(req))?)
+    }
+
+    #[inline]
+    pub fn list_pending_receives(&mut self) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::ListPendingReceives)?)
+    }
+
+    #[inline]
+    pub fn cancel_receive(
+        &mut self,
+        endpoint: OutpointHash,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::CancelReceive(endpoint))?)
+    }
+
+    #[inline]
+    pub fn list_transfers(&mut self) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::ListTransfers)?)
+    }
+
+    #[inline]
+    pub fn transfer_status(&mut self, txid: Txid) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::TransferStatus(txid))?)
+    }
+
+    #[inline]
+    pub fn export_accepted_consignment(
+        &mut self,
+        consignment_id: sha256::Hash,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(
+            fungible::Request::ExportAcceptedConsignment(consignment_id),
+        )?)
+    }
+
+    #[inline]
+    pub fn reconsign(&mut self, txid: Txid) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Reconsign(txid))?)
+    }
+
+    #[inline]
+    pub fn export_claims(
+        &mut self,
+        passphrase: String,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::ExportClaims(
+            passphrase.into(),
+        ))?)
+    }
+
+    #[inline]
+    pub fn import_claims(
+        &mut self,
+        data: Vec<u8>,
+        passphrase: String,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::ImportClaims(
+            fungible::ImportClaimsReq {
+                data,
+                passphrase: passphrase.into(),
+            },
+        ))?)
+    }
+
+    #[inline]
+    pub fn backup(&mut self) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Backup)?)
+    }
+
+    #[inline]
+    pub fn funding_balance(&mut self) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::FundingBalance)?)
+    }
+
+    #[inline]
+    pub fn new_funding_address(&mut self) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::NewFundingAddress)?)
+    }
+
+    #[inline]
+    pub fn asset_meta(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self
+            .fungible_command(fungible::Request::GetAssetMeta(contract_id))?)
+    }
+
+    #[inline]
+    pub fn set_asset_meta(
+        &mut self,
+        req: fungible::SetAssetMetaReq,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::SetAssetMeta(req))?)
+    }
+
+    #[inline]
+    pub fn status(&mut self) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Status)?)
+    }
+
+    #[inline]
+    pub fn bulk_import(
+        &mut self,
+        filename: String,
+        batch_size: u32,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::BulkImport(
+            BulkImportReq {
+                filename,
+                batch_size,
+            },
+        ))?)
+    }
+
+    #[inline]
+    pub fn export_ledger(
+        &mut self,
+        height: u32,
+        format: LedgerFormat,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::ExportLedger(
+            ExportLedgerReq { height, format },
+        ))?)
+    }
+
+    #[inline]
+    pub fn audit_log(
+        &mut self,
+        from: i64,
+        to: i64,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(
+            self.fungible_command(fungible::Request::AuditLog(AuditLogReq {
+                from,
+                to,
+            }))?,
+        )
+    }
+
+    #[inline]
+    pub fn reload_config(&mut self) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::ReloadConfig)?)
+    }
 }
@@ -11,10 +11,13 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use std::fs;
+use std::path::PathBuf;
+
 use rgb::{ContractId, SchemaId, ToBech32};
 
 use crate::cli::{Error, OutputFormat, Runtime};
-use crate::rpc::Reply;
+use crate::rpc::{reply, Reply};
 
 #[derive(Clap, Clone, Debug, Display)]
 #[display(Debug)]
@@ -56,6 +59,23 @@ pub enum GenesisCommand {
         #[clap()]
         contract_id: ContractId,
     },
+
+    /// Export snapshot of the contract state known to the stash
+    State {
+        /// Format for information output
+        #[clap(short, long, arg_enum, default_value = "yaml")]
+        format: OutputFormat,
+
+        #[clap()]
+        contract_id: ContractId,
+    },
+
+    /// Export graph of the contract nodes known to the stash in Graphviz DOT
+    /// format, e.g. for rendering with `dot -Tsvg`
+    Graph {
+        #[clap()]
+        contract_id: ContractId,
+    },
 }
 
 impl SchemaCommand {
@@ -132,6 +152,13 @@ impl GenesisCommand {
                 format,
                 contract_id,
             } => self.exec_export(runtime, format, contract_id),
+            GenesisCommand::State {
+                format,
+                contract_id,
+            } => self.exec_state(runtime, format, contract_id),
+            GenesisCommand::Graph { contract_id } => {
+                self.exec_graph(runtime, contract_id)
+            }
         }
     }
 
@@ -189,4 +216,69 @@ impl GenesisCommand {
         }
         Ok(())
     }
+
+    fn exec_state(
+        &self,
+        mut runtime: Runtime,
+        format: OutputFormat,
+        contract_id: ContractId,
+    ) -> Result<(), Error> {
+        match &*runtime.contract_state(contract_id, format)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::ContractState(reply::SyncFormat(_, data)) => {
+                println!("{}", String::from_utf8_lossy(data));
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_graph(
+        &self,
+        mut runtime: Runtime,
+        contract_id: ContractId,
+    ) -> Result<(), Error> {
+        match &*runtime.contract_graph(contract_id)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Graph(dot) => {
+                print!("{}", dot);
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Imports checkpoint file to the stash
+pub fn checkpoint(mut runtime: Runtime, file: PathBuf) -> Result<(), Error> {
+    let path = fs::canonicalize(&file)
+        .map_err(|_| Error::InputFileIoError(format!("{:?}", file)))?;
+    match &*runtime.import_checkpoint(path.to_string_lossy().to_string())? {
+        Reply::Failure(failure) => {
+            eprintln!("Server returned error: {}", failure);
+        }
+        Reply::CheckpointImport(report) => {
+            runtime.output().print(report, || {
+                println!("Checkpoint imported: {}", report);
+            })?;
+        }
+        _ => {
+            eprintln!(
+                "Unexpected server error; probably you connecting with outdated client version"
+            );
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,54 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use super::{Error, Runtime};
+use crate::rpc::Reply;
+
+/// Prints status of the daemons managed by `rgbd` supervisor
+pub fn status(mut runtime: Runtime) -> Result<(), Error> {
+    match &*runtime.daemons()? {
+        Reply::Failure(failure) => {
+            eprintln!("Server returned error: {}", failure);
+        }
+        Reply::Daemons(daemons) => {
+            runtime.output().print(daemons, || {
+                println!(
+                    "{:<12} {:<10} {:<10} {:>8} {:>8}  {}",
+                    "Daemon",
+                    "Network",
+                    "State",
+                    "Uptime",
+                    "Restarts",
+                    "Last exit"
+                );
+                for daemon in daemons {
+                    println!(
+                        "{:<12} {:<10} {:<10} {:>7}s {:>8}  {}",
+                        daemon.name,
+                        daemon.network,
+                        daemon.state,
+                        daemon.uptime,
+                        daemon.restarts,
+                        daemon.last_exit.as_deref().unwrap_or("-")
+                    );
+                }
+            })?;
+        }
+        _ => {
+            eprintln!(
+                "Unexpected server error; probably you connecting with outdated client version"
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,313 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Interactive transfer mode, guiding the user through input selection and
+//! change allocation and producing the same result as the `transfer` command
+
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bitcoin::OutPoint;
+use lnpbp::seals::OutpointHash;
+use rgb::{AtomicValue, ContractId, FromBech32};
+use rgb20::SealCoins;
+
+use super::asset::known_assets;
+use super::fungible::TransferCli;
+use super::{Error, Runtime};
+use crate::rpc::reply::AllocationStatus;
+use crate::rpc::Reply;
+use crate::util::{format_amount, parse_amount};
+
+#[derive(Clap, Clone, PartialEq, Debug, Display)]
+#[display(Debug)]
+pub struct TransferWizard {
+    /// Asset to transfer; if omitted, it is selected from the known assets
+    #[clap(long, parse(try_from_str = ContractId::from_bech32_str))]
+    pub asset: Option<ContractId>,
+
+    /// Partially-signed transaction prototype; if omitted, the path is asked
+    /// for interactively
+    #[clap(long)]
+    pub prototype: Option<PathBuf>,
+}
+
+/// Asks the user for a value, returning the default one for an empty answer
+fn ask(question: &str, default: Option<&str>) -> Result<String, Error> {
+    match default {
+        Some(default) => eprint!("{} [{}]: ", question, default),
+        None => eprint!("{}: ", question),
+    }
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer)? == 0 {
+        // Input stream is closed, so there is nobody to ask anymore
+        Err(io::Error::from(io::ErrorKind::UnexpectedEof))?
+    }
+    match (answer.trim(), default) {
+        ("", Some(default)) => Ok(default.to_string()),
+        (answer, _) => Ok(answer.to_string()),
+    }
+}
+
+/// Repeats the question until the answer can be parsed
+fn ask_parse<T>(question: &str, default: Option<&str>) -> Result<T, Error>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    loop {
+        match ask(question, default)?.parse() {
+            Ok(value) => return Ok(value),
+            Err(err) => eprintln!("Invalid value: {}", err),
+        }
+    }
+}
+
+/// Asks for one or more (comma-separated) item numbers in `1..=count` range,
+/// returning zero-based indexes
+fn ask_choice(
+    question: &str,
+    count: usize,
+    default: Option<&str>,
+) -> Result<Vec<usize>, Error> {
+    loop {
+        let answer = ask(question, default)?;
+        if answer == "all" {
+            return Ok((0..count).collect());
+        }
+        let choice = answer
+            .split(',')
+            .map(|item| item.trim().parse::<usize>())
+            .collect::<Result<Vec<_>, _>>();
+        match choice {
+            Ok(choice)
+                if !choice.is_empty()
+                    && choice.iter().all(|no| *no >= 1 && *no <= count) =>
+            {
+                return Ok(choice.into_iter().map(|no| no - 1).collect())
+            }
+            _ => eprintln!("Please enter numbers from 1 to {}", count),
+        }
+    }
+}
+
+impl TransferWizard {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        let mut assets = match known_assets(&mut runtime)? {
+            Some(assets) => assets,
+            None => return Ok(()),
+        };
+        let asset = match self.asset {
+            Some(contract_id) => {
+                match assets.into_iter().find(|a| *a.id() == contract_id) {
+                    Some(asset) => asset,
+                    None => {
+                        eprintln!("Asset {} is not known", contract_id);
+                        return Ok(());
+                    }
+                }
+            }
+            None if assets.is_empty() => {
+                eprintln!("No assets are known");
+                return Ok(());
+            }
+            None => {
+                eprintln!("Known assets:");
+                for (no, asset) in assets.iter().enumerate() {
+                    eprintln!(
+                        "  {}) {} {} ({})",
+                        no + 1,
+                        asset.ticker(),
+                        asset.name(),
+                        asset.id()
+                    );
+                }
+                let choice =
+                    ask_choice("Asset to transfer", assets.len(), None)?;
+                if choice.len() != 1 {
+                    eprintln!("Only a single asset can be transferred");
+                    return Ok(());
+                }
+                assets.remove(choice[0])
+            }
+        };
+        let precision = *asset.decimal_precision();
+
+        let allocations = match &*runtime.allocations((*asset.id()).into())? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+                return Ok(());
+            }
+            Reply::AssetAllocations(allocations) => allocations.clone(),
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+                return Ok(());
+            }
+        };
+        let spendable = allocations
+            .into_iter()
+            .filter(|(_, allocated)| {
+                allocated.status != AllocationStatus::Spent
+                    && allocated.status != AllocationStatus::Reserved
+                    && allocated.status != AllocationStatus::Held
+            })
+            .map(|(outpoint, allocated)| {
+                (
+                    outpoint,
+                    allocated.amounts.iter().sum::<AtomicValue>(),
+                    allocated.status,
+                )
+            })
+            .collect::<Vec<(OutPoint, AtomicValue, AllocationStatus)>>();
+        if spendable.is_empty() {
+            eprintln!("There are no spendable allocations of {}", asset);
+            return Ok(());
+        }
+        eprintln!("Spendable allocations of {}:", asset.ticker());
+        for (no, (outpoint, value, status)) in spendable.iter().enumerate() {
+            eprintln!(
+                "  {}) {} @ {} ({})",
+                no + 1,
+                format_amount(*value, precision),
+                outpoint,
+                status
+            );
+        }
+        let choice = ask_choice(
+            "Inputs to spend (comma-separated numbers or `all`)",
+            spendable.len(),
+            Some("all"),
+        )?;
+        let inputs = choice
+            .iter()
+            .map(|index| spendable[*index].0)
+            .collect::<Vec<_>>();
+        let available = choice
+            .iter()
+            .map(|index| spendable[*index].1)
+            .sum::<AtomicValue>();
+
+        let question = format!(
+            "Amount to pay (up to {})",
+            format_amount(available, precision)
+        );
+        let payment = loop {
+            match parse_amount(&ask(&question, None)?, precision) {
+                Ok(payment) => break payment,
+                Err(err) => eprintln!("Invalid amount: {}", err),
+            }
+        };
+        if payment == 0 || payment > available {
+            eprintln!(
+                "Amount must be positive and not exceed {} available in the \
+                selected inputs",
+                format_amount(available, precision)
+            );
+            return Ok(());
+        }
+        let receiver: OutpointHash =
+            ask_parse("Blinded outpoint of the receiver", None)?;
+
+        let change = available - payment;
+        let mut allocate = vec![];
+        if change > 0 {
+            let seal = loop {
+                let answer = ask(
+                    &format!(
+                        "Output for the change of {}: witness transaction \
+                        output number or `txid:vout`",
+                        format_amount(change, precision)
+                    ),
+                    None,
+                )?;
+                if let Ok(vout) = answer.parse::<u32>() {
+                    break SealCoins {
+                        coins: change,
+                        vout,
+                        txid: None,
+                    };
+                }
+                match answer.parse::<OutPoint>() {
+                    Ok(outpoint) => {
+                        break SealCoins {
+                            coins: change,
+                            vout: outpoint.vout,
+                            txid: Some(outpoint.txid),
+                        }
+                    }
+                    Err(err) => eprintln!("Invalid value: {}", err),
+                }
+            };
+            allocate.push(seal);
+        }
+
+        let prototype = match self.prototype {
+            Some(prototype) => prototype,
+            None => {
+                ask_parse("Partially-signed witness transaction file", None)?
+            }
+        };
+        let default_path = |name: &str| {
+            prototype.with_file_name(name).to_string_lossy().to_string()
+        };
+        let consignment: PathBuf = ask_parse(
+            "File to save consignment to",
+            Some(&default_path("consignment.rgb")),
+        )?;
+        let disclosure: PathBuf = ask_parse(
+            "File to save disclosure to",
+            Some(&default_path("disclosure.rgb")),
+        )?;
+        let transaction: PathBuf = ask_parse(
+            "File to save witness transaction to",
+            Some(&default_path("witness.psbt")),
+        )?;
+
+        eprintln!(
+            "Paying {} {} to {}",
+            format_amount(payment, precision),
+            asset.ticker(),
+            receiver
+        );
+        for seal in &allocate {
+            eprintln!("Change: {}", seal);
+        }
+        if !ask("Proceed with the transfer? [y/N]", Some("n"))?
+            .eq_ignore_ascii_case("y")
+        {
+            eprintln!("Transfer is cancelled");
+            return Ok(());
+        }
+
+        TransferCli {
+            inputs,
+            allocate,
+            confirmed_only: false,
+            container: None,
+            theirs_witness: vec![],
+            receiver,
+            amount: payment,
+            asset: *asset.id(),
+            prototype,
+            consignment,
+            disclosure,
+            transaction,
+        }
+        .exec(runtime)
+    }
+}
@@ -0,0 +1,47 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::time::Duration;
+
+use internet2::ZmqSocketAddr;
+
+use crate::constants::*;
+
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display(Debug)]
+pub struct Config {
+    /// ZMQ RPC endpoint of the fungible contract daemon
+    pub rpc_endpoint: ZmqSocketAddr,
+
+    /// ZMQ endpoint at which the fungible contract daemon publishes events
+    pub pub_endpoint: ZmqSocketAddr,
+
+    /// Maximal time to wait for the node reply; after it expires the
+    /// request fails and the connection is re-established with the next
+    /// request
+    pub timeout: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            rpc_endpoint: FUNGIBLED_RPC_ENDPOINT
+                .parse()
+                .expect("Error in FUNGIBLED_RPC_ENDPOINT value"),
+            pub_endpoint: FUNGIBLED_PUB_ENDPOINT
+                .parse()
+                .expect("Error in FUNGIBLED_PUB_ENDPOINT value"),
+            timeout: Duration::from_secs(RGB_CLIENT_TIMEOUT),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::time::Duration;
+
+use crate::error::ServiceErrorDomain;
+use crate::rpc::reply;
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum Error {
+    /// RPC client internal error: {0}
+    #[from]
+    ServiceError(ServiceErrorDomain),
+
+    /// RGB Node returned error: {0}
+    #[from]
+    Reply(reply::Failure),
+
+    /// RGB node has not replied within {0:?}
+    Timeout(Duration),
+
+    /// Unexpected server response; please check that RGB node uses the same
+    /// API version as the client
+    UnexpectedResponse,
+
+    /// Consignment is kept pending until its witness transactions get enough
+    /// confirmations: {0}
+    Pending(reply::PendingConsignment),
+}
@@ -0,0 +1,116 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::sync::{Arc, Mutex};
+
+use internet2::{
+    presentation, session, transport, PlainTranscoder, Session, ZmqType,
+};
+use lnpbp::strict_encoding::strict_deserialize;
+
+use super::{Config, Error};
+use crate::error::ServiceErrorDomain;
+use crate::ln::{Event, EventRecord};
+use crate::util::Spawned;
+
+/// Subscription to the events published by the fungible contract daemon.
+/// ZMQ subscriber sockets reconnect automatically, so events published while
+/// the node was restarting are lost, but the subscription stays valid.
+pub struct EventSubscription {
+    session: Arc<
+        Mutex<session::Raw<PlainTranscoder, transport::zmqsocket::Connection>>,
+    >,
+}
+
+impl EventSubscription {
+    pub(super) fn with(config: &Config) -> Result<Self, Error> {
+        debug!("Subscribing to RGB node events at {}", config.pub_endpoint);
+        let session = session::Raw::with_zmq_unencrypted(
+            ZmqType::Sub,
+            &config.pub_endpoint,
+            None,
+            None,
+        )
+        .map_err(ServiceErrorDomain::from)?;
+        session
+            .as_socket()
+            .set_subscribe(b"")
+            .map_err(transport::Error::from)
+            .map_err(ServiceErrorDomain::from)?;
+        Ok(Self {
+            session: Arc::new(Mutex::new(session)),
+        })
+    }
+
+    /// Waits for the next event published by the node
+    pub fn next(&self) -> Spawned<Result<Event, Error>> {
+        let session = self.session.clone();
+        Spawned::spawn(move || Self::recv(&session).map(|record| record.event))
+    }
+
+    /// Waits for the next event published by the node together with its
+    /// sequence number, which may be used to request replay of the events
+    /// missed after it with [`Request::ReplayEvents`]
+    ///
+    /// [`Request::ReplayEvents`]: crate::rpc::fungible::Request::ReplayEvents
+    pub fn next_record(&self) -> Spawned<Result<EventRecord, Error>> {
+        let session = self.session.clone();
+        Spawned::spawn(move || Self::recv(&session))
+    }
+
+    fn recv(
+        session: &Mutex<
+            session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
+        >,
+    ) -> Result<EventRecord, Error> {
+        let mut session = session.lock().map_err(|_| {
+            ServiceErrorDomain::Internal(s!("event session is broken"))
+        })?;
+        let raw = session
+            .recv_raw_message()
+            .map_err(ServiceErrorDomain::from)?;
+        decode(&raw)
+    }
+}
+
+/// Decodes event record published by the node
+fn decode(raw: &[u8]) -> Result<EventRecord, Error> {
+    let record = strict_deserialize(raw)
+        .map_err(presentation::Error::from)
+        .map_err(ServiceErrorDomain::from)?;
+    Ok(record)
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::OutPoint;
+    use lnpbp::strict_encoding::strict_serialize;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_published_record() {
+        // Runtime publishes strict-serialized event records
+        let record = EventRecord {
+            seq: 42,
+            event: Event::AllocationSpent(OutPoint::default()),
+        };
+        let raw = strict_serialize(&record).unwrap();
+        let decoded = decode(&raw).unwrap();
+        assert_eq!(decoded.seq, 42);
+        assert!(matches!(
+            decoded.event,
+            Event::AllocationSpent(outpoint) if outpoint == OutPoint::default()
+        ));
+    }
+}
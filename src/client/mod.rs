@@ -0,0 +1,25 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Typed asynchronous client for the RGB node RPC API, which can be used by
+//! wallets instead of talking to the node ZMQ sockets directly.
+
+mod config;
+mod error;
+mod events;
+mod rgb_client;
+
+pub use config::Config;
+pub use error::Error;
+pub use events::EventSubscription;
+pub use rgb_client::RgbClient;
@@ -0,0 +1,167 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use amplify::Wrapper;
+use internet2::{
+    presentation, session, transport, CreateUnmarshaller, PlainTranscoder,
+    TypedEnum, Unmarshall, ZmqType,
+};
+use rgb::validation;
+use rgb20::Asset;
+
+use super::{Config, Error, EventSubscription};
+use crate::error::ServiceErrorDomain;
+use crate::rpc::fungible::{
+    AcceptReq, IssueReq, Request, TransferReq, ValidateReq,
+};
+use crate::rpc::{reply, Reply};
+use crate::util::compression::req_rep;
+use crate::util::Spawned;
+
+type RpcSession =
+    session::Raw<PlainTranscoder, transport::zmqsocket::Connection>;
+
+/// Asynchronous client to the fungible contract daemon. Requests are
+/// processed one by one over a single ZMQ REQ connection, which is
+/// established lazily and re-established after any transport failure or
+/// timeout. Requests are never retried automatically, since most of them
+/// are not idempotent.
+pub struct RgbClient {
+    config: Config,
+    session: Arc<Mutex<Option<RpcSession>>>,
+}
+
+impl RgbClient {
+    pub fn with(config: Config) -> Self {
+        Self {
+            config,
+            session: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Sends request to the node; the returned future is resolved with the
+    /// node reply
+    pub fn request(&self, request: Request) -> Spawned<Result<Reply, Error>> {
+        let session = self.session.clone();
+        let config = self.config.clone();
+        Spawned::spawn(move || exchange(&session, &config, request))
+    }
+
+    pub async fn issue(&self, issue: IssueReq) -> Result<Asset, Error> {
+        match self.request(Request::Issue(issue)).await? {
+            Reply::Asset(asset) => Ok(asset),
+            Reply::Failure(failure) => Err(Error::Reply(failure)),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    pub async fn transfer(
+        &self,
+        transfer: TransferReq,
+    ) -> Result<reply::Transfer, Error> {
+        match self.request(Request::Transfer(transfer)).await? {
+            Reply::Transfer(transfer) => Ok(transfer),
+            Reply::Failure(failure) => Err(Error::Reply(failure)),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    pub async fn validate(
+        &self,
+        validate: ValidateReq,
+    ) -> Result<validation::Status, Error> {
+        match self.request(Request::Validate(validate)).await? {
+            Reply::ValidationStatus(status) => Ok(status),
+            Reply::Pending(pending) => Err(Error::Pending(pending)),
+            Reply::Failure(failure) => Err(Error::Reply(failure)),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    pub async fn accept(&self, accept: AcceptReq) -> Result<(), Error> {
+        match self.request(Request::Accept(accept)).await? {
+            Reply::Success | Reply::AlreadyAccepted(_) => Ok(()),
+            Reply::Pending(pending) => Err(Error::Pending(pending)),
+            Reply::Failure(failure) => Err(Error::Reply(failure)),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    #[inline]
+    pub fn subscribe_events(&self) -> Result<EventSubscription, Error> {
+        EventSubscription::with(&self.config)
+    }
+}
+
+fn connect(config: &Config) -> Result<RpcSession, transport::Error> {
+    debug!("Connecting to RGB node at {}", config.rpc_endpoint);
+    let session = session::Raw::with_zmq_unencrypted(
+        ZmqType::Req,
+        &config.rpc_endpoint,
+        None,
+        None,
+    )?;
+    let timeout = config.timeout.as_millis() as i32;
+    let socket = session.as_socket();
+    socket.set_rcvtimeo(timeout)?;
+    socket.set_sndtimeo(timeout)?;
+    // Do not keep undelivered requests after the connection is dropped
+    socket.set_linger(0)?;
+    Ok(session)
+}
+
+fn is_timeout(err: &presentation::Error) -> bool {
+    match err {
+        presentation::Error::Transport(transport::Error::TimedOut) => true,
+        presentation::Error::Transport(transport::Error::Zmq(err)) => {
+            io::Error::from_raw_os_error(err.into_inner()).kind()
+                == io::ErrorKind::WouldBlock
+        }
+        _ => false,
+    }
+}
+
+fn exchange(
+    session: &Mutex<Option<RpcSession>>,
+    config: &Config,
+    request: Request,
+) -> Result<Reply, Error> {
+    let mut session = session.lock().map_err(|_| {
+        ServiceErrorDomain::Internal(s!("RPC session is broken"))
+    })?;
+    if session.is_none() {
+        *session = Some(connect(config).map_err(ServiceErrorDomain::from)?);
+    }
+    let connection = session.as_mut().expect("connection is established");
+
+    let raw = match req_rep(connection, request.serialize()) {
+        Ok(raw) => raw,
+        Err(err) => {
+            // REQ socket can't be used after a failed exchange, so it is
+            // dropped and the next request connects again
+            *session = None;
+            if is_timeout(&err) {
+                warn!("RGB node has not replied within {:?}", config.timeout);
+                Err(Error::Timeout(config.timeout))?
+            }
+            Err(ServiceErrorDomain::from(err))?
+        }
+    };
+    let reply = Reply::create_unmarshaller()
+        .unmarshall(&raw)
+        .map_err(ServiceErrorDomain::from)?;
+    Ok((*reply).clone())
+}
@@ -13,20 +13,145 @@
 
 //! Shared constants, including configuration parameters etc
 
+use lnpbp::Chain;
+
 pub const RGB20_BECH32_HRP_INVOICE: &'static str = "rgb20:";
 
+/// Maximum decimal precision of RGB20 assets: the largest power of ten which
+/// fits into `u64` atomic value is 10^19
+pub const RGB20_MAX_PRECISION: u8 = 19;
+
 pub const RGB_DATA_DIR: &'static str = "/var/lib/rgb";
 pub const RGB_BIN_DIR: &'static str = "/usr/local/bin";
 pub const RGB_CONTRACTS: &'static str = "fungible";
 pub const RGB_NETWORK: &'static str = "signet";
 
+pub const RGBD_RPC_ENDPOINT: &'static str = "lnpz:{data_dir}/rgbd.rpc";
+/// Delay (in seconds) before the restart of the exited daemon; it is doubled
+/// after each restart which was followed by an early exit
+pub const RGBD_RESTART_DELAY: u32 = 1;
+/// Maximum delay (in seconds) before the restart of the exited daemon
+pub const RGBD_MAX_RESTART_DELAY: u32 = 60;
+/// Time (in seconds) the restarted daemon has to run for its restart delay
+/// to be reset
+pub const RGBD_STABLE_UPTIME: u32 = 60;
+
 pub const STASHD_STASH: &'static str = "{data_dir}/{network}/stash/";
 pub const STASHD_INDEX: &'static str = "{data_dir}/{network}/index/";
 pub const STASHD_RPC_ENDPOINT: &'static str =
     "lnpz:{data_dir}/{network}/stashd.rpc";
+/// Time (in seconds) the primary stash waits for the standby node to accept
+/// replicated update before dropping it
+pub const STASHD_REPLICATION_TIMEOUT: u32 = 5;
+/// Time (in seconds) the stash waits for the peer to provide history of the
+/// contract imported from a checkpoint
+pub const STASHD_BACKFILL_TIMEOUT: u32 = 30;
+/// Number of the recently used anchors, and of the transitions, kept in
+/// memory by the S3 stash storage
+pub const STASHD_S3_CACHED_ITEMS: usize = 10_000;
 
 pub const FUNGIBLED_CACHE: &'static str = "{data_dir}/{network}/cache/fungible";
 pub const FUNGIBLED_RPC_ENDPOINT: &'static str =
     "lnpz:{data_dir}/{network}/fungibled.rpc";
+pub const FUNGIBLED_PUB_ENDPOINT: &'static str =
+    "lnpz:{data_dir}/{network}/fungibled.pub";
+/// Time (in seconds) after which transfer inputs reserved by a pending spend
+/// are released if the witness transaction was not mined
+pub const FUNGIBLED_RESERVATION_TIMEOUT: u32 = 24 * 60 * 60;
+/// Number of days during which forgotten allocations can be restored
+pub const FUNGIBLED_TOMBSTONE_DAYS: u32 = 30;
+/// Number of days during which accepted consignments are kept for re-export
+pub const FUNGIBLED_CONSIGNMENT_RETENTION_DAYS: u32 = 90;
+/// Interval (in seconds) between re-checks of the asset allocation outpoints
+/// status
+pub const FUNGIBLED_WATCH_INTERVAL: u32 = 10 * 60;
+/// Genesis timestamp used for deterministic asset issuance (the minimal
+/// timestamp allowed by RGB20 schema)
+pub const FUNGIBLED_GENESIS_TIMESTAMP: i64 = 1602340666;
+/// Time (in seconds) the health endpoint waits for the daemon status reply
+pub const FUNGIBLED_HEALTH_TIMEOUT: u32 = 10;
+/// Time (in seconds) during which status of the completed background job
+/// remains available to the clients
+pub const FUNGIBLED_JOB_RETENTION: u32 = 24 * 60 * 60;
+/// Number of keys derived on each chain of the external signer descriptors
+pub const FUNGIBLED_DESCRIPTOR_GAP: u32 = 100;
+/// Time (in seconds) the node waits for the co-signing service reply
+pub const FUNGIBLED_SIGNER_TIMEOUT: u32 = 30;
+/// Number of the latest published events kept for replay to the subscribers
+pub const FUNGIBLED_EVENT_LOG_SIZE: usize = 10_000;
+/// Time (in seconds) during which signatures of the admin and account
+/// requests remain valid
+pub const FUNGIBLED_SIGNATURE_TTL: u32 = 5 * 60;
+/// Number of allocation records of the bulk import applied to the cache at
+/// once, unless specified by the request
+pub const FUNGIBLED_BULK_IMPORT_BATCH: u32 = 1000;
+/// Maximum number of the metadata entries attached to a cached asset
+pub const FUNGIBLED_ASSET_META_ENTRIES: usize = 64;
+/// Maximum length (in bytes) of the asset metadata key or value
+pub const FUNGIBLED_ASSET_META_SIZE: usize = 16 * 1024;
+/// Maximum number of labels of an outpoint or asset allocation
+pub const FUNGIBLED_LABELS_PER_TARGET: usize = 64;
+/// Maximum length (in bytes) of the outpoint or allocation label
+pub const FUNGIBLED_LABEL_SIZE: usize = 256;
+/// Time (in seconds) the node waits for the webhook endpoint reply
+pub const FUNGIBLED_WEBHOOK_TIMEOUT: u32 = 10;
+/// Number of attempts to deliver the event to the webhook
+pub const FUNGIBLED_WEBHOOK_ATTEMPTS: u32 = 6;
+/// Delay (in seconds) before the first retry of the failed webhook
+/// delivery; each next retry waits twice as long
+pub const FUNGIBLED_WEBHOOK_BACKOFF: u32 = 5;
+/// Number of PBKDF2 rounds deriving encryption key of the exported claims
+/// from the passphrase
+pub const FUNGIBLED_CLAIMS_KDF_ROUNDS: u32 = 100_000;
+/// Maximal number of PBKDF2 rounds accepted from the imported claims bundle,
+/// limiting the time the daemon spends on a single import
+pub const FUNGIBLED_CLAIMS_KDF_MAX_ROUNDS: u32 = 1_000_000;
+/// Interval (in seconds) between the scheduled backups
+pub const FUNGIBLED_BACKUP_INTERVAL: u32 = 24 * 60 * 60;
+/// Number of the latest backups kept by the rotation
+pub const FUNGIBLED_BACKUP_KEEP: u32 = 7;
+
+/// Maximum size (in bytes) of the RPC message, both as received and after
+/// decompression
+pub const RPC_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+/// Maximum nesting level of the API messages embedded into other messages,
+/// like batched or idempotent requests
+pub const RPC_MAX_NESTING_DEPTH: usize = 8;
+/// Maximum number of requests in a batch
+pub const RPC_MAX_BATCH_SIZE: usize = 1_000;
+/// Maximum number of state transitions and extensions in a consignment
+pub const RPC_MAX_CONSIGNMENT_NODES: usize = 10_000;
+/// Maximum number of endpoints in a consignment
+pub const RPC_MAX_CONSIGNMENT_ENDPOINTS: usize = 1_000;
 
+/// Time (in seconds) RPC client waits for the node reply before reconnecting
+pub const RGB_CLIENT_TIMEOUT: u64 = 30;
+
+/// Time (in seconds) the node waits for the S3 endpoint reply
+pub const S3_TIMEOUT: u32 = 60;
+
+/// Endpoint of the lnp-node ZMQ bus publishing channel lifecycle events
+pub const LNP_NODE_EVENTS_ENDPOINT: &'static str =
+    "lnpz:{data_dir}/{network}/lnpd.pub";
+
+/// Electrum server for the default (signet) network
 pub const DEFAULT_ELECTRUM_ENDPOINT: &'static str = "pandora.network:60601";
+pub const ELECTRUM_MAINNET_ENDPOINT: &'static str = "pandora.network:50001";
+pub const ELECTRUM_TESTNET_ENDPOINT: &'static str = "pandora.network:60001";
+/// Private regtest and signet networks, as well as non-bitcoin chains, use
+/// locally running Electrum server on its default port for the network
+pub const ELECTRUM_REGTEST_ENDPOINT: &'static str = "127.0.0.1:60401";
+pub const ELECTRUM_SIGNET_CUSTOM_ENDPOINT: &'static str = "127.0.0.1:60601";
+pub const ELECTRUM_LOCAL_ENDPOINT: &'static str = "127.0.0.1:50001";
+
+/// Electrum server used for the network if no server is configured
+pub fn default_electrum_endpoint(network: &Chain) -> &'static str {
+    match network {
+        Chain::Mainnet => ELECTRUM_MAINNET_ENDPOINT,
+        Chain::Testnet3 => ELECTRUM_TESTNET_ENDPOINT,
+        Chain::Signet => DEFAULT_ELECTRUM_ENDPOINT,
+        Chain::SignetCustom(_) => ELECTRUM_SIGNET_CUSTOM_ENDPOINT,
+        Chain::Regtest(_) => ELECTRUM_REGTEST_ENDPOINT,
+        _ => ELECTRUM_LOCAL_ENDPOINT,
+    }
+}
@@ -14,9 +14,12 @@
 // TODO #152: Consider moving parts of this file to common daemon modules
 //       (Internet2)
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
 use std::io;
 
+use lnpbp::Chain;
+
 #[derive(Debug, Display, Error, From)]
 #[display(Debug)]
 #[non_exhaustive]
@@ -38,11 +41,25 @@ pub enum BootstrapError {
 
     StorageError,
 
-    #[cfg(feature = "fungibles")]
+    #[cfg(all(feature = "_rpc", feature = "fungibles"))]
     #[from(crate::fungibled::FileCacheError)]
     #[cfg_attr(feature = "sql", from(crate::fungibled::SqlCacheError))]
     CacheError,
 
+    #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+    #[from(crate::fungibled::PolicyError)]
+    PolicyError,
+
+    #[from(crate::util::config::ConfigError)]
+    ConfigError,
+
+    #[from(crate::util::logging::LoggingError)]
+    LoggingError,
+
+    #[cfg(feature = "server")]
+    #[from]
+    LifecycleError(crate::util::daemon::LifecycleError),
+
     Other,
 }
 
@@ -89,7 +106,7 @@ pub enum ServiceErrorDomain {
 
     Index(String),
 
-    #[cfg(feature = "fungibles")]
+    #[cfg(all(feature = "_rpc", feature = "fungibles"))]
     #[from(crate::fungibled::FileCacheError)]
     #[cfg_attr(feature = "sql", from(crate::fungibled::SqlCacheError))]
     Cache,
@@ -124,6 +141,67 @@ pub enum ServiceErrorDomain {
 
     Anchor(String),
 
+    Config(String),
+
+    /// Node runs in read-only mode and does not serve state-changing
+    /// requests; they must be sent to the primary node, if it is known
+    ReadOnly(Option<String>),
+
+    /// Genesis or consignment belongs to a bitcoin network different from
+    /// the one served by the daemon
+    WrongNetwork {
+        expected: Chain,
+        found: Chain,
+    },
+
+    #[cfg(feature = "_rpc")]
+    #[from]
+    Issue(crate::rpc::fungible::IssueError),
+
+    #[cfg(feature = "_rpc")]
+    #[from]
+    Transfer(crate::rpc::fungible::TransferError),
+
+    #[cfg(feature = "_rpc")]
+    #[from]
+    Idempotency(crate::rpc::fungible::IdempotencyError),
+
+    #[cfg(feature = "_rpc")]
+    #[from]
+    Job(crate::rpc::fungible::JobError),
+
+    #[cfg(feature = "_rpc")]
+    #[from]
+    Account(crate::rpc::fungible::AccountError),
+
+    #[cfg(feature = "_rpc")]
+    #[from]
+    Limit(crate::rpc::limits::LimitError),
+
+    #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+    #[from]
+    Policy(crate::fungibled::PolicyViolation),
+
+    #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+    #[from]
+    Channel(crate::ln::ChannelError),
+
+    #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+    #[from]
+    Swap(crate::fungibled::SwapError),
+
+    #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+    #[from]
+    Signer(crate::fungibled::SignerError),
+
+    #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+    #[from]
+    Compose(crate::fungibled::ComposeError),
+
+    #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+    #[from]
+    Funding(crate::fungibled::FundingError),
+
     #[from]
     #[cfg_attr(
         feature = "fungibles",
@@ -165,6 +243,711 @@ pub enum ApiErrorType {
     UnexpectedReply,
 }
 
+/// Category of the errors reported to the API clients
+#[derive(
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Display,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum ErrorCategory {
+    /// Message transport or encoding failure
+    #[display("transport")]
+    Transport = 1,
+
+    /// Internal failure of the daemon
+    #[display("runtime")]
+    Runtime = 2,
+
+    /// Request is malformed or can't be served in its present form
+    #[display("request")]
+    Request = 3,
+
+    /// Failure of the local data storage
+    #[display("storage")]
+    Storage = 4,
+
+    /// Service the daemon depends on is not available or has failed
+    #[display("dependency")]
+    Dependency = 5,
+
+    /// Request violates RGB contract rules or node policies
+    #[display("contract")]
+    Contract = 6,
+
+    /// Daemon configuration is invalid
+    #[display("config")]
+    Config = 7,
+}
+
+/// Stable numeric codes of the errors reported to the API clients. Higher
+/// byte of the code matches [`ErrorCategory`] of the error. Codes are never
+/// reused, so clients may rely on them across daemon versions.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[display(Debug)]
+#[non_exhaustive]
+#[repr(u16)]
+pub enum ErrorCode {
+    Encoding = 0x0101,
+    Transport = 0x0102,
+
+    Runtime = 0x0201,
+    Internal = 0x0202,
+    Multithreading = 0x0203,
+
+    MalformedRequest = 0x0301,
+    UnknownCommand = 0x0302,
+    UnimplementedCommand = 0x0303,
+    MissedArgument = 0x0304,
+    UnknownArgument = 0x0305,
+    MalformedArgument = 0x0306,
+    UnexpectedReply = 0x0307,
+    IdempotencyKeyReused = 0x0310,
+    NotStateChanging = 0x0311,
+    JobNotSupported = 0x0312,
+    UnknownJob = 0x0313,
+    ReadOnly = 0x0314,
+    UnknownAccount = 0x0315,
+    InvalidAccountSignature = 0x0316,
+    NotAccountScoped = 0x0317,
+    NotOwnedByAccount = 0x0318,
+    LimitExceeded = 0x0319,
+    AccountRequired = 0x031A,
+
+    Io = 0x0401,
+    Storage = 0x0402,
+    Index = 0x0403,
+    Cache = 0x0404,
+
+    StashUnavailable = 0x0501,
+    ChainBackend = 0x0502,
+    NetworkService = 0x0503,
+    Signer = 0x0504,
+
+    Schema = 0x0601,
+    Anchor = 0x0602,
+    WrongNetwork = 0x0603,
+    PrecisionTooLarge = 0x0610,
+    IssueOverflow = 0x0611,
+    SupplyOverflow = 0x0612,
+    SupplyCapExceeded = 0x0613,
+    DustOutput = 0x0620,
+    UnknownTransfer = 0x0621,
+    InputReserved = 0x0622,
+    UnconfirmedInput = 0x0623,
+    SealAlreadySpent = 0x0624,
+    OutpointReserved = 0x0625,
+    AnchorMismatch = 0x0626,
+    UnsignedInput = 0x0627,
+    ApprovalPending = 0x0628,
+    UnknownApprover = 0x0629,
+    InvalidApproval = 0x062A,
+    AllocationOnHold = 0x062B,
+    UnknownAdmin = 0x062C,
+    InvalidAdminSignature = 0x062D,
+    WitnessUnknown = 0x062E,
+    NoOwnedChange = 0x062F,
+    MaxTransferExceeded = 0x0630,
+    RecipientNotAllowed = 0x0631,
+    InsufficientConfirmations = 0x0632,
+    ConfirmationsUnknown = 0x0633,
+    ChangeNotOwned = 0x0634,
+    ChannelTransition = 0x0640,
+    NoChannelFunding = 0x0641,
+    ChannelUnbalanced = 0x0642,
+    SwapMissingAsset = 0x0650,
+    SwapSameAsset = 0x0651,
+    SwapWrongHistory = 0x0652,
+    SwapUnderpaid = 0x0653,
+    SwapOtherAssets = 0x0654,
+    SwapInvalidLeg = 0x0655,
+    SwapWitnessMismatch = 0x0656,
+    SwapNotAnchored = 0x0657,
+    InsufficientFunds = 0x0660,
+    UnknownWitnessInput = 0x0661,
+    NoChangeOutput = 0x0662,
+    NoFundingWallet = 0x0663,
+    FundingGapLimit = 0x0664,
+    NoUnlockedFunding = 0x0665,
+
+    Config = 0x0701,
+}
+
+impl ErrorCode {
+    pub fn category(self) -> ErrorCategory {
+        match self as u16 >> 8 {
+            0x01 => ErrorCategory::Transport,
+            0x02 => ErrorCategory::Runtime,
+            0x03 => ErrorCategory::Request,
+            0x04 => ErrorCategory::Storage,
+            0x05 => ErrorCategory::Dependency,
+            0x06 => ErrorCategory::Contract,
+            0x07 => ErrorCategory::Config,
+            _ => unreachable!("error code outside of the known categories"),
+        }
+    }
+
+    const ALL: [ErrorCode; 76] = [
+        ErrorCode::Encoding,
+        ErrorCode::Transport,
+        ErrorCode::Runtime,
+        ErrorCode::Internal,
+        ErrorCode::Multithreading,
+        ErrorCode::MalformedRequest,
+        ErrorCode::UnknownCommand,
+        ErrorCode::UnimplementedCommand,
+        ErrorCode::MissedArgument,
+        ErrorCode::UnknownArgument,
+        ErrorCode::MalformedArgument,
+        ErrorCode::UnexpectedReply,
+        ErrorCode::IdempotencyKeyReused,
+        ErrorCode::NotStateChanging,
+        ErrorCode::JobNotSupported,
+        ErrorCode::UnknownJob,
+        ErrorCode::ReadOnly,
+        ErrorCode::UnknownAccount,
+        ErrorCode::InvalidAccountSignature,
+        ErrorCode::NotAccountScoped,
+        ErrorCode::NotOwnedByAccount,
+        ErrorCode::LimitExceeded,
+        ErrorCode::AccountRequired,
+        ErrorCode::Io,
+        ErrorCode::Storage,
+        ErrorCode::Index,
+        ErrorCode::Cache,
+        ErrorCode::StashUnavailable,
+        ErrorCode::ChainBackend,
+        ErrorCode::NetworkService,
+        ErrorCode::Signer,
+        ErrorCode::Schema,
+        ErrorCode::Anchor,
+        ErrorCode::WrongNetwork,
+        ErrorCode::PrecisionTooLarge,
+        ErrorCode::IssueOverflow,
+        ErrorCode::SupplyOverflow,
+        ErrorCode::SupplyCapExceeded,
+        ErrorCode::DustOutput,
+        ErrorCode::UnknownTransfer,
+        ErrorCode::InputReserved,
+        ErrorCode::UnconfirmedInput,
+        ErrorCode::SealAlreadySpent,
+        ErrorCode::OutpointReserved,
+        ErrorCode::AnchorMismatch,
+        ErrorCode::UnsignedInput,
+        ErrorCode::ApprovalPending,
+        ErrorCode::UnknownApprover,
+        ErrorCode::InvalidApproval,
+        ErrorCode::AllocationOnHold,
+        ErrorCode::UnknownAdmin,
+        ErrorCode::InvalidAdminSignature,
+        ErrorCode::WitnessUnknown,
+        ErrorCode::NoOwnedChange,
+        ErrorCode::MaxTransferExceeded,
+        ErrorCode::RecipientNotAllowed,
+        ErrorCode::InsufficientConfirmations,
+        ErrorCode::ConfirmationsUnknown,
+        ErrorCode::ChangeNotOwned,
+        ErrorCode::ChannelTransition,
+        ErrorCode::NoChannelFunding,
+        ErrorCode::ChannelUnbalanced,
+        ErrorCode::SwapMissingAsset,
+        ErrorCode::SwapSameAsset,
+        ErrorCode::SwapWrongHistory,
+        ErrorCode::SwapUnderpaid,
+        ErrorCode::SwapOtherAssets,
+        ErrorCode::SwapInvalidLeg,
+        ErrorCode::SwapWitnessMismatch,
+        ErrorCode::SwapNotAnchored,
+        ErrorCode::InsufficientFunds,
+        ErrorCode::UnknownWitnessInput,
+        ErrorCode::NoChangeOutput,
+        ErrorCode::NoFundingWallet,
+        ErrorCode::FundingGapLimit,
+        ErrorCode::NoUnlockedFunding,
+        ErrorCode::Config,
+    ];
+}
+
+/// Code unknown to this version of the library
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Display, Error, From)]
+#[display("unknown error code {0:#06x}")]
+pub struct UnknownErrorCode(pub u16);
+
+impl TryFrom<u16> for ErrorCode {
+    type Error = UnknownErrorCode;
+
+    fn try_from(code: u16) -> Result<Self, Self::Error> {
+        ErrorCode::ALL
+            .iter()
+            .copied()
+            .find(|known| *known as u16 == code)
+            .ok_or(UnknownErrorCode(code))
+    }
+}
+
+impl ServiceErrorDomain {
+    /// Stable error code reported to the API clients
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ServiceErrorDomain::Io(_) => ErrorCode::Io,
+            ServiceErrorDomain::Stash => ErrorCode::StashUnavailable,
+            ServiceErrorDomain::Storage(_) => ErrorCode::Storage,
+            ServiceErrorDomain::Index(_) => ErrorCode::Index,
+            #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+            ServiceErrorDomain::Cache => ErrorCode::Cache,
+            ServiceErrorDomain::Multithreading => ErrorCode::Multithreading,
+            ServiceErrorDomain::LnpRpc(_) => ErrorCode::Encoding,
+            ServiceErrorDomain::LnpTransport(_) => ErrorCode::Transport,
+            ServiceErrorDomain::Api(api) => api.code(),
+            ServiceErrorDomain::P2pwire
+            | ServiceErrorDomain::Monitoring
+            | ServiceErrorDomain::Bifrost
+            | ServiceErrorDomain::BpNode
+            | ServiceErrorDomain::LnpNode
+            | ServiceErrorDomain::Lightning => ErrorCode::NetworkService,
+            ServiceErrorDomain::Bitcoin | ServiceErrorDomain::Electrum => {
+                ErrorCode::ChainBackend
+            }
+            ServiceErrorDomain::Schema(_) => ErrorCode::Schema,
+            ServiceErrorDomain::Anchor(_) => ErrorCode::Anchor,
+            ServiceErrorDomain::Config(_) => ErrorCode::Config,
+            ServiceErrorDomain::ReadOnly(_) => ErrorCode::ReadOnly,
+            ServiceErrorDomain::WrongNetwork { .. } => ErrorCode::WrongNetwork,
+            #[cfg(feature = "_rpc")]
+            ServiceErrorDomain::Issue(err) => {
+                use crate::rpc::fungible::IssueError;
+                match err {
+                    IssueError::PrecisionTooLarge(_) => {
+                        ErrorCode::PrecisionTooLarge
+                    }
+                    IssueError::IssueOverflow => ErrorCode::IssueOverflow,
+                    IssueError::SupplyOverflow => ErrorCode::SupplyOverflow,
+                    IssueError::SupplyCapExceeded { .. } => {
+                        ErrorCode::SupplyCapExceeded
+                    }
+                }
+            }
+            #[cfg(feature = "_rpc")]
+            ServiceErrorDomain::Transfer(err) => {
+                use crate::rpc::fungible::TransferError;
+                match err {
+                    TransferError::DustOutput { .. } => ErrorCode::DustOutput,
+                    TransferError::UnknownTransfer(_) => {
+                        ErrorCode::UnknownTransfer
+                    }
+                    TransferError::InputReserved { .. } => {
+                        ErrorCode::InputReserved
+                    }
+                    TransferError::UnconfirmedInput { .. } => {
+                        ErrorCode::UnconfirmedInput
+                    }
+                    TransferError::SealAlreadySpent { .. } => {
+                        ErrorCode::SealAlreadySpent
+                    }
+                    TransferError::OutpointReserved(_) => {
+                        ErrorCode::OutpointReserved
+                    }
+                    TransferError::AnchorMismatch(_) => {
+                        ErrorCode::AnchorMismatch
+                    }
+                    TransferError::UnsignedInput { .. } => {
+                        ErrorCode::UnsignedInput
+                    }
+                    TransferError::ApprovalPending(_) => {
+                        ErrorCode::ApprovalPending
+                    }
+                    TransferError::UnknownApprover(_) => {
+                        ErrorCode::UnknownApprover
+                    }
+                    TransferError::InvalidApproval(_) => {
+                        ErrorCode::InvalidApproval
+                    }
+                    TransferError::AllocationOnHold(_) => {
+                        ErrorCode::AllocationOnHold
+                    }
+                    TransferError::UnknownAdmin(_) => ErrorCode::UnknownAdmin,
+                    TransferError::InvalidAdminSignature => {
+                        ErrorCode::InvalidAdminSignature
+                    }
+                    TransferError::WitnessUnknown(_) => {
+                        ErrorCode::WitnessUnknown
+                    }
+                    TransferError::NoOwnedChange(_) => ErrorCode::NoOwnedChange,
+                }
+            }
+            #[cfg(feature = "_rpc")]
+            ServiceErrorDomain::Idempotency(err) => {
+                use crate::rpc::fungible::IdempotencyError;
+                match err {
+                    IdempotencyError::KeyReused(_) => {
+                        ErrorCode::IdempotencyKeyReused
+                    }
+                    IdempotencyError::NotStateChanging(_) => {
+                        ErrorCode::NotStateChanging
+                    }
+                }
+            }
+            #[cfg(feature = "_rpc")]
+            ServiceErrorDomain::Job(err) => {
+                use crate::rpc::fungible::JobError;
+                match err {
+                    JobError::NotSupported(_) => ErrorCode::JobNotSupported,
+                    JobError::UnknownJob(_) => ErrorCode::UnknownJob,
+                }
+            }
+            #[cfg(feature = "_rpc")]
+            ServiceErrorDomain::Account(err) => {
+                use crate::rpc::fungible::AccountError;
+                match err {
+                    AccountError::UnknownAccount(_) => {
+                        ErrorCode::UnknownAccount
+                    }
+                    AccountError::InvalidSignature => {
+                        ErrorCode::InvalidAccountSignature
+                    }
+                    AccountError::NotSupported(_) => {
+                        ErrorCode::NotAccountScoped
+                    }
+                    AccountError::NotOwned(_) => ErrorCode::NotOwnedByAccount,
+                    AccountError::Required(_) => ErrorCode::AccountRequired,
+                }
+            }
+            #[cfg(feature = "_rpc")]
+            ServiceErrorDomain::Limit(_) => ErrorCode::LimitExceeded,
+            #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+            ServiceErrorDomain::Policy(err) => {
+                use crate::fungibled::PolicyViolation;
+                match err {
+                    PolicyViolation::MaxTransferExceeded { .. } => {
+                        ErrorCode::MaxTransferExceeded
+                    }
+                    PolicyViolation::RecipientNotAllowed { .. } => {
+                        ErrorCode::RecipientNotAllowed
+                    }
+                    PolicyViolation::InsufficientConfirmations { .. } => {
+                        ErrorCode::InsufficientConfirmations
+                    }
+                    PolicyViolation::ConfirmationsUnknown(_) => {
+                        ErrorCode::ConfirmationsUnknown
+                    }
+                    PolicyViolation::ChangeNotOwned { .. } => {
+                        ErrorCode::ChangeNotOwned
+                    }
+                }
+            }
+            #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+            ServiceErrorDomain::Channel(err) => {
+                use crate::ln::ChannelError;
+                match err {
+                    ChannelError::Transfer(_) => ErrorCode::ChannelTransition,
+                    ChannelError::NoFunding { .. } => {
+                        ErrorCode::NoChannelFunding
+                    }
+                    ChannelError::Unbalanced { .. } => {
+                        ErrorCode::ChannelUnbalanced
+                    }
+                }
+            }
+            #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+            ServiceErrorDomain::Swap(err) => {
+                use crate::fungibled::SwapError;
+                match err {
+                    SwapError::MissingAsset(_) => ErrorCode::SwapMissingAsset,
+                    SwapError::SameAsset(_) => ErrorCode::SwapSameAsset,
+                    SwapError::WrongHistory(_) => ErrorCode::SwapWrongHistory,
+                    SwapError::AssetUnderpaid { .. }
+                    | SwapError::BitcoinUnderpaid { .. } => {
+                        ErrorCode::SwapUnderpaid
+                    }
+                    SwapError::OtherAssets { .. } => ErrorCode::SwapOtherAssets,
+                    SwapError::InvalidLeg(_) => ErrorCode::SwapInvalidLeg,
+                    SwapError::WitnessMismatch => {
+                        ErrorCode::SwapWitnessMismatch
+                    }
+                    SwapError::NotAnchored(_) => ErrorCode::SwapNotAnchored,
+                    SwapError::Anchor(_) => ErrorCode::Anchor,
+                }
+            }
+            #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+            ServiceErrorDomain::Signer(_) => ErrorCode::Signer,
+            #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+            ServiceErrorDomain::Compose(err) => {
+                use crate::fungibled::ComposeError;
+                match err {
+                    ComposeError::InsufficientFunds { .. } => {
+                        ErrorCode::InsufficientFunds
+                    }
+                    ComposeError::UnknownInput(_) => {
+                        ErrorCode::UnknownWitnessInput
+                    }
+                    ComposeError::NoChange => ErrorCode::NoChangeOutput,
+                }
+            }
+            #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+            ServiceErrorDomain::Funding(err) => {
+                use crate::fungibled::FundingError;
+                match err {
+                    FundingError::NoDescriptors => ErrorCode::NoFundingWallet,
+                    FundingError::GapLimit(_) => ErrorCode::FundingGapLimit,
+                    FundingError::NoUnlockedOutput => {
+                        ErrorCode::NoUnlockedFunding
+                    }
+                }
+            }
+            ServiceErrorDomain::Internal(_) => ErrorCode::Internal,
+        }
+    }
+
+    /// Machine-readable parameters of the error, which are also mentioned
+    /// in its human-readable description
+    pub fn details(&self) -> BTreeMap<String, String> {
+        match self {
+            ServiceErrorDomain::WrongNetwork { expected, found } => vec![
+                ("expected".to_string(), expected.to_string()),
+                ("found".to_string(), found.to_string()),
+            ]
+            .into_iter()
+            .collect(),
+            ServiceErrorDomain::ReadOnly(Some(primary)) => {
+                vec![("primary".to_string(), primary.clone())]
+                    .into_iter()
+                    .collect()
+            }
+            #[cfg(feature = "_rpc")]
+            ServiceErrorDomain::Issue(
+                crate::rpc::fungible::IssueError::SupplyCapExceeded {
+                    supply,
+                    cap,
+                },
+            ) => bmap! {
+                s!("supply") => supply.to_string(),
+                s!("cap") => cap.to_string()
+            },
+            #[cfg(feature = "_rpc")]
+            ServiceErrorDomain::Transfer(err) => {
+                use crate::rpc::fungible::{HoldTarget, TransferError};
+                match err {
+                    TransferError::DustOutput {
+                        endpoint,
+                        amount,
+                        dust_limit,
+                    } => bmap! {
+                        s!("endpoint") => endpoint.to_string(),
+                        s!("amount") => amount.to_string(),
+                        s!("dust_limit") => dust_limit.to_string()
+                    },
+                    TransferError::UnknownTransfer(txid)
+                    | TransferError::AnchorMismatch(txid)
+                    | TransferError::ApprovalPending(txid)
+                    | TransferError::InvalidApproval(txid)
+                    | TransferError::WitnessUnknown(txid) => bmap! {
+                        s!("txid") => txid.to_string()
+                    },
+                    TransferError::InputReserved { outpoint, txid }
+                    | TransferError::SealAlreadySpent { outpoint, txid } => {
+                        bmap! {
+                            s!("outpoint") => outpoint.to_string(),
+                            s!("txid") => txid.to_string()
+                        }
+                    }
+                    TransferError::UnconfirmedInput { outpoint, status } => {
+                        bmap! {
+                            s!("outpoint") => outpoint.to_string(),
+                            s!("status") => status.to_string()
+                        }
+                    }
+                    TransferError::OutpointReserved(outpoint) => bmap! {
+                        s!("outpoint") => outpoint.to_string()
+                    },
+                    TransferError::UnknownApprover(approver) => bmap! {
+                        s!("approver") => approver.to_string()
+                    },
+                    TransferError::UnsignedInput { txid, input } => bmap! {
+                        s!("txid") => txid.to_string(),
+                        s!("input") => input.to_string()
+                    },
+                    TransferError::AllocationOnHold(HoldTarget::Contract(
+                        contract_id,
+                    ))
+                    | TransferError::NoOwnedChange(contract_id) => bmap! {
+                        s!("contract_id") => contract_id.to_string()
+                    },
+                    TransferError::AllocationOnHold(HoldTarget::Outpoint(
+                        outpoint,
+                    )) => bmap! {
+                        s!("outpoint") => outpoint.to_string()
+                    },
+                    TransferError::UnknownAdmin(admin) => bmap! {
+                        s!("admin") => admin.to_string()
+                    },
+                    TransferError::InvalidAdminSignature => bmap! {},
+                }
+            }
+            #[cfg(feature = "_rpc")]
+            ServiceErrorDomain::Idempotency(
+                crate::rpc::fungible::IdempotencyError::KeyReused(key),
+            ) => bmap! { s!("key") => key.clone() },
+            #[cfg(feature = "_rpc")]
+            ServiceErrorDomain::Job(
+                crate::rpc::fungible::JobError::UnknownJob(id),
+            ) => bmap! { s!("job") => id.to_string() },
+            #[cfg(feature = "_rpc")]
+            ServiceErrorDomain::Account(
+                crate::rpc::fungible::AccountError::UnknownAccount(account),
+            ) => bmap! { s!("account") => account.to_string() },
+            #[cfg(feature = "_rpc")]
+            ServiceErrorDomain::Limit(err) => {
+                use crate::rpc::limits::LimitError;
+                match err {
+                    LimitError::MessageTooLarge { size, limit } => bmap! {
+                        s!("size") => size.to_string(),
+                        s!("limit") => limit.to_string()
+                    },
+                    LimitError::TooManyItems { what, count, limit } => bmap! {
+                        s!("what") => what.to_string(),
+                        s!("count") => count.to_string(),
+                        s!("limit") => limit.to_string()
+                    },
+                }
+            }
+            #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+            ServiceErrorDomain::Policy(err) => {
+                use crate::fungibled::PolicyViolation;
+                match err {
+                    PolicyViolation::MaxTransferExceeded {
+                        contract_id,
+                        amount,
+                        max,
+                    } => bmap! {
+                        s!("contract_id") => contract_id.to_string(),
+                        s!("amount") => amount.to_string(),
+                        s!("max") => max.to_string()
+                    },
+                    PolicyViolation::RecipientNotAllowed {
+                        contract_id,
+                        endpoint,
+                    } => bmap! {
+                        s!("contract_id") => contract_id.to_string(),
+                        s!("endpoint") => endpoint.to_string()
+                    },
+                    PolicyViolation::InsufficientConfirmations {
+                        outpoint,
+                        confirmations,
+                        required,
+                    } => bmap! {
+                        s!("outpoint") => outpoint.to_string(),
+                        s!("confirmations") => confirmations.to_string(),
+                        s!("required") => required.to_string()
+                    },
+                    PolicyViolation::ConfirmationsUnknown(outpoint) => bmap! {
+                        s!("outpoint") => outpoint.to_string()
+                    },
+                    PolicyViolation::ChangeNotOwned { contract_id, seal } => {
+                        bmap! {
+                            s!("contract_id") => contract_id.to_string(),
+                            s!("seal") => seal.to_string()
+                        }
+                    }
+                }
+            }
+            #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+            ServiceErrorDomain::Swap(err) => {
+                use crate::fungibled::SwapError;
+                match err {
+                    SwapError::MissingAsset(contract_id)
+                    | SwapError::SameAsset(contract_id)
+                    | SwapError::WrongHistory(contract_id)
+                    | SwapError::InvalidLeg(contract_id)
+                    | SwapError::NotAnchored(contract_id) => bmap! {
+                        s!("contract_id") => contract_id.to_string()
+                    },
+                    SwapError::AssetUnderpaid {
+                        contract_id,
+                        endpoint,
+                        expected,
+                        received,
+                    } => bmap! {
+                        s!("contract_id") => contract_id.to_string(),
+                        s!("endpoint") => endpoint.to_string(),
+                        s!("expected") => expected.to_string(),
+                        s!("received") => received.to_string()
+                    },
+                    SwapError::BitcoinUnderpaid { expected, received } => {
+                        bmap! {
+                            s!("expected") => expected.to_string(),
+                            s!("received") => received.to_string()
+                        }
+                    }
+                    SwapError::OtherAssets {
+                        outpoint,
+                        contract_id,
+                    } => bmap! {
+                        s!("outpoint") => outpoint.to_string(),
+                        s!("contract_id") => contract_id.to_string()
+                    },
+                    SwapError::WitnessMismatch | SwapError::Anchor(_) => {
+                        BTreeMap::new()
+                    }
+                }
+            }
+            #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+            ServiceErrorDomain::Compose(err) => {
+                use crate::fungibled::ComposeError;
+                match err {
+                    ComposeError::InsufficientFunds {
+                        available,
+                        required,
+                    } => bmap! {
+                        s!("available") => available.to_string(),
+                        s!("required") => required.to_string()
+                    },
+                    ComposeError::UnknownInput(outpoint) => bmap! {
+                        s!("outpoint") => outpoint.to_string()
+                    },
+                    ComposeError::NoChange => BTreeMap::new(),
+                }
+            }
+            #[cfg(all(feature = "_rpc", feature = "fungibles"))]
+            ServiceErrorDomain::Funding(
+                crate::fungibled::FundingError::GapLimit(gap_limit),
+            ) => bmap! {
+                s!("gap_limit") => gap_limit.to_string()
+            },
+            _ => BTreeMap::new(),
+        }
+    }
+}
+
+impl ApiErrorType {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ApiErrorType::MalformedRequest { .. } => {
+                ErrorCode::MalformedRequest
+            }
+            ApiErrorType::UnknownCommand { .. } => ErrorCode::UnknownCommand,
+            ApiErrorType::UnimplementedCommand => {
+                ErrorCode::UnimplementedCommand
+            }
+            ApiErrorType::MissedArgument { .. } => ErrorCode::MissedArgument,
+            ApiErrorType::UnknownArgument { .. } => ErrorCode::UnknownArgument,
+            ApiErrorType::MalformedArgument { .. } => {
+                ErrorCode::MalformedArgument
+            }
+            ApiErrorType::UnexpectedReply => ErrorCode::UnexpectedReply,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
 #[display(Debug)]
 #[non_exhaustive]
@@ -202,3 +985,17 @@ pub struct ServiceErrorRepresentation {
     pub description: String,
     pub info: HashMap<String, String>,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        for code in ErrorCode::ALL.iter().copied() {
+            assert_eq!(ErrorCode::try_from(code as u16), Ok(code));
+            assert_eq!(code.category() as u16, code as u16 >> 8);
+        }
+        assert_eq!(ErrorCode::try_from(0x0600), Err(UnknownErrorCode(0x0600)));
+    }
+}
@@ -0,0 +1,253 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::thread;
+
+use clap::Clap;
+use serde_json::{json, Value};
+
+use super::params::{
+    event_json, transfer_json, AcceptParams, NodeParams, TransferParams,
+};
+use crate::fungibled;
+use crate::i9n::Embedded;
+use crate::rpc::fungible::IssueReq;
+use crate::stashd;
+use crate::util::{block_on, DataFormat};
+
+/// Handle to the node embedded into the application process
+pub struct RgbNode {
+    embedded: Embedded,
+}
+
+/// Callback receiving JSON-encoded node events. The event string is owned by
+/// the library and is valid only during the callback invocation.
+pub type RgbEventCallback =
+    extern "C" fn(event: *const c_char, context: *mut c_void);
+
+/// Opaque application context passed back to the event callback
+struct CallbackContext(*mut c_void);
+
+// The context is never dereferenced by the library; it is the responsibility
+// of the application to make it usable from the callback thread
+unsafe impl Send for CallbackContext {}
+
+fn into_c_string(value: Value) -> *mut c_char {
+    CString::new(value.to_string())
+        .expect("JSON string does not contain zero bytes")
+        .into_raw()
+}
+
+fn error_json(err: impl ToString) -> Value {
+    json!({ "error": err.to_string() })
+}
+
+unsafe fn read_json<T>(json: *const c_char) -> Result<T, String>
+where
+    T: for<'de> serde::Deserialize<'de>,
+{
+    if json.is_null() {
+        Err(s!("null pointer is passed instead of JSON parameters"))?
+    }
+    let json = CStr::from_ptr(json)
+        .to_str()
+        .map_err(|err| err.to_string())?;
+    serde_json::from_str(json).map_err(|err| err.to_string())
+}
+
+/// Runs the call, converting its result, error or panic into JSON string
+fn call(f: impl FnOnce() -> Result<Value, String>) -> *mut c_char {
+    // Panics must not unwind into the foreign code
+    into_c_string(match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(err)) => error_json(err),
+        Err(_) => error_json("RGB node has panicked"),
+    })
+}
+
+/// Starts stash and fungible contract runtimes with the configuration given
+/// as a JSON object with `data_dir`, `network` and optional
+/// `electrum_server` and `verbose` fields. Returns null pointer on failure,
+/// putting error description into `error`, if it is not null.
+///
+/// # Safety
+///
+/// `config` must be a valid zero-terminated C string; `error` must be either
+/// null or a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn rgb_node_start(
+    config: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut RgbNode {
+    let result = read_json::<NodeParams>(config).and_then(|params| {
+        let stash_opts = stashd::Opts::try_parse_from(params.args("stashd"))
+            .map_err(|err| err.to_string())?;
+        let fungible_opts =
+            fungibled::Opts::try_parse_from(params.args("fungibled"))
+                .map_err(|err| err.to_string())?;
+        Embedded::start(stash_opts.into(), fungible_opts.into())
+            .map_err(|err| err.to_string())
+    });
+    match result {
+        Ok(embedded) => Box::into_raw(Box::new(RgbNode { embedded })),
+        Err(err) => {
+            if !error.is_null() {
+                *error = into_c_string(error_json(err));
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Releases the node handle. Node runtimes keep running until the
+/// application process terminates.
+///
+/// # Safety
+///
+/// `node` must be either null or a pointer returned by [`rgb_node_start`],
+/// which was not released before.
+#[no_mangle]
+pub unsafe extern "C" fn rgb_node_free(node: *mut RgbNode) {
+    if !node.is_null() {
+        drop(Box::from_raw(node));
+    }
+}
+
+/// Releases string returned by any of the library functions
+///
+/// # Safety
+///
+/// `string` must be either null or a pointer returned by the library, which
+/// was not released before.
+#[no_mangle]
+pub unsafe extern "C" fn rgb_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Issues new asset; parameters match the ones of `rgb-cli fungible issue`.
+/// Returns JSON-encoded asset.
+///
+/// # Safety
+///
+/// `node` must be a valid pointer returned by [`rgb_node_start`] and not yet
+/// released; string parameters must be valid zero-terminated C strings.
+#[no_mangle]
+pub unsafe extern "C" fn rgb_node_issue(
+    node: *const RgbNode,
+    params: *const c_char,
+) -> *mut c_char {
+    let embedded = &(*node).embedded;
+    let params = read_json::<IssueReq>(params);
+    call(|| {
+        let asset =
+            block_on(embedded.issue(params?)).map_err(|err| err.to_string())?;
+        serde_json::to_value(asset).map_err(|err| err.to_string())
+    })
+}
+
+/// Creates asset transfer. Returns bech32-encoded consignment and disclosure
+/// together with base64-encoded witness transaction.
+///
+/// # Safety
+///
+/// `node` must be a valid pointer returned by [`rgb_node_start`] and not yet
+/// released; string parameters must be valid zero-terminated C strings.
+#[no_mangle]
+pub unsafe extern "C" fn rgb_node_transfer(
+    node: *const RgbNode,
+    params: *const c_char,
+) -> *mut c_char {
+    let embedded = &(*node).embedded;
+    let params = read_json::<TransferParams>(params);
+    call(|| {
+        let transfer = block_on(embedded.transfer(params?.into_request()?))
+            .map_err(|err| err.to_string())?;
+        Ok(transfer_json(&transfer))
+    })
+}
+
+/// Accepts incoming consignment
+///
+/// # Safety
+///
+/// `node` must be a valid pointer returned by [`rgb_node_start`] and not yet
+/// released; string parameters must be valid zero-terminated C strings.
+#[no_mangle]
+pub unsafe extern "C" fn rgb_node_accept(
+    node: *const RgbNode,
+    params: *const c_char,
+) -> *mut c_char {
+    let embedded = &(*node).embedded;
+    let params = read_json::<AcceptParams>(params);
+    call(|| {
+        block_on(embedded.accept(params?.into()))
+            .map_err(|err| err.to_string())?;
+        Ok(json!({}))
+    })
+}
+
+/// Lists all known assets
+///
+/// # Safety
+///
+/// `node` must be a valid pointer returned by [`rgb_node_start`] and not yet
+/// released; string parameters must be valid zero-terminated C strings.
+#[no_mangle]
+pub unsafe extern "C" fn rgb_node_list_assets(
+    node: *const RgbNode,
+) -> *mut c_char {
+    let embedded = &(*node).embedded;
+    call(|| {
+        let sync = block_on(embedded.list_assets(DataFormat::Json))
+            .map_err(|err| err.to_string())?;
+        serde_json::from_slice(&sync.data).map_err(|err| err.to_string())
+    })
+}
+
+/// Registers callback receiving all node events, which is called from a
+/// separate thread
+///
+/// # Safety
+///
+/// `node` must be a valid pointer returned by [`rgb_node_start`] and not yet
+/// released; string parameters must be valid zero-terminated C strings.
+#[no_mangle]
+pub unsafe extern "C" fn rgb_node_set_event_callback(
+    node: *const RgbNode,
+    callback: RgbEventCallback,
+    context: *mut c_void,
+) {
+    let embedded = (*node).embedded.clone();
+    let context = CallbackContext(context);
+    thread::spawn(move || {
+        let context = context;
+        loop {
+            let event = match embedded.next_event() {
+                Ok(event) => event,
+                Err(err) => {
+                    error!("Event subscription is terminated: {}", err);
+                    break;
+                }
+            };
+            let json = CString::new(event_json(&event).to_string())
+                .expect("JSON string does not contain zero bytes");
+            callback(json.as_ptr(), context.0);
+        }
+    });
+}
@@ -0,0 +1,25 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! C-compatible bindings to the node embedded into the application process,
+//! intended for mobile wallets integrating RGB via Kotlin/Swift. All
+//! parameters and results are passed as JSON-encoded C strings; strings
+//! returned by the library must be released with [`rgb_string_free`].
+//!
+//! Each call returns either the JSON-encoded result or an object with a
+//! single `error` field describing the failure.
+
+mod api;
+mod params;
+
+pub use api::*;
@@ -0,0 +1,268 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::OutPoint;
+use lnpbp::seals::OutpointReveal;
+use rgb::{seal, AtomicValue, Consignment, ContractId, SealEndpoint};
+use rgb20::SealCoins;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use serde_with::{As, DisplayFromStr};
+
+use crate::ln::{Event, EventRecord};
+use crate::rpc::fungible::{AcceptReq, TransferReq, VoutCoins};
+use crate::rpc::reply;
+use crate::util::{AnchorContainer, AtomicValueString};
+
+/// Parameters of the embedded node
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize)]
+#[serde(crate = "serde_crate")]
+pub(super) struct NodeParams {
+    pub data_dir: String,
+    pub network: String,
+    pub electrum_server: Option<String>,
+    #[serde(default)]
+    pub verbose: u8,
+}
+
+impl NodeParams {
+    /// Command-line arguments for the daemon with the given name, which are
+    /// parsed into the daemon configuration in the same way as it is done by
+    /// `rgbd` in threaded mode
+    pub fn args(&self, daemon: &str) -> Vec<String> {
+        let mut args = vec![
+            daemon.to_string(),
+            s!("--data-dir"),
+            self.data_dir.clone(),
+            s!("--network"),
+            self.network.clone(),
+        ];
+        if let Some(ref electrum_server) = self.electrum_server {
+            args.extend(vec![s!("--electrum"), electrum_server.clone()]);
+        }
+        if self.verbose > 0 {
+            args.push(format!("-{}", "v".repeat(self.verbose as usize)));
+        }
+        args
+    }
+}
+
+/// Transfer parameters, matching the ones of `rgb-cli fungible transfer`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(crate = "serde_crate")]
+pub(super) struct TransferParams {
+    #[serde(with = "As::<DisplayFromStr>")]
+    pub contract_id: ContractId,
+
+    /// Asset inputs in form of `<txid>:<vout>`
+    pub inputs: Vec<OutPoint>,
+
+    /// Blinded UTXO of the receiver
+    #[serde(with = "As::<DisplayFromStr>")]
+    pub receiver: seal::Confidential,
+
+    /// Amount in atomic units, preferably given as a string
+    #[serde(with = "As::<AtomicValueString>")]
+    pub amount: AtomicValue,
+
+    /// Payments to the witness transaction outputs in form of
+    /// `<amount>@<vout>`
+    #[serde(default, with = "As::<Vec<DisplayFromStr>>")]
+    pub theirs_witness: Vec<VoutCoins>,
+
+    /// Change allocations in form of `<amount>@[<txid>:]<vout>`
+    #[serde(default, with = "As::<Vec<DisplayFromStr>>")]
+    pub change: Vec<SealCoins>,
+
+    /// Base64-encoded partially signed witness transaction
+    pub witness: String,
+
+    #[serde(default)]
+    pub confirmed_only: bool,
+
+    /// Anchor commitment container, `tweak` or `op-return`; defaults to
+    /// the node configuration
+    #[serde(default, with = "As::<Option<DisplayFromStr>>")]
+    pub container: Option<AnchorContainer>,
+}
+
+impl TransferParams {
+    pub fn into_request(self) -> Result<TransferReq, String> {
+        let witness = base64::decode(&self.witness)
+            .map_err(|err| err.to_string())
+            .and_then(|data| {
+                deserialize::<PartiallySignedTransaction>(&data)
+                    .map_err(|err| err.to_string())
+            })?;
+        Ok(TransferReq {
+            contract_id: self.contract_id,
+            witness,
+            inputs: self.inputs.into_iter().collect(),
+            payment: bmap! {
+                SealEndpoint::TxOutpoint(self.receiver) => self.amount
+            },
+            theirs_witness: self
+                .theirs_witness
+                .into_iter()
+                .map(|vout_coins| (vout_coins.vout, vout_coins.coins))
+                .collect(),
+            change: self
+                .change
+                .into_iter()
+                .map(|seal_coins| {
+                    (seal_coins.seal_definition(), seal_coins.coins)
+                })
+                .collect::<BTreeMap<_, _>>(),
+            confirmed_only: self.confirmed_only,
+            container: self.container,
+        })
+    }
+}
+
+/// Parameters for accepting incoming consignment
+#[derive(Clone, Debug, Deserialize)]
+#[serde(crate = "serde_crate")]
+pub(super) struct AcceptParams {
+    /// Bech32-encoded consignment
+    #[serde(with = "As::<DisplayFromStr>")]
+    pub consignment: Consignment,
+
+    pub reveal_outpoints: Vec<OutpointReveal>,
+
+    #[serde(default)]
+    pub min_confirmations: Option<u32>,
+}
+
+impl From<AcceptParams> for AcceptReq {
+    fn from(params: AcceptParams) -> Self {
+        AcceptReq {
+            consignment: params.consignment,
+            reveal_outpoints: params.reveal_outpoints,
+            min_confirmations: params.min_confirmations,
+        }
+    }
+}
+
+pub(super) fn transfer_json(transfer: &reply::Transfer) -> Value {
+    json!({
+        "consignment": transfer.consignment.to_string(),
+        "disclosure": transfer.disclosure.to_string(),
+        "witness": base64::encode(serialize(&transfer.witness)),
+    })
+}
+
+pub(super) fn event_json(record: &EventRecord) -> Value {
+    let mut json = event_body_json(&record.event);
+    json["seq"] = json!(record.seq);
+    json
+}
+
+fn event_body_json(event: &Event) -> Value {
+    let (name, channel) = match event {
+        Event::ConsignmentAccepted(consignment_id) => {
+            return json!({
+                "event": "consignment_accepted",
+                "consignment_id": consignment_id.to_string(),
+            })
+        }
+        Event::ConsignmentPending(pending) => {
+            return json!({
+                "event": "consignment_pending",
+                "consignment_id": pending.consignment_id.to_string(),
+                "contract_id": pending.contract_id.to_string(),
+                "required": pending.required,
+            })
+        }
+        Event::ConsignmentRejected(consignment_id) => {
+            return json!({
+                "event": "consignment_rejected",
+                "consignment_id": consignment_id.to_string(),
+            })
+        }
+        Event::JobCompleted(status) => {
+            return json!({
+                "event": "job_completed",
+                "id": status.id,
+                "request": status.request,
+                "result": status.result.as_ref().map(|reply| reply.to_string()),
+            })
+        }
+        Event::BulkImportProgress(report) => {
+            return json!({
+                "event": "bulk_import_progress",
+                "records": report.records,
+                "imported": report.imported,
+                "duplicates": report.duplicates,
+            })
+        }
+        Event::ReceiveFulfilled(receive) | Event::ReceiveExpired(receive) => {
+            let name = match event {
+                Event::ReceiveFulfilled(_) => "receive_fulfilled",
+                _ => "receive_expired",
+            };
+            return json!({
+                "event": name,
+                "contract_id": receive.invoice.contract_id.to_string(),
+                "endpoint": receive.invoice.endpoint.to_string(),
+                "outpoint": receive.outpoint.to_string(),
+                "amount": receive.invoice.amount.to_string(),
+            });
+        }
+        Event::TransferUpdated(status) => {
+            return json!({
+                "event": "transfer_updated",
+                "txid": status.txid.to_string(),
+                "contract_id": status.contract_id.to_string(),
+                "amount": status.amount.to_string(),
+                "state": status.state.to_string(),
+            })
+        }
+        Event::AllocationSpent(outpoint) => {
+            return json!({
+                "event": "allocation_spent",
+                "outpoint": outpoint.to_string(),
+            })
+        }
+        Event::BackupCompleted(backup) => {
+            return json!({
+                "event": "backup_completed",
+                "name": backup.name,
+                "cache_files": backup.cache_files,
+                "removed": backup.removed,
+            })
+        }
+        Event::BackupFailed(reason) => {
+            return json!({
+                "event": "backup_failed",
+                "reason": reason,
+            })
+        }
+        Event::AssetChannelOpened(channel) => ("asset_channel_opened", channel),
+        Event::AssetChannelUpdated(channel) => {
+            ("asset_channel_updated", channel)
+        }
+        Event::AssetChannelClosed(channel) => ("asset_channel_closed", channel),
+    };
+    json!({
+        "event": name,
+        "contract_id": channel.contract_id().to_string(),
+        "funding": channel.funding().to_string(),
+        "capacity": channel.capacity(),
+        "local": channel.local(),
+        "remote": channel.remote(),
+    })
+}
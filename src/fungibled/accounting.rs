@@ -0,0 +1,166 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Ledger of the asset allocation changes for accounting and tax reporting.
+//! Each known allocation is recorded as incoming by the transaction creating
+//! its outpoint and, once the outpoint is spent, as outgoing by the spending
+//! transaction. Only the changes mined at or below the ledger height are
+//! included, so repeated exports for the same height produce the same ledger
+//! unless the chain is reorganized.
+
+use bitcoin::{OutPoint, Txid};
+use rgb::{AtomicValue, ContractId};
+
+use crate::rpc::fungible::LedgerFormat;
+
+/// Direction of the allocation change
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+pub enum Direction {
+    #[display("incoming")]
+    Incoming,
+
+    #[display("outgoing")]
+    Outgoing,
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct LedgerEntry {
+    /// Height of the block mining the transaction
+    pub height: u32,
+
+    /// Timestamp of the block mining the transaction
+    pub timestamp: u32,
+
+    /// Transaction creating the allocation (for incoming entries) or
+    /// spending it (for outgoing entries)
+    pub txid: Txid,
+
+    pub asset: ContractId,
+    pub outpoint: OutPoint,
+    pub direction: Direction,
+    pub amount: AtomicValue,
+}
+
+/// Allocation changes ordered by their block height
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Ledger(Vec<LedgerEntry>);
+
+impl Ledger {
+    pub fn with(mut entries: Vec<LedgerEntry>) -> Self {
+        entries.sort();
+        Ledger(entries)
+    }
+
+    /// Serializes the ledger in the given format
+    pub fn export(&self, format: LedgerFormat) -> Vec<u8> {
+        match format {
+            LedgerFormat::Csv => self.to_csv(),
+            LedgerFormat::Json => self.to_json(),
+        }
+        .into_bytes()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv =
+            s!("asset,outpoint,amount,direction,txid,height,timestamp\n");
+        for entry in &self.0 {
+            csv += &format!(
+                "{},{},{},{},{},{},{}\n",
+                entry.asset,
+                entry.outpoint,
+                entry.amount,
+                entry.direction,
+                entry.txid,
+                entry.height,
+                entry.timestamp
+            );
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        let entries = self
+            .0
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{{\"asset\":\"{}\",\"outpoint\":\"{}\",\"amount\":{},\
+                     \"direction\":\"{}\",\"txid\":\"{}\",\"height\":{},\
+                     \"timestamp\":{}}}",
+                    entry.asset,
+                    entry.outpoint,
+                    entry.amount,
+                    entry.direction,
+                    entry.txid,
+                    entry.height,
+                    entry.timestamp
+                )
+            })
+            .collect::<Vec<_>>();
+        format!("[{}]", entries.join(","))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use amplify::Wrapper;
+    use bitcoin::hashes::Hash;
+
+    #[test]
+    fn test_ledger_export() {
+        let asset = ContractId::from_inner(Hash::hash(b"asset"));
+        let receive = Txid::hash(b"receive");
+        let spend = Txid::hash(b"spend");
+        let outpoint = OutPoint::new(receive, 1);
+        let ledger = Ledger::with(vec![
+            LedgerEntry {
+                height: 120,
+                timestamp: 1620000600,
+                txid: spend,
+                asset,
+                outpoint,
+                direction: Direction::Outgoing,
+                amount: 500,
+            },
+            LedgerEntry {
+                height: 100,
+                timestamp: 1620000000,
+                txid: receive,
+                asset,
+                outpoint,
+                direction: Direction::Incoming,
+                amount: 500,
+            },
+        ]);
+
+        let csv = ledger.to_csv();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            format!(
+                "{},{},500,incoming,{},100,1620000000",
+                asset, outpoint, receive
+            )
+        );
+        assert!(lines[2].contains(",outgoing,"));
+
+        let json =
+            String::from_utf8(ledger.export(LedgerFormat::Json)).unwrap();
+        assert!(json.starts_with("[{\"asset\""));
+        assert!(
+            json.contains(&format!("\"txid\":\"{}\",\"height\":120", spend))
+        );
+    }
+}
@@ -0,0 +1,116 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+
+use bitcoin::hashes::sha256;
+
+use crate::rpc::stash::AcceptRequest;
+
+/// Consignment accepted once its witness transactions get the required
+/// number of confirmations
+#[derive(Clone, Debug, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct PendingAccept {
+    pub accept: AcceptRequest,
+    pub required: u32,
+}
+
+/// Consignments waiting for the confirmations of their witness
+/// transactions, indexed by the consignment id
+#[derive(Clone, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Accepts {
+    pending: BTreeMap<sha256::Hash, PendingAccept>,
+}
+
+impl Accepts {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues consignment, replacing previously queued consignment with the
+    /// same id, if any; returns whether the consignment was not queued yet
+    #[inline]
+    pub fn add(
+        &mut self,
+        consignment_id: sha256::Hash,
+        accept: AcceptRequest,
+        required: u32,
+    ) -> bool {
+        self.pending
+            .insert(consignment_id, PendingAccept { accept, required })
+            .is_none()
+    }
+
+    #[inline]
+    pub fn remove(
+        &mut self,
+        consignment_id: &sha256::Hash,
+    ) -> Option<PendingAccept> {
+        self.pending.remove(consignment_id)
+    }
+
+    #[inline]
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&sha256::Hash, &PendingAccept)> {
+        self.pending.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::OutPoint;
+    use lnpbp::strict_encoding::{strict_deserialize, strict_serialize};
+    use lnpbp::Chain;
+    use rgb::Consignment;
+
+    #[test]
+    fn test_pending_accepts() {
+        let (_, genesis) = rgb20::issue(
+            Chain::Regtest(Default::default()),
+            s!("TEST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(OutPoint::default(), 100)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let accept = AcceptRequest {
+            consignment: Consignment::with(genesis, vec![], vec![], vec![])
+                .into(),
+            reveal_outpoints: vec![],
+        };
+        let consignment_id = sha256::Hash::hash(b"consignment");
+        let mut accepts = Accepts::new();
+        assert!(accepts.add(consignment_id, accept.clone(), 3));
+        assert!(!accepts.add(consignment_id, accept, 6));
+
+        let accepts: Accepts =
+            strict_deserialize(&strict_serialize(&accepts).unwrap()).unwrap();
+        let (id, pending) = accepts.iter().next().unwrap();
+        assert_eq!(*id, consignment_id);
+        assert_eq!(pending.required, 6);
+
+        let mut accepts = accepts;
+        assert!(accepts.remove(&consignment_id).is_some());
+        assert!(accepts.iter().next().is_none());
+    }
+}
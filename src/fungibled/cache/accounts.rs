@@ -0,0 +1,68 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::OutPoint;
+use rgb::ContractId;
+
+/// Assets and outpoints registered under the accounts (tenants) of the node.
+/// An asset may be registered under several accounts, while each outpoint
+/// belongs to a single account.
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Accounts {
+    assets: BTreeMap<PublicKey, BTreeSet<ContractId>>,
+    outpoints: BTreeMap<OutPoint, PublicKey>,
+}
+
+impl Accounts {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn register_asset(&mut self, account: PublicKey, id: ContractId) {
+        self.assets.entry(account).or_default().insert(id);
+    }
+
+    /// Registers outpoint under the account, unless it already belongs to
+    /// another account; returns whether the outpoint belongs to the account
+    pub fn register_outpoint(
+        &mut self,
+        account: PublicKey,
+        outpoint: OutPoint,
+    ) -> bool {
+        *self.outpoints.entry(outpoint).or_insert(account) == account
+    }
+
+    /// Assets registered under the account
+    pub fn assets(&self, account: PublicKey) -> BTreeSet<ContractId> {
+        self.assets.get(&account).cloned().unwrap_or_default()
+    }
+
+    #[inline]
+    pub fn has_asset(&self, account: PublicKey, id: ContractId) -> bool {
+        self.assets
+            .get(&account)
+            .map(|assets| assets.contains(&id))
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    pub fn owner(&self, outpoint: OutPoint) -> Option<PublicKey> {
+        self.outpoints.get(&outpoint).copied()
+    }
+}
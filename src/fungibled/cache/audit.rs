@@ -0,0 +1,120 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use bitcoin::hashes::sha256;
+
+use crate::rpc::reply::{AuditOutcome, AuditRecord};
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum AuditError {
+    /// audit record #{0} does not follow the preceding record: the audit log
+    /// was tampered with
+    BrokenChain(u64),
+}
+
+/// Append-only log of the state-changing requests, hash-chained so that the
+/// records can't be altered or removed without detection
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct AuditLog {
+    records: Vec<AuditRecord>,
+}
+
+impl AuditLog {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hash which must be committed to by the next record
+    #[inline]
+    pub fn last_hash(&self) -> sha256::Hash {
+        self.records
+            .last()
+            .map(AuditRecord::hash)
+            .unwrap_or_else(sha256::Hash::default)
+    }
+
+    /// Constructs record chained to the last record of the log; the record
+    /// has to be appended with [`AuditLog::append`]
+    pub fn next_record(
+        &self,
+        timestamp: i64,
+        caller: String,
+        request: String,
+        params_digest: sha256::Hash,
+        outcome: AuditOutcome,
+    ) -> AuditRecord {
+        AuditRecord {
+            seq: self.records.len() as u64,
+            timestamp,
+            caller,
+            request,
+            params_digest,
+            outcome,
+            prev_hash: self.last_hash(),
+        }
+    }
+
+    /// Appends record, checking that it follows the last record of the log
+    pub fn append(&mut self, record: AuditRecord) -> Result<(), AuditError> {
+        if record.seq != self.records.len() as u64
+            || record.prev_hash != self.last_hash()
+        {
+            Err(AuditError::BrokenChain(record.seq))?
+        }
+        self.records.push(record);
+        Ok(())
+    }
+
+    /// Records made within `from..to` unix timestamp range
+    pub fn range(&self, from: i64, to: i64) -> Vec<AuditRecord> {
+        self.records
+            .iter()
+            .filter(|record| record.timestamp >= from && record.timestamp < to)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::Hash;
+
+    #[test]
+    fn test_audit_chain() {
+        let mut log = AuditLog::new();
+        for no in 0..3 {
+            let record = log.next_record(
+                1620000000 + no,
+                s!("rpc"),
+                format!("forget({})", no),
+                sha256::Hash::hash(&[no as u8]),
+                AuditOutcome::Success,
+            );
+            log.append(record).unwrap();
+        }
+        assert_eq!(log.range(1620000001, 1620000003).len(), 2);
+
+        let mut forged = log.next_record(
+            1620000003,
+            s!("rpc"),
+            s!("forget(3)"),
+            sha256::Hash::default(),
+            AuditOutcome::Success,
+        );
+        forged.prev_hash = log.range(0, i64::MAX)[1].hash();
+        assert_eq!(log.append(forged), Err(AuditError::BrokenChain(3)));
+    }
+}
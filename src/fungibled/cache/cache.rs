@@ -11,19 +11,33 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1::PublicKey;
+use lnpbp::seals::{OutpointHash, OutpointReveal};
 use rgb::prelude::*;
 use rgb20::Asset;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use super::FileCacheError;
+use super::{
+    Accepts, Accounts, AuditError, AuditLog, Consignments, EventLog,
+    FileCacheError, Holds, JournalEntry, Labels, MetaStore,
+    OutpointReservation, Payees, PendingTransfers, Receives, Reservation,
+    Tombstone, Transfers,
+};
 use crate::error::{BootstrapError, ServiceErrorDomain};
+use crate::rpc::fungible::{HoldTarget, LabelTarget};
+use crate::rpc::reply::{
+    self, AuditRecord, PendingReceive, TransferState, TransferStatus,
+};
+use crate::rpc::stash::AcceptRequest;
 use crate::util::file::FileMode;
 
 pub trait Cache {
     type Error: ::std::error::Error + Into<ServiceErrorDomain>;
 
-    fn assets(&self) -> Result<Vec<&Asset>, Self::Error>;
-    fn asset(&self, id: ContractId) -> Result<&Asset, Self::Error>;
+    fn assets(&self) -> Result<Vec<Asset>, Self::Error>;
+    /// Returns asset, loading it into memory if it was evicted before
+    fn asset(&mut self, id: ContractId) -> Result<&Asset, Self::Error>;
     fn has_asset(&self, id: ContractId) -> Result<bool, Self::Error>;
     fn add_asset(&mut self, asset: Asset) -> Result<bool, Self::Error>;
     fn remove_asset(&mut self, id: ContractId) -> Result<bool, Self::Error>;
@@ -35,10 +49,258 @@ pub trait Cache {
     ) -> Result<BTreeMap<bitcoin::OutPoint, Vec<AtomicValue>>, Self::Error>;
 
     /// Returns the map of Asset-Allocation_amount for a given Outpoint
+    /// Outpoints holding known allocations of any of the assets
+    fn allocation_outpoints(
+        &self,
+    ) -> Result<BTreeSet<bitcoin::OutPoint>, Self::Error>;
+
     fn outpoint_assets(
         &self,
         outpoint: bitcoin::OutPoint,
     ) -> Result<BTreeMap<ContractId, Vec<AtomicValue>>, CacheError>;
+
+    /// Marks transfer inputs as reserved by a pending spend
+    fn reserve_inputs(
+        &mut self,
+        reservation: Reservation,
+    ) -> Result<(), Self::Error>;
+
+    /// Releases inputs reserved by the transfer with the given witness txid
+    fn release_inputs(
+        &mut self,
+        txid: bitcoin::Txid,
+    ) -> Result<Option<Reservation>, Self::Error>;
+
+    /// Returns reservation holding the outpoint, if any
+    fn input_reservation(
+        &self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Option<&Reservation>, Self::Error>;
+
+    /// Lists all pending spends
+    fn reservations(&self) -> Result<Vec<&Reservation>, Self::Error>;
+
+    /// Reserves outpoint for a protocol run outside of the node
+    fn reserve_outpoint(
+        &mut self,
+        reservation: OutpointReservation,
+    ) -> Result<(), Self::Error>;
+
+    /// Removes reservation of the outpoint made for an external protocol
+    fn release_outpoint(
+        &mut self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Option<OutpointReservation>, Self::Error>;
+
+    /// Returns reservation of the outpoint for an external protocol, if any
+    fn outpoint_reservation(
+        &self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Option<&OutpointReservation>, Self::Error>;
+
+    /// Releases outpoints which reservations have expired at the given unix
+    /// timestamp
+    fn release_expired_outpoints(
+        &mut self,
+        now: i64,
+    ) -> Result<usize, Self::Error>;
+
+    /// Keeps forgotten allocation recoverable
+    fn bury(&mut self, tombstone: Tombstone) -> Result<(), Self::Error>;
+
+    /// Removes and returns tombstones of the allocations on the outpoint
+    fn exhume(
+        &mut self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Vec<Tombstone>, Self::Error>;
+
+    /// Removes tombstones buried before the given unix timestamp
+    fn purge_tombstones(&mut self, before: i64) -> Result<usize, Self::Error>;
+
+    /// Keeps reveal data for the outpoint blinded by the node, so it can be
+    /// used to accept consignments sent to the blinded outpoint
+    fn add_reveal(&mut self, reveal: OutpointReveal)
+        -> Result<(), Self::Error>;
+
+    /// Returns reveal data for the outpoint blinded by the node, if any
+    fn reveal(
+        &self,
+        seal: OutpointHash,
+    ) -> Result<Option<OutpointReveal>, Self::Error>;
+
+    /// Returns reveal data of all outpoints blinded by the node
+    fn reveals(&self) -> Result<Vec<OutpointReveal>, Self::Error>;
+
+    /// Records result of the request made with idempotency key
+    fn add_journal_entry(
+        &mut self,
+        entry: JournalEntry,
+    ) -> Result<(), Self::Error>;
+
+    /// Returns journal entry for the request made with the given idempotency
+    /// key, if the request was completed before
+    fn journal_entry(
+        &self,
+        key: &str,
+    ) -> Result<Option<&JournalEntry>, Self::Error>;
+
+    /// Persists serialized published event, keeping no more than `capacity`
+    /// latest events; returns sequence number assigned to the event
+    fn log_event(
+        &mut self,
+        event: Vec<u8>,
+        capacity: usize,
+    ) -> Result<u64, Self::Error>;
+
+    fn event_log(&self) -> Result<&EventLog, Self::Error>;
+
+    /// Persists record of the audit log; the record must be chained to the
+    /// last record of the log
+    fn append_audit_record(
+        &mut self,
+        record: AuditRecord,
+    ) -> Result<(), Self::Error>;
+
+    fn audit_log(&self) -> Result<&AuditLog, Self::Error>;
+
+    /// Sets or clears administrative hold; returns whether the hold was
+    /// changed
+    fn set_hold(
+        &mut self,
+        target: HoldTarget,
+        held: bool,
+    ) -> Result<bool, Self::Error>;
+
+    fn holds(&self) -> Result<&Holds, Self::Error>;
+
+    /// Sets or removes (if no value is given) asset metadata entry; returns
+    /// whether the metadata were changed
+    fn set_asset_meta(
+        &mut self,
+        contract_id: ContractId,
+        key: String,
+        value: Option<String>,
+    ) -> Result<bool, Self::Error>;
+
+    fn asset_meta(&self) -> Result<&MetaStore, Self::Error>;
+
+    /// Adds or removes label of the outpoint or asset allocation; returns
+    /// whether the labels were changed
+    fn set_label(
+        &mut self,
+        target: LabelTarget,
+        label: String,
+        set: bool,
+    ) -> Result<bool, Self::Error>;
+
+    fn labels(&self) -> Result<&Labels, Self::Error>;
+
+    fn add_pending_receive(
+        &mut self,
+        receive: PendingReceive,
+    ) -> Result<(), Self::Error>;
+
+    /// Removes pending receive to the blinded outpoint, returning it if it
+    /// was known
+    fn remove_pending_receive(
+        &mut self,
+        endpoint: &OutpointHash,
+    ) -> Result<Option<PendingReceive>, Self::Error>;
+
+    fn pending_receives(&self) -> Result<&Receives, Self::Error>;
+
+    /// Starts tracking of the outgoing transfer, recording its payee
+    /// endpoints for re-consignment
+    fn register_transfer(
+        &mut self,
+        status: TransferStatus,
+        payees: BTreeSet<SealEndpoint>,
+    ) -> Result<(), Self::Error>;
+
+    /// Moves outgoing transfer to the given state at the given unix
+    /// timestamp; returns whether the state was changed
+    fn update_transfer(
+        &mut self,
+        txid: bitcoin::Txid,
+        state: TransferState,
+        reason: Option<String>,
+        now: i64,
+    ) -> Result<bool, Self::Error>;
+
+    fn transfers(&self) -> Result<&Transfers, Self::Error>;
+
+    fn payees(&self) -> Result<&Payees, Self::Error>;
+
+    /// Registers assets and outpoints under the account. Outpoints already
+    /// belonging to other accounts are skipped; returns whether all of the
+    /// outpoints belong to the account.
+    fn register_account_items(
+        &mut self,
+        account: PublicKey,
+        assets: &[ContractId],
+        outpoints: &[bitcoin::OutPoint],
+    ) -> Result<bool, Self::Error>;
+
+    fn accounts(&self) -> Result<&Accounts, Self::Error>;
+
+    /// Keeps copy of the consignment accepted at the given unix timestamp
+    fn keep_consignment(
+        &mut self,
+        consignment_id: sha256::Hash,
+        consignment: Consignment,
+        now: i64,
+    ) -> Result<(), Self::Error>;
+
+    /// Removes consignments accepted before the given unix timestamp
+    fn purge_consignments(&mut self, before: i64)
+        -> Result<usize, Self::Error>;
+
+    fn accepted_consignments(&self) -> Result<&Consignments, Self::Error>;
+
+    /// Queues consignment for the acceptance once its witness transactions
+    /// get `required` confirmations; returns whether the consignment was not
+    /// queued yet
+    fn add_pending_accept(
+        &mut self,
+        consignment_id: sha256::Hash,
+        accept: AcceptRequest,
+        required: u32,
+    ) -> Result<bool, Self::Error>;
+
+    fn remove_pending_accept(
+        &mut self,
+        consignment_id: &sha256::Hash,
+    ) -> Result<Option<AcceptRequest>, Self::Error>;
+
+    fn pending_accepts(&self) -> Result<&Accepts, Self::Error>;
+
+    /// Keeps transfer prepared with the two-phase transfer procedure until
+    /// it is committed or cancelled
+    fn add_prepared_transfer(
+        &mut self,
+        txid: bitcoin::Txid,
+        transfer: reply::Transfer,
+    ) -> Result<(), Self::Error>;
+
+    fn remove_prepared_transfer(
+        &mut self,
+        txid: &bitcoin::Txid,
+    ) -> Result<Option<reply::Transfer>, Self::Error>;
+
+    /// Keeps RPC-encoded reply of the transfer or swap until it is approved
+    /// by an operator
+    fn hold_reply(
+        &mut self,
+        txid: bitcoin::Txid,
+        reply: Vec<u8>,
+    ) -> Result<(), Self::Error>;
+
+    fn release_reply(
+        &mut self,
+        txid: &bitcoin::Txid,
+    ) -> Result<Option<Vec<u8>>, Self::Error>;
+
+    fn pending_transfers(&self) -> Result<&PendingTransfers, Self::Error>;
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
@@ -70,6 +332,12 @@ impl From<CacheError> for BootstrapError {
     }
 }
 
+impl From<AuditError> for CacheError {
+    fn from(err: AuditError) -> Self {
+        Self::DataIntegrityError(err.to_string())
+    }
+}
+
 impl From<FileCacheError> for CacheError {
     fn from(err: FileCacheError) -> Self {
         match err {
@@ -95,6 +363,22 @@ impl From<FileCacheError> for CacheError {
             FileCacheError::SerdeToml => Self::DataIntegrityError(format!(
                 "TOML serialization/deserialization error"
             )),
+            #[cfg(feature = "serde_cbor")]
+            FileCacheError::SerdeCbor(e) => {
+                Self::DataIntegrityError(format!("{:?}", e))
+            }
+            #[cfg(feature = "rmp-serde")]
+            FileCacheError::SerdeMsgPack => Self::DataIntegrityError(format!(
+                "MessagePack serialization/deserialization error"
+            )),
+            #[cfg(feature = "prost")]
+            FileCacheError::Protobuf(e) => {
+                Self::DataIntegrityError(format!("{:?}", e))
+            }
+            FileCacheError::Migration(e) => {
+                Self::DataIntegrityError(e.to_string())
+            }
+            FileCacheError::Audit(e) => e.into(),
             FileCacheError::NotFound => {
                 Self::DataIntegrityError("Data file is not found".to_string())
             }
@@ -0,0 +1,110 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+
+use bitcoin::hashes::sha256;
+use rgb::Consignment;
+
+/// Consignment accepted by the node, kept for re-export as a proof of the
+/// payment
+#[derive(Clone, PartialEq, Eq, Debug, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct AcceptedConsignment {
+    pub consignment: Consignment,
+    /// Unix timestamp of the acceptance
+    pub accepted: i64,
+}
+
+/// Accepted consignments kept within the retention window, indexed by their
+/// consignment id
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Consignments {
+    accepted: BTreeMap<sha256::Hash, AcceptedConsignment>,
+}
+
+impl Consignments {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps consignment accepted at `now`; repeated acceptance of the same
+    /// consignment does not extend its retention
+    pub fn add(
+        &mut self,
+        consignment_id: sha256::Hash,
+        consignment: Consignment,
+        now: i64,
+    ) {
+        self.accepted
+            .entry(consignment_id)
+            .or_insert(AcceptedConsignment {
+                consignment,
+                accepted: now,
+            });
+    }
+
+    #[inline]
+    pub fn get(
+        &self,
+        consignment_id: &sha256::Hash,
+    ) -> Option<&AcceptedConsignment> {
+        self.accepted.get(consignment_id)
+    }
+
+    /// Removes consignments accepted before the given unix timestamp,
+    /// returning number of the removed consignments
+    pub fn purge(&mut self, before: i64) -> usize {
+        let count = self.accepted.len();
+        self.accepted.retain(|_, kept| kept.accepted >= before);
+        count - self.accepted.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::OutPoint;
+    use lnpbp::Chain;
+
+    #[test]
+    fn test_consignment_retention() {
+        let (_, genesis) = rgb20::issue(
+            Chain::Regtest(Default::default()),
+            s!("TEST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(OutPoint::default(), 100)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let consignment = Consignment::with(genesis, vec![], vec![], vec![]);
+        let (first, second) =
+            (sha256::Hash::hash(b"first"), sha256::Hash::hash(b"second"));
+        let mut consignments = Consignments::new();
+        consignments.add(first, consignment.clone(), 10);
+        consignments.add(second, consignment.clone(), 20);
+        consignments.add(first, consignment.clone(), 30);
+        assert_eq!(consignments.get(&first).unwrap().accepted, 10);
+        assert_eq!(consignments.purge(15), 1);
+        assert_eq!(consignments.get(&first), None);
+        assert_eq!(consignments.get(&second).unwrap().consignment, consignment);
+        assert_eq!(consignments.accepted.len(), 1);
+    }
+}
@@ -0,0 +1,85 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+
+/// Ring buffer of the serialized events published by the daemon, indexed by
+/// their sequence numbers. Sequence numbers are never reused, so clients may
+/// detect events which were evicted from the buffer.
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct EventLog {
+    next_seq: u64,
+    events: BTreeMap<u64, Vec<u8>>,
+}
+
+impl EventLog {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends serialized event, evicting the oldest events so that no more
+    /// than `capacity` events are kept. Returns sequence number assigned to
+    /// the event.
+    pub fn append(&mut self, event: Vec<u8>, capacity: usize) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.events.insert(seq, event);
+        while self.events.len() > capacity {
+            let oldest = *self.events.keys().next().expect("non-empty log");
+            self.events.remove(&oldest);
+        }
+        seq
+    }
+
+    /// Sequence number which will be assigned to the next event
+    #[inline]
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Sequence number of the oldest event kept in the log
+    #[inline]
+    pub fn first_seq(&self) -> u64 {
+        self.events.keys().next().copied().unwrap_or(self.next_seq)
+    }
+
+    /// Events starting from the given sequence number, in the order of
+    /// their publication
+    #[inline]
+    pub fn since(&self, from_seq: u64) -> BTreeMap<u64, Vec<u8>> {
+        self.events
+            .range(from_seq..)
+            .map(|(seq, event)| (*seq, event.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_event_log_eviction() {
+        let mut log = EventLog::new();
+        for no in 0..5u8 {
+            assert_eq!(log.append(vec![no], 3), no as u64);
+        }
+        assert_eq!(log.first_seq(), 2);
+        assert_eq!(log.next_seq(), 5);
+        assert_eq!(log.since(0), log.since(2));
+        assert_eq!(log.since(4), bmap! { 4u64 => vec![4u8] });
+        assert!(log.since(5).is_empty());
+    }
+}
@@ -13,24 +13,91 @@
 
 #[cfg(feature = "serde")]
 use serde_json;
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet};
 #[cfg(any(
     feature = "serde_yaml",
     feature = "serde_json",
     feature = "toml"
 ))]
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::io::Read;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 
+use amplify::Wrapper;
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1::PublicKey;
+use lnpbp::client_side_validation::CommitConceal;
+use lnpbp::seals::{OutpointHash, OutpointReveal};
 use lnpbp::strict_encoding::{strict_serialize, StrictDecode, StrictEncode};
-use microservices::FileFormat;
+#[cfg(feature = "prost")]
+use prost::Message;
 use rgb::prelude::*;
 use rgb20::Asset;
 
-use super::Cache;
+use super::{
+    Accepts, Accounts, AuditError, AuditLog, Cache, Consignments, EventLog,
+    Graveyard, Holds, Journal, JournalEntry, Labels, MetaStore,
+    OutpointReservation, Payees, PendingTransfers, Receives, Reservation,
+    SpendLedger, Tombstone, Transfers,
+};
 use crate::fungibled::cache::CacheError;
+use crate::rpc::fungible::{HoldTarget, LabelTarget};
+#[cfg(feature = "prost")]
+use crate::rpc::proto;
+use crate::rpc::reply::{
+    self, AuditRecord, CacheStats, PendingReceive, TransferState,
+    TransferStatus,
+};
+use crate::rpc::stash::AcceptRequest;
 use crate::util::file::*;
+use crate::util::migration::{self, Migration, MigrationError};
+use crate::util::DataFormat;
+
+/// Version of the asset cache data layout
+pub const FORMAT_VERSION: u16 = 2;
+
+/// Migrations upgrading older asset cache layouts to the current one
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "marking cache created before format versioning",
+        migrate: migration::unversioned,
+    },
+    Migration {
+        from: 1,
+        description: "adding outpoint reservations to pending spends ledger",
+        migrate: add_outpoint_reservations,
+    },
+];
+
+/// Pending spends ledger got outpoint reservations as its last field, so
+/// the ledger of the previous version is complemented with an empty list of
+/// them
+fn add_outpoint_reservations(assets_dir: &Path) -> Result<(), MigrationError> {
+    let filename = assets_dir
+        .join("pending")
+        .with_extension(DataFormat::StrictEncode.extension());
+    if !filename.exists() {
+        return Ok(());
+    }
+    let reservations =
+        BTreeMap::<bitcoin::OutPoint, OutpointReservation>::new();
+    let data = strict_serialize(&reservations).map_err(|err| {
+        MigrationError::Failed {
+            from: 1,
+            details: err.to_string(),
+        }
+    })?;
+    fs::OpenOptions::new()
+        .append(true)
+        .open(filename)?
+        .write_all(&data)?;
+    Ok(())
+}
 
 #[derive(Debug, Display, Error, From)]
 #[display(Debug)]
@@ -61,14 +128,39 @@ pub enum FileCacheError {
     #[from(toml::ser::Error)]
     SerdeToml,
 
+    #[cfg(feature = "serde")]
+    #[from]
+    SerdeCbor(serde_cbor::Error),
+
+    #[cfg(feature = "serde")]
+    #[from(rmp_serde::encode::Error)]
+    #[from(rmp_serde::decode::Error)]
+    SerdeMsgPack,
+
+    #[cfg(feature = "prost")]
+    #[from]
+    Protobuf(prost::EncodeError),
+
+    #[from]
+    Migration(MigrationError),
+
+    #[from]
+    Audit(AuditError),
+
     NotFound,
+
+    UnsupportedFormat(DataFormat),
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
 #[display(Debug)]
 pub struct FileCacheConfig {
     pub data_dir: PathBuf,
-    pub data_format: FileFormat,
+    pub data_format: DataFormat,
+    /// Amount of memory (in bytes of encoded asset data) which may be taken
+    /// by the assets kept in memory; least recently used assets over the
+    /// budget are moved to disk. `None` keeps all assets in memory.
+    pub memory_budget: Option<usize>,
 }
 
 impl FileCacheConfig {
@@ -83,13 +175,185 @@ impl FileCacheConfig {
             .join("assets")
             .with_extension(self.data_format.extension())
     }
+
+    /// Assets evicted from memory are kept one per file in strict encoding,
+    /// so that each of them can be reloaded without reading the rest
+    #[inline]
+    pub fn cold_dir(&self) -> PathBuf {
+        self.assets_dir().join("cold")
+    }
+
+    #[inline]
+    pub fn cold_filename(&self, id: ContractId) -> PathBuf {
+        self.cold_dir()
+            .join(id.to_hex())
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    /// Pending spends ledger is always kept in strict encoding, since it
+    /// contains no user-facing data
+    #[inline]
+    pub fn ledger_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("pending")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    /// Forgotten allocations are kept in strict encoding for the same reason
+    /// as the pending spends ledger
+    #[inline]
+    pub fn graveyard_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("forgotten")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    /// Outpoint reveal data contain blinding secrets and must never be
+    /// exported, so they are kept in strict encoding as well
+    #[inline]
+    pub fn reveals_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("blinded")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    /// Published events are kept in their wire encoding, as the operation
+    /// journal replies are
+    #[inline]
+    pub fn events_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("events")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    /// Operation journal keeps serialized RPC replies, which can't be
+    /// represented in other formats
+    #[inline]
+    pub fn journal_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("journal")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    #[inline]
+    pub fn holds_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("holds")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    #[inline]
+    pub fn meta_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("meta")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    #[inline]
+    pub fn labels_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("labels")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    #[inline]
+    pub fn receives_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("receives")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    #[inline]
+    pub fn transfers_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("transfers")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    #[inline]
+    pub fn payees_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("payees")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    #[inline]
+    pub fn accounts_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("accounts")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    #[inline]
+    pub fn consignments_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("consignments")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    #[inline]
+    pub fn pending_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("pending")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    #[inline]
+    pub fn accepts_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("accepts")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
+
+    /// Audit log is append-only, so the records are strict-encoded one
+    /// after another and the file is never rewritten
+    #[inline]
+    pub fn audit_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("audit")
+            .with_extension(DataFormat::StrictEncode.extension())
+    }
 }
 
+/// Amounts of the known allocations of each asset on an outpoint
+type AllocationIndex =
+    BTreeMap<bitcoin::OutPoint, BTreeMap<ContractId, Vec<AtomicValue>>>;
+
 /// Keeps all source/binary RGB contract data, stash etc
 #[derive(Debug)]
 pub struct FileCache {
     config: FileCacheConfig,
+    /// Assets kept in memory
     assets: BTreeMap<ContractId, Asset>,
+    /// Assets evicted to disk
+    cold: BTreeSet<ContractId>,
+    /// Last access tick and encoded size of each of the in-memory assets;
+    /// sizes are measured only when the memory budget is set
+    usage: BTreeMap<ContractId, (u64, usize)>,
+    /// Known allocations of all assets, including the evicted ones, indexed
+    /// by their outpoint
+    allocations: AllocationIndex,
+    clock: u64,
+    memory_used: usize,
+    hits: Cell<u64>,
+    misses: Cell<u64>,
+    evictions: u64,
+    ledger: SpendLedger,
+    graveyard: Graveyard,
+    reveals: BTreeMap<OutpointHash, OutpointReveal>,
+    journal: Journal,
+    events: EventLog,
+    audit: AuditLog,
+    holds: Holds,
+    meta: MetaStore,
+    labels: Labels,
+    receives: Receives,
+    transfers: Transfers,
+    payees: Payees,
+    accounts: Accounts,
+    consignments: Consignments,
+    accepts: Accepts,
+    pending: PendingTransfers,
 }
 
 impl FileCache {
@@ -110,12 +374,37 @@ impl FileCache {
                 "RGB fungible assets information directory '{:?}' is not found; creating one",
                 assets_dir
             );
-            fs::create_dir_all(assets_dir)?;
+            fs::create_dir_all(&assets_dir)?;
         }
+        migration::upgrade(&assets_dir, FORMAT_VERSION, MIGRATIONS)?;
 
         let mut me = Self {
             config,
             assets: bmap![],
+            cold: bset![],
+            usage: bmap![],
+            allocations: bmap![],
+            clock: 0,
+            memory_used: 0,
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+            evictions: 0,
+            ledger: SpendLedger::new(),
+            graveyard: Graveyard::new(),
+            reveals: bmap![],
+            journal: Journal::new(),
+            events: EventLog::new(),
+            audit: AuditLog::new(),
+            holds: Holds::new(),
+            meta: MetaStore::new(),
+            labels: Labels::new(),
+            receives: Receives::new(),
+            transfers: Transfers::new(),
+            payees: Payees::new(),
+            accounts: Accounts::new(),
+            consignments: Consignments::new(),
+            accepts: Accepts::new(),
+            pending: PendingTransfers::new(),
         };
         let filename = me.config.assets_filename();
         if filename.exists() {
@@ -124,31 +413,355 @@ impl FileCache {
             debug!("Initializing assets file {:?} ...", filename.to_str());
             me.save()?;
         }
+        me.load_cold()?;
+        let filename = me.config.ledger_filename();
+        if filename.exists() {
+            debug!("Reading pending spends ledger ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.ledger = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.graveyard_filename();
+        if filename.exists() {
+            debug!("Reading forgotten allocations ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.graveyard = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.reveals_filename();
+        if filename.exists() {
+            debug!("Reading blinded outpoints ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.reveals = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.journal_filename();
+        if filename.exists() {
+            debug!("Reading operation journal ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.journal = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.events_filename();
+        if filename.exists() {
+            debug!("Reading event log ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.events = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.holds_filename();
+        if filename.exists() {
+            debug!("Reading administrative holds ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.holds = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.meta_filename();
+        if filename.exists() {
+            debug!("Reading asset metadata ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.meta = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.labels_filename();
+        if filename.exists() {
+            debug!("Reading outpoint and allocation labels ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.labels = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.receives_filename();
+        if filename.exists() {
+            debug!("Reading pending receives ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.receives = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.transfers_filename();
+        if filename.exists() {
+            debug!("Reading outgoing transfers ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.transfers = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.payees_filename();
+        if filename.exists() {
+            debug!("Reading outgoing transfer payees ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.payees = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.accounts_filename();
+        if filename.exists() {
+            debug!("Reading account registrations ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.accounts = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.consignments_filename();
+        if filename.exists() {
+            debug!("Reading accepted consignments ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.consignments = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.accepts_filename();
+        if filename.exists() {
+            debug!("Reading consignments pending confirmations ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.accepts = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.pending_filename();
+        if filename.exists() {
+            debug!("Reading prepared and held transfers ...");
+            let mut f = file(filename, FileMode::Read)?;
+            me.pending = StrictDecode::strict_decode(&mut f)?;
+        }
+        let filename = me.config.audit_filename();
+        if filename.exists() {
+            debug!("Reading and verifying audit log ...");
+            let data = fs::read(filename)?;
+            let mut cursor = io::Cursor::new(&data);
+            while (cursor.position() as usize) < data.len() {
+                me.audit.append(AuditRecord::strict_decode(&mut cursor)?)?;
+            }
+        }
 
         Ok(me)
     }
 
+    /// Checks that the assets file is still present on disk and writable
+    pub fn is_available(&self) -> bool {
+        fs::metadata(self.config.assets_filename())
+            .map(|meta| !meta.permissions().readonly())
+            .unwrap_or_default()
+    }
+
     fn load(&mut self) -> Result<(), FileCacheError> {
         debug!("Reading assets information ...");
         let filename = self.config.assets_filename();
         let mut f = file(filename, FileMode::Read)?;
         self.assets = match self.config.data_format {
             #[cfg(feature = "serde_yaml")]
-            FileFormat::Yaml => serde_yaml::from_reader(&f)?,
+            DataFormat::Yaml => serde_yaml::from_reader(&f)?,
             #[cfg(feature = "serde_json")]
-            FileFormat::Json => serde_json::from_reader(&f)?,
+            DataFormat::Json => serde_json::from_reader(&f)?,
             #[cfg(feature = "toml")]
-            FileFormat::Toml => {
+            DataFormat::Toml => {
                 let mut data = String::new();
                 f.read_to_string(&mut data)?;
                 toml::from_str(&data)?
             }
-            FileFormat::StrictEncode => StrictDecode::strict_decode(&mut f)?,
-            _ => unimplemented!(),
+            #[cfg(feature = "serde_cbor")]
+            DataFormat::Cbor => serde_cbor::from_reader(&f)?,
+            #[cfg(feature = "rmp-serde")]
+            DataFormat::MsgPack => rmp_serde::from_read(&f)?,
+            DataFormat::StrictEncode => StrictDecode::strict_decode(&mut f)?,
+            #[allow(unreachable_patterns)]
+            format => Err(FileCacheError::UnsupportedFormat(format))?,
         };
+        self.usage.clear();
+        self.memory_used = 0;
+        self.allocations.clear();
+        for id in self.assets.keys().copied().collect::<Vec<_>>() {
+            self.account(id)?;
+            Self::index(&mut self.allocations, &self.assets[&id]);
+        }
         Ok(())
     }
 
+    fn load_cold(&mut self) -> Result<(), FileCacheError> {
+        let cold_dir = self.config.cold_dir();
+        if !cold_dir.exists() {
+            return Ok(());
+        }
+        debug!("Indexing assets evicted to disk ...");
+        for entry in fs::read_dir(cold_dir)? {
+            let path = entry?.path();
+            let id = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or(FileCacheError::BrokenHexFilenames)?;
+            let id = ContractId::from_inner(FromHex::from_hex(id)?);
+            if self.assets.contains_key(&id) {
+                // Asset was reloaded into memory, but the node was stopped
+                // before the copy on disk got removed
+                trace!("Removing stale copy of asset {}", id);
+                fs::remove_file(path)?;
+            } else {
+                // Evicted assets are read once to index their allocations
+                let asset = self.read_cold(id)?;
+                Self::index(&mut self.allocations, &asset);
+                self.cold.insert(id);
+            }
+        }
+        self.evict()
+    }
+
+    /// Adds known allocations of the asset to the outpoint index, replacing
+    /// the previously indexed ones
+    fn index(allocations: &mut AllocationIndex, asset: &Asset) {
+        let id = *asset.id();
+        Self::unindex(allocations, id);
+        for allocation in asset.known_allocations() {
+            allocations
+                .entry(*allocation.outpoint())
+                .or_default()
+                .entry(id)
+                .or_default()
+                .push(allocation.revealed_amount().value);
+        }
+    }
+
+    /// Removes allocations of the asset from the outpoint index
+    fn unindex(allocations: &mut AllocationIndex, id: ContractId) {
+        allocations.retain(|_, assets| {
+            assets.remove(&id);
+            !assets.is_empty()
+        });
+    }
+
+    /// Marks in-memory asset as the most recently used one
+    fn touch(&mut self, id: ContractId) {
+        self.clock += 1;
+        if let Some((tick, _)) = self.usage.get_mut(&id) {
+            *tick = self.clock;
+        }
+    }
+
+    /// Encoded size of the in-memory asset; assets are not encoded to
+    /// measure them unless the memory budget is set
+    fn measure(&self, id: ContractId) -> Result<usize, FileCacheError> {
+        Ok(match (self.assets.get(&id), self.config.memory_budget) {
+            (Some(asset), Some(_)) => strict_serialize(asset)?.len(),
+            _ => 0,
+        })
+    }
+
+    /// Updates memory accounting for the in-memory asset which was loaded or
+    /// changed, marking it as the most recently used one
+    fn account(&mut self, id: ContractId) -> Result<(), FileCacheError> {
+        if !self.assets.contains_key(&id) {
+            return Ok(());
+        }
+        let size = self.measure(id)?;
+        self.clock += 1;
+        if let Some((_, prev)) = self.usage.insert(id, (self.clock, size)) {
+            self.memory_used -= prev;
+        }
+        self.memory_used += size;
+        Ok(())
+    }
+
+    /// Moves least recently used assets to disk until the memory taken by
+    /// the rest of them fits into the budget. The most recently used asset
+    /// is always kept in memory.
+    fn evict(&mut self) -> Result<(), FileCacheError> {
+        let budget = match self.config.memory_budget {
+            Some(budget) => budget,
+            None => return Ok(()),
+        };
+        let mut evicted = false;
+        while self.memory_used > budget && self.assets.len() > 1 {
+            let id = match self
+                .usage
+                .iter()
+                .min_by_key(|(_, (tick, _))| *tick)
+                .map(|(id, _)| *id)
+            {
+                Some(id) => id,
+                None => break,
+            };
+            trace!("Evicting asset {} to disk", id);
+            let cold_dir = self.config.cold_dir();
+            if !cold_dir.exists() {
+                fs::create_dir_all(cold_dir)?;
+            }
+            // Copy on disk must exist before the asset is dropped from the
+            // assets file
+            let filename = self.config.cold_filename(id);
+            let _ = fs::remove_file(&filename);
+            let mut f = file(filename, FileMode::Create)?;
+            self.assets[&id].strict_encode(&mut f)?;
+            self.assets.remove(&id);
+            if let Some((_, size)) = self.usage.remove(&id) {
+                self.memory_used -= size;
+            }
+            self.cold.insert(id);
+            self.evictions += 1;
+            evicted = true;
+        }
+        if evicted {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn read_cold(&self, id: ContractId) -> Result<Asset, FileCacheError> {
+        let mut f = file(self.config.cold_filename(id), FileMode::Read)?;
+        Ok(StrictDecode::strict_decode(&mut f)?)
+    }
+
+    /// Moves asset evicted to disk back into memory
+    fn promote(&mut self, id: ContractId) -> Result<(), FileCacheError> {
+        trace!("Reloading asset {} from disk", id);
+        let asset = self.read_cold(id)?;
+        self.assets.insert(id, asset);
+        self.cold.remove(&id);
+        self.account(id)?;
+        self.evict()?;
+        // Assets file must include the asset before its copy on disk is
+        // removed
+        self.save()?;
+        fs::remove_file(self.config.cold_filename(id))?;
+        Ok(())
+    }
+
+    /// Returns asset without moving it into memory, if it was evicted
+    fn load_asset(
+        &self,
+        id: ContractId,
+    ) -> Result<Cow<'_, Asset>, FileCacheError> {
+        if let Some(asset) = self.assets.get(&id) {
+            self.hits.set(self.hits.get() + 1);
+            Ok(Cow::Borrowed(asset))
+        } else if self.cold.contains(&id) {
+            self.misses.set(self.misses.get() + 1);
+            Ok(Cow::Owned(self.read_cold(id)?))
+        } else {
+            Err(FileCacheError::NotFound)
+        }
+    }
+
+    fn all_assets(&self) -> Result<Vec<Asset>, FileCacheError> {
+        let mut assets = self.assets.clone();
+        for id in &self.cold {
+            assets.insert(*id, self.read_cold(*id)?);
+        }
+        Ok(assets.into_iter().map(|(_, asset)| asset).collect())
+    }
+
+    /// Changes memory budget, evicting assets over the new budget
+    pub fn set_memory_budget(
+        &mut self,
+        budget: Option<usize>,
+    ) -> Result<(), FileCacheError> {
+        let measured = self.config.memory_budget.is_some();
+        self.config.memory_budget = budget;
+        if budget.is_some() != measured {
+            // Sizes were not measured without the budget, and are not
+            // needed any more once it is removed
+            for id in self.assets.keys().copied().collect::<Vec<_>>() {
+                let size = self.measure(id)?;
+                if let Some((_, prev)) = self.usage.get_mut(&id) {
+                    self.memory_used = self.memory_used - *prev + size;
+                    *prev = size;
+                }
+            }
+        }
+        self.evict()
+    }
+
+    /// Memory usage and efficiency of keeping assets in memory
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hot: self.assets.len() as u32,
+            cold: self.cold.len() as u32,
+            memory_used: self.memory_used as u64,
+            memory_budget: self.config.memory_budget.map(|b| b as u64),
+            hits: self.hits.get(),
+            misses: self.misses.get(),
+            evictions: self.evictions,
+        }
+    }
+
     pub fn save(&self) -> Result<(), FileCacheError> {
         trace!("Saving assets information ...");
         let filename = self.config.assets_filename();
@@ -156,34 +769,197 @@ impl FileCache {
         let mut f = file(filename, FileMode::Create)?;
         match self.config.data_format {
             #[cfg(feature = "serde_yaml")]
-            FileFormat::Yaml => serde_yaml::to_writer(&f, &self.assets)?,
+            DataFormat::Yaml => serde_yaml::to_writer(&f, &self.assets)?,
             #[cfg(feature = "serde_json")]
-            FileFormat::Json => serde_json::to_writer(&f, &self.assets)?,
+            DataFormat::Json => serde_json::to_writer(&f, &self.assets)?,
             #[cfg(feature = "toml")]
-            FileFormat::Toml => f.write_all(&toml::to_vec(&self.assets)?)?,
-            FileFormat::StrictEncode => {
+            DataFormat::Toml => f.write_all(&toml::to_vec(&self.assets)?)?,
+            #[cfg(feature = "serde_cbor")]
+            DataFormat::Cbor => serde_cbor::to_writer(&f, &self.assets)?,
+            #[cfg(feature = "rmp-serde")]
+            DataFormat::MsgPack => {
+                rmp_serde::encode::write_named(&mut f, &self.assets)?
+            }
+            DataFormat::StrictEncode => {
                 self.assets.strict_encode(&mut f)?;
             }
-            _ => unimplemented!(),
+            #[allow(unreachable_patterns)]
+            format => Err(FileCacheError::UnsupportedFormat(format))?,
         }
         Ok(())
     }
 
+    fn save_ledger(&self) -> Result<(), FileCacheError> {
+        trace!("Saving pending spends ledger ...");
+        let filename = self.config.ledger_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.ledger.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_graveyard(&self) -> Result<(), FileCacheError> {
+        trace!("Saving forgotten allocations ...");
+        let filename = self.config.graveyard_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.graveyard.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_reveals(&self) -> Result<(), FileCacheError> {
+        trace!("Saving blinded outpoints ...");
+        let filename = self.config.reveals_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.reveals.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_journal(&self) -> Result<(), FileCacheError> {
+        trace!("Saving operation journal ...");
+        let filename = self.config.journal_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.journal.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_events(&self) -> Result<(), FileCacheError> {
+        trace!("Saving event log ...");
+        let filename = self.config.events_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.events.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_holds(&self) -> Result<(), FileCacheError> {
+        trace!("Saving administrative holds ...");
+        let filename = self.config.holds_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.holds.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_meta(&self) -> Result<(), FileCacheError> {
+        trace!("Saving asset metadata ...");
+        let filename = self.config.meta_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.meta.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_labels(&self) -> Result<(), FileCacheError> {
+        trace!("Saving outpoint and allocation labels ...");
+        let filename = self.config.labels_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.labels.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_receives(&self) -> Result<(), FileCacheError> {
+        trace!("Saving pending receives ...");
+        let filename = self.config.receives_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.receives.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_transfers(&self) -> Result<(), FileCacheError> {
+        trace!("Saving outgoing transfers ...");
+        let filename = self.config.transfers_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.transfers.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_payees(&self) -> Result<(), FileCacheError> {
+        trace!("Saving outgoing transfer payees ...");
+        let filename = self.config.payees_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.payees.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_accounts(&self) -> Result<(), FileCacheError> {
+        trace!("Saving account registrations ...");
+        let filename = self.config.accounts_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.accounts.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_consignments(&self) -> Result<(), FileCacheError> {
+        trace!("Saving accepted consignments ...");
+        let filename = self.config.consignments_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.consignments.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_accepts(&self) -> Result<(), FileCacheError> {
+        trace!("Saving consignments pending confirmations ...");
+        let filename = self.config.accepts_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.accepts.strict_encode(&mut f)?;
+        Ok(())
+    }
+
+    fn save_pending(&self) -> Result<(), FileCacheError> {
+        trace!("Saving prepared and held transfers ...");
+        let filename = self.config.pending_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        self.pending.strict_encode(&mut f)?;
+        Ok(())
+    }
+
     pub fn export(
         &self,
-        data_format: Option<FileFormat>,
+        data_format: Option<DataFormat>,
     ) -> Result<Vec<u8>, FileCacheError> {
         trace!("Exporting assets information ...");
-        let assets = self.assets.values().cloned().collect::<Vec<Asset>>();
+        self.export_assets(self.all_assets()?, data_format)
+    }
+
+    /// Serializes the given assets in the same way as [`FileCache::export`]
+    /// does for all known assets
+    pub fn export_assets(
+        &self,
+        assets: Vec<Asset>,
+        data_format: Option<DataFormat>,
+    ) -> Result<Vec<u8>, FileCacheError> {
         Ok(match data_format.unwrap_or(self.config.data_format) {
             #[cfg(feature = "serde_yaml")]
-            FileFormat::Yaml => serde_yaml::to_vec(&assets)?,
+            DataFormat::Yaml => serde_yaml::to_vec(&assets)?,
             #[cfg(feature = "serde_json")]
-            FileFormat::Json => serde_json::to_vec(&assets)?,
+            DataFormat::Json => serde_json::to_vec(&assets)?,
             #[cfg(feature = "toml")]
-            FileFormat::Toml => toml::to_vec(&assets)?,
-            FileFormat::StrictEncode => strict_serialize(&assets)?,
-            _ => unimplemented!(),
+            DataFormat::Toml => toml::to_vec(&assets)?,
+            #[cfg(feature = "serde_cbor")]
+            DataFormat::Cbor => serde_cbor::to_vec(&assets)?,
+            #[cfg(feature = "rmp-serde")]
+            DataFormat::MsgPack => rmp_serde::to_vec_named(&assets)?,
+            #[cfg(feature = "prost")]
+            DataFormat::Protobuf => {
+                let list: proto::AssetList = assets.iter().collect();
+                let mut buf = Vec::with_capacity(list.encoded_len());
+                list.encode(&mut buf)?;
+                buf
+            }
+            DataFormat::StrictEncode => strict_serialize(&assets)?,
+            #[allow(unreachable_patterns)]
+            format => Err(FileCacheError::UnsupportedFormat(format))?,
         })
     }
 }
@@ -191,12 +967,18 @@ impl FileCache {
 impl Cache for FileCache {
     type Error = CacheError;
 
-    fn assets(&self) -> Result<Vec<&Asset>, CacheError> {
-        Ok(self.assets.values().collect())
+    fn assets(&self) -> Result<Vec<Asset>, CacheError> {
+        Ok(self.all_assets()?)
     }
 
-    #[inline]
-    fn asset(&self, id: ContractId) -> Result<&Asset, CacheError> {
+    fn asset(&mut self, id: ContractId) -> Result<&Asset, CacheError> {
+        if self.assets.contains_key(&id) {
+            self.hits.set(self.hits.get() + 1);
+            self.touch(id);
+        } else if self.cold.contains(&id) {
+            self.misses.set(self.misses.get() + 1);
+            self.promote(id)?;
+        }
         Ok(self.assets.get(&id).ok_or(CacheError::DataIntegrityError(
             "Asset is not known".to_string(),
         ))?)
@@ -204,19 +986,36 @@ impl Cache for FileCache {
 
     #[inline]
     fn has_asset(&self, id: ContractId) -> Result<bool, CacheError> {
-        Ok(self.assets.contains_key(&id))
+        Ok(self.assets.contains_key(&id) || self.cold.contains(&id))
     }
 
     fn add_asset(&mut self, asset: Asset) -> Result<bool, CacheError> {
-        let exists = self.assets.insert(*asset.id(), asset).is_some();
+        let id = *asset.id();
+        let was_cold = self.cold.remove(&id);
+        Self::index(&mut self.allocations, &asset);
+        let exists = self.assets.insert(id, asset).is_some() || was_cold;
+        self.account(id)?;
+        self.evict()?;
         self.save()?;
+        if was_cold {
+            fs::remove_file(self.config.cold_filename(id))
+                .map_err(FileCacheError::from)?;
+        }
         Ok(exists)
     }
 
-    #[inline]
     fn remove_asset(&mut self, id: ContractId) -> Result<bool, CacheError> {
-        let existed = self.assets.remove(&id).is_some();
+        let was_cold = self.cold.remove(&id);
+        let existed = self.assets.remove(&id).is_some() || was_cold;
+        if let Some((_, size)) = self.usage.remove(&id) {
+            self.memory_used -= size;
+        }
+        Self::unindex(&mut self.allocations, id);
         self.save()?;
+        if was_cold {
+            fs::remove_file(self.config.cold_filename(id))
+                .map_err(FileCacheError::from)?;
+        }
         Ok(existed)
     }
 
@@ -226,7 +1025,7 @@ impl Cache for FileCache {
     ) -> Result<BTreeMap<bitcoin::OutPoint, Vec<AtomicValue>>, CacheError> {
         // Process known_allocation map to produce the intended map
         let mut result = BTreeMap::<bitcoin::OutPoint, Vec<AtomicValue>>::new();
-        for allocation in self.asset(contract_id)?.known_allocations() {
+        for allocation in self.load_asset(contract_id)?.known_allocations() {
             result
                 .entry(*allocation.outpoint())
                 .or_insert(default!())
@@ -239,32 +1038,483 @@ impl Cache for FileCache {
         &self,
         outpoint: bitcoin::OutPoint,
     ) -> Result<BTreeMap<ContractId, Vec<AtomicValue>>, CacheError> {
-        let mut result = BTreeMap::new();
-
-        for asset in self.assets()? {
-            result.insert(
-                *asset.id(),
-                asset
-                    .allocations(outpoint)
-                    .into_iter()
-                    .map(|a| a.revealed_amount().value)
-                    .collect(),
-            );
+        // Assets without allocations on the outpoint are not indexed, so
+        // they are not listed, in the same way as by the SQL cache
+        Ok(self.allocations.get(&outpoint).cloned().unwrap_or_default())
+    }
+
+    #[inline]
+    fn allocation_outpoints(
+        &self,
+    ) -> Result<BTreeSet<bitcoin::OutPoint>, CacheError> {
+        Ok(self.allocations.keys().copied().collect())
+    }
+
+    fn reserve_inputs(
+        &mut self,
+        reservation: Reservation,
+    ) -> Result<(), CacheError> {
+        self.ledger.reserve(reservation);
+        self.save_ledger()?;
+        Ok(())
+    }
+
+    fn release_inputs(
+        &mut self,
+        txid: bitcoin::Txid,
+    ) -> Result<Option<Reservation>, CacheError> {
+        let reservation = self.ledger.release(txid);
+        if reservation.is_some() {
+            self.save_ledger()?;
         }
+        Ok(reservation)
+    }
 
-        Ok(result)
+    #[inline]
+    fn input_reservation(
+        &self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Option<&Reservation>, CacheError> {
+        Ok(self.ledger.reservation(outpoint))
+    }
+
+    #[inline]
+    fn reservations(&self) -> Result<Vec<&Reservation>, CacheError> {
+        Ok(self.ledger.reservations())
+    }
+
+    fn reserve_outpoint(
+        &mut self,
+        reservation: OutpointReservation,
+    ) -> Result<(), CacheError> {
+        self.ledger.reserve_outpoint(reservation);
+        self.save_ledger()?;
+        Ok(())
+    }
+
+    fn release_outpoint(
+        &mut self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Option<OutpointReservation>, CacheError> {
+        let reservation = self.ledger.release_outpoint(outpoint);
+        if reservation.is_some() {
+            self.save_ledger()?;
+        }
+        Ok(reservation)
+    }
+
+    #[inline]
+    fn outpoint_reservation(
+        &self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Option<&OutpointReservation>, CacheError> {
+        Ok(self.ledger.outpoint_reservation(outpoint))
+    }
+
+    fn release_expired_outpoints(
+        &mut self,
+        now: i64,
+    ) -> Result<usize, CacheError> {
+        let count = self.ledger.release_expired_outpoints(now);
+        if count > 0 {
+            self.save_ledger()?;
+        }
+        Ok(count)
+    }
+
+    fn bury(&mut self, tombstone: Tombstone) -> Result<(), CacheError> {
+        self.graveyard.bury(tombstone);
+        self.save_graveyard()?;
+        Ok(())
+    }
+
+    fn exhume(
+        &mut self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Vec<Tombstone>, CacheError> {
+        let tombstones = self.graveyard.exhume(outpoint);
+        if !tombstones.is_empty() {
+            self.save_graveyard()?;
+        }
+        Ok(tombstones)
+    }
+
+    fn purge_tombstones(&mut self, before: i64) -> Result<usize, CacheError> {
+        let count = self.graveyard.purge(before);
+        if count > 0 {
+            self.save_graveyard()?;
+        }
+        Ok(count)
+    }
+
+    fn add_reveal(&mut self, reveal: OutpointReveal) -> Result<(), CacheError> {
+        self.reveals.insert(reveal.commit_conceal(), reveal);
+        self.save_reveals()?;
+        Ok(())
+    }
+
+    #[inline]
+    fn reveal(
+        &self,
+        seal: OutpointHash,
+    ) -> Result<Option<OutpointReveal>, CacheError> {
+        Ok(self.reveals.get(&seal).copied())
+    }
+
+    #[inline]
+    fn reveals(&self) -> Result<Vec<OutpointReveal>, CacheError> {
+        Ok(self.reveals.values().copied().collect())
+    }
+
+    fn add_journal_entry(
+        &mut self,
+        entry: JournalEntry,
+    ) -> Result<(), CacheError> {
+        self.journal.record(entry);
+        self.save_journal()?;
+        Ok(())
+    }
+
+    #[inline]
+    fn journal_entry(
+        &self,
+        key: &str,
+    ) -> Result<Option<&JournalEntry>, CacheError> {
+        Ok(self.journal.entry(key))
+    }
+
+    fn log_event(
+        &mut self,
+        event: Vec<u8>,
+        capacity: usize,
+    ) -> Result<u64, CacheError> {
+        let seq = self.events.append(event, capacity);
+        self.save_events()?;
+        Ok(seq)
+    }
+
+    #[inline]
+    fn event_log(&self) -> Result<&EventLog, CacheError> {
+        Ok(&self.events)
+    }
+
+    fn append_audit_record(
+        &mut self,
+        record: AuditRecord,
+    ) -> Result<(), CacheError> {
+        self.audit.append(record.clone())?;
+        let mut f = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.config.audit_filename())
+            .map_err(FileCacheError::from)?;
+        record.strict_encode(&mut f).map_err(FileCacheError::from)?;
+        f.sync_data().map_err(FileCacheError::from)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn audit_log(&self) -> Result<&AuditLog, CacheError> {
+        Ok(&self.audit)
+    }
+
+    fn set_hold(
+        &mut self,
+        target: HoldTarget,
+        held: bool,
+    ) -> Result<bool, CacheError> {
+        let changed = self.holds.set(target, held);
+        if changed {
+            self.save_holds()?;
+        }
+        Ok(changed)
+    }
+
+    #[inline]
+    fn holds(&self) -> Result<&Holds, CacheError> {
+        Ok(&self.holds)
+    }
+
+    fn set_asset_meta(
+        &mut self,
+        contract_id: ContractId,
+        key: String,
+        value: Option<String>,
+    ) -> Result<bool, CacheError> {
+        let changed = self.meta.set(contract_id, key, value);
+        if changed {
+            self.save_meta()?;
+        }
+        Ok(changed)
+    }
+
+    #[inline]
+    fn asset_meta(&self) -> Result<&MetaStore, CacheError> {
+        Ok(&self.meta)
+    }
+
+    fn set_label(
+        &mut self,
+        target: LabelTarget,
+        label: String,
+        set: bool,
+    ) -> Result<bool, CacheError> {
+        let changed = self.labels.set(target, label, set);
+        if changed {
+            self.save_labels()?;
+        }
+        Ok(changed)
+    }
+
+    #[inline]
+    fn labels(&self) -> Result<&Labels, CacheError> {
+        Ok(&self.labels)
+    }
+
+    fn add_pending_receive(
+        &mut self,
+        receive: PendingReceive,
+    ) -> Result<(), CacheError> {
+        self.receives.add(receive);
+        Ok(self.save_receives()?)
+    }
+
+    fn remove_pending_receive(
+        &mut self,
+        endpoint: &OutpointHash,
+    ) -> Result<Option<PendingReceive>, CacheError> {
+        let receive = self.receives.remove(endpoint);
+        if receive.is_some() {
+            self.save_receives()?;
+        }
+        Ok(receive)
+    }
+
+    #[inline]
+    fn pending_receives(&self) -> Result<&Receives, CacheError> {
+        Ok(&self.receives)
+    }
+
+    fn register_transfer(
+        &mut self,
+        status: TransferStatus,
+        payees: BTreeSet<SealEndpoint>,
+    ) -> Result<(), CacheError> {
+        self.payees.add(status.txid, payees);
+        self.save_payees()?;
+        self.transfers.add(status);
+        Ok(self.save_transfers()?)
+    }
+
+    fn update_transfer(
+        &mut self,
+        txid: bitcoin::Txid,
+        state: TransferState,
+        reason: Option<String>,
+        now: i64,
+    ) -> Result<bool, CacheError> {
+        let changed = self.transfers.update(txid, state, reason, now);
+        if changed {
+            self.save_transfers()?;
+        }
+        Ok(changed)
+    }
+
+    #[inline]
+    fn transfers(&self) -> Result<&Transfers, CacheError> {
+        Ok(&self.transfers)
+    }
+
+    #[inline]
+    fn payees(&self) -> Result<&Payees, CacheError> {
+        Ok(&self.payees)
+    }
+
+    fn register_account_items(
+        &mut self,
+        account: PublicKey,
+        assets: &[ContractId],
+        outpoints: &[bitcoin::OutPoint],
+    ) -> Result<bool, CacheError> {
+        for id in assets {
+            self.accounts.register_asset(account, *id);
+        }
+        let mut owned = true;
+        for outpoint in outpoints {
+            owned &= self.accounts.register_outpoint(account, *outpoint);
+        }
+        self.save_accounts()?;
+        Ok(owned)
+    }
+
+    #[inline]
+    fn accounts(&self) -> Result<&Accounts, CacheError> {
+        Ok(&self.accounts)
+    }
+
+    fn keep_consignment(
+        &mut self,
+        consignment_id: sha256::Hash,
+        consignment: Consignment,
+        now: i64,
+    ) -> Result<(), CacheError> {
+        self.consignments.add(consignment_id, consignment, now);
+        Ok(self.save_consignments()?)
+    }
+
+    fn purge_consignments(&mut self, before: i64) -> Result<usize, CacheError> {
+        let count = self.consignments.purge(before);
+        if count > 0 {
+            self.save_consignments()?;
+        }
+        Ok(count)
+    }
+
+    #[inline]
+    fn accepted_consignments(&self) -> Result<&Consignments, CacheError> {
+        Ok(&self.consignments)
+    }
+
+    fn add_pending_accept(
+        &mut self,
+        consignment_id: sha256::Hash,
+        accept: AcceptRequest,
+        required: u32,
+    ) -> Result<bool, CacheError> {
+        let added = self.accepts.add(consignment_id, accept, required);
+        self.save_accepts()?;
+        Ok(added)
+    }
+
+    fn remove_pending_accept(
+        &mut self,
+        consignment_id: &sha256::Hash,
+    ) -> Result<Option<AcceptRequest>, CacheError> {
+        let pending = self.accepts.remove(consignment_id);
+        if pending.is_some() {
+            self.save_accepts()?;
+        }
+        Ok(pending.map(|pending| pending.accept))
+    }
+
+    #[inline]
+    fn pending_accepts(&self) -> Result<&Accepts, CacheError> {
+        Ok(&self.accepts)
+    }
+
+    fn add_prepared_transfer(
+        &mut self,
+        txid: bitcoin::Txid,
+        transfer: reply::Transfer,
+    ) -> Result<(), CacheError> {
+        self.pending.prepare(txid, transfer);
+        Ok(self.save_pending()?)
+    }
+
+    fn remove_prepared_transfer(
+        &mut self,
+        txid: &bitcoin::Txid,
+    ) -> Result<Option<reply::Transfer>, CacheError> {
+        let transfer = self.pending.remove_prepared(txid);
+        if transfer.is_some() {
+            self.save_pending()?;
+        }
+        Ok(transfer)
+    }
+
+    fn hold_reply(
+        &mut self,
+        txid: bitcoin::Txid,
+        reply: Vec<u8>,
+    ) -> Result<(), CacheError> {
+        self.pending.hold(txid, reply);
+        Ok(self.save_pending()?)
+    }
+
+    fn release_reply(
+        &mut self,
+        txid: &bitcoin::Txid,
+    ) -> Result<Option<Vec<u8>>, CacheError> {
+        let reply = self.pending.release(txid);
+        if reply.is_some() {
+            self.save_pending()?;
+        }
+        Ok(reply)
+    }
+
+    #[inline]
+    fn pending_transfers(&self) -> Result<&PendingTransfers, CacheError> {
+        Ok(&self.pending)
     }
 }
 
-#[cfg(all(test, feature = "sql"))]
+#[cfg(test)]
 mod test {
+    #[cfg(feature = "sql")]
     use super::super::sql::{SqlCache, SqlCacheConfig};
     use super::*;
-    use bitcoin::hashes::hex::FromHex;
+    #[cfg(feature = "sql")]
+    use amplify::Wrapper;
+    #[cfg(feature = "sql")]
+    use bitcoin::hashes::hex::{FromHex, ToHex};
+    use lnpbp::Chain;
+    #[cfg(feature = "sql")]
     use lnpbp::TaggedHash;
     use std::env;
 
+    fn issue(outpoint: bitcoin::OutPoint, amount: AtomicValue) -> Asset {
+        let (asset, _) = rgb20::issue(
+            Chain::Regtest(Default::default()),
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint, amount)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        asset
+    }
+
+    #[test]
+    fn test_outpoint_index() {
+        let data_dir = env::temp_dir()
+            .join(format!("rgb-node-outpoint-index-{}", std::process::id()));
+        let config = FileCacheConfig {
+            data_dir: data_dir.clone(),
+            data_format: DataFormat::StrictEncode,
+            memory_budget: Some(1),
+        };
+        let outpoint = bitcoin::OutPoint::new(Default::default(), 1);
+        let first = issue(outpoint, 100);
+        let second = issue(outpoint, 50);
+        let expected = bmap! {
+            *first.id() => vec![100],
+            *second.id() => vec![50]
+        };
+
+        let mut cache = FileCache::new(config.clone()).unwrap();
+        cache.add_asset(first).unwrap();
+        cache.add_asset(second).unwrap();
+        assert_eq!(cache.stats().cold, 1);
+        assert_eq!(cache.outpoint_assets(outpoint).unwrap(), expected);
+        drop(cache);
+
+        // The index covers the evicted asset after a restart as well
+        let cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(cache.allocation_outpoints().unwrap(), bset![outpoint]);
+
+        // Lookups are served from the index without reading cold assets
+        fs::remove_dir_all(config.cold_dir()).unwrap();
+        assert_eq!(cache.outpoint_assets(outpoint).unwrap(), expected);
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
     #[test]
+    #[cfg(feature = "sql")]
     #[ignore]
     fn test_filecache_mappings() {
         // -------------------------------------------------
@@ -290,9 +1540,10 @@ mod test {
         let filecache_config = FileCacheConfig {
             data_dir: filepath.clone(),
             #[cfg(feature = "serde_json")]
-            data_format: FileFormat::Json,
+            data_format: DataFormat::Json,
             #[cfg(not(feature = "serde_json"))]
-            data_format: FileFormat::StrictEncode,
+            data_format: DataFormat::StrictEncode,
+            memory_budget: None,
         };
 
         // Init new FileCache
@@ -301,7 +1552,7 @@ mod test {
         // Save the test assets inside FileCache
         // You should see an assets.json file in the DataDir
         for asset in assets {
-            filecache.add_asset(asset.clone()).unwrap();
+            filecache.add_asset(asset).unwrap();
         }
 
         // -------------------------------------------------
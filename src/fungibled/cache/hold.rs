@@ -0,0 +1,63 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeSet;
+
+use bitcoin::OutPoint;
+use rgb::ContractId;
+
+use crate::rpc::fungible::HoldTarget;
+
+/// Assets and outpoints put on administrative hold
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Holds {
+    targets: BTreeSet<HoldTarget>,
+}
+
+impl Holds {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets or clears the hold, returning whether the hold was changed
+    pub fn set(&mut self, target: HoldTarget, held: bool) -> bool {
+        if held {
+            self.targets.insert(target)
+        } else {
+            self.targets.remove(&target)
+        }
+    }
+
+    /// Hold preventing transfer of the asset allocation on the outpoint, if
+    /// any
+    pub fn hold(
+        &self,
+        contract_id: ContractId,
+        outpoint: OutPoint,
+    ) -> Option<HoldTarget> {
+        [
+            HoldTarget::Contract(contract_id),
+            HoldTarget::Outpoint(outpoint),
+        ]
+        .iter()
+        .find(|target| self.targets.contains(target))
+        .copied()
+    }
+
+    #[inline]
+    pub fn is_held(&self, target: &HoldTarget) -> bool {
+        self.targets.contains(target)
+    }
+}
@@ -0,0 +1,61 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+
+use bitcoin::hashes::sha256;
+
+/// Completed state-changing request made with a client-supplied idempotency
+/// key
+#[derive(Clone, PartialEq, Eq, Debug, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct JournalEntry {
+    /// Idempotency key provided by the client
+    pub key: String,
+
+    /// Hash of the serialized request, used to detect reuse of the key for
+    /// a different request
+    pub request_hash: sha256::Hash,
+
+    /// Serialized reply returned to the client
+    pub reply: Vec<u8>,
+
+    /// Unix timestamp of the request completion
+    pub completed: i64,
+}
+
+/// Log of the completed requests made with idempotency keys
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Journal {
+    entries: BTreeMap<String, JournalEntry>,
+}
+
+impl Journal {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records completed request, replacing previous entry with the same
+    /// idempotency key, if any
+    #[inline]
+    pub fn record(&mut self, entry: JournalEntry) {
+        self.entries.insert(entry.key.clone(), entry);
+    }
+
+    #[inline]
+    pub fn entry(&self, key: &str) -> Option<&JournalEntry> {
+        self.entries.get(key)
+    }
+}
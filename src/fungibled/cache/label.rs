@@ -0,0 +1,104 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::OutPoint;
+use rgb::ContractId;
+
+use crate::rpc::fungible::LabelTarget;
+
+/// Bookkeeping labels of the outpoints and asset allocations
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Labels {
+    targets: BTreeMap<LabelTarget, BTreeSet<String>>,
+}
+
+impl Labels {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or removes the label, returning whether the labels were changed
+    pub fn set(
+        &mut self,
+        target: LabelTarget,
+        label: String,
+        set: bool,
+    ) -> bool {
+        let labels = self.targets.entry(target).or_default();
+        let changed = if set {
+            labels.insert(label)
+        } else {
+            labels.remove(&label)
+        };
+        if labels.is_empty() {
+            self.targets.remove(&target);
+        }
+        changed
+    }
+
+    /// Labels of the target itself
+    #[inline]
+    pub fn get(&self, target: &LabelTarget) -> BTreeSet<String> {
+        self.targets.get(target).cloned().unwrap_or_default()
+    }
+
+    /// Labels of the asset allocation, including labels of its outpoint
+    pub fn allocation(
+        &self,
+        contract_id: ContractId,
+        outpoint: OutPoint,
+    ) -> BTreeSet<String> {
+        let mut labels = self.get(&LabelTarget::Outpoint(outpoint));
+        labels
+            .extend(self.get(&LabelTarget::Allocation(contract_id, outpoint)));
+        labels
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_allocation_labels() {
+        let contract_id = ContractId::default();
+        let outpoint = OutPoint::default();
+        let mut labels = Labels::new();
+        assert!(labels.set(LabelTarget::Outpoint(outpoint), s!("cold"), true));
+        assert!(labels.set(
+            LabelTarget::Allocation(contract_id, outpoint),
+            s!("customer #123"),
+            true
+        ));
+        assert!(!labels.set(LabelTarget::Outpoint(outpoint), s!("cold"), true));
+        assert_eq!(labels.allocation(contract_id, outpoint).len(), 2);
+        assert!(labels.set(LabelTarget::Outpoint(outpoint), s!("cold"), false));
+        assert_eq!(
+            labels.allocation(contract_id, outpoint),
+            bset! {s!("customer #123")}
+        );
+        assert_eq!(labels, {
+            let mut expected = Labels::new();
+            expected.set(
+                LabelTarget::Allocation(contract_id, outpoint),
+                s!("customer #123"),
+                true,
+            );
+            expected
+        });
+    }
+}
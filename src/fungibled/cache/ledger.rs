@@ -0,0 +1,115 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::{OutPoint, Txid};
+
+/// Inputs of a transfer for which consignment was produced, but the witness
+/// transaction is not yet known to be mined
+#[derive(Clone, PartialEq, Eq, Debug, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Reservation {
+    /// Witness transaction id of the transfer
+    pub txid: Txid,
+
+    /// Outpoints spent by the transfer
+    pub inputs: BTreeSet<OutPoint>,
+
+    /// Unix timestamp after which the reservation is released
+    pub expires: i64,
+}
+
+/// Outpoint reserved for a protocol run outside of the node, like channel
+/// funding or a swap
+#[derive(Clone, PartialEq, Eq, Debug, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct OutpointReservation {
+    pub outpoint: OutPoint,
+
+    /// Protocol the outpoint is reserved for, as given by the client
+    pub purpose: String,
+
+    /// Unix timestamp after which the reservation is released
+    pub expires: i64,
+}
+
+/// Ledger of pending spends, preventing the same allocations from being used
+/// by concurrent transfers before the witness transaction of the first one
+/// gets confirmed, and of the outpoints reserved for external protocols
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct SpendLedger {
+    reservations: BTreeMap<Txid, Reservation>,
+    outpoints: BTreeMap<OutPoint, OutpointReservation>,
+}
+
+impl SpendLedger {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds reservation, replacing previous reservation for the same
+    /// witness transaction, if any
+    pub fn reserve(&mut self, reservation: Reservation) {
+        self.reservations.insert(reservation.txid, reservation);
+    }
+
+    /// Removes reservation made by the transfer with the given witness txid
+    pub fn release(&mut self, txid: Txid) -> Option<Reservation> {
+        self.reservations.remove(&txid)
+    }
+
+    /// Returns reservation holding the given outpoint, if any
+    pub fn reservation(&self, outpoint: OutPoint) -> Option<&Reservation> {
+        self.reservations
+            .values()
+            .find(|reservation| reservation.inputs.contains(&outpoint))
+    }
+
+    #[inline]
+    pub fn reservations(&self) -> Vec<&Reservation> {
+        self.reservations.values().collect()
+    }
+
+    /// Reserves outpoint for external protocol, replacing previous
+    /// reservation of the same outpoint, if any
+    pub fn reserve_outpoint(&mut self, reservation: OutpointReservation) {
+        self.outpoints.insert(reservation.outpoint, reservation);
+    }
+
+    pub fn release_outpoint(
+        &mut self,
+        outpoint: OutPoint,
+    ) -> Option<OutpointReservation> {
+        self.outpoints.remove(&outpoint)
+    }
+
+    #[inline]
+    pub fn outpoint_reservation(
+        &self,
+        outpoint: OutPoint,
+    ) -> Option<&OutpointReservation> {
+        self.outpoints.get(&outpoint)
+    }
+
+    /// Releases outpoint reservations expired at the given unix timestamp;
+    /// returns number of the released outpoints
+    pub fn release_expired_outpoints(&mut self, now: i64) -> usize {
+        let count = self.outpoints.len();
+        self.outpoints
+            .retain(|_, reservation| reservation.expires > now);
+        count - self.outpoints.len()
+    }
+}
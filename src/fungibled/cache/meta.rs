@@ -0,0 +1,93 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+
+use rgb::ContractId;
+
+use crate::rpc::reply::AssetMeta;
+
+/// Client-provided metadata of the cached assets (labels, exchange ids,
+/// icons etc); the metadata are not part of the contract data and are never
+/// transferred to other parties
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct MetaStore {
+    assets: BTreeMap<ContractId, AssetMeta>,
+}
+
+impl MetaStore {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets value of the asset metadata key or removes the key if no value
+    /// is given, returning whether the metadata were changed
+    pub fn set(
+        &mut self,
+        contract_id: ContractId,
+        key: String,
+        value: Option<String>,
+    ) -> bool {
+        let meta = self.assets.entry(contract_id).or_default();
+        let changed = match value {
+            Some(value) => meta.insert(key, value.clone()) != Some(value),
+            None => meta.remove(&key).is_some(),
+        };
+        if meta.is_empty() {
+            self.assets.remove(&contract_id);
+        }
+        changed
+    }
+
+    /// Metadata of the asset; empty if none were set
+    #[inline]
+    pub fn get(&self, contract_id: ContractId) -> AssetMeta {
+        self.assets.get(&contract_id).cloned().unwrap_or_default()
+    }
+
+    /// Metadata of the given assets, skipping assets without metadata
+    pub fn select<'a>(
+        &self,
+        contract_ids: impl IntoIterator<Item = &'a ContractId>,
+    ) -> BTreeMap<ContractId, AssetMeta> {
+        contract_ids
+            .into_iter()
+            .filter_map(|contract_id| {
+                self.assets
+                    .get(contract_id)
+                    .map(|meta| (*contract_id, meta.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_meta_store() {
+        let contract_id = ContractId::default();
+        let mut store = MetaStore::new();
+        assert!(store.set(contract_id, s!("label"), Some(s!("Token"))));
+        assert!(!store.set(contract_id, s!("label"), Some(s!("Token"))));
+        assert_eq!(store.get(contract_id).get("label"), Some(&s!("Token")));
+        assert_eq!(store.select(&[contract_id]).len(), 1);
+        assert!(store.set(contract_id, s!("label"), None));
+        assert!(!store.set(contract_id, s!("label"), None));
+        assert!(store.get(contract_id).is_empty());
+        assert!(store.select(&[contract_id]).is_empty());
+    }
+}
@@ -11,12 +11,40 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+mod accept;
+mod accounts;
+mod audit;
 mod cache;
+mod consignment;
+mod events;
 mod file;
+mod hold;
+mod journal;
+mod label;
+mod ledger;
+mod meta;
+mod pending;
+mod receive;
 #[cfg(feature = "sql")]
 mod sql;
+mod tombstone;
+mod transfer;
 
+pub use accept::{Accepts, PendingAccept};
+pub use accounts::Accounts;
+pub use audit::{AuditError, AuditLog};
 pub use cache::{Cache, CacheError};
+pub use consignment::Consignments;
+pub use events::EventLog;
 pub use file::{FileCache, FileCacheConfig, FileCacheError};
+pub use hold::Holds;
+pub use journal::{Journal, JournalEntry};
+pub use label::Labels;
+pub use ledger::{OutpointReservation, Reservation, SpendLedger};
+pub use meta::MetaStore;
+pub use pending::PendingTransfers;
+pub use receive::Receives;
 #[cfg(feature = "sql")]
 pub use sql::{SqlCache, SqlCacheConfig, SqlCacheError};
+pub use tombstone::{Graveyard, Tombstone};
+pub use transfer::{Payees, Transfers};
@@ -0,0 +1,122 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+
+use bitcoin::Txid;
+
+use crate::rpc::reply::Transfer;
+
+/// Transfers prepared with the two-phase transfer procedure which were not
+/// committed or cancelled yet, together with the replies held until the
+/// operator approval, indexed by their witness transaction id. Held replies
+/// are kept in their RPC encoding, since they may be any of the transfer or
+/// swap replies.
+#[derive(Clone, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct PendingTransfers {
+    prepared: BTreeMap<Txid, Transfer>,
+    held: BTreeMap<Txid, Vec<u8>>,
+}
+
+impl PendingTransfers {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn prepare(&mut self, txid: Txid, transfer: Transfer) {
+        self.prepared.insert(txid, transfer);
+    }
+
+    #[inline]
+    pub fn prepared(&self, txid: &Txid) -> Option<&Transfer> {
+        self.prepared.get(txid)
+    }
+
+    #[inline]
+    pub fn remove_prepared(&mut self, txid: &Txid) -> Option<Transfer> {
+        self.prepared.remove(txid)
+    }
+
+    #[inline]
+    pub fn hold(&mut self, txid: Txid, reply: Vec<u8>) {
+        self.held.insert(txid, reply);
+    }
+
+    #[inline]
+    pub fn is_held(&self, txid: &Txid) -> bool {
+        self.held.contains_key(txid)
+    }
+
+    #[inline]
+    pub fn release(&mut self, txid: &Txid) -> Option<Vec<u8>> {
+        self.held.remove(txid)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+    use bitcoin::{OutPoint, Transaction};
+    use lnpbp::strict_encoding::{strict_deserialize, strict_serialize};
+    use lnpbp::Chain;
+    use rgb::{Consignment, Disclosure};
+
+    #[test]
+    fn test_pending_transfers() {
+        let (_, genesis) = rgb20::issue(
+            Chain::Regtest(Default::default()),
+            s!("TEST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(OutPoint::default(), 100)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        };
+        let transfer = Transfer {
+            consignment: Consignment::with(genesis, vec![], vec![], vec![]),
+            other_consignments: bmap! {},
+            disclosure: Disclosure::default(),
+            witness: Psbt::from_unsigned_tx(tx).unwrap(),
+            optimization: bmap! {},
+        };
+        let (prepared, held) = (Txid::hash(b"prepared"), Txid::hash(b"held"));
+        let mut pending = PendingTransfers::new();
+        pending.prepare(prepared, transfer);
+        pending.hold(held, vec![1, 2, 3]);
+
+        let mut pending: PendingTransfers =
+            strict_deserialize(&strict_serialize(&pending).unwrap()).unwrap();
+        assert!(pending.prepared(&prepared).is_some());
+        assert!(pending.prepared(&held).is_none());
+        assert!(pending.is_held(&held));
+        assert!(!pending.is_held(&prepared));
+        assert_eq!(pending.release(&held), Some(vec![1, 2, 3]));
+        assert!(!pending.is_held(&held));
+        assert!(pending.remove_prepared(&prepared).is_some());
+        assert!(pending.prepared(&prepared).is_none());
+    }
+}
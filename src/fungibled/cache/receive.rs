@@ -0,0 +1,98 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+
+use lnpbp::seals::OutpointHash;
+
+use crate::rpc::reply::PendingReceive;
+
+/// Receives created by the node which are neither fulfilled nor expired,
+/// indexed by their blinded outpoint
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Receives {
+    pending: BTreeMap<OutpointHash, PendingReceive>,
+}
+
+impl Receives {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn add(&mut self, receive: PendingReceive) {
+        self.pending.insert(receive.invoice.endpoint, receive);
+    }
+
+    #[inline]
+    pub fn remove(
+        &mut self,
+        endpoint: &OutpointHash,
+    ) -> Option<PendingReceive> {
+        self.pending.remove(endpoint)
+    }
+
+    #[inline]
+    pub fn list(&self) -> Vec<PendingReceive> {
+        self.pending.values().cloned().collect()
+    }
+
+    /// Blinded outpoints of the receives which have expired by `now`
+    pub fn expired(&self, now: i64) -> Vec<OutpointHash> {
+        self.pending
+            .values()
+            .filter(|receive| receive.expires <= now)
+            .map(|receive| receive.invoice.endpoint)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::OutPoint;
+    use lnpbp::client_side_validation::CommitConceal;
+    use lnpbp::seals::OutpointReveal;
+    use rgb::ContractId;
+
+    use crate::rpc::fungible::Invoice;
+
+    #[test]
+    fn test_expired_receives() {
+        let receive = |vout, expires| {
+            let outpoint = OutPoint::new(Default::default(), vout);
+            PendingReceive {
+                invoice: Invoice {
+                    contract_id: ContractId::default(),
+                    amount: 100,
+                    endpoint: OutpointReveal::from(outpoint).commit_conceal(),
+                    expiry: Some(expires),
+                },
+                outpoint,
+                created: 0,
+                expires,
+                reserved: false,
+            }
+        };
+        let mut receives = Receives::new();
+        let (first, second) = (receive(0, 10), receive(1, 20));
+        receives.add(first.clone());
+        receives.add(second.clone());
+        assert_eq!(receives.expired(5), vec![]);
+        assert_eq!(receives.expired(15), vec![first.invoice.endpoint]);
+        assert_eq!(receives.remove(&first.invoice.endpoint), Some(first));
+        assert_eq!(receives.list(), vec![second]);
+    }
+}
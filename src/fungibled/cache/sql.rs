@@ -11,14 +11,22 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use diesel::connection::SimpleConnection;
 use diesel::prelude::*;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::{fmt, fs, fs::File};
 
 use amplify::IoError;
 use bitcoin::hashes::hex::ToHex;
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1::PublicKey;
+use lnpbp::client_side_validation::CommitConceal;
+use lnpbp::seals::{OutpointHash, OutpointReveal};
+use lnpbp::strict_encoding::{
+    strict_deserialize, strict_serialize, StrictDecode, StrictEncode,
+};
 use rgb::bech32;
 use rgb::prelude::*;
 use rgb20::Asset;
@@ -27,11 +35,24 @@ use crate::fungibled::sql::schema as cache_schema;
 use cache_schema::sql_allocation_utxo::dsl::sql_allocation_utxo as sql_allocation_utxo_table;
 use cache_schema::sql_allocations::dsl::sql_allocations as sql_allocation_table;
 use cache_schema::sql_assets::dsl::sql_assets as sql_asset_table;
+use cache_schema::sql_audit_records::dsl::sql_audit_records as sql_audit_table;
 use cache_schema::sql_inflation::dsl::sql_inflation as sql_inflation_table;
 use cache_schema::sql_issues::dsl::sql_issues as sql_issue_table;
+use cache_schema::sql_stores::dsl::sql_stores as sql_store_table;
 
 use super::cache::{Cache, CacheError};
+use super::{
+    Accepts, Accounts, AuditLog, Consignments, EventLog, Graveyard, Holds,
+    Journal, JournalEntry, Labels, MetaStore, OutpointReservation, Payees,
+    PendingTransfers, Receives, Reservation, SpendLedger, Tombstone, Transfers,
+};
 use crate::fungibled::sql::models::*;
+use crate::rpc::fungible::{HoldTarget, LabelTarget};
+use crate::rpc::reply::{
+    self, AuditRecord, PendingReceive, TransferState, TransferStatus,
+};
+use crate::rpc::stash::AcceptRequest;
+use crate::util::migration::MigrationError;
 
 #[derive(Debug, Display, Error, From)]
 #[display(inner)]
@@ -57,6 +78,12 @@ pub enum SqlCacheError {
     #[from]
     WrongChainData(lnpbp::chain::ParseError),
 
+    #[from]
+    Encoding(lnpbp::strict_encoding::Error),
+
+    #[from]
+    Migration(MigrationError),
+
     #[display("Item not found")]
     NotFound,
 }
@@ -84,6 +111,13 @@ impl From<SqlCacheError> for CacheError {
                 e
             )),
             SqlCacheError::Bech32(e) => Self::DataIntegrityError(e.to_string()),
+            SqlCacheError::Encoding(e) => Self::DataIntegrityError(format!(
+                "Wrong strict encoded data in sqlite asset cache: {}",
+                e
+            )),
+            SqlCacheError::Migration(e) => {
+                Self::DataIntegrityError(e.to_string())
+            }
         }
     }
 }
@@ -106,10 +140,153 @@ impl SqlCacheConfig {
     }
 }
 
+/// Step of the asset cache database schema, embedded from the diesel
+/// migrations kept in `db/cache/migrations`
+struct SchemaStep {
+    description: &'static str,
+    /// Query counting objects created by the step, by which the step is
+    /// detected in the databases set up with diesel CLI before the schema
+    /// was versioned
+    probe: &'static str,
+    sql: &'static str,
+}
+
+/// Schema steps in the order of their application; the version of the
+/// database schema, kept in its `user_version`, is the number of the
+/// applied steps
+const SCHEMA: &[SchemaStep] = &[
+    SchemaStep {
+        description: "creating asset tables",
+        probe: "SELECT count(*) AS count FROM sqlite_master \
+                WHERE type = 'table' AND name = 'sql_assets'",
+        sql: include_str!(
+            "../../../db/cache/migrations/2020-03-21-151310_base/up.sql"
+        ),
+    },
+    SchemaStep {
+        description: "adding node data stores",
+        probe: "SELECT count(*) AS count FROM sqlite_master \
+                WHERE type = 'table' AND name = 'sql_stores'",
+        sql: include_str!(
+            "../../../db/cache/migrations/2021-05-18-120000_stores/up.sql"
+        ),
+    },
+    SchemaStep {
+        description: "adding audit log",
+        probe: "SELECT count(*) AS count FROM sqlite_master \
+                WHERE type = 'table' AND name = 'sql_audit_records'",
+        sql: include_str!(
+            "../../../db/cache/migrations/2021-05-18-130000_audit/up.sql"
+        ),
+    },
+    SchemaStep {
+        description: "adding asset genesis",
+        probe: "SELECT count(*) AS count FROM pragma_table_info('sql_assets') \
+                WHERE name = 'genesis'",
+        sql: include_str!(
+            "../../../db/cache/migrations/2021-05-18-140000_asset_genesis/up.sql"
+        ),
+    },
+];
+
+#[derive(QueryableByName)]
+struct SchemaVersion {
+    #[sql_type = "diesel::sql_types::Integer"]
+    user_version: i32,
+}
+
+#[derive(QueryableByName)]
+struct ObjectCount {
+    #[sql_type = "diesel::sql_types::BigInt"]
+    count: i64,
+}
+
+/// Brings the database schema to the current version, applying the
+/// embedded schema steps in order. Newer schemata are refused, since they
+/// may be damaged by an outdated daemon. Databases without version are
+/// considered to have all the steps applied up to the last one whose
+/// objects are present.
+fn upgrade_schema(connection: &SqliteConnection) -> Result<(), SqlCacheError> {
+    let current = SCHEMA.len() as u16;
+    let mut version = diesel::sql_query("PRAGMA user_version")
+        .get_result::<SchemaVersion>(connection)?
+        .user_version as u16;
+    if version == 0 {
+        for (no, step) in SCHEMA.iter().enumerate() {
+            let present = diesel::sql_query(step.probe)
+                .get_result::<ObjectCount>(connection)?
+                .count;
+            if present > 0 {
+                version = no as u16 + 1;
+            }
+        }
+    }
+    if version > current {
+        Err(MigrationError::Unsupported {
+            found: version,
+            supported: current,
+        })?
+    }
+
+    for step in &SCHEMA[version as usize..] {
+        info!(
+            "Upgrading asset cache database from schema version {}: {}",
+            version, step.description
+        );
+        connection
+            .transaction::<_, diesel::result::Error, _>(|| {
+                connection.batch_execute(step.sql)?;
+                connection.batch_execute(&format!(
+                    "PRAGMA user_version = {}",
+                    version + 1
+                ))
+            })
+            .map_err(|err| MigrationError::Failed {
+                from: version,
+                details: err.to_string(),
+            })?;
+        version += 1;
+    }
+    Ok(())
+}
+
+/// Names under which node data are kept in the `sql_stores` table
+const STORE_LEDGER: &str = "ledger";
+const STORE_REVEALS: &str = "reveals";
+const STORE_JOURNAL: &str = "journal";
+const STORE_EVENTS: &str = "events";
+const STORE_CONSIGNMENTS: &str = "consignments";
+const STORE_HOLDS: &str = "holds";
+const STORE_GRAVEYARD: &str = "graveyard";
+const STORE_META: &str = "meta";
+const STORE_LABELS: &str = "labels";
+const STORE_RECEIVES: &str = "receives";
+const STORE_TRANSFERS: &str = "transfers";
+const STORE_PAYEES: &str = "payees";
+const STORE_ACCOUNTS: &str = "accounts";
+const STORE_ACCEPTS: &str = "accepts";
+const STORE_PENDING: &str = "pending";
+
 /// Keeps all source/binary RGB contract data, stash etc
 pub struct SqlCache {
     connection: SqliteConnection,
     assets: HashMap<ContractId, Asset>,
+    ledger: SpendLedger,
+    graveyard: Graveyard,
+    reveals: BTreeMap<OutpointHash, OutpointReveal>,
+    journal: Journal,
+    events: EventLog,
+    audit: AuditLog,
+    holds: Holds,
+    meta: MetaStore,
+    labels: Labels,
+    receives: Receives,
+    transfers: Transfers,
+    payees: Payees,
+    accounts: Accounts,
+    consignments: Consignments,
+    accepts: Accepts,
+    pending: PendingTransfers,
 }
 
 impl fmt::Display for SqlCache {
@@ -151,10 +328,27 @@ impl SqlCache {
                     .ok_or(SqlCacheError::NotFound)?,
             )
             .expect(&format!("Error connecting to asset.db"));
+            upgrade_schema(&connection)?;
 
             let mut sql_cache = Self {
                 connection,
                 assets: map![],
+                ledger: SpendLedger::new(),
+                graveyard: Graveyard::new(),
+                reveals: bmap![],
+                journal: Journal::new(),
+                events: EventLog::new(),
+                audit: AuditLog::new(),
+                holds: Holds::new(),
+                meta: MetaStore::new(),
+                labels: Labels::new(),
+                receives: Receives::new(),
+                transfers: Transfers::new(),
+                payees: Payees::new(),
+                accounts: Accounts::new(),
+                consignments: Consignments::new(),
+                accepts: Accepts::new(),
+                pending: PendingTransfers::new(),
             };
 
             sql_cache.load()?;
@@ -173,10 +367,27 @@ impl SqlCache {
                     .ok_or(SqlCacheError::NotFound)?,
             )
             .expect(&format!("Error connecting to asset.db"));
+            upgrade_schema(&connection)?;
 
             let sql_cache = Self {
                 connection,
                 assets: map![],
+                ledger: SpendLedger::new(),
+                graveyard: Graveyard::new(),
+                reveals: bmap![],
+                journal: Journal::new(),
+                events: EventLog::new(),
+                audit: AuditLog::new(),
+                holds: Holds::new(),
+                meta: MetaStore::new(),
+                labels: Labels::new(),
+                receives: Receives::new(),
+                transfers: Transfers::new(),
+                payees: Payees::new(),
+                accounts: Accounts::new(),
+                consignments: Consignments::new(),
+                accepts: Accepts::new(),
+                pending: PendingTransfers::new(),
             };
 
             Ok(sql_cache)
@@ -198,9 +409,200 @@ impl SqlCache {
 
         self.assets = asset_map;
 
+        if let Some(ledger) = self.load_store(STORE_LEDGER)? {
+            debug!("Reading pending spends ledger ...");
+            self.ledger = ledger;
+        }
+
+        if let Some(reveals) = self.load_store(STORE_REVEALS)? {
+            debug!("Reading blinded outpoints ...");
+            self.reveals = reveals;
+        }
+
+        if let Some(journal) = self.load_store(STORE_JOURNAL)? {
+            debug!("Reading operation journal ...");
+            self.journal = journal;
+        }
+
+        if let Some(events) = self.load_store(STORE_EVENTS)? {
+            debug!("Reading event log ...");
+            self.events = events;
+        }
+
+        if let Some(consignments) = self.load_store(STORE_CONSIGNMENTS)? {
+            debug!("Reading accepted consignments ...");
+            self.consignments = consignments;
+        }
+
+        debug!("Reading and verifying audit log ...");
+        let records = sql_audit_table
+            .order(cache_schema::sql_audit_records::seq)
+            .load::<SqlAuditRecord>(&self.connection)?;
+        for entry in records {
+            let record: AuditRecord = strict_deserialize(&entry.record)?;
+            self.audit.append(record).map_err(|err| err.to_string())?;
+        }
+
+        if let Some(holds) = self.load_store(STORE_HOLDS)? {
+            debug!("Reading administrative holds ...");
+            self.holds = holds;
+        }
+
+        if let Some(graveyard) = self.load_store(STORE_GRAVEYARD)? {
+            debug!("Reading forgotten allocations ...");
+            self.graveyard = graveyard;
+        }
+
+        if let Some(meta) = self.load_store(STORE_META)? {
+            debug!("Reading asset metadata ...");
+            self.meta = meta;
+        }
+
+        if let Some(labels) = self.load_store(STORE_LABELS)? {
+            debug!("Reading outpoint and allocation labels ...");
+            self.labels = labels;
+        }
+
+        if let Some(receives) = self.load_store(STORE_RECEIVES)? {
+            debug!("Reading pending receives ...");
+            self.receives = receives;
+        }
+
+        if let Some(transfers) = self.load_store(STORE_TRANSFERS)? {
+            debug!("Reading outgoing transfers ...");
+            self.transfers = transfers;
+        }
+
+        if let Some(payees) = self.load_store(STORE_PAYEES)? {
+            debug!("Reading outgoing transfer payees ...");
+            self.payees = payees;
+        }
+
+        if let Some(accounts) = self.load_store(STORE_ACCOUNTS)? {
+            debug!("Reading account registrations ...");
+            self.accounts = accounts;
+        }
+
+        if let Some(accepts) = self.load_store(STORE_ACCEPTS)? {
+            debug!("Reading consignments pending confirmations ...");
+            self.accepts = accepts;
+        }
+
+        if let Some(pending) = self.load_store(STORE_PENDING)? {
+            debug!("Reading prepared and held transfers ...");
+            self.pending = pending;
+        }
+
+        Ok(())
+    }
+
+    /// Reads strict-encoded data saved under the store name, if any
+    fn load_store<T>(&self, store: &str) -> Result<Option<T>, SqlCacheError>
+    where
+        T: StrictDecode,
+    {
+        use cache_schema::sql_stores::dsl::*;
+
+        let entry = sql_store_table
+            .filter(name.eq(store))
+            .first::<SqlStore>(&self.connection)
+            .optional()?;
+        Ok(match entry {
+            Some(entry) => Some(strict_deserialize(&entry.data)?),
+            None => None,
+        })
+    }
+
+    /// Replaces data saved under the store name with the strict encoding of
+    /// the given value
+    fn save_store<T>(&self, store: &str, value: &T) -> Result<(), SqlCacheError>
+    where
+        T: StrictEncode,
+    {
+        diesel::replace_into(sql_store_table)
+            .values(SqlStore {
+                name: store.to_owned(),
+                data: strict_serialize(value)?,
+            })
+            .execute(&self.connection)?;
         Ok(())
     }
 
+    fn save_ledger(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving pending spends ledger ...");
+        self.save_store(STORE_LEDGER, &self.ledger)
+    }
+
+    fn save_reveals(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving blinded outpoints ...");
+        self.save_store(STORE_REVEALS, &self.reveals)
+    }
+
+    fn save_journal(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving operation journal ...");
+        self.save_store(STORE_JOURNAL, &self.journal)
+    }
+
+    fn save_events(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving event log ...");
+        self.save_store(STORE_EVENTS, &self.events)
+    }
+
+    fn save_consignments(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving accepted consignments ...");
+        self.save_store(STORE_CONSIGNMENTS, &self.consignments)
+    }
+
+    fn save_holds(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving administrative holds ...");
+        self.save_store(STORE_HOLDS, &self.holds)
+    }
+
+    fn save_graveyard(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving forgotten allocations ...");
+        self.save_store(STORE_GRAVEYARD, &self.graveyard)
+    }
+
+    fn save_meta(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving asset metadata ...");
+        self.save_store(STORE_META, &self.meta)
+    }
+
+    fn save_labels(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving outpoint and allocation labels ...");
+        self.save_store(STORE_LABELS, &self.labels)
+    }
+
+    fn save_receives(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving pending receives ...");
+        self.save_store(STORE_RECEIVES, &self.receives)
+    }
+
+    fn save_transfers(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving outgoing transfers ...");
+        self.save_store(STORE_TRANSFERS, &self.transfers)
+    }
+
+    fn save_payees(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving outgoing transfer payees ...");
+        self.save_store(STORE_PAYEES, &self.payees)
+    }
+
+    fn save_accounts(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving account registrations ...");
+        self.save_store(STORE_ACCOUNTS, &self.accounts)
+    }
+
+    fn save_accepts(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving consignments pending confirmations ...");
+        self.save_store(STORE_ACCEPTS, &self.accepts)
+    }
+
+    fn save_pending(&self) -> Result<(), SqlCacheError> {
+        trace!("Saving prepared and held transfers ...");
+        self.save_store(STORE_PENDING, &self.pending)
+    }
+
     /// Deletes and recreates the full database with updated cache
     pub fn save(&self) -> Result<(), SqlCacheError> {
         // Delet the existing data
@@ -265,12 +667,12 @@ impl SqlCache {
 impl Cache for SqlCache {
     type Error = CacheError;
 
-    fn assets(&self) -> Result<Vec<&Asset>, CacheError> {
-        Ok(self.assets.values().collect())
+    fn assets(&self) -> Result<Vec<Asset>, CacheError> {
+        Ok(self.assets.values().cloned().collect())
     }
 
     #[inline]
-    fn asset(&self, id: ContractId) -> Result<&Asset, CacheError> {
+    fn asset(&mut self, id: ContractId) -> Result<&Asset, CacheError> {
         Ok(self.assets.get(&id).ok_or(CacheError::DataIntegrityError(
             "Asset is not known".to_string(),
         ))?)
@@ -302,7 +704,10 @@ impl Cache for SqlCache {
     ) -> Result<BTreeMap<bitcoin::OutPoint, Vec<AtomicValue>>, CacheError> {
         // Process known_allocation map to produce the intended map
         let mut result = BTreeMap::<bitcoin::OutPoint, Vec<AtomicValue>>::new();
-        for allocation in self.asset(contract_id)?.known_allocations() {
+        let asset = self.assets.get(&contract_id).ok_or(
+            CacheError::DataIntegrityError("Asset is not known".to_string()),
+        )?;
+        for allocation in asset.known_allocations() {
             result
                 .entry(*allocation.outpoint())
                 .or_insert(default!())
@@ -311,6 +716,22 @@ impl Cache for SqlCache {
         Ok(result)
     }
 
+    fn allocation_outpoints(
+        &self,
+    ) -> Result<BTreeSet<bitcoin::OutPoint>, CacheError> {
+        Ok(self
+            .assets
+            .values()
+            .flat_map(|asset| {
+                asset
+                    .known_allocations()
+                    .iter()
+                    .map(|allocation| *allocation.outpoint())
+                    .collect::<Vec<_>>()
+            })
+            .collect())
+    }
+
     fn outpoint_assets(
         &self,
         outpoint: bitcoin::OutPoint,
@@ -365,6 +786,405 @@ impl Cache for SqlCache {
 
         Ok(result)
     }
+
+    fn reserve_inputs(
+        &mut self,
+        reservation: Reservation,
+    ) -> Result<(), CacheError> {
+        self.ledger.reserve(reservation);
+        self.save_ledger()?;
+        Ok(())
+    }
+
+    fn release_inputs(
+        &mut self,
+        txid: bitcoin::Txid,
+    ) -> Result<Option<Reservation>, CacheError> {
+        let reservation = self.ledger.release(txid);
+        if reservation.is_some() {
+            self.save_ledger()?;
+        }
+        Ok(reservation)
+    }
+
+    #[inline]
+    fn input_reservation(
+        &self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Option<&Reservation>, CacheError> {
+        Ok(self.ledger.reservation(outpoint))
+    }
+
+    #[inline]
+    fn reservations(&self) -> Result<Vec<&Reservation>, CacheError> {
+        Ok(self.ledger.reservations())
+    }
+
+    fn reserve_outpoint(
+        &mut self,
+        reservation: OutpointReservation,
+    ) -> Result<(), CacheError> {
+        self.ledger.reserve_outpoint(reservation);
+        self.save_ledger()?;
+        Ok(())
+    }
+
+    fn release_outpoint(
+        &mut self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Option<OutpointReservation>, CacheError> {
+        let reservation = self.ledger.release_outpoint(outpoint);
+        if reservation.is_some() {
+            self.save_ledger()?;
+        }
+        Ok(reservation)
+    }
+
+    #[inline]
+    fn outpoint_reservation(
+        &self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Option<&OutpointReservation>, CacheError> {
+        Ok(self.ledger.outpoint_reservation(outpoint))
+    }
+
+    fn release_expired_outpoints(
+        &mut self,
+        now: i64,
+    ) -> Result<usize, CacheError> {
+        let count = self.ledger.release_expired_outpoints(now);
+        if count > 0 {
+            self.save_ledger()?;
+        }
+        Ok(count)
+    }
+
+    #[inline]
+    fn bury(&mut self, tombstone: Tombstone) -> Result<(), CacheError> {
+        self.graveyard.bury(tombstone);
+        self.save_graveyard()?;
+        Ok(())
+    }
+
+    fn exhume(
+        &mut self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Vec<Tombstone>, CacheError> {
+        let tombstones = self.graveyard.exhume(outpoint);
+        if !tombstones.is_empty() {
+            self.save_graveyard()?;
+        }
+        Ok(tombstones)
+    }
+
+    fn purge_tombstones(&mut self, before: i64) -> Result<usize, CacheError> {
+        let count = self.graveyard.purge(before);
+        if count > 0 {
+            self.save_graveyard()?;
+        }
+        Ok(count)
+    }
+
+    fn add_reveal(&mut self, reveal: OutpointReveal) -> Result<(), CacheError> {
+        self.reveals.insert(reveal.commit_conceal(), reveal);
+        self.save_reveals()?;
+        Ok(())
+    }
+
+    #[inline]
+    fn reveal(
+        &self,
+        seal: OutpointHash,
+    ) -> Result<Option<OutpointReveal>, CacheError> {
+        Ok(self.reveals.get(&seal).copied())
+    }
+
+    #[inline]
+    fn reveals(&self) -> Result<Vec<OutpointReveal>, CacheError> {
+        Ok(self.reveals.values().copied().collect())
+    }
+
+    fn add_journal_entry(
+        &mut self,
+        entry: JournalEntry,
+    ) -> Result<(), CacheError> {
+        self.journal.record(entry);
+        self.save_journal()?;
+        Ok(())
+    }
+
+    #[inline]
+    fn journal_entry(
+        &self,
+        key: &str,
+    ) -> Result<Option<&JournalEntry>, CacheError> {
+        Ok(self.journal.entry(key))
+    }
+
+    fn log_event(
+        &mut self,
+        event: Vec<u8>,
+        capacity: usize,
+    ) -> Result<u64, CacheError> {
+        let seq = self.events.append(event, capacity);
+        self.save_events()?;
+        Ok(seq)
+    }
+
+    #[inline]
+    fn event_log(&self) -> Result<&EventLog, CacheError> {
+        Ok(&self.events)
+    }
+
+    fn append_audit_record(
+        &mut self,
+        record: AuditRecord,
+    ) -> Result<(), CacheError> {
+        self.audit.append(record.clone())?;
+        diesel::insert_into(sql_audit_table)
+            .values(SqlAuditRecord {
+                seq: record.seq as i64,
+                record: strict_serialize(&record)
+                    .map_err(SqlCacheError::from)?,
+            })
+            .execute(&self.connection)
+            .map_err(SqlCacheError::from)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn audit_log(&self) -> Result<&AuditLog, CacheError> {
+        Ok(&self.audit)
+    }
+
+    fn set_hold(
+        &mut self,
+        target: HoldTarget,
+        held: bool,
+    ) -> Result<bool, CacheError> {
+        let changed = self.holds.set(target, held);
+        if changed {
+            self.save_holds()?;
+        }
+        Ok(changed)
+    }
+
+    #[inline]
+    fn holds(&self) -> Result<&Holds, CacheError> {
+        Ok(&self.holds)
+    }
+
+    fn set_asset_meta(
+        &mut self,
+        contract_id: ContractId,
+        key: String,
+        value: Option<String>,
+    ) -> Result<bool, CacheError> {
+        let changed = self.meta.set(contract_id, key, value);
+        if changed {
+            self.save_meta()?;
+        }
+        Ok(changed)
+    }
+
+    #[inline]
+    fn asset_meta(&self) -> Result<&MetaStore, CacheError> {
+        Ok(&self.meta)
+    }
+
+    fn set_label(
+        &mut self,
+        target: LabelTarget,
+        label: String,
+        set: bool,
+    ) -> Result<bool, CacheError> {
+        let changed = self.labels.set(target, label, set);
+        if changed {
+            self.save_labels()?;
+        }
+        Ok(changed)
+    }
+
+    #[inline]
+    fn labels(&self) -> Result<&Labels, CacheError> {
+        Ok(&self.labels)
+    }
+
+    fn add_pending_receive(
+        &mut self,
+        receive: PendingReceive,
+    ) -> Result<(), CacheError> {
+        self.receives.add(receive);
+        Ok(self.save_receives()?)
+    }
+
+    fn remove_pending_receive(
+        &mut self,
+        endpoint: &OutpointHash,
+    ) -> Result<Option<PendingReceive>, CacheError> {
+        let receive = self.receives.remove(endpoint);
+        if receive.is_some() {
+            self.save_receives()?;
+        }
+        Ok(receive)
+    }
+
+    #[inline]
+    fn pending_receives(&self) -> Result<&Receives, CacheError> {
+        Ok(&self.receives)
+    }
+
+    fn register_transfer(
+        &mut self,
+        status: TransferStatus,
+        payees: BTreeSet<SealEndpoint>,
+    ) -> Result<(), CacheError> {
+        self.payees.add(status.txid, payees);
+        self.save_payees()?;
+        self.transfers.add(status);
+        Ok(self.save_transfers()?)
+    }
+
+    fn update_transfer(
+        &mut self,
+        txid: bitcoin::Txid,
+        state: TransferState,
+        reason: Option<String>,
+        now: i64,
+    ) -> Result<bool, CacheError> {
+        let changed = self.transfers.update(txid, state, reason, now);
+        if changed {
+            self.save_transfers()?;
+        }
+        Ok(changed)
+    }
+
+    #[inline]
+    fn transfers(&self) -> Result<&Transfers, CacheError> {
+        Ok(&self.transfers)
+    }
+
+    #[inline]
+    fn payees(&self) -> Result<&Payees, CacheError> {
+        Ok(&self.payees)
+    }
+
+    fn register_account_items(
+        &mut self,
+        account: PublicKey,
+        assets: &[ContractId],
+        outpoints: &[bitcoin::OutPoint],
+    ) -> Result<bool, CacheError> {
+        for id in assets {
+            self.accounts.register_asset(account, *id);
+        }
+        let mut owned = true;
+        for outpoint in outpoints {
+            owned &= self.accounts.register_outpoint(account, *outpoint);
+        }
+        self.save_accounts()?;
+        Ok(owned)
+    }
+
+    #[inline]
+    fn accounts(&self) -> Result<&Accounts, CacheError> {
+        Ok(&self.accounts)
+    }
+
+    fn keep_consignment(
+        &mut self,
+        consignment_id: sha256::Hash,
+        consignment: Consignment,
+        now: i64,
+    ) -> Result<(), CacheError> {
+        self.consignments.add(consignment_id, consignment, now);
+        Ok(self.save_consignments()?)
+    }
+
+    fn purge_consignments(&mut self, before: i64) -> Result<usize, CacheError> {
+        let count = self.consignments.purge(before);
+        if count > 0 {
+            self.save_consignments()?;
+        }
+        Ok(count)
+    }
+
+    #[inline]
+    fn accepted_consignments(&self) -> Result<&Consignments, CacheError> {
+        Ok(&self.consignments)
+    }
+
+    fn add_pending_accept(
+        &mut self,
+        consignment_id: sha256::Hash,
+        accept: AcceptRequest,
+        required: u32,
+    ) -> Result<bool, CacheError> {
+        let added = self.accepts.add(consignment_id, accept, required);
+        self.save_accepts()?;
+        Ok(added)
+    }
+
+    fn remove_pending_accept(
+        &mut self,
+        consignment_id: &sha256::Hash,
+    ) -> Result<Option<AcceptRequest>, CacheError> {
+        let pending = self.accepts.remove(consignment_id);
+        if pending.is_some() {
+            self.save_accepts()?;
+        }
+        Ok(pending.map(|pending| pending.accept))
+    }
+
+    #[inline]
+    fn pending_accepts(&self) -> Result<&Accepts, CacheError> {
+        Ok(&self.accepts)
+    }
+
+    fn add_prepared_transfer(
+        &mut self,
+        txid: bitcoin::Txid,
+        transfer: reply::Transfer,
+    ) -> Result<(), CacheError> {
+        self.pending.prepare(txid, transfer);
+        Ok(self.save_pending()?)
+    }
+
+    fn remove_prepared_transfer(
+        &mut self,
+        txid: &bitcoin::Txid,
+    ) -> Result<Option<reply::Transfer>, CacheError> {
+        let transfer = self.pending.remove_prepared(txid);
+        if transfer.is_some() {
+            self.save_pending()?;
+        }
+        Ok(transfer)
+    }
+
+    fn hold_reply(
+        &mut self,
+        txid: bitcoin::Txid,
+        reply: Vec<u8>,
+    ) -> Result<(), CacheError> {
+        self.pending.hold(txid, reply);
+        Ok(self.save_pending()?)
+    }
+
+    fn release_reply(
+        &mut self,
+        txid: &bitcoin::Txid,
+    ) -> Result<Option<Vec<u8>>, CacheError> {
+        let reply = self.pending.release(txid);
+        if reply.is_some() {
+            self.save_pending()?;
+        }
+        Ok(reply)
+    }
+
+    #[inline]
+    fn pending_transfers(&self) -> Result<&PendingTransfers, CacheError> {
+        Ok(&self.pending)
+    }
 }
 
 #[cfg(test)]
@@ -942,4 +1762,50 @@ mod test {
         // Assert caclulation meets expectation
         assert_eq!(expected_map, allocation_map_calculated);
     }
+
+    fn schema_version(cache: &SqlCache) -> usize {
+        diesel::sql_query("PRAGMA user_version")
+            .get_result::<SchemaVersion>(&cache.connection)
+            .unwrap()
+            .user_version as usize
+    }
+
+    #[test]
+    fn test_schema_creation() {
+        let data_dir = env::temp_dir()
+            .join(format!("rgb-node-sql-schema-{}", std::process::id()));
+        let config = SqlCacheConfig {
+            data_dir: data_dir.clone(),
+        };
+        let cache = SqlCache::new(&config).unwrap();
+        assert_eq!(schema_version(&cache), SCHEMA.len());
+        drop(cache);
+
+        // Reopened cache reads audit log and stores from the created tables
+        let cache = SqlCache::new(&config).unwrap();
+        assert_eq!(schema_version(&cache), SCHEMA.len());
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_unversioned_schema_upgrade() {
+        let data_dir = env::temp_dir()
+            .join(format!("rgb-node-sql-legacy-{}", std::process::id()));
+        let config = SqlCacheConfig {
+            data_dir: data_dir.clone(),
+        };
+        // Database set up with diesel CLI before the audit log was added
+        fs::create_dir_all(config.assets_dir()).unwrap();
+        let connection = SqliteConnection::establish(
+            config.assets_filename().to_str().unwrap(),
+        )
+        .unwrap();
+        connection.batch_execute(SCHEMA[0].sql).unwrap();
+        connection.batch_execute(SCHEMA[1].sql).unwrap();
+        drop(connection);
+
+        let cache = SqlCache::new(&config).unwrap();
+        assert_eq!(schema_version(&cache), SCHEMA.len());
+        fs::remove_dir_all(data_dir).unwrap();
+    }
 }
@@ -0,0 +1,68 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use bitcoin::OutPoint;
+use rgb::{value, ContractId, NodeId};
+
+/// Asset allocation removed from the cache by a forget request, which can be
+/// restored until the tombstone expires
+#[derive(Clone, PartialEq, Eq, Debug, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Tombstone {
+    pub contract_id: ContractId,
+    pub outpoint: OutPoint,
+    pub node_id: NodeId,
+    pub index: u16,
+    pub value: value::Revealed,
+
+    /// Unix timestamp of the allocation removal
+    pub buried: i64,
+}
+
+/// Storage of the forgotten allocations
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Graveyard {
+    tombstones: Vec<Tombstone>,
+}
+
+impl Graveyard {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn bury(&mut self, tombstone: Tombstone) {
+        self.tombstones.push(tombstone);
+    }
+
+    /// Removes and returns all tombstones for the allocations on the outpoint
+    pub fn exhume(&mut self, outpoint: OutPoint) -> Vec<Tombstone> {
+        let (exhumed, rest) = self
+            .tombstones
+            .drain(..)
+            .partition(|tombstone| tombstone.outpoint == outpoint);
+        self.tombstones = rest;
+        exhumed
+    }
+
+    /// Removes tombstones buried before the given unix timestamp, returning
+    /// number of removed tombstones
+    pub fn purge(&mut self, before: i64) -> usize {
+        let count = self.tombstones.len();
+        self.tombstones
+            .retain(|tombstone| tombstone.buried >= before);
+        count - self.tombstones.len()
+    }
+}
@@ -0,0 +1,166 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::Txid;
+use rgb::SealEndpoint;
+
+use crate::rpc::reply::{TransferState, TransferStatus};
+
+/// Registry of the outgoing transfers, indexed by their witness transaction
+/// id
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Transfers {
+    transfers: BTreeMap<Txid, TransferStatus>,
+}
+
+impl Transfers {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds transfer, replacing previous transfer with the same witness
+    /// transaction, if any
+    #[inline]
+    pub fn add(&mut self, status: TransferStatus) {
+        self.transfers.insert(status.txid, status);
+    }
+
+    /// Moves transfer to the given state; returns whether the state was
+    /// changed. Transfers are never moved backwards, and only transfers
+    /// which are not broadcast yet may fail.
+    pub fn update(
+        &mut self,
+        txid: Txid,
+        state: TransferState,
+        reason: Option<String>,
+        now: i64,
+    ) -> bool {
+        let status = match self.transfers.get_mut(&txid) {
+            Some(status) => status,
+            None => return false,
+        };
+        let allowed = match state {
+            TransferState::Failed => status.state < TransferState::Broadcast,
+            _ => state > status.state,
+        };
+        if allowed {
+            status.state = state;
+            status.reason = reason;
+            status.updated = now;
+        }
+        allowed
+    }
+
+    #[inline]
+    pub fn get(&self, txid: &Txid) -> Option<&TransferStatus> {
+        self.transfers.get(txid)
+    }
+
+    #[inline]
+    pub fn list(&self) -> Vec<TransferStatus> {
+        self.transfers.values().cloned().collect()
+    }
+
+    /// Transfers whose witness transaction is yet to be mined
+    pub fn unconfirmed(&self) -> Vec<&TransferStatus> {
+        self.transfers
+            .values()
+            .filter(|status| {
+                status.state >= TransferState::Consigned
+                    && status.state < TransferState::Confirmed
+            })
+            .collect()
+    }
+}
+
+/// Payee endpoints of the outgoing transfers, indexed by their witness
+/// transaction id. Kept apart from [`Transfers`] so the transfers recorded
+/// before the payees were tracked remain readable.
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Payees {
+    payees: BTreeMap<Txid, BTreeSet<SealEndpoint>>,
+}
+
+impl Payees {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records payees of the transfer, replacing previously recorded payees
+    /// of the same witness transaction, if any
+    #[inline]
+    pub fn add(&mut self, txid: Txid, payees: BTreeSet<SealEndpoint>) {
+        self.payees.insert(txid, payees);
+    }
+
+    /// Payees of the transfer; empty if none were recorded
+    #[inline]
+    pub fn get(&self, txid: &Txid) -> Vec<SealEndpoint> {
+        self.payees
+            .get(txid)
+            .map(|payees| payees.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rgb::ContractId;
+
+    #[test]
+    fn test_transfer_states() {
+        let txid = Txid::default();
+        let mut transfers = Transfers::new();
+        transfers.add(TransferStatus {
+            txid,
+            contract_id: ContractId::default(),
+            amount: 100,
+            state: TransferState::Consigned,
+            created: 0,
+            updated: 0,
+            reason: None,
+        });
+        assert!(!transfers.update(txid, TransferState::Created, None, 1));
+        assert!(transfers.update(txid, TransferState::Broadcast, None, 2));
+        assert_eq!(transfers.unconfirmed().len(), 1);
+        assert!(!transfers.update(
+            txid,
+            TransferState::Failed,
+            Some(s!("cancelled")),
+            3
+        ));
+        assert!(transfers.update(txid, TransferState::Confirmed, None, 4));
+        assert!(transfers.unconfirmed().is_empty());
+        assert_eq!(transfers.get(&txid).unwrap().updated, 4);
+    }
+
+    #[test]
+    fn test_transfer_payees() {
+        let txid = Txid::default();
+        let payee = SealEndpoint::WitnessVout {
+            vout: 1,
+            blinding: 42,
+        };
+        let mut payees = Payees::new();
+        assert!(payees.get(&txid).is_empty());
+        payees.add(txid, bset![payee]);
+        assert_eq!(payees.get(&txid), vec![payee]);
+    }
+}
@@ -0,0 +1,119 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Introspection of the operations permitted by the contract: a state
+//! transition is permitted when the contract schema defines it and the
+//! genesis assigns at least one of the owned rights the transition closes.
+
+use std::collections::BTreeSet;
+
+use rgb::{data, AtomicValue, Genesis, Node, Schema};
+use rgb20::schema::{FieldType, OwnedRightsType, TransitionType};
+
+use crate::rpc::reply::{AssetCapabilities, ContractCapabilities};
+
+/// Computes operations permitted by the contract schema and genesis
+pub fn introspect(schema: &Schema, genesis: &Genesis) -> ContractCapabilities {
+    let transitions = schema
+        .transitions
+        .iter()
+        .filter(|(_, transition)| {
+            transition
+                .closes
+                .keys()
+                .any(|ty| genesis.owned_rights_by_type(*ty).is_some())
+        })
+        .map(|(ty, _)| *ty as u16)
+        .collect::<BTreeSet<_>>();
+    let permits = |ty: TransitionType| transitions.contains(&(*ty as u16));
+    let capabilities = AssetCapabilities {
+        inflation: permits(TransitionType::Issue),
+        renomination: permits(TransitionType::Renomination),
+        burn: permits(TransitionType::Epoch)
+            || permits(TransitionType::Burn)
+            || permits(TransitionType::BurnAndReplace),
+    };
+
+    let issued = genesis
+        .metadata()
+        .u64(*FieldType::IssuedSupply)
+        .into_iter()
+        .fold(0 as AtomicValue, AtomicValue::saturating_add);
+    let inflation = if capabilities.inflation {
+        genesis
+            .owned_rights_by_type(*OwnedRightsType::Inflation)
+            .map(|assignments| {
+                assignments
+                    .known_state_data()
+                    .into_iter()
+                    .filter_map(|state| match state {
+                        data::Revealed::U64(value) => Some(*value),
+                        _ => None,
+                    })
+                    .fold(0, AtomicValue::saturating_add)
+            })
+            .unwrap_or_default()
+    } else {
+        0
+    };
+
+    ContractCapabilities {
+        contract_id: genesis.contract_id(),
+        schema_id: genesis.schema_id(),
+        transitions,
+        capabilities,
+        issued,
+        max_supply: issued.saturating_add(inflation),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::OutPoint;
+    use lnpbp::Chain;
+
+    #[test]
+    fn test_introspect() {
+        let outpoint = |vout| OutPoint {
+            txid: Default::default(),
+            vout,
+        };
+        let (_, genesis) = rgb20::issue(
+            Chain::Regtest(Default::default()),
+            s!("TEST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 100)],
+            bmap! { outpoint(1) => 50 },
+            None,
+            None,
+        )
+        .unwrap();
+        let report = introspect(&rgb20::schema::schema(), &genesis);
+        assert_eq!(
+            report.capabilities,
+            AssetCapabilities {
+                inflation: true,
+                renomination: false,
+                burn: false,
+            }
+        );
+        assert!(report
+            .transitions
+            .contains(&(*TransitionType::Transfer as u16)));
+        assert_eq!(report.issued, 100);
+        assert_eq!(report.max_supply, 150);
+    }
+}
@@ -0,0 +1,220 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Cold-storage bundle with everything required to claim the allocations
+//! owned by the node on a fresh node: contract geneses and histories, cached
+//! assets, reveal secrets of the blinded outpoints and funding descriptors.
+//!
+//! Bundle is `RGBCLAIM` magic, version byte, number of PBKDF2 rounds (u32
+//! little-endian), 16-byte salt and 12-byte nonce, followed by the strict
+//! encoded claims encrypted with ChaCha20-Poly1305. The key is derived from
+//! the passphrase with PBKDF2-HMAC-SHA256; the header is authenticated
+//! together with the ciphertext.
+
+use bitcoin::hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
+use bitcoin::secp256k1::rand::{thread_rng, RngCore};
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use lnpbp::seals::OutpointReveal;
+use lnpbp::strict_encoding::{strict_deserialize, strict_serialize};
+use rgb::{Disclosure, Genesis};
+use rgb20::Asset;
+
+use crate::constants::{
+    FUNGIBLED_CLAIMS_KDF_MAX_ROUNDS, FUNGIBLED_CLAIMS_KDF_ROUNDS,
+};
+
+const CLAIMS_MAGIC: &[u8; 8] = b"RGBCLAIM";
+const CLAIMS_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = 8 + 1 + 4 + SALT_LEN + NONCE_LEN;
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum ClaimsError {
+    /// data are not a claims bundle
+    Malformed,
+
+    /// claims bundle version {0} is not supported
+    UnsupportedVersion(u8),
+
+    /// claims bundle requires {0} key derivation rounds, which is outside
+    /// of the supported range
+    UnsupportedRounds(u32),
+
+    /// wrong passphrase or corrupted claims bundle
+    Decryption,
+
+    /// unable to encode claims: {0}
+    Encoding(String),
+}
+
+/// Data of a single contract required to restore its state
+#[derive(Clone, PartialEq, Debug, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct ContractClaims {
+    pub genesis: Genesis,
+    /// Anchored state transitions and extensions known to the stash
+    pub history: Disclosure,
+    /// Cached asset with the allocations owned by the node
+    pub asset: Asset,
+}
+
+#[derive(Clone, PartialEq, Debug, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Claims {
+    /// Unix timestamp of the export
+    pub created: i64,
+    pub contracts: Vec<ContractClaims>,
+    /// Reveal data of the outpoints blinded by the node
+    pub reveals: Vec<OutpointReveal>,
+    /// Output descriptors of the funding wallet keys
+    pub descriptors: Vec<String>,
+}
+
+impl Claims {
+    /// Encrypts the claims with the key derived from the passphrase
+    pub fn seal(&self, passphrase: &str) -> Result<Vec<u8>, ClaimsError> {
+        self.seal_with(passphrase, FUNGIBLED_CLAIMS_KDF_ROUNDS)
+    }
+
+    fn seal_with(
+        &self,
+        passphrase: &str,
+        rounds: u32,
+    ) -> Result<Vec<u8>, ClaimsError> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        thread_rng().fill_bytes(&mut salt);
+        thread_rng().fill_bytes(&mut nonce);
+
+        let mut bundle = Vec::with_capacity(HEADER_LEN);
+        bundle.extend_from_slice(CLAIMS_MAGIC);
+        bundle.push(CLAIMS_VERSION);
+        bundle.extend_from_slice(&rounds.to_le_bytes());
+        bundle.extend_from_slice(&salt);
+        bundle.extend_from_slice(&nonce);
+
+        let data = strict_serialize(self)
+            .map_err(|err| ClaimsError::Encoding(err.to_string()))?;
+        let key = pbkdf2(passphrase, &salt, rounds);
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &data,
+                    aad: &bundle,
+                },
+            )
+            .map_err(|_| ClaimsError::Encoding(s!("encryption failure")))?;
+        bundle.extend(ciphertext);
+        Ok(bundle)
+    }
+
+    /// Decrypts the claims bundle produced by [`Claims::seal`]
+    pub fn open(bundle: &[u8], passphrase: &str) -> Result<Self, ClaimsError> {
+        if bundle.len() < HEADER_LEN || &bundle[..8] != CLAIMS_MAGIC {
+            return Err(ClaimsError::Malformed);
+        }
+        if bundle[8] != CLAIMS_VERSION {
+            return Err(ClaimsError::UnsupportedVersion(bundle[8]));
+        }
+        let mut rounds = [0u8; 4];
+        rounds.copy_from_slice(&bundle[9..13]);
+        let rounds = u32::from_le_bytes(rounds);
+        // Number of rounds comes from the untrusted data, so it is checked
+        // before spending time on the key derivation
+        if rounds == 0 || rounds > FUNGIBLED_CLAIMS_KDF_MAX_ROUNDS {
+            return Err(ClaimsError::UnsupportedRounds(rounds));
+        }
+        let salt = &bundle[13..13 + SALT_LEN];
+        let (header, ciphertext) = bundle.split_at(HEADER_LEN);
+
+        let key = pbkdf2(passphrase, salt, rounds);
+        let data = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .decrypt(
+                Nonce::from_slice(&header[HEADER_LEN - NONCE_LEN..]),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| ClaimsError::Decryption)?;
+        strict_deserialize(&data).map_err(|_| ClaimsError::Malformed)
+    }
+}
+
+/// PBKDF2-HMAC-SHA256 producing a single 32-byte block
+fn pbkdf2(passphrase: &str, salt: &[u8], rounds: u32) -> [u8; 32] {
+    let prf = HmacEngine::<sha256::Hash>::new(passphrase.as_bytes());
+    let mut engine = prf.clone();
+    engine.input(salt);
+    engine.input(&1u32.to_be_bytes());
+    let mut block = Hmac::from_engine(engine).into_inner();
+    let mut key = block;
+    for _ in 1..rounds {
+        let mut engine = prf.clone();
+        engine.input(&block);
+        block = Hmac::from_engine(engine).into_inner();
+        key.iter_mut().zip(&block).for_each(|(k, b)| *k ^= b);
+    }
+    key
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::hashes::hex::ToHex;
+    use bitcoin::OutPoint;
+
+    use super::*;
+
+    #[test]
+    fn test_claims_bundle() {
+        assert_eq!(
+            pbkdf2("password", b"salt", 2).to_hex(),
+            "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"
+        );
+
+        let claims = Claims {
+            created: 1_600_000_000,
+            contracts: vec![],
+            reveals: vec![OutpointReveal::from(OutPoint::default())],
+            descriptors: vec![s!("wpkh([d34db33f/84h/0h/0h]xpub)")],
+        };
+        let bundle = claims.seal_with("secret", 16).unwrap();
+        assert_eq!(Claims::open(&bundle, "secret"), Ok(claims));
+        assert_eq!(
+            Claims::open(&bundle, "wrong"),
+            Err(ClaimsError::Decryption)
+        );
+
+        // Number of rounds is authenticated together with the ciphertext
+        let mut tampered = bundle.clone();
+        tampered[9] = 17;
+        assert_eq!(
+            Claims::open(&tampered, "secret"),
+            Err(ClaimsError::Decryption)
+        );
+        assert_eq!(
+            Claims::open(&bundle[..HEADER_LEN - 1], "secret"),
+            Err(ClaimsError::Malformed)
+        );
+        let mut expensive = bundle.clone();
+        expensive[9..13].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            Claims::open(&expensive, "secret"),
+            Err(ClaimsError::UnsupportedRounds(u32::MAX))
+        );
+    }
+}
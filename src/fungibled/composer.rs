@@ -0,0 +1,223 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Construction of the witness transaction skeleton from the outputs of the
+//! registered descriptors. Asset inputs are always spent; bitcoin inputs are
+//! selected largest-first until they cover the fee and leave a change output
+//! above the dust limit. The change output goes first, so it can be used by
+//! the transfer for the witness transaction seals, and in `tweak` mode it
+//! holds the anchor commitment.
+
+use std::collections::BTreeSet;
+
+use bitcoin::util::psbt::raw::ProprietaryKey;
+use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+use bitcoin::{OutPoint, PublicKey, Script, Transaction, TxIn, TxOut};
+use rgb::{PSBT_OUT_PUBKEY, PSBT_PREFIX};
+
+use crate::util::AnchorContainer;
+
+/// Outputs below this value are not relayed by bitcoin nodes
+const DUST_LIMIT: u64 = 546;
+
+/// Version, locktime, input and output counters and segwit marker
+const TX_OVERHEAD_VSIZE: u64 = 11;
+
+/// Size of OP_RETURN output with 32-byte commitment
+const OP_RETURN_VSIZE: u64 = 43;
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum ComposeError {
+    /// Descriptor outputs have {available} sats, while the witness
+    /// transaction requires at least {required} sats
+    InsufficientFunds { available: u64, required: u64 },
+
+    /// Input {0} is not an unspent output of the registered descriptors
+    UnknownInput(OutPoint),
+
+    /// No descriptors are registered, so the change output can't be created
+    NoChange,
+}
+
+/// Witness transaction skeleton ready for the transfer and signing
+#[derive(Clone, PartialEq, Debug)]
+pub struct Template {
+    pub psbt: Psbt,
+    pub fee: u64,
+    pub funding: BTreeSet<OutPoint>,
+    pub change_vout: u32,
+}
+
+/// Estimated virtual size of the input spending the output with the script
+fn input_vsize(script: &Script) -> u64 {
+    if script.is_v0_p2wpkh() {
+        68
+    } else if script.is_p2sh() {
+        // We support only `sh(wpkh)` descriptors
+        91
+    } else {
+        148
+    }
+}
+
+fn output_vsize(script: &Script) -> u64 {
+    9 + script.len() as u64
+}
+
+/// Composes witness transaction spending all of the asset `inputs` and the
+/// funding outputs selected from `candidates`, with the change sent to the
+/// `change` script. Fee rate is measured in sats per virtual byte.
+pub fn compose(
+    inputs: &[(OutPoint, TxOut)],
+    candidates: &[(OutPoint, TxOut)],
+    change: (Script, PublicKey),
+    fee_rate: u64,
+    container: AnchorContainer,
+) -> Result<Template, ComposeError> {
+    let (change_script, change_key) = change;
+    let mut vsize = TX_OVERHEAD_VSIZE + output_vsize(&change_script);
+    if container == AnchorContainer::OpReturn {
+        vsize += OP_RETURN_VSIZE;
+    }
+
+    let mut spent = inputs.to_vec();
+    let mut candidates = candidates
+        .iter()
+        .filter(|(outpoint, _)| {
+            !inputs.iter().any(|(input, _)| input == outpoint)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|(_, txout)| txout.value);
+
+    let mut funding = BTreeSet::new();
+    let fee = loop {
+        let available = spent.iter().map(|(_, txout)| txout.value).sum();
+        let fee = (vsize
+            + spent
+                .iter()
+                .map(|(_, txout)| input_vsize(&txout.script_pubkey))
+                .sum::<u64>())
+            * fee_rate;
+        if available >= fee + DUST_LIMIT {
+            break fee;
+        }
+        match candidates.pop() {
+            Some((outpoint, txout)) => {
+                funding.insert(outpoint);
+                spent.push((outpoint, txout));
+            }
+            None => Err(ComposeError::InsufficientFunds {
+                available,
+                required: fee + DUST_LIMIT,
+            })?,
+        }
+    };
+    let available = spent.iter().map(|(_, txout)| txout.value).sum::<u64>();
+
+    let tx = Transaction {
+        version: 2,
+        lock_time: 0,
+        input: spent
+            .iter()
+            .map(|(outpoint, _)| TxIn {
+                previous_output: *outpoint,
+                script_sig: Script::new(),
+                sequence: 0xFFFFFFFF,
+                witness: vec![],
+            })
+            .collect(),
+        output: vec![TxOut {
+            value: available - fee,
+            script_pubkey: change_script,
+        }],
+    };
+    let mut psbt =
+        Psbt::from_unsigned_tx(tx).expect("transaction inputs are unsigned");
+    for (input, (_, txout)) in psbt.inputs.iter_mut().zip(spent) {
+        if txout.script_pubkey.is_witness_program() {
+            input.witness_utxo = Some(txout);
+        }
+    }
+    if container == AnchorContainer::Tweak {
+        psbt.outputs[0].proprietary.insert(
+            ProprietaryKey {
+                prefix: PSBT_PREFIX.to_vec(),
+                subtype: PSBT_OUT_PUBKEY,
+                key: vec![],
+            },
+            change_key.key.serialize().to_vec(),
+        );
+    }
+
+    Ok(Template {
+        psbt,
+        fee,
+        funding,
+        change_vout: 0,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Txid, WPubkeyHash};
+    use core::str::FromStr;
+    use wallet::psbt::Fee;
+
+    #[test]
+    fn test_compose() {
+        let script = Script::new_v0_wpkh(&WPubkeyHash::hash(b"change"));
+        let key = PublicKey::from_str(
+            "0279be667ef9dcbbac55a06295ce870b07\
+             029bfcdb2dce28d959f2815b16f81798",
+        )
+        .unwrap();
+        let utxo = |vout, value| {
+            (
+                OutPoint::new(Txid::default(), vout),
+                TxOut {
+                    value,
+                    script_pubkey: script.clone(),
+                },
+            )
+        };
+        let inputs = vec![utxo(0, 1_000)];
+        let candidates = vec![utxo(0, 1_000), utxo(1, 500), utxo(2, 3_000)];
+
+        let template = compose(
+            &inputs,
+            &candidates,
+            (script.clone(), key),
+            10,
+            AnchorContainer::Tweak,
+        )
+        .unwrap();
+        let tx = &template.psbt.global.unsigned_tx;
+        assert_eq!(template.funding, bset! { utxo(2, 0).0 });
+        assert_eq!(template.fee, (11 + 31 + 68 * 2) * 10);
+        assert_eq!(tx.output[0].value, 4_000 - template.fee);
+        assert_eq!(template.psbt.fee().unwrap(), template.fee);
+        assert_eq!(template.psbt.outputs[0].proprietary.len(), 1);
+
+        assert_eq!(
+            compose(&inputs, &[], (script, key), 10, AnchorContainer::OpReturn),
+            Err(ComposeError::InsufficientFunds {
+                available: 1_000,
+                required: (11 + 31 + 43 + 68) * 10 + DUST_LIMIT
+            })
+        );
+    }
+}
@@ -13,13 +13,33 @@
 
 use core::fmt::Display;
 use core::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+#[cfg(feature = "serde")]
+use serde_with::As;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
+use super::deterministic::GenesisSeed;
+use super::keychain::Descriptor;
+use super::signer::Signer;
+use super::subscription::Subscription;
+use super::webhook::Webhook;
+use crate::constants::*;
+use crate::rpc::relay::RelayKey;
+use crate::util::backup::BackupTarget;
+#[cfg(feature = "node")]
+use crate::util::config::{self, LayeredOpts};
+#[cfg(feature = "server")]
+use crate::util::daemon::{Credentials, LifecycleError};
+use crate::util::logging::{LogFilter, LogTarget};
+#[cfg(feature = "serde")]
+use crate::util::AtomicValueString;
+use crate::util::{AnchorContainer, DataFormat};
+use bitcoin::secp256k1::PublicKey;
 use internet2::zmqsocket::ZmqSocketAddr;
 use lnpbp::Chain;
-use microservices::FileFormat;
-
-use crate::constants::*;
+use rgb::AtomicValue;
 
 #[derive(Clap)]
 #[clap(
@@ -43,8 +63,14 @@ pub struct Opts {
     pub cache: String,
 
     /// Data format for cache storage (valid only if file storage is used)
-    #[clap(short, long, default_value = "yaml", env = "RGB_FUNGIBLED_FORMAT")]
-    pub format: FileFormat,
+    #[clap(
+        short,
+        long,
+        default_value = "yaml",
+        env = "RGB_FUNGIBLED_FORMAT",
+        parse(try_from_str = DataFormat::parse_storage)
+    )]
+    pub format: DataFormat,
 
     /// ZMQ socket address string for REQ/REP API
     #[clap(
@@ -54,6 +80,14 @@ pub struct Opts {
     )]
     pub rpc_endpoint: String,
 
+    /// ZMQ socket address string for PUB/SUB API publishing asset events
+    #[clap(
+        long = "pub",
+        default_value = FUNGIBLED_PUB_ENDPOINT,
+        env = "RGB_FUNGIBLED_PUB"
+    )]
+    pub pub_endpoint: String,
+
     /// ZMQ socket address string for REQ/REP API
     #[clap(
         long,
@@ -62,9 +96,376 @@ pub struct Opts {
     )]
     pub stash_rpc: String,
 
-    /// Bitcoin network to use
+    /// Bitcoin network to use (`bitcoin`, `testnet`, `signet` or `regtest`);
+    /// private signet and regtest networks are given as
+    /// `signet:<genesis hash>` and `regtest:<genesis hash>`
     #[clap(short, long, default_value = RGB_NETWORK, env = "RGB_NETWORK")]
     pub network: Chain,
+
+    /// Maximum total supply (in atomic units, including inflation) of the
+    /// assets which may be issued by this node
+    #[clap(long, env = "RGB_FUNGIBLED_SUPPLY_CAP")]
+    pub supply_cap: Option<AtomicValue>,
+
+    /// Minimal amount (in atomic units) which may be assigned to a transfer
+    /// output; smaller change outputs are merged, smaller payments are
+    /// rejected
+    #[clap(long, env = "RGB_FUNGIBLED_DUST_LIMIT")]
+    pub dust_limit: Option<AtomicValue>,
+
+    /// Witness transaction output holding the anchor commitment of the
+    /// transfers which do not specify it: `tweak` for the pay-to-contract
+    /// tweak of an existing output key or `op-return` for an OP_RETURN output
+    /// added to the transaction, for the wallets unable to sign for tweaked
+    /// keys [default: tweak]
+    #[clap(long, env = "RGB_FUNGIBLED_ANCHOR_CONTAINER")]
+    pub anchor_container: Option<AnchorContainer>,
+
+    /// Path to a file with per-asset spending policies (YAML, JSON or TOML)
+    #[clap(long, env = "RGB_FUNGIBLED_POLICY")]
+    pub policy: Option<String>,
+
+    /// Electrum server to use to check input confirmations required by
+    /// spending policies [default: depends on the network]
+    #[clap(long = "electrum", env = "RGB_ELECTRUM_SERVER")]
+    pub electrum_server: Option<String>,
+
+    /// Output descriptors of the keys signing witness transactions outside
+    /// of the node, in `wpkh([fingerprint/path]xpub)`,
+    /// `sh(wpkh([fingerprint/path]xpub))` or `pkh([fingerprint/path]xpub)`
+    /// form; used to fill PSBTs prepared for signing with key derivation
+    /// paths
+    #[clap(
+        long = "descriptor",
+        env = "RGB_FUNGIBLED_DESCRIPTORS",
+        use_delimiter = true
+    )]
+    pub descriptors: Vec<String>,
+
+    /// Co-signing service called with witness PSBTs prepared for signing,
+    /// given as `exec:<command>` or `http://<host>[:<port>][/<path>]`; it is
+    /// called only for the transfers within `auto-sign-limit` of the asset
+    /// spending policy
+    #[clap(long, env = "RGB_FUNGIBLED_SIGNER")]
+    pub signer: Option<String>,
+
+    /// Amount (in atomic units) of any asset leaving the node, including
+    /// change assigned to the seals not controlled by the node, above which
+    /// transfers and swaps require approval of a second operator before the
+    /// consignment is released [default: no approval is required]
+    #[clap(long, env = "RGB_FUNGIBLED_APPROVAL_THRESHOLD")]
+    pub approval_threshold: Option<AtomicValue>,
+
+    /// Public keys (hex-encoded) of the operators allowed to approve
+    /// transfers above the approval threshold
+    #[clap(
+        long = "approver",
+        env = "RGB_FUNGIBLED_APPROVERS",
+        use_delimiter = true
+    )]
+    pub approvers: Vec<String>,
+
+    /// Public keys (hex-encoded) of the admins allowed to put assets and
+    /// outpoints on administrative hold
+    #[clap(long = "admin", env = "RGB_FUNGIBLED_ADMINS", use_delimiter = true)]
+    pub admins: Vec<String>,
+
+    /// Public keys (hex-encoded) of the accounts (tenants) allowed to make
+    /// requests scoped to their own assets and allocations. If any accounts
+    /// are given, all other requests, except liveness checks and the ones
+    /// signed by approvers and admins, are rejected
+    #[clap(
+        long = "account",
+        env = "RGB_FUNGIBLED_ACCOUNTS",
+        use_delimiter = true
+    )]
+    pub accounts: Vec<String>,
+
+    /// Time (in seconds) after which inputs reserved by a transfer are
+    /// released if its witness transaction is not mined [default: 1 day]
+    #[clap(long, env = "RGB_FUNGIBLED_RESERVATION_TIMEOUT")]
+    pub reservation_timeout: Option<u32>,
+
+    /// Number of days during which forgotten allocations can be restored
+    /// [default: 30]
+    #[clap(long, env = "RGB_FUNGIBLED_TOMBSTONE_DAYS")]
+    pub tombstone_days: Option<u32>,
+
+    /// Number of days during which accepted consignments are kept, so they
+    /// can be exported as a proof of payment; zero disables keeping them
+    /// [default: 90]
+    #[clap(long, env = "RGB_FUNGIBLED_CONSIGNMENT_RETENTION_DAYS")]
+    pub consignment_retention_days: Option<u32>,
+
+    /// Interval (in seconds) between re-checks of the confirmation status of
+    /// the outpoints holding assets [default: 10 minutes]
+    #[clap(long, env = "RGB_FUNGIBLED_WATCH_INTERVAL")]
+    pub watch_interval: Option<u32>,
+
+    /// Number of confirmations which witness transactions of the incoming
+    /// consignments must have before the consignment is accepted; less
+    /// confirmed consignments are kept pending [default: 0]
+    #[clap(long, env = "RGB_FUNGIBLED_MIN_CONFIRMATIONS")]
+    pub min_confirmations: Option<u32>,
+
+    /// Contracts whose consignments published by the relay peers are
+    /// accepted automatically if they assign assets to the seals owned by
+    /// this node, as `<contract_id>[:<min_confirmations>]`
+    #[clap(
+        long = "subscribe",
+        env = "RGB_FUNGIBLED_SUBSCRIPTIONS",
+        use_delimiter = true
+    )]
+    pub subscriptions: Vec<String>,
+
+    /// ZMQ socket addresses of the stash daemon relays publishing
+    /// consignments of the subscribed contracts
+    #[clap(
+        long = "relay-peer",
+        env = "RGB_FUNGIBLED_RELAY_PEERS",
+        use_delimiter = true
+    )]
+    pub relay_peers: Vec<String>,
+
+    /// Hex-encoded secret shared with the stash daemon relays, which
+    /// authenticates the relayed consignments; required if `relay-peer` is
+    /// given
+    #[clap(long, env = "RGB_FUNGIBLED_RELAY_KEY")]
+    pub relay_key: Option<String>,
+
+    /// Webhooks receiving asset events, as `<event>=<url>`, where event is
+    /// one of `transfer-confirmed`, `consignment-received` or
+    /// `allocation-spent` and url is `https://<host>[:<port>][/<path>]`
+    #[clap(
+        long = "webhook",
+        env = "RGB_FUNGIBLED_WEBHOOKS",
+        use_delimiter = true
+    )]
+    pub webhooks: Vec<String>,
+
+    /// Secret for signing the webhook payloads; required if any webhooks are
+    /// given
+    #[clap(long, env = "RGB_FUNGIBLED_WEBHOOK_SECRET")]
+    pub webhook_secret: Option<String>,
+
+    /// Directory or S3 bucket URL, as
+    /// `s3://<host>[:<port>]/<bucket>[/<prefix>][?region=<region>]`, where
+    /// cache and stash are backed up periodically; the stash daemon must
+    /// have access to it as well [default: no backups]
+    #[clap(long, env = "RGB_FUNGIBLED_BACKUP")]
+    pub backup: Option<String>,
+
+    /// Interval (in seconds) between the scheduled backups [default: 1 day]
+    #[clap(long, env = "RGB_FUNGIBLED_BACKUP_INTERVAL")]
+    pub backup_interval: Option<u32>,
+
+    /// Number of the latest backups kept; older backups are removed
+    /// [default: 7]
+    #[clap(long, env = "RGB_FUNGIBLED_BACKUP_KEEP")]
+    pub backup_keep: Option<u32>,
+
+    /// Maximum number of RPC requests per minute, not including expensive
+    /// ones; requests over the limit are rejected. Each account has its own
+    /// quota, while the requests not signed by an account share a single
+    /// one [default: not limited]
+    #[clap(long, env = "RGB_FUNGIBLED_RATE_LIMIT")]
+    pub rate_limit: Option<u32>,
+
+    /// Maximum number of expensive RPC requests (consignment validation and
+    /// acceptance, cache sync) per minute [default: not limited]
+    #[clap(long, env = "RGB_FUNGIBLED_EXPENSIVE_RATE_LIMIT")]
+    pub expensive_rate_limit: Option<u32>,
+
+    /// Amount of memory (in bytes) the assets kept in the cache may take;
+    /// least recently used assets over it are moved to disk and read back
+    /// on access [default: not limited]
+    #[clap(long, env = "RGB_FUNGIBLED_CACHE_MEMORY")]
+    pub cache_memory: Option<u64>,
+
+    /// Construct asset geneses deterministically, with fixed timestamp and
+    /// blinding factors derived from the seed; intended for integration tests
+    /// and not allowed on mainnet
+    #[clap(long)]
+    pub deterministic: bool,
+
+    /// Genesis timestamp used in deterministic mode; must not be less than
+    /// 1602340666 required by RGB20 schema [default: 1602340666]
+    #[clap(long, env = "RGB_FUNGIBLED_GENESIS_TIMESTAMP")]
+    pub genesis_timestamp: Option<i64>,
+
+    /// Seed for the blinding factors used in deterministic mode [default: 0]
+    #[clap(long, env = "RGB_FUNGIBLED_BLINDING_SEED")]
+    pub blinding_seed: Option<u64>,
+
+    /// Address for the HTTP health endpoint reporting daemon readiness; the
+    /// endpoint is not started if the address is not given
+    #[clap(long = "health", env = "RGB_FUNGIBLED_HEALTH")]
+    pub health_endpoint: Option<SocketAddr>,
+
+    /// Serve only the queries (assets, allocations and sync requests) from
+    /// the replicated or shared cache, rejecting state-changing requests;
+    /// used for scaling out query traffic
+    #[clap(long)]
+    pub read_only: bool,
+
+    /// RPC endpoint of the primary node, reported to the clients whose
+    /// state-changing requests are rejected in read-only mode
+    #[clap(long, env = "RGB_FUNGIBLED_PRIMARY")]
+    pub primary: Option<String>,
+
+    /// Log target: `stderr`, `syslog`, `journald` or `json:<path>` for a
+    /// file with one JSON object per log record
+    #[clap(long, default_value = "stderr", env = "RGB_LOG")]
+    pub log: LogTarget,
+
+    /// Per-module log levels overriding the verbosity, as comma-separated
+    /// `<module>=<level>` directives (like `rgb_node::fungibled=debug`)
+    #[clap(long, env = "RGB_LOG_FILTER")]
+    pub log_filter: Option<LogFilter>,
+
+    /// Run in background, detached from the terminal
+    #[clap(long)]
+    pub daemon: bool,
+
+    /// Path to the file receiving id of the daemon process
+    #[clap(long, env = "RGB_FUNGIBLED_PID_FILE")]
+    pub pid_file: Option<String>,
+
+    /// Confine the daemon to the data directory, making it the filesystem
+    /// root (requires root privileges); all of the files used by the daemon
+    /// must be located in the data directory
+    #[clap(long)]
+    pub chroot: bool,
+
+    /// Unprivileged user the daemon switches to once it has bound its
+    /// sockets
+    #[clap(long, env = "RGB_USER")]
+    pub user: Option<String>,
+
+    /// Path to the configuration file; tunable settings are re-read from it
+    /// on SIGHUP or `reload-config` request [default:
+    /// {data_dir}/rgb-node.toml, if exists]
+    #[clap(long = "config", env = "RGB_CONFIG")]
+    pub config_file: Option<String>,
+
+    /// Print effective configuration in the configuration file format and
+    /// exit
+    #[clap(long)]
+    pub dump_config: bool,
+}
+
+#[cfg(feature = "node")]
+impl LayeredOpts for Opts {
+    const DAEMON: &'static str = "fungibled";
+    const KEYS: &'static [(&'static str, &'static str)] = &[
+        ("data-dir", "RGB_DATA_DIR"),
+        ("log", "RGB_LOG"),
+        ("log-filter", "RGB_LOG_FILTER"),
+        ("pid-file", "RGB_FUNGIBLED_PID_FILE"),
+        ("user", "RGB_USER"),
+        ("cache", "RGB_FUNGIBLED_CACHE"),
+        ("format", "RGB_FUNGIBLED_FORMAT"),
+        ("rpc", "RGB_FUNGIBLED_RPC"),
+        ("pub", "RGB_FUNGIBLED_PUB"),
+        ("stash-rpc", "RGB_STASHD_RPC"),
+        ("network", "RGB_NETWORK"),
+        ("supply-cap", "RGB_FUNGIBLED_SUPPLY_CAP"),
+        ("dust-limit", "RGB_FUNGIBLED_DUST_LIMIT"),
+        ("anchor-container", "RGB_FUNGIBLED_ANCHOR_CONTAINER"),
+        ("policy", "RGB_FUNGIBLED_POLICY"),
+        ("electrum", "RGB_ELECTRUM_SERVER"),
+        ("descriptor", "RGB_FUNGIBLED_DESCRIPTORS"),
+        ("signer", "RGB_FUNGIBLED_SIGNER"),
+        ("approval-threshold", "RGB_FUNGIBLED_APPROVAL_THRESHOLD"),
+        ("approver", "RGB_FUNGIBLED_APPROVERS"),
+        ("admin", "RGB_FUNGIBLED_ADMINS"),
+        ("account", "RGB_FUNGIBLED_ACCOUNTS"),
+        ("reservation-timeout", "RGB_FUNGIBLED_RESERVATION_TIMEOUT"),
+        ("tombstone-days", "RGB_FUNGIBLED_TOMBSTONE_DAYS"),
+        (
+            "consignment-retention-days",
+            "RGB_FUNGIBLED_CONSIGNMENT_RETENTION_DAYS",
+        ),
+        ("watch-interval", "RGB_FUNGIBLED_WATCH_INTERVAL"),
+        ("min-confirmations", "RGB_FUNGIBLED_MIN_CONFIRMATIONS"),
+        ("subscribe", "RGB_FUNGIBLED_SUBSCRIPTIONS"),
+        ("relay-peer", "RGB_FUNGIBLED_RELAY_PEERS"),
+        ("relay-key", "RGB_FUNGIBLED_RELAY_KEY"),
+        ("webhook", "RGB_FUNGIBLED_WEBHOOKS"),
+        ("webhook-secret", "RGB_FUNGIBLED_WEBHOOK_SECRET"),
+        ("backup", "RGB_FUNGIBLED_BACKUP"),
+        ("backup-interval", "RGB_FUNGIBLED_BACKUP_INTERVAL"),
+        ("backup-keep", "RGB_FUNGIBLED_BACKUP_KEEP"),
+        ("rate-limit", "RGB_FUNGIBLED_RATE_LIMIT"),
+        ("expensive-rate-limit", "RGB_FUNGIBLED_EXPENSIVE_RATE_LIMIT"),
+        ("cache-memory", "RGB_FUNGIBLED_CACHE_MEMORY"),
+        ("genesis-timestamp", "RGB_FUNGIBLED_GENESIS_TIMESTAMP"),
+        ("blinding-seed", "RGB_FUNGIBLED_BLINDING_SEED"),
+        ("health", "RGB_FUNGIBLED_HEALTH"),
+        ("primary", "RGB_FUNGIBLED_PRIMARY"),
+    ];
+
+    fn data_dir(&self) -> &str {
+        &self.data_dir
+    }
+
+    fn config_file(&self) -> Option<&str> {
+        self.config_file.as_deref()
+    }
+
+    fn verbose_mut(&mut self) -> &mut u8 {
+        &mut self.verbose
+    }
+}
+
+/// Settings which can be changed without restarting the daemon; connection
+/// endpoints, network and storage settings are not among them. Absent
+/// fields keep their current values.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Deserialize),
+    serde(crate = "serde_crate", rename_all = "kebab-case")
+)]
+pub struct Tunables {
+    pub verbose: Option<u8>,
+    pub log_filter: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "electrum"))]
+    pub electrum_server: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "As::<Option<AtomicValueString>>")
+    )]
+    pub supply_cap: Option<AtomicValue>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "As::<Option<AtomicValueString>>")
+    )]
+    pub dust_limit: Option<AtomicValue>,
+    pub anchor_container: Option<String>,
+    pub policy: Option<String>,
+    pub signer: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "As::<Option<AtomicValueString>>")
+    )]
+    pub approval_threshold: Option<AtomicValue>,
+    #[cfg_attr(feature = "serde", serde(rename = "approver"))]
+    pub approvers: Option<Vec<String>>,
+    #[cfg_attr(feature = "serde", serde(rename = "admin"))]
+    pub admins: Option<Vec<String>>,
+    #[cfg_attr(feature = "serde", serde(rename = "account"))]
+    pub accounts: Option<Vec<String>>,
+    pub reservation_timeout: Option<u32>,
+    pub tombstone_days: Option<u32>,
+    pub consignment_retention_days: Option<u32>,
+    pub watch_interval: Option<u32>,
+    pub min_confirmations: Option<u32>,
+    pub backup_interval: Option<u32>,
+    pub backup_keep: Option<u32>,
+    pub rate_limit: Option<u32>,
+    pub expensive_rate_limit: Option<u32>,
+    pub cache_memory: Option<u64>,
 }
 
 // We need config structure since not all of the parameters can be specified
@@ -74,25 +475,154 @@ pub struct Opts {
 #[display(Debug)]
 pub struct Config {
     pub verbose: u8,
+    pub log: LogTarget,
+    pub log_filter: LogFilter,
+    pub user: Option<String>,
+    /// User resolved before the daemon was confined to the data directory
+    #[cfg(feature = "server")]
+    pub credentials: Option<Credentials>,
     pub data_dir: PathBuf,
     pub cache: String,
-    pub format: FileFormat,
+    pub format: DataFormat,
     pub rpc_endpoint: ZmqSocketAddr,
+    pub pub_endpoint: ZmqSocketAddr,
     pub stash_rpc: ZmqSocketAddr,
     pub network: Chain,
+    pub supply_cap: Option<AtomicValue>,
+    pub dust_limit: Option<AtomicValue>,
+    pub anchor_container: AnchorContainer,
+    pub policy: Option<PathBuf>,
+    pub electrum_server: String,
+    pub descriptors: Vec<Descriptor>,
+    pub signer: Option<Signer>,
+    pub approval_threshold: Option<AtomicValue>,
+    pub approvers: Vec<PublicKey>,
+    pub admins: Vec<PublicKey>,
+    pub accounts: Vec<PublicKey>,
+    pub reservation_timeout: u32,
+    pub tombstone_days: u32,
+    pub consignment_retention_days: u32,
+    pub watch_interval: u32,
+    pub min_confirmations: u32,
+    pub subscriptions: Vec<Subscription>,
+    pub relay_peers: Vec<ZmqSocketAddr>,
+    pub relay_key: Option<RelayKey>,
+    pub webhooks: Vec<Webhook>,
+    pub webhook_secret: Option<String>,
+    pub backup: Option<BackupTarget>,
+    pub backup_interval: u32,
+    pub backup_keep: u32,
+    pub rate_limit: Option<u32>,
+    pub expensive_rate_limit: Option<u32>,
+    pub cache_memory: Option<u64>,
+    pub deterministic: Option<GenesisSeed>,
+    pub health_endpoint: Option<SocketAddr>,
+    pub read_only: bool,
+    pub primary: Option<String>,
+    pub config_file: Option<PathBuf>,
 }
 
 impl From<Opts> for Config {
     fn from(opts: Opts) -> Self {
         let mut me = Self {
             verbose: opts.verbose,
+            log: opts.log,
+            log_filter: opts.log_filter.unwrap_or_default(),
+            user: opts.user,
+            #[cfg(feature = "server")]
+            credentials: None,
             network: opts.network,
+            supply_cap: opts.supply_cap,
+            dust_limit: opts.dust_limit,
+            anchor_container: opts.anchor_container.unwrap_or_default(),
+            approval_threshold: opts.approval_threshold,
+            rate_limit: opts.rate_limit,
+            expensive_rate_limit: opts.expensive_rate_limit,
+            cache_memory: opts.cache_memory,
+            health_endpoint: opts.health_endpoint,
+            read_only: opts.read_only,
+            webhook_secret: opts.webhook_secret,
             ..Config::default()
         };
         me.data_dir = me.parse_param(opts.data_dir);
         me.cache = me.parse_param(opts.cache);
         me.rpc_endpoint = me.parse_param(opts.rpc_endpoint);
+        me.pub_endpoint = me.parse_param(opts.pub_endpoint);
         me.stash_rpc = me.parse_param(opts.stash_rpc);
+        me.policy = opts.policy.map(|policy| me.parse_param(policy));
+        me.electrum_server = match opts.electrum_server {
+            Some(server) => me.parse_param(server),
+            None => default_electrum_endpoint(&me.network).to_string(),
+        };
+        me.descriptors = opts
+            .descriptors
+            .into_iter()
+            .map(|descriptor| me.parse_param(descriptor))
+            .collect();
+        me.signer = opts.signer.map(|signer| me.parse_param(signer));
+        me.primary = opts.primary.map(|primary| me.parse_param(primary));
+        me.approvers = opts
+            .approvers
+            .into_iter()
+            .map(|approver| me.parse_param(approver))
+            .collect();
+        me.admins = opts
+            .admins
+            .into_iter()
+            .map(|admin| me.parse_param(admin))
+            .collect();
+        me.accounts = opts
+            .accounts
+            .into_iter()
+            .map(|account| me.parse_param(account))
+            .collect();
+        me.subscriptions = opts
+            .subscriptions
+            .into_iter()
+            .map(|subscription| me.parse_param(subscription))
+            .collect();
+        me.relay_peers = opts
+            .relay_peers
+            .into_iter()
+            .map(|peer| me.parse_param(peer))
+            .collect();
+        me.relay_key = opts.relay_key.map(|key| me.parse_param(key));
+        me.webhooks = opts
+            .webhooks
+            .into_iter()
+            .map(|webhook| me.parse_param(webhook))
+            .collect();
+        me.backup = opts.backup.map(|target| me.parse_param(target));
+        me.config_file = opts.config_file.map(|path| me.parse_param(path));
+        if let Some(timeout) = opts.reservation_timeout {
+            me.reservation_timeout = timeout;
+        }
+        if let Some(days) = opts.tombstone_days {
+            me.tombstone_days = days;
+        }
+        if let Some(days) = opts.consignment_retention_days {
+            me.consignment_retention_days = days;
+        }
+        if let Some(interval) = opts.watch_interval {
+            me.watch_interval = interval;
+        }
+        if let Some(interval) = opts.backup_interval {
+            me.backup_interval = interval;
+        }
+        if let Some(keep) = opts.backup_keep {
+            me.backup_keep = keep;
+        }
+        if let Some(confirmations) = opts.min_confirmations {
+            me.min_confirmations = confirmations;
+        }
+        if opts.deterministic {
+            me.deterministic = Some(GenesisSeed {
+                timestamp: opts
+                    .genesis_timestamp
+                    .unwrap_or(FUNGIBLED_GENESIS_TIMESTAMP),
+                blinding_seed: opts.blinding_seed.unwrap_or_default(),
+            });
+        }
         me
     }
 }
@@ -101,28 +631,308 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             verbose: 0,
+            log: LogTarget::Stderr,
+            log_filter: LogFilter::default(),
+            user: None,
+            #[cfg(feature = "server")]
+            credentials: None,
             data_dir: RGB_DATA_DIR
                 .parse()
                 .expect("Error in RGB_DATA_DIR constant value"),
             cache: FUNGIBLED_CACHE.to_string(),
             #[cfg(feature = "serde_yaml")]
-            format: FileFormat::Yaml,
+            format: DataFormat::Yaml,
             #[cfg(not(feature = "serde"))]
-            format: FileFormat::StrictEncode,
+            format: DataFormat::StrictEncode,
             rpc_endpoint: FUNGIBLED_RPC_ENDPOINT
                 .parse()
                 .expect("Error in FUNGIBLED_RPC_ENDPOINT constant value"),
+            pub_endpoint: FUNGIBLED_PUB_ENDPOINT
+                .parse()
+                .expect("Error in FUNGIBLED_PUB_ENDPOINT constant value"),
             stash_rpc: STASHD_RPC_ENDPOINT
                 .parse()
                 .expect("Error in STASHD_RPC_ENDPOINT constant value"),
             network: RGB_NETWORK
                 .parse()
                 .expect("Error in RGB_NETWORK constant value"),
+            supply_cap: None,
+            dust_limit: None,
+            anchor_container: AnchorContainer::default(),
+            policy: None,
+            electrum_server: DEFAULT_ELECTRUM_ENDPOINT
+                .parse()
+                .expect("Error in DEFAULT_ELECTRUM_ENDPOINT constant value"),
+            descriptors: vec![],
+            signer: None,
+            approval_threshold: None,
+            approvers: vec![],
+            admins: vec![],
+            accounts: vec![],
+            reservation_timeout: FUNGIBLED_RESERVATION_TIMEOUT,
+            tombstone_days: FUNGIBLED_TOMBSTONE_DAYS,
+            consignment_retention_days: FUNGIBLED_CONSIGNMENT_RETENTION_DAYS,
+            watch_interval: FUNGIBLED_WATCH_INTERVAL,
+            min_confirmations: 0,
+            subscriptions: vec![],
+            relay_peers: vec![],
+            relay_key: None,
+            webhooks: vec![],
+            webhook_secret: None,
+            backup: None,
+            backup_interval: FUNGIBLED_BACKUP_INTERVAL,
+            backup_keep: FUNGIBLED_BACKUP_KEEP,
+            rate_limit: None,
+            expensive_rate_limit: None,
+            cache_memory: None,
+            deterministic: None,
+            health_endpoint: None,
+            read_only: false,
+            primary: None,
+            config_file: None,
         }
     }
 }
 
 impl Config {
+    /// Unprivileged user the daemon switches to, unless it was resolved
+    /// before confinement
+    #[cfg(feature = "server")]
+    pub fn credentials(&self) -> Result<Option<Credentials>, LifecycleError> {
+        match self.credentials {
+            Some(ref credentials) => Ok(Some(credentials.clone())),
+            None => self.user.as_deref().map(Credentials::resolve).transpose(),
+        }
+    }
+
+    /// Effective configuration with the configuration file keys
+    #[cfg(feature = "node")]
+    pub fn dump(&self) -> toml::value::Table {
+        let mut table = toml::value::Table::new();
+        table.insert(s!("verbose"), config::integer(self.verbose));
+        table.insert(s!("log"), config::value(&self.log));
+        if !self.log_filter.is_empty() {
+            table.insert(s!("log-filter"), config::value(&self.log_filter));
+        }
+        if let Some(ref user) = self.user {
+            table.insert(s!("user"), config::value(user));
+        }
+        table.insert(s!("data-dir"), config::value(self.data_dir.display()));
+        table.insert(s!("cache"), config::value(&self.cache));
+        table.insert(s!("format"), config::value(self.format));
+        table.insert(s!("rpc"), config::value(&self.rpc_endpoint));
+        table.insert(s!("pub"), config::value(&self.pub_endpoint));
+        table.insert(s!("stash-rpc"), config::value(&self.stash_rpc));
+        table.insert(s!("network"), config::value(&self.network));
+        if let Some(cap) = self.supply_cap {
+            table.insert(s!("supply-cap"), config::integer(cap));
+        }
+        if let Some(limit) = self.dust_limit {
+            table.insert(s!("dust-limit"), config::integer(limit));
+        }
+        table.insert(
+            s!("anchor-container"),
+            config::value(self.anchor_container),
+        );
+        if let Some(ref policy) = self.policy {
+            table.insert(s!("policy"), config::value(policy.display()));
+        }
+        table.insert(s!("electrum"), config::value(&self.electrum_server));
+        if !self.descriptors.is_empty() {
+            table.insert(
+                s!("descriptor"),
+                toml::Value::Array(
+                    self.descriptors.iter().map(config::value).collect(),
+                ),
+            );
+        }
+        if let Some(ref signer) = self.signer {
+            table.insert(s!("signer"), config::value(signer));
+        }
+        if let Some(threshold) = self.approval_threshold {
+            table.insert(s!("approval-threshold"), config::integer(threshold));
+        }
+        if !self.approvers.is_empty() {
+            table.insert(
+                s!("approver"),
+                toml::Value::Array(
+                    self.approvers.iter().map(config::value).collect(),
+                ),
+            );
+        }
+        if !self.admins.is_empty() {
+            table.insert(
+                s!("admin"),
+                toml::Value::Array(
+                    self.admins.iter().map(config::value).collect(),
+                ),
+            );
+        }
+        if !self.accounts.is_empty() {
+            table.insert(
+                s!("account"),
+                toml::Value::Array(
+                    self.accounts.iter().map(config::value).collect(),
+                ),
+            );
+        }
+        table.insert(
+            s!("reservation-timeout"),
+            config::integer(self.reservation_timeout),
+        );
+        table
+            .insert(s!("tombstone-days"), config::integer(self.tombstone_days));
+        table.insert(
+            s!("consignment-retention-days"),
+            config::integer(self.consignment_retention_days),
+        );
+        table
+            .insert(s!("watch-interval"), config::integer(self.watch_interval));
+        table.insert(
+            s!("min-confirmations"),
+            config::integer(self.min_confirmations),
+        );
+        if !self.subscriptions.is_empty() {
+            table.insert(
+                s!("subscribe"),
+                toml::Value::Array(
+                    self.subscriptions.iter().map(config::value).collect(),
+                ),
+            );
+        }
+        if !self.relay_peers.is_empty() {
+            table.insert(
+                s!("relay-peer"),
+                toml::Value::Array(
+                    self.relay_peers.iter().map(config::value).collect(),
+                ),
+            );
+        }
+        // Relay key and webhook secret are not dumped, since the dump may be
+        // shared
+        if !self.webhooks.is_empty() {
+            table.insert(
+                s!("webhook"),
+                toml::Value::Array(
+                    self.webhooks.iter().map(config::value).collect(),
+                ),
+            );
+        }
+        if let Some(ref target) = self.backup {
+            table.insert(s!("backup"), config::value(target));
+        }
+        table.insert(
+            s!("backup-interval"),
+            config::integer(self.backup_interval),
+        );
+        table.insert(s!("backup-keep"), config::integer(self.backup_keep));
+        if let Some(limit) = self.rate_limit {
+            table.insert(s!("rate-limit"), config::integer(limit));
+        }
+        if let Some(limit) = self.expensive_rate_limit {
+            table.insert(s!("expensive-rate-limit"), config::integer(limit));
+        }
+        if let Some(budget) = self.cache_memory {
+            table.insert(s!("cache-memory"), config::integer(budget));
+        }
+        if let Some(seed) = self.deterministic {
+            table.insert(
+                s!("genesis-timestamp"),
+                toml::Value::Integer(seed.timestamp),
+            );
+            table.insert(
+                s!("blinding-seed"),
+                config::integer(seed.blinding_seed),
+            );
+        }
+        if let Some(addr) = self.health_endpoint {
+            table.insert(s!("health"), config::value(addr));
+        }
+        if let Some(ref primary) = self.primary {
+            table.insert(s!("primary"), config::value(primary));
+        }
+        table
+    }
+
+    /// Updates tunable settings with the values from the configuration file
+    pub fn apply(&mut self, tunables: Tunables) {
+        if let Some(verbose) = tunables.verbose {
+            self.verbose = verbose;
+        }
+        if let Some(filter) = tunables.log_filter {
+            self.log_filter = self.parse_param(filter);
+        }
+        if let Some(server) = tunables.electrum_server {
+            self.electrum_server = self.parse_param(server);
+        }
+        if let Some(cap) = tunables.supply_cap {
+            self.supply_cap = Some(cap);
+        }
+        if let Some(limit) = tunables.dust_limit {
+            self.dust_limit = Some(limit);
+        }
+        if let Some(container) = tunables.anchor_container {
+            self.anchor_container = self.parse_param(container);
+        }
+        if let Some(policy) = tunables.policy {
+            self.policy = Some(self.parse_param(policy));
+        }
+        if let Some(signer) = tunables.signer {
+            self.signer = Some(self.parse_param(signer));
+        }
+        if let Some(threshold) = tunables.approval_threshold {
+            self.approval_threshold = Some(threshold);
+        }
+        if let Some(approvers) = tunables.approvers {
+            self.approvers = approvers
+                .into_iter()
+                .map(|approver| self.parse_param(approver))
+                .collect();
+        }
+        if let Some(admins) = tunables.admins {
+            self.admins = admins
+                .into_iter()
+                .map(|admin| self.parse_param(admin))
+                .collect();
+        }
+        if let Some(accounts) = tunables.accounts {
+            self.accounts = accounts
+                .into_iter()
+                .map(|account| self.parse_param(account))
+                .collect();
+        }
+        if let Some(timeout) = tunables.reservation_timeout {
+            self.reservation_timeout = timeout;
+        }
+        if let Some(days) = tunables.tombstone_days {
+            self.tombstone_days = days;
+        }
+        if let Some(days) = tunables.consignment_retention_days {
+            self.consignment_retention_days = days;
+        }
+        if let Some(interval) = tunables.watch_interval {
+            self.watch_interval = interval;
+        }
+        if let Some(interval) = tunables.backup_interval {
+            self.backup_interval = interval;
+        }
+        if let Some(keep) = tunables.backup_keep {
+            self.backup_keep = keep;
+        }
+        if let Some(confirmations) = tunables.min_confirmations {
+            self.min_confirmations = confirmations;
+        }
+        if let Some(limit) = tunables.rate_limit {
+            self.rate_limit = Some(limit);
+        }
+        if let Some(limit) = tunables.expensive_rate_limit {
+            self.expensive_rate_limit = Some(limit);
+        }
+        if let Some(budget) = tunables.cache_memory {
+            self.cache_memory = Some(budget);
+        }
+    }
+
     pub fn parse_param<T>(&self, param: String) -> T
     where
         T: FromStr,
@@ -0,0 +1,323 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Deterministic version of the RGB20 issue procedure for integration tests.
+//! Genesis timestamp and all blinding factors are taken from the node
+//! configuration, so the same issue parameters always produce the same
+//! contract id.
+//!
+//! The same procedure, with a random seed, is used for geneses which can't be
+//! constructed by the RGB20 schema processor, i.e. assigning assets to blinded
+//! outpoints or witness transaction outputs.
+
+use core::convert::TryFrom;
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::rand::{thread_rng, RngCore};
+use bitcoin::OutPoint;
+use lnpbp::seals::OutpointReveal;
+use lnpbp::Chain;
+use rgb::secp256k1zkp::{self, ContextFlag, Secp256k1};
+use rgb::{data, seal, value, Assignments, AtomicValue, Genesis, OwnedState};
+use rgb20::schema::{self, FieldType, OwnedRightsType};
+use rgb20::Asset;
+
+use crate::rpc::fungible::IssueSeal;
+
+/// Parameters of the deterministic genesis construction
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GenesisSeed {
+    /// Unix timestamp put into all created geneses
+    pub timestamp: i64,
+
+    /// Seed from which seal and amount blinding factors are derived
+    pub blinding_seed: u64,
+}
+
+impl GenesisSeed {
+    /// Seed with the current timestamp and random blinding seed. Since all
+    /// genesis assignments are revealed, the blinding factors derived from it
+    /// do not need to be kept secret.
+    pub fn random() -> Self {
+        Self {
+            timestamp: chrono::Utc::now().timestamp(),
+            blinding_seed: thread_rng().next_u64(),
+        }
+    }
+}
+
+/// Source of the blinding factors derived from the seed
+struct Blinder {
+    seed: u64,
+    counter: u32,
+    secp: Secp256k1,
+}
+
+impl Blinder {
+    fn with(seed: u64) -> Self {
+        Self {
+            seed,
+            counter: 0,
+            secp: Secp256k1::with_caps(ContextFlag::Commit),
+        }
+    }
+
+    fn next_hash(&mut self) -> sha256::Hash {
+        let mut data = self.seed.to_le_bytes().to_vec();
+        data.extend(&self.counter.to_le_bytes());
+        self.counter += 1;
+        sha256::Hash::hash(&data)
+    }
+
+    fn blinding(&mut self) -> u64 {
+        let mut blinding = [0u8; 8];
+        blinding.copy_from_slice(&self.next_hash()[..8]);
+        u64::from_le_bytes(blinding)
+    }
+
+    fn seal(&mut self, outpoint: OutPoint) -> seal::Revealed {
+        seal::Revealed::TxOutpoint(OutpointReveal {
+            blinding: self.blinding(),
+            txid: outpoint.txid,
+            vout: outpoint.vout,
+        })
+    }
+
+    fn secret(&mut self) -> secp256k1zkp::SecretKey {
+        let hash = self.next_hash();
+        secp256k1zkp::SecretKey::from_slice(&self.secp, &hash[..]).expect(
+            "hash is not a valid secret key with negligible probability",
+        )
+    }
+
+    /// Creates amount assignments with blinding factors summing up to the
+    /// blinding factor of the issued supply, in the same way as it is done by
+    /// `Assignments::zero_balanced`
+    fn amounts(
+        &mut self,
+        allocation: Vec<(IssueSeal, AtomicValue)>,
+    ) -> Assignments {
+        let mut blindings = (1..allocation.len())
+            .map(|_| self.secret())
+            .collect::<Vec<_>>();
+        if !allocation.is_empty() {
+            blindings.push(
+                self.secp
+                    .blind_sum(
+                        vec![secp256k1zkp::key::ONE_KEY],
+                        blindings.clone(),
+                    )
+                    .expect("SECP256K1_ZKP failure has negligible probability"),
+            );
+        }
+        Assignments::DiscreteFiniteField(
+            allocation
+                .into_iter()
+                .zip(blindings)
+                .map(|((seal, value), blinding)| {
+                    let assigned_state = value::Revealed {
+                        value,
+                        blinding: blinding.into(),
+                    };
+                    match seal {
+                        IssueSeal::Revealed(outpoint) => OwnedState::Revealed {
+                            seal_definition: self.seal(outpoint),
+                            assigned_state,
+                        },
+                        IssueSeal::Concealed(seal_definition) => {
+                            OwnedState::ConfidentialSeal {
+                                seal_definition,
+                                assigned_state,
+                            }
+                        }
+                        IssueSeal::WitnessVout(vout) => OwnedState::Revealed {
+                            seal_definition: seal::Revealed::WitnessVout {
+                                vout,
+                                blinding: self.blinding(),
+                            },
+                            assigned_state,
+                        },
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Deterministic equivalent of [`rgb20::issue`]
+pub fn issue(
+    seed: GenesisSeed,
+    chain: Chain,
+    ticker: String,
+    name: String,
+    description: Option<String>,
+    precision: u8,
+    allocation: Vec<(IssueSeal, AtomicValue)>,
+    inflation: BTreeMap<OutPoint, AtomicValue>,
+    renomination: Option<OutPoint>,
+    epoch: Option<OutPoint>,
+) -> Result<(Asset, Genesis), rgb20::Error> {
+    let mut blinder = Blinder::with(seed.blinding_seed);
+    let issued_supply = allocation.iter().map(|(_, value)| value).sum();
+
+    let mut metadata = BTreeMap::<usize, BTreeSet<data::Revealed>>::new();
+    metadata.insert(
+        *FieldType::Ticker,
+        bset![data::Revealed::String(ticker.to_uppercase())],
+    );
+    metadata.insert(*FieldType::Name, bset![data::Revealed::String(name)]);
+    metadata
+        .insert(*FieldType::Precision, bset![data::Revealed::U8(precision)]);
+    metadata.insert(
+        *FieldType::Timestamp,
+        bset![data::Revealed::I64(seed.timestamp)],
+    );
+    if let Some(description) = description {
+        metadata.insert(
+            *FieldType::ContractText,
+            bset![data::Revealed::String(description)],
+        );
+    }
+    metadata.insert(
+        *FieldType::IssuedSupply,
+        bset![data::Revealed::U64(issued_supply)],
+    );
+
+    let mut owned_rights = BTreeMap::new();
+    owned_rights.insert(*OwnedRightsType::Assets, blinder.amounts(allocation));
+    if !inflation.is_empty() {
+        owned_rights.insert(
+            *OwnedRightsType::Inflation,
+            Assignments::CustomData(
+                inflation
+                    .into_iter()
+                    .map(|(outpoint, value)| OwnedState::Revealed {
+                        seal_definition: blinder.seal(outpoint),
+                        assigned_state: data::Revealed::U64(value),
+                    })
+                    .collect(),
+            ),
+        );
+    }
+    if let Some(outpoint) = renomination {
+        owned_rights.insert(
+            *OwnedRightsType::Renomination,
+            Assignments::Declarative(vec![OwnedState::Revealed {
+                seal_definition: blinder.seal(outpoint),
+                assigned_state: data::Void,
+            }]),
+        );
+    }
+    if let Some(outpoint) = epoch {
+        owned_rights.insert(
+            *OwnedRightsType::BurnReplace,
+            Assignments::Declarative(vec![OwnedState::Revealed {
+                seal_definition: blinder.seal(outpoint),
+                assigned_state: data::Void,
+            }]),
+        );
+    }
+
+    let genesis = Genesis::with(
+        schema::schema().schema_id(),
+        chain,
+        metadata.into(),
+        owned_rights,
+        bset![],
+        vec![],
+    );
+    let asset = Asset::try_from(genesis.clone())?;
+
+    Ok((asset, genesis))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rgb::Validity;
+
+    use crate::constants::FUNGIBLED_GENESIS_TIMESTAMP;
+
+    fn issue_with(blinding_seed: u64) -> Genesis {
+        let outpoint = |vout| OutPoint {
+            txid: Default::default(),
+            vout,
+        };
+        let seed = GenesisSeed {
+            timestamp: FUNGIBLED_GENESIS_TIMESTAMP,
+            blinding_seed,
+        };
+        let (_, genesis) = issue(
+            seed,
+            Chain::Regtest(bitcoin::BlockHash::default()),
+            s!("TEST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![
+                (IssueSeal::Revealed(outpoint(0)), 100),
+                (IssueSeal::Revealed(outpoint(1)), 200),
+                (IssueSeal::Revealed(outpoint(2)), 300),
+            ],
+            bmap! { outpoint(3) => 1000 },
+            Some(outpoint(4)),
+            None,
+        )
+        .unwrap();
+        genesis
+    }
+
+    #[test]
+    fn test_deterministic_contract_id() {
+        assert_eq!(issue_with(0).contract_id(), issue_with(0).contract_id());
+        assert_ne!(issue_with(0).contract_id(), issue_with(1).contract_id());
+    }
+
+    #[test]
+    fn test_deterministic_genesis_validity() {
+        let genesis = issue_with(0);
+        let status = schema::schema().validate(&bmap! {}, &genesis);
+        assert_eq!(status.validity(), Validity::Valid);
+    }
+
+    #[test]
+    fn test_concealed_genesis_validity() {
+        let outpoint = OutPoint::default();
+        let (asset, genesis) = issue(
+            GenesisSeed::random(),
+            Chain::Regtest(bitcoin::BlockHash::default()),
+            s!("TEST"),
+            s!("Test asset"),
+            None,
+            0,
+            vec![
+                (IssueSeal::Revealed(outpoint), 100),
+                (
+                    IssueSeal::Concealed(
+                        OutpointReveal::from(outpoint).outpoint_hash(),
+                    ),
+                    200,
+                ),
+                (IssueSeal::WitnessVout(0), 300),
+            ],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let status = schema::schema().validate(&bmap! {}, &genesis);
+        assert_eq!(status.validity(), Validity::Valid);
+        assert_eq!(asset.known_allocations().len(), 1);
+    }
+}
@@ -0,0 +1,182 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Seals of the RGB20 epoch-based burn and replacement procedure: lookup of
+//! the unspent epoch and burn-and-replace seals in the contract history and
+//! construction of the epoch-opening state transitions.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::{OutPoint, Txid};
+use rgb::{
+    data, seal, Assignments, Disclosure, Genesis, Node, OwnedState,
+    SealDefinition, Transition,
+};
+use rgb20::schema::{OwnedRightsType, TransitionType};
+
+use crate::rpc::reply::{EpochRight, EpochSeal};
+
+const RIGHTS: [(EpochRight, OwnedRightsType); 2] = [
+    (EpochRight::Epoch, OwnedRightsType::Epoch),
+    (EpochRight::BurnReplace, OwnedRightsType::BurnReplace),
+];
+
+/// Returns epoch and burn-and-replace seals assigned by the contract genesis
+/// or its state transitions which are not closed by any of the known
+/// transitions
+pub fn seals(genesis: &Genesis, history: &Disclosure) -> Vec<EpochSeal> {
+    let contract_id = genesis.contract_id();
+    let transitions = history
+        .transitions()
+        .values()
+        .filter_map(|(anchor, transitions)| {
+            transitions
+                .get(&contract_id)
+                .map(|transition| (anchor.txid, transition))
+        })
+        .collect::<Vec<_>>();
+
+    let spent = transitions
+        .iter()
+        .flat_map(|(_, transition)| transition.parent_owned_rights().iter())
+        .flat_map(|(node_id, rights)| {
+            rights.iter().flat_map(move |(ty, indexes)| {
+                indexes.iter().map(move |index| (*node_id, *ty, *index))
+            })
+        })
+        .collect::<BTreeSet<_>>();
+
+    let mut seals = assigned(genesis, None);
+    for (txid, transition) in transitions {
+        seals.extend(assigned(transition, Some(txid)));
+    }
+    seals
+        .into_iter()
+        .filter(|seal| {
+            let ty = RIGHTS
+                .iter()
+                .find(|(right, _)| *right == seal.right)
+                .map(|(_, ty)| **ty)
+                .expect("epoch rights list is incomplete");
+            !spent.contains(&(seal.node_id, ty, seal.index))
+        })
+        .collect()
+}
+
+/// Constructs transition opening new epoch by closing the given epoch seal
+pub fn open(
+    epoch: &EpochSeal,
+    next_epoch: Option<SealDefinition>,
+    burn_replace: Option<SealDefinition>,
+) -> Transition {
+    let mut owned_rights = BTreeMap::new();
+    for (ty, seal_definition) in vec![
+        (OwnedRightsType::Epoch, next_epoch),
+        (OwnedRightsType::BurnReplace, burn_replace),
+    ] {
+        if let Some(seal_definition) = seal_definition {
+            owned_rights.insert(
+                *ty,
+                Assignments::Declarative(vec![OwnedState::Revealed {
+                    seal_definition,
+                    assigned_state: data::Void,
+                }]),
+            );
+        }
+    }
+    let parent = bmap! {
+        epoch.node_id => bmap! { *OwnedRightsType::Epoch => vec![epoch.index] }
+    };
+    Transition::with(
+        *TransitionType::Epoch,
+        BTreeMap::<usize, BTreeSet<data::Revealed>>::new().into(),
+        parent,
+        owned_rights,
+        bset![],
+        vec![],
+    )
+}
+
+fn assigned(node: &impl Node, witness: Option<Txid>) -> Vec<EpochSeal> {
+    let node_id = node.node_id();
+    let mut seals = vec![];
+    for (right, ty) in &RIGHTS {
+        let assignments = match node.owned_rights_by_type(**ty) {
+            Some(assignments) => assignments,
+            None => continue,
+        };
+        for index in 0..assignments.len() as u16 {
+            let outpoint = match assignments.seal_definition(index) {
+                Ok(Some(seal::Revealed::TxOutpoint(reveal))) => {
+                    Some(OutPoint::from(reveal))
+                }
+                Ok(Some(seal @ seal::Revealed::WitnessVout { .. })) => witness
+                    .map(|txid| OutPoint::from(seal.outpoint_reveal(txid))),
+                _ => None,
+            };
+            seals.push(EpochSeal {
+                right: *right,
+                node_id,
+                index,
+                outpoint,
+            });
+        }
+    }
+    seals
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lnpbp::seals::OutpointReveal;
+    use lnpbp::Chain;
+
+    #[test]
+    fn test_epoch_seals() {
+        let outpoint = |vout| OutPoint {
+            txid: Default::default(),
+            vout,
+        };
+        let (_, genesis) = rgb20::issue(
+            Chain::Regtest(Default::default()),
+            s!("TEST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 100)],
+            bmap! {},
+            None,
+            Some(outpoint(1)),
+        )
+        .unwrap();
+        let seals = seals(&genesis, &Disclosure::default());
+        assert_eq!(seals.len(), 1);
+        assert_eq!(seals[0].right, EpochRight::BurnReplace);
+        assert_eq!(seals[0].node_id, genesis.node_id());
+        assert_eq!(seals[0].outpoint, Some(outpoint(1)));
+
+        let epoch = EpochSeal {
+            right: EpochRight::Epoch,
+            ..seals[0].clone()
+        };
+        let next =
+            SealDefinition::TxOutpoint(OutpointReveal::from(outpoint(2)));
+        let transition = open(&epoch, Some(next), None);
+        assert_eq!(transition.transition_type(), Some(*TransitionType::Epoch));
+        assert_eq!(
+            transition.parent_owned_rights().get(&genesis.node_id()),
+            Some(&bmap! { *OwnedRightsType::Epoch => vec![0u16] })
+        );
+        assert_eq!(assigned(&transition, None).len(), 1);
+    }
+}
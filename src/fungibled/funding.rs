@@ -0,0 +1,150 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Bitcoin funds of the node used to pay for the witness transactions. The
+//! funding wallet is formed by the outputs of the registered descriptors,
+//! which are scanned with Electrum server; outputs holding assets or
+//! reserved by the transfers and external protocols are locked and are not
+//! used for the fees.
+
+use std::collections::BTreeSet;
+
+use bitcoin::{OutPoint, PublicKey, Script, TxOut};
+use electrum_client::{Client as ElectrumClient, ElectrumApi};
+
+use super::keychain::Keychain;
+use crate::rpc::reply::FundingBalance;
+
+/// Chain of the descriptor keys used for receiving funds
+pub const RECEIVE_CHAIN: u32 = 0;
+
+/// Chain of the descriptor keys used for the change
+pub const CHANGE_CHAIN: u32 = 1;
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum FundingError {
+    /// No descriptors are registered, so the node has no funding wallet
+    NoDescriptors,
+
+    /// All funding addresses below the descriptor gap limit of {0} are
+    /// already used
+    GapLimit(u32),
+
+    /// There are no confirmed funding outputs which are not locked, so the
+    /// assets can't be received without providing an outpoint
+    NoUnlockedOutput,
+}
+
+/// Unspent output of the registered descriptors
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Utxo {
+    pub outpoint: OutPoint,
+    pub txout: TxOut,
+    /// Block height at which the output is mined; zero for unconfirmed
+    /// outputs
+    pub height: usize,
+}
+
+/// Lists unspent outputs of all keys of the keychain
+pub fn scan(
+    electrum: &ElectrumClient,
+    keychain: &Keychain,
+) -> Result<Vec<Utxo>, electrum_client::Error> {
+    let scripts = keychain.scripts().collect::<Vec<_>>();
+    trace!(
+        "Scanning {} descriptor scripts for unspent outputs",
+        scripts.len()
+    );
+    Ok(electrum
+        .batch_script_list_unspent(scripts.iter().copied())?
+        .into_iter()
+        .zip(scripts)
+        .flat_map(|(list, script)| {
+            list.into_iter().map(move |utxo| Utxo {
+                outpoint: OutPoint::new(utxo.tx_hash, utxo.tx_pos as u32),
+                txout: TxOut {
+                    value: utxo.value,
+                    script_pubkey: script.clone(),
+                },
+                height: utxo.height,
+            })
+        })
+        .collect())
+}
+
+/// Returns first script of the chain, together with its key, which has no
+/// transaction history and is not in the `skip` set
+pub fn unused_script(
+    electrum: &ElectrumClient,
+    keychain: &Keychain,
+    chain: u32,
+    skip: &BTreeSet<Script>,
+) -> Result<Option<(Script, PublicKey)>, electrum_client::Error> {
+    let scripts = keychain
+        .chain_scripts(chain)
+        .into_iter()
+        .filter(|(script, _)| !skip.contains(script))
+        .collect::<Vec<_>>();
+    Ok(electrum
+        .batch_script_get_history(scripts.iter().map(|(script, _)| script))?
+        .into_iter()
+        .zip(scripts)
+        .find(|(history, _)| history.is_empty())
+        .map(|(_, script)| script))
+}
+
+/// Sums up value of the outputs which may be spent to pay the fees
+pub fn balance(
+    utxos: &[Utxo],
+    locked: impl Fn(OutPoint) -> bool,
+) -> FundingBalance {
+    let mut balance = FundingBalance::default();
+    for utxo in utxos {
+        let value = utxo.txout.value;
+        if locked(utxo.outpoint) {
+            balance.locked += value;
+        } else if utxo.height == 0 {
+            balance.unconfirmed += value;
+        } else {
+            balance.confirmed += value;
+            balance.outputs += 1;
+        }
+    }
+    balance
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::Txid;
+
+    #[test]
+    fn test_balance() {
+        let utxo = |vout, value, height| Utxo {
+            outpoint: OutPoint::new(Txid::default(), vout),
+            txout: TxOut {
+                value,
+                script_pubkey: Script::new(),
+            },
+            height,
+        };
+        let utxos =
+            vec![utxo(0, 1_000, 10), utxo(1, 2_000, 0), utxo(2, 4_000, 12)];
+        let balance = balance(&utxos, |outpoint| outpoint.vout == 2);
+        assert_eq!(balance.confirmed, 1_000);
+        assert_eq!(balance.unconfirmed, 2_000);
+        assert_eq!(balance.locked, 4_000);
+        assert_eq!(balance.outputs, 1);
+    }
+}
@@ -0,0 +1,133 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Optional HTTP endpoint for the orchestrator health probes. The endpoint
+//! requests daemon status through its own RPC socket, so a daemon stuck in
+//! processing of some request is reported as unhealthy.
+
+use std::fmt::Display;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+
+use internet2::zmqsocket::{ZmqSocketAddr, ZmqType};
+use internet2::{
+    session, transport, CreateUnmarshaller, TypedEnum, Unmarshall,
+};
+
+use crate::constants::FUNGIBLED_HEALTH_TIMEOUT;
+use crate::error::{ApiErrorType, BootstrapError, ServiceErrorDomain};
+use crate::rpc::fungible::Request;
+use crate::rpc::reply::HealthStatus;
+use crate::rpc::Reply;
+use crate::util::compression::req_rep;
+
+/// Starts HTTP endpoint on a separate thread. Any `GET` request is replied
+/// with JSON-encoded daemon status, with `200 OK` code if the daemon is ready
+/// and `503 Service Unavailable` otherwise.
+pub fn serve(
+    addr: SocketAddr,
+    rpc_endpoint: ZmqSocketAddr,
+) -> Result<(), BootstrapError> {
+    let listener = TcpListener::bind(addr)?;
+    info!("Health endpoint is listening on {}", addr);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            if let Err(err) =
+                stream.and_then(|stream| respond(stream, &rpc_endpoint))
+            {
+                warn!("Error serving health request: {}", err);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn respond(stream: TcpStream, rpc_endpoint: &ZmqSocketAddr) -> io::Result<()> {
+    // Request headers are not used, but they must be read before replying
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let (ready, body) = match probe(rpc_endpoint) {
+        Ok(status) => (status.is_ready(), status_json(&status)),
+        Err(err) => {
+            warn!("Daemon status is not available: {}", err);
+            (
+                false,
+                format!("{{\"ready\":false,\"error\":{:?}}}", err.to_string()),
+            )
+        }
+    };
+    let code = if ready {
+        "200 OK"
+    } else {
+        "503 Service Unavailable"
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn probe(
+    rpc_endpoint: &ZmqSocketAddr,
+) -> Result<HealthStatus, ServiceErrorDomain> {
+    let mut session = session::Raw::with_zmq_unencrypted(
+        ZmqType::Req,
+        rpc_endpoint,
+        None,
+        None,
+    )?;
+    let socket = session.as_socket();
+    socket
+        .set_rcvtimeo(FUNGIBLED_HEALTH_TIMEOUT as i32 * 1000)
+        .map_err(transport::Error::from)?;
+    socket.set_linger(0).map_err(transport::Error::from)?;
+
+    let raw = req_rep(&mut session, Request::Status.serialize())?;
+    match &*Reply::create_unmarshaller().unmarshall(&raw)? {
+        Reply::Health(status) => Ok(*status),
+        Reply::Failure(failure) => {
+            Err(ServiceErrorDomain::Internal(failure.to_string()))
+        }
+        _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
+    }
+}
+
+fn json_option(value: Option<impl Display>) -> String {
+    value
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| s!("null"))
+}
+
+fn status_json(status: &HealthStatus) -> String {
+    format!(
+        "{{\"ready\":{},\"stash_connected\":{},\"cache_available\":{},\
+         \"schema_registered\":{},\"chain_height\":{},\"chain_lag\":{}}}",
+        status.is_ready(),
+        status.stash_connected,
+        status.cache_available,
+        status.schema_registered,
+        json_option(status.chain_height),
+        json_option(status.chain_lag),
+    )
+}
@@ -0,0 +1,442 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Keys of the wallet signing witness transactions outside of the node, like
+//! hardware wallets. The wallet is registered with output descriptors of
+//! `wpkh([fingerprint/path]xpub)`, `sh(wpkh([fingerprint/path]xpub))` or
+//! `pkh([fingerprint/path]xpub)` form; receive (`0`) and change (`1`) chains
+//! of each xpub are derived up to the gap limit. The keychain fills PSBT
+//! inputs and outputs with the derivation paths, so the signer is able to
+//! recognize its keys, and finalizes the signed inputs.
+
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+use std::collections::BTreeMap;
+
+use bitcoin::blockdata::script::Builder;
+use bitcoin::secp256k1::{Secp256k1, Verification};
+use bitcoin::util::bip32::{
+    self, ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint, KeySource,
+};
+use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+use bitcoin::{OutPoint, PublicKey, Script, Transaction, TxOut, Txid};
+
+/// Script type of the descriptor
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
+pub enum DescriptorKind {
+    #[display("pkh")]
+    Pkh,
+    #[display("sh(wpkh)")]
+    ShWpkh,
+    #[display("wpkh")]
+    Wpkh,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum DescriptorError {
+    /// unsupported descriptor `{0}`; only `wpkh`, `sh(wpkh)` and `pkh` with a
+    /// single xpub are supported
+    Unsupported(String),
+
+    /// invalid key origin `{0}`
+    Origin(String),
+
+    /// invalid extended public key: {0}
+    Xpub(bip32::Error),
+}
+
+/// Output descriptor of the external signer keys
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Descriptor {
+    pub kind: DescriptorKind,
+    pub origin: KeySource,
+    pub xpub: ExtendedPubKey,
+}
+
+impl Display for Descriptor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (fingerprint, path) = &self.origin;
+        let key = format!(
+            "[{}{}]{}",
+            fingerprint,
+            path.to_string().trim_start_matches('m'),
+            self.xpub
+        );
+        match self.kind {
+            DescriptorKind::Pkh => write!(f, "pkh({})", key),
+            DescriptorKind::ShWpkh => write!(f, "sh(wpkh({}))", key),
+            DescriptorKind::Wpkh => write!(f, "wpkh({})", key),
+        }
+    }
+}
+
+/// Strips `prefix(` and closing bracket around the descriptor fragment
+fn unwrap<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    s.strip_prefix(prefix)?.strip_suffix(')')
+}
+
+impl FromStr for Descriptor {
+    type Err = DescriptorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, key) = if let Some(key) =
+            unwrap(s, "sh(").and_then(|inner| unwrap(inner, "wpkh("))
+        {
+            (DescriptorKind::ShWpkh, key)
+        } else if let Some(key) = unwrap(s, "wpkh(") {
+            (DescriptorKind::Wpkh, key)
+        } else if let Some(key) = unwrap(s, "pkh(") {
+            (DescriptorKind::Pkh, key)
+        } else {
+            return Err(DescriptorError::Unsupported(s.to_string()));
+        };
+
+        let (origin, xpub) = match key.strip_prefix('[') {
+            Some(rest) => {
+                let (origin, xpub) = rest.split_at(
+                    rest.find(']')
+                        .ok_or(DescriptorError::Origin(key.to_string()))?,
+                );
+                (Some(origin), &xpub[1..])
+            }
+            None => (None, key),
+        };
+        let xpub = ExtendedPubKey::from_str(xpub)
+            .map_err(|err| DescriptorError::Xpub(err))?;
+        let origin = match origin {
+            Some(origin) => {
+                let err = || DescriptorError::Origin(origin.to_string());
+                let mut split = origin.splitn(2, '/');
+                let fingerprint = split
+                    .next()
+                    .and_then(|fp| Fingerprint::from_str(fp).ok())
+                    .ok_or_else(err)?;
+                let path = match split.next() {
+                    Some(path) => {
+                        DerivationPath::from_str(&format!("m/{}", path))
+                            .map_err(|_| err())?
+                    }
+                    None => DerivationPath::from(vec![]),
+                };
+                (fingerprint, path)
+            }
+            None => (xpub.fingerprint(), DerivationPath::from(vec![])),
+        };
+        Ok(Descriptor { kind, origin, xpub })
+    }
+}
+
+/// Key used by the script together with its origin
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Derivation {
+    pubkey: PublicKey,
+    source: KeySource,
+    redeem_script: Option<Script>,
+}
+
+impl Descriptor {
+    fn derive<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        chain: u32,
+        index: u32,
+    ) -> Result<(Script, Derivation), bip32::Error> {
+        let path = [
+            ChildNumber::from_normal_idx(chain)?,
+            ChildNumber::from_normal_idx(index)?,
+        ];
+        let pubkey = self.xpub.derive_pub(secp, &path)?.public_key;
+        let (fingerprint, origin) = &self.origin;
+        let source = (*fingerprint, origin.extend(&path));
+        let wpkh = pubkey.wpubkey_hash().map(|hash| Script::new_v0_wpkh(&hash));
+        let (script, redeem_script) = match (self.kind, wpkh) {
+            (DescriptorKind::Pkh, _) => {
+                (Script::new_p2pkh(&pubkey.pubkey_hash()), None)
+            }
+            (DescriptorKind::ShWpkh, Some(wpkh)) => {
+                (wpkh.to_p2sh(), Some(wpkh))
+            }
+            (DescriptorKind::Wpkh, Some(wpkh)) => (wpkh, None),
+            // Keys derived from xpubs are always compressed
+            (_, None) => unreachable!("uncompressed key derived from xpub"),
+        };
+        Ok((
+            script,
+            Derivation {
+                pubkey,
+                source,
+                redeem_script,
+            },
+        ))
+    }
+}
+
+/// Scripts of all keys derived from the registered descriptors
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Keychain {
+    scripts: BTreeMap<Script, Derivation>,
+}
+
+impl Keychain {
+    /// Derives keys of the descriptors with indexes below `gap_limit` on
+    /// both receive and change chains
+    pub fn with(descriptors: &[Descriptor], gap_limit: u32) -> Self {
+        let secp = Secp256k1::verification_only();
+        let scripts = descriptors
+            .iter()
+            .flat_map(|descriptor| {
+                (0..2u32).flat_map(move |chain| {
+                    (0..gap_limit).map(move |index| (chain, index, descriptor))
+                })
+            })
+            .filter_map(|(chain, index, descriptor)| {
+                descriptor.derive(&secp, chain, index).ok()
+            })
+            .collect();
+        Keychain { scripts }
+    }
+
+    /// Detects whether keychain has any keys
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    /// Scripts of all derived keys
+    pub fn scripts(&self) -> impl Iterator<Item = &Script> {
+        self.scripts.keys()
+    }
+
+    /// Scripts of the receive (`0`) or change (`1`) chain together with
+    /// their keys, ordered by the derivation index
+    pub fn chain_scripts(&self, chain: u32) -> Vec<(Script, PublicKey)> {
+        let mut scripts = self
+            .scripts
+            .iter()
+            .filter_map(|(script, derivation)| {
+                match derivation.source.1.as_ref() {
+                    [.., c, ChildNumber::Normal { index }]
+                        if *c == ChildNumber::Normal { index: chain } =>
+                    {
+                        Some((*index, script.clone(), derivation.pubkey))
+                    }
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        scripts.sort_by_key(|(index, ..)| *index);
+        scripts
+            .into_iter()
+            .map(|(_, script, pubkey)| (script, pubkey))
+            .collect()
+    }
+
+    /// Fills PSBT inputs with the spent transactions and outputs, and adds
+    /// derivation paths and redeem scripts to the inputs and outputs
+    /// controlled by the keychain. Returns outpoint of the first input whose
+    /// transaction is not provided.
+    pub fn populate(
+        &self,
+        psbt: &mut Psbt,
+        transactions: &BTreeMap<Txid, Transaction>,
+    ) -> Result<(), OutPoint> {
+        for (txin, input) in psbt
+            .global
+            .unsigned_tx
+            .input
+            .iter()
+            .zip(psbt.inputs.iter_mut())
+        {
+            let outpoint = txin.previous_output;
+            let tx = transactions.get(&outpoint.txid).ok_or(outpoint)?;
+            let txout = tx
+                .output
+                .get(outpoint.vout as usize)
+                .ok_or(outpoint)?
+                .clone();
+            let derivation = self.scripts.get(&txout.script_pubkey);
+            if txout.script_pubkey.is_witness_program()
+                || derivation
+                    .map(|derivation| derivation.redeem_script.is_some())
+                    .unwrap_or_default()
+            {
+                input.witness_utxo = Some(txout);
+            }
+            input.non_witness_utxo = Some(tx.clone());
+            if let Some(derivation) = derivation {
+                input
+                    .bip32_derivation
+                    .insert(derivation.pubkey, derivation.source.clone());
+                input.redeem_script = derivation.redeem_script.clone();
+            }
+        }
+        for (txout, output) in psbt
+            .global
+            .unsigned_tx
+            .output
+            .iter()
+            .zip(psbt.outputs.iter_mut())
+        {
+            if let Some(derivation) = self.scripts.get(&txout.script_pubkey) {
+                output
+                    .bip32_derivation
+                    .insert(derivation.pubkey, derivation.source.clone());
+                output.redeem_script = derivation.redeem_script.clone();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Output spent by the PSBT input
+fn spent_output(psbt: &Psbt, index: usize) -> Option<TxOut> {
+    let input = &psbt.inputs[index];
+    if let Some(ref txout) = input.witness_utxo {
+        return Some(txout.clone());
+    }
+    let outpoint = psbt.global.unsigned_tx.input[index].previous_output;
+    input
+        .non_witness_utxo
+        .as_ref()?
+        .output
+        .get(outpoint.vout as usize)
+        .cloned()
+}
+
+/// Constructs final script sig and witness of the single-key inputs signed
+/// by the external signer. Inputs which are already final are kept as is.
+/// Returns index of the first input which can't be finalized.
+pub fn finalize(psbt: &mut Psbt) -> Result<(), usize> {
+    for index in 0..psbt.inputs.len() {
+        let script = spent_output(psbt, index).ok_or(index)?.script_pubkey;
+        let input = &mut psbt.inputs[index];
+        if input.final_script_sig.is_some()
+            || input.final_script_witness.is_some()
+        {
+            continue;
+        }
+        if input.partial_sigs.len() != 1 {
+            return Err(index);
+        }
+        let (pubkey, sig) = input
+            .partial_sigs
+            .iter()
+            .next()
+            .map(|(pubkey, sig)| (pubkey.to_bytes(), sig.clone()))
+            .expect("partial signature presence is checked above");
+        let redeem_script = input.redeem_script.clone();
+        if script.is_v0_p2wpkh() {
+            input.final_script_witness = Some(vec![sig, pubkey]);
+        } else if script.is_p2sh()
+            && redeem_script
+                .as_ref()
+                .filter(|redeem_script| {
+                    redeem_script.is_v0_p2wpkh()
+                        && redeem_script.to_p2sh() == script
+                })
+                .is_some()
+        {
+            let redeem_script = redeem_script.unwrap_or_default();
+            input.final_script_sig = Some(
+                Builder::new()
+                    .push_slice(redeem_script.as_bytes())
+                    .into_script(),
+            );
+            input.final_script_witness = Some(vec![sig, pubkey]);
+        } else if script.is_p2pkh() {
+            input.final_script_sig = Some(
+                Builder::new()
+                    .push_slice(&sig)
+                    .push_slice(&pubkey)
+                    .into_script(),
+            );
+        } else {
+            return Err(index);
+        }
+        input.partial_sigs.clear();
+        input.sighash_type = None;
+        input.redeem_script = None;
+        input.bip32_derivation.clear();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::TxIn;
+
+    const DESCRIPTOR: &str = "wpkh([d34db33f/84'/0'/0']xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8)";
+
+    #[test]
+    fn test_descriptor_signing() {
+        let descriptor = Descriptor::from_str(DESCRIPTOR).unwrap();
+        assert_eq!(descriptor.kind, DescriptorKind::Wpkh);
+        assert_eq!(descriptor.to_string(), DESCRIPTOR);
+        assert!(Descriptor::from_str("tr(xpub)").is_err());
+
+        let keychain = Keychain::with(&[descriptor.clone()], 2);
+        let secp = Secp256k1::verification_only();
+        let (script, derivation) = descriptor.derive(&secp, 1, 1).unwrap();
+        assert_eq!(keychain.scripts.get(&script), Some(&derivation));
+        assert_eq!(keychain.chain_scripts(1).len(), 2);
+        assert_eq!(keychain.chain_scripts(1)[1].0, script);
+        assert_eq!(
+            derivation.source.1.to_string(),
+            "m/84'/0'/0'/1/1".to_string()
+        );
+
+        let prev_tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![TxOut {
+                value: 10000,
+                script_pubkey: script.clone(),
+            }],
+        };
+        let outpoint = OutPoint::new(prev_tx.txid(), 0);
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                script_sig: Script::new(),
+                sequence: 0xFFFFFFFF,
+                witness: vec![],
+            }],
+            output: vec![TxOut {
+                value: 9000,
+                script_pubkey: script,
+            }],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        assert_eq!(keychain.populate(&mut psbt, &bmap! {}), Err(outpoint));
+        keychain
+            .populate(&mut psbt, &bmap! { prev_tx.txid() => prev_tx })
+            .unwrap();
+        assert!(psbt.inputs[0].witness_utxo.is_some());
+        assert_eq!(psbt.inputs[0].bip32_derivation.len(), 1);
+        assert_eq!(psbt.outputs[0].bip32_derivation.len(), 1);
+
+        assert_eq!(finalize(&mut psbt), Err(0));
+        psbt.inputs[0]
+            .partial_sigs
+            .insert(derivation.pubkey, vec![0x30; 71]);
+        finalize(&mut psbt).unwrap();
+        assert_eq!(
+            psbt.inputs[0].final_script_witness.as_ref().map(Vec::len),
+            Some(2)
+        );
+    }
+}
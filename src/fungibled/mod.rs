@@ -11,15 +11,42 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+mod accounting;
+mod capabilities;
+pub mod claims;
+pub mod composer;
 mod config;
+mod deterministic;
+mod epoch;
+pub mod funding;
+mod health;
+pub mod keychain;
+pub mod policy;
+mod ratelimit;
 mod runtime;
+pub mod signer;
+pub mod snapshot;
 #[cfg(feature = "sql")]
 pub(self) mod sql;
+pub mod subscription;
+mod supply;
+pub mod swap;
+mod watcher;
+pub mod webhook;
 
 pub(self) mod cache;
 
-pub use config::{Config, Opts};
+pub use claims::{Claims, ClaimsError};
+pub use composer::ComposeError;
+pub use config::{Config, Opts, Tunables};
+pub use funding::FundingError;
+pub use keychain::{Descriptor, DescriptorError, Keychain};
+pub use policy::{PolicyEngine, PolicyError, PolicyViolation, SpendPolicy};
 pub use runtime::{main_with_config, Runtime};
+pub use signer::{Signer, SignerError};
+pub use subscription::Subscription;
+pub use swap::SwapError;
+pub use webhook::{Webhook, WebhookError, WebhookEvent};
 
 #[cfg(feature = "sql")]
 pub use cache::SqlCacheError;
@@ -0,0 +1,264 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Operator-defined spending policies applied to asset transfers before the
+//! consignment is produced.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_with::As;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::Path;
+
+use bitcoin::OutPoint;
+use rgb::{AtomicValue, ContractId, SealDefinition, SealEndpoint};
+
+use crate::rpc::fungible::TransferReq;
+use crate::util::file::{file, FileMode};
+#[cfg(feature = "serde")]
+use crate::util::AtomicValueString;
+use crate::util::DataFormat;
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+#[non_exhaustive]
+pub enum PolicyError {
+    /// I/O error reading policy file: {0}
+    #[from]
+    Io(io::Error),
+
+    /// policy file format `{0}` is not supported
+    UnsupportedFormat(String),
+
+    /// error parsing policy file: {0}
+    Parse(String),
+}
+
+/// Rules applied to the transfers of a specific asset
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "kebab-case")
+)]
+pub struct SpendPolicy {
+    /// Maximum amount (in atomic units) which may leave the node with a
+    /// single transfer, not counting the change assigned to the seals
+    /// controlled by the node
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "As::<Option<AtomicValueString>>")
+    )]
+    pub max_transfer: Option<AtomicValue>,
+
+    /// If present, payments are allowed only to the listed seal endpoints,
+    /// and the change must be assigned to the seals controlled by the node
+    pub allowed_recipients: Option<BTreeSet<SealEndpoint>>,
+
+    /// Minimal number of confirmations of the transactions which created the
+    /// spent allocations
+    pub min_confirmations: Option<u32>,
+
+    /// Maximum amount (in atomic units) leaving the node with a transfer
+    /// which may be signed by the co-signing service without operator
+    /// involvement; if absent, the co-signing service is never called for
+    /// the asset
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "As::<Option<AtomicValueString>>")
+    )]
+    pub auto_sign_limit: Option<AtomicValue>,
+}
+
+/// Reason for the transfer being rejected by the spending policy
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum PolicyViolation {
+    /// transfer of {amount} atomic units exceeds maximum of {max} allowed by
+    /// the policy for asset {contract_id}
+    MaxTransferExceeded {
+        contract_id: ContractId,
+        amount: AtomicValue,
+        max: AtomicValue,
+    },
+
+    /// recipient {endpoint} is not in the allowlist of the policy for asset
+    /// {contract_id}
+    RecipientNotAllowed {
+        contract_id: ContractId,
+        endpoint: SealEndpoint,
+    },
+
+    /// change seal {seal} is not controlled by the node, while the policy for
+    /// asset {contract_id} restricts the recipients
+    ChangeNotOwned {
+        contract_id: ContractId,
+        seal: SealDefinition,
+    },
+
+    /// input {outpoint} has {confirmations} confirmations, while the policy
+    /// requires at least {required}
+    InsufficientConfirmations {
+        outpoint: OutPoint,
+        confirmations: u32,
+        required: u32,
+    },
+
+    /// unable to determine number of confirmations for input {0}
+    ConfirmationsUnknown(OutPoint),
+}
+
+/// Set of per-contract spending policies
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct PolicyEngine {
+    policies: BTreeMap<ContractId, SpendPolicy>,
+}
+
+impl PolicyEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads policies from a file; data format is detected from the file
+    /// extension. File must contain a map of contract ids to the
+    /// [`SpendPolicy`] data.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PolicyError> {
+        let path = path.as_ref();
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        let format = ext
+            .parse::<DataFormat>()
+            .map_err(|_| PolicyError::UnsupportedFormat(ext.to_string()))?;
+        #[allow(unused_mut, unused_variables)]
+        let mut f = file(path, FileMode::Read)?;
+        let policies = match format {
+            #[cfg(feature = "serde_yaml")]
+            DataFormat::Yaml => serde_yaml::from_reader(&f)
+                .map_err(|err| PolicyError::Parse(err.to_string()))?,
+            #[cfg(feature = "serde_json")]
+            DataFormat::Json => serde_json::from_reader(&f)
+                .map_err(|err| PolicyError::Parse(err.to_string()))?,
+            #[cfg(feature = "toml")]
+            DataFormat::Toml => {
+                use std::io::Read;
+                let mut data = String::new();
+                f.read_to_string(&mut data)?;
+                toml::from_str(&data)
+                    .map_err(|err| PolicyError::Parse(err.to_string()))?
+            }
+            _ => Err(PolicyError::UnsupportedFormat(format.to_string()))?,
+        };
+        Ok(Self { policies })
+    }
+
+    #[inline]
+    pub fn policy(&self, contract_id: ContractId) -> Option<&SpendPolicy> {
+        self.policies.get(&contract_id)
+    }
+
+    #[inline]
+    pub fn set_policy(&mut self, contract_id: ContractId, policy: SpendPolicy) {
+        self.policies.insert(contract_id, policy);
+    }
+
+    /// Detects whether the transfer may be signed by the co-signing service;
+    /// `foreign` must contain the change seals which are not controlled by
+    /// the node
+    pub fn allows_auto_signing(
+        &self,
+        transfer: &TransferReq,
+        foreign: &BTreeSet<SealDefinition>,
+    ) -> bool {
+        let limit = match self
+            .policy(transfer.contract_id)
+            .and_then(|policy| policy.auto_sign_limit)
+        {
+            Some(limit) => limit,
+            None => return false,
+        };
+        transfer.outgoing_amount(foreign) <= limit
+    }
+
+    /// Checks the transfer against the policy defined for its asset, if any.
+    ///
+    /// `inputs` must contain only the outpoints holding allocations of the
+    /// transferred asset; `foreign` must contain the change seals which are
+    /// not controlled by the node; `confirmations` is used to resolve number
+    /// of confirmations for the inputs and is called only if the policy
+    /// requires so.
+    pub fn check(
+        &self,
+        transfer: &TransferReq,
+        inputs: &BTreeSet<OutPoint>,
+        foreign: &BTreeSet<SealDefinition>,
+        mut confirmations: impl FnMut(&OutPoint) -> Option<u32>,
+    ) -> Result<(), PolicyViolation> {
+        let contract_id = transfer.contract_id;
+        let policy = match self.policy(contract_id) {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+
+        if let Some(max) = policy.max_transfer {
+            let amount = transfer.outgoing_amount(foreign);
+            if amount > max {
+                Err(PolicyViolation::MaxTransferExceeded {
+                    contract_id,
+                    amount,
+                    max,
+                })?
+            }
+        }
+
+        if let Some(ref allowed) = policy.allowed_recipients {
+            if let Some(endpoint) = transfer
+                .payment
+                .keys()
+                .find(|endpoint| !allowed.contains(endpoint))
+            {
+                Err(PolicyViolation::RecipientNotAllowed {
+                    contract_id,
+                    endpoint: *endpoint,
+                })?
+            }
+            if let Some(seal) =
+                transfer.change.keys().find(|seal| foreign.contains(seal))
+            {
+                Err(PolicyViolation::ChangeNotOwned {
+                    contract_id,
+                    seal: *seal,
+                })?
+            }
+        }
+
+        if let Some(required) = policy.min_confirmations {
+            for outpoint in inputs {
+                let confirmations = confirmations(outpoint)
+                    .ok_or(PolicyViolation::ConfirmationsUnknown(*outpoint))?;
+                if confirmations < required {
+                    Err(PolicyViolation::InsufficientConfirmations {
+                        outpoint: *outpoint,
+                        confirmations,
+                        required,
+                    })?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
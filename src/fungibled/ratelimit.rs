@@ -0,0 +1,199 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Token-bucket limiting of the RPC request rate.
+
+use bitcoin::secp256k1::PublicKey;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+/// Class of the RPC request; each class has its own quota
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+pub enum RequestClass {
+    /// Cheap queries of the cached data
+    #[display("query")]
+    Query,
+
+    /// Requests requiring consignment validation or export of the whole
+    /// cache, like `validate`, `accept` and `sync`
+    #[display("expensive")]
+    Expensive,
+}
+
+/// Client which the request quota is accounted to. The RPC socket does not
+/// distinguish connected peers, so the clients are told apart by the account
+/// key signing their requests; all unsigned requests share the same quota.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+pub enum ClientId {
+    /// Requests signed by the account key
+    #[display("account:{0}")]
+    Account(PublicKey),
+
+    /// Requests not wrapped into an account request
+    #[display("anonymous")]
+    Anonymous,
+}
+
+impl From<Option<PublicKey>> for ClientId {
+    fn from(account: Option<PublicKey>) -> Self {
+        account
+            .map(ClientId::Account)
+            .unwrap_or(ClientId::Anonymous)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Per-client token buckets. Bucket capacity equals to the number of
+/// requests allowed per minute, so the client may send a burst of all its
+/// minute quota at once; after that the tokens are refilled at a constant
+/// rate.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct RateLimiter<K>
+where
+    K: Ord,
+{
+    buckets: BTreeMap<(K, RequestClass), Bucket>,
+}
+
+impl<K> RateLimiter<K>
+where
+    K: Ord + Clone,
+{
+    #[inline]
+    pub fn new() -> Self {
+        Self { buckets: bmap! {} }
+    }
+
+    /// Takes a token from the client bucket for the given request class.
+    /// Requests are not limited if `per_minute` is `None`. If the bucket is
+    /// empty returns number of seconds after which the request may be
+    /// retried.
+    pub fn check(
+        &mut self,
+        client: &K,
+        class: RequestClass,
+        per_minute: Option<u32>,
+        now: Instant,
+    ) -> Result<(), u32> {
+        let capacity = match per_minute {
+            None => return Ok(()),
+            Some(0) => return Err(60),
+            Some(per_minute) => per_minute as f64,
+        };
+        let rate = capacity / 60.0;
+        let bucket =
+            self.buckets
+                .entry((client.clone(), class))
+                .or_insert(Bucket {
+                    tokens: capacity,
+                    updated: now,
+                });
+        let elapsed = now.saturating_duration_since(bucket.updated);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * rate).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) / rate).ceil().max(1.0) as u32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_unlimited() {
+        let mut limiter = RateLimiter::new();
+        let now = Instant::now();
+        for _ in 0..1000 {
+            assert_eq!(
+                limiter.check(&0, RequestClass::Query, None, now),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn test_burst_and_refill() {
+        let mut limiter = RateLimiter::new();
+        let now = Instant::now();
+        for _ in 0..6 {
+            assert_eq!(
+                limiter.check(&0, RequestClass::Expensive, Some(6), now),
+                Ok(())
+            );
+        }
+        assert_eq!(
+            limiter.check(&0, RequestClass::Expensive, Some(6), now),
+            Err(10)
+        );
+        // Other classes and clients have their own quota
+        assert_eq!(
+            limiter.check(&0, RequestClass::Query, Some(6), now),
+            Ok(())
+        );
+        assert_eq!(
+            limiter.check(&1, RequestClass::Expensive, Some(6), now),
+            Ok(())
+        );
+        let later = now + Duration::from_secs(10);
+        assert_eq!(
+            limiter.check(&0, RequestClass::Expensive, Some(6), later),
+            Ok(())
+        );
+        assert_eq!(
+            limiter.check(&0, RequestClass::Expensive, Some(6), later),
+            Err(10)
+        );
+    }
+
+    #[test]
+    fn test_accounts() {
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let key = |byte| {
+            let sk =
+                bitcoin::secp256k1::SecretKey::from_slice(&[byte; 32]).unwrap();
+            PublicKey::from_secret_key(&secp, &sk)
+        };
+        let alice = ClientId::from(Some(key(1)));
+        let bob = ClientId::from(Some(key(2)));
+        assert_eq!(ClientId::from(None), ClientId::Anonymous);
+
+        let mut limiter = RateLimiter::new();
+        let now = Instant::now();
+        for client in &[alice, bob, ClientId::Anonymous] {
+            for _ in 0..3 {
+                assert_eq!(
+                    limiter.check(client, RequestClass::Query, Some(3), now),
+                    Ok(())
+                );
+            }
+        }
+        for client in &[alice, bob, ClientId::Anonymous] {
+            assert_eq!(
+                limiter.check(client, RequestClass::Query, Some(3), now),
+                Err(20)
+            );
+        }
+    }
+}
@@ -11,56 +11,112 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use core::borrow::Borrow;
 use core::convert::TryFrom;
-use std::collections::{BTreeMap, BTreeSet};
-use std::path::PathBuf;
+use std::collections::{btree_map, BTreeMap, BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{fs, io, iter};
 
-use bitcoin::{OutPoint, Txid};
+use amplify::Wrapper;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+use bitcoin::{Address, OutPoint, Script, Txid};
+use chrono::Utc;
+use electrum_client::{Client as ElectrumClient, ElectrumApi};
 use internet2::zmqsocket::ZmqType;
 use internet2::TypedEnum;
 use internet2::{
-    session, transport, CreateUnmarshaller, PlainTranscoder, Session,
-    Unmarshall, Unmarshaller,
+    session, CreateUnmarshaller, Session, Unmarshall, Unmarshaller,
 };
 use lnpbp::client_side_validation::CommitConceal;
-use lnpbp::seals::OutpointReveal;
+use lnpbp::seals::{OutpointHash, OutpointReveal};
+use lnpbp::strict_encoding::strict_serialize;
+use lnpbp::Chain;
 use microservices::node::TryService;
-use microservices::FileFormat;
+use rgb::validation::Validity;
 use rgb::{
-    AtomicValue, Consignment, ContractId, Disclosure, Genesis, Node,
-    SealDefinition, SealEndpoint, Transition,
+    AtomicValue, Consignment, ContractId, Disclosure, Genesis, Node, Schema,
+    SchemaId, SealDefinition, SealEndpoint, Transition,
 };
 use rgb20::schema::OwnedRightsType;
 use rgb20::{schema, Asset, OutpointCoins};
 
-use super::cache::{Cache, FileCache, FileCacheConfig};
-use super::Config;
+use super::accounting::{Direction, Ledger, LedgerEntry};
+use super::cache::{
+    Cache, CacheError, FileCache, FileCacheConfig, JournalEntry,
+    OutpointReservation, Reservation, Tombstone,
+};
+use super::capabilities;
+use super::claims::{Claims, ContractClaims};
+use super::composer::{self, ComposeError};
+use super::deterministic::{self, GenesisSeed};
+use super::epoch;
+use super::funding::{self, FundingError};
+use super::keychain::{self, Keychain};
+use super::ratelimit::{ClientId, RateLimiter, RequestClass};
+use super::snapshot::{AllocationRecord, SnapshotError, SnapshotReader};
+use super::subscription;
+use super::supply;
+use super::swap::{self, SwapError};
+use super::watcher::Watcher;
+use super::webhook::{Dispatcher, WebhookEvent};
+use super::{health, Config, PolicyEngine};
+use crate::constants::{
+    FUNGIBLED_ASSET_META_ENTRIES, FUNGIBLED_ASSET_META_SIZE,
+    FUNGIBLED_BULK_IMPORT_BATCH, FUNGIBLED_DESCRIPTOR_GAP,
+    FUNGIBLED_EVENT_LOG_SIZE, FUNGIBLED_JOB_RETENTION,
+    FUNGIBLED_LABELS_PER_TARGET, FUNGIBLED_LABEL_SIZE, FUNGIBLED_SIGNATURE_TTL,
+};
 use crate::error::{
-    ApiErrorType, BootstrapError, RuntimeError, ServiceError,
+    ApiErrorType, BootstrapError, ErrorCode, RuntimeError, ServiceError,
     ServiceErrorDomain, ServiceErrorSource,
 };
+use crate::ln::{ChannelAssets, ChannelError, Event, EventRecord};
 use crate::rpc::{
     self,
-    fungible::{AcceptReq, IssueReq, Request, TransferReq},
+    fungible::{
+        AcceptReq, AccountError, AccountReq, AllocationsReq,
+        ApproveTransferReq, AssetLeg, AsyncReq, AuditLogReq, BulkImportReq,
+        ChannelFundedReq, ChannelUpdateReq, ComposeWitnessReq, ExportLedgerReq,
+        HoldTarget, IdempotencyError, IdempotentReq, ImportClaimsReq, Invoice,
+        IssueAllocation, IssueReq, IssueSeal, JobError, LabelTarget,
+        OpenEpochReq, ReceiveAssetReq, Request, ReserveOutpointReq,
+        SetAssetMetaReq, SetHoldReq, SetLabelReq, SwapCompleteReq, SwapLeg,
+        SwapOfferReq, SwapReceive, SwapSend, SwapVerifyReq, TransferError,
+        TransferReq, ValidateReq,
+    },
+    limits::{self, Limited},
     reply,
     stash::AcceptRequest,
+    stash::BackupRequest,
+    stash::HistoryRequest,
+    stash::ReconsignRequest,
     stash::TransferRequest,
-    Reply,
+    Batch, Reply, Shared,
+};
+use crate::util::backup::backup_name;
+use crate::util::compression::{
+    compress, decompress, req_rep, RPC_COMPRESSION_THRESHOLD,
+};
+#[cfg(feature = "server")]
+use crate::util::daemon;
+use crate::util::{
+    config, logging, psbt, reload, shutdown, DataFormat, ToBech32Data,
 };
-use crate::util::ToBech32Data;
 
 pub struct Runtime {
     /// Original configuration object
     config: Config,
 
     /// Request-response API session
-    fungible_rpc_server:
-        session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
+    fungible_rpc_server: Box<dyn Session>,
+
+    /// Publish-subscribe API session for asset-channel events
+    fungible_pub: Box<dyn Session>,
 
     /// Stash RPC client session
-    stash_rpc_client:
-        session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
+    stash_rpc_client: Box<dyn Session>,
 
     /// RGB fungible assets data cache: relational database sharing the client-
     /// friendly asset information with clients
@@ -71,6 +127,53 @@ pub struct Runtime {
 
     /// Unmarshaller instance used for parsing RPC request
     reply_unmarshaller: Unmarshaller<Reply>,
+
+    /// Operator-defined spending policies checked on each transfer
+    policy: PolicyEngine,
+
+    /// Keys of the external signer, derived from the configured descriptors
+    keychain: Keychain,
+
+    /// Funding addresses given out since the daemon start, which are not
+    /// returned again even while they have no transactions
+    revealed_funding: BTreeSet<Script>,
+
+    /// Assets allocated to lightning channels, indexed by the channel funding
+    /// outpoint and asset id
+    channels: BTreeMap<(OutPoint, ContractId), ChannelAssets>,
+
+    /// Bitcoin status of the outpoints holding asset allocations
+    watcher: Watcher,
+
+    /// Time of the last outpoint status update
+    last_watch: Instant,
+
+    /// Time of the last scheduled backup attempt
+    last_backup: Instant,
+
+    /// Reload generation of the current configuration
+    reload_generation: usize,
+
+    /// Request quotas of the RPC clients. ZMQ REP socket does not expose
+    /// identity of the connected peers, so the clients are identified by the
+    /// RPC session endpoint and share the same quota.
+    limiter: RateLimiter<ClientId>,
+
+    /// Status of the background jobs, indexed by the job id
+    jobs: BTreeMap<u64, reply::JobStatus>,
+
+    /// Requests queued for background execution, in the order of their
+    /// submission
+    job_queue: VecDeque<(u64, Request)>,
+
+    /// Id which will be assigned to the next background job
+    next_job: u64,
+
+    /// Account on whose behalf the current request is executed, if any
+    caller_account: Option<PublicKey>,
+
+    /// Delivery of the events to the webhooks, if any are configured
+    webhooks: Option<Dispatcher>,
 }
 
 impl Runtime {
@@ -83,15 +186,6 @@ impl Runtime {
     }
 
     pub fn init(config: Config) -> Result<Self, BootstrapError> {
-        let cacher = FileCache::new(FileCacheConfig {
-            data_dir: PathBuf::from(&config.cache),
-            data_format: config.format,
-        })
-        .map_err(|err| {
-            error!("{}", err);
-            err
-        })?;
-
         let session_rpc = session::Raw::with_zmq_unencrypted(
             ZmqType::Rep,
             &config.rpc_endpoint,
@@ -99,6 +193,13 @@ impl Runtime {
             None,
         )?;
 
+        let session_pub = session::Raw::with_zmq_unencrypted(
+            ZmqType::Pub,
+            &config.pub_endpoint,
+            None,
+            None,
+        )?;
+
         let stash_rpc = session::Raw::with_zmq_unencrypted(
             ZmqType::Req,
             &config.stash_rpc,
@@ -106,13 +207,102 @@ impl Runtime {
             None,
         )?;
 
+        if let Some(addr) = config.health_endpoint {
+            health::serve(addr, config.rpc_endpoint.clone())?;
+        }
+
+        Self::with_sessions(
+            config,
+            Box::new(session_rpc),
+            Box::new(session_pub),
+            Box::new(stash_rpc),
+        )
+    }
+
+    /// Constructs runtime using the provided sessions for serving requests,
+    /// publishing events and talking to stash daemon instead of the ZMQ
+    /// sockets specified in the configuration
+    pub fn with_sessions(
+        config: Config,
+        fungible_rpc_server: Box<dyn Session>,
+        fungible_pub: Box<dyn Session>,
+        stash_rpc_client: Box<dyn Session>,
+    ) -> Result<Self, BootstrapError> {
+        if config.deterministic.is_some() {
+            if config.network == Chain::Mainnet {
+                Err("Deterministic asset issuance is not allowed on mainnet")?
+            }
+            warn!("Asset geneses will be constructed deterministically");
+        }
+
+        let cacher = FileCache::new(FileCacheConfig {
+            data_dir: PathBuf::from(&config.cache),
+            data_format: config.format,
+            memory_budget: config.cache_memory.map(|budget| budget as usize),
+        })
+        .map_err(|err| {
+            error!("{}", err);
+            err
+        })?;
+
+        let policy = match config.policy {
+            Some(ref path) => {
+                debug!("Loading spending policies from {:?}", path);
+                PolicyEngine::load(path).map_err(|err| {
+                    error!("{}", err);
+                    err
+                })?
+            }
+            None => PolicyEngine::new(),
+        };
+
+        if config.approval_threshold.is_some() && config.approvers.is_empty() {
+            Err("Approval threshold is set, but no approvers are registered")?
+        }
+
+        let keychain =
+            Keychain::with(&config.descriptors, FUNGIBLED_DESCRIPTOR_GAP);
+
+        let webhooks =
+            match (config.webhooks.is_empty(), &config.webhook_secret) {
+                (true, _) => None,
+                (false, None) => {
+                    Err("Webhooks are given without webhook secret")?
+                }
+                (false, Some(secret)) => {
+                    info!(
+                        "Delivering events to {} webhooks",
+                        config.webhooks.len()
+                    );
+                    Some(Dispatcher::start(
+                        config.webhooks.clone(),
+                        secret.clone(),
+                    ))
+                }
+            };
+
         Ok(Self {
             config,
-            fungible_rpc_server: session_rpc,
-            stash_rpc_client: stash_rpc,
+            fungible_rpc_server,
+            fungible_pub,
+            stash_rpc_client,
             cacher,
             unmarshaller: Request::create_unmarshaller(),
             reply_unmarshaller: Reply::create_unmarshaller(),
+            policy,
+            keychain,
+            revealed_funding: bset! {},
+            channels: bmap! {},
+            watcher: Watcher::new(),
+            last_watch: Instant::now(),
+            last_backup: Instant::now(),
+            reload_generation: reload::generation(),
+            limiter: RateLimiter::new(),
+            jobs: bmap! {},
+            job_queue: VecDeque::new(),
+            next_job: 1,
+            caller_account: None,
+            webhooks,
         })
     }
 }
@@ -129,7 +319,9 @@ impl TryService for Runtime {
             )
         })?;
 
-        loop {
+        self.watch();
+
+        while !shutdown::is_requested() {
             match self.run() {
                 Ok(_) => debug!("API request processing complete"),
                 Err(err) => {
@@ -137,22 +329,112 @@ impl TryService for Runtime {
                     Err(err)?;
                 }
             }
+            if self.reload_generation != reload::generation() {
+                if let Err(err) = self.reload() {
+                    error!("Unable to reload configuration: {}", err);
+                }
+            }
         }
+        self.shutdown()
     }
 }
 
 impl Runtime {
+    /// Re-reads tunable settings from the configuration file and reloads
+    /// spending policies. If any of them fails to load, the current
+    /// configuration is kept.
+    fn reload(&mut self) -> Result<(), ServiceErrorDomain> {
+        self.reload_generation = reload::generation();
+        let mut updated = self.config.clone();
+        if let Some(ref path) = self.config.config_file {
+            debug!("Reading configuration file {:?}", path);
+            let tunables = config::tunables(path, "fungibled")
+                .map_err(|err| ServiceErrorDomain::Config(err.to_string()))?;
+            updated.apply(tunables);
+        }
+        self.policy = match updated.policy {
+            Some(ref path) => {
+                debug!("Loading spending policies from {:?}", path);
+                PolicyEngine::load(path).map_err(|err| {
+                    ServiceErrorDomain::Config(err.to_string())
+                })?
+            }
+            None => PolicyEngine::new(),
+        };
+        if updated.verbose != self.config.verbose
+            || updated.log_filter != self.config.log_filter
+        {
+            logging::set_filter(updated.verbose, &updated.log_filter);
+        }
+        if updated.cache_memory != self.config.cache_memory {
+            self.cacher
+                .set_memory_budget(
+                    updated.cache_memory.map(|budget| budget as usize),
+                )
+                .map_err(CacheError::from)?;
+        }
+        self.config = updated;
+        info!("Configuration reloaded");
+        Ok(())
+    }
+
+    /// Rejects contract data which belong to another bitcoin network
+    fn check_network(
+        &self,
+        genesis: &Genesis,
+    ) -> Result<(), ServiceErrorDomain> {
+        if genesis.chain() != &self.config.network {
+            Err(ServiceErrorDomain::WrongNetwork {
+                expected: self.config.network.clone(),
+                found: genesis.chain().clone(),
+            })?
+        }
+        Ok(())
+    }
+
+    /// Persists the cache; sessions are closed when the runtime is dropped
+    fn shutdown(self) -> Result<(), RuntimeError> {
+        info!("Fungible contract runtime is shutting down");
+        if self.config.read_only {
+            return Ok(());
+        }
+        self.cacher
+            .save()
+            .map_err(|err| RuntimeError::Internal(err.to_string()))
+    }
+
     fn run(&mut self) -> Result<(), RuntimeError> {
         trace!("Awaiting for ZMQ RPC requests...");
         let raw = self.fungible_rpc_server.recv_raw_message()?;
         let reply = self.rpc_process(raw).unwrap_or_else(|err| err);
         trace!("Preparing ZMQ RPC reply: {:?}", reply);
-        let data = reply.serialize();
+        let data = compress(reply.serialize(), RPC_COMPRESSION_THRESHOLD);
         trace!(
             "Sending {} bytes back to the client over ZMQ RPC",
             data.len()
         );
         self.fungible_rpc_server.send_raw_message(&data)?;
+
+        // Background jobs are executed once the client got its reply; the
+        // requests arriving in the meantime wait in the socket queue
+        self.run_jobs();
+
+        // Outpoint statuses are updated in between of the requests, since the
+        // API socket is processed in a blocking way. Read-only node leaves
+        // this to the primary one.
+        if self.config.read_only {
+            return Ok(());
+        }
+        let interval = Duration::from_secs(self.config.watch_interval as u64);
+        if self.last_watch.elapsed() >= interval {
+            self.watch();
+        }
+        let interval = Duration::from_secs(self.config.backup_interval as u64);
+        if self.config.backup.is_some()
+            && self.last_backup.elapsed() >= interval
+        {
+            self.scheduled_backup();
+        }
         Ok(())
     }
 
@@ -162,178 +444,1223 @@ impl Runtime {
             raw.len(),
             raw.to_bech32data()
         );
-        let message = &*self.unmarshaller.unmarshall(&raw).map_err(|err| {
+        limits::check_size(&raw).map_err(|err| {
+            error!("Rejecting the data: {}", err);
+            ServiceError::contract(err.into(), "fungible")
+        })?;
+        let raw = decompress(raw).map_err(|err| {
+            error!("Error decompressing the data: {}", err);
+            ServiceError::from_rpc(
+                ServiceErrorSource::Contract(s!("fungible")),
+                err,
+            )
+        })?;
+        let message = self.unmarshaller.unmarshall(&raw).map_err(|err| {
             error!("Error unmarshalling the data: {}", err);
             ServiceError::from_rpc(
                 ServiceErrorSource::Contract(s!("fungible")),
                 err,
             )
         })?;
+        // Raw data are not needed anymore; releasing them before processing
+        // the request, which may take a while for large consignments
+        drop(raw);
+        let message = &*message;
+        message.check_limits().map_err(|err| {
+            error!("Rejecting the request: {}", err);
+            ServiceError::contract(err.into(), "fungible")
+        })?;
         debug!("Received ZMQ RPC request: {:?}", message);
+        self.rpc_request(message)
+    }
+
+    fn rpc_request(&mut self, message: &Request) -> Result<Reply, Reply> {
+        self.check_read_only(message)
+            .and_then(|_| self.check_account_scope(message))
+            .map_err(|err| ServiceError::contract(err, "fungible"))?;
+        if let Some(retry_after) = self.rate_limit(message) {
+            return Ok(Reply::RateLimited(retry_after));
+        }
+        self.rpc_dispatch(message)
+    }
+
+    /// Rejects state-changing requests if the node runs in read-only mode,
+    /// pointing the client to the primary node. Batched requests are checked
+    /// separately.
+    fn check_read_only(
+        &self,
+        request: &Request,
+    ) -> Result<(), ServiceErrorDomain> {
+        if !self.config.read_only || Self::is_read_only(request) {
+            return Ok(());
+        }
+        Err(ServiceErrorDomain::ReadOnly(self.config.primary.clone()))
+    }
+
+    /// Detects requests which do not change the node state. Wrapping requests
+    /// are classified by the request they wrap; batches are classified by
+    /// each of the batched requests separately.
+    fn is_read_only(request: &Request) -> bool {
+        match request {
+            Request::Assets(_)
+            | Request::Allocations(_)
+            | Request::GetAssetMeta(_)
+            | Request::SupplyReport(_)
+            | Request::EpochSeals(_)
+            | Request::ContractCapabilities(_)
+            | Request::ComposeWitness(_)
+            | Request::FundingBalance
+            | Request::ListPendingReceives
+            | Request::ListTransfers
+            | Request::TransferStatus(_)
+            | Request::ExportAcceptedConsignment(_)
+            | Request::Reconsign(_)
+            | Request::ExportClaims(_)
+            | Request::Sync(_)
+            | Request::ExportAsset(_)
+            | Request::PreviewIssue(_)
+            | Request::ExportLedger(_)
+            | Request::AuditLog(_)
+            | Request::Ping
+            | Request::Status
+            | Request::ReloadConfig
+            | Request::JobStatus(_)
+            | Request::ReplayEvents(_)
+            | Request::Batch(_) => true,
+            Request::Async(req) => Self::is_read_only(&req.request),
+            Request::Idempotent(req) => Self::is_read_only(&req.request),
+            Request::Account(req) => Self::is_read_only(&req.request),
+            _ => false,
+        }
+    }
+
+    /// Rejects requests which are not made on behalf of an account if the
+    /// node serves accounts. Only liveness checks and the requests signed by
+    /// the approvers and admins may be sent without an account.
+    fn check_account_scope(
+        &self,
+        request: &Request,
+    ) -> Result<(), ServiceErrorDomain> {
+        if self.config.accounts.is_empty() || self.caller_account.is_some() {
+            return Ok(());
+        }
+        match request {
+            Request::Ping
+            | Request::Status
+            | Request::Account(_)
+            | Request::Batch(_)
+            | Request::ApproveTransfer(_)
+            | Request::SetHold(_) => Ok(()),
+            Request::Async(req) => self.check_account_scope(&req.request),
+            Request::Idempotent(req) => self.check_account_scope(&req.request),
+            _ => Err(AccountError::Required(request.to_string()))?,
+        }
+    }
+
+    /// Checks that the spent outpoints are registered under the account on
+    /// behalf of which the request is made
+    fn check_account_inputs(
+        &self,
+        inputs: &BTreeSet<OutPoint>,
+    ) -> Result<(), ServiceErrorDomain> {
+        let account = match self.caller_account {
+            Some(account) => account,
+            None => return Ok(()),
+        };
+        let accounts = self.cacher.accounts()?;
+        if let Some(outpoint) = inputs
+            .iter()
+            .find(|outpoint| accounts.owner(**outpoint) != Some(account))
+        {
+            Err(AccountError::NotOwned(format!("outpoint {}", outpoint)))?
+        }
+        Ok(())
+    }
+
+    /// Executes the request, recording it in the audit log if the request
+    /// changes the node state. Wrapping requests are not recorded, since the
+    /// requests they wrap are recorded once executed.
+    fn rpc_dispatch(&mut self, message: &Request) -> Result<Reply, Reply> {
+        let reply = self.rpc_execute(message);
+        match message {
+            Request::Batch(_)
+            | Request::Async(_)
+            | Request::Idempotent(_)
+            | Request::Account(_) => {}
+            // Claims export discloses the secrets, so it is recorded as well
+            Request::ExportClaims(_) => self.audit(message, &reply),
+            request if !Self::is_read_only(request) => {
+                self.audit(message, &reply)
+            }
+            _ => {}
+        }
+        reply
+    }
+
+    fn rpc_execute(&mut self, message: &Request) -> Result<Reply, Reply> {
         Ok(match message {
             Request::Issue(issue) => self.rpc_issue(issue),
-            Request::Transfer(transfer) => self.rpc_transfer(transfer),
+            Request::PreviewIssue(issue) => self.rpc_preview_issue(issue),
+            Request::Transfer(transfer) => self
+                .rpc_transfer(transfer)
+                .and_then(|reply| self.hold_for_approval(transfer, reply)),
+            Request::PrepareTransfer(transfer) => self
+                .rpc_prepare_transfer(transfer)
+                .and_then(|reply| self.hold_for_approval(transfer, reply)),
+            Request::CommitTransfer(txid) => self.rpc_commit_transfer(*txid),
+            Request::CancelTransfer(txid) => self.rpc_cancel_transfer(*txid),
             Request::Validate(consignment) => self.rpc_validate(consignment),
             Request::Accept(accept) => self.rpc_accept(accept),
+            Request::AutoAccept(consignment) => {
+                self.rpc_auto_accept(consignment)
+            }
             Request::Enclose(disclosure) => self.rpc_enclose(disclosure),
             Request::Forget(outpoint) => self.rpc_forget(outpoint),
+            Request::Blind(outpoint) => self.rpc_blind(*outpoint),
+            Request::ForgetContract(req) => {
+                self.rpc_forget_contracts(vec![req.contract_id], req.dry_run)
+            }
+            Request::ForgetAll(dry_run) => self.rpc_forget_all(*dry_run),
+            Request::Restore(outpoint) => self.rpc_restore(*outpoint),
+            Request::ChannelFunded(req) => self.rpc_channel_funded(req),
+            Request::ChannelUpdated(req) => self.rpc_channel_updated(req),
+            Request::ChannelClosed(funding) => {
+                self.rpc_channel_closed(*funding)
+            }
             Request::ImportAsset(genesis) => self.rpc_import_asset(genesis),
+            Request::ImportFromConsignment(consignment) => {
+                self.rpc_import_from_consignment(consignment)
+            }
             Request::ExportAsset(asset_id) => self.rpc_export_asset(asset_id),
             Request::Sync(data_format) => self.rpc_sync(*data_format),
             Request::Assets(outpoint) => self.rpc_outpoint_assets(*outpoint),
-            Request::Allocations(contract_id) => {
-                self.rpc_asset_allocations(*contract_id)
+            Request::Allocations(req) => self.rpc_asset_allocations(req),
+            Request::SetLabel(req) => self.rpc_set_label(req),
+            Request::SetAssetMeta(req) => self.rpc_set_asset_meta(req),
+            Request::GetAssetMeta(contract_id) => {
+                self.rpc_get_asset_meta(*contract_id)
+            }
+            Request::SupplyReport(contract_id) => {
+                self.rpc_supply_report(*contract_id)
+            }
+            Request::EpochSeals(contract_id) => {
+                self.rpc_epoch_seals(*contract_id)
+            }
+            Request::OpenEpoch(req) => self.rpc_open_epoch(req),
+            Request::ContractCapabilities(contract_id) => {
+                self.rpc_contract_capabilities(*contract_id)
+            }
+            Request::ComposeWitness(req) => self.rpc_compose_witness(req),
+            Request::FundingBalance => self.rpc_funding_balance(),
+            Request::NewFundingAddress => self.rpc_new_funding_address(),
+            Request::ReceiveAsset(req) => self.rpc_receive_asset(req),
+            Request::ListPendingReceives => self.rpc_list_pending_receives(),
+            Request::ListTransfers => self.rpc_list_transfers(),
+            Request::TransferStatus(txid) => self.rpc_transfer_status(*txid),
+            Request::ExportAcceptedConsignment(consignment_id) => {
+                self.rpc_export_accepted_consignment(*consignment_id)
+            }
+            Request::Reconsign(txid) => self.rpc_reconsign(*txid),
+            Request::ExportClaims(passphrase) => {
+                self.rpc_export_claims(passphrase.as_str())
+            }
+            Request::ImportClaims(req) => self.rpc_import_claims(req),
+            Request::Backup => self.rpc_backup(),
+            Request::CancelReceive(endpoint) => {
+                self.rpc_cancel_receive(*endpoint)
+            }
+            Request::Ping => Ok(Reply::Success),
+            Request::Status => self.rpc_status(),
+            Request::ReloadConfig => self.rpc_reload_config(),
+            Request::Batch(batch) => Ok(self.rpc_batch(batch)),
+            Request::Idempotent(req) => return self.rpc_idempotent(req),
+            Request::Async(req) => self.rpc_async(req),
+            Request::JobStatus(id) => self.rpc_job_status(*id),
+            Request::ReplayEvents(from_seq) => {
+                self.rpc_replay_events(*from_seq)
+            }
+            Request::ReserveOutpoint(req) => self.rpc_reserve_outpoint(req),
+            Request::SwapOffer(req) => self.rpc_swap_offer(req),
+            Request::SwapComplete(req) => self.rpc_swap_complete(req),
+            Request::SwapVerify(req) => self.rpc_swap_verify(req),
+            Request::PrepareSigning(transfer) => self
+                .rpc_prepare_signing(transfer)
+                .and_then(|reply| self.hold_for_approval(transfer, reply)),
+            Request::ApproveTransfer(req) => self.rpc_approve_transfer(req),
+            Request::ExportLedger(req) => self.rpc_export_ledger(req),
+            Request::AuditLog(req) => self.rpc_audit_log(req),
+            Request::SetHold(req) => self.rpc_set_hold(req),
+            Request::Account(req) => return self.rpc_account(req),
+            Request::BulkImport(req) => self.rpc_bulk_import(req),
+            Request::FinalizeTransfer(signed) => {
+                self.rpc_finalize_transfer(signed)
+            }
+            Request::ReleaseOutpoint(outpoint) => {
+                self.rpc_release_outpoint(*outpoint)
             }
         }
         .map_err(|err| ServiceError::contract(err, "fungible"))?)
     }
 
-    fn rpc_issue(
-        &mut self,
-        issue: &IssueReq,
-    ) -> Result<Reply, ServiceErrorDomain> {
-        debug!("Got ISSUE {}", issue);
-
-        let issue = issue.clone();
-        let (asset, genesis) = rgb20::issue(
-            self.config.network.clone(),
-            issue.ticker,
-            issue.name,
-            issue.description,
-            issue.precision,
-            issue
-                .allocation
-                .into_iter()
-                .map(|OutpointCoins { coins, outpoint }| (outpoint, coins))
-                .collect(),
-            issue.inflation.into_iter().fold(
-                BTreeMap::new(),
-                |mut map, OutpointCoins { coins, outpoint }| {
-                    // We may have only a single secondary issuance right per
-                    // outpoint, so folding all outpoints
-                    map.entry(outpoint)
-                        .and_modify(|amount| *amount += coins)
-                        .or_insert(coins);
-                    map
-                },
-            ),
-            issue.renomination,
-            issue.epoch,
-        )?;
-
-        self.import_asset(asset.clone(), genesis)?;
+    /// Takes the request from the client quota; returns number of seconds
+    /// after which the request may be retried if the quota is exceeded.
+    /// Batches are not limited by themselves, since each of the batched
+    /// requests is checked separately.
+    fn rate_limit(&mut self, request: &Request) -> Option<u32> {
+        let (class, per_minute) = match request {
+            // Used for liveness checks and to unblock RPC loop on shutdown
+            Request::Ping
+            | Request::Batch(_)
+            | Request::Idempotent(_)
+            | Request::Account(_) => return None,
+            // Background requests are limited by the class of the request
+            // being queued
+            Request::Async(req) => return self.rate_limit(&req.request),
+            Request::Validate(_)
+            | Request::Accept(_)
+            | Request::AutoAccept(_)
+            | Request::Sync(_)
+            | Request::SwapComplete(_)
+            | Request::SwapVerify(_)
+            | Request::ExportLedger(_)
+            | Request::ExportAcceptedConsignment(_)
+            | Request::Reconsign(_)
+            | Request::ExportClaims(_)
+            | Request::ImportClaims(_)
+            | Request::Backup
+            | Request::SupplyReport(_)
+            | Request::ComposeWitness(_)
+            | Request::FundingBalance
+            | Request::BulkImport(_) => {
+                (RequestClass::Expensive, self.config.expensive_rate_limit)
+            }
+            _ => (RequestClass::Query, self.config.rate_limit),
+        };
+        let client = ClientId::from(self.caller_account);
+        let retry_after = self
+            .limiter
+            .check(&client, class, per_minute, Instant::now())
+            .err()?;
+        warn!(
+            "Rate limit for {} requests of {} is exceeded; rejecting {}",
+            class, client, request
+        );
+        Some(retry_after)
+    }
 
-        // TODO #154: Send push request to client informing about cache update
+    /// Executes state-changing request unless it was already completed with
+    /// the same idempotency key. Only successful replies are recorded, so
+    /// failed requests are executed again when retried.
+    fn rpc_idempotent(&mut self, req: &IdempotentReq) -> Result<Reply, Reply> {
+        debug!("Got {}", req);
+        let request_hash = sha256::Hash::hash(&req.request.serialize());
+        if let Some(reply) = self
+            .journaled_reply(req, request_hash)
+            .map_err(|err| ServiceError::contract(err, "fungible"))?
+        {
+            debug!("Request with key {} is already completed", req.key);
+            return Ok(reply);
+        }
 
-        Ok(Reply::Asset(asset))
+        let reply = self.rpc_request(&req.request)?;
+        if let Reply::RateLimited(_) = reply {
+            return Ok(reply);
+        }
+        if let Err(err) = self.cacher.add_journal_entry(JournalEntry {
+            key: req.key.clone(),
+            request_hash,
+            reply: reply.serialize(),
+            completed: Utc::now().timestamp(),
+        }) {
+            // The request is already executed, so we must return its reply
+            error!("Unable to record request with key {}: {}", req.key, err);
+        }
+        Ok(reply)
     }
 
-    fn rpc_transfer(
-        &mut self,
-        transfer: &TransferReq,
-    ) -> Result<Reply, ServiceErrorDomain> {
-        debug!("Got TRANSFER {}", transfer);
-
-        // TODO #66: Check inputs that they really exist and have sufficient
-        //       amount of asset for the transfer operation
+    fn journaled_reply(
+        &self,
+        req: &IdempotentReq,
+        request_hash: sha256::Hash,
+    ) -> Result<Option<Reply>, ServiceErrorDomain> {
+        match *req.request {
+            Request::Issue(_)
+            | Request::Transfer(_)
+            | Request::PrepareTransfer(_)
+            | Request::PrepareSigning(_)
+            | Request::FinalizeTransfer(_)
+            | Request::ApproveTransfer(_)
+            | Request::Accept(_)
+            | Request::Forget(_)
+            | Request::ForgetContract(_)
+            | Request::ForgetAll(_)
+            | Request::ReserveOutpoint(_)
+            | Request::ReleaseOutpoint(_)
+            | Request::SwapOffer(_)
+            | Request::SwapComplete(_)
+            | Request::SwapVerify(_) => {}
+            ref request => {
+                Err(IdempotencyError::NotStateChanging(request.to_string()))?
+            }
+        }
+        let entry = match self.cacher.journal_entry(&req.key)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        if entry.request_hash != request_hash {
+            Err(IdempotencyError::KeyReused(req.key.clone()))?
+        }
+        let reply = self.reply_unmarshaller.unmarshall(&entry.reply)?;
+        Ok(Some((*reply).clone()))
+    }
 
-        trace!("Looking for asset information");
-        debug!("Transferring asset {}", transfer.contract_id);
+    /// Executes request on behalf of the account, limiting it to the assets
+    /// and allocations registered under the account and registering the
+    /// assets and outpoints it creates or receives
+    fn rpc_account(&mut self, req: &AccountReq) -> Result<Reply, Reply> {
+        debug!("Got {}", req);
+        self.check_account_request(req)
+            .map_err(|err| ServiceError::contract(err, "fungible"))?;
+        self.caller_account = Some(req.account);
+        let reply = self.rpc_request(&req.request);
+        self.caller_account = None;
+        Ok(self
+            .scope_reply(req, reply?)
+            .map_err(|err| ServiceError::contract(err, "fungible"))?)
+    }
 
-        trace!("Preparing state transition");
-        // Filtering inputs which do not have this assets: we will need them
-        // later, but not for constructing the main RGB20 transfer transition
-        let asset = self.cacher.asset(transfer.contract_id)?;
-        let inputs = transfer
-            .inputs
-            .iter()
-            .filter(|outpoint| !asset.allocations(**outpoint).is_empty())
-            .cloned()
-            .collect();
-        let transition = rgb20::transfer(
-            asset,
-            inputs,
-            transfer.payment.clone(),
-            transfer.change.clone(),
-        )?;
-        debug!("State transition: {}", transition);
+    fn check_account_request(
+        &self,
+        req: &AccountReq,
+    ) -> Result<(), ServiceErrorDomain> {
+        let account = req.account;
+        if !self.config.accounts.contains(&account) {
+            Err(AccountError::UnknownAccount(account))?
+        }
+        let age = (Utc::now().timestamp() - req.timestamp).abs();
+        if age > FUNGIBLED_SIGNATURE_TTL as i64 || !req.verify() {
+            Err(AccountError::InvalidSignature)?
+        }
 
-        trace!("Collecting other assets on the spent outpoints and preparing blank state transitions");
-        let mut other_outpoint_assets: BTreeMap<
-            ContractId,
-            BTreeSet<(OutPoint, AtomicValue)>,
-        > = bmap! {};
-        for outpoint in &transfer.inputs {
-            for (other_contract_id, amounts) in
-                self.cacher.outpoint_assets(*outpoint)?
-            {
-                let sum = amounts.into_iter().sum();
-                // Ignoring native asset, current contract and zero balances
-                if other_contract_id == transfer.contract_id || sum == 0 {
-                    continue;
+        let accounts = self.cacher.accounts()?;
+        let check_asset = |contract_id: ContractId| {
+            if accounts.has_asset(account, contract_id) {
+                Ok(())
+            } else {
+                Err(AccountError::NotOwned(format!("asset {}", contract_id)))
+            }
+        };
+        // Outpoints which are not registered yet may be claimed by the
+        // account receiving assets on them
+        let check_outpoint = |outpoint: OutPoint, claim: bool| match accounts
+            .owner(outpoint)
+        {
+            Some(owner) if owner == account => Ok(()),
+            None if claim => Ok(()),
+            _ => Err(AccountError::NotOwned(format!("outpoint {}", outpoint))),
+        };
+        match &*req.request {
+            Request::Issue(_)
+            | Request::Validate(_)
+            | Request::Sync(_)
+            | Request::Ping => {}
+            Request::Transfer(transfer) => check_asset(transfer.contract_id)?,
+            Request::Accept(accept) => {
+                for reveal in &accept.reveal_outpoints {
+                    check_outpoint(OutPoint::from(*reveal), true)?;
                 }
-                other_outpoint_assets
-                    .entry(other_contract_id)
-                    .or_insert(empty!())
-                    .insert((*outpoint, sum));
             }
+            Request::Blind(outpoint) => check_outpoint(*outpoint, true)?,
+            Request::ReceiveAsset(ReceiveAssetReq {
+                contract_id,
+                utxo: Some(outpoint),
+                ..
+            }) => {
+                check_asset(*contract_id)?;
+                check_outpoint(*outpoint, true)?
+            }
+            Request::Forget(outpoint) | Request::Assets(outpoint) => {
+                check_outpoint(*outpoint, false)?
+            }
+            Request::Allocations(AllocationsReq { contract_id, .. })
+            | Request::ExportAsset(contract_id)
+            | Request::GetAssetMeta(contract_id)
+            | Request::SupplyReport(contract_id)
+            | Request::EpochSeals(contract_id)
+            | Request::ContractCapabilities(contract_id)
+            | Request::OpenEpoch(OpenEpochReq { contract_id, .. }) => {
+                check_asset(*contract_id)?
+            }
+            Request::SetLabel(SetLabelReq { target, .. }) => match *target {
+                LabelTarget::Outpoint(outpoint) => {
+                    check_outpoint(outpoint, false)?
+                }
+                LabelTarget::Allocation(contract_id, outpoint) => {
+                    check_asset(contract_id)?;
+                    check_outpoint(outpoint, false)?
+                }
+            },
+            request => Err(AccountError::NotSupported(request.to_string()))?,
         }
-        debug!(
-            "Total {} other assets are found on the spent outpoints",
-            other_outpoint_assets.len()
-        );
-        trace!("{:?}", other_outpoint_assets);
-        let change_seal = if other_outpoint_assets.len() > 0 {
-            transfer.change.keys().find(|_| true).ok_or(
-                ServiceErrorDomain::Internal(s!(
-                    "Other assets are present on the provided inputs, but no change address given"
-                ))
-            )?.clone()
-        } else {
-            SealDefinition::WitnessVout {
-                vout: 0,
-                blinding: 0,
-            } // Not used
-        };
-        let mut other_transitions = bmap! {};
-        for (other_contract, outpoints) in other_outpoint_assets {
-            other_transitions.insert(
-                other_contract,
-                rgb20::transfer(
-                    self.cacher.asset(other_contract)?,
-                    outpoints.iter().map(|(outpoint, _)| *outpoint).collect(),
-                    empty!(),
-                    outpoints
-                        .iter()
-                        .map(|(_, amount)| (change_seal, *amount))
-                        .collect(),
-                )?,
+        Ok(())
+    }
+
+    /// Registers assets and outpoints created or received by the account
+    /// request and removes data of other accounts from the reply
+    fn scope_reply(
+        &mut self,
+        req: &AccountReq,
+        reply: Reply,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        let account = req.account;
+        let (assets, outpoints) = match (&*req.request, &reply) {
+            (Request::Issue(_), Reply::Asset(asset)) => (
+                vec![*asset.id()],
+                asset
+                    .known_allocations()
+                    .iter()
+                    .map(|allocation| *allocation.outpoint())
+                    .collect(),
+            ),
+            (Request::Transfer(transfer), Reply::Transfer(prepared)) => {
+                let txid = prepared.witness.global.unsigned_tx.txid();
+                (vec![], Self::change_outpoints(transfer, txid))
+            }
+            (Request::Transfer(transfer), Reply::ApprovalRequired(txid)) => {
+                (vec![], Self::change_outpoints(transfer, *txid))
+            }
+            (Request::Accept(accept), _) => (
+                vec![accept.consignment.genesis.contract_id()],
+                accept
+                    .reveal_outpoints
+                    .iter()
+                    .copied()
+                    .map(OutPoint::from)
+                    .collect(),
+            ),
+            (Request::Blind(outpoint), _) => (vec![], vec![*outpoint]),
+            (Request::ReceiveAsset(_), Reply::Receive(receive)) => {
+                (vec![], vec![OutPoint::from(receive.reveal)])
+            }
+            (Request::Sync(data_format), _) => {
+                return self.account_assets(account, *data_format)
+            }
+            (Request::Allocations(_), Reply::AssetAllocations(data)) => {
+                let accounts = self.cacher.accounts()?;
+                let data = data
+                    .iter()
+                    .filter(|(outpoint, _)| {
+                        accounts.owner(**outpoint) == Some(account)
+                    })
+                    .map(|(outpoint, amounts)| (*outpoint, amounts.clone()))
+                    .collect();
+                return Ok(Reply::AssetAllocations(data));
+            }
+            _ => return Ok(reply),
+        };
+        if !self
+            .cacher
+            .register_account_items(account, &assets, &outpoints)?
+        {
+            warn!(
+                "Some of the outpoints created by {} belong to other accounts",
+                req
             );
         }
+        Ok(reply)
+    }
 
-        trace!("Requesting consignment from stash daemon");
-        let endpoints = transfer
+    fn change_outpoints(transfer: &TransferReq, txid: Txid) -> Vec<OutPoint> {
+        transfer
             .change
             .keys()
-            .copied()
-            .map(SealEndpoint::from)
-            .chain(transfer.payment.keys().copied())
-            .collect();
-        let mut reply = self.consign(TransferRequest {
-            contract_id: transfer.contract_id,
-            inputs: transfer.inputs.clone(),
-            transition,
-            other_transitions,
-            endpoints,
-            psbt: transfer.witness.clone(),
-        })?;
+            .map(|seal| seal.outpoint_reveal(txid).into())
+            .collect()
+    }
 
-        // Concealing internal data
-        if let Reply::Transfer(reply::Transfer {
+    /// Exports assets registered under the account, keeping only the
+    /// allocations on the account outpoints
+    fn account_assets(
+        &mut self,
+        account: PublicKey,
+        data_format: DataFormat,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        let mut assets = vec![];
+        for contract_id in self.cacher.accounts()?.assets(account) {
+            let mut asset = self.cacher.asset(contract_id)?.clone();
+            let accounts = self.cacher.accounts()?;
+            for allocation in asset.known_allocations().clone() {
+                if accounts.owner(*allocation.outpoint()) != Some(account) {
+                    asset.remove_allocation(
+                        *allocation.outpoint(),
+                        *allocation.node_id(),
+                        *allocation.index(),
+                        allocation.revealed_amount().clone(),
+                    );
+                }
+            }
+            assets.push(asset);
+        }
+        let meta = self
+            .cacher
+            .asset_meta()?
+            .select(assets.iter().map(|asset| asset.id()));
+        let capabilities = reply::AssetCapabilities::with_assets(&assets);
+        let data = self.cacher.export_assets(assets, Some(data_format))?;
+        Ok(Reply::Sync(reply::AssetSync {
+            format: data_format,
+            data,
+            meta,
+            capabilities,
+        }))
+    }
+
+    fn rpc_async(
+        &mut self,
+        req: &AsyncReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        match *req.request {
+            Request::Issue(_)
+            | Request::Transfer(_)
+            | Request::Validate(_)
+            | Request::Accept(_)
+            | Request::Sync(_)
+            | Request::ImportAsset(_)
+            | Request::ImportFromConsignment(_)
+            | Request::ExportLedger(_)
+            | Request::SupplyReport(_)
+            | Request::BulkImport(_)
+            | Request::ExportClaims(_)
+            | Request::ImportClaims(_)
+            | Request::Backup
+            | Request::Forget(_)
+            | Request::ForgetContract(_)
+            | Request::ForgetAll(_)
+            | Request::Idempotent(_) => {}
+            ref request => Err(JobError::NotSupported(request.to_string()))?,
+        }
+        let id = self.next_job;
+        self.next_job += 1;
+        self.jobs.insert(
+            id,
+            reply::JobStatus {
+                id,
+                request: req.request.to_string(),
+                queued: Utc::now().timestamp(),
+                completed: None,
+                result: None,
+            },
+        );
+        self.job_queue.push_back((id, (*req.request).clone()));
+        info!("Request {} is queued as job #{}", req.request, id);
+        Ok(Reply::JobQueued(id))
+    }
+
+    fn rpc_job_status(&mut self, id: u64) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got JOB_STATUS {}", id);
+        let status = self.jobs.get(&id).ok_or(JobError::UnknownJob(id))?;
+        Ok(Reply::Job(status.clone()))
+    }
+
+    /// Appends record of the executed request to the audit log. ZMQ REP
+    /// socket does not expose identity of the connected peers, so the caller
+    /// is identified by the key signing the request: approver, admin or
+    /// account one. Requests which are not signed are recorded as anonymous.
+    fn audit(&mut self, request: &Request, reply: &Result<Reply, Reply>) {
+        let caller = match request {
+            Request::ApproveTransfer(req) => {
+                format!("approver:{}", req.approver)
+            }
+            Request::SetHold(req) => format!("admin:{}", req.admin),
+            _ => ClientId::from(self.caller_account).to_string(),
+        };
+        let outcome = match reply {
+            Ok(_) => reply::AuditOutcome::Success,
+            Err(Reply::Failure(failure)) => reply::AuditOutcome::Failure {
+                code: failure.code,
+                info: failure.info.clone(),
+            },
+            Err(reply) => reply::AuditOutcome::Failure {
+                code: ErrorCode::Internal as u16,
+                info: reply.to_string(),
+            },
+        };
+        let result = self.cacher.audit_log().map(|log| {
+            log.next_record(
+                Utc::now().timestamp(),
+                caller,
+                request.to_string(),
+                sha256::Hash::hash(&request.serialize()),
+                outcome,
+            )
+        });
+        if let Err(err) =
+            result.and_then(|record| self.cacher.append_audit_record(record))
+        {
+            // The request is already executed, so we must return its reply
+            error!("Unable to record {} in the audit log: {}", request, err);
+        }
+    }
+
+    fn rpc_audit_log(
+        &mut self,
+        req: &AuditLogReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        let records = self.cacher.audit_log()?.range(req.from, req.to);
+        Ok(Reply::AuditLog(records))
+    }
+
+    fn rpc_replay_events(
+        &mut self,
+        from_seq: u64,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got REPLAY_EVENTS from {}", from_seq);
+        let log = self.cacher.event_log()?;
+        Ok(Reply::EventReplay(reply::EventReplay {
+            truncated: from_seq < log.first_seq(),
+            next_seq: log.next_seq(),
+            events: log.since(from_seq),
+        }))
+    }
+
+    /// Executes all queued background jobs, publishing their results, and
+    /// prunes statuses of the jobs completed long time ago
+    fn run_jobs(&mut self) {
+        while let Some((id, request)) = self.job_queue.pop_front() {
+            debug!("Executing job #{}: {}", id, request);
+            let reply = self.rpc_dispatch(&request).unwrap_or_else(|err| err);
+            let status = match self.jobs.get_mut(&id) {
+                Some(status) => status,
+                None => continue,
+            };
+            status.completed = Some(Utc::now().timestamp());
+            status.result = Some(Box::new(reply));
+            let event = Event::JobCompleted(status.clone());
+            info!("Job #{} is completed", id);
+            if let Err(err) = self.publish(event) {
+                error!("Unable to publish job #{} completion: {}", id, err);
+            }
+        }
+
+        let expired = Utc::now().timestamp() - FUNGIBLED_JOB_RETENTION as i64;
+        self.jobs.retain(|_, status| {
+            status.completed.map(|time| time > expired).unwrap_or(true)
+        });
+    }
+
+    fn rpc_batch(&mut self, batch: &Batch<Request>) -> Reply {
+        debug!("Got BATCH of {} requests", batch.0.len());
+        Reply::Batch(Batch(
+            batch
+                .0
+                .iter()
+                .map(|request| {
+                    self.rpc_request(request).unwrap_or_else(|reply| reply)
+                })
+                .collect(),
+        ))
+    }
+
+    fn rpc_status(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got STATUS");
+
+        let schema_ids =
+            match self.stash_req_rep(rpc::stash::Request::ListSchemata()) {
+                Ok(Reply::SchemaIds(ids)) => Some(ids),
+                Ok(_) | Err(_) => None,
+            };
+        let header = ElectrumClient::new(&self.config.electrum_server)
+            .and_then(|electrum| electrum.block_headers_subscribe())
+            .map_err(|err| warn!("Chain backend is not available: {:?}", err))
+            .ok();
+        let now = Utc::now().timestamp() as u64;
+
+        Ok(Reply::Health(reply::HealthStatus {
+            stash_connected: schema_ids.is_some(),
+            cache_available: self.cacher.is_available(),
+            schema_registered: schema_ids
+                .map(|ids| ids.contains(&schema::schema().schema_id()))
+                .unwrap_or_default(),
+            chain_height: header.as_ref().map(|header| header.height as u32),
+            chain_lag: header
+                .map(|header| now.saturating_sub(header.header.time as u64)),
+            cache: self.cacher.stats(),
+        }))
+    }
+
+    fn rpc_reload_config(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got RELOAD_CONFIG");
+        self.reload()?;
+        Ok(Reply::Success)
+    }
+
+    fn rpc_issue(
+        &mut self,
+        issue: &IssueReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got ISSUE {}", issue);
+
+        let (asset, genesis) = self.create_asset(issue)?;
+        self.import_asset(asset.clone(), genesis)?;
+
+        // TODO #154: Send push request to client informing about cache update
+
+        Ok(Reply::Asset(asset))
+    }
+
+    fn rpc_preview_issue(
+        &mut self,
+        issue: &IssueReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got PREVIEW_ISSUE {}", issue);
+
+        let (asset, genesis) = self.create_asset(issue)?;
+        let issue = issue.clone().restrict();
+        let status = schema::schema().validate(&bmap! {}, &genesis);
+        let genesis_size = strict_serialize(&genesis)
+            .map_err(|err| ServiceErrorDomain::Internal(err.to_string()))?
+            .len() as u32;
+
+        Ok(Reply::IssuePreview(reply::IssuePreview {
+            contract_id: *asset.id(),
+            genesis_size,
+            issued: issue.allocation.iter().map(|coins| coins.coins).sum(),
+            inflation: issue.inflation.iter().map(|coins| coins.coins).sum(),
+            allocations: issue.allocation.iter().fold(
+                bmap! {},
+                |mut map, IssueAllocation { coins, seal }| {
+                    *map.entry(*seal).or_insert(0) += coins;
+                    map
+                },
+            ),
+            status,
+        }))
+    }
+
+    /// Creates asset genesis with RGB20 schema processor from the validated
+    /// issue parameters, without storing it
+    fn create_asset(
+        &self,
+        issue: &IssueReq,
+    ) -> Result<(Asset, Genesis), ServiceErrorDomain> {
+        let issue = issue.clone().restrict();
+        let issued = issue.validate(self.config.supply_cap)?;
+        trace!(
+            "Issue parameters are valid; issuing {} atomic units",
+            issued
+        );
+
+        let allocation = issue
+            .allocation
+            .into_iter()
+            .map(|IssueAllocation { coins, seal }| (seal, coins))
+            .collect::<Vec<_>>();
+        // RGB20 schema processor supports only revealed outpoints
+        let outpoints = allocation
+            .iter()
+            .map(|(seal, coins)| match seal {
+                IssueSeal::Revealed(outpoint) => Some((*outpoint, *coins)),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>();
+        let inflation = issue.inflation.into_iter().fold(
+            BTreeMap::new(),
+            |mut map, OutpointCoins { coins, outpoint }| {
+                // We may have only a single secondary issuance right per
+                // outpoint, so folding all outpoints
+                map.entry(outpoint)
+                    .and_modify(|amount| *amount += coins)
+                    .or_insert(coins);
+                map
+            },
+        );
+        Ok(match (self.config.deterministic, outpoints) {
+            (None, Some(allocation)) => rgb20::issue(
+                self.config.network.clone(),
+                issue.ticker,
+                issue.name,
+                issue.description,
+                issue.precision,
+                allocation,
+                inflation,
+                issue.renomination,
+                issue.epoch,
+            )?,
+            (seed, _) => deterministic::issue(
+                seed.unwrap_or_else(GenesisSeed::random),
+                self.config.network.clone(),
+                issue.ticker,
+                issue.name,
+                issue.description,
+                issue.precision,
+                allocation,
+                inflation,
+                issue.renomination,
+                issue.epoch,
+            )?,
+        })
+    }
+
+    /// Rejects inputs reserved by pending transfers or for the protocols
+    /// run outside of the node
+    fn check_reservations(
+        &mut self,
+        inputs: &BTreeSet<OutPoint>,
+    ) -> Result<(), ServiceErrorDomain> {
+        self.release_stale_reservations()?;
+        for outpoint in inputs {
+            if let Some(reservation) =
+                self.cacher.input_reservation(*outpoint)?
+            {
+                Err(TransferError::InputReserved {
+                    outpoint: *outpoint,
+                    txid: reservation.txid,
+                })?
+            }
+            if self.cacher.outpoint_reservation(*outpoint)?.is_some() {
+                Err(TransferError::OutpointReserved(*outpoint))?
+            }
+        }
+        Ok(())
+    }
+
+    /// Rejects inputs holding allocations of the transferred or any other
+    /// asset put on administrative hold, since the transfer moves all of
+    /// them
+    fn check_holds(
+        &self,
+        contract_id: ContractId,
+        inputs: &BTreeSet<OutPoint>,
+    ) -> Result<(), ServiceErrorDomain> {
+        let holds = self.cacher.holds()?;
+        for outpoint in inputs {
+            let assets = self.cacher.outpoint_assets(*outpoint)?;
+            let allocated = assets
+                .iter()
+                .filter(|(_, allocations)| !allocations.is_empty())
+                .map(|(contract_id, _)| contract_id);
+            if let Some(target) = iter::once(&contract_id)
+                .chain(allocated)
+                .find_map(|contract_id| holds.hold(*contract_id, *outpoint))
+            {
+                Err(TransferError::AllocationOnHold(target))?
+            }
+        }
+        Ok(())
+    }
+
+    /// Detects whether any of the cached assets has allocations on the
+    /// outpoint
+    fn has_allocations(
+        &self,
+        outpoint: OutPoint,
+    ) -> Result<bool, ServiceErrorDomain> {
+        Ok(self
+            .cacher
+            .outpoint_assets(outpoint)?
+            .values()
+            .any(|allocations| !allocations.is_empty()))
+    }
+
+    /// Change seals of the transfer which are not controlled by the node.
+    /// Outpoint seals are controlled by the node if they were blinded by it
+    /// or already hold its allocations; witness output seals are controlled
+    /// if the output pays to the node descriptors.
+    fn foreign_change(
+        &self,
+        transfer: &TransferReq,
+    ) -> Result<BTreeSet<SealDefinition>, ServiceErrorDomain> {
+        let mut foreign = bset! {};
+        for seal in transfer.change.keys() {
+            let owned = match seal {
+                SealDefinition::TxOutpoint(reveal) => {
+                    self.cacher.reveal(reveal.commit_conceal())?.is_some()
+                        || self.has_allocations(OutPoint::new(
+                            reveal.txid,
+                            reveal.vout,
+                        ))?
+                }
+                SealDefinition::WitnessVout { vout, .. } => transfer
+                    .witness
+                    .global
+                    .unsigned_tx
+                    .output
+                    .get(*vout as usize)
+                    .map(|txout| {
+                        self.keychain
+                            .scripts()
+                            .any(|script| *script == txout.script_pubkey)
+                    })
+                    .unwrap_or_default(),
+            };
+            if !owned {
+                foreign.insert(*seal);
+            }
+        }
+        Ok(foreign)
+    }
+
+    /// Transfers of the other assets allocated on the inputs of the
+    /// transfer. Spending the inputs moves these allocations, so they are
+    /// assigned by blank state transitions to the first change seal of the
+    /// transfer controlled by the node; the transfer is rejected if there is
+    /// no such seal.
+    fn blank_transfers(
+        &self,
+        transfer: &TransferReq,
+    ) -> Result<Vec<TransferReq>, ServiceErrorDomain> {
+        let mut allocations =
+            BTreeMap::<ContractId, (BTreeSet<OutPoint>, AtomicValue)>::new();
+        for outpoint in &transfer.inputs {
+            for (contract_id, amounts) in
+                self.cacher.outpoint_assets(*outpoint)?
+            {
+                let sum = amounts.into_iter().sum::<AtomicValue>();
+                // Ignoring current contract and zero balances
+                if contract_id == transfer.contract_id || sum == 0 {
+                    continue;
+                }
+                let (inputs, amount) =
+                    allocations.entry(contract_id).or_default();
+                inputs.insert(*outpoint);
+                *amount += sum;
+            }
+        }
+        let contract_id = match allocations.keys().next() {
+            Some(contract_id) => *contract_id,
+            None => return Ok(vec![]),
+        };
+        let foreign = self.foreign_change(transfer)?;
+        let change_seal = *transfer
+            .change
+            .keys()
+            .find(|seal| !foreign.contains(seal))
+            .ok_or(TransferError::NoOwnedChange(contract_id))?;
+        Ok(allocations
+            .into_iter()
+            .map(|(contract_id, (inputs, amount))| TransferReq {
+                contract_id,
+                witness: transfer.witness.clone(),
+                inputs,
+                payment: empty!(),
+                theirs_witness: empty!(),
+                change: bmap! { change_seal => amount },
+                confirmed_only: transfer.confirmed_only,
+                container: transfer.container,
+            })
+            .collect())
+    }
+
+    /// Checks the blank transfers against the spending policy and composes
+    /// their state transitions together with the consignment endpoints,
+    /// which are the change seals receiving the moved allocations
+    fn blank_transitions(
+        &self,
+        blank: &[TransferReq],
+    ) -> Result<
+        (
+            BTreeMap<ContractId, Transition>,
+            BTreeMap<ContractId, BTreeSet<SealEndpoint>>,
+        ),
+        ServiceErrorDomain,
+    > {
+        let mut transitions = bmap! {};
+        let mut endpoints = bmap! {};
+        for other in blank {
+            self.check_policy(other, &other.inputs)?;
+            transitions.insert(
+                other.contract_id,
+                rgb20::transfer(
+                    self.cacher.asset(other.contract_id)?,
+                    other.inputs.clone(),
+                    empty!(),
+                    other.change.clone(),
+                )?,
+            );
+            endpoints.insert(
+                other.contract_id,
+                other
+                    .change
+                    .keys()
+                    .copied()
+                    .map(SealEndpoint::from)
+                    .collect(),
+            );
+        }
+        Ok((transitions, endpoints))
+    }
+
+    fn check_policy(
+        &self,
+        transfer: &TransferReq,
+        inputs: &BTreeSet<OutPoint>,
+    ) -> Result<(), ServiceErrorDomain> {
+        trace!("Checking transfer against spending policy");
+        if self.policy.policy(transfer.contract_id).is_none() {
+            return Ok(());
+        }
+        let foreign = self.foreign_change(transfer)?;
+        let mut electrum = None;
+        self.policy.check(transfer, inputs, &foreign, |outpoint| {
+            if electrum.is_none() {
+                electrum =
+                    ElectrumClient::new(&self.config.electrum_server).ok();
+            }
+            Self::confirmations(electrum.as_ref()?, outpoint)
+        })?;
+        Ok(())
+    }
+
+    fn rpc_transfer(
+        &mut self,
+        transfer: &TransferReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got TRANSFER {}", transfer);
+
+        self.check_account_inputs(&transfer.inputs)?;
+        self.check_reservations(&transfer.inputs)?;
+        self.check_holds(transfer.contract_id, &transfer.inputs)?;
+
+        let mut transfer = transfer.clone();
+        transfer.reveal_witness_payments();
+        if let Some(dust_limit) = self.config.dust_limit {
+            trace!("Applying dust limit of {} to transfer outputs", dust_limit);
+            transfer.apply_dust_limit(dust_limit)?;
+        }
+
+        trace!("Checking that transfer inputs are not spent on-chain");
+        match ElectrumClient::new(&self.config.electrum_server) {
+            Ok(electrum) => {
+                for outpoint in &transfer.inputs {
+                    if let Some(txid) =
+                        Watcher::spending_txid(&electrum, outpoint)
+                    {
+                        Err(TransferError::SealAlreadySpent {
+                            outpoint: *outpoint,
+                            txid,
+                        })?
+                    }
+                }
+            }
+            Err(err) => warn!(
+                "Electrum server is not available, transfer inputs are not \
+                 checked for being spent: {:?}",
+                err
+            ),
+        }
+
+        if transfer.confirmed_only {
+            trace!("Checking that all transfer inputs are confirmed");
+            if let Ok(electrum) =
+                ElectrumClient::new(&self.config.electrum_server)
+            {
+                self.watcher.refresh(&electrum, &transfer.inputs);
+            }
+            for outpoint in &transfer.inputs {
+                match self.watcher.status(*outpoint) {
+                    reply::AllocationStatus::Confirmed(_) => {}
+                    status => Err(TransferError::UnconfirmedInput {
+                        outpoint: *outpoint,
+                        status,
+                    })?,
+                }
+            }
+        }
+
+        // TODO #66: Check inputs that they really exist and have sufficient
+        //       amount of asset for the transfer operation
+
+        trace!("Looking for asset information");
+        debug!("Transferring asset {}", transfer.contract_id);
+
+        trace!("Preparing state transition");
+        // Filtering inputs which do not have this assets: we will need them
+        // later, but not for constructing the main RGB20 transfer transition
+        let asset = self.cacher.asset(transfer.contract_id)?.clone();
+        let inputs: BTreeSet<OutPoint> = transfer
+            .inputs
+            .iter()
+            .filter(|outpoint| !asset.allocations(**outpoint).is_empty())
+            .cloned()
+            .collect();
+
+        self.check_policy(&transfer, &inputs)?;
+        let mut spent = bmap! { transfer.contract_id => inputs.clone() };
+        let transition = rgb20::transfer(
+            &asset,
+            inputs,
+            transfer.payment.clone(),
+            transfer.change.clone(),
+        )?;
+        debug!("State transition: {}", transition);
+
+        trace!("Preparing blank state transitions for other assets on the spent outpoints");
+        let blank = self.blank_transfers(&transfer)?;
+        debug!(
+            "Total {} other assets are found on the spent outpoints",
+            blank.len()
+        );
+        let (other_transitions, other_endpoints) =
+            self.blank_transitions(&blank)?;
+        for other in &blank {
+            spent.insert(other.contract_id, other.inputs.clone());
+        }
+
+        trace!("Requesting consignment from stash daemon");
+        let endpoints = transfer
+            .change
+            .keys()
+            .copied()
+            .map(SealEndpoint::from)
+            .chain(transfer.payment.keys().copied())
+            .collect();
+        let mut reply = self.consign(TransferRequest {
+            contract_id: transfer.contract_id,
+            inputs: transfer.inputs.clone(),
+            transition,
+            other_transitions,
+            endpoints,
+            other_endpoints,
+            psbt: transfer.witness.clone(),
+            container: transfer
+                .container
+                .unwrap_or(self.config.anchor_container),
+        })?;
+
+        // Concealing internal data
+        if let Reply::Transfer(reply::Transfer {
             ref mut consignment,
+            ref mut witness,
             ..
         }) = reply
         {
+            for (contract_id, outpoints) in &spent {
+                psbt::mark_inputs(witness, *contract_id, outpoints);
+            }
+
+            let txid = witness.global.unsigned_tx.txid();
+            let now = Utc::now().timestamp();
+            trace!("Reserving inputs for pending spend {}", txid);
+            self.cacher.reserve_inputs(Reservation {
+                txid,
+                inputs: transfer.inputs.clone(),
+                expires: now + self.config.reservation_timeout as i64,
+            })?;
+            self.cacher.register_transfer(
+                reply::TransferStatus {
+                    txid,
+                    contract_id: transfer.contract_id,
+                    amount: transfer.payment_amount(),
+                    state: reply::TransferState::Created,
+                    created: now,
+                    updated: now,
+                    reason: None,
+                },
+                transfer.payment.keys().copied().collect(),
+            )?;
+
             let receivers = transfer.payment.keys().collect::<BTreeSet<_>>();
             let expose = consignment
                 .endpoints
@@ -349,78 +1676,1979 @@ impl Runtime {
             consignment.finalize(&expose, transfer.contract_id);
         }
 
-        Ok(reply)
+        Ok(reply)
+    }
+
+    fn rpc_prepare_transfer(
+        &mut self,
+        transfer: &TransferReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got PREPARE {}", transfer);
+        let reply = self.rpc_transfer(transfer)?;
+        if let Reply::Transfer(ref prepared) = reply {
+            let txid = prepared.witness.global.unsigned_tx.txid();
+            debug!("Transfer {} is pending for commitment", txid);
+            self.cacher.add_prepared_transfer(txid, prepared.clone())?;
+            Ok(reply)
+        } else {
+            Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply))
+        }
+    }
+
+    /// Transfers of all assets moved by the transfer: the transferred asset
+    /// and the other assets allocated on its inputs
+    fn moved_transfers(
+        &self,
+        transfer: &TransferReq,
+    ) -> Result<Vec<TransferReq>, ServiceErrorDomain> {
+        let mut moved = vec![transfer.clone()];
+        moved.extend(self.blank_transfers(transfer)?);
+        Ok(moved)
+    }
+
+    /// Holds the reply releasing the outgoing consignments of the transfer
+    /// or swap until a second operator approval if the amount of any of the
+    /// assets leaving the node exceeds the approval threshold
+    fn hold_for_approval(
+        &mut self,
+        transfer: &TransferReq,
+        reply: Reply,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        let txid = match reply {
+            Reply::Transfer(ref prepared) => {
+                prepared.witness.global.unsigned_tx.txid()
+            }
+            Reply::SwapLeg(ref leg) => leg.witness.global.unsigned_tx.txid(),
+            Reply::Swap(ref swap) => swap.witness.global.unsigned_tx.txid(),
+            _ => return Ok(reply),
+        };
+        let mut exceeded = None;
+        if let Some(threshold) = self.config.approval_threshold {
+            for moved in self.moved_transfers(transfer)? {
+                let foreign = self.foreign_change(&moved)?;
+                if moved.outgoing_amount(&foreign) > threshold {
+                    exceeded = Some(threshold);
+                    break;
+                }
+            }
+        }
+        let threshold = match exceeded {
+            Some(threshold) => threshold,
+            None => {
+                if let Reply::Transfer(_) = reply {
+                    self.track_transfer(
+                        txid,
+                        reply::TransferState::Consigned,
+                        None,
+                    )?;
+                }
+                return Ok(reply);
+            }
+        };
+        info!(
+            "Transfer {} exceeds approval threshold of {}; waiting for \
+             operator approval",
+            txid, threshold
+        );
+        self.cacher.hold_reply(txid, reply.serialize())?;
+        Ok(Reply::ApprovalRequired(txid))
+    }
+
+    fn rpc_approve_transfer(
+        &mut self,
+        req: &ApproveTransferReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        if !self.cacher.pending_transfers()?.is_held(&req.txid) {
+            Err(TransferError::UnknownTransfer(req.txid))?
+        }
+        if !self.config.approvers.contains(&req.approver) {
+            Err(TransferError::UnknownApprover(req.approver))?
+        }
+        if !req.verify() {
+            Err(TransferError::InvalidApproval(req.txid))?
+        }
+        let raw = self
+            .cacher
+            .release_reply(&req.txid)?
+            .expect("pending approval presence is checked above");
+        let reply = rpc::take(self.reply_unmarshaller.unmarshall(&raw)?);
+        info!("Transfer {} is approved by {}", req.txid, req.approver);
+        if let Reply::Transfer(_) = reply {
+            self.track_transfer(
+                req.txid,
+                reply::TransferState::Consigned,
+                None,
+            )?;
+        }
+        Ok(reply)
+    }
+
+    /// Sets or clears administrative hold, if the request is signed by one
+    /// of the registered admins
+    fn rpc_set_hold(
+        &mut self,
+        req: &SetHoldReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        if !self.config.admins.contains(&req.admin) {
+            Err(TransferError::UnknownAdmin(req.admin))?
+        }
+        let age = (Utc::now().timestamp() - req.timestamp).abs();
+        if age > FUNGIBLED_SIGNATURE_TTL as i64 || !req.verify() {
+            Err(TransferError::InvalidAdminSignature)?
+        }
+        if !self.cacher.set_hold(req.target, req.held)? {
+            return Ok(Reply::Nothing);
+        }
+        if req.held {
+            info!("{} is put on hold by {}", req.target, req.admin);
+        } else {
+            info!("Hold of {} is released by {}", req.target, req.admin);
+        }
+        Ok(Reply::Success)
+    }
+
+    fn rpc_commit_transfer(
+        &mut self,
+        txid: Txid,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got COMMIT {}", txid);
+        if self.cacher.pending_transfers()?.is_held(&txid) {
+            Err(TransferError::ApprovalPending(txid))?
+        }
+        let disclosure = self
+            .cacher
+            .pending_transfers()?
+            .prepared(&txid)
+            .ok_or(TransferError::UnknownTransfer(txid))?
+            .disclosure
+            .clone();
+        let reply = self.enclose(disclosure)?;
+        self.cacher.remove_prepared_transfer(&txid)?;
+        Ok(reply)
+    }
+
+    fn rpc_prepare_signing(
+        &mut self,
+        transfer: &TransferReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got PREPARE SIGNING {}", transfer);
+        let mut reply = self.rpc_prepare_transfer(transfer)?;
+        if let Reply::Transfer(ref mut prepared) = reply {
+            let txid = prepared.witness.global.unsigned_tx.txid();
+            if let Err(err) = self
+                .populate_witness(&mut prepared.witness)
+                .and_then(|_| self.cosign(transfer, &mut prepared.witness))
+            {
+                self.cacher.remove_prepared_transfer(&txid)?;
+                self.cacher.release_inputs(txid)?;
+                Err(err)?
+            }
+            self.cacher.add_prepared_transfer(txid, prepared.clone())?;
+        }
+        Ok(reply)
+    }
+
+    /// Fills witness PSBT inputs with the spent transactions and adds key
+    /// derivation paths of the external signer
+    fn populate_witness(
+        &self,
+        witness: &mut Psbt,
+    ) -> Result<(), ServiceErrorDomain> {
+        let txids = witness
+            .global
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output.txid)
+            .collect::<BTreeSet<_>>();
+        trace!("Fetching {} transactions spent by the witness", txids.len());
+        let transactions = ElectrumClient::new(&self.config.electrum_server)
+            .and_then(|electrum| electrum.batch_transaction_get(&txids))
+            .map_err(|err| {
+                error!("Unable to fetch spent transactions: {:?}", err);
+                ServiceErrorDomain::Electrum
+            })?
+            .into_iter()
+            .map(|tx| (tx.txid(), tx))
+            .collect();
+        if self.keychain.is_empty() {
+            warn!(
+                "No descriptors are registered; key derivation paths are not \
+                 added to the witness PSBT"
+            );
+        }
+        self.keychain
+            .populate(witness, &transactions)
+            .map_err(|outpoint| {
+                ServiceErrorDomain::Internal(format!(
+                    "Electrum server has not returned transaction spent by {}",
+                    outpoint
+                ))
+            })
+    }
+
+    fn electrum(&self) -> Result<ElectrumClient, ServiceErrorDomain> {
+        ElectrumClient::new(&self.config.electrum_server).map_err(|err| {
+            error!("Unable to connect to Electrum server: {:?}", err);
+            ServiceErrorDomain::Electrum
+        })
+    }
+
+    /// Lists unspent outputs of the registered descriptors
+    fn funding_utxos(
+        &self,
+        electrum: &ElectrumClient,
+    ) -> Result<Vec<funding::Utxo>, ServiceErrorDomain> {
+        if self.keychain.is_empty() {
+            Err(FundingError::NoDescriptors)?
+        }
+        funding::scan(electrum, &self.keychain).map_err(|err| {
+            error!("Unable to list descriptor outputs: {:?}", err);
+            ServiceErrorDomain::Electrum
+        })
+    }
+
+    /// Detects whether the descriptor output can't be spent to pay the fees
+    /// since it holds assets or is reserved
+    fn is_funding_locked(
+        &self,
+        outpoint: OutPoint,
+    ) -> Result<bool, ServiceErrorDomain> {
+        Ok(self.cacher.input_reservation(outpoint)?.is_some()
+            || self.cacher.outpoint_reservation(outpoint)?.is_some()
+            || self.has_allocations(outpoint)?)
+    }
+
+    /// Composes witness transaction from the unspent outputs of the
+    /// registered descriptors. Unconfirmed and locked outputs are not used
+    /// to pay the fee; the change goes to the first unused change script.
+    fn rpc_compose_witness(
+        &mut self,
+        req: &ComposeWitnessReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        let change_scripts = self.keychain.chain_scripts(funding::CHANGE_CHAIN);
+        if change_scripts.is_empty() {
+            Err(ComposeError::NoChange)?
+        }
+        self.release_stale_reservations()?;
+
+        let electrum = self.electrum()?;
+        let unspent = self.funding_utxos(&electrum)?;
+        let change = funding::unused_script(
+            &electrum,
+            &self.keychain,
+            funding::CHANGE_CHAIN,
+            &bset! {},
+        )
+        .map_err(|err| {
+            error!("Unable to fetch change script history: {:?}", err);
+            ServiceErrorDomain::Electrum
+        })?
+        .unwrap_or_else(|| change_scripts[0].clone());
+
+        let holds = self.cacher.holds()?.clone();
+        let mut inputs = vec![];
+        for outpoint in &req.inputs {
+            let target = HoldTarget::Outpoint(*outpoint);
+            if holds.is_held(&target) {
+                Err(TransferError::AllocationOnHold(target))?
+            }
+            let txout = unspent
+                .iter()
+                .find(|utxo| utxo.outpoint == *outpoint)
+                .map(|utxo| utxo.txout.clone())
+                .ok_or(ComposeError::UnknownInput(*outpoint))?;
+            inputs.push((*outpoint, txout));
+        }
+        let mut candidates = vec![];
+        for utxo in unspent {
+            if utxo.height == 0
+                || req.inputs.contains(&utxo.outpoint)
+                || holds.is_held(&HoldTarget::Outpoint(utxo.outpoint))
+                || self.is_funding_locked(utxo.outpoint)?
+            {
+                continue;
+            }
+            candidates.push((utxo.outpoint, utxo.txout));
+        }
+
+        let container = req.container.unwrap_or(self.config.anchor_container);
+        let mut template = composer::compose(
+            &inputs,
+            &candidates,
+            change,
+            req.fee_rate,
+            container,
+        )?;
+        self.populate_witness(&mut template.psbt)?;
+        debug!(
+            "Composed witness transaction {} with {} funding inputs and fee \
+             of {} sats",
+            template.psbt.global.unsigned_tx.txid(),
+            template.funding.len(),
+            template.fee
+        );
+        Ok(Reply::WitnessTemplate(reply::WitnessTemplate {
+            witness: template.psbt,
+            funding: template.funding,
+            fee: template.fee,
+            change_vout: template.change_vout,
+        }))
+    }
+
+    fn rpc_funding_balance(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got FUNDING_BALANCE");
+        self.release_stale_reservations()?;
+        let electrum = self.electrum()?;
+        let utxos = self.funding_utxos(&electrum)?;
+        let mut locked = BTreeSet::new();
+        for utxo in &utxos {
+            if self.is_funding_locked(utxo.outpoint)? {
+                locked.insert(utxo.outpoint);
+            }
+        }
+        Ok(Reply::FundingBalance(funding::balance(
+            &utxos,
+            |outpoint| locked.contains(&outpoint),
+        )))
+    }
+
+    /// Returns first unused receive address of the descriptors which was
+    /// not given out since the daemon start
+    fn rpc_new_funding_address(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got NEW_FUNDING_ADDRESS");
+        if self.keychain.is_empty() {
+            Err(FundingError::NoDescriptors)?
+        }
+        let electrum = self.electrum()?;
+        let (script, _) = funding::unused_script(
+            &electrum,
+            &self.keychain,
+            funding::RECEIVE_CHAIN,
+            &self.revealed_funding,
+        )
+        .map_err(|err| {
+            error!("Unable to fetch funding script history: {:?}", err);
+            ServiceErrorDomain::Electrum
+        })?
+        .ok_or(FundingError::GapLimit(FUNGIBLED_DESCRIPTOR_GAP))?;
+        let network = bitcoin::Network::try_from(&self.config.network)
+            .unwrap_or(match self.config.network {
+                Chain::Regtest(_) => bitcoin::Network::Regtest,
+                _ => bitcoin::Network::Signet,
+            });
+        let address =
+            Address::from_script(&script, network).ok_or_else(|| {
+                ServiceErrorDomain::Internal(format!(
+                    "descriptor script {} has no address form",
+                    script
+                ))
+            })?;
+        self.revealed_funding.insert(script);
+        Ok(Reply::FundingAddress(address.to_string()))
+    }
+
+    /// Obtains signatures from the co-signing service, if one is configured
+    /// and the asset spending policy allows the transfer to be signed
+    /// without operator involvement
+    fn cosign(
+        &self,
+        transfer: &TransferReq,
+        witness: &mut Psbt,
+    ) -> Result<(), ServiceErrorDomain> {
+        let signer = match self.config.signer {
+            Some(ref signer) => signer,
+            None => return Ok(()),
+        };
+        // Other assets moved by the transfer must not leave the node beyond
+        // the auto-signing limits of their policies as well
+        for moved in self.moved_transfers(transfer)? {
+            let foreign = self.foreign_change(&moved)?;
+            let allowed = self.policy.allows_auto_signing(&moved, &foreign)
+                || (moved.contract_id != transfer.contract_id
+                    && moved.outgoing_amount(&foreign) == 0);
+            if !allowed {
+                debug!(
+                    "Transfer of {} is not within auto-signing limit of the \
+                     policy",
+                    moved.contract_id
+                );
+                return Ok(());
+            }
+        }
+        debug!("Requesting signatures from co-signing service {}", signer);
+        signer.sign(witness).map_err(|err| {
+            error!("Co-signing service has failed: {}", err);
+            ServiceErrorDomain::from(err)
+        })
+    }
+
+    fn rpc_finalize_transfer(
+        &mut self,
+        signed: &Psbt,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        let txid = signed.global.unsigned_tx.txid();
+        debug!("Got FINALIZE {}", txid);
+        if self.cacher.pending_transfers()?.is_held(&txid) {
+            Err(TransferError::ApprovalPending(txid))?
+        }
+        let prepared = &self
+            .cacher
+            .pending_transfers()?
+            .prepared(&txid)
+            .ok_or(TransferError::UnknownTransfer(txid))?
+            .witness;
+
+        let mismatch = TransferError::AnchorMismatch(txid);
+        let anchors = psbt::anchors(signed).map_err(|_| mismatch)?;
+        let transitions = psbt::transitions(prepared).map_err(|_| mismatch)?;
+        if Some(&anchors) != psbt::anchors(prepared).ok().as_ref()
+            || transitions.iter().any(|(contract_id, transition)| {
+                anchors
+                    .get(contract_id)
+                    .map(|anchor| {
+                        anchor.txid != txid
+                            || !anchor
+                                .validate(contract_id, &transition.node_id())
+                    })
+                    .unwrap_or(true)
+            })
+        {
+            Err(mismatch)?
+        }
+
+        let mut signed = signed.clone();
+        keychain::finalize(&mut signed)
+            .map_err(|input| TransferError::UnsignedInput { txid, input })?;
+        self.track_transfer(txid, reply::TransferState::Signed, None)?;
+        let tx = signed.extract_tx();
+        ElectrumClient::new(&self.config.electrum_server)
+            .and_then(|electrum| electrum.transaction_broadcast(&tx))
+            .map_err(|err| {
+                error!("Unable to broadcast witness transaction: {:?}", err);
+                ServiceErrorDomain::Electrum
+            })?;
+        info!("Witness transaction {} is broadcasted", txid);
+        self.track_transfer(txid, reply::TransferState::Broadcast, None)?;
+        self.rpc_commit_transfer(txid)
+    }
+
+    fn rpc_cancel_transfer(
+        &mut self,
+        txid: Txid,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CANCEL {}", txid);
+        let approval = self.cacher.release_reply(&txid)?;
+        if self.cacher.remove_prepared_transfer(&txid)?.is_none()
+            && approval.is_none()
+        {
+            Err(TransferError::UnknownTransfer(txid))?
+        }
+        self.cacher.release_inputs(txid)?;
+        self.track_transfer(
+            txid,
+            reply::TransferState::Failed,
+            Some(s!("cancelled")),
+        )?;
+        Ok(Reply::Success)
+    }
+
+    fn rpc_list_transfers(&self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got LIST_TRANSFERS");
+        Ok(Reply::Transfers(self.cacher.transfers()?.list()))
+    }
+
+    fn rpc_transfer_status(
+        &self,
+        txid: Txid,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got TRANSFER_STATUS {}", txid);
+        let status = self
+            .cacher
+            .transfers()?
+            .get(&txid)
+            .cloned()
+            .ok_or(TransferError::UnknownTransfer(txid))?;
+        Ok(Reply::TransferStatus(status))
+    }
+
+    fn rpc_export_accepted_consignment(
+        &self,
+        consignment_id: sha256::Hash,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got EXPORT_ACCEPTED_CONSIGNMENT {}", consignment_id);
+        let before = Utc::now().timestamp()
+            - self.config.consignment_retention_days as i64 * 24 * 60 * 60;
+        match self.cacher.accepted_consignments()?.get(&consignment_id) {
+            Some(kept) if kept.accepted >= before => {
+                Ok(Reply::Consignment(kept.consignment.clone()))
+            }
+            _ => Ok(Reply::Nothing),
+        }
+    }
+
+    fn rpc_reconsign(
+        &mut self,
+        txid: Txid,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got RECONSIGN {}", txid);
+        let contract_id = self
+            .cacher
+            .transfers()?
+            .get(&txid)
+            .ok_or(TransferError::UnknownTransfer(txid))?
+            .contract_id;
+        let payees = self.cacher.payees()?.get(&txid);
+        let request = ReconsignRequest {
+            contract_id,
+            txid,
+            payees,
+        };
+        match self.stash_req_rep(rpc::stash::Request::Reconsign(request))? {
+            reply @ Reply::Consignment(_) | reply @ Reply::Failure(_) => {
+                Ok(reply)
+            }
+            Reply::Nothing => Err(TransferError::WitnessUnknown(txid))?,
+            _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
+        }
+    }
+
+    fn rpc_export_claims(
+        &mut self,
+        passphrase: &str,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got EXPORT_CLAIMS");
+        if passphrase.is_empty() {
+            Err(Self::malformed_claims("export_claims", "empty passphrase"))?
+        }
+        let mut contracts = vec![];
+        for asset in self.cacher.assets()? {
+            let contract_id = *asset.id();
+            contracts.push(ContractClaims {
+                genesis: self.export_asset(contract_id)?,
+                history: self.contract_history(contract_id)?,
+                asset,
+            });
+        }
+        let claims = Claims {
+            created: Utc::now().timestamp(),
+            contracts,
+            reveals: self.cacher.reveals()?,
+            descriptors: self
+                .config
+                .descriptors
+                .iter()
+                .map(keychain::Descriptor::to_string)
+                .collect(),
+        };
+        let data = claims
+            .seal(passphrase)
+            .map_err(|err| ServiceErrorDomain::Storage(err.to_string()))?;
+        info!(
+            "Exported claims on {} contracts and {} reveals",
+            claims.contracts.len(),
+            claims.reveals.len()
+        );
+        Ok(Reply::Claims(data))
+    }
+
+    fn rpc_import_claims(
+        &mut self,
+        req: &ImportClaimsReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        let claims = Claims::open(&req.data, req.passphrase.as_str())
+            .map_err(|err| Self::malformed_claims("import_claims", err))?;
+        for contract in &claims.contracts {
+            self.check_network(&contract.genesis)?;
+        }
+        self.register_schema()?;
+
+        let mut report = reply::ClaimsImport {
+            contracts: 0,
+            reveals: 0,
+            descriptors: claims.descriptors,
+        };
+        for contract in claims.contracts {
+            // Cached asset already has the allocations resulting from the
+            // history, so the history is added to the stash only
+            self.import_asset(contract.asset, contract.genesis)?;
+            match self
+                .stash_req_rep(rpc::stash::Request::Enclose(contract.history))?
+            {
+                Reply::Success => report.contracts += 1,
+                reply @ Reply::Failure(_) => return Ok(reply),
+                _ => {
+                    Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply))?
+                }
+            }
+        }
+        for reveal in claims.reveals {
+            if self.cacher.reveal(reveal.commit_conceal())?.is_none() {
+                self.cacher.add_reveal(reveal)?;
+                report.reveals += 1;
+            }
+        }
+        info!("Claims imported: {}", report);
+        Ok(Reply::ClaimsImport(report))
+    }
+
+    fn rpc_backup(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got BACKUP");
+        if self.config.backup.is_none() {
+            Err(ServiceErrorDomain::Config(s!(
+                "backup target is not configured"
+            )))?
+        }
+        Ok(Reply::Backup(self.backup()?))
+    }
+
+    fn malformed_claims(
+        request: &str,
+        err: impl ToString,
+    ) -> ServiceErrorDomain {
+        ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+            request: request.to_string(),
+            argument: err.to_string(),
+        })
+    }
+
+    /// Moves outgoing transfer to the given state, if it is tracked by the
+    /// node and the state change is allowed
+    fn track_transfer(
+        &mut self,
+        txid: Txid,
+        state: reply::TransferState,
+        reason: Option<String>,
+    ) -> Result<(), ServiceErrorDomain> {
+        let now = Utc::now().timestamp();
+        if self.cacher.update_transfer(txid, state, reason, now)? {
+            debug!("Transfer {} is {}", txid, state);
+            if let Some(status) = self.cacher.transfers()?.get(&txid) {
+                self.publish(Event::TransferUpdated(status.clone()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Releases inputs reserved by the transfers which have timed out or
+    /// whose witness transaction is already mined. Pending two-phase
+    /// transfers are dropped only on timeout, since the mined ones still
+    /// have to be committed.
+    fn release_stale_reservations(&mut self) -> Result<(), ServiceErrorDomain> {
+        let now = Utc::now().timestamp();
+        let mut electrum = None;
+        let mut expired = vec![];
+        let mut mined = vec![];
+        for reservation in self.cacher.reservations()? {
+            if reservation.expires <= now {
+                expired.push(reservation.txid);
+                continue;
+            }
+            if electrum.is_none() {
+                electrum =
+                    ElectrumClient::new(&self.config.electrum_server).ok();
+            }
+            let confirmations = electrum.as_ref().and_then(|electrum| {
+                Self::confirmations(
+                    electrum,
+                    &OutPoint::new(reservation.txid, 0),
+                )
+            });
+            if confirmations.unwrap_or_default() > 0 {
+                mined.push(reservation.txid);
+            }
+        }
+        for txid in expired {
+            debug!("Reservation for pending spend {} has expired", txid);
+            self.cacher.release_inputs(txid)?;
+            self.cacher.remove_prepared_transfer(&txid)?;
+            self.cacher.release_reply(&txid)?;
+            self.track_transfer(
+                txid,
+                reply::TransferState::Failed,
+                Some(s!("reservation timed out")),
+            )?;
+        }
+        for txid in mined {
+            debug!("Witness transaction {} is mined; releasing inputs", txid);
+            self.cacher.release_inputs(txid)?;
+        }
+        let count = self.cacher.release_expired_outpoints(now)?;
+        if count > 0 {
+            debug!("Released {} outpoints with expired reservations", count);
+        }
+        Ok(())
+    }
+
+    fn rpc_reserve_outpoint(
+        &mut self,
+        req: &ReserveOutpointReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        self.release_stale_reservations()?;
+        if let Some(reservation) =
+            self.cacher.input_reservation(req.outpoint)?
+        {
+            Err(TransferError::InputReserved {
+                outpoint: req.outpoint,
+                txid: reservation.txid,
+            })?
+        }
+        if let Some(reservation) =
+            self.cacher.outpoint_reservation(req.outpoint)?
+        {
+            if reservation.purpose != req.purpose {
+                Err(TransferError::OutpointReserved(req.outpoint))?
+            }
+        }
+        self.cacher.reserve_outpoint(OutpointReservation {
+            outpoint: req.outpoint,
+            purpose: req.purpose.clone(),
+            expires: Utc::now().timestamp() + req.duration as i64,
+        })?;
+        Ok(Reply::Success)
+    }
+
+    fn rpc_release_outpoint(
+        &mut self,
+        outpoint: OutPoint,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got RELEASE_OUTPOINT {}", outpoint);
+        match self.cacher.release_outpoint(outpoint)? {
+            Some(reservation) => {
+                debug!(
+                    "Outpoint {} reserved for {} is released",
+                    outpoint, reservation.purpose
+                );
+                Ok(Reply::Success)
+            }
+            None => Ok(Reply::Nothing),
+        }
+    }
+
+    /// Bitcoin status of the outpoint, unless the allocation is on hold or
+    /// the outpoint is reserved for an external protocol
+    fn allocation_status(
+        &self,
+        contract_id: ContractId,
+        outpoint: OutPoint,
+    ) -> reply::AllocationStatus {
+        if let Ok(Some(_)) = self
+            .cacher
+            .holds()
+            .map(|holds| holds.hold(contract_id, outpoint))
+        {
+            return reply::AllocationStatus::Held;
+        }
+        match self.cacher.outpoint_reservation(outpoint) {
+            Ok(Some(reservation))
+                if reservation.expires > Utc::now().timestamp() =>
+            {
+                reply::AllocationStatus::Reserved
+            }
+            _ => self.watcher.status(outpoint),
+        }
+    }
+
+    /// Prepares own asset leg of an atomic swap. Inputs holding other
+    /// assets are rejected, since the counterparty does not know about them
+    /// and can't anchor blank transitions for them.
+    fn swap_leg(
+        &mut self,
+        send: &SwapSend,
+        witness: &Psbt,
+    ) -> Result<AssetLeg, ServiceErrorDomain> {
+        self.check_reservations(&send.inputs)?;
+        self.check_account_inputs(&send.inputs)?;
+        self.check_holds(send.contract_id, &send.inputs)?;
+        let mut transfer = send.transfer_req(witness);
+        if let Some(dust_limit) = self.config.dust_limit {
+            transfer.apply_dust_limit(dust_limit)?;
+        }
+
+        for outpoint in &transfer.inputs {
+            for (contract_id, amounts) in
+                self.cacher.outpoint_assets(*outpoint)?
+            {
+                if contract_id != transfer.contract_id
+                    && amounts.into_iter().sum::<AtomicValue>() > 0
+                {
+                    Err(SwapError::OtherAssets {
+                        outpoint: *outpoint,
+                        contract_id,
+                    })?
+                }
+            }
+        }
+
+        let asset = self.cacher.asset(transfer.contract_id)?.clone();
+        let inputs: BTreeSet<OutPoint> = transfer
+            .inputs
+            .iter()
+            .filter(|outpoint| !asset.allocations(**outpoint).is_empty())
+            .cloned()
+            .collect();
+        self.check_policy(&transfer, &inputs)?;
+        let transition = rgb20::transfer(
+            &asset,
+            inputs.clone(),
+            transfer.payment.clone(),
+            transfer.change.clone(),
+        )?;
+        debug!("Swap state transition: {}", transition);
+
+        let history = self.history(HistoryRequest {
+            contract_id: transfer.contract_id,
+            transition: transition.clone(),
+            endpoints: transfer.payment.keys().copied().collect(),
+        })?;
+        Ok(AssetLeg {
+            contract_id: transfer.contract_id,
+            inputs,
+            transition,
+            history,
+        })
+    }
+
+    /// Reserves inputs of the asset leg until the swap witness transaction
+    /// is mined or the reservation times out
+    fn reserve_swap_inputs(
+        &mut self,
+        leg: &AssetLeg,
+        txid: Txid,
+    ) -> Result<(), ServiceErrorDomain> {
+        trace!("Reserving swap inputs for pending spend {}", txid);
+        self.cacher.reserve_inputs(Reservation {
+            txid,
+            inputs: leg.inputs.clone(),
+            expires: Utc::now().timestamp()
+                + self.config.reservation_timeout as i64,
+        })?;
+        Ok(())
+    }
+
+    /// Validates consignment for the asset received in the swap; witness
+    /// transaction is not mined yet, so only invalid consignments are
+    /// rejected
+    fn check_swap_consignment(
+        &mut self,
+        consignment: &Consignment,
+    ) -> Result<(), ServiceErrorDomain> {
+        self.check_network(&consignment.genesis)?;
+        if let Reply::ValidationStatus(status) =
+            self.validate(Shared::from(consignment.clone()))?
+        {
+            if status.validity() == Validity::Invalid {
+                debug!("Swap consignment validation failed: {}", status);
+                Err(SwapError::InvalidLeg(consignment.genesis.contract_id()))?
+            }
+        }
+        Ok(())
+    }
+
+    fn rpc_swap_offer(
+        &mut self,
+        req: &SwapOfferReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        let leg = self.swap_leg(&req.send, &req.witness)?;
+        self.reserve_swap_inputs(&leg, req.witness.global.unsigned_tx.txid())?;
+        let reply = Reply::SwapLeg(SwapLeg {
+            asset: Some(leg),
+            witness: req.witness.clone(),
+        });
+        self.hold_for_approval(&req.send.transfer_req(&req.witness), reply)
+    }
+
+    fn rpc_swap_complete(
+        &mut self,
+        req: &SwapCompleteReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        swap::check_offer(&req.offer, &req.receive)?;
+
+        let mut witness = swap::merge_witness(&req.offer.witness, &req.witness);
+        let mut sent = None;
+        let own = match req.send {
+            Some(ref send) => {
+                let mut send = send.clone();
+                send.shift_vouts(
+                    req.offer.witness.global.unsigned_tx.output.len() as u32,
+                );
+                let leg = self.swap_leg(&send, &witness)?;
+                sent = Some(send.transfer_req(&witness));
+                Some(leg)
+            }
+            None => None,
+        };
+        if let (Some(offered), Some(own)) = (&req.offer.asset, &own) {
+            if offered.contract_id == own.contract_id {
+                Err(SwapError::SameAsset(own.contract_id))?
+            }
+        }
+
+        trace!("Anchoring swap legs in the witness transaction");
+        let legs = req.offer.asset.iter().chain(own.iter()).collect::<Vec<_>>();
+        let (mut consignments, disclosure) = swap::anchor(&legs, &mut witness)?;
+        let incoming = req
+            .offer
+            .asset
+            .as_ref()
+            .and_then(|leg| consignments.remove(&leg.contract_id));
+        let outgoing = own
+            .as_ref()
+            .and_then(|leg| consignments.remove(&leg.contract_id));
+
+        if let Some(ref consignment) = incoming {
+            self.check_swap_consignment(consignment)?;
+        }
+        if let Some(ref leg) = own {
+            self.reserve_swap_inputs(leg, witness.global.unsigned_tx.txid())?;
+        }
+
+        let reply = Reply::Swap(reply::Swap {
+            outgoing,
+            incoming,
+            disclosure,
+            witness,
+        });
+        match sent {
+            Some(transfer) => self.hold_for_approval(&transfer, reply),
+            None => Ok(reply),
+        }
+    }
+
+    fn rpc_swap_verify(
+        &mut self,
+        req: &SwapVerifyReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        let witness = &req.swap.witness;
+        let txid = witness.global.unsigned_tx.txid();
+        if !swap::includes_leg(witness, &req.offer.witness) {
+            Err(SwapError::WitnessMismatch)?
+        }
+        if let Some(ref leg) = req.offer.asset {
+            if !swap::is_anchored(
+                &req.swap.disclosure,
+                txid,
+                leg.contract_id,
+                &leg.transition,
+            ) {
+                Err(SwapError::NotAnchored(leg.contract_id))?
+            }
+        }
+
+        match req.receive {
+            SwapReceive::Asset {
+                contract_id,
+                ref allocations,
+            } => {
+                // Consignment sent by the counterparty completing the swap
+                let consignment = req
+                    .swap
+                    .outgoing
+                    .as_ref()
+                    .ok_or(SwapError::MissingAsset(contract_id))?;
+                swap::check_consignment(
+                    consignment,
+                    txid,
+                    contract_id,
+                    allocations,
+                )?;
+                self.check_swap_consignment(consignment)?;
+            }
+            SwapReceive::Bitcoin {
+                ref script_pubkey,
+                amount,
+            } => {
+                // Only the outputs added by the counterparty are counted
+                let offered = req.offer.witness.global.unsigned_tx.output.len();
+                swap::check_bitcoin(
+                    witness.global.unsigned_tx.output.iter().skip(offered),
+                    script_pubkey,
+                    amount,
+                )?;
+            }
+        }
+
+        if let Some(ref leg) = req.offer.asset {
+            self.cacher
+                .release_inputs(req.offer.witness.global.unsigned_tx.txid())?;
+            self.reserve_swap_inputs(leg, txid)?;
+        }
+        Ok(Reply::Success)
+    }
+
+    fn rpc_validate(
+        &mut self,
+        validate: &ValidateReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got VALIDATE");
+        self.check_network(&validate.consignment.genesis)?;
+        let reply =
+            self.validate(Shared::from(validate.consignment.clone()))?;
+        if let Reply::ValidationStatus(ref status) = reply {
+            if status.validity() == Validity::Valid {
+                let required = validate
+                    .min_confirmations
+                    .unwrap_or(self.config.min_confirmations);
+                if let Some(pending) =
+                    self.pending_confirmations(&validate.consignment, required)
+                {
+                    return Ok(Reply::Pending(pending));
+                }
+            }
+        }
+        Ok(reply)
+    }
+
+    fn rpc_accept(
+        &mut self,
+        accept: &AcceptReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got ACCEPT");
+        self.check_network(&accept.consignment.genesis)?;
+        let reveal_outpoints = if accept.reveal_outpoints.is_empty() {
+            self.blinded_endpoints(&accept.consignment)?
+        } else {
+            accept.reveal_outpoints.clone()
+        };
+        // Consignment is copied only once; all further processing, including
+        // the stash requests, works with the shared instance
+        let accept_req = AcceptRequest {
+            consignment: Shared::from(accept.consignment.clone()),
+            reveal_outpoints,
+        };
+        let required = accept
+            .min_confirmations
+            .unwrap_or(self.config.min_confirmations);
+        if let Some(pending) =
+            self.pending_confirmations(&accept_req.consignment, required)
+        {
+            // Invalid consignments are not kept pending; they are passed to
+            // the stash which reports the failure
+            if let Reply::ValidationStatus(status) =
+                self.validate(accept_req.consignment.clone())?
+            {
+                if status.validity() != Validity::Invalid {
+                    debug!(
+                        "Consignment {} is pending confirmations",
+                        pending.consignment_id
+                    );
+                    self.cacher.add_pending_accept(
+                        pending.consignment_id,
+                        accept_req,
+                        required,
+                    )?;
+                    return Ok(Reply::Pending(pending));
+                }
+            }
+        }
+        let consignment_id = sha256::Hash::from_inner(
+            accept_req.consignment.id().into_inner().into_inner(),
+        );
+        let reply = self.accept(accept_req)?;
+        if let Reply::Success = reply {
+            self.publish(Event::ConsignmentAccepted(consignment_id))?;
+        }
+        Ok(reply)
+    }
+
+    /// Accepts relayed consignment of a subscribed contract assigning assets
+    /// to the seals blinded by this node; the outcome is published as an
+    /// event. Consignments of other contracts are ignored.
+    fn rpc_auto_accept(
+        &mut self,
+        consignment: &Consignment,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got AUTO_ACCEPT");
+        let contract_id = consignment.genesis.contract_id();
+        let subscription = match self
+            .config
+            .subscriptions
+            .iter()
+            .find(|subscription| subscription.contract_id == contract_id)
+        {
+            Some(subscription) => *subscription,
+            None => {
+                trace!("Contract {} is not subscribed", contract_id);
+                return Ok(Reply::Nothing);
+            }
+        };
+        self.check_network(&consignment.genesis)?;
+        let consignment_id = sha256::Hash::from_inner(
+            consignment.id().into_inner().into_inner(),
+        );
+
+        let shared = Shared::from(consignment.clone());
+        let reply = self.validate(shared.clone())?;
+        match reply {
+            Reply::ValidationStatus(ref status)
+                if status.validity() == Validity::Valid => {}
+            _ => {
+                warn!("Relayed consignment {} is rejected", consignment_id);
+                self.publish(Event::ConsignmentRejected(consignment_id))?;
+                return Ok(reply);
+            }
+        }
+
+        let reveal_outpoints = self.blinded_endpoints(consignment)?;
+        if reveal_outpoints.is_empty() {
+            trace!(
+                "Consignment for contract {} has no seals owned by the node",
+                contract_id
+            );
+            self.track_peer_accept(consignment)?;
+            return Ok(Reply::Nothing);
+        }
+        let accept = AcceptRequest {
+            consignment: shared,
+            reveal_outpoints,
+        };
+
+        let required = subscription
+            .min_confirmations
+            .unwrap_or(self.config.min_confirmations);
+        if let Some(pending) =
+            self.pending_confirmations(&accept.consignment, required)
+        {
+            debug!("Consignment {} is pending confirmations", consignment_id);
+            if self.cacher.add_pending_accept(
+                consignment_id,
+                accept,
+                required,
+            )? {
+                self.publish(Event::ConsignmentPending(pending.clone()))?;
+            }
+            return Ok(Reply::Pending(pending));
+        }
+
+        let reply = self.accept(accept)?;
+        if let Reply::Success = reply {
+            info!("Relayed consignment {} is accepted", consignment_id);
+            self.track_peer_accept(consignment)?;
+            self.publish(Event::ConsignmentAccepted(consignment_id))?;
+        }
+        Ok(reply)
+    }
+
+    /// Relayed consignments spending our outputs are the ones accepted by
+    /// the payee of our transfers; they are tracked only once validated and
+    /// accepted by this node, if they assign assets to its seals
+    fn track_peer_accept(
+        &mut self,
+        consignment: &Consignment,
+    ) -> Result<(), ServiceErrorDomain> {
+        for txid in consignment.txids() {
+            self.track_transfer(
+                txid,
+                reply::TransferState::AcceptedByPeer,
+                None,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Reveal data for the consignment endpoints which were blinded by this
+    /// node with [`Request::Blind`]
+    fn blinded_endpoints(
+        &self,
+        consignment: &Consignment,
+    ) -> Result<Vec<OutpointReveal>, ServiceErrorDomain> {
+        let mut reveals = vec![];
+        for (_, endpoint) in &consignment.endpoints {
+            if let SealEndpoint::TxOutpoint(seal) = endpoint {
+                if let Some(reveal) = self.cacher.reveal(*seal)? {
+                    reveals.push(reveal);
+                }
+            }
+        }
+        Ok(reveals)
+    }
+
+    fn rpc_enclose(
+        &mut self,
+        disclosure: &Disclosure,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got ENCLOSE");
+        Ok(self.enclose(disclosure.clone())?)
+    }
+
+    fn rpc_blind(
+        &mut self,
+        outpoint: OutPoint,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got BLIND {}", outpoint);
+        let reveal = OutpointReveal::from(outpoint);
+        self.cacher.add_reveal(reveal)?;
+        Ok(Reply::OutpointReveal(reveal))
+    }
+
+    /// Blinds the receiving outpoint and creates the invoice. If the
+    /// outpoint is not given, the smallest confirmed unlocked funding output
+    /// is used and reserved for the receive, so it is not spent for the
+    /// fees until the transfer is accepted.
+    fn rpc_receive_asset(
+        &mut self,
+        req: &ReceiveAssetReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        self.cacher.asset(req.contract_id)?;
+        let created = Utc::now().timestamp();
+        let expires = created
+            + req.expiry.unwrap_or(self.config.reservation_timeout) as i64;
+        let (outpoint, reserved) = match req.utxo {
+            Some(outpoint) => (outpoint, false),
+            None => {
+                self.release_stale_reservations()?;
+                let electrum = self.electrum()?;
+                let mut utxos = self.funding_utxos(&electrum)?;
+                utxos.sort_by_key(|utxo| utxo.txout.value);
+                let mut selected = None;
+                for utxo in utxos {
+                    if utxo.height > 0
+                        && !self.is_funding_locked(utxo.outpoint)?
+                    {
+                        selected = Some(utxo.outpoint);
+                        break;
+                    }
+                }
+                let outpoint =
+                    selected.ok_or(FundingError::NoUnlockedOutput)?;
+                self.cacher.reserve_outpoint(OutpointReservation {
+                    outpoint,
+                    purpose: s!("receive"),
+                    expires,
+                })?;
+                debug!("Funding output {} is reserved for receive", outpoint);
+                (outpoint, true)
+            }
+        };
+
+        let reveal = OutpointReveal::from(outpoint);
+        self.cacher.add_reveal(reveal)?;
+        let invoice = Invoice {
+            contract_id: req.contract_id,
+            amount: req.amount,
+            endpoint: reveal.commit_conceal(),
+            expiry: Some(expires),
+        };
+        self.cacher.add_pending_receive(reply::PendingReceive {
+            invoice,
+            outpoint,
+            created,
+            expires,
+            reserved,
+        })?;
+        Ok(Reply::Receive(reply::Receive { reveal, invoice }))
+    }
+
+    fn rpc_list_pending_receives(&self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got LIST_PENDING_RECEIVES");
+        // Expired receives are dropped by the watcher; here we just skip them
+        let now = Utc::now().timestamp();
+        Ok(Reply::PendingReceives(
+            self.cacher
+                .pending_receives()?
+                .list()
+                .into_iter()
+                .filter(|receive| receive.expires > now)
+                .collect(),
+        ))
+    }
+
+    fn rpc_cancel_receive(
+        &mut self,
+        endpoint: OutpointHash,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CANCEL_RECEIVE {}", endpoint);
+        match self.close_receive(endpoint)? {
+            Some(_) => Ok(Reply::Success),
+            None => Ok(Reply::Nothing),
+        }
+    }
+
+    /// Removes pending receive, releasing the funding output reserved for it
+    fn close_receive(
+        &mut self,
+        endpoint: OutpointHash,
+    ) -> Result<Option<reply::PendingReceive>, ServiceErrorDomain> {
+        let receive = match self.cacher.remove_pending_receive(&endpoint)? {
+            Some(receive) => receive,
+            None => return Ok(None),
+        };
+        if receive.reserved {
+            self.cacher.release_outpoint(receive.outpoint)?;
+        }
+        Ok(Some(receive))
+    }
+
+    /// Drops pending receives which have expired, releasing their funding
+    /// outputs
+    fn expire_receives(&mut self) -> Result<(), ServiceErrorDomain> {
+        let now = Utc::now().timestamp();
+        for endpoint in self.cacher.pending_receives()?.expired(now) {
+            if let Some(receive) = self.close_receive(endpoint)? {
+                debug!("Receive {} has expired", receive.invoice);
+                self.publish(Event::ReceiveExpired(receive))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Closes pending receives paid by the accepted consignment
+    fn fulfill_receives(
+        &mut self,
+        reveals: &[OutpointReveal],
+    ) -> Result<(), ServiceErrorDomain> {
+        for reveal in reveals {
+            if let Some(receive) =
+                self.close_receive(reveal.commit_conceal())?
+            {
+                info!("Receive {} is fulfilled", receive.invoice);
+                self.publish(Event::ReceiveFulfilled(receive))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn rpc_forget(
+        &mut self,
+        outpoint: &OutPoint,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got FORGET");
+        Ok(self.forget(outpoint.clone())?)
+    }
+
+    fn rpc_restore(
+        &mut self,
+        outpoint: OutPoint,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got RESTORE {}", outpoint);
+        self.purge_tombstones()?;
+        let tombstones = self.cacher.exhume(outpoint)?;
+        if tombstones.is_empty() {
+            return Ok(Reply::Nothing);
+        }
+        for tombstone in tombstones {
+            trace!(
+                "Restoring allocation {}:{} of asset {}",
+                tombstone.node_id,
+                tombstone.index,
+                tombstone.contract_id
+            );
+            let mut asset = self.cacher.asset(tombstone.contract_id)?.clone();
+            asset.add_allocation(
+                tombstone.outpoint,
+                tombstone.node_id,
+                tombstone.index,
+                tombstone.value,
+            );
+            self.cacher.add_asset(asset)?;
+        }
+        Ok(Reply::Success)
+    }
+
+    fn rpc_forget_all(
+        &mut self,
+        dry_run: bool,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got FORGET_ALL (dry run: {})", dry_run);
+        let contract_ids = self
+            .cacher
+            .assets()?
+            .into_iter()
+            .map(|asset| *asset.id())
+            .collect();
+        self.rpc_forget_contracts(contract_ids, dry_run)
+    }
+
+    fn rpc_forget_contracts(
+        &mut self,
+        contract_ids: Vec<ContractId>,
+        dry_run: bool,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!(
+            "Got FORGET_CONTRACT {:?} (dry run: {})",
+            contract_ids, dry_run
+        );
+        let mut report = reply::ForgetReport {
+            dry_run,
+            assets: bmap! {},
+        };
+        let mut removal_list = Vec::<_>::new();
+        for contract_id in contract_ids {
+            let asset = self.cacher.asset(contract_id)?;
+            removal_list.extend(asset.known_allocations().iter().map(
+                |allocation| (*allocation.node_id(), *allocation.index()),
+            ));
+            report.assets.insert(
+                contract_id,
+                self.cacher.asset_allocations(contract_id)?,
+            );
+        }
+        if dry_run {
+            return Ok(Reply::Forgotten(report));
+        }
+
+        for contract_id in report.assets.keys() {
+            trace!("Removing asset {} from the cache", contract_id);
+            self.cacher.remove_asset(*contract_id)?;
+            self.channels.retain(|(_, channel_contract), _| {
+                channel_contract != contract_id
+            });
+        }
+        if !removal_list.is_empty() {
+            match self
+                .stash_req_rep(rpc::stash::Request::Forget(removal_list))?
+            {
+                Reply::Success => {}
+                reply @ Reply::Failure(_) => return Ok(reply),
+                _ => {
+                    Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply))?
+                }
+            }
+        }
+        Ok(Reply::Forgotten(report))
+    }
+
+    fn rpc_channel_funded(
+        &mut self,
+        req: &ChannelFundedReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CHANNEL_FUNDED {}", req);
+        let holds = self.cacher.holds()?;
+        let contract_ids = self
+            .cacher
+            .outpoint_assets(req.funding)?
+            .keys()
+            .copied()
+            .filter(|contract_id| match holds.hold(*contract_id, req.funding) {
+                Some(target) => {
+                    warn!(
+                        "Asset {} is not added to channel {} since {} is on \
+                         hold",
+                        contract_id, req.funding, target
+                    );
+                    false
+                }
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        let mut channels = vec![];
+        for contract_id in contract_ids {
+            channels.push(ChannelAssets::with(
+                self.cacher.asset(contract_id)?,
+                req.funding,
+                req.local_funded,
+            )?);
+        }
+        if channels.is_empty() {
+            debug!("Channel {} holds no known assets", req.funding);
+        }
+        for channel in channels {
+            trace!("Asset channel opened: {}", channel);
+            self.channels
+                .insert((req.funding, *channel.contract_id()), channel.clone());
+            self.publish(Event::AssetChannelOpened(channel))?;
+        }
+        Ok(Reply::Success)
+    }
+
+    fn rpc_channel_updated(
+        &mut self,
+        req: &ChannelUpdateReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CHANNEL_UPDATED {}", req);
+        let channel = self
+            .channels
+            .get_mut(&(req.funding, req.contract_id))
+            .ok_or(ChannelError::NoFunding {
+                contract_id: req.contract_id,
+                funding: req.funding,
+            })?;
+        channel.update(req.local, req.remote)?;
+        let channel = channel.clone();
+        self.publish(Event::AssetChannelUpdated(channel))?;
+        Ok(Reply::Success)
+    }
+
+    fn rpc_channel_closed(
+        &mut self,
+        funding: OutPoint,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CHANNEL_CLOSED {}", funding);
+        let keys = self
+            .channels
+            .keys()
+            .filter(|(outpoint, _)| *outpoint == funding)
+            .copied()
+            .collect::<Vec<_>>();
+        for key in keys {
+            if let Some(channel) = self.channels.remove(&key) {
+                self.publish(Event::AssetChannelClosed(channel))?;
+            }
+        }
+        // Assets from the funding output are released to the closing
+        // transaction outputs, which are added with the closing consignment
+        self.forget(funding)
+    }
+
+    fn rpc_sync(
+        &mut self,
+        data_format: DataFormat,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got SYNC");
+        let data = self.cacher.export(Some(data_format))?;
+        let assets = self.cacher.assets()?;
+        let meta = self
+            .cacher
+            .asset_meta()?
+            .select(assets.iter().map(|asset| asset.id()));
+        Ok(Reply::Sync(reply::AssetSync {
+            format: data_format,
+            data,
+            meta,
+            capabilities: reply::AssetCapabilities::with_assets(&assets),
+        }))
     }
 
-    fn rpc_validate(
+    fn rpc_outpoint_assets(
         &mut self,
-        consignment: &Consignment,
+        outpoint: OutPoint,
     ) -> Result<Reply, ServiceErrorDomain> {
-        debug!("Got VALIDATE");
-        self.validate(consignment.clone())
+        debug!("Got ASSETS");
+        let labels = self.cacher.labels()?;
+        let assets: BTreeMap<_, _> = self
+            .cacher
+            .outpoint_assets(outpoint)?
+            .into_iter()
+            .map(|(contract_id, amounts)| {
+                let status = self.allocation_status(contract_id, outpoint);
+                let labels = labels.allocation(contract_id, outpoint);
+                (
+                    contract_id,
+                    reply::AllocatedAmounts {
+                        status,
+                        amounts,
+                        labels,
+                    },
+                )
+            })
+            .collect();
+        let meta = self.cacher.asset_meta()?.select(assets.keys());
+        Ok(Reply::OutpointAssets(reply::OutpointAssets {
+            assets,
+            meta,
+        }))
     }
 
-    fn rpc_accept(
+    fn rpc_set_asset_meta(
         &mut self,
-        accept: &AcceptReq,
+        req: &SetAssetMetaReq,
     ) -> Result<Reply, ServiceErrorDomain> {
-        debug!("Got ACCEPT");
-        Ok(self.accept(accept.clone())?)
+        debug!("Got {}", req);
+        let malformed = |argument: &str| {
+            ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+                request: req.to_string(),
+                argument: argument.to_string(),
+            })
+        };
+        // Metadata may be attached only to the known assets
+        self.cacher.asset(req.contract_id)?;
+        if req.key.is_empty() || req.key.len() > FUNGIBLED_ASSET_META_SIZE {
+            Err(malformed("key"))?
+        }
+        if let Some(ref value) = req.value {
+            if value.len() > FUNGIBLED_ASSET_META_SIZE {
+                Err(malformed("value"))?
+            }
+            let meta = self.cacher.asset_meta()?.get(req.contract_id);
+            if !meta.contains_key(&req.key)
+                && meta.len() >= FUNGIBLED_ASSET_META_ENTRIES
+            {
+                Err(malformed("key"))?
+            }
+        }
+        if !self.cacher.set_asset_meta(
+            req.contract_id,
+            req.key.clone(),
+            req.value.clone(),
+        )? {
+            return Ok(Reply::Nothing);
+        }
+        Ok(Reply::Success)
     }
 
-    fn rpc_enclose(
+    fn rpc_get_asset_meta(
         &mut self,
-        disclosure: &Disclosure,
+        contract_id: ContractId,
     ) -> Result<Reply, ServiceErrorDomain> {
-        debug!("Got ENCLOSE");
-        Ok(self.enclose(disclosure.clone())?)
+        debug!("Got GET_ASSET_META {}", contract_id);
+        self.cacher.asset(contract_id)?;
+        Ok(Reply::AssetMeta(self.cacher.asset_meta()?.get(contract_id)))
     }
 
-    fn rpc_forget(
+    fn rpc_supply_report(
         &mut self,
-        outpoint: &OutPoint,
+        contract_id: ContractId,
     ) -> Result<Reply, ServiceErrorDomain> {
-        debug!("Got FORGET");
-        Ok(self.forget(outpoint.clone())?)
+        debug!("Got SUPPLY_REPORT {}", contract_id);
+        let asset = self.cacher.asset(contract_id)?.clone();
+        let genesis = self.export_asset(contract_id)?;
+        let history = self.contract_history(contract_id)?;
+        let report = supply::reconcile(&genesis, &history, &asset);
+        for mismatch in &report.mismatches {
+            warn!("Supply mismatch for {}: {}", contract_id, mismatch);
+        }
+        Ok(Reply::SupplyReport(report))
     }
 
-    fn rpc_sync(
+    fn rpc_epoch_seals(
         &mut self,
-        data_format: FileFormat,
+        contract_id: ContractId,
     ) -> Result<Reply, ServiceErrorDomain> {
-        debug!("Got SYNC");
-        let data = self.cacher.export(Some(data_format))?;
-        Ok(Reply::Sync(reply::SyncFormat(data_format, data)))
+        debug!("Got EPOCH_SEALS {}", contract_id);
+        self.cacher.asset(contract_id)?;
+        let genesis = self.export_asset(contract_id)?;
+        let history = self.contract_history(contract_id)?;
+        Ok(Reply::EpochSeals(epoch::seals(&genesis, &history)))
     }
 
-    fn rpc_outpoint_assets(
+    fn rpc_contract_capabilities(
         &mut self,
-        outpoint: OutPoint,
+        contract_id: ContractId,
     ) -> Result<Reply, ServiceErrorDomain> {
-        debug!("Got ASSETS");
-        let data = self.cacher.outpoint_assets(outpoint)?;
-        Ok(Reply::OutpointAssets(data))
+        debug!("Got CONTRACT_CAPABILITIES {}", contract_id);
+        let genesis = self.export_asset(contract_id)?;
+        let schema = self.read_schema(genesis.schema_id())?;
+        Ok(Reply::ContractCapabilities(capabilities::introspect(
+            &schema, &genesis,
+        )))
+    }
+
+    fn rpc_open_epoch(
+        &mut self,
+        req: &OpenEpochReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        let contract_id = req.contract_id;
+        let inputs = bset![req.epoch];
+        self.check_account_inputs(&inputs)?;
+        self.check_reservations(&inputs)?;
+        self.check_holds(contract_id, &inputs)?;
+        self.cacher.asset(contract_id)?;
+        let genesis = self.export_asset(contract_id)?;
+        let history = self.contract_history(contract_id)?;
+        let seal = epoch::seals(&genesis, &history)
+            .into_iter()
+            .find(|seal| {
+                seal.right == reply::EpochRight::Epoch
+                    && seal.outpoint == Some(req.epoch)
+            })
+            .ok_or_else(|| {
+                ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+                    request: req.to_string(),
+                    argument: s!("epoch"),
+                })
+            })?;
+        // Epoch rights carry no value, so the policy and approval checks
+        // see the opening as a zero-amount transfer to the epoch seals
+        let transfer = TransferReq {
+            contract_id,
+            witness: req.witness.clone(),
+            inputs: inputs.clone(),
+            payment: empty!(),
+            theirs_witness: empty!(),
+            change: req
+                .next_epoch
+                .iter()
+                .chain(req.burn_replace.iter())
+                .map(|seal| (*seal, 0))
+                .collect(),
+            confirmed_only: false,
+            container: None,
+        };
+        self.check_policy(&transfer, &inputs)?;
+
+        let transition = epoch::open(&seal, req.next_epoch, req.burn_replace);
+        debug!("State transition: {}", transition);
+
+        // Assets allocated on the epoch seal are moved to the epoch seals
+        // controlled by the node
+        let blank = self.blank_transfers(&transfer)?;
+        let (other_transitions, other_endpoints) =
+            self.blank_transitions(&blank)?;
+        let mut reply = self.consign(TransferRequest {
+            contract_id,
+            inputs: inputs.clone(),
+            transition,
+            other_transitions,
+            endpoints: transfer
+                .change
+                .keys()
+                .copied()
+                .map(SealEndpoint::from)
+                .collect(),
+            other_endpoints,
+            psbt: req.witness.clone(),
+            container: self.config.anchor_container,
+        })?;
+        if let Reply::Transfer(reply::Transfer {
+            ref mut witness, ..
+        }) = reply
+        {
+            psbt::mark_inputs(witness, contract_id, &inputs);
+            for other in &blank {
+                psbt::mark_inputs(witness, other.contract_id, &other.inputs);
+            }
+        }
+        self.hold_for_approval(&transfer, reply)
     }
 
     fn rpc_asset_allocations(
         &mut self,
-        contract_id: ContractId,
+        req: &AllocationsReq,
     ) -> Result<Reply, ServiceErrorDomain> {
-        debug!("Got ALLOCATIONS");
-        let data = self.cacher.asset_allocations(contract_id)?;
+        debug!("Got {}", req);
+        let contract_id = req.contract_id;
+        let labels = self.cacher.labels()?;
+        let data = self
+            .cacher
+            .asset_allocations(contract_id)?
+            .into_iter()
+            .map(|(outpoint, amounts)| {
+                let status = self.allocation_status(contract_id, outpoint);
+                let labels = labels.allocation(contract_id, outpoint);
+                (
+                    outpoint,
+                    reply::AllocatedAmounts {
+                        status,
+                        amounts,
+                        labels,
+                    },
+                )
+            })
+            .filter(|(_, allocated)| req.labels.is_subset(&allocated.labels))
+            .collect();
         Ok(Reply::AssetAllocations(data))
     }
 
+    fn rpc_set_label(
+        &mut self,
+        req: &SetLabelReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        if req.label.is_empty() || req.label.len() > FUNGIBLED_LABEL_SIZE {
+            Err(ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+                request: req.to_string(),
+                argument: s!("label"),
+            }))?
+        }
+        if req.set
+            && self.cacher.labels()?.get(&req.target).len()
+                >= FUNGIBLED_LABELS_PER_TARGET
+        {
+            Err(ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+                request: req.to_string(),
+                argument: s!("target"),
+            }))?
+        }
+        if !self
+            .cacher
+            .set_label(req.target, req.label.clone(), req.set)?
+        {
+            return Ok(Reply::Nothing);
+        }
+        Ok(Reply::Success)
+    }
+
+    fn rpc_export_ledger(
+        &mut self,
+        req: &ExportLedgerReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        let electrum = ElectrumClient::new(&self.config.electrum_server)
+            .map_err(|err| {
+                error!("Chain backend is not available: {:?}", err);
+                ServiceErrorDomain::Electrum
+            })?;
+        let mut blocks = BTreeMap::<Txid, Option<(u32, u32)>>::new();
+        let mut mined = |txid: Txid| {
+            blocks
+                .entry(txid)
+                .or_insert_with(|| Watcher::mined(&electrum, txid))
+                .filter(|(height, _)| *height <= req.height)
+        };
+
+        let mut entries = vec![];
+        for asset in self.cacher.assets()? {
+            for allocation in asset.known_allocations() {
+                let outpoint = *allocation.outpoint();
+                let (height, timestamp) = match mined(outpoint.txid) {
+                    Some(block) => block,
+                    None => continue,
+                };
+                let entry = LedgerEntry {
+                    height,
+                    timestamp,
+                    txid: outpoint.txid,
+                    asset: *asset.id(),
+                    outpoint,
+                    direction: Direction::Incoming,
+                    amount: allocation.revealed_amount().value,
+                };
+                if let Some(txid) = Watcher::spending_txid(&electrum, &outpoint)
+                {
+                    if let Some((height, timestamp)) = mined(txid) {
+                        entries.push(LedgerEntry {
+                            height,
+                            timestamp,
+                            txid,
+                            direction: Direction::Outgoing,
+                            ..entry.clone()
+                        });
+                    }
+                }
+                entries.push(entry);
+            }
+        }
+        let ledger = Ledger::with(entries);
+        Ok(Reply::Ledger(reply::Ledger {
+            format: req.format,
+            height: req.height,
+            data: ledger.export(req.format),
+        }))
+    }
+
     fn rpc_import_asset(
         &mut self,
         genesis: &Genesis,
     ) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got IMPORT_ASSET");
+        self.check_network(genesis)?;
         let asset = Asset::try_from(genesis.clone())?;
         self.import_asset(asset.clone(), genesis.clone())?;
         Ok(Reply::Asset(asset))
     }
 
+    fn rpc_import_from_consignment(
+        &mut self,
+        consignment: &Consignment,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got IMPORT_FROM_CONSIGNMENT");
+        let genesis = &consignment.genesis;
+        self.check_network(genesis)?;
+        let contract_id = genesis.contract_id();
+        if self.cacher.has_asset(contract_id)? {
+            debug!("Asset {} is already known", contract_id);
+            return Ok(Reply::Asset(self.cacher.asset(contract_id)?.clone()));
+        }
+        let mut asset = Asset::try_from(genesis.clone())?;
+        // Genesis allocations revealed in the consignment belong to other
+        // parties unless the consignment is accepted
+        for allocation in asset.known_allocations().clone() {
+            asset.remove_allocation(
+                *allocation.outpoint(),
+                *allocation.node_id(),
+                *allocation.index(),
+                allocation.revealed_amount().clone(),
+            );
+        }
+        self.register_schema()?;
+        self.import_asset(asset.clone(), genesis.clone())?;
+        Ok(Reply::Asset(asset))
+    }
+
+    fn rpc_bulk_import(
+        &mut self,
+        req: &BulkImportReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", req);
+        let file = fs::File::open(&req.filename)?;
+        let mut snapshot = SnapshotReader::with(io::BufReader::new(file))
+            .map_err(Self::malformed_snapshot)?;
+        let batch_size = match req.batch_size {
+            0 => FUNGIBLED_BULK_IMPORT_BATCH,
+            size => size,
+        } as usize;
+
+        // Batches which were imported before a malformed record is met are
+        // kept; since the known allocations are skipped, the import may be
+        // repeated after the snapshot is fixed
+        let mut report = reply::BulkImportReport::default();
+        loop {
+            let batch = (&mut snapshot)
+                .take(batch_size)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(Self::malformed_snapshot)?;
+            if batch.is_empty() {
+                break;
+            }
+            self.import_allocations(batch, &mut report)?;
+            debug!("Bulk import progress: {}", report);
+            self.publish(Event::BulkImportProgress(report))?;
+        }
+        info!("Bulk import from {} completed: {}", req.filename, report);
+        Ok(Reply::BulkImport(report))
+    }
+
+    fn malformed_snapshot(err: SnapshotError) -> ServiceErrorDomain {
+        ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+            request: s!("bulk_import"),
+            argument: err.to_string(),
+        })
+    }
+
+    /// Adds batch of allocations to their assets, saving each of the
+    /// modified assets to the cache only once
+    fn import_allocations(
+        &mut self,
+        batch: Vec<AllocationRecord>,
+        report: &mut reply::BulkImportReport,
+    ) -> Result<(), ServiceErrorDomain> {
+        let mut assets = BTreeMap::<ContractId, Asset>::new();
+        for record in batch {
+            let asset = match assets.entry(record.contract_id) {
+                btree_map::Entry::Occupied(entry) => entry.into_mut(),
+                btree_map::Entry::Vacant(entry) => {
+                    if !self.cacher.has_asset(record.contract_id)? {
+                        Err(ServiceErrorDomain::Api(
+                            ApiErrorType::MalformedArgument {
+                                request: s!("bulk_import"),
+                                argument: format!(
+                                    "unknown asset {}",
+                                    record.contract_id
+                                ),
+                            },
+                        ))?
+                    }
+                    entry.insert(self.cacher.asset(record.contract_id)?.clone())
+                }
+            };
+            report.records += 1;
+            if asset.add_allocation(
+                record.outpoint,
+                record.node_id,
+                record.index,
+                record.amount,
+            ) {
+                report.imported += 1;
+            } else {
+                report.duplicates += 1;
+            }
+        }
+        for (_, asset) in assets {
+            self.cacher.add_asset(asset)?;
+        }
+        Ok(())
+    }
+
     fn rpc_export_asset(
         &mut self,
         asset_id: &ContractId,
@@ -460,6 +3688,28 @@ impl Runtime {
         }
     }
 
+    fn read_schema(
+        &mut self,
+        schema_id: SchemaId,
+    ) -> Result<Schema, ServiceErrorDomain> {
+        match self.stash_req_rep(rpc::stash::Request::ReadSchema(schema_id))? {
+            Reply::Schema(schema) => Ok(schema),
+            _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
+        }
+    }
+
+    fn contract_history(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Disclosure, ServiceErrorDomain> {
+        match self
+            .stash_req_rep(rpc::stash::Request::ContractHistory(contract_id))?
+        {
+            Reply::Disclosure(disclosure) => Ok(disclosure),
+            _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
+        }
+    }
+
     fn consign(
         &mut self,
         transfer_req: TransferRequest,
@@ -473,9 +3723,19 @@ impl Runtime {
         }
     }
 
+    fn history(
+        &mut self,
+        history_req: HistoryRequest,
+    ) -> Result<Consignment, ServiceErrorDomain> {
+        match self.stash_req_rep(rpc::stash::Request::History(history_req))? {
+            Reply::Consignment(consignment) => Ok(consignment),
+            _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
+        }
+    }
+
     fn validate(
         &mut self,
-        consignment: Consignment,
+        consignment: Shared<Consignment>,
     ) -> Result<Reply, ServiceErrorDomain> {
         let reply =
             self.stash_req_rep(rpc::stash::Request::Validate(consignment))?;
@@ -488,19 +3748,16 @@ impl Runtime {
 
     fn accept(
         &mut self,
-        accept: AcceptReq,
+        accept: AcceptRequest,
     ) -> Result<Reply, ServiceErrorDomain> {
         let reply =
-            self.stash_req_rep(rpc::stash::Request::Accept(AcceptRequest {
-                consignment: accept.consignment.clone(),
-                reveal_outpoints: accept.reveal_outpoints.clone(),
-            }))?;
+            self.stash_req_rep(rpc::stash::Request::Accept(accept.clone()))?;
         if let Reply::Success = reply {
             let asset_id = accept.consignment.genesis.contract_id();
             let asset = if self.cacher.has_asset(asset_id)? {
                 self.cacher.asset(asset_id)?.clone()
             } else {
-                Asset::try_from(accept.consignment.genesis)?
+                Asset::try_from(accept.consignment.genesis.clone())?
             };
             // NB: Previously we were adding endpoint-only data; but I think
             // this filtering is not necessary
@@ -513,6 +3770,21 @@ impl Runtime {
                     .map(|(anchor, transition)| (transition, anchor.txid)),
                 &accept.reveal_outpoints,
             )?;
+            self.fulfill_receives(&accept.reveal_outpoints)?;
+            if self.config.consignment_retention_days > 0 {
+                let consignment_id = sha256::Hash::from_inner(
+                    accept.consignment.id().into_inner().into_inner(),
+                );
+                self.cacher.keep_consignment(
+                    consignment_id,
+                    (*accept.consignment).clone(),
+                    Utc::now().timestamp(),
+                )?;
+            }
+            Ok(reply)
+        } else if let Reply::AlreadyAccepted(consignment_id) = &reply {
+            // Asset data were already updated by the first acceptance
+            debug!("Consignment {} was accepted before", consignment_id);
             Ok(reply)
         } else if let Reply::Failure(_) = &reply {
             Ok(reply)
@@ -550,6 +3822,9 @@ impl Runtime {
                     .flatten();
                 self.update_asset(asset, data, &vec![])?;
             }
+            for (anchor, _) in disclosure.transitions().values() {
+                self.cacher.release_inputs(anchor.txid)?;
+            }
             Ok(reply)
         } else if let Reply::Failure(_) = &reply {
             Ok(reply)
@@ -562,15 +3837,17 @@ impl Runtime {
         &mut self,
         outpoint: OutPoint,
     ) -> Result<Reply, ServiceErrorDomain> {
+        self.purge_tombstones()?;
         let mut removal_list = Vec::<_>::new();
-        let assets = self
+        let contract_ids = self
             .cacher
-            .assets()?
-            .into_iter()
-            .map(Clone::clone)
+            .outpoint_assets(outpoint)?
+            .keys()
+            .copied()
             .collect::<Vec<_>>();
-        for asset in assets {
-            let mut asset = asset.clone();
+        let now = Utc::now().timestamp();
+        for contract_id in contract_ids {
+            let mut asset = self.cacher.asset(contract_id)?.clone();
             for allocation in asset.clone().allocations(outpoint) {
                 asset.remove_allocation(
                     outpoint,
@@ -579,6 +3856,14 @@ impl Runtime {
                     allocation.revealed_amount().clone(),
                 );
                 removal_list.push((*allocation.node_id(), *allocation.index()));
+                self.cacher.bury(Tombstone {
+                    contract_id: *asset.id(),
+                    outpoint,
+                    node_id: *allocation.node_id(),
+                    index: *allocation.index(),
+                    value: allocation.revealed_amount().clone(),
+                    buried: now,
+                })?;
             }
             self.cacher.add_asset(asset)?;
         }
@@ -595,6 +3880,28 @@ impl Runtime {
         }
     }
 
+    /// Removes forgotten allocations which can't be restored anymore
+    fn purge_tombstones(&mut self) -> Result<(), ServiceErrorDomain> {
+        let before = Utc::now().timestamp()
+            - self.config.tombstone_days as i64 * 24 * 60 * 60;
+        let count = self.cacher.purge_tombstones(before)?;
+        if count > 0 {
+            debug!("Purged {} expired forgotten allocations", count);
+        }
+        Ok(())
+    }
+
+    /// Removes accepted consignments kept longer than the retention window
+    fn purge_consignments(&mut self) -> Result<(), ServiceErrorDomain> {
+        let before = Utc::now().timestamp()
+            - self.config.consignment_retention_days as i64 * 24 * 60 * 60;
+        let count = self.cacher.purge_consignments(before)?;
+        if count > 0 {
+            debug!("Removed {} expired accepted consignments", count);
+        }
+        Ok(())
+    }
+
     fn update_asset<'a>(
         &mut self,
         mut asset: Asset,
@@ -645,30 +3952,344 @@ impl Runtime {
         Ok(())
     }
 
+    /// Performs scheduled checks of the bitcoin transactions
+    fn watch(&mut self) {
+        if let Err(err) = self.watch_allocations() {
+            error!("Error updating allocation statuses: {}", err);
+        }
+        if let Err(err) = self.finalize_pending_accepts() {
+            error!("Error accepting pending consignments: {}", err);
+        }
+        if let Err(err) = self.expire_receives() {
+            error!("Error expiring pending receives: {}", err);
+        }
+        if let Err(err) = self.watch_transfers() {
+            error!("Error updating outgoing transfer statuses: {}", err);
+        }
+        if let Err(err) = self.purge_consignments() {
+            error!("Error removing expired consignments: {}", err);
+        }
+    }
+
+    /// Performs scheduled backup, publishing its outcome
+    fn scheduled_backup(&mut self) {
+        let event = match self.backup() {
+            Ok(backup) => Event::BackupCompleted(backup),
+            Err(err) => {
+                error!("Error backing up node data: {}", err);
+                Event::BackupFailed(err.to_string())
+            }
+        };
+        if let Err(err) = self.publish(event) {
+            error!("Error publishing backup event: {}", err);
+        }
+    }
+
+    /// Backs up cache and stash into a new backup in the configured target
+    /// and removes the backups exceeding the configured number of kept ones
+    fn backup(&mut self) -> Result<reply::Backup, ServiceErrorDomain> {
+        self.last_backup = Instant::now();
+        let target = self.config.backup.clone().ok_or_else(|| {
+            ServiceErrorDomain::Config(s!("backup target is not configured"))
+        })?;
+        let name = backup_name(Utc::now());
+        trace!("Backing up node data into {} at {}", name, target);
+
+        self.cacher
+            .save()
+            .map_err(|err| ServiceErrorDomain::Storage(err.to_string()))?;
+        let cache_files =
+            target.store_dir(&name, "cache", Path::new(&self.config.cache))?;
+        match self.stash_req_rep(rpc::stash::Request::Backup(
+            BackupRequest {
+                target: target.to_string(),
+                backup: name.clone(),
+            },
+        ))? {
+            Reply::Success => {}
+            Reply::Failure(failure) => {
+                Err(ServiceErrorDomain::Storage(failure.to_string()))?
+            }
+            _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply))?,
+        }
+        let removed = target.rotate(self.config.backup_keep as usize)?;
+        for backup in &removed {
+            debug!("Removed outdated backup {}", backup);
+        }
+
+        let backup = reply::Backup {
+            name,
+            cache_files: cache_files as u32,
+            removed: removed.len() as u32,
+        };
+        info!("Node data backed up: {}", backup);
+        Ok(backup)
+    }
+
+    /// Checks whether witness transactions of the outgoing transfers are
+    /// broadcast or mined
+    fn watch_transfers(&mut self) -> Result<(), ServiceErrorDomain> {
+        let txids = self
+            .cacher
+            .transfers()?
+            .unconfirmed()
+            .into_iter()
+            .map(|status| status.txid)
+            .collect::<Vec<_>>();
+        if txids.is_empty() {
+            return Ok(());
+        }
+        trace!("Checking {} outgoing transfers", txids.len());
+        let electrum = self.electrum()?;
+        for txid in txids {
+            let state =
+                match Self::confirmations(&electrum, &OutPoint::new(txid, 0)) {
+                    Some(0) => reply::TransferState::Broadcast,
+                    Some(_) => reply::TransferState::Confirmed,
+                    None => continue,
+                };
+            self.track_transfer(txid, state, None)?;
+        }
+        Ok(())
+    }
+
+    /// Accepts pending consignments whose witness transactions have got the
+    /// required number of confirmations
+    fn finalize_pending_accepts(&mut self) -> Result<(), ServiceErrorDomain> {
+        let confirmed = self
+            .cacher
+            .pending_accepts()?
+            .iter()
+            .filter(|(_, pending)| {
+                self.pending_confirmations(
+                    &pending.accept.consignment,
+                    pending.required,
+                )
+                .is_none()
+            })
+            .map(|(consignment_id, _)| *consignment_id)
+            .collect::<Vec<_>>();
+        for consignment_id in confirmed {
+            let accept =
+                match self.cacher.remove_pending_accept(&consignment_id)? {
+                    Some(accept) => accept,
+                    None => continue,
+                };
+            debug!("Accepting confirmed consignment {}", consignment_id);
+            let consignment = accept.consignment.clone();
+            match self.accept(accept)? {
+                Reply::Success | Reply::AlreadyAccepted(_) => {
+                    self.track_peer_accept(&consignment)?;
+                    self.publish(Event::ConsignmentAccepted(consignment_id))?
+                }
+                reply => error!(
+                    "Pending consignment {} was not accepted: {}",
+                    consignment_id, reply
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that all consignment witness transactions have at least
+    /// `required` confirmations, returning information on the pending
+    /// consignment otherwise
+    fn pending_confirmations(
+        &self,
+        consignment: &Consignment,
+        required: u32,
+    ) -> Option<reply::PendingConsignment> {
+        if required == 0 {
+            return None;
+        }
+        let electrum = ElectrumClient::new(&self.config.electrum_server).ok();
+        let confirmations = consignment
+            .txids()
+            .into_iter()
+            .map(|txid| {
+                let depth = electrum
+                    .as_ref()
+                    .and_then(|electrum| {
+                        Self::confirmations(electrum, &OutPoint::new(txid, 0))
+                    })
+                    .unwrap_or_default();
+                (txid, depth)
+            })
+            .filter(|(_, depth)| *depth < required)
+            .collect::<BTreeMap<_, _>>();
+        if confirmations.is_empty() {
+            return None;
+        }
+        Some(reply::PendingConsignment {
+            consignment_id: sha256::Hash::from_inner(
+                consignment.id().into_inner().into_inner(),
+            ),
+            contract_id: consignment.genesis.contract_id(),
+            required,
+            confirmations,
+        })
+    }
+
+    /// Re-checks bitcoin status of all outpoints holding known asset
+    /// allocations
+    fn watch_allocations(&mut self) -> Result<(), ServiceErrorDomain> {
+        trace!("Updating status of the asset allocation outpoints");
+        self.last_watch = Instant::now();
+        let outpoints = self.cacher.allocation_outpoints()?;
+        let electrum = ElectrumClient::new(&self.config.electrum_server)
+            .map_err(|err| {
+                ServiceErrorDomain::Internal(format!("{:?}", err))
+            })?;
+        let changed = self.watcher.update(&electrum, &outpoints);
+        for (outpoint, status) in changed {
+            if status == reply::AllocationStatus::Spent {
+                self.publish(Event::AllocationSpent(outpoint))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns number of confirmations for the transaction creating the
+    /// outpoint, or `None` if it can't be determined
+    fn confirmations(
+        electrum: &ElectrumClient,
+        outpoint: &OutPoint,
+    ) -> Option<u32> {
+        let tip = electrum.block_headers_subscribe().ok()?.height as u32;
+        let tx = electrum.transaction_get(&outpoint.txid).ok()?;
+        let script = &tx.output.get(outpoint.vout as usize)?.script_pubkey;
+        let height = electrum
+            .script_get_history(script)
+            .ok()?
+            .into_iter()
+            .find(|item| item.tx_hash == outpoint.txid)?
+            .height;
+        if height <= 0 {
+            return Some(0);
+        }
+        Some((tip + 1).saturating_sub(height as u32))
+    }
+
+    /// Logs the event for the later replay and publishes it with its
+    /// sequence number
+    fn publish(&mut self, event: Event) -> Result<(), ServiceErrorDomain> {
+        let seq = self
+            .cacher
+            .log_event(event.serialize(), FUNGIBLED_EVENT_LOG_SIZE)?;
+        let record = EventRecord { seq, event };
+        trace!("Publishing event {}", record);
+        let data = strict_serialize(&record)
+            .map_err(|err| ServiceErrorDomain::Internal(err.to_string()))?;
+        self.fungible_pub.send_raw_message(&data)?;
+        self.notify_webhooks(&record.event)?;
+        Ok(())
+    }
+
+    /// Passes the event to the webhooks subscribed to its type
+    fn notify_webhooks(&self, event: &Event) -> Result<(), ServiceErrorDomain> {
+        let webhooks = match self.webhooks {
+            Some(ref webhooks) => webhooks,
+            None => return Ok(()),
+        };
+        let (webhook_event, data) = match event {
+            Event::TransferUpdated(status)
+                if status.state == reply::TransferState::Confirmed =>
+            {
+                (
+                    WebhookEvent::TransferConfirmed,
+                    format!(
+                        "{{\"txid\":\"{}\",\"contract_id\":\"{}\",\
+                         \"amount\":{}}}",
+                        status.txid, status.contract_id, status.amount
+                    ),
+                )
+            }
+            Event::ConsignmentAccepted(consignment_id) => (
+                WebhookEvent::ConsignmentReceived,
+                format!("{{\"consignment_id\":\"{}\"}}", consignment_id),
+            ),
+            Event::AllocationSpent(outpoint) => {
+                let assets = self
+                    .cacher
+                    .outpoint_assets(*outpoint)?
+                    .into_iter()
+                    .filter(|(_, amounts)| !amounts.is_empty())
+                    .map(|(contract_id, amounts)| {
+                        format!(
+                            "\"{}\":{}",
+                            contract_id,
+                            amounts.into_iter().sum::<AtomicValue>()
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",");
+                (
+                    WebhookEvent::AllocationSpent,
+                    format!(
+                        "{{\"outpoint\":\"{}\",\"assets\":{{{}}}}}",
+                        outpoint, assets
+                    ),
+                )
+            }
+            _ => return Ok(()),
+        };
+        webhooks.dispatch(webhook_event, data);
+        Ok(())
+    }
+
     fn stash_req_rep(
         &mut self,
         request: rpc::stash::Request,
     ) -> Result<Reply, ServiceErrorDomain> {
         let data = request.serialize();
+        drop(request);
         trace!(
             "Sending {} bytes to stashd: {}",
             data.len(),
             data.to_bech32data()
         );
-        self.stash_rpc_client.send_raw_message(data.borrow())?;
-        let raw = self.stash_rpc_client.recv_raw_message()?;
-        let reply = &*self.reply_unmarshaller.unmarshall(&raw)?.clone();
+        let raw = req_rep(&mut *self.stash_rpc_client, data)?;
+        let reply = rpc::take(self.reply_unmarshaller.unmarshall(&raw)?);
         if let Reply::Failure(ref failmsg) = reply {
             error!("Stash daemon has returned failure code: {}", failmsg);
             Err(ServiceErrorDomain::Stash)?
         }
-        Ok(reply.clone())
+        Ok(reply)
     }
 }
 
 pub fn main_with_config(config: Config) -> Result<(), BootstrapError> {
+    #[cfg(feature = "server")]
+    shutdown::install(config.rpc_endpoint.clone(), Request::Ping.serialize())?;
+    #[cfg(feature = "server")]
+    let credentials = config.credentials()?;
+    let relay_peers = config.relay_peers.clone();
+    let subscriptions = config.subscriptions.clone();
+    let relay_key = match (relay_peers.is_empty(), &config.relay_key) {
+        (true, _) => None,
+        (false, Some(key)) => Some(key.clone()),
+        (false, None) => Err(BootstrapError::ArgParseError(s!(
+            "relay subscription requires `relay-key` to be set"
+        )))?,
+    };
+    let rpc_endpoint = config.rpc_endpoint.clone();
     let runtime = Runtime::init(config)?;
-    runtime.run_or_panic("Fungible contract runtime");
-
-    unreachable!()
+    if let Some(key) = relay_key {
+        if !subscriptions.is_empty() {
+            subscription::subscribe(
+                &relay_peers,
+                &subscriptions,
+                key,
+                &rpc_endpoint,
+            )?;
+        }
+    }
+    #[cfg(feature = "server")]
+    if let Some(ref credentials) = credentials {
+        daemon::drop_privileges(credentials)?;
+    }
+    if let Err(err) = runtime.try_run_loop() {
+        panic!("Fungible contract runtime has failed with error {}", err);
+    }
+    Ok(())
 }
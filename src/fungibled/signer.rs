@@ -0,0 +1,199 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Co-signing service hooks for custodial deployments. Witness PSBTs prepared
+//! for signing are passed to an external signer, which is either a command
+//! receiving base64-encoded PSBT on its standard input and printing the
+//! signed PSBT to the standard output, or an HTTP endpoint receiving the PSBT
+//! in the body of `POST` request and replying with the signed PSBT in the
+//! same encoding.
+
+use core::str::FromStr;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+
+use crate::constants::FUNGIBLED_SIGNER_TIMEOUT;
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum SignerError {
+    /// signer `{0}` is not supported; it must be either `exec:<command>` or
+    /// `http://<host>[:<port>][/<path>]`
+    Unsupported(String),
+
+    /// unable to run signer: {0}
+    Io(String),
+
+    /// signer has failed with {0}
+    Failed(String),
+
+    /// signer has returned malformed PSBT: {0}
+    Malformed(String),
+
+    /// signer has modified the witness transaction
+    TransactionChanged,
+}
+
+impl From<io::Error> for SignerError {
+    fn from(err: io::Error) -> Self {
+        SignerError::Io(err.to_string())
+    }
+}
+
+/// External service co-signing witness transactions
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+pub enum Signer {
+    /// Shell command
+    #[display("exec:{0}")]
+    Command(String),
+
+    /// HTTP endpoint
+    #[display("http://{host}{path}")]
+    Http { host: String, path: String },
+}
+
+impl FromStr for Signer {
+    type Err = SignerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(command) = s.strip_prefix("exec:") {
+            Ok(Signer::Command(command.to_string()))
+        } else if let Some(url) = s.strip_prefix("http://") {
+            let (host, path) = url.split_at(url.find('/').unwrap_or(url.len()));
+            if host.is_empty() {
+                Err(SignerError::Unsupported(s.to_string()))?
+            }
+            Ok(Signer::Http {
+                host: host.to_string(),
+                path: if path.is_empty() {
+                    s!("/")
+                } else {
+                    path.to_string()
+                },
+            })
+        } else {
+            Err(SignerError::Unsupported(s.to_string()))
+        }
+    }
+}
+
+impl Signer {
+    /// Passes PSBT to the signer and merges the signatures it has produced
+    /// into the PSBT
+    pub fn sign(&self, psbt: &mut Psbt) -> Result<(), SignerError> {
+        let request = base64::encode(serialize(psbt));
+        let response = match self {
+            Signer::Command(command) => Self::exec(command, &request)?,
+            Signer::Http { host, path } => Self::post(host, path, &request)?,
+        };
+        let data = base64::decode(response.trim())
+            .map_err(|err| SignerError::Malformed(err.to_string()))?;
+        let signed: Psbt = deserialize(&data)
+            .map_err(|err| SignerError::Malformed(err.to_string()))?;
+        if signed.global.unsigned_tx != psbt.global.unsigned_tx {
+            Err(SignerError::TransactionChanged)?
+        }
+        psbt.merge(signed)
+            .map_err(|err| SignerError::Malformed(err.to_string()))
+    }
+
+    fn exec(command: &str, request: &str) -> Result<String, SignerError> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        child
+            .stdin
+            .take()
+            .expect("stdin of the child process is piped")
+            .write_all(request.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            Err(SignerError::Failed(output.status.to_string()))?
+        }
+        String::from_utf8(output.stdout)
+            .map_err(|err| SignerError::Malformed(err.to_string()))
+    }
+
+    fn post(
+        host: &str,
+        path: &str,
+        request: &str,
+    ) -> Result<String, SignerError> {
+        let addr = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{}:80", host)
+        };
+        let timeout = Duration::from_secs(FUNGIBLED_SIGNER_TIMEOUT as u64);
+        let mut stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            host,
+            request.len(),
+            request
+        )?;
+        stream.flush()?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let (head, body) =
+            response.split_at(response.find("\r\n\r\n").ok_or_else(|| {
+                SignerError::Malformed(s!("incomplete HTTP response"))
+            })?);
+        let status = head.lines().next().unwrap_or_default();
+        match status.split_whitespace().nth(1) {
+            Some("200") => Ok(body[4..].to_string()),
+            _ => Err(SignerError::Failed(status.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_signer_parse() {
+        assert_eq!(
+            Signer::from_str("exec:hwi signtx").unwrap(),
+            Signer::Command(s!("hwi signtx"))
+        );
+        let signer = Signer::from_str("http://127.0.0.1:3000/sign").unwrap();
+        assert_eq!(
+            signer,
+            Signer::Http {
+                host: s!("127.0.0.1:3000"),
+                path: s!("/sign")
+            }
+        );
+        assert_eq!(signer.to_string(), "http://127.0.0.1:3000/sign");
+        assert_eq!(
+            Signer::from_str("http://signer").unwrap().to_string(),
+            "http://signer/"
+        );
+        assert!(Signer::from_str("https://signer").is_err());
+    }
+}
@@ -0,0 +1,179 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Snapshot files with asset allocations used for the bulk import. Snapshot
+//! is a comma-separated file with the header row
+//! `contract_id,outpoint,node_id,index,amount`, where `amount` is the
+//! revealed amount in `<value>#<blinding_factor>` form. Empty lines and lines
+//! starting with `#` are ignored. Records are read one by one, so the file
+//! size is not limited by the available memory.
+
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+use bitcoin::OutPoint;
+use lnpbp::TaggedHash;
+use rgb::{value, ContractId, NodeId};
+
+/// Header row of the snapshot file
+pub const SNAPSHOT_HEADER: &'static str =
+    "contract_id,outpoint,node_id,index,amount";
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum SnapshotError {
+    /// I/O error reading snapshot file: {0}
+    Io(String),
+
+    /// snapshot file must start with `{0}` header
+    NoHeader(&'static str),
+
+    /// malformed allocation record at line {line}: {details}
+    Malformed { line: usize, details: String },
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err.to_string())
+    }
+}
+
+/// Allocation of the asset as it is recorded in the snapshot file
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AllocationRecord {
+    pub contract_id: ContractId,
+    pub outpoint: OutPoint,
+    pub node_id: NodeId,
+    pub index: u16,
+    pub amount: value::Revealed,
+}
+
+impl FromStr for AllocationRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s.split(',').map(str::trim).collect::<Vec<_>>();
+        if fields.len() != 5 {
+            return Err(format!("expected 5 fields, found {}", fields.len()));
+        }
+        Ok(AllocationRecord {
+            contract_id: ContractId::from_str(fields[0])
+                .map_err(|err| format!("contract id: {}", err))?,
+            outpoint: OutPoint::from_str(fields[1])
+                .map_err(|err| format!("outpoint: {}", err))?,
+            node_id: NodeId::from_hex(fields[2])
+                .map_err(|err| format!("node id: {}", err))?,
+            index: fields[3]
+                .parse()
+                .map_err(|err| format!("index: {}", err))?,
+            amount: value::Revealed::from_str(fields[4])
+                .map_err(|err| format!("amount: {}", err))?,
+        })
+    }
+}
+
+/// Iterator over the allocation records of the snapshot file
+pub struct SnapshotReader<R: BufRead> {
+    lines: io::Lines<R>,
+    line: usize,
+}
+
+impl<R> SnapshotReader<R>
+where
+    R: BufRead,
+{
+    /// Creates reader checking the snapshot header
+    pub fn with(reader: R) -> Result<Self, SnapshotError> {
+        let mut snapshot = SnapshotReader {
+            lines: reader.lines(),
+            line: 0,
+        };
+        match snapshot.next_line()? {
+            Some(header) if header == SNAPSHOT_HEADER => Ok(snapshot),
+            _ => Err(SnapshotError::NoHeader(SNAPSHOT_HEADER)),
+        }
+    }
+
+    /// Number of the last line read from the file
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>, SnapshotError> {
+        for line in &mut self.lines {
+            self.line += 1;
+            let line = line?;
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                return Ok(Some(line.to_string()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<R> Iterator for SnapshotReader<R>
+where
+    R: BufRead,
+{
+    type Item = Result<AllocationRecord, SnapshotError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = match self.next_line() {
+            Ok(line) => line?,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(AllocationRecord::from_str(&line).map_err(|details| {
+            SnapshotError::Malformed {
+                line: self.line,
+                details,
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_reader() {
+        let contract_id = ContractId::default();
+        let record = format!(
+            "{},{}:1,{},2,100#{}",
+            contract_id,
+            bitcoin::Txid::default(),
+            NodeId::default(),
+            "01".repeat(32)
+        );
+        let data = format!(
+            "{}\n\n# comment\n{}\n{}\nmalformed\n",
+            SNAPSHOT_HEADER, record, record
+        );
+        let mut reader = SnapshotReader::with(data.as_bytes()).unwrap();
+        let allocation = reader.next().unwrap().unwrap();
+        assert_eq!(allocation.contract_id, contract_id);
+        assert_eq!(allocation.outpoint.vout, 1);
+        assert_eq!(allocation.index, 2);
+        assert_eq!(allocation.amount.value, 100);
+        assert_eq!(reader.next().unwrap(), Ok(allocation));
+        assert!(matches!(
+            reader.next().unwrap(),
+            Err(SnapshotError::Malformed { line: 6, .. })
+        ));
+        assert!(reader.next().is_none());
+
+        assert!(SnapshotReader::with(&b"contract_id\n"[..]).is_err());
+    }
+}
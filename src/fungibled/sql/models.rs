@@ -443,3 +443,22 @@ pub fn read_allocations(
 
     Ok(allocations)
 }
+
+/// Strict-encoded node data which are not a part of the asset cache, like
+/// pending spends ledger; each such data set is kept in a single entry named
+/// after it.
+#[derive(Queryable, Insertable, Clone, Debug)]
+#[table_name = "sql_stores"]
+pub struct SqlStore {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Record of the audit log in its strict encoding; records are only appended
+/// to the table and are verified to form a hash chain when read.
+#[derive(Queryable, Insertable, Clone, Debug)]
+#[table_name = "sql_audit_records"]
+pub struct SqlAuditRecord {
+    pub seq: i64,
+    pub record: Vec<u8>,
+}
@@ -1,3 +1,10 @@
+table! {
+    sql_audit_records (seq) {
+        seq -> BigInt,
+        record -> Binary,
+    }
+}
+
 table! {
     sql_allocation_utxo (id) {
         id -> Integer,
@@ -57,10 +64,19 @@ table! {
     }
 }
 
+table! {
+    sql_stores (name) {
+        name -> Text,
+        data -> Binary,
+    }
+}
+
 allow_tables_to_appear_in_same_query!(
+    sql_audit_records,
     sql_allocation_utxo,
     sql_allocations,
     sql_assets,
     sql_inflation,
     sql_issues,
+    sql_stores,
 );
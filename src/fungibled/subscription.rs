@@ -0,0 +1,201 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Contract subscriptions: consignments of the subscribed contracts which are
+//! published by the relay peers are validated and accepted automatically if
+//! they assign assets to the seals owned by this node. The relayed
+//! consignments are received on a separate thread and passed to the daemon
+//! RPC socket with [`Request::AutoAccept`], so the acceptance is reported with
+//! the daemon events instead of the replies to the client requests.
+
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+use std::thread;
+
+use internet2::zmqsocket::{ZmqSocketAddr, ZmqType};
+use internet2::{
+    session, transport, CreateUnmarshaller, Session, TypedEnum, Unmarshall,
+    ZMQ_CONTEXT,
+};
+use rgb::ContractId;
+
+use crate::error::{BootstrapError, ServiceErrorDomain};
+use crate::rpc::fungible::Request;
+use crate::rpc::relay::{self, Message, RelayKey};
+use crate::rpc::Reply;
+use crate::util::compression::{decompress, req_rep};
+
+/// Contract whose consignments are accepted automatically, with the number
+/// of confirmations required for their witness transactions; if not given,
+/// node-wide setting applies
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Subscription {
+    pub contract_id: ContractId,
+    pub min_confirmations: Option<u32>,
+}
+
+impl Display for Subscription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.contract_id)?;
+        if let Some(confirmations) = self.min_confirmations {
+            write!(f, ":{}", confirmations)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Subscription {
+    type Err = SubscriptionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut iter = s.split(':');
+        match (iter.next(), iter.next(), iter.next()) {
+            (Some(contract_id), confirmations, None) => Ok(Self {
+                contract_id: contract_id
+                    .parse()
+                    .map_err(|_| SubscriptionParseError)?,
+                min_confirmations: confirmations
+                    .map(u32::from_str)
+                    .transpose()
+                    .map_err(|_| SubscriptionParseError)?,
+            }),
+            _ => Err(SubscriptionParseError),
+        }
+    }
+}
+
+/// Contract subscription must be in form of <contract_id> or
+/// <contract_id>:<confirmations>
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub struct SubscriptionParseError;
+
+/// Starts receiving consignments of the subscribed contracts from the relay
+/// peers on a separate thread; each consignment authenticated with the `key`
+/// is sent to the fungible daemon RPC endpoint for the automatic acceptance
+pub fn subscribe(
+    peers: &[ZmqSocketAddr],
+    subscriptions: &[Subscription],
+    key: RelayKey,
+    rpc_endpoint: &ZmqSocketAddr,
+) -> Result<(), BootstrapError> {
+    let socket = ZMQ_CONTEXT
+        .socket(ZmqType::Sub.socket_type())
+        .map_err(transport::Error::from)?;
+    for peer in peers {
+        socket
+            .connect(&peer.zmq_socket_string())
+            .map_err(transport::Error::from)?;
+        info!("Receiving consignments from relay peer {}", peer);
+    }
+    for subscription in subscriptions {
+        socket
+            .set_subscribe(&relay::topic(subscription.contract_id))
+            .map_err(transport::Error::from)?;
+        info!("Subscribed to contract {}", subscription);
+    }
+    let mut receiver =
+        session::Raw::from_zmq_socket_unencrypted(ZmqType::Sub, socket);
+    let mut fungibled = session::Raw::with_zmq_unencrypted(
+        ZmqType::Req,
+        rpc_endpoint,
+        None,
+        None,
+    )?;
+
+    thread::spawn(move || {
+        let unmarshaller = Message::create_unmarshaller();
+        loop {
+            let frame = match receiver.recv_raw_message() {
+                Ok(frame) => frame,
+                Err(err) => {
+                    error!("Error receiving relayed update: {}", err);
+                    continue;
+                }
+            };
+            let (contract_id, data) = match relay::unframe(&key, &frame) {
+                Ok(update) => update,
+                Err(err) => {
+                    warn!("Dropping relay frame: {}", err);
+                    continue;
+                }
+            };
+            let message = match decompress(data.to_vec())
+                .map_err(ServiceErrorDomain::from)
+                .and_then(|raw| Ok(unmarshaller.unmarshall(&raw)?))
+            {
+                Ok(message) => message,
+                Err(err) => {
+                    warn!("Dropping relayed update: {}", err);
+                    continue;
+                }
+            };
+            // Contract data other than consignments are kept by the stash
+            // daemon relay
+            let consignment = match &*message {
+                Message::Consignment(consignment) => consignment,
+                message => {
+                    trace!("Skipping relayed {}", message);
+                    continue;
+                }
+            };
+            debug!("Relayed consignment for contract {}", contract_id);
+            let request = Request::AutoAccept((**consignment).clone());
+            match auto_accept(&mut fungibled, request) {
+                Ok(reply) => trace!("Relayed consignment: {}", reply),
+                Err(err) => error!(
+                    "Relayed consignment for contract {} is not processed: {}",
+                    contract_id, err
+                ),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn auto_accept(
+    fungibled: &mut impl Session,
+    request: Request,
+) -> Result<Reply, ServiceErrorDomain> {
+    let raw = req_rep(fungibled, request.serialize())?;
+    match &*Reply::create_unmarshaller().unmarshall(&raw)? {
+        Reply::Failure(failure) => {
+            Err(ServiceErrorDomain::Internal(failure.to_string()))
+        }
+        reply => Ok(reply.clone()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_subscription_parse() {
+        let contract_id = ContractId::default();
+        let subscription: Subscription =
+            format!("{}:6", contract_id).parse().unwrap();
+        assert_eq!(subscription.contract_id, contract_id);
+        assert_eq!(subscription.min_confirmations, Some(6));
+        assert_eq!(subscription.to_string().parse(), Ok(subscription));
+
+        let subscription: Subscription =
+            contract_id.to_string().parse().unwrap();
+        assert_eq!(subscription.min_confirmations, None);
+
+        assert!(format!("{}:x", contract_id)
+            .parse::<Subscription>()
+            .is_err());
+        assert!("6".parse::<Subscription>().is_err());
+    }
+}
@@ -0,0 +1,102 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Reconciliation of the asset supply: issued, burned and replaced amounts
+//! are recomputed from the contract genesis and the state transitions kept
+//! by the stash and compared with the values cached by the node.
+
+use rgb::{AtomicValue, Disclosure, Genesis, Node, Transition};
+use rgb20::schema::{FieldType, OwnedRightsType, TransitionType};
+use rgb20::Asset;
+
+use crate::rpc::reply::{SupplyMismatch, SupplyReport};
+
+/// Recomputes asset supply from the contract genesis and the disclosure of
+/// all contract state transitions known to the stash
+pub fn reconcile(
+    genesis: &Genesis,
+    history: &Disclosure,
+    asset: &Asset,
+) -> SupplyReport {
+    let contract_id = genesis.contract_id();
+    let mut report = SupplyReport {
+        contract_id,
+        issued: sum(genesis.metadata().u64(*FieldType::IssuedSupply)),
+        ..SupplyReport::default()
+    };
+    let transitions = history
+        .transitions()
+        .values()
+        .filter_map(|(_, transitions)| transitions.get(&contract_id));
+    for transition in transitions {
+        match transition.transition_type() {
+            Some(ty) if ty == *TransitionType::Issue => {
+                report.issued += supply(transition, FieldType::IssuedSupply);
+                report.secondary_issues += 1;
+            }
+            Some(ty) if ty == *TransitionType::Burn => {
+                report.burned += supply(transition, FieldType::BurnedSupply);
+            }
+            Some(ty) if ty == *TransitionType::BurnAndReplace => {
+                report.burned += supply(transition, FieldType::BurnedSupply);
+                let assignments = match transition
+                    .owned_rights_by_type(*OwnedRightsType::Assets)
+                {
+                    Some(assignments) => assignments,
+                    None => continue,
+                };
+                for state in assignments.to_discrete_state() {
+                    match state.assigned_state() {
+                        Some(revealed) => report.replaced += revealed.value,
+                        None => report.confidential_replacements += 1,
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    report.circulating = report
+        .issued
+        .saturating_sub(report.burned)
+        .saturating_add(report.replaced);
+
+    let cached_issued = asset
+        .known_issues()
+        .iter()
+        .map(|issue| *issue.amount())
+        .sum();
+    let cached_circulating = *asset.supply().known_circulating();
+    for (value, cached, computed) in vec![
+        ("issued", cached_issued, report.issued),
+        ("circulating", cached_circulating, report.circulating),
+    ] {
+        if cached != computed {
+            report.mismatches.push(SupplyMismatch {
+                value: value.to_string(),
+                cached,
+                computed,
+            });
+        }
+    }
+    report
+}
+
+fn supply(transition: &Transition, field: FieldType) -> AtomicValue {
+    sum(transition.metadata().u64(*field))
+}
+
+fn sum(values: Vec<AtomicValue>) -> AtomicValue {
+    values
+        .into_iter()
+        .fold(0, |total, value| total.saturating_add(value))
+}
@@ -0,0 +1,384 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Atomic swaps: both parties contribute inputs and outputs into a single
+//! witness transaction, which anchors state transitions of both swap legs.
+//! A leg may also consist of bitcoin inputs and outputs only, which allows
+//! swapping assets for bitcoins.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::util::psbt::raw::ProprietaryKey;
+use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+use bitcoin::{Script, TxOut, Txid};
+use lnpbp::client_side_validation::CommitConceal;
+use rgb::{
+    Anchor, Assignments, AtomicValue, Consignment, ContractId, Disclosure,
+    Node, SealEndpoint, Transition, PSBT_OUT_TWEAK, PSBT_PREFIX,
+};
+
+use crate::rpc::fungible::{AssetLeg, SwapLeg, SwapReceive};
+use crate::util::psbt;
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum SwapError {
+    /// Counterparty swap leg does not send asset {0}
+    MissingAsset(ContractId),
+
+    /// Both swap legs send the same asset {0}
+    SameAsset(ContractId),
+
+    /// History provided with the counterparty swap leg does not belong to
+    /// the contract {0}
+    WrongHistory(ContractId),
+
+    /// Counterparty assigns {received} atomic units of asset {contract_id}
+    /// to {endpoint} instead of {expected}
+    AssetUnderpaid {
+        contract_id: ContractId,
+        endpoint: SealEndpoint,
+        expected: AtomicValue,
+        received: AtomicValue,
+    },
+
+    /// Counterparty pays {received} satoshis instead of {expected}
+    BitcoinUnderpaid { expected: u64, received: u64 },
+
+    /// Swap input {outpoint} also holds asset {contract_id}, which would be
+    /// lost by the swap
+    OtherAssets {
+        outpoint: bitcoin::OutPoint,
+        contract_id: ContractId,
+    },
+
+    /// Consignment of asset {0} received in the swap is invalid
+    InvalidLeg(ContractId),
+
+    /// Witness transaction misses some of the inputs or outputs of the
+    /// offered swap leg
+    WitnessMismatch,
+
+    /// State transition of asset {0} is not anchored in the witness
+    /// transaction
+    NotAnchored(ContractId),
+
+    /// Unable to anchor swap state transitions: {0}
+    Anchor(String),
+}
+
+/// Sums up revealed amounts assigned by the transition to the given seal
+fn assigned_value(
+    transition: &Transition,
+    endpoint: SealEndpoint,
+) -> AtomicValue {
+    let seal = endpoint.commit_conceal();
+    transition
+        .owned_rights()
+        .values()
+        .filter_map(|assignments| match assignments {
+            Assignments::DiscreteFiniteField(set) => Some(set),
+            _ => None,
+        })
+        .flatten()
+        .filter(|state| state.seal_definition_confidential() == seal)
+        .filter_map(|state| state.assigned_state())
+        .fold(0, |sum, revealed| sum.saturating_add(revealed.value))
+}
+
+/// Checks that the transition assigns at least the expected amounts of the
+/// asset to each of the seals
+pub fn check_asset(
+    contract_id: ContractId,
+    transition: &Transition,
+    allocations: &BTreeMap<SealEndpoint, AtomicValue>,
+) -> Result<(), SwapError> {
+    for (endpoint, expected) in allocations {
+        let received = assigned_value(transition, *endpoint);
+        if received < *expected {
+            Err(SwapError::AssetUnderpaid {
+                contract_id,
+                endpoint: *endpoint,
+                expected: *expected,
+                received,
+            })?
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the outputs pay at least `amount` satoshis to the script
+pub fn check_bitcoin<'a>(
+    outputs: impl IntoIterator<Item = &'a TxOut>,
+    script_pubkey: &Script,
+    amount: u64,
+) -> Result<(), SwapError> {
+    let received = outputs
+        .into_iter()
+        .filter(|txout| &txout.script_pubkey == script_pubkey)
+        .fold(0u64, |sum, txout| sum.saturating_add(txout.value));
+    if received < amount {
+        Err(SwapError::BitcoinUnderpaid {
+            expected: amount,
+            received,
+        })?
+    }
+    Ok(())
+}
+
+/// Checks that the leg offered by the counterparty pays what the node
+/// expects to receive
+pub fn check_offer(
+    offer: &SwapLeg,
+    receive: &SwapReceive,
+) -> Result<(), SwapError> {
+    match receive {
+        SwapReceive::Asset {
+            contract_id,
+            allocations,
+        } => {
+            let leg = offer
+                .asset
+                .as_ref()
+                .filter(|leg| leg.contract_id == *contract_id)
+                .ok_or(SwapError::MissingAsset(*contract_id))?;
+            if leg.history.genesis.contract_id() != *contract_id {
+                Err(SwapError::WrongHistory(*contract_id))?
+            }
+            check_asset(*contract_id, &leg.transition, allocations)
+        }
+        SwapReceive::Bitcoin {
+            script_pubkey,
+            amount,
+        } => check_bitcoin(
+            &offer.witness.global.unsigned_tx.output,
+            script_pubkey,
+            *amount,
+        ),
+    }
+}
+
+/// Checks that the consignment received in the swap transfers the asset
+/// with the state transition anchored in the witness transaction, assigning
+/// at least the expected amounts to the seals
+pub fn check_consignment(
+    consignment: &Consignment,
+    txid: Txid,
+    contract_id: ContractId,
+    allocations: &BTreeMap<SealEndpoint, AtomicValue>,
+) -> Result<(), SwapError> {
+    if consignment.genesis.contract_id() != contract_id {
+        Err(SwapError::WrongHistory(contract_id))?
+    }
+    let (_, transition) = consignment
+        .state_transitions
+        .iter()
+        .find(|(anchor, _)| anchor.txid == txid)
+        .ok_or(SwapError::NotAnchored(contract_id))?;
+    check_asset(contract_id, transition, allocations)
+}
+
+/// Constructs witness transaction with inputs and outputs of the offered
+/// leg followed by the ones of the own leg
+pub fn merge_witness(offer: &Psbt, own: &Psbt) -> Psbt {
+    let mut witness = offer.clone();
+    let tx = &mut witness.global.unsigned_tx;
+    tx.input
+        .extend(own.global.unsigned_tx.input.iter().cloned());
+    tx.output
+        .extend(own.global.unsigned_tx.output.iter().cloned());
+    witness.inputs.extend(own.inputs.iter().cloned());
+    witness.outputs.extend(own.outputs.iter().cloned());
+    witness.global.xpub.extend(own.global.xpub.clone());
+    witness
+        .global
+        .proprietary
+        .extend(own.global.proprietary.clone());
+    witness.global.unknown.extend(own.global.unknown.clone());
+    witness
+}
+
+/// Checks that the witness transaction keeps all inputs of the offered leg
+/// and its outputs at the same positions. Outputs tweaked by the anchor
+/// commitment are compared by their value only.
+pub fn includes_leg(witness: &Psbt, offer: &Psbt) -> bool {
+    let tweak_key = ProprietaryKey {
+        prefix: PSBT_PREFIX.to_vec(),
+        subtype: PSBT_OUT_TWEAK,
+        key: vec![],
+    };
+    let tx = &witness.global.unsigned_tx;
+    let inputs = tx
+        .input
+        .iter()
+        .map(|txin| txin.previous_output)
+        .collect::<BTreeSet<_>>();
+    offer
+        .global
+        .unsigned_tx
+        .input
+        .iter()
+        .all(|txin| inputs.contains(&txin.previous_output))
+        && offer.global.unsigned_tx.output.iter().enumerate().all(
+            |(vout, txout)| match tx.output.get(vout) {
+                Some(found) if found == txout => true,
+                Some(found) => {
+                    found.value == txout.value
+                        && witness
+                            .outputs
+                            .get(vout)
+                            .map(|output| {
+                                output.proprietary.contains_key(&tweak_key)
+                            })
+                            .unwrap_or_default()
+                }
+                None => false,
+            },
+        )
+}
+
+/// Checks that the disclosure anchors the transition in the witness
+/// transaction
+pub fn is_anchored(
+    disclosure: &Disclosure,
+    txid: Txid,
+    contract_id: ContractId,
+    transition: &Transition,
+) -> bool {
+    let node_id = transition.node_id();
+    disclosure
+        .transitions()
+        .values()
+        .any(|(anchor, transitions)| {
+            anchor.txid == txid
+                && anchor.validate(&contract_id, &node_id)
+                && transitions
+                    .get(&contract_id)
+                    .map(|anchored| anchored.node_id() == node_id)
+                    .unwrap_or_default()
+        })
+}
+
+/// Anchors state transitions of the swap legs in the witness transaction,
+/// completing consignments for each of the legs and the disclosure of all
+/// anchored transitions
+pub fn anchor(
+    legs: &[&AssetLeg],
+    witness: &mut Psbt,
+) -> Result<(BTreeMap<ContractId, Consignment>, Disclosure), SwapError> {
+    let transitions = legs
+        .iter()
+        .map(|leg| (leg.contract_id, leg.transition.clone()))
+        .collect::<BTreeMap<_, _>>();
+    let (anchors, map) = Anchor::commit(
+        transitions
+            .iter()
+            .map(|(contract_id, transition)| {
+                (*contract_id, transition.node_id())
+            })
+            .collect(),
+        witness,
+    )
+    .map_err(|err| SwapError::Anchor(err.to_string()))?;
+    psbt::embed_transfer(
+        witness,
+        &transitions,
+        &map.iter()
+            .map(|(contract_id, index)| (*contract_id, anchors[*index].clone()))
+            .collect(),
+    );
+    for leg in legs {
+        psbt::mark_inputs(witness, leg.contract_id, &leg.inputs);
+    }
+
+    let mut consignments = bmap! {};
+    for leg in legs {
+        let anchor = anchors[*map
+            .get(&leg.contract_id)
+            .expect("Core LNP/BP anchor commitment procedure is broken")]
+        .clone();
+        let mut consignment = leg.history.clone();
+        consignment
+            .state_transitions
+            .insert(0, (anchor, leg.transition.clone()));
+        let expose = consignment
+            .endpoints
+            .iter()
+            .map(|(_, endpoint)| *endpoint)
+            .collect();
+        consignment.finalize(&expose, leg.contract_id);
+        consignments.insert(leg.contract_id, consignment);
+    }
+
+    let mut disclosure = Disclosure::default();
+    for (index, anchor) in anchors.into_iter().enumerate() {
+        let anchored_transitions = transitions
+            .iter()
+            .filter(|(contract_id, _)| map.get(*contract_id) == Some(&index))
+            .map(|(contract_id, transition)| (*contract_id, transition.clone()))
+            .collect();
+        disclosure.insert_anchored_transitions(anchor, anchored_transitions);
+    }
+
+    Ok((consignments, disclosure))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{OutPoint, Transaction, TxIn};
+
+    fn leg(seed: u8, value: u64) -> Psbt {
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::from_inner([seed; 32]), 0),
+                script_sig: Script::new(),
+                sequence: 0xFFFFFFFF,
+                witness: vec![],
+            }],
+            output: vec![TxOut {
+                value,
+                script_pubkey: Script::from(vec![seed]),
+            }],
+        };
+        Psbt::from_unsigned_tx(tx).unwrap()
+    }
+
+    #[test]
+    fn test_merge_witness() {
+        let offer = leg(1, 1000);
+        let own = leg(2, 5000);
+        let witness = merge_witness(&offer, &own);
+        assert_eq!(witness.global.unsigned_tx.input.len(), 2);
+        assert_eq!(witness.global.unsigned_tx.output.len(), 2);
+        assert_eq!(witness.inputs.len(), 2);
+        assert_eq!(witness.outputs.len(), 2);
+        assert!(includes_leg(&witness, &offer));
+        assert!(!includes_leg(&witness, &own));
+        assert!(!includes_leg(&witness, &leg(1, 999)));
+
+        let script = Script::from(vec![2]);
+        let outputs = &witness.global.unsigned_tx.output;
+        assert!(check_bitcoin(outputs.iter().skip(1), &script, 5000).is_ok());
+        assert_eq!(
+            check_bitcoin(outputs.iter().take(1), &script, 5000),
+            Err(SwapError::BitcoinUnderpaid {
+                expected: 5000,
+                received: 0
+            })
+        );
+    }
+}
@@ -0,0 +1,183 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Tracking of the bitcoin status of the outpoints holding asset allocations.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::{OutPoint, Txid};
+use electrum_client::{Client as ElectrumClient, ElectrumApi};
+
+use crate::rpc::reply::AllocationStatus;
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Watcher {
+    statuses: BTreeMap<OutPoint, AllocationStatus>,
+}
+
+impl Watcher {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Last known status of the outpoint; outpoints which were never checked
+    /// are reported as unconfirmed
+    #[inline]
+    pub fn status(&self, outpoint: OutPoint) -> AllocationStatus {
+        self.statuses.get(&outpoint).copied().unwrap_or_default()
+    }
+
+    /// Re-checks status of the outpoints with the electrum server and stops
+    /// tracking all other outpoints. If the server is not reachable, the
+    /// previously known statuses are kept. Returns new statuses of the
+    /// outpoints which were tracked before and whose status has changed.
+    pub fn update(
+        &mut self,
+        electrum: &ElectrumClient,
+        outpoints: &BTreeSet<OutPoint>,
+    ) -> BTreeMap<OutPoint, AllocationStatus> {
+        self.statuses
+            .retain(|outpoint, _| outpoints.contains(outpoint));
+        self.refresh(electrum, outpoints)
+    }
+
+    /// Re-checks status of the given outpoints with the electrum server
+    /// without affecting the rest of the tracked outpoints; returns changed
+    /// statuses like [`Watcher::update`]
+    pub fn refresh<'a>(
+        &mut self,
+        electrum: &ElectrumClient,
+        outpoints: impl IntoIterator<Item = &'a OutPoint>,
+    ) -> BTreeMap<OutPoint, AllocationStatus> {
+        let mut changed = BTreeMap::new();
+        if let Err(err) = electrum.ping() {
+            warn!("Electrum server is not available: {:?}", err);
+            return changed;
+        }
+        for outpoint in outpoints {
+            let prev = self.status(*outpoint);
+            let status = match (prev, Self::query(electrum, outpoint)) {
+                // Transaction which was mined before has left the chain
+                (
+                    AllocationStatus::Confirmed(_),
+                    AllocationStatus::Unconfirmed,
+                )
+                | (AllocationStatus::Reorged, AllocationStatus::Unconfirmed) => {
+                    AllocationStatus::Reorged
+                }
+                (_, status) => status,
+            };
+            if status != prev {
+                debug!("Outpoint {} status changed: {}", outpoint, status);
+            }
+            if let Some(prev) = self.statuses.insert(*outpoint, status) {
+                if prev != status {
+                    changed.insert(*outpoint, status);
+                }
+            }
+        }
+        changed
+    }
+
+    /// Looks up transaction spending the outpoint, either mined or still in
+    /// the mempool. Returns `None` if the outpoint is unspent or its status
+    /// can't be determined.
+    pub fn spending_txid(
+        electrum: &ElectrumClient,
+        outpoint: &OutPoint,
+    ) -> Option<Txid> {
+        let tx = electrum.transaction_get(&outpoint.txid).ok()?;
+        let script = &tx.output.get(outpoint.vout as usize)?.script_pubkey;
+        let unspent =
+            electrum.script_list_unspent(script).ok()?.into_iter().any(
+                |utxo| {
+                    utxo.tx_hash == outpoint.txid
+                        && utxo.tx_pos == outpoint.vout as usize
+                },
+            );
+        if unspent {
+            return None;
+        }
+        electrum
+            .script_get_history(script)
+            .ok()?
+            .into_iter()
+            .filter(|item| item.tx_hash != outpoint.txid)
+            .find(|item| {
+                electrum
+                    .transaction_get(&item.tx_hash)
+                    .map(|tx| {
+                        tx.input
+                            .iter()
+                            .any(|txin| txin.previous_output == *outpoint)
+                    })
+                    .unwrap_or(false)
+            })
+            .map(|item| item.tx_hash)
+    }
+
+    /// Height and timestamp of the block mining the transaction. Returns
+    /// `None` if the transaction is not mined or is not known.
+    pub fn mined(electrum: &ElectrumClient, txid: Txid) -> Option<(u32, u32)> {
+        let tx = electrum.transaction_get(&txid).ok()?;
+        let script = &tx.output.first()?.script_pubkey;
+        let height = electrum
+            .script_get_history(script)
+            .ok()?
+            .into_iter()
+            .find(|item| item.tx_hash == txid)
+            .map(|item| item.height)
+            .filter(|height| *height > 0)? as usize;
+        let header = electrum.block_header(height).ok()?;
+        Some((height as u32, header.time))
+    }
+
+    fn query(
+        electrum: &ElectrumClient,
+        outpoint: &OutPoint,
+    ) -> AllocationStatus {
+        let tx = match electrum.transaction_get(&outpoint.txid) {
+            Ok(tx) => tx,
+            Err(_) => return AllocationStatus::Unconfirmed,
+        };
+        let script = match tx.output.get(outpoint.vout as usize) {
+            Some(output) => &output.script_pubkey,
+            None => return AllocationStatus::Unconfirmed,
+        };
+        let height = electrum
+            .script_get_history(script)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|item| item.tx_hash == outpoint.txid)
+            .map(|item| item.height)
+            .unwrap_or_default();
+        if height <= 0 {
+            return AllocationStatus::Unconfirmed;
+        }
+        let unspent = electrum
+            .script_list_unspent(script)
+            .map(|utxos| {
+                utxos.into_iter().any(|utxo| {
+                    utxo.tx_hash == outpoint.txid
+                        && utxo.tx_pos == outpoint.vout as usize
+                })
+            })
+            .unwrap_or(true);
+        if unspent {
+            AllocationStatus::Confirmed(height as u32)
+        } else {
+            AllocationStatus::Spent
+        }
+    }
+}
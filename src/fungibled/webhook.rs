@@ -0,0 +1,350 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Outbound webhooks notifying external services (like exchanges) about the
+//! asset events without maintaining ZMQ subscription. Each webhook receives
+//! events of a single type as JSON body of `POST` request, signed with
+//! HMAC-SHA256 of the node webhook secret put into `X-RGB-Signature` header.
+//! Requests are sent from a separate thread; failed deliveries are retried
+//! with exponential backoff and are dropped after the last attempt.
+
+use core::str::FromStr;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
+
+use crate::constants::{
+    FUNGIBLED_WEBHOOK_ATTEMPTS, FUNGIBLED_WEBHOOK_BACKOFF,
+    FUNGIBLED_WEBHOOK_TIMEOUT,
+};
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum WebhookError {
+    /// webhook `{0}` is not supported; it must be given as
+    /// `<event>=https://<host>[:<port>][/<path>]`
+    Unsupported(String),
+
+    /// unknown webhook event `{0}`; it must be one of `transfer-confirmed`,
+    /// `consignment-received` or `allocation-spent`
+    UnknownEvent(String),
+
+    /// unable to deliver webhook: {0}
+    Io(String),
+
+    /// webhook endpoint has replied with {0}
+    Failed(String),
+}
+
+impl From<io::Error> for WebhookError {
+    fn from(err: io::Error) -> Self {
+        WebhookError::Io(err.to_string())
+    }
+}
+
+/// Type of the events delivered to the webhook
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
+pub enum WebhookEvent {
+    /// Witness transaction of the outgoing transfer is mined
+    #[display("transfer-confirmed")]
+    TransferConfirmed,
+
+    /// Consignment paying to the node is accepted
+    #[display("consignment-received")]
+    ConsignmentReceived,
+
+    /// Outpoint holding asset allocations is spent
+    #[display("allocation-spent")]
+    AllocationSpent,
+}
+
+impl FromStr for WebhookEvent {
+    type Err = WebhookError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "transfer-confirmed" => Ok(WebhookEvent::TransferConfirmed),
+            "consignment-received" => Ok(WebhookEvent::ConsignmentReceived),
+            "allocation-spent" => Ok(WebhookEvent::AllocationSpent),
+            _ => Err(WebhookError::UnknownEvent(s.to_string())),
+        }
+    }
+}
+
+/// HTTP(S) endpoint receiving events of the given type
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display("{event}={scheme}://{host}:{port}{path}")]
+pub struct Webhook {
+    pub event: WebhookEvent,
+    scheme: &'static str,
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl FromStr for Webhook {
+    type Err = WebhookError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unsupported = || WebhookError::Unsupported(s.to_string());
+        let mut split = s.splitn(2, '=');
+        let event = WebhookEvent::from_str(split.next().unwrap_or_default())?;
+        let url = split.next().ok_or_else(unsupported)?;
+        let (scheme, url) = if let Some(url) = url.strip_prefix("https://") {
+            ("https", url)
+        } else if let Some(url) = url.strip_prefix("http://") {
+            ("http", url)
+        } else {
+            Err(unsupported())?
+        };
+        let (addr, path) = url.split_at(url.find('/').unwrap_or(url.len()));
+        let (host, port) = match addr.rfind(':') {
+            Some(pos) => (
+                &addr[..pos],
+                u16::from_str(&addr[pos + 1..]).map_err(|_| unsupported())?,
+            ),
+            None if scheme == "https" => (addr, 443),
+            None => (addr, 80),
+        };
+        if host.is_empty() {
+            Err(unsupported())?
+        }
+        Ok(Webhook {
+            event,
+            scheme,
+            host: host.to_string(),
+            port,
+            path: if path.is_empty() {
+                s!("/")
+            } else {
+                path.to_string()
+            },
+        })
+    }
+}
+
+impl Webhook {
+    #[inline]
+    fn is_tls(&self) -> bool {
+        self.scheme == "https"
+    }
+
+    /// Connects to the webhook host, trying all of its addresses; each of
+    /// the connection attempts is limited by the webhook timeout, so an
+    /// unreachable host does not hold the delivery queue
+    fn connect(&self, timeout: Duration) -> Result<TcpStream, WebhookError> {
+        let mut last_err = None;
+        for addr in (self.host.as_str(), self.port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err
+            .map(WebhookError::from)
+            .unwrap_or_else(|| WebhookError::Io(s!("host has no addresses"))))
+    }
+
+    /// Sends signed payload to the webhook, succeeding if the endpoint
+    /// replies with `2xx` status code
+    fn post(&self, body: &str, signature: &str) -> Result<(), WebhookError> {
+        let timeout = Duration::from_secs(FUNGIBLED_WEBHOOK_TIMEOUT as u64);
+        let stream = self.connect(timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nX-RGB-Signature: sha256={}\r\n\
+             Connection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            body.len(),
+            signature,
+            body
+        );
+        let status = if self.is_tls() {
+            let mut config = rustls::ClientConfig::new();
+            config
+                .root_store
+                .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+            let dns_name =
+                webpki::DNSNameRef::try_from_ascii_str(&self.host)
+                    .map_err(|_| WebhookError::Unsupported(self.to_string()))?;
+            let session =
+                rustls::ClientSession::new(&Arc::new(config), dns_name);
+            exchange(rustls::StreamOwned::new(session, stream), &request)?
+        } else {
+            exchange(stream, &request)?
+        };
+        match status.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            _ => Err(WebhookError::Failed(status)),
+        }
+    }
+}
+
+/// Writes HTTP request and returns status line of the response
+fn exchange(
+    mut stream: impl Read + Write,
+    request: &str,
+) -> Result<String, WebhookError> {
+    stream.write_all(request.as_bytes())?;
+    stream.flush()?;
+    let mut status = String::new();
+    BufReader::new(stream).read_line(&mut status)?;
+    Ok(status.trim().to_string())
+}
+
+/// Signature of the webhook payload
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut engine = HmacEngine::<sha256::Hash>::new(secret.as_bytes());
+    engine.input(body.as_bytes());
+    Hmac::<sha256::Hash>::from_engine(engine).to_hex()
+}
+
+struct Delivery {
+    webhook: Webhook,
+    body: String,
+    attempt: u32,
+}
+
+/// Queue of the webhook deliveries served by a separate thread
+pub struct Dispatcher {
+    webhooks: Vec<Webhook>,
+    sender: Sender<Delivery>,
+}
+
+impl Dispatcher {
+    /// Starts thread delivering payloads to the webhooks
+    pub fn start(webhooks: Vec<Webhook>, secret: String) -> Self {
+        let (sender, receiver) = mpsc::channel::<Delivery>();
+        thread::spawn(move || {
+            // Deliveries waiting for their next attempt
+            let mut queue = VecDeque::<(Instant, Delivery)>::new();
+            loop {
+                let timeout = queue
+                    .iter()
+                    .map(|(at, _)| at.saturating_duration_since(Instant::now()))
+                    .min()
+                    .unwrap_or_else(|| Duration::from_secs(60));
+                match receiver.recv_timeout(timeout) {
+                    Ok(delivery) => queue.push_back((Instant::now(), delivery)),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) if queue.is_empty() => {
+                        break
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        thread::sleep(timeout)
+                    }
+                }
+                let now = Instant::now();
+                let (due, waiting) = queue
+                    .drain(..)
+                    .partition::<Vec<_>, _>(|(at, _)| *at <= now);
+                queue.extend(waiting);
+                for (_, mut delivery) in due {
+                    let signature = sign(&secret, &delivery.body);
+                    match delivery.webhook.post(&delivery.body, &signature) {
+                        Ok(()) => {
+                            debug!("Webhook {} is delivered", delivery.webhook)
+                        }
+                        Err(err)
+                            if delivery.attempt + 1
+                                < FUNGIBLED_WEBHOOK_ATTEMPTS =>
+                        {
+                            let backoff = FUNGIBLED_WEBHOOK_BACKOFF
+                                * 2u32.pow(delivery.attempt);
+                            warn!(
+                                "Webhook {} has failed: {}; retrying in {} \
+                                 seconds",
+                                delivery.webhook, err, backoff
+                            );
+                            delivery.attempt += 1;
+                            queue.push_back((
+                                now + Duration::from_secs(backoff as u64),
+                                delivery,
+                            ));
+                        }
+                        Err(err) => error!(
+                            "Webhook {} has failed: {}; dropping the event",
+                            delivery.webhook, err
+                        ),
+                    }
+                }
+            }
+        });
+        Dispatcher { webhooks, sender }
+    }
+
+    /// Queues payload for delivery to all webhooks of the event type; `data`
+    /// must be JSON-encoded object
+    pub fn dispatch(&self, event: WebhookEvent, data: String) {
+        let body = format!(
+            "{{\"event\":\"{}\",\"timestamp\":{},\"data\":{}}}",
+            event,
+            chrono::Utc::now().timestamp(),
+            data
+        );
+        for webhook in self.webhooks.iter().filter(|hook| hook.event == event) {
+            let delivery = Delivery {
+                webhook: webhook.clone(),
+                body: body.clone(),
+                attempt: 0,
+            };
+            if self.sender.send(delivery).is_err() {
+                error!("Webhook thread has stopped; dropping {} event", event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_webhook_parse() {
+        let webhook =
+            Webhook::from_str("transfer-confirmed=https://exchange.com/rgb")
+                .unwrap();
+        assert_eq!(webhook.event, WebhookEvent::TransferConfirmed);
+        assert!(webhook.is_tls());
+        assert_eq!(
+            webhook.to_string(),
+            "transfer-confirmed=https://exchange.com:443/rgb"
+        );
+        assert_eq!(
+            Webhook::from_str("allocation-spent=http://127.0.0.1:8080")
+                .unwrap()
+                .to_string(),
+            "allocation-spent=http://127.0.0.1:8080/"
+        );
+        assert!(Webhook::from_str("transfer-confirmed=ftp://host").is_err());
+        assert_eq!(
+            Webhook::from_str("spent=https://host"),
+            Err(WebhookError::UnknownEvent(s!("spent")))
+        );
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::any::Any;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use internet2::transport::{self, RoutedFrame};
+use internet2::Session;
+
+/// In-memory session replacing ZMQ socket when node services are embedded
+/// into the application process. Messages are passed as-is, without any
+/// framing or encryption.
+pub struct ChannelSession {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+}
+
+impl ChannelSession {
+    /// Creates a pair of sessions connected to each other
+    pub fn pair() -> (Self, Self) {
+        let (left_sender, right_receiver) = channel();
+        let (right_sender, left_receiver) = channel();
+        (
+            Self {
+                sender: left_sender,
+                receiver: left_receiver,
+            },
+            Self {
+                sender: right_sender,
+                receiver: right_receiver,
+            },
+        )
+    }
+}
+
+impl Session for ChannelSession {
+    fn recv_raw_message(&mut self) -> Result<Vec<u8>, transport::Error> {
+        self.receiver
+            .recv()
+            .map_err(|_| transport::Error::ServiceOffline)
+    }
+
+    fn send_raw_message(
+        &mut self,
+        raw: &[u8],
+    ) -> Result<usize, transport::Error> {
+        self.sender
+            .send(raw.to_vec())
+            .map(|_| raw.len())
+            .map_err(|_| transport::Error::ServiceOffline)
+    }
+
+    fn recv_routed_message(&mut self) -> Result<RoutedFrame, transport::Error> {
+        Err(transport::Error::FrameBroken(
+            "routed messages are not supported by in-memory channels",
+        ))
+    }
+
+    fn send_routed_message(
+        &mut self,
+        _source: &[u8],
+        _route: &[u8],
+        _dest: &[u8],
+        _raw: &[u8],
+    ) -> Result<usize, transport::Error> {
+        Err(transport::Error::FrameBroken(
+            "routed messages are not supported by in-memory channels",
+        ))
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
@@ -24,9 +24,14 @@ use crate::rgbd::ContractName;
 pub struct Config {
     pub verbose: u8,
     pub data_dir: String,
-    pub electrum_server: String,
+    /// Electrum server; if none is given, a default server for the network
+    /// is used
+    pub electrum_server: Option<String>,
     pub stash_rpc_endpoint: ZmqSocketAddr,
     pub contract_endpoints: HashMap<ContractName, ZmqSocketAddr>,
+    /// Endpoint where the fungible contract runtime publishes its events;
+    /// must match the endpoint used by the runtime
+    pub fungible_pub_endpoint: ZmqSocketAddr,
     pub network: Chain,
     pub run_embedded: bool,
 }
@@ -36,13 +41,16 @@ impl Default for Config {
         Self {
             verbose: 0,
             data_dir: RGB_DATA_DIR.to_owned(),
-            electrum_server: DEFAULT_ELECTRUM_ENDPOINT.to_owned(),
+            electrum_server: None,
             stash_rpc_endpoint: STASHD_RPC_ENDPOINT
                 .parse()
                 .expect("Error in STASHD_RPC_ENDPOINT value"),
             contract_endpoints: map! {
                 ContractName::Fungible => FUNGIBLED_RPC_ENDPOINT.parse().expect("Error in FUNGIBLED_RPC_ENDPOINT value")
             },
+            fungible_pub_endpoint: FUNGIBLED_PUB_ENDPOINT
+                .parse()
+                .expect("Error in FUNGIBLED_PUB_ENDPOINT value"),
             network: RGB_NETWORK
                 .parse()
                 .expect("Error in RGB_NETWORK constant value"),
@@ -0,0 +1,252 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Node embedded into the application process: stash and fungible contract
+//! runtimes are run on their own threads and talk to each other and to the
+//! application over in-memory channels instead of ZMQ sockets.
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use internet2::{
+    presentation, CreateUnmarshaller, Session, TypedEnum, Unmarshall,
+};
+use lnpbp::strict_encoding::strict_deserialize;
+use microservices::node::TryService;
+use rgb20::Asset;
+
+use super::channel::ChannelSession;
+use super::events::{decode_replay, EventSource, EventStream};
+use super::Error;
+use crate::error::{BootstrapError, ServiceErrorDomain};
+use crate::fungibled;
+use crate::ln::EventRecord;
+use crate::rpc::fungible::{AcceptReq, IssueReq, Request, TransferReq};
+use crate::rpc::{self, reply, Reply};
+use crate::stashd;
+use crate::util::compression::req_rep;
+use crate::util::{block_on, DataFormat, Spawned};
+
+#[derive(Clone)]
+pub struct Embedded {
+    /// Session to the fungible contract runtime shared between the pending
+    /// requests
+    session: Arc<Mutex<ChannelSession>>,
+
+    /// Session receiving events published by the fungible contract runtime
+    events: Arc<Mutex<ChannelSession>>,
+}
+
+impl Embedded {
+    /// Starts stash and fungible contract runtimes on separate threads.
+    /// Endpoint parameters of the provided configurations are ignored.
+    pub fn start(
+        stash_config: stashd::Config,
+        fungible_config: fungibled::Config,
+    ) -> Result<Self, BootstrapError> {
+        let (stash_client, stash_server) = ChannelSession::pair();
+        let (ready_sender, ready) = mpsc::channel();
+
+        thread::spawn(move || {
+            match stashd::Runtime::with_session(
+                stash_config,
+                Box::new(stash_server),
+            ) {
+                Ok(runtime) => {
+                    let _ = ready_sender.send(true);
+                    if let Err(err) = runtime.try_run_loop() {
+                        error!("Stash runtime has failed: {}", err);
+                    }
+                }
+                Err(err) => {
+                    error!("Unable to start stash runtime: {}", err);
+                    let _ = ready_sender.send(false);
+                }
+            }
+        });
+
+        if !ready.recv().unwrap_or_default() {
+            Err(BootstrapError::Other)?
+        }
+
+        Self::with_stash_session(stash_client, fungible_config)
+    }
+
+    /// Starts fungible contract runtime on a separate thread, connecting it
+    /// to the stash served over the provided session
+    pub(crate) fn with_stash_session(
+        stash_client: ChannelSession,
+        fungible_config: fungibled::Config,
+    ) -> Result<Self, BootstrapError> {
+        let (fungible_client, fungible_server) = ChannelSession::pair();
+        let (events, publisher) = ChannelSession::pair();
+        let (ready_sender, ready) = mpsc::channel();
+
+        thread::spawn(move || {
+            match fungibled::Runtime::with_sessions(
+                fungible_config,
+                Box::new(fungible_server),
+                Box::new(publisher),
+                Box::new(stash_client),
+            ) {
+                Ok(runtime) => {
+                    let _ = ready_sender.send(true);
+                    if let Err(err) = runtime.try_run_loop() {
+                        error!("Fungible runtime has failed: {}", err);
+                    }
+                }
+                Err(err) => {
+                    error!("Unable to start fungible runtime: {}", err);
+                    let _ = ready_sender.send(false);
+                }
+            }
+        });
+
+        if !ready.recv().unwrap_or_default() {
+            Err(BootstrapError::Other)?
+        }
+
+        Ok(Self {
+            session: Arc::new(Mutex::new(fungible_client)),
+            events: Arc::new(Mutex::new(events)),
+        })
+    }
+
+    /// Sends request to the fungible contract runtime; the returned future
+    /// is resolved with the runtime reply
+    pub fn request(&self, request: Request) -> Spawned<Result<Reply, Error>> {
+        let session = self.session.clone();
+        Spawned::spawn(move || exchange(&session, request).map_err(Error::from))
+    }
+
+    /// Sends request to the fungible contract runtime, blocking the current
+    /// thread until the reply is received
+    pub(crate) fn request_blocking(
+        &self,
+        request: Request,
+    ) -> Result<Reply, Error> {
+        exchange(&self.session, request).map_err(Error::from)
+    }
+
+    pub async fn issue(&self, issue: IssueReq) -> Result<Asset, Error> {
+        match self.request(Request::Issue(issue)).await? {
+            Reply::Asset(asset) => Ok(asset),
+            Reply::Failure(failure) => Err(Error::Reply(failure)),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    pub async fn transfer(
+        &self,
+        transfer: TransferReq,
+    ) -> Result<reply::Transfer, Error> {
+        match self.request(Request::Transfer(transfer)).await? {
+            Reply::Transfer(transfer) => Ok(transfer),
+            Reply::ApprovalRequired(txid) => Err(Error::ApprovalRequired(txid)),
+            Reply::Failure(failure) => Err(Error::Reply(failure)),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    pub async fn accept(&self, accept: AcceptReq) -> Result<(), Error> {
+        match self.request(Request::Accept(accept)).await? {
+            Reply::Success | Reply::AlreadyAccepted(_) => Ok(()),
+            Reply::Pending(pending) => Err(Error::Pending(pending)),
+            Reply::Failure(failure) => Err(Error::Reply(failure)),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    pub async fn list_assets(
+        &self,
+        data_format: DataFormat,
+    ) -> Result<reply::AssetSync, Error> {
+        match self.request(Request::Sync(data_format)).await? {
+            Reply::Sync(sync) => Ok(sync),
+            Reply::Failure(failure) => Err(Error::Reply(failure)),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Events published since the given sequence number which are still kept
+    /// in the runtime event log, together with the flag whether some of them
+    /// were already evicted from the log
+    pub async fn replay_events(
+        &self,
+        from_seq: u64,
+    ) -> Result<(Vec<EventRecord>, bool), Error> {
+        match self.request(Request::ReplayEvents(from_seq)).await? {
+            Reply::EventReplay(replay) => Ok(decode_replay(replay)?),
+            Reply::Failure(failure) => Err(Error::Reply(failure)),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Waits for the next event published by the fungible contract runtime
+    pub fn next_event(&self) -> Result<EventRecord, Error> {
+        let raw = self
+            .events
+            .lock()
+            .map_err(|_| {
+                ServiceErrorDomain::Internal(s!("event session is broken"))
+            })?
+            .recv_raw_message()
+            .map_err(ServiceErrorDomain::from)?;
+        let record = strict_deserialize(&raw)
+            .map_err(presentation::Error::from)
+            .map_err(ServiceErrorDomain::from)?;
+        Ok(record)
+    }
+
+    /// Stream of the events published by the fungible contract runtime
+    /// after this call
+    #[inline]
+    pub fn events(&self) -> EventStream<Embedded> {
+        EventStream::with(self.clone())
+    }
+}
+
+impl EventSource for Embedded {
+    #[inline]
+    fn recv(&mut self) -> Result<EventRecord, Error> {
+        self.next_event()
+    }
+
+    fn resubscribe(&mut self) -> Result<(), Error> {
+        // Embedded runtime publishes events into the in-memory channel, which
+        // is closed only when the runtime has stopped
+        Err(ServiceErrorDomain::Internal(s!(
+            "embedded fungible runtime has stopped"
+        )))?
+    }
+
+    #[inline]
+    fn replay(
+        &mut self,
+        from_seq: u64,
+    ) -> Result<(Vec<EventRecord>, bool), Error> {
+        block_on(self.replay_events(from_seq))
+    }
+}
+
+fn exchange(
+    session: &Mutex<ChannelSession>,
+    request: Request,
+) -> Result<Reply, ServiceErrorDomain> {
+    let mut session = session.lock().map_err(|_| {
+        ServiceErrorDomain::Internal(s!("embedded node session is broken"))
+    })?;
+    let raw = req_rep(&mut *session, request.serialize())?;
+    let reply = Reply::create_unmarshaller().unmarshall(&raw)?;
+    Ok(rpc::take(reply))
+}
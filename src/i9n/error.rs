@@ -11,7 +11,7 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use crate::error::ServiceErrorDomain;
+use crate::error::{ErrorCode, ServiceErrorDomain};
 use crate::rpc::reply;
 
 #[derive(Debug, Display, Error, From)]
@@ -43,7 +43,33 @@ pub enum Error {
     #[display(doc_comments)]
     UnexpectedResponse,
 
+    /// RGB node rate limit is exceeded; retry after {0} seconds
+    #[display(doc_comments)]
+    RateLimited(u32),
+
     /// The provided network id does not match the network used by the RGB node
     #[display(doc_comments)]
     WrongNetwork,
+
+    /// Transfer with witness transaction id {0} exceeds the approval
+    /// threshold; its consignment is released once approved by a second
+    /// operator
+    #[display(doc_comments)]
+    ApprovalRequired(bitcoin::Txid),
+
+    /// Consignment is kept pending until its witness transactions get enough
+    /// confirmations: {0}
+    #[display(doc_comments)]
+    Pending(reply::PendingConsignment),
+}
+
+impl Error {
+    /// Code of the error reported by RGB node, if the error was reported by
+    /// the node and its code is known to this version of the library
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            Error::Reply(failure) => failure.error_code().ok(),
+            _ => None,
+        }
+    }
 }
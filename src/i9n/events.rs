@@ -0,0 +1,477 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Asynchronous stream of the events published by the fungible contract
+//! runtime. Subscription is served on a separate thread, so the stream does
+//! not block the executor; lost subscriptions are re-established and the
+//! events missed in between (detected by the gaps in the event sequence
+//! numbers) are replayed from the runtime event log.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::collections::VecDeque;
+
+use tokio::stream::Stream;
+
+use internet2::zmqsocket::{ZmqSocketAddr, ZmqType};
+use internet2::{
+    presentation, session, transport, CreateUnmarshaller, PlainTranscoder,
+    Session, TypedEnum, Unmarshall, ZMQ_CONTEXT,
+};
+use lnpbp::strict_encoding::strict_deserialize;
+
+use super::Error;
+use crate::error::ServiceErrorDomain;
+use crate::ln::{Event, EventRecord};
+use crate::rpc::fungible::Request;
+use crate::rpc::{self, reply, Reply};
+use crate::util::compression::req_rep;
+use crate::util::Spawned;
+
+/// Source of the event records published by the fungible contract runtime.
+/// All methods are blocking; they are called by [`EventStream`] from a
+/// separate thread.
+pub trait EventSource: Send + 'static {
+    /// Waits for the next published event record
+    fn recv(&mut self) -> Result<EventRecord, Error>;
+
+    /// Re-establishes subscription after failure to receive an event
+    fn resubscribe(&mut self) -> Result<(), Error>;
+
+    /// Event records starting from the given sequence number which are still
+    /// kept in the runtime event log, together with the flag whether some of
+    /// them were already evicted from the log
+    fn replay(
+        &mut self,
+        from_seq: u64,
+    ) -> Result<(Vec<EventRecord>, bool), Error>;
+}
+
+/// Event records received by a single subscription poll
+struct Received {
+    records: Vec<EventRecord>,
+    missed: u64,
+}
+
+/// Stream of the events published by the fungible contract runtime:
+/// `while let Some(event) = events.next().await { ... }`. It implements
+/// [`Stream`], so it may also be used with the stream combinators.
+///
+/// The stream ends when the subscription can't be re-established; the
+/// error which has caused this is reported by [`EventStream::error`].
+pub struct EventStream<S: EventSource> {
+    source: Option<S>,
+    pending: Option<Spawned<(S, Result<Received, Error>)>>,
+    queue: VecDeque<EventRecord>,
+    next_seq: Option<u64>,
+    missed: u64,
+    error: Option<Error>,
+}
+
+impl<S: EventSource> EventStream<S> {
+    /// Starts the stream with the first event published after the
+    /// subscription
+    pub fn with(source: S) -> Self {
+        Self {
+            source: Some(source),
+            pending: None,
+            queue: VecDeque::new(),
+            next_seq: None,
+            missed: 0,
+            error: None,
+        }
+    }
+
+    /// Starts the stream with the event having the given sequence number,
+    /// replaying events which were already published from the runtime
+    /// event log
+    pub fn since(source: S, from_seq: u64) -> Self {
+        Self {
+            next_seq: Some(from_seq),
+            ..Self::with(source)
+        }
+    }
+
+    /// Waits for the next event
+    pub async fn next(&mut self) -> Option<Event> {
+        self.next_record().await.map(|record| record.event)
+    }
+
+    /// Waits for the next event together with its sequence number, which
+    /// may be persisted to resume the stream with [`EventStream::since`]
+    pub async fn next_record(&mut self) -> Option<EventRecord> {
+        NextRecord(self).await
+    }
+
+    fn poll_record(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<EventRecord>> {
+        loop {
+            if let Some(record) = self.queue.pop_front() {
+                return Poll::Ready(Some(record));
+            }
+            if self.pending.is_none() {
+                let mut source = match self.source.take() {
+                    Some(source) => source,
+                    None => return Poll::Ready(None),
+                };
+                let next_seq = self.next_seq;
+                self.pending = Some(Spawned::spawn(move || {
+                    let received = receive(&mut source, next_seq);
+                    (source, received)
+                }));
+            }
+            // Pending poll is kept in the stream, so dropping the future
+            // returned by `next` does not lose the received events
+            let pending = self
+                .pending
+                .as_mut()
+                .expect("pending event poll is always present here");
+            let (source, received) = match Pin::new(pending).poll(cx) {
+                Poll::Ready(output) => output,
+                Poll::Pending => return Poll::Pending,
+            };
+            self.pending = None;
+            match received {
+                Ok(received) => {
+                    self.source = Some(source);
+                    self.missed += received.missed;
+                    if let Some(last) = received.records.last() {
+                        self.next_seq = Some(last.seq + 1);
+                    }
+                    self.queue.extend(received.records);
+                }
+                Err(err) => {
+                    error!("Event subscription has failed: {}", err);
+                    self.error = Some(err);
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+
+    /// Number of events which were evicted from the runtime event log
+    /// before they were replayed, and so were skipped by the stream
+    #[inline]
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    /// Error which has terminated the stream
+    #[inline]
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+}
+
+// The source is moved to the polling thread and back, but never pinned
+impl<S: EventSource> Unpin for EventStream<S> {}
+
+impl<S: EventSource> Stream for EventStream<S> {
+    type Item = Event;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Event>> {
+        self.get_mut()
+            .poll_record(cx)
+            .map(|record| record.map(|record| record.event))
+    }
+}
+
+/// Future returned by [`EventStream::next_record`]
+struct NextRecord<'a, S: EventSource>(&'a mut EventStream<S>);
+
+impl<'a, S: EventSource> Future for NextRecord<'a, S> {
+    type Output = Option<EventRecord>;
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<EventRecord>> {
+        self.0.poll_record(cx)
+    }
+}
+
+/// Waits for new event records, filling gaps in their sequence from the
+/// runtime event log
+fn receive<S: EventSource>(
+    source: &mut S,
+    next_seq: Option<u64>,
+) -> Result<Received, Error> {
+    let next_seq = match next_seq {
+        Some(seq) => seq,
+        None => {
+            return Ok(Received {
+                records: vec![source.recv()?],
+                missed: 0,
+            })
+        }
+    };
+    loop {
+        let received = match source.recv() {
+            Ok(record) if record.seq < next_seq => continue,
+            Ok(record) if record.seq == next_seq => {
+                return Ok(Received {
+                    records: vec![record],
+                    missed: 0,
+                })
+            }
+            Ok(record) => {
+                warn!(
+                    "Events #{}..#{} were not received; replaying them",
+                    next_seq, record.seq
+                );
+                Some(record)
+            }
+            Err(err) => {
+                warn!("Lost event subscription: {}; resubscribing", err);
+                source.resubscribe()?;
+                None
+            }
+        };
+
+        let (mut records, truncated) = source.replay(next_seq)?;
+        if let Some(ref record) = received {
+            records.retain(|replayed| replayed.seq < record.seq);
+        }
+        let missed = if truncated {
+            let first = records
+                .first()
+                .or(received.as_ref())
+                .map(|record| record.seq)
+                .unwrap_or(next_seq);
+            warn!("Events #{}..#{} were evicted from the log", next_seq, first);
+            first - next_seq
+        } else {
+            0
+        };
+        records.extend(received);
+        if !records.is_empty() {
+            return Ok(Received { records, missed });
+        }
+    }
+}
+
+/// Decodes events returned by [`Request::ReplayEvents`]
+pub(super) fn decode_replay(
+    replay: reply::EventReplay,
+) -> Result<(Vec<EventRecord>, bool), ServiceErrorDomain> {
+    let records = replay
+        .events
+        .into_iter()
+        .map(|(seq, data)| {
+            Ok(EventRecord {
+                seq,
+                event: EventRecord::decode_event(&data)
+                    .map_err(presentation::Error::from)?,
+            })
+        })
+        .collect::<Result<_, ServiceErrorDomain>>()?;
+    Ok((records, replay.truncated))
+}
+
+/// Subscription to the events published by a fungible contract runtime
+/// over ZMQ PUB socket; missed events are replayed with the requests to the
+/// runtime RPC socket
+pub struct Subscriber {
+    pub_endpoint: ZmqSocketAddr,
+    rpc_endpoint: ZmqSocketAddr,
+    session: session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
+}
+
+impl Subscriber {
+    pub fn connect(
+        pub_endpoint: ZmqSocketAddr,
+        rpc_endpoint: ZmqSocketAddr,
+    ) -> Result<Self, Error> {
+        let session = Self::subscribe(&pub_endpoint)?;
+        Ok(Self {
+            pub_endpoint,
+            rpc_endpoint,
+            session,
+        })
+    }
+
+    fn subscribe(
+        endpoint: &ZmqSocketAddr,
+    ) -> Result<
+        session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
+        ServiceErrorDomain,
+    > {
+        let socket = ZMQ_CONTEXT
+            .socket(ZmqType::Sub.socket_type())
+            .map_err(transport::Error::from)?;
+        socket
+            .connect(&endpoint.zmq_socket_string())
+            .map_err(transport::Error::from)?;
+        socket.set_subscribe(b"").map_err(transport::Error::from)?;
+        debug!("Subscribed to fungible runtime events at {}", endpoint);
+        Ok(session::Raw::from_zmq_socket_unencrypted(
+            ZmqType::Sub,
+            socket,
+        ))
+    }
+}
+
+impl EventSource for Subscriber {
+    fn recv(&mut self) -> Result<EventRecord, Error> {
+        let raw = self
+            .session
+            .recv_raw_message()
+            .map_err(ServiceErrorDomain::from)?;
+        let record = strict_deserialize(&raw)
+            .map_err(presentation::Error::from)
+            .map_err(ServiceErrorDomain::from)?;
+        Ok(record)
+    }
+
+    fn resubscribe(&mut self) -> Result<(), Error> {
+        self.session = Self::subscribe(&self.pub_endpoint)?;
+        Ok(())
+    }
+
+    fn replay(
+        &mut self,
+        from_seq: u64,
+    ) -> Result<(Vec<EventRecord>, bool), Error> {
+        // Dedicated session is used, so replays do not interfere with the
+        // requests sent by the runtime owning the stream
+        let mut session = session::Raw::with_zmq_unencrypted(
+            ZmqType::Req,
+            &self.rpc_endpoint,
+            None,
+            None,
+        )
+        .map_err(ServiceErrorDomain::from)?;
+        let request = Request::ReplayEvents(from_seq);
+        let raw = req_rep(&mut session, request.serialize())
+            .map_err(ServiceErrorDomain::from)?;
+        let reply = Reply::create_unmarshaller()
+            .unmarshall(&raw)
+            .map_err(ServiceErrorDomain::from)?;
+        match rpc::take(reply) {
+            Reply::EventReplay(replay) => Ok(decode_replay(replay)?),
+            Reply::Failure(failure) => Err(Error::Reply(failure)),
+            Reply::RateLimited(retry_after) => {
+                Err(Error::RateLimited(retry_after))
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::OutPoint;
+
+    use super::*;
+    use crate::util::block_on;
+
+    struct Scripted {
+        received: VecDeque<Option<u64>>,
+        log: Vec<u64>,
+        first_seq: u64,
+    }
+
+    fn record(seq: u64) -> EventRecord {
+        EventRecord {
+            seq,
+            event: Event::AllocationSpent(OutPoint::default()),
+        }
+    }
+
+    impl EventSource for Scripted {
+        fn recv(&mut self) -> Result<EventRecord, Error> {
+            match self.received.pop_front() {
+                Some(Some(seq)) => Ok(record(seq)),
+                Some(None) => Err(Error::UnexpectedResponse),
+                None => Err(Error::WrongNetwork),
+            }
+        }
+
+        fn resubscribe(&mut self) -> Result<(), Error> {
+            match self.received.is_empty() {
+                true => Err(Error::WrongNetwork),
+                false => Ok(()),
+            }
+        }
+
+        fn replay(
+            &mut self,
+            from_seq: u64,
+        ) -> Result<(Vec<EventRecord>, bool), Error> {
+            let records = self
+                .log
+                .iter()
+                .filter(|seq| **seq >= from_seq.max(self.first_seq))
+                .map(|seq| record(*seq))
+                .collect();
+            Ok((records, from_seq < self.first_seq))
+        }
+    }
+
+    #[test]
+    fn test_event_stream_gaps() {
+        let source = Scripted {
+            received: vec![Some(1), Some(4), Some(3), None, Some(9), Some(10)]
+                .into_iter()
+                .collect(),
+            log: (3..=10).collect(),
+            first_seq: 3,
+        };
+        let mut stream = EventStream::since(source, 1);
+        let mut seqs = vec![];
+        while let Some(record) = block_on(stream.next_record()) {
+            seqs.push(record.seq);
+        }
+        // #2 is evicted from the log; #5..#10 are replayed after the lost
+        // subscription, so #9 and #10 received later are skipped
+        assert_eq!(seqs, vec![1, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(stream.missed(), 1);
+        assert!(matches!(stream.error(), Some(Error::WrongNetwork)));
+    }
+
+    struct Next<'a, T: Stream + Unpin>(&'a mut T);
+
+    impl<'a, T: Stream + Unpin> Future for Next<'a, T> {
+        type Output = Option<T::Item>;
+
+        fn poll(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Option<T::Item>> {
+            Pin::new(&mut *self.0).poll_next(cx)
+        }
+    }
+
+    #[test]
+    fn test_event_stream_poll_next() {
+        let source = Scripted {
+            received: vec![Some(7), Some(8)].into_iter().collect(),
+            log: vec![],
+            first_seq: 7,
+        };
+        let mut stream = EventStream::with(source);
+        let mut events = 0;
+        while let Some(event) = block_on(Next(&mut stream)) {
+            assert!(matches!(event, Event::AllocationSpent(_)));
+            events += 1;
+        }
+        assert_eq!(events, 2);
+        assert!(matches!(stream.error(), Some(Error::WrongNetwork)));
+    }
+}
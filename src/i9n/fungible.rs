@@ -14,13 +14,15 @@
 use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1::SecretKey;
 use bitcoin::util::psbt::raw::ProprietaryKey;
 use bitcoin::util::psbt::PartiallySignedTransaction;
-use bitcoin::OutPoint;
-use internet2::{Session, TypedEnum, Unmarshall};
-use lnpbp::seals::OutpointReveal;
+use bitcoin::{OutPoint, Txid};
+use chrono::Utc;
+use internet2::{TypedEnum, Unmarshall};
+use lnpbp::seals::{OutpointHash, OutpointReveal};
 use lnpbp::Chain;
-use microservices::FileFormat;
 use rgb::{
     AtomicValue, Consignment, ContractId, Disclosure, Genesis, SealDefinition,
     SealEndpoint, PSBT_OUT_PUBKEY,
@@ -31,19 +33,30 @@ use super::{Error, Runtime};
 use crate::error::ServiceErrorDomain;
 use crate::rpc::reply::Transfer;
 use crate::rpc::{
-    fungible::AcceptReq, fungible::IssueReq, fungible::Request,
-    fungible::TransferReq, reply, Reply,
+    fungible::AcceptReq, fungible::AccountReq, fungible::ApproveTransferReq,
+    fungible::AsyncReq, fungible::AuditLogReq, fungible::ExportLedgerReq,
+    fungible::HoldTarget, fungible::IdempotentReq, fungible::ImportClaimsReq,
+    fungible::IssueAllocation, fungible::IssueReq, fungible::LedgerFormat,
+    fungible::ReceiveAssetReq, fungible::Request, fungible::ReserveOutpointReq,
+    fungible::SetHoldReq, fungible::SwapCompleteReq, fungible::SwapLeg,
+    fungible::SwapOfferReq, fungible::SwapReceive, fungible::SwapSend,
+    fungible::SwapVerifyReq, fungible::TransferReq, fungible::ValidateReq,
+    reply, Batch, Reply,
 };
+use crate::util::compression::req_rep;
+use crate::util::DataFormat;
 
 impl Runtime {
-    fn command(
-        &mut self,
-        command: Request,
-    ) -> Result<Arc<Reply>, ServiceErrorDomain> {
-        let data = command.serialize();
-        self.session_rpc.send_raw_message(&data)?;
-        let raw = self.session_rpc.recv_raw_message()?;
-        let reply = self.unmarshaller.unmarshall(&raw)?;
+    fn command(&mut self, command: Request) -> Result<Arc<Reply>, Error> {
+        let raw = req_rep(&mut self.session_rpc, command.serialize())
+            .map_err(ServiceErrorDomain::from)?;
+        let reply = self
+            .unmarshaller
+            .unmarshall(&raw)
+            .map_err(ServiceErrorDomain::from)?;
+        if let Reply::RateLimited(retry_after) = *reply {
+            Err(Error::RateLimited(retry_after))?
+        }
         Ok(reply)
     }
 
@@ -54,7 +67,7 @@ impl Runtime {
         name: String,
         description: Option<String>,
         precision: u8,
-        allocation: Vec<OutpointCoins>,
+        allocation: Vec<IssueAllocation>,
         inflation: Vec<OutpointCoins>,
         renomination: Option<OutPoint>,
         epoch: Option<OutPoint>,
@@ -71,6 +84,9 @@ impl Runtime {
             inflation,
             renomination,
             epoch,
+            no_inflation: false,
+            no_renomination: false,
+            no_burn: false,
         });
         match &*self.command(command)? {
             Reply::Asset(asset) => Ok(asset.clone()),
@@ -85,8 +101,216 @@ impl Runtime {
         inputs: BTreeSet<OutPoint>,
         payment: BTreeMap<SealEndpoint, AtomicValue>,
         change: BTreeMap<SealDefinition, AtomicValue>,
-        mut witness: PartiallySignedTransaction,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        let api =
+            Self::transfer_req(contract_id, inputs, payment, change, witness);
+
+        match &*self.command(Request::Transfer(api))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Transfer(transfer) => {
+                info!("Transfer succeeded");
+
+                Ok(transfer.clone())
+            }
+            Reply::ApprovalRequired(txid) => {
+                Err(Error::ApprovalRequired(*txid))
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Prepares transfer without marking spent allocations; the transfer
+    /// must be finalized with [`Runtime::commit_transfer`] once the witness
+    /// transaction is signed, or cancelled with [`Runtime::cancel_transfer`]
+    pub fn prepare_transfer(
+        &mut self,
+        contract_id: ContractId,
+        inputs: BTreeSet<OutPoint>,
+        payment: BTreeMap<SealEndpoint, AtomicValue>,
+        change: BTreeMap<SealDefinition, AtomicValue>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        let api =
+            Self::transfer_req(contract_id, inputs, payment, change, witness);
+
+        match &*self.command(Request::PrepareTransfer(api))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Transfer(transfer) => {
+                info!("Transfer is prepared");
+
+                Ok(transfer.clone())
+            }
+            Reply::ApprovalRequired(txid) => {
+                Err(Error::ApprovalRequired(*txid))
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Approves transfer held for the second operator approval with the
+    /// operator key, returning the released transfer data
+    pub fn approve_transfer(
+        &mut self,
+        txid: Txid,
+        secret_key: &SecretKey,
     ) -> Result<Transfer, Error> {
+        let api = ApproveTransferReq::sign(txid, secret_key);
+        match &*self.command(Request::ApproveTransfer(api))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Transfer(transfer) => {
+                info!("Transfer {} is approved", txid);
+                Ok(transfer.clone())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Sets or clears administrative hold of the asset or of the outpoint,
+    /// signing the request with the admin key; returns whether the hold
+    /// was changed
+    pub fn set_hold(
+        &mut self,
+        target: HoldTarget,
+        held: bool,
+        secret_key: &SecretKey,
+    ) -> Result<bool, Error> {
+        let timestamp = Utc::now().timestamp();
+        let api = SetHoldReq::sign(target, held, timestamp, secret_key);
+        match &*self.command(Request::SetHold(api))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Success => Ok(true),
+            Reply::Nothing => Ok(false),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    pub fn commit_transfer(&mut self, txid: Txid) -> Result<(), Error> {
+        match &*self.command(Request::CommitTransfer(txid))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Success => {
+                info!("Transfer {} is committed", txid);
+                Ok(())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Prepares transfer whose witness PSBT is filled with the data required
+    /// by hardware wallets; the signed PSBT must be passed to
+    /// [`Runtime::finalize_transfer`]
+    pub fn prepare_signing(
+        &mut self,
+        contract_id: ContractId,
+        inputs: BTreeSet<OutPoint>,
+        payment: BTreeMap<SealEndpoint, AtomicValue>,
+        change: BTreeMap<SealDefinition, AtomicValue>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        let api =
+            Self::transfer_req(contract_id, inputs, payment, change, witness);
+
+        match &*self.command(Request::PrepareSigning(api))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Transfer(transfer) => {
+                info!("Transfer is prepared for signing");
+
+                Ok(transfer.clone())
+            }
+            Reply::ApprovalRequired(txid) => {
+                Err(Error::ApprovalRequired(*txid))
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Broadcasts signed witness transaction of the pending transfer and
+    /// commits the transfer
+    pub fn finalize_transfer(
+        &mut self,
+        signed: PartiallySignedTransaction,
+    ) -> Result<(), Error> {
+        let txid = signed.global.unsigned_tx.txid();
+        match &*self.command(Request::FinalizeTransfer(signed))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Success => {
+                info!("Transfer {} is finalized", txid);
+                Ok(())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    pub fn cancel_transfer(&mut self, txid: Txid) -> Result<(), Error> {
+        match &*self.command(Request::CancelTransfer(txid))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Success => {
+                info!("Transfer {} is cancelled", txid);
+                Ok(())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Reserves outpoint for a protocol run outside of the node for the
+    /// given number of seconds, so transfers can't spend it
+    pub fn reserve_outpoint(
+        &mut self,
+        outpoint: OutPoint,
+        purpose: String,
+        duration: u32,
+    ) -> Result<(), Error> {
+        match &*self.command(Request::ReserveOutpoint(ReserveOutpointReq {
+            outpoint,
+            purpose,
+            duration,
+        }))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Success => {
+                info!("Outpoint {} is reserved", outpoint);
+                Ok(())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Releases reserved outpoint; returns whether the outpoint was reserved
+    pub fn release_outpoint(
+        &mut self,
+        outpoint: OutPoint,
+    ) -> Result<bool, Error> {
+        match &*self.command(Request::ReleaseOutpoint(outpoint))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Success => Ok(true),
+            Reply::Nothing => Ok(false),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    fn transfer_req(
+        contract_id: ContractId,
+        inputs: BTreeSet<OutPoint>,
+        payment: BTreeMap<SealEndpoint, AtomicValue>,
+        change: BTreeMap<SealDefinition, AtomicValue>,
+        witness: PartiallySignedTransaction,
+    ) -> TransferReq {
+        TransferReq {
+            witness: Self::commitment_keys(witness),
+            contract_id,
+            inputs,
+            payment,
+            theirs_witness: empty!(),
+            change,
+            confirmed_only: false,
+            container: None,
+        }
+    }
+
+    /// Marks keys of the witness outputs which may be tweaked with the
+    /// anchor commitment
+    pub(super) fn commitment_keys(
+        mut witness: PartiallySignedTransaction,
+    ) -> PartiallySignedTransaction {
         for (index, output) in &mut witness.outputs.iter_mut().enumerate() {
             if let Some(key) = output.bip32_derivation.keys().next() {
                 let key = key.clone();
@@ -110,26 +334,70 @@ impl Runtime {
             }
         }
         trace!("{:?}", witness);
+        witness
+    }
 
-        let api = TransferReq {
-            witness,
-            contract_id,
-            inputs,
-            payment,
-            change,
-        };
-
-        match &*self.command(Request::Transfer(api))? {
+    /// Prepares own leg of an atomic swap, which has to be passed to the
+    /// counterparty
+    pub fn swap_offer(
+        &mut self,
+        send: SwapSend,
+        witness: PartiallySignedTransaction,
+    ) -> Result<SwapLeg, Error> {
+        let witness = Self::commitment_keys(witness);
+        match &*self
+            .command(Request::SwapOffer(SwapOfferReq { send, witness }))?
+        {
             Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
-            Reply::Transfer(transfer) => {
-                info!("Transfer succeeded");
+            Reply::SwapLeg(leg) => Ok(leg.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
 
-                Ok(transfer.clone())
+    /// Completes atomic swap offered by the counterparty. The returned
+    /// witness transaction has to be signed by both parties.
+    pub fn swap_complete(
+        &mut self,
+        offer: SwapLeg,
+        send: Option<SwapSend>,
+        witness: PartiallySignedTransaction,
+        receive: SwapReceive,
+    ) -> Result<reply::Swap, Error> {
+        let witness = Self::commitment_keys(witness);
+        match &*self.command(Request::SwapComplete(SwapCompleteReq {
+            offer,
+            send,
+            witness,
+            receive,
+        }))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Swap(swap) => {
+                info!("Swap is completed");
+                Ok(swap.clone())
             }
             _ => Err(Error::UnexpectedResponse),
         }
     }
 
+    /// Verifies atomic swap completed by the counterparty; the witness
+    /// transaction may be signed only if the verification succeeds
+    pub fn swap_verify(
+        &mut self,
+        offer: SwapLeg,
+        swap: reply::Swap,
+        receive: SwapReceive,
+    ) -> Result<(), Error> {
+        match &*self.command(Request::SwapVerify(SwapVerifyReq {
+            offer,
+            swap,
+            receive,
+        }))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Success => Ok(()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
     pub fn accept(
         &mut self,
         consignment: Consignment,
@@ -138,6 +406,7 @@ impl Runtime {
         let api = AcceptReq {
             consignment,
             reveal_outpoints,
+            min_confirmations: None,
         };
 
         match &*self.command(Request::Accept(api))? {
@@ -146,6 +415,11 @@ impl Runtime {
                 info!("Accept command succeeded");
                 Ok(())
             }
+            Reply::AlreadyAccepted(consignment_id) => {
+                info!("Consignment {} was already accepted", consignment_id);
+                Ok(())
+            }
+            Reply::Pending(pending) => Err(Error::Pending(pending.clone())),
             _ => Err(Error::UnexpectedResponse),
         }
     }
@@ -154,12 +428,17 @@ impl Runtime {
         &mut self,
         consignment: Consignment,
     ) -> Result<rgb::validation::Status, Error> {
-        match &*self.command(Request::Validate(consignment))? {
+        let api = ValidateReq {
+            consignment,
+            min_confirmations: None,
+        };
+        match &*self.command(Request::Validate(api))? {
             Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
             Reply::ValidationStatus(status) => {
                 info!("Validation succeeded");
                 Ok(status.clone())
             }
+            Reply::Pending(pending) => Err(Error::Pending(pending.clone())),
             _ => Err(Error::UnexpectedResponse),
         }
     }
@@ -175,11 +454,222 @@ impl Runtime {
         }
     }
 
+    /// Creates blinded outpoint for receiving assets; the blinding factor is
+    /// kept by the node, so consignments sent to the blinded outpoint can be
+    /// accepted without providing reveal data
+    pub fn blind(
+        &mut self,
+        outpoint: OutPoint,
+    ) -> Result<OutpointReveal, Error> {
+        match &*self.command(Request::Blind(outpoint))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::OutpointReveal(reveal) => Ok(*reveal),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Blinds the receiving outpoint (or one of the node funding outputs)
+    /// and creates invoice for the payer in a single call
+    pub fn receive(
+        &mut self,
+        req: ReceiveAssetReq,
+    ) -> Result<reply::Receive, Error> {
+        match &*self.command(Request::ReceiveAsset(req))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Receive(receive) => Ok(receive.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Lists receives which are neither fulfilled nor expired
+    pub fn pending_receives(
+        &mut self,
+    ) -> Result<Vec<reply::PendingReceive>, Error> {
+        match &*self.command(Request::ListPendingReceives)? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::PendingReceives(receives) => Ok(receives.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Cancels pending receive; returns `false` if there is no such receive
+    pub fn cancel_receive(
+        &mut self,
+        endpoint: OutpointHash,
+    ) -> Result<bool, Error> {
+        match &*self.command(Request::CancelReceive(endpoint))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Success => Ok(true),
+            Reply::Nothing => Ok(false),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Lists outgoing transfers known to the node
+    pub fn transfers(&mut self) -> Result<Vec<reply::TransferStatus>, Error> {
+        match &*self.command(Request::ListTransfers)? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Transfers(transfers) => Ok(transfers.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Returns stage of the outgoing transfer with the given witness
+    /// transaction id
+    pub fn transfer_status(
+        &mut self,
+        txid: Txid,
+    ) -> Result<reply::TransferStatus, Error> {
+        match &*self.command(Request::TransferStatus(txid))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::TransferStatus(status) => Ok(status.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Consignment accepted by the node, if it is still kept within the
+    /// retention window
+    pub fn export_accepted_consignment(
+        &mut self,
+        consignment_id: sha256::Hash,
+    ) -> Result<Option<Consignment>, Error> {
+        match &*self
+            .command(Request::ExportAcceptedConsignment(consignment_id))?
+        {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Consignment(consignment) => Ok(Some(consignment.clone())),
+            Reply::Nothing => Ok(None),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Rebuilds consignment of the earlier outgoing transfer for the
+    /// recipient who has lost it
+    pub fn reconsign(&mut self, txid: Txid) -> Result<Consignment, Error> {
+        match &*self.command(Request::Reconsign(txid))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Consignment(consignment) => Ok(consignment.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Exports bundle with everything required to claim the owned
+    /// allocations on a fresh node, encrypted with the passphrase
+    pub fn export_claims(
+        &mut self,
+        passphrase: &str,
+    ) -> Result<Vec<u8>, Error> {
+        match &*self
+            .command(Request::ExportClaims(passphrase.to_string().into()))?
+        {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Claims(data) => Ok(data.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Imports claims bundle produced by [`Runtime::export_claims`]; funding
+    /// descriptors from the bundle are returned in the report and must be
+    /// added to the node configuration
+    pub fn import_claims(
+        &mut self,
+        data: Vec<u8>,
+        passphrase: &str,
+    ) -> Result<reply::ClaimsImport, Error> {
+        let request = ImportClaimsReq {
+            data,
+            passphrase: passphrase.to_string().into(),
+        };
+        match &*self.command(Request::ImportClaims(request))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::ClaimsImport(report) => Ok(report.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Backs up cache and stash into the backup target configured for the
+    /// node, rotating the older backups
+    pub fn backup(&mut self) -> Result<reply::Backup, Error> {
+        match &*self.command(Request::Backup)? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Backup(backup) => Ok(backup.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Sends all requests in a single message; replies are returned in the
+    /// same order, with failed requests replied by `Reply::Failure`
+    pub fn batch(
+        &mut self,
+        requests: Vec<Request>,
+    ) -> Result<Vec<Reply>, Error> {
+        match &*self.command(Request::Batch(Batch(requests)))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Batch(Batch(replies)) => Ok(replies.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Makes state-changing request with the idempotency key, so it can be
+    /// safely retried after a failure or crash of the client
+    pub fn idempotent(
+        &mut self,
+        key: String,
+        request: Request,
+    ) -> Result<Reply, Error> {
+        let request = Request::Idempotent(IdempotentReq {
+            key,
+            request: Box::new(request),
+        });
+        match &*self.command(request)? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            reply => Ok(reply.clone()),
+        }
+    }
+
+    /// Makes request on behalf of the account, signing it with the account
+    /// key; the request sees only the assets and allocations registered
+    /// under the account
+    pub fn account(
+        &mut self,
+        request: Request,
+        secret_key: &SecretKey,
+    ) -> Result<Reply, Error> {
+        let timestamp = Utc::now().timestamp();
+        let api = AccountReq::sign(request, timestamp, secret_key);
+        match &*self.command(Request::Account(api))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            reply => Ok(reply.clone()),
+        }
+    }
+
+    /// Queues the request for background execution, returning the job id.
+    /// The request reply is provided by [`Self::job_status`] once the job
+    /// is completed.
+    pub fn submit(&mut self, request: Request) -> Result<u64, Error> {
+        let request = Request::Async(AsyncReq {
+            request: Box::new(request),
+        });
+        match &*self.command(request)? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::JobQueued(id) => Ok(*id),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    pub fn job_status(&mut self, id: u64) -> Result<reply::JobStatus, Error> {
+        match &*self.command(Request::JobStatus(id))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Job(status) => Ok(status.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
     pub fn asset_allocations(
         &mut self,
         contract_id: ContractId,
-    ) -> Result<BTreeMap<OutPoint, Vec<AtomicValue>>, Error> {
-        match &*self.command(Request::Allocations(contract_id))? {
+    ) -> Result<BTreeMap<OutPoint, reply::AllocatedAmounts>, Error> {
+        match &*self.command(Request::Allocations(contract_id.into()))? {
             Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
             Reply::AssetAllocations(response) => Ok(response.clone()),
             _ => Err(Error::UnexpectedResponse),
@@ -189,7 +679,7 @@ impl Runtime {
     pub fn outpoint_assets(
         &mut self,
         outpoint: OutPoint,
-    ) -> Result<BTreeMap<ContractId, Vec<AtomicValue>>, Error> {
+    ) -> Result<reply::OutpointAssets, Error> {
         match &*self.command(Request::Assets(outpoint))? {
             Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
             Reply::OutpointAssets(response) => Ok(response.clone()),
@@ -219,10 +709,56 @@ impl Runtime {
         }
     }
 
+    /// Registers asset from the consignment genesis with zero balance,
+    /// without accepting the consignment
+    pub fn import_from_consignment(
+        &mut self,
+        consignment: Consignment,
+    ) -> Result<Asset, Error> {
+        match &*self.command(Request::ImportFromConsignment(consignment))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Asset(asset) => {
+                info!("Asset import succeeded");
+                Ok(asset.clone())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Exports ledger of the allocation changes mined at or below the given
+    /// block height
+    pub fn export_ledger(
+        &mut self,
+        height: u32,
+        format: LedgerFormat,
+    ) -> Result<reply::Ledger, Error> {
+        let request = Request::ExportLedger(ExportLedgerReq { height, format });
+        match &*self.command(request)? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Ledger(ledger) => Ok(ledger.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Exports records of the audit log made within `from..to` unix
+    /// timestamp range
+    pub fn audit_log(
+        &mut self,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<reply::AuditRecord>, Error> {
+        let request = Request::AuditLog(AuditLogReq { from, to });
+        match &*self.command(request)? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::AuditLog(records) => Ok(records.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
     pub fn list_assets(
         &mut self,
-        data_format: FileFormat,
-    ) -> Result<reply::SyncFormat, Error> {
+        data_format: DataFormat,
+    ) -> Result<reply::AssetSync, Error> {
         match &*self.command(Request::Sync(data_format))? {
             Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
             Reply::Sync(response) => Ok(response.clone()),
@@ -11,11 +11,23 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+mod channel;
 mod config;
+#[cfg(feature = "fungibles")]
+mod embedded;
 mod error;
+#[cfg(feature = "fungibles")]
+mod events;
 mod fungible;
+#[cfg(feature = "fungibles")]
+pub mod regtest;
 mod runtime;
 
+pub use channel::ChannelSession;
 pub use config::Config;
+#[cfg(feature = "fungibles")]
+pub use embedded::Embedded;
 pub use error::Error;
+#[cfg(feature = "fungibles")]
+pub use events::{EventSource, EventStream, Subscriber};
 pub use runtime::Runtime;
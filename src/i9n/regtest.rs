@@ -0,0 +1,527 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Orchestration of the end-to-end scenarios on a regtest chain: a minimal
+//! bitcoind JSON-RPC client mining blocks, funding outpoints and signing
+//! witness transactions, plus the complete issue → transfer → accept cycle
+//! between two embedded nodes with the final balance check.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::str::FromStr;
+
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+use bitcoin::{Address, Amount, BlockHash, OutPoint, Transaction, Txid};
+use lnpbp::seals::OutpointReveal;
+use rgb::{AtomicValue, ContractId, SealDefinition, SealEndpoint};
+use serde_json::{json, Value};
+
+use super::{Embedded, Error, Runtime};
+use crate::rpc::fungible::{
+    AcceptReq, IssueAllocation, IssueReq, Request, TransferReq,
+};
+use crate::rpc::Reply;
+
+/// Amount of satoshis put on each of the outpoints funded by the cycle
+pub const REGTEST_FUNDING_SATS: u64 = 100_000;
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum RegtestError {
+    /// I/O error talking to bitcoind: {0}
+    #[from(io::Error)]
+    Io(amplify::IoError),
+
+    /// bitcoind has returned malformed response: {0}
+    MalformedResponse(String),
+
+    /// bitcoind has failed to process `{method}` call: {message}
+    Rpc { method: String, message: String },
+
+    /// funding transaction {0} does not pay to the requested address
+    FundingNotFound(Txid),
+
+    /// RGB node error: {0}
+    #[from]
+    Node(Error),
+
+    /// RGB node has replied with unexpected response {0}
+    UnexpectedReply(String),
+
+    /// allocation of {contract_id} on {outpoint} is {actual} instead of
+    /// expected {expected}
+    BalanceMismatch {
+        contract_id: ContractId,
+        outpoint: OutPoint,
+        expected: AtomicValue,
+        actual: AtomicValue,
+    },
+}
+
+impl From<serde_json::Error> for RegtestError {
+    fn from(err: serde_json::Error) -> Self {
+        RegtestError::MalformedResponse(err.to_string())
+    }
+}
+
+impl From<bitcoin::consensus::encode::Error> for RegtestError {
+    fn from(err: bitcoin::consensus::encode::Error) -> Self {
+        RegtestError::MalformedResponse(err.to_string())
+    }
+}
+
+impl From<bitcoin::hashes::hex::Error> for RegtestError {
+    fn from(err: bitcoin::hashes::hex::Error) -> Self {
+        RegtestError::MalformedResponse(err.to_string())
+    }
+}
+
+impl From<bitcoin::util::address::Error> for RegtestError {
+    fn from(err: bitcoin::util::address::Error) -> Self {
+        RegtestError::MalformedResponse(err.to_string())
+    }
+}
+
+impl From<base64::DecodeError> for RegtestError {
+    fn from(err: base64::DecodeError) -> Self {
+        RegtestError::MalformedResponse(err.to_string())
+    }
+}
+
+/// Minimal bitcoind JSON-RPC client covering the calls required by the
+/// regtest scenarios. The node must run with a loaded wallet.
+#[derive(Clone, Debug)]
+pub struct Bitcoind {
+    /// `host:port` of the bitcoind RPC interface
+    addr: String,
+    /// Base64-encoded `user:password` pair
+    auth: String,
+    /// Name of the wallet to use, if the node has multiple wallets loaded
+    wallet: Option<String>,
+}
+
+impl Bitcoind {
+    pub fn new(addr: impl ToString, user: &str, password: &str) -> Self {
+        Self {
+            addr: addr.to_string(),
+            auth: base64::encode(format!("{}:{}", user, password)),
+            wallet: None,
+        }
+    }
+
+    pub fn with_wallet(mut self, wallet: impl ToString) -> Self {
+        self.wallet = Some(wallet.to_string());
+        self
+    }
+
+    /// Performs JSON-RPC call returning its result
+    pub fn call(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, RegtestError> {
+        let body = json!({
+            "jsonrpc": "1.0",
+            "id": "rgb-node",
+            "method": method,
+            "params": params,
+        })
+        .to_string();
+        let path = match self.wallet {
+            Some(ref wallet) => format!("/wallet/{}", wallet),
+            None => s!("/"),
+        };
+        trace!("Calling bitcoind {}{}: {}", self.addr, path, body);
+
+        // HTTP/1.0 is used so the response is never chunked and the
+        // connection is closed once it is sent
+        let mut stream = TcpStream::connect(&self.addr)?;
+        write!(
+            stream,
+            "POST {} HTTP/1.0\r\nHost: {}\r\nAuthorization: Basic {}\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            path,
+            self.addr,
+            self.auth,
+            body.len(),
+            body
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+
+        // RPC errors are returned with non-200 HTTP status, but still have
+        // JSON body, so the status is not checked
+        let body = response
+            .splitn(2, "\r\n\r\n")
+            .nth(1)
+            .filter(|body| !body.trim().is_empty())
+            .ok_or_else(|| RegtestError::Rpc {
+                method: method.to_string(),
+                message: response.lines().next().unwrap_or_default().into(),
+            })?;
+        let mut reply: Value = serde_json::from_str(body)?;
+        if !reply["error"].is_null() {
+            Err(RegtestError::Rpc {
+                method: method.to_string(),
+                message: reply["error"]["message"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| reply["error"].to_string()),
+            })?
+        }
+        Ok(reply["result"].take())
+    }
+
+    fn call_str(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<String, RegtestError> {
+        match self.call(method, params)? {
+            Value::String(s) => Ok(s),
+            other => Err(RegtestError::MalformedResponse(other.to_string())),
+        }
+    }
+
+    pub fn new_address(&self) -> Result<Address, RegtestError> {
+        Ok(Address::from_str(
+            &self.call_str("getnewaddress", json!([]))?,
+        )?)
+    }
+
+    /// Mines given number of blocks paying to the wallet
+    pub fn mine(&self, blocks: u32) -> Result<Vec<BlockHash>, RegtestError> {
+        let address = self.new_address()?;
+        let hashes = self
+            .call("generatetoaddress", json!([blocks, address.to_string()]))?;
+        Ok(serde_json::from_value::<Vec<String>>(hashes)?
+            .iter()
+            .map(|hash| BlockHash::from_hex(hash))
+            .collect::<Result<_, _>>()?)
+    }
+
+    pub fn transaction(&self, txid: Txid) -> Result<Transaction, RegtestError> {
+        let hex =
+            self.call_str("getrawtransaction", json!([txid.to_string()]))?;
+        Ok(deserialize(&Vec::<u8>::from_hex(&hex)?)?)
+    }
+
+    /// Creates new wallet outpoint with the given amount and mines it
+    pub fn fund(&self, sats: u64) -> Result<OutPoint, RegtestError> {
+        let address = self.new_address()?;
+        let txid = Txid::from_hex(&self.call_str(
+            "sendtoaddress",
+            json!([address.to_string(), Amount::from_sat(sats).as_btc()]),
+        )?)?;
+        self.mine(1)?;
+        let script = address.script_pubkey();
+        let vout = self
+            .transaction(txid)?
+            .output
+            .iter()
+            .position(|txout| txout.script_pubkey == script)
+            .ok_or(RegtestError::FundingNotFound(txid))?;
+        Ok(OutPoint::new(txid, vout as u32))
+    }
+
+    /// Constructs witness PSBT spending the outpoint to a new wallet
+    /// address; the transaction fee is subtracted from the output
+    pub fn witness_psbt(&self, input: OutPoint) -> Result<Psbt, RegtestError> {
+        let txout =
+            self.call("gettxout", json!([input.txid.to_string(), input.vout]))?;
+        let value = txout["value"].as_f64().ok_or_else(|| {
+            RegtestError::MalformedResponse(txout.to_string())
+        })?;
+        let address = self.new_address()?;
+        let funded = self.call(
+            "walletcreatefundedpsbt",
+            json!([
+                [{ "txid": input.txid.to_string(), "vout": input.vout }],
+                [{ address.to_string(): value }],
+                0,
+                { "subtractFeeFromOutputs": [0] },
+                true
+            ]),
+        )?;
+        let psbt = funded["psbt"].as_str().ok_or_else(|| {
+            RegtestError::MalformedResponse(funded.to_string())
+        })?;
+        Ok(deserialize(&base64::decode(psbt)?)?)
+    }
+
+    /// Signs witness PSBT with the wallet keys and broadcasts the finalized
+    /// transaction
+    pub fn sign_and_broadcast(
+        &self,
+        psbt: &Psbt,
+    ) -> Result<Txid, RegtestError> {
+        let processed = self.call(
+            "walletprocesspsbt",
+            json!([base64::encode(serialize(psbt))]),
+        )?;
+        let finalized =
+            self.call("finalizepsbt", json!([processed["psbt"]]))?;
+        let hex = finalized["hex"].as_str().ok_or_else(|| {
+            RegtestError::MalformedResponse(finalized.to_string())
+        })?;
+        Ok(Txid::from_hex(
+            &self.call_str("sendrawtransaction", json!([hex]))?,
+        )?)
+    }
+}
+
+/// Outpoints and transactions created by [`Regtest::transfer_cycle`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CycleReport {
+    pub contract_id: ContractId,
+    /// Outpoint holding the issued supply
+    pub issuance: OutPoint,
+    /// Outpoint receiving the change of the sender
+    pub change: OutPoint,
+    /// Outpoint receiving the transferred amount
+    pub receive: OutPoint,
+    pub witness_txid: Txid,
+}
+
+/// Pair of embedded nodes run against the regtest bitcoind. Both nodes must
+/// be configured to use the same regtest network and an Electrum server
+/// indexing it.
+pub struct Regtest {
+    bitcoind: Bitcoind,
+    sender: Embedded,
+    receiver: Embedded,
+}
+
+impl Regtest {
+    pub fn with(
+        bitcoind: Bitcoind,
+        sender: Embedded,
+        receiver: Embedded,
+    ) -> Self {
+        Self {
+            bitcoind,
+            sender,
+            receiver,
+        }
+    }
+
+    #[inline]
+    pub fn bitcoind(&self) -> &Bitcoind {
+        &self.bitcoind
+    }
+
+    #[inline]
+    pub fn sender(&self) -> &Embedded {
+        &self.sender
+    }
+
+    #[inline]
+    pub fn receiver(&self) -> &Embedded {
+        &self.receiver
+    }
+
+    /// Issues `supply` of a new asset by the sender, transfers `amount` of
+    /// it to the outpoint blinded by the receiver, mines the witness
+    /// transaction, accepts the consignment by the receiver and checks the
+    /// resulting balances of both nodes
+    pub fn transfer_cycle(
+        &self,
+        supply: AtomicValue,
+        amount: AtomicValue,
+    ) -> Result<CycleReport, RegtestError> {
+        let issuance = self.bitcoind.fund(REGTEST_FUNDING_SATS)?;
+        let change = self.bitcoind.fund(REGTEST_FUNDING_SATS)?;
+        let receive = self.bitcoind.fund(REGTEST_FUNDING_SATS)?;
+
+        let issue = IssueReq {
+            ticker: s!("RGBT"),
+            name: s!("Regtest asset"),
+            description: None,
+            precision: 0,
+            allocation: vec![IssueAllocation::from(rgb20::OutpointCoins {
+                coins: supply,
+                outpoint: issuance,
+            })],
+            inflation: vec![],
+            renomination: None,
+            epoch: None,
+            no_inflation: false,
+            no_renomination: false,
+            no_burn: false,
+        };
+        let contract_id =
+            match self.sender.request_blocking(Request::Issue(issue))? {
+                Reply::Asset(asset) => *asset.id(),
+                reply => return Err(Self::unexpected(reply)),
+            };
+        info!("Issued asset {} on {}", contract_id, issuance);
+
+        let reveal =
+            match self.receiver.request_blocking(Request::Blind(receive))? {
+                Reply::OutpointReveal(reveal) => reveal,
+                reply => return Err(Self::unexpected(reply)),
+            };
+
+        let witness =
+            Runtime::commitment_keys(self.bitcoind.witness_psbt(issuance)?);
+        let transfer_req = TransferReq {
+            contract_id,
+            witness,
+            inputs: bset! { issuance },
+            payment: bmap! {
+                SealEndpoint::TxOutpoint(reveal.outpoint_hash()) => amount
+            },
+            theirs_witness: bmap! {},
+            change: bmap! {
+                SealDefinition::TxOutpoint(OutpointReveal::from(change))
+                    => supply.saturating_sub(amount)
+            },
+            confirmed_only: false,
+            container: None,
+        };
+        let transfer = match self
+            .sender
+            .request_blocking(Request::Transfer(transfer_req))?
+        {
+            Reply::Transfer(transfer) => transfer,
+            reply => return Err(Self::unexpected(reply)),
+        };
+
+        let witness_txid =
+            self.bitcoind.sign_and_broadcast(&transfer.witness)?;
+        self.bitcoind.mine(1)?;
+        info!("Witness transaction {} is mined", witness_txid);
+
+        self.expect_success(
+            &self.sender,
+            Request::Enclose(transfer.disclosure),
+        )?;
+        self.expect_success(
+            &self.receiver,
+            Request::Accept(AcceptReq {
+                consignment: transfer.consignment,
+                reveal_outpoints: vec![reveal],
+                min_confirmations: None,
+            }),
+        )?;
+
+        self.assert_balance(
+            &self.sender,
+            contract_id,
+            change,
+            supply.saturating_sub(amount),
+        )?;
+        self.assert_balance(&self.receiver, contract_id, receive, amount)?;
+
+        Ok(CycleReport {
+            contract_id,
+            issuance,
+            change,
+            receive,
+            witness_txid,
+        })
+    }
+
+    /// Checks that the node knows exactly `expected` amount of the asset
+    /// allocated to the outpoint
+    pub fn assert_balance(
+        &self,
+        node: &Embedded,
+        contract_id: ContractId,
+        outpoint: OutPoint,
+        expected: AtomicValue,
+    ) -> Result<(), RegtestError> {
+        let actual = match node
+            .request_blocking(Request::Allocations(contract_id.into()))?
+        {
+            Reply::AssetAllocations(allocations) => allocations
+                .get(&outpoint)
+                .map(|allocated| allocated.amounts.iter().sum())
+                .unwrap_or_default(),
+            reply => return Err(Self::unexpected(reply)),
+        };
+        if actual != expected {
+            Err(RegtestError::BalanceMismatch {
+                contract_id,
+                outpoint,
+                expected,
+                actual,
+            })?
+        }
+        Ok(())
+    }
+
+    fn expect_success(
+        &self,
+        node: &Embedded,
+        request: Request,
+    ) -> Result<(), RegtestError> {
+        match node.request_blocking(request)? {
+            Reply::Success => Ok(()),
+            reply => Err(Self::unexpected(reply)),
+        }
+    }
+
+    fn unexpected(reply: Reply) -> RegtestError {
+        match reply {
+            Reply::Failure(failure) => Error::Reply(failure).into(),
+            reply => RegtestError::UnexpectedReply(reply.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    fn serve(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        addr
+    }
+
+    #[test]
+    fn test_bitcoind_call() {
+        let addr = serve(
+            "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n\
+             {\"result\":101,\"error\":null,\"id\":\"rgb-node\"}",
+        );
+        let bitcoind = Bitcoind::new(addr, "user", "password");
+        assert_eq!(bitcoind.call("getblockcount", json!([])).unwrap(), 101);
+
+        let addr = serve(
+            "HTTP/1.0 500 Internal Server Error\r\n\r\n\
+             {\"result\":null,\"error\":{\"code\":-18,\"message\":\"no \
+             wallet\"},\"id\":\"rgb-node\"}",
+        );
+        let bitcoind = Bitcoind::new(addr, "user", "password");
+        match bitcoind.call("getnewaddress", json!([])) {
+            Err(RegtestError::Rpc { method, message }) => {
+                assert_eq!(method, "getnewaddress");
+                assert_eq!(message, "no wallet");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -19,6 +19,8 @@ use internet2::{
 };
 
 use super::Config;
+#[cfg(feature = "fungibles")]
+use super::{Error, EventStream, Subscriber};
 use crate::error::BootstrapError;
 use crate::rgbd::{self, ContractName};
 use crate::rpc::Reply;
@@ -50,7 +52,7 @@ impl Runtime {
                     )))?
                     .to_string(),
                 stash_rpc_endpoint: config.stash_rpc_endpoint.to_string(),
-                network: config.network.clone(),
+                networks: vec![config.network.clone()],
                 threaded: true,
                 ..rgbd::Opts::default()
             };
@@ -78,4 +80,22 @@ impl Runtime {
             unmarshaller: Reply::create_unmarshaller(),
         })
     }
+
+    /// Subscribes to the events published by the fungible contract runtime;
+    /// events missed while the subscription was lost are replayed with the
+    /// requests to the runtime RPC endpoint
+    #[cfg(feature = "fungibles")]
+    pub fn events(&self) -> Result<EventStream<Subscriber>, Error> {
+        let rpc_endpoint = self
+            .config
+            .contract_endpoints
+            .get(&ContractName::Fungible)
+            .expect("Fungible engine is not connected in the configuration")
+            .clone();
+        let subscriber = Subscriber::connect(
+            self.config.fungible_pub_endpoint.clone(),
+            rpc_endpoint,
+        )?;
+        Ok(EventStream::with(subscriber))
+    }
 }
@@ -50,17 +50,26 @@ extern crate diesel;
 
 #[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(all(feature = "client", feature = "fungibles"))]
+pub mod client;
 pub mod constants;
 pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 #[cfg(feature = "node")]
 pub mod i9n;
 #[cfg(feature = "_rpc")]
 pub mod rpc;
 pub mod util;
+pub mod validator;
 
 #[cfg(all(feature = "_rpc", feature = "fungibles"))]
 pub mod fungibled;
+#[cfg(all(feature = "_rpc", feature = "fungibles"))]
+pub mod ln;
 #[cfg(feature = "_rpc")]
 pub mod rgbd;
 #[cfg(feature = "node")]
 pub mod stashd;
+#[cfg(feature = "testing")]
+pub mod testing;
@@ -0,0 +1,171 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use core::fmt::Display;
+use core::str::FromStr;
+use std::path::PathBuf;
+
+use internet2::zmqsocket::ZmqSocketAddr;
+use lnpbp::Chain;
+
+use crate::constants::*;
+#[cfg(feature = "node")]
+use crate::util::config::{self, LayeredOpts};
+
+#[derive(Clap)]
+#[clap(
+    name = "lnbridge",
+    version = "0.1.0",
+    author = "Dr Maxim Orlovsky <orlovsky@pandoracore.com>",
+    about = "RGB bridge forwarding lnp-node channel events to fungibled; part of RGB suite"
+)]
+pub struct Opts {
+    /// Sets verbosity level; can be used multiple times to increase verbosity
+    #[clap(short, long, global = true, parse(from_occurrences))]
+    pub verbose: u8,
+
+    /// Data directory path
+    #[clap(short, long, default_value = RGB_DATA_DIR, env = "RGB_DATA_DIR")]
+    pub data_dir: String,
+
+    /// ZMQ socket address string of lnp-node PUB/SUB channel events API
+    #[clap(
+        long = "lnp",
+        default_value = LNP_NODE_EVENTS_ENDPOINT,
+        env = "RGB_LNP_NODE_EVENTS"
+    )]
+    pub lnp_endpoint: String,
+
+    /// ZMQ socket address string for fungibled REQ/REP API
+    #[clap(
+        long,
+        default_value = FUNGIBLED_RPC_ENDPOINT,
+        env = "RGB_FUNGIBLED_RPC"
+    )]
+    pub fungible_rpc: String,
+
+    /// Bitcoin network to use (`bitcoin`, `testnet`, `signet` or `regtest`);
+    /// private signet and regtest networks are given as
+    /// `signet:<genesis hash>` and `regtest:<genesis hash>`
+    #[clap(short, long, default_value = RGB_NETWORK, env = "RGB_NETWORK")]
+    pub network: Chain,
+
+    /// Path to the configuration file [default: {data_dir}/rgb-node.toml,
+    /// if exists]
+    #[clap(long = "config", env = "RGB_CONFIG")]
+    pub config_file: Option<String>,
+
+    /// Print effective configuration in the configuration file format and
+    /// exit
+    #[clap(long)]
+    pub dump_config: bool,
+}
+
+#[cfg(feature = "node")]
+impl LayeredOpts for Opts {
+    const DAEMON: &'static str = "lnbridge";
+    const KEYS: &'static [(&'static str, &'static str)] = &[
+        ("data-dir", "RGB_DATA_DIR"),
+        ("lnp", "RGB_LNP_NODE_EVENTS"),
+        ("fungible-rpc", "RGB_FUNGIBLED_RPC"),
+        ("network", "RGB_NETWORK"),
+    ];
+
+    fn data_dir(&self) -> &str {
+        &self.data_dir
+    }
+
+    fn config_file(&self) -> Option<&str> {
+        self.config_file.as_deref()
+    }
+
+    fn verbose_mut(&mut self) -> &mut u8 {
+        &mut self.verbose
+    }
+}
+
+// We need config structure since not all of the parameters can be specified
+// via environment and command-line arguments. Thus we need a config file and
+// default set of configuration
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display(Debug)]
+pub struct Config {
+    pub verbose: u8,
+    pub data_dir: PathBuf,
+    pub lnp_endpoint: ZmqSocketAddr,
+    pub fungible_rpc: ZmqSocketAddr,
+    pub network: Chain,
+}
+
+impl From<Opts> for Config {
+    fn from(opts: Opts) -> Self {
+        let mut me = Self {
+            verbose: opts.verbose,
+            network: opts.network,
+            ..Config::default()
+        };
+        me.data_dir = me.parse_param(opts.data_dir);
+        me.lnp_endpoint = me.parse_param(opts.lnp_endpoint);
+        me.fungible_rpc = me.parse_param(opts.fungible_rpc);
+        me
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            verbose: 0,
+            data_dir: RGB_DATA_DIR
+                .parse()
+                .expect("Error in RGB_DATA_DIR constant value"),
+            lnp_endpoint: LNP_NODE_EVENTS_ENDPOINT
+                .parse()
+                .expect("Error in LNP_NODE_EVENTS_ENDPOINT constant value"),
+            fungible_rpc: FUNGIBLED_RPC_ENDPOINT
+                .parse()
+                .expect("Error in FUNGIBLED_RPC_ENDPOINT constant value"),
+            network: RGB_NETWORK
+                .parse()
+                .expect("Error in RGB_NETWORK constant value"),
+        }
+    }
+}
+
+impl Config {
+    /// Effective configuration with the configuration file keys
+    #[cfg(feature = "node")]
+    pub fn dump(&self) -> toml::value::Table {
+        let mut table = toml::value::Table::new();
+        table.insert(s!("verbose"), config::integer(self.verbose));
+        table.insert(s!("data-dir"), config::value(self.data_dir.display()));
+        table.insert(s!("lnp"), config::value(&self.lnp_endpoint));
+        table.insert(s!("fungible-rpc"), config::value(&self.fungible_rpc));
+        table.insert(s!("network"), config::value(&self.network));
+        table
+    }
+
+    pub fn parse_param<T>(&self, param: String) -> T
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        param
+            .replace("{id}", "default")
+            .replace("{network}", &self.network.to_string())
+            .replace("{data_dir}", self.data_dir.to_str().unwrap())
+            .parse()
+            .unwrap_or_else(|err| {
+                panic!("Error parsing parameter `{}`: {}", param, err)
+            })
+    }
+}
@@ -0,0 +1,40 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use bitcoin::OutPoint;
+
+use crate::rpc::fungible::{ChannelFundedReq, ChannelUpdateReq};
+
+/// Channel lifecycle events published by lnp-node on its ZMQ bus, which are
+/// relevant for the RGB asset accounting. Other lnp-node events are ignored
+/// by the bridge.
+#[derive(Clone, Debug, Display, Api)]
+#[api(encoding = "strict")]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[non_exhaustive]
+pub enum LnpEvent {
+    /// Channel funding transaction was published
+    #[api(type = 0x0201)]
+    #[display(inner)]
+    ChannelFunded(ChannelFundedReq),
+
+    /// New channel state was signed by both parties
+    #[api(type = 0x0203)]
+    #[display(inner)]
+    ChannelUpdated(ChannelUpdateReq),
+
+    /// Channel closing transaction was published
+    #[api(type = 0x0205)]
+    #[display("channel_closed({0})")]
+    ChannelClosed(OutPoint),
+}
@@ -0,0 +1,24 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Bridge service subscribing to lnp-node channel lifecycle events and
+//! forwarding them to fungibled, which updates allocations of the assets put
+//! into the channels and publishes asset-channel events.
+
+mod config;
+mod event;
+mod runtime;
+
+pub use config::{Config, Opts};
+pub use event::LnpEvent;
+pub use runtime::{main_with_config, Runtime};
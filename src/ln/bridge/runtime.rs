@@ -0,0 +1,153 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use internet2::zmqsocket::ZmqType;
+use internet2::TypedEnum;
+use internet2::{
+    session, transport, CreateUnmarshaller, PlainTranscoder, Session,
+    Unmarshall, Unmarshaller,
+};
+use microservices::node::TryService;
+
+use super::{Config, LnpEvent};
+use crate::error::{BootstrapError, RuntimeError};
+use crate::rpc::fungible::Request;
+use crate::rpc::Reply;
+use crate::util::compression::req_rep;
+use crate::util::ToBech32Data;
+
+pub struct Runtime {
+    /// Original configuration object
+    config: Config,
+
+    /// Subscription to lnp-node channel events
+    lnp_sub: session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
+
+    /// Request-response API client for fungibled
+    fungible_rpc:
+        session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
+
+    /// Unmarshaller instance used for parsing lnp-node events
+    unmarshaller: Unmarshaller<LnpEvent>,
+
+    /// Unmarshaller instance used for parsing fungibled replies
+    reply_unmarshaller: Unmarshaller<Reply>,
+}
+
+impl Runtime {
+    pub fn init(config: Config) -> Result<Self, BootstrapError> {
+        debug!("Subscribing to lnp-node events at {}", config.lnp_endpoint);
+        let lnp_sub = session::Raw::with_zmq_unencrypted(
+            ZmqType::Sub,
+            &config.lnp_endpoint,
+            None,
+            None,
+        )?;
+        lnp_sub
+            .as_socket()
+            .set_subscribe(b"")
+            .map_err(transport::Error::from)?;
+
+        debug!("Connecting to fungibled at {}", config.fungible_rpc);
+        let fungible_rpc = session::Raw::with_zmq_unencrypted(
+            ZmqType::Req,
+            &config.fungible_rpc,
+            None,
+            None,
+        )?;
+
+        Ok(Self {
+            config,
+            lnp_sub,
+            fungible_rpc,
+            unmarshaller: LnpEvent::create_unmarshaller(),
+            reply_unmarshaller: Reply::create_unmarshaller(),
+        })
+    }
+}
+
+impl TryService for Runtime {
+    type ErrorType = RuntimeError;
+
+    fn try_run_loop(mut self) -> Result<(), Self::ErrorType> {
+        debug!("Bridge runtime started with {}", self.config);
+        loop {
+            match self.run() {
+                Ok(_) => debug!("lnp-node event processing complete"),
+                Err(err) => {
+                    error!("Error processing lnp-node event: {}", err);
+                    Err(err)?;
+                }
+            }
+        }
+    }
+}
+
+impl Runtime {
+    fn run(&mut self) -> Result<(), RuntimeError> {
+        trace!("Awaiting for lnp-node events...");
+        let raw = self.lnp_sub.recv_raw_message()?;
+        trace!(
+            "Got {} bytes from lnp-node: {}",
+            raw.len(),
+            raw.to_bech32data()
+        );
+        // lnp-node publishes many other events we are not interested in
+        let event = match self.unmarshaller.unmarshall(&raw) {
+            Ok(event) => event,
+            Err(err) => {
+                trace!("Ignoring lnp-node event: {}", err);
+                return Ok(());
+            }
+        };
+        debug!("Received lnp-node event: {}", event);
+        let request = match &*event {
+            LnpEvent::ChannelFunded(req) => Request::ChannelFunded(*req),
+            LnpEvent::ChannelUpdated(req) => Request::ChannelUpdated(*req),
+            LnpEvent::ChannelClosed(funding) => {
+                Request::ChannelClosed(*funding)
+            }
+        };
+        match self.fungible_req_rep(request)? {
+            Reply::Failure(failure) => {
+                // Channel may hold no assets, or the asset data may not be
+                // known to fungibled yet; this must not stop the bridge
+                warn!("fungibled has not processed {}: {}", event, failure)
+            }
+            reply => trace!("fungibled reply: {}", reply),
+        }
+        Ok(())
+    }
+
+    fn fungible_req_rep(
+        &mut self,
+        request: Request,
+    ) -> Result<Reply, RuntimeError> {
+        let data = request.serialize();
+        trace!(
+            "Sending {} bytes to fungibled: {}",
+            data.len(),
+            data.to_bech32data()
+        );
+        let raw = req_rep(&mut self.fungible_rpc, data)?;
+        let reply = self.reply_unmarshaller.unmarshall(&raw)?;
+        Ok((*reply).clone())
+    }
+}
+
+pub fn main_with_config(config: Config) -> Result<(), BootstrapError> {
+    let runtime = Runtime::init(config)?;
+    runtime.run_or_panic("LN bridge runtime");
+
+    unreachable!()
+}
@@ -0,0 +1,217 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::OutPoint;
+use rgb::{AtomicValue, ContractId, SealDefinition, SealEndpoint, Transition};
+use rgb20::{Asset, TransferError};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ChannelError {
+    /// Error constructing RGB20 state transition: {0}
+    #[from]
+    Transfer(TransferError),
+
+    /// No allocations of asset {contract_id} are known for the channel
+    /// funding outpoint {funding}
+    NoFunding {
+        contract_id: ContractId,
+        funding: OutPoint,
+    },
+
+    /// Channel balances ({local} local and {remote} remote) do not sum up to
+    /// the channel capacity of {capacity} atomic units
+    Unbalanced {
+        local: AtomicValue,
+        remote: AtomicValue,
+        capacity: AtomicValue,
+    },
+}
+
+/// Creates state transition assigning `amount` of the asset to the channel
+/// funding output `funding_vout` of the witness (funding) transaction. The
+/// rest of the assets from the `inputs` goes to `change`.
+pub fn funding_transition(
+    asset: &Asset,
+    inputs: BTreeSet<OutPoint>,
+    funding_vout: u32,
+    amount: AtomicValue,
+    mut change: BTreeMap<SealDefinition, AtomicValue>,
+) -> Result<Transition, ChannelError> {
+    change.insert(
+        SealDefinition::WitnessVout {
+            vout: funding_vout,
+            blinding: 0,
+        },
+        amount,
+    );
+    Ok(rgb20::transfer(asset, inputs, empty!(), change)?)
+}
+
+/// Creates blank state transitions for the other assets allocated to the
+/// channel funding outpoint, moving them to the provided seal. These
+/// transitions are required for each commitment transaction, since spending
+/// the funding output without them burns the other assets.
+pub fn blank_transitions<'a>(
+    assets: impl IntoIterator<Item = &'a Asset>,
+    funding: OutPoint,
+    seal: SealDefinition,
+) -> Result<BTreeMap<ContractId, Transition>, ChannelError> {
+    let mut transitions = bmap! {};
+    for asset in assets {
+        let amount = asset
+            .allocations(funding)
+            .iter()
+            .map(|allocation| allocation.revealed_amount().value)
+            .sum::<AtomicValue>();
+        if amount == 0 {
+            continue;
+        }
+        transitions.insert(
+            *asset.id(),
+            rgb20::transfer(
+                asset,
+                bset! {funding},
+                empty!(),
+                bmap! { seal => amount },
+            )?,
+        );
+    }
+    Ok(transitions)
+}
+
+/// Distribution of a RGB20 asset between lightning channel parties
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    Debug,
+    Display,
+    Getters,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("{contract_id}@{funding}: {local}/{remote}")]
+pub struct ChannelAssets {
+    /// Asset allocated to the channel
+    contract_id: ContractId,
+
+    /// Channel funding outpoint
+    funding: OutPoint,
+
+    /// Channel capacity in atomic units of the asset
+    capacity: AtomicValue,
+
+    /// Amount belonging to the local node
+    local: AtomicValue,
+
+    /// Amount belonging to the remote peer
+    remote: AtomicValue,
+}
+
+impl ChannelAssets {
+    /// Constructs channel asset accounting from the asset allocations on the
+    /// funding outpoint. The party which has funded the channel initially owns
+    /// the whole capacity.
+    pub fn with(
+        asset: &Asset,
+        funding: OutPoint,
+        local_funded: bool,
+    ) -> Result<Self, ChannelError> {
+        let capacity = asset
+            .allocations(funding)
+            .iter()
+            .map(|allocation| allocation.revealed_amount().value)
+            .sum::<AtomicValue>();
+        if capacity == 0 {
+            Err(ChannelError::NoFunding {
+                contract_id: *asset.id(),
+                funding,
+            })?
+        }
+        let (local, remote) = if local_funded {
+            (capacity, 0)
+        } else {
+            (0, capacity)
+        };
+        Ok(Self {
+            contract_id: *asset.id(),
+            funding,
+            capacity,
+            local,
+            remote,
+        })
+    }
+
+    /// Updates channel balances for a new channel state
+    pub fn update(
+        &mut self,
+        local: AtomicValue,
+        remote: AtomicValue,
+    ) -> Result<(), ChannelError> {
+        if local.checked_add(remote) != Some(self.capacity) {
+            Err(ChannelError::Unbalanced {
+                local,
+                remote,
+                capacity: self.capacity,
+            })?
+        }
+        self.local = local;
+        self.remote = remote;
+        Ok(())
+    }
+
+    /// Creates state transition for a commitment transaction spending the
+    /// funding outpoint, distributing channel assets according to the current
+    /// balances between local seal (revealed) and remote seal (which may be
+    /// concealed). Zero balances produce no outputs.
+    pub fn commitment_transition(
+        &self,
+        asset: &Asset,
+        local_seal: SealDefinition,
+        remote_seal: SealEndpoint,
+    ) -> Result<Transition, ChannelError> {
+        let mut payment = bmap! {};
+        let mut change = bmap! {};
+        if self.remote > 0 {
+            payment.insert(remote_seal, self.remote);
+        }
+        if self.local > 0 {
+            change.insert(local_seal, self.local);
+        }
+        Ok(rgb20::transfer(
+            asset,
+            bset! {self.funding},
+            payment,
+            change,
+        )?)
+    }
+
+    /// Creates state transition for the cooperative channel close, releasing
+    /// channel assets to the final outputs of the closing transaction. After
+    /// the closing transaction is mined the funding outpoint may be forgotten
+    /// by the node.
+    #[inline]
+    pub fn close_transition(
+        &self,
+        asset: &Asset,
+        local_seal: SealDefinition,
+        remote_seal: SealEndpoint,
+    ) -> Result<Transition, ChannelError> {
+        self.commitment_transition(asset, local_seal, remote_seal)
+    }
+}
@@ -0,0 +1,150 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::io;
+
+use internet2::{CreateUnmarshaller, TypedEnum};
+use lnpbp::strict_encoding::{self, StrictDecode, StrictEncode};
+
+use super::ChannelAssets;
+use crate::rpc::reply::{
+    Backup, BulkImportReport, JobStatus, PendingConsignment, PendingReceive,
+    TransferStatus,
+};
+
+/// Asset events published by fungibled on its PUB socket within
+/// [`EventRecord`]
+#[derive(Clone, Debug, Display, Api)]
+#[api(encoding = "strict")]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[non_exhaustive]
+pub enum Event {
+    /// Consignment with the given id, which was pending confirmations of its
+    /// witness transactions, has been accepted
+    #[api(type = 0x0101)]
+    #[display("consignment_accepted({0})")]
+    ConsignmentAccepted(::bitcoin::hashes::sha256::Hash),
+
+    /// Consignment of a subscribed contract is valid, but it is kept pending
+    /// confirmations of its witness transactions
+    #[api(type = 0x0103)]
+    #[display("consignment_pending({0})")]
+    ConsignmentPending(PendingConsignment),
+
+    /// Consignment of a subscribed contract with the given id has failed
+    /// validation and was not accepted
+    #[api(type = 0x0105)]
+    #[display("consignment_rejected({0})")]
+    ConsignmentRejected(::bitcoin::hashes::sha256::Hash),
+
+    /// Asset was put into a newly funded channel
+    #[api(type = 0x0201)]
+    #[display("asset_channel_opened({0})")]
+    AssetChannelOpened(ChannelAssets),
+
+    /// Distribution of the channel asset between the parties has changed
+    #[api(type = 0x0203)]
+    #[display("asset_channel_updated({0})")]
+    AssetChannelUpdated(ChannelAssets),
+
+    /// Channel was closed; the last known distribution of the asset is
+    /// provided
+    #[api(type = 0x0205)]
+    #[display("asset_channel_closed({0})")]
+    AssetChannelClosed(ChannelAssets),
+
+    /// Request queued for background execution was completed; the job
+    /// status contains the request reply
+    #[api(type = 0x0301)]
+    #[display("job_completed({0})")]
+    JobCompleted(JobStatus),
+
+    /// Batch of allocations from the snapshot file was imported; the report
+    /// contains totals since the start of the import
+    #[api(type = 0x0401)]
+    #[display("bulk_import_progress({0})")]
+    BulkImportProgress(BulkImportReport),
+
+    /// Pending receive was fulfilled by the accepted consignment
+    #[api(type = 0x0501)]
+    #[display("receive_fulfilled({0})")]
+    ReceiveFulfilled(PendingReceive),
+
+    /// Pending receive has expired; the funding output reserved for it, if
+    /// any, is released
+    #[api(type = 0x0503)]
+    #[display("receive_expired({0})")]
+    ReceiveExpired(PendingReceive),
+
+    /// Outgoing transfer has moved to the next stage
+    #[api(type = 0x0601)]
+    #[display("transfer_updated({0})")]
+    TransferUpdated(TransferStatus),
+
+    /// Outpoint holding asset allocations is spent
+    #[api(type = 0x0701)]
+    #[display("allocation_spent({0})")]
+    AllocationSpent(::bitcoin::OutPoint),
+
+    /// Scheduled backup of the node data was completed
+    #[api(type = 0x0801)]
+    #[display("backup_completed({0})")]
+    BackupCompleted(Backup),
+
+    /// Scheduled backup of the node data has failed; the next attempt is
+    /// made after the backup interval
+    #[api(type = 0x0803)]
+    #[display("backup_failed({0})")]
+    BackupFailed(String),
+}
+
+/// Event together with its sequence number, as it is published and kept in
+/// the daemon event log. Subscribers may catch up with the events published
+/// while they were disconnected by requesting replay of the events following
+/// the last received sequence number.
+#[derive(Clone, Debug, Display)]
+#[display("#{seq} {event}")]
+pub struct EventRecord {
+    pub seq: u64,
+    pub event: Event,
+}
+
+impl StrictEncode for EventRecord {
+    fn strict_encode<E: io::Write>(
+        &self,
+        mut e: E,
+    ) -> Result<usize, strict_encoding::Error> {
+        Ok(self.seq.strict_encode(&mut e)?
+            + self.event.serialize().strict_encode(&mut e)?)
+    }
+}
+
+impl StrictDecode for EventRecord {
+    fn strict_decode<D: io::Read>(
+        mut d: D,
+    ) -> Result<Self, strict_encoding::Error> {
+        let seq = u64::strict_decode(&mut d)?;
+        let data = Vec::<u8>::strict_decode(&mut d)?;
+        Ok(Self {
+            seq,
+            event: EventRecord::decode_event(&data)?,
+        })
+    }
+}
+
+impl EventRecord {
+    /// Decodes event serialized with its API type id
+    pub fn decode_event(data: &[u8]) -> Result<Event, strict_encoding::Error> {
+        crate::rpc::batch::unmarshall(&Event::create_unmarshaller(), data)
+    }
+}
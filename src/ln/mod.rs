@@ -0,0 +1,30 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Accounting of RGB20 assets allocated to lightning channels.
+//!
+//! Provides state transitions required by LN node to put assets into a
+//! channel funding output, to update their distribution with each new
+//! commitment transaction and to release them on cooperative channel close.
+//! With `node` feature also provides bridge service driving this accounting
+//! from lnp-node channel lifecycle events.
+
+#[cfg(feature = "node")]
+pub mod bridge;
+mod channel;
+mod event;
+
+pub use channel::{
+    blank_transitions, funding_transition, ChannelAssets, ChannelError,
+};
+pub use event::{Event, EventRecord};
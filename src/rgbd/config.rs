@@ -18,11 +18,15 @@ use std::fmt::Display;
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use internet2::ZmqSocketAddr;
-use lnpbp::Chain;
-use microservices::FileFormat;
-
 use crate::constants::*;
+#[cfg(feature = "node")]
+use crate::util::config::{self, LayeredOpts};
+#[cfg(feature = "server")]
+use crate::util::daemon::{Credentials, LifecycleError};
+use crate::util::logging::{LogFilter, LogTarget};
+use crate::util::DataFormat;
+use internet2::zmqsocket::ZmqSocketAddr;
+use lnpbp::Chain;
 
 #[derive(Clap)]
 #[clap(
@@ -45,6 +49,15 @@ pub struct Opts {
     #[clap(short, long, default_value = RGB_DATA_DIR, env = "RGB_DATA_DIR")]
     pub data_dir: String,
 
+    /// ZMQ socket address string for REQ/REP API of the supervisor
+    /// reporting status of the launched daemons
+    #[clap(
+        long = "rpc",
+        default_value = RGBD_RPC_ENDPOINT,
+        env = "RGB_RGBD_RPC"
+    )]
+    pub rpc_endpoint: String,
+
     /// Contract daemons to launch
     #[clap(arg_enum, long = "contract", default_value = RGB_CONTRACTS, env = "RGB_CONTRACTS")]
     pub contracts: Vec<ContractName>,
@@ -77,8 +90,14 @@ pub struct Opts {
 
     /// Data format for fungibled cache storage (valid only if file storage is
     /// used)
-    #[clap(short, long, default_value = "yaml", env = "RGB_FUNGIBLED_FORMAT")]
-    pub format: FileFormat,
+    #[clap(
+        short,
+        long,
+        default_value = "yaml",
+        env = "RGB_FUNGIBLED_FORMAT",
+        parse(try_from_str = DataFormat::parse_storage)
+    )]
+    pub format: DataFormat,
 
     /// Connection string to stashd stash (exact format depends on used storage
     /// engine)
@@ -103,17 +122,104 @@ pub struct Opts {
     #[clap(short, long)]
     pub threaded: bool,
 
-    /// Bitcoin network to use
-    #[clap(short, long, default_value = RGB_NETWORK, env = "RGB_NETWORK")]
-    pub network: Chain,
+    /// Run stash and contract daemons as threads of a single process, with
+    /// the stash daemon reached by contract daemons in-process and not
+    /// exposing its API outside of it; intended for desktop wallets keeping
+    /// all of the node data in a single data directory
+    #[clap(long)]
+    pub all_in_one: bool,
 
-    /// Electrum server to use to fecth Bitcoin transactions
+    /// Bitcoin networks to serve; a separate set of daemons with its own
+    /// data directory, cache and RPC endpoints is launched for each of them,
+    /// so cache, stash, index and endpoint parameters must contain
+    /// `{network}` placeholder if more than one network is given
     #[clap(
-        long = "electrum",
-        default_value = DEFAULT_ELECTRUM_ENDPOINT,
-        env = "RGB_ELECTRUM_SERVER"
+        short,
+        long = "network",
+        default_value = RGB_NETWORK,
+        env = "RGB_NETWORK",
+        use_delimiter = true
     )]
-    pub electrum_server: String,
+    pub networks: Vec<Chain>,
+
+    /// Electrum server to use to fecth Bitcoin transactions [default: depends
+    /// on the network]
+    #[clap(long = "electrum", env = "RGB_ELECTRUM_SERVER")]
+    pub electrum_server: Option<String>,
+
+    /// Log target: `stderr`, `syslog`, `journald` or `json:<path>` for a
+    /// file with one JSON object per log record
+    #[clap(long, default_value = "stderr", env = "RGB_LOG")]
+    pub log: LogTarget,
+
+    /// Per-module log levels overriding the verbosity, as comma-separated
+    /// `<module>=<level>` directives (like `rgb_node::fungibled=debug`)
+    #[clap(long, env = "RGB_LOG_FILTER")]
+    pub log_filter: Option<LogFilter>,
+
+    /// Run in background, detached from the terminal
+    #[clap(long)]
+    pub daemon: bool,
+
+    /// Path to the file receiving id of the daemon process
+    #[clap(long, env = "RGB_RGBD_PID_FILE")]
+    pub pid_file: Option<String>,
+
+    /// Confine the daemon to the data directory, making it the filesystem
+    /// root (requires root privileges); all of the files used by the daemon
+    /// must be located in the data directory
+    #[clap(long)]
+    pub chroot: bool,
+
+    /// Unprivileged user the daemon switches to once it has bound its
+    /// sockets
+    #[clap(long, env = "RGB_USER")]
+    pub user: Option<String>,
+
+    /// Path to the configuration file [default: {data_dir}/rgb-node.toml,
+    /// if exists]
+    #[clap(long = "config", env = "RGB_CONFIG")]
+    pub config_file: Option<String>,
+
+    /// Print effective configuration in the configuration file format and
+    /// exit
+    #[clap(long)]
+    pub dump_config: bool,
+}
+
+#[cfg(feature = "node")]
+impl LayeredOpts for Opts {
+    const DAEMON: &'static str = "rgbd";
+    const KEYS: &'static [(&'static str, &'static str)] = &[
+        ("bin-dir", "RGB_BIN_DIR"),
+        ("data-dir", "RGB_DATA_DIR"),
+        ("log", "RGB_LOG"),
+        ("log-filter", "RGB_LOG_FILTER"),
+        ("pid-file", "RGB_RGBD_PID_FILE"),
+        ("user", "RGB_USER"),
+        ("rpc", "RGB_RGBD_RPC"),
+        ("contract", "RGB_CONTRACTS"),
+        ("fungible-rpc", "RGB_FUNGIBLED_RPC"),
+        ("stash-rpc", "RGB_STASHD_RPC"),
+        ("cache", "RGB_FUNGIBLED_CACHE"),
+        ("format", "RGB_FUNGIBLED_FORMAT"),
+        ("stash", "RGB_STASHD_STASH"),
+        ("index", "RGB_STASHD_INDEX"),
+        ("network", "RGB_NETWORK"),
+        ("electrum", "RGB_ELECTRUM_SERVER"),
+    ];
+
+    fn data_dir(&self) -> &str {
+        &self.data_dir
+    }
+
+    fn config_file(&self) -> Option<&str> {
+        self.config_file.as_deref()
+    }
+
+    fn verbose_mut(&mut self) -> &mut u8 {
+        &mut self.verbose
+    }
 }
 
 #[derive(
@@ -149,31 +255,49 @@ pub struct Config {
     pub data_dir: PathBuf,
     pub bin_dir: PathBuf,
     pub threaded: bool,
+    pub all_in_one: bool,
+    pub rpc_endpoint: ZmqSocketAddr,
     pub contracts: Vec<ContractName>,
-    pub network: Chain,
+    pub networks: Vec<Chain>,
     pub verbose: u8,
-    pub fungible_rpc_endpoint: ZmqSocketAddr,
-    pub stash_rpc_endpoint: ZmqSocketAddr,
+    pub log: LogTarget,
+    pub log_filter: LogFilter,
+    pub user: Option<String>,
+    /// User resolved before the daemon was confined to the data directory
+    #[cfg(feature = "server")]
+    pub credentials: Option<Credentials>,
+    /// Endpoint parameters, as well as cache, stash and index, may contain
+    /// `{network}` placeholder, which is resolved for each of the served
+    /// networks
+    pub fungible_rpc_endpoint: String,
+    pub stash_rpc_endpoint: String,
     pub cache: String,
-    pub format: FileFormat,
+    pub format: DataFormat,
     pub stash: String,
     pub index: String,
-    pub electrum_server: String,
+    pub electrum_server: Option<String>,
 }
 
 impl From<Opts> for Config {
     fn from(opts: Opts) -> Self {
         let mut me = Self {
-            threaded: opts.threaded,
-            network: opts.network,
+            threaded: opts.threaded || opts.all_in_one,
+            all_in_one: opts.all_in_one,
+            networks: opts.networks,
             contracts: opts.contracts,
             format: opts.format,
             verbose: opts.verbose,
+            log: opts.log,
+            log_filter: opts.log_filter.unwrap_or_default(),
+            user: opts.user,
+            #[cfg(feature = "server")]
+            credentials: None,
             electrum_server: opts.electrum_server,
             ..Default::default()
         };
         me.bin_dir = me.parse_param(opts.bin_dir);
         me.data_dir = me.parse_param(opts.data_dir);
+        me.rpc_endpoint = me.parse_param(opts.rpc_endpoint);
         me.cache = me.parse_param(opts.cache);
         me.stash = me.parse_param(opts.stash);
         me.index = me.parse_param(opts.index);
@@ -193,28 +317,31 @@ impl Default for Config {
                 .parse()
                 .expect("Error in RGB_BIN_DIR constant value"),
             threaded: false,
+            all_in_one: false,
+            rpc_endpoint: RGBD_RPC_ENDPOINT
+                .parse()
+                .expect("Error in RGBD_RPC_ENDPOINT constant value"),
             contracts: vec![ContractName::from_str(RGB_CONTRACTS, false)
                 .expect("Error in RGB_CONTRACTS constant value")],
-            fungible_rpc_endpoint: FUNGIBLED_RPC_ENDPOINT
-                .parse()
-                .expect("Error in FUNGIBLED_RPC_ENDPOINT value"),
-            stash_rpc_endpoint: STASHD_RPC_ENDPOINT
-                .parse()
-                .expect("Error in STASHD_RPC_ENDPOINT value"),
+            fungible_rpc_endpoint: FUNGIBLED_RPC_ENDPOINT.to_string(),
+            stash_rpc_endpoint: STASHD_RPC_ENDPOINT.to_string(),
             cache: FUNGIBLED_CACHE.to_string(),
             #[cfg(feature = "serde_yaml")]
-            format: FileFormat::Yaml,
+            format: DataFormat::Yaml,
             #[cfg(not(feature = "serde_yaml"))]
-            format: FileFormat::StrictEncode,
+            format: DataFormat::StrictEncode,
             stash: STASHD_STASH.to_string(),
             index: STASHD_INDEX.to_string(),
-            network: RGB_NETWORK
+            networks: vec![RGB_NETWORK
                 .parse()
-                .expect("Error in RGB_NETWORK constant value"),
+                .expect("Error in RGB_NETWORK constant value")],
             verbose: 0,
-            electrum_server: DEFAULT_ELECTRUM_ENDPOINT
-                .parse()
-                .expect("Error in DEFAULT_ELECTRUM_ENDPOINT constant value"),
+            log: LogTarget::Stderr,
+            log_filter: LogFilter::default(),
+            user: None,
+            #[cfg(feature = "server")]
+            credentials: None,
+            electrum_server: None,
         }
     }
 }
@@ -229,29 +356,147 @@ impl Default for Opts {
                 .parse()
                 .expect("Error in RGB_BIN_DIR constant value"),
             threaded: false,
+            all_in_one: false,
+            rpc_endpoint: RGBD_RPC_ENDPOINT.to_string(),
             contracts: vec![ContractName::from_str(RGB_CONTRACTS, false)
                 .expect("Error in RGB_CONTRACTS constant value")],
             fungible_rpc_endpoint: FUNGIBLED_RPC_ENDPOINT.to_string(),
             stash_rpc_endpoint: STASHD_RPC_ENDPOINT.to_string(),
             cache: FUNGIBLED_CACHE.to_string(),
             #[cfg(feature = "serde_yaml")]
-            format: FileFormat::Yaml,
+            format: DataFormat::Yaml,
             #[cfg(not(feature = "serde_yaml"))]
-            format: FileFormat::StrictEncode,
+            format: DataFormat::StrictEncode,
             stash: STASHD_STASH.to_string(),
             index: STASHD_INDEX.to_string(),
-            network: RGB_NETWORK
+            networks: vec![RGB_NETWORK
                 .parse()
-                .expect("Error in RGB_NETWORK constant value"),
+                .expect("Error in RGB_NETWORK constant value")],
             verbose: 0,
-            electrum_server: DEFAULT_ELECTRUM_ENDPOINT
-                .parse()
-                .expect("Error in DEFAULT_ELECTRUM_ENDPOINT constant value"),
+            log: LogTarget::Stderr,
+            log_filter: None,
+            daemon: false,
+            pid_file: None,
+            chroot: false,
+            user: None,
+            electrum_server: None,
+            config_file: None,
+            dump_config: false,
         }
     }
 }
 
 impl Config {
+    /// Unprivileged user the daemon switches to, unless it was resolved
+    /// before confinement
+    #[cfg(feature = "server")]
+    pub fn credentials(&self) -> Result<Option<Credentials>, LifecycleError> {
+        match self.credentials {
+            Some(ref credentials) => Ok(Some(credentials.clone())),
+            None => self.user.as_deref().map(Credentials::resolve).transpose(),
+        }
+    }
+
+    /// Effective configuration with the configuration file keys
+    #[cfg(feature = "node")]
+    pub fn dump(&self) -> toml::value::Table {
+        let mut table = toml::value::Table::new();
+        table.insert(s!("verbose"), config::integer(self.verbose));
+        table.insert(s!("log"), config::value(&self.log));
+        if !self.log_filter.is_empty() {
+            table.insert(s!("log-filter"), config::value(&self.log_filter));
+        }
+        if let Some(ref user) = self.user {
+            table.insert(s!("user"), config::value(user));
+        }
+        table.insert(s!("bin-dir"), config::value(self.bin_dir.display()));
+        table.insert(s!("data-dir"), config::value(self.data_dir.display()));
+        table.insert(s!("rpc"), config::value(&self.rpc_endpoint));
+        table.insert(
+            s!("contract"),
+            toml::Value::Array(
+                self.contracts
+                    .iter()
+                    .map(|name| config::value(name.to_string().to_lowercase()))
+                    .collect(),
+            ),
+        );
+        table.insert(
+            s!("fungible-rpc"),
+            config::value(&self.fungible_rpc_endpoint),
+        );
+        table.insert(s!("stash-rpc"), config::value(&self.stash_rpc_endpoint));
+        table.insert(s!("cache"), config::value(&self.cache));
+        table.insert(s!("format"), config::value(self.format));
+        table.insert(s!("stash"), config::value(&self.stash));
+        table.insert(s!("index"), config::value(&self.index));
+        table.insert(
+            s!("network"),
+            toml::Value::Array(
+                self.networks.iter().map(config::value).collect(),
+            ),
+        );
+        if let Some(ref server) = self.electrum_server {
+            table.insert(s!("electrum"), config::value(server));
+        }
+        table
+    }
+
+    /// Checks that the daemons serving different networks will not share
+    /// their data or endpoints
+    pub fn check_networks(&self) -> Result<(), String> {
+        for (index, network) in self.networks.iter().enumerate() {
+            if self.networks[..index].contains(network) {
+                Err(format!("network {} is given more than once", network))?
+            }
+        }
+        if self.networks.len() < 2 {
+            return Ok(());
+        }
+        for (name, param) in &[
+            ("cache", &self.cache),
+            ("stash", &self.stash),
+            ("index", &self.index),
+            ("fungible-rpc", &self.fungible_rpc_endpoint),
+            ("stash-rpc", &self.stash_rpc_endpoint),
+        ] {
+            // Stash endpoints are assigned by the supervisor in all-in-one
+            // mode
+            if *name == "stash-rpc" && self.all_in_one {
+                continue;
+            }
+            if !param.contains("{network}") {
+                Err(format!(
+                    "`{}` parameter must contain `{{network}}` placeholder \
+                     when several networks are served",
+                    name
+                ))?
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves parameter for the daemons serving the given network
+    pub fn network_param(&self, param: &str, network: &Chain) -> String {
+        param.replace("{network}", &network.to_string())
+    }
+
+    /// Stash RPC endpoint used by the daemons serving the given network; in
+    /// all-in-one mode stash daemon is reached in-process
+    pub fn stash_rpc(&self, network: &Chain) -> String {
+        if !self.all_in_one {
+            return self.network_param(&self.stash_rpc_endpoint, network);
+        }
+        let index = self
+            .networks
+            .iter()
+            .position(|known| known == network)
+            .unwrap_or_default();
+        format!("inproc://stashd-{}", index)
+    }
+
+    /// Resolves all placeholders except `{network}`, which is resolved for
+    /// each of the served networks with [`Config::network_param`]
     pub fn parse_param<T>(&self, param: String) -> T
     where
         T: FromStr,
@@ -259,7 +504,6 @@ impl Config {
     {
         param
             .replace("{id}", "default")
-            .replace("{network}", &self.network.to_string())
             .replace("{data_dir}", self.data_dir.to_str().unwrap())
             .parse()
             .unwrap_or_else(|err| {
@@ -267,3 +511,34 @@ impl Config {
             })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use internet2::zmqsocket::ZmqSocketAddr;
+
+    #[test]
+    fn test_all_in_one_endpoints() {
+        let mut config = Config {
+            networks: vec![Chain::Testnet3, Chain::Signet],
+            cache: s!("{network}/cache"),
+            stash: s!("{network}/stash"),
+            index: s!("{network}/index"),
+            fungible_rpc_endpoint: s!("lnpz:{network}/fungibled.rpc"),
+            stash_rpc_endpoint: s!("lnpz:stashd.rpc"),
+            ..Config::default()
+        };
+        assert!(config.check_networks().is_err());
+
+        config.all_in_one = true;
+        assert!(config.check_networks().is_ok());
+        assert_ne!(
+            config.stash_rpc(&Chain::Testnet3),
+            config.stash_rpc(&Chain::Signet)
+        );
+        assert!(matches!(
+            config.stash_rpc(&Chain::Signet).parse(),
+            Ok(ZmqSocketAddr::Inproc(_))
+        ));
+    }
+}
@@ -11,27 +11,107 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-#[cfg(any(feature = "node"))]
-use clap::Clap;
+//! Supervisor launching stash and contract daemons for each of the served
+//! networks as processes or threads, restarting them with increasing delay
+//! once they exit and reporting their status via RPC API. Configuration file
+//! location is passed to the daemons in `RGB_CONFIG` environment variable,
+//! and SIGTERM and SIGHUP received by the supervisor are forwarded to the
+//! daemon processes.
+
+#[cfg(feature = "node")]
+use std::sync::{mpsc, Arc, Mutex};
+#[cfg(feature = "node")]
+use std::time::{Duration, Instant};
+#[cfg(feature = "node")]
 use std::{process, thread};
 
+#[cfg(feature = "node")]
+use internet2::zmqsocket::ZmqType;
+#[cfg(feature = "node")]
+use internet2::{session, CreateUnmarshaller, Session, TypedEnum, Unmarshall};
+#[cfg(feature = "node")]
+use lnpbp::Chain;
 #[cfg(any(feature = "node"))]
 use microservices::node::TryService;
 
 use super::Config;
+#[cfg(feature = "node")]
+use crate::constants::*;
 use crate::error::{BootstrapError, RuntimeError};
 #[cfg(any(feature = "node"))]
 use crate::fungibled;
 #[cfg(feature = "node")]
+use crate::rpc::reply::{DaemonState, DaemonStatus};
+#[cfg(feature = "node")]
+use crate::rpc::supervisor::Request;
+#[cfg(feature = "node")]
+use crate::rpc::Reply;
+#[cfg(feature = "node")]
 use crate::stashd;
+#[cfg(feature = "node")]
+use crate::util::compression::{
+    compress, decompress, RPC_COMPRESSION_THRESHOLD,
+};
+#[cfg(feature = "server")]
+use crate::util::daemon;
+#[cfg(feature = "node")]
+use crate::util::{config, reload, shutdown};
+
+/// Interval between checks of the daemon state
+#[cfg(feature = "node")]
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 pub struct Runtime {
     config: Config,
+
+    /// Daemons managed by the supervisor
+    #[cfg(feature = "node")]
+    daemons: Vec<Daemon>,
+
+    /// Status of the daemons as of the last check, shared with the RPC API
+    /// thread
+    #[cfg(feature = "node")]
+    status: Arc<Mutex<Vec<DaemonStatus>>>,
+
+    /// Reload generation forwarded to the daemon processes
+    #[cfg(feature = "node")]
+    reload_generation: usize,
 }
 
 impl Runtime {
     pub fn init(config: Config) -> Result<Self, BootstrapError> {
-        Ok(Self { config })
+        config.check_networks()?;
+        #[cfg(feature = "node")]
+        let daemons = config
+            .networks
+            .iter()
+            .flat_map(|network| {
+                Some("stashd")
+                    .into_iter()
+                    .chain(config.contracts.iter().map(|c| c.daemon_name()))
+                    .map(move |name| (name, network))
+            })
+            .map(|(name, network)| Daemon {
+                name: name.to_string(),
+                network: network.clone(),
+                args: Self::daemon_args(&config, name, network),
+                handle: None,
+                started: Instant::now(),
+                restart_at: None,
+                delay: Duration::from_secs(RGBD_RESTART_DELAY as u64),
+                restarts: 0,
+                last_exit: None,
+            })
+            .collect();
+        Ok(Self {
+            config,
+            #[cfg(feature = "node")]
+            daemons,
+            #[cfg(feature = "node")]
+            status: Arc::new(Mutex::new(vec![])),
+            #[cfg(feature = "node")]
+            reload_generation: reload::generation(),
+        })
     }
 
     #[cfg(any(feature = "node"))]
@@ -41,13 +121,15 @@ impl Runtime {
     ) -> Result<thread::JoinHandle<Result<(), DaemonError>>, DaemonError> {
         match name {
             "stashd" => {
-                let opts = stashd::Opts::parse_from(args.into_iter());
+                let opts = config::parse_from::<stashd::Opts, _, _>(args)
+                    .map_err(BootstrapError::from)?;
                 Ok(thread::spawn(move || {
                     Ok(stashd::main_with_config(opts.into())?)
                 }))
             }
             "fungibled" => {
-                let opts = fungibled::Opts::parse_from(args.into_iter());
+                let opts = config::parse_from::<fungibled::Opts, _, _>(args)
+                    .map_err(BootstrapError::from)?;
                 Ok(thread::spawn(move || {
                     Ok(fungibled::main_with_config(opts.into())?)
                 }))
@@ -56,68 +138,313 @@ impl Runtime {
         }
     }
 
+    /// Command-line arguments of the daemon serving the given network
     #[cfg(any(feature = "node"))]
-    fn daemon(&self, bin: &str) -> Result<DaemonHandle, DaemonError> {
-        let common_args: Vec<String> = vec![
-            s!("-v"), // required flag but doesn't change verbosity
+    fn daemon_args(config: &Config, bin: &str, network: &Chain) -> Vec<String> {
+        let param = |param: &str| config.network_param(param, network);
+        // First argument is the program name
+        let mut common_args: Vec<String> = vec![
+            bin.to_string(),
             s!("--data-dir"),
-            self.config
+            config
                 .data_dir
                 .to_str()
                 .expect("Datadir path is wrong")
                 .to_string(),
             s!("--network"),
-            self.config.network.to_string(),
+            network.to_string(),
+            s!("--log"),
+            config.log.to_string(),
         ];
+        if config.verbose > 0 {
+            common_args
+                .push(format!("-{}", "v".repeat(config.verbose as usize)));
+        }
+        if !config.log_filter.is_empty() {
+            common_args.extend(vec![
+                s!("--log-filter"),
+                config.log_filter.to_string(),
+            ]);
+        }
         let mut fungibled_args: Vec<String> = common_args.clone();
         let mut stashd_args: Vec<String> = common_args.clone();
         fungibled_args.extend(
             vec![
                 s!("--rpc"),
-                self.config.fungible_rpc_endpoint.to_string(),
+                param(&config.fungible_rpc_endpoint),
                 s!("--stash-rpc"),
-                self.config.stash_rpc_endpoint.to_string(),
+                config.stash_rpc(network),
                 s!("--cache"),
-                self.config.cache.to_string(),
+                param(&config.cache),
                 s!("--format"),
-                self.config.format.to_string(),
+                config.format.to_string(),
             ]
             .iter()
             .cloned(),
         );
         stashd_args.extend(vec![
             s!("--rpc"),
-            self.config.stash_rpc_endpoint.to_string(),
+            config.stash_rpc(network),
             s!("--stash"),
-            self.config.stash.to_string(),
+            param(&config.stash),
             s!("--index"),
-            self.config.index.to_string(),
-            s!("--electrum"),
-            self.config.electrum_server.to_string(),
+            param(&config.index),
         ]);
-        let args;
+        if let Some(ref server) = config.electrum_server {
+            fungibled_args.extend(vec![s!("--electrum"), server.clone()]);
+            stashd_args.extend(vec![s!("--electrum"), server.clone()]);
+        }
         match bin {
-            "stashd" => {
-                args = stashd_args;
+            "stashd" => stashd_args,
+            "fungibled" => fungibled_args,
+            _ => common_args,
+        }
+    }
+
+    /// Starts thread serving the supervisor RPC API with the daemon status
+    #[cfg(feature = "node")]
+    fn serve_rpc(&self) -> Result<(), BootstrapError> {
+        let endpoint = self.config.rpc_endpoint.clone();
+        let status = self.status.clone();
+        let (sender, receiver) = mpsc::sync_channel(1);
+        thread::spawn(move || {
+            let mut session = match session::Raw::with_zmq_unencrypted(
+                ZmqType::Rep,
+                &endpoint,
+                None,
+                None,
+            ) {
+                Ok(session) => {
+                    let _ = sender.send(Ok(()));
+                    session
+                }
+                Err(err) => {
+                    let _ = sender.send(Err(err));
+                    return;
+                }
+            };
+            let unmarshaller = Request::create_unmarshaller();
+            while !shutdown::is_requested() {
+                let raw = match session.recv_raw_message() {
+                    Ok(raw) => raw,
+                    Err(err) => {
+                        error!("Error receiving supervisor request: {}", err);
+                        return;
+                    }
+                };
+                let reply = match decompress(raw)
+                    .and_then(|raw| unmarshaller.unmarshall(&raw))
+                {
+                    Ok(request) => {
+                        debug!("Received supervisor request {}", request);
+                        match *request {
+                            Request::Ping => Reply::Success,
+                            Request::Status => Reply::Daemons(
+                                status
+                                    .lock()
+                                    .map(|status| status.clone())
+                                    .unwrap_or_default(),
+                            ),
+                        }
+                    }
+                    Err(err) => Reply::from(err),
+                };
+                let data =
+                    compress(reply.serialize(), RPC_COMPRESSION_THRESHOLD);
+                if let Err(err) = session.send_raw_message(&data) {
+                    error!("Error sending supervisor reply: {}", err);
+                    return;
+                }
             }
-            "fungibled" => {
-                args = fungibled_args;
+        });
+        receiver.recv().map_err(|_| BootstrapError::Other)??;
+        Ok(())
+    }
+
+    /// Restarts the exited daemons once their restart delay passes and
+    /// forwards configuration reload requests to the daemon processes
+    #[cfg(feature = "node")]
+    fn supervise(&mut self) {
+        let reload = self.reload_generation != reload::generation();
+        self.reload_generation = reload::generation();
+        for daemon in &mut self.daemons {
+            if let Some(exit) = daemon.check_exit() {
+                daemon.schedule_restart(exit);
+            }
+            match daemon.restart_at {
+                Some(time) if time <= Instant::now() => {
+                    daemon.restarts += 1;
+                    if let Err(err) = daemon.start(&self.config) {
+                        daemon.schedule_restart(err.to_string());
+                    }
+                }
+                _ => {}
+            }
+            #[cfg(feature = "server")]
+            if reload {
+                daemon.signal(nix::sys::signal::Signal::SIGHUP);
+            }
+        }
+        #[cfg(not(feature = "server"))]
+        let _ = reload;
+        self.publish_status();
+    }
+
+    #[cfg(feature = "node")]
+    fn publish_status(&self) {
+        let status = self.daemons.iter().map(Daemon::status).collect();
+        if let Ok(mut shared) = self.status.lock() {
+            *shared = status;
+        }
+    }
+
+    /// Stops all of the daemons, waiting for them to exit
+    #[cfg(feature = "node")]
+    fn shutdown(&mut self) {
+        info!("Shutting down daemons");
+        for daemon in &mut self.daemons {
+            daemon.restart_at = None;
+            #[cfg(feature = "server")]
+            daemon.signal(nix::sys::signal::Signal::SIGTERM);
+            #[cfg(not(feature = "server"))]
+            if let Some(DaemonHandle::Process(ref mut proc)) = daemon.handle {
+                let _ = proc.kill();
             }
-            _ => args = [].to_vec(),
         }
+        for daemon in &mut self.daemons {
+            if let Some(exit) = daemon.wait() {
+                info!("{} has {}", daemon, exit);
+                daemon.last_exit = Some(exit);
+            }
+        }
+        self.publish_status();
+    }
+}
 
-        if self.config.threaded {
-            Ok(DaemonHandle::Task(Self::get_task_for(bin, &args)?))
+/// Daemon managed by the supervisor
+#[cfg(feature = "node")]
+#[derive(Debug, Display)]
+#[display("{name} for {network} network")]
+struct Daemon {
+    name: String,
+    network: Chain,
+    args: Vec<String>,
+    handle: Option<DaemonHandle>,
+    /// Time of the last (re)start
+    started: Instant,
+    /// Time when the exited daemon has to be restarted
+    restart_at: Option<Instant>,
+    /// Delay before the next restart
+    delay: Duration,
+    restarts: u32,
+    last_exit: Option<String>,
+}
+
+#[cfg(feature = "node")]
+impl Daemon {
+    fn start(&mut self, config: &Config) -> Result<(), DaemonError> {
+        info!("Launching {}", self);
+        self.handle = Some(if config.threaded {
+            DaemonHandle::Task(Runtime::get_task_for(&self.name, &self.args)?)
         } else {
-            let mut daemon = self.config.bin_dir.clone();
-            daemon.push(bin);
+            let mut daemon = config.bin_dir.clone();
+            daemon.push(&self.name);
             let mut cmd = process::Command::new(daemon);
-            cmd.args(&args);
-            Ok(DaemonHandle::Process(cmd.spawn()?))
+            cmd.args(&self.args[1..]);
+            DaemonHandle::Process(cmd.spawn()?)
+        });
+        self.started = Instant::now();
+        self.restart_at = None;
+        Ok(())
+    }
+
+    /// Checks whether the daemon has exited, returning description of the
+    /// exit reason
+    fn check_exit(&mut self) -> Option<String> {
+        let exited = match self.handle {
+            Some(DaemonHandle::Process(ref mut proc)) => {
+                !matches!(proc.try_wait(), Ok(None))
+            }
+            Some(DaemonHandle::Task(ref thread)) => thread.is_finished(),
+            None => false,
+        };
+        if exited {
+            self.wait()
+        } else {
+            None
+        }
+    }
+
+    /// Waits for the daemon to exit, returning description of the exit
+    /// reason
+    fn wait(&mut self) -> Option<String> {
+        Some(match self.handle.take()? {
+            DaemonHandle::Process(mut proc) => match proc.wait() {
+                Ok(status) => format!("exited with {}", status),
+                Err(err) => format!("failed: {}", err),
+            },
+            DaemonHandle::Task(thread) => match thread.join() {
+                Ok(Ok(())) => s!("exited"),
+                Ok(Err(err)) => format!("failed: {}", err),
+                Err(_) => s!("panicked"),
+            },
+        })
+    }
+
+    fn schedule_restart(&mut self, exit: String) {
+        let min_delay = Duration::from_secs(RGBD_RESTART_DELAY as u64);
+        let max_delay = Duration::from_secs(RGBD_MAX_RESTART_DELAY as u64);
+        if self.started.elapsed().as_secs() >= RGBD_STABLE_UPTIME as u64 {
+            self.delay = min_delay;
+        }
+        warn!(
+            "{} has {}; restarting in {} s",
+            self,
+            exit,
+            self.delay.as_secs()
+        );
+        self.restart_at = Some(Instant::now() + self.delay);
+        self.delay = (self.delay * 2).min(max_delay);
+        self.last_exit = Some(exit);
+    }
+
+    /// Sends signal to the daemon process; daemon threads receive signals
+    /// together with the supervisor
+    #[cfg(feature = "server")]
+    fn signal(&self, signal: nix::sys::signal::Signal) {
+        if let Some(DaemonHandle::Process(ref proc)) = self.handle {
+            let pid = nix::unistd::Pid::from_raw(proc.id() as i32);
+            if let Err(err) = nix::sys::signal::kill(pid, signal) {
+                warn!("Unable to send {} to {}: {}", signal, self, err);
+            }
+        }
+    }
+
+    fn status(&self) -> DaemonStatus {
+        let state = match (&self.handle, self.restart_at) {
+            (Some(_), _) => DaemonState::Running,
+            (None, Some(_)) => DaemonState::Restarting,
+            (None, None) => DaemonState::Stopped,
+        };
+        DaemonStatus {
+            name: self.name.clone(),
+            network: self.network.to_string(),
+            state,
+            pid: match self.handle {
+                Some(DaemonHandle::Process(ref proc)) => Some(proc.id()),
+                _ => None,
+            },
+            uptime: match self.handle {
+                Some(_) => self.started.elapsed().as_secs(),
+                None => 0,
+            },
+            restarts: self.restarts,
+            last_exit: self.last_exit.clone(),
         }
     }
 }
 
+#[cfg(feature = "node")]
 #[derive(Debug)]
 enum DaemonHandle {
     Process(process::Child),
@@ -157,50 +484,75 @@ impl From<BootstrapError> for DaemonError {
     }
 }
 
-impl DaemonHandle {
-    fn future(self) -> Result<(), DaemonError> {
-        match self {
-            DaemonHandle::Process(mut proc) => Ok(proc.wait().map(|_| ())?),
-            DaemonHandle::Task(thread) => {
-                Ok(thread.join().map_err(|_| DaemonError::Thread)??)
-            }
-        }
-    }
-}
-
 #[cfg(any(feature = "node"))]
 impl TryService for Runtime {
     type ErrorType = DaemonError;
 
-    fn try_run_loop(self) -> Result<(), DaemonError> {
-        let mut handlers = vec![];
-
-        handlers.push(self.daemon("stashd")?);
-
-        self.config.contracts.iter().try_for_each(
-            |contract_name| -> Result<(), DaemonError> {
-                handlers.push(self.daemon(contract_name.daemon_name())?);
-                Ok(())
-            },
-        )?;
-
-        handlers
-            .into_iter()
-            .map(|d| d.future())
-            .into_iter()
-            .try_for_each(|res| -> Result<(), DaemonError> {
-                res?;
-                Ok(())
-            })?;
+    fn try_run_loop(mut self) -> Result<(), DaemonError> {
+        self.serve_rpc()?;
+        // Daemons are launched by the unprivileged user
+        #[cfg(feature = "server")]
+        if let Some(credentials) =
+            self.config.credentials().map_err(BootstrapError::from)?
+        {
+            daemon::drop_privileges(&credentials)
+                .map_err(BootstrapError::from)?;
+        }
+        for daemon in &mut self.daemons {
+            daemon.start(&self.config)?;
+        }
+        self.publish_status();
 
-        unreachable!()
+        while !shutdown::is_requested() {
+            thread::sleep(POLL_INTERVAL);
+            self.supervise();
+        }
+        self.shutdown();
+        Ok(())
     }
 }
 
 #[cfg(any(feature = "node"))]
 pub fn main_with_config(config: Config) -> Result<(), BootstrapError> {
+    #[cfg(feature = "server")]
+    shutdown::install(config.rpc_endpoint.clone(), Request::Ping.serialize())?;
     let runtime = Runtime::init(config)?;
-    runtime.run_or_panic("RGBd runtime");
+    if let Err(err) = runtime.try_run_loop() {
+        panic!("RGBd runtime has failed with error {}", err);
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "node"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_restart_backoff() {
+        let mut daemon = Daemon {
+            name: s!("stashd"),
+            network: Chain::Regtest(Default::default()),
+            args: vec![],
+            handle: None,
+            started: Instant::now(),
+            restart_at: None,
+            delay: Duration::from_secs(RGBD_RESTART_DELAY as u64),
+            restarts: 0,
+            last_exit: None,
+        };
+        assert_eq!(daemon.status().state, DaemonState::Stopped);
 
-    unreachable!()
+        let delays = (0..8)
+            .map(|_| {
+                daemon.schedule_restart(s!("exited"));
+                daemon.delay.as_secs()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(delays, vec![2, 4, 8, 16, 32, 60, 60, 60]);
+
+        let status = daemon.status();
+        assert_eq!(status.state, DaemonState::Restarting);
+        assert_eq!(status.uptime, 0);
+        assert_eq!(status.last_exit, Some(s!("exited")));
+    }
 }
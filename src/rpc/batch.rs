@@ -0,0 +1,101 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::cell::Cell;
+use std::io;
+
+use internet2::{
+    presentation, CreateUnmarshaller, TypedEnum, Unmarshall, Unmarshaller,
+};
+use lnpbp::strict_encoding::{self, StrictDecode, StrictEncode};
+
+use crate::constants::RPC_MAX_NESTING_DEPTH;
+
+thread_local! {
+    /// Nesting level of the embedded message being unmarshalled
+    static NESTING_DEPTH: Cell<usize> = Cell::new(0);
+}
+
+/// Sequence of API messages transferred as a single message. Each of the
+/// messages is encoded together with its type id, so a batch may contain
+/// any of the API messages, including other batches.
+#[derive(Clone, PartialEq, Eq, Debug, Default, From)]
+pub struct Batch<T>(pub Vec<T>)
+where
+    T: TypedEnum;
+
+impl<T> StrictEncode for Batch<T>
+where
+    T: TypedEnum,
+{
+    fn strict_encode<E: io::Write>(
+        &self,
+        e: E,
+    ) -> Result<usize, strict_encoding::Error> {
+        self.0
+            .iter()
+            .map(T::serialize)
+            .collect::<Vec<_>>()
+            .strict_encode(e)
+    }
+}
+
+impl<T> StrictDecode for Batch<T>
+where
+    T: TypedEnum + CreateUnmarshaller + Clone,
+{
+    fn strict_decode<D: io::Read>(
+        d: D,
+    ) -> Result<Self, strict_encoding::Error> {
+        let unmarshaller = T::create_unmarshaller();
+        Vec::<Vec<u8>>::strict_decode(d)?
+            .into_iter()
+            .map(|data| unmarshall(&unmarshaller, &data))
+            .collect::<Result<_, _>>()
+            .map(Batch)
+    }
+}
+
+/// Decodes API message embedded into another message. Since embedded
+/// messages may contain other embedded messages, the nesting depth is
+/// limited by [`RPC_MAX_NESTING_DEPTH`] to prevent stack exhaustion.
+pub(crate) fn unmarshall<T>(
+    unmarshaller: &Unmarshaller<T>,
+    data: &[u8],
+) -> Result<T, strict_encoding::Error>
+where
+    T: TypedEnum + Clone,
+{
+    let depth = NESTING_DEPTH.with(|depth| {
+        depth.set(depth.get() + 1);
+        depth.get()
+    });
+    let result = if depth > RPC_MAX_NESTING_DEPTH {
+        Err(strict_encoding::Error::DataIntegrityError(format!(
+            "embedded messages are nested deeper than {} levels",
+            RPC_MAX_NESTING_DEPTH
+        )))
+    } else {
+        unmarshaller
+            .unmarshall(&data)
+            .map(super::take)
+            .map_err(|err| match err {
+                presentation::Error::StrictEncoding(err) => err,
+                _ => strict_encoding::Error::DataIntegrityError(s!(
+                    "embedded message has unknown type"
+                )),
+            })
+    };
+    NESTING_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    result
+}
@@ -13,18 +13,33 @@
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_with::As;
 use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::str::FromStr;
 
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::rand::thread_rng;
+use bitcoin::secp256k1::{self, Message, PublicKey, Secp256k1, SecretKey};
 use bitcoin::util::psbt::PartiallySignedTransaction;
-use bitcoin::OutPoint;
-use lnpbp::seals::OutpointReveal;
+use bitcoin::{OutPoint, Script, Txid};
+use internet2::{CreateUnmarshaller, TypedEnum};
+use lnpbp::seals::{OutpointHash, OutpointReveal};
+use lnpbp::strict_encoding::{self, StrictDecode, StrictEncode};
 use rgb::{
     AtomicValue, Consignment, ContractId, Disclosure, Genesis, SealDefinition,
-    SealEndpoint,
+    SealEndpoint, Transition,
 };
 use rgb20::OutpointCoins;
 
-use microservices::FileFormat;
+use crate::constants::RGB20_MAX_PRECISION;
+use crate::rpc::reply::{AllocationStatus, Swap};
+use crate::rpc::Batch;
+#[cfg(feature = "serde")]
+use crate::util::AtomicValueString;
+use crate::util::{AnchorContainer, DataFormat};
 
 #[derive(Clone, Debug, Display, Api)]
 #[api(encoding = "strict")]
@@ -35,12 +50,35 @@ pub enum Request {
     #[api(type = 0x0101)]
     Issue(IssueReq),
 
+    /// Runs asset issue procedure without storing the created asset
+    #[api(type = 0x011b)]
+    #[display("preview_{0}")]
+    PreviewIssue(IssueReq),
+
     #[api(type = 0x0103)]
     Transfer(TransferReq),
 
+    /// Prepares transfer without updating asset allocations: the produced
+    /// consignment, disclosure and witness PSBT are kept by the node as a
+    /// pending transfer identified by the witness transaction id until
+    /// [`Request::CommitTransfer`] or [`Request::CancelTransfer`] is received
+    #[api(type = 0x0111)]
+    #[display("prepare_{0}")]
+    PrepareTransfer(TransferReq),
+
+    /// Commits pending transfer with the given witness transaction id,
+    /// marking the spent allocations as such
+    #[api(type = 0x0113)]
+    #[display("commit_transfer({0})")]
+    CommitTransfer(Txid),
+
+    /// Cancels pending transfer with the given witness transaction id
+    #[api(type = 0x0115)]
+    #[display("cancel_transfer({0})")]
+    CancelTransfer(Txid),
+
     #[api(type = 0x0105)]
-    #[display("validate(...)")]
-    Validate(Consignment),
+    Validate(ValidateReq),
 
     #[api(type = 0x0107)]
     Accept(AcceptReq),
@@ -53,6 +91,12 @@ pub enum Request {
     #[display("import_asset({0})")]
     ImportAsset(Genesis),
 
+    /// Registers asset defined by the consignment genesis without accepting
+    /// any of the consignment state, so the asset is known with zero balance
+    #[api(type = 0x0131)]
+    #[display("import_from_consignment(...)")]
+    ImportFromConsignment(Consignment),
+
     #[api(type = 0x010b)]
     #[display("export_asset({0})")]
     ExportAsset(ContractId),
@@ -61,17 +105,314 @@ pub enum Request {
     #[display("forget({0})")]
     Forget(OutPoint),
 
+    /// Creates a blinded version of the outpoint, keeping the blinding
+    /// factor in the node so it can be used for accepting consignments sent
+    /// to the blinded outpoint
+    #[api(type = 0x011d)]
+    #[display("blind({0})")]
+    Blind(OutPoint),
+
+    /// Restores allocations on the outpoint removed by a previous `Forget`
+    /// request, if they were not purged yet
+    #[api(type = 0x0119)]
+    #[display("restore({0})")]
+    Restore(OutPoint),
+
+    /// Removes all data about the asset: its cache entry and allocations,
+    /// which are also requested to be forgotten by the stash
+    #[api(type = 0x010f)]
+    ForgetContract(ForgetContractReq),
+
+    /// Removes all known assets; the argument is a dry-run flag
+    #[api(type = 0x0117)]
+    #[display("forget_all(dry_run: {0})")]
+    ForgetAll(bool),
+
+    /// Notifies about new lightning channel funded with the assets allocated
+    /// to the funding outpoint
+    #[api(type = 0x0121)]
+    ChannelFunded(ChannelFundedReq),
+
+    /// Notifies about new channel state changing distribution of the channel
+    /// assets between the parties
+    #[api(type = 0x0123)]
+    ChannelUpdated(ChannelUpdateReq),
+
+    /// Notifies that the channel with the given funding outpoint was closed
+    /// and the funding output is spent
+    #[api(type = 0x0125)]
+    #[display("channel_closed({0})")]
+    ChannelClosed(OutPoint),
+
+    /// Processes each of the requests in order, independently of failures
+    /// of the other requests, replying with `Reply::Batch`
+    #[api(type = 0x0127)]
+    #[display("batch(...)")]
+    Batch(Batch<Request>),
+
+    /// State-changing request made with idempotency key; if the request
+    /// with the same key was already completed, its reply is returned
+    /// without executing the request for the second time
+    #[api(type = 0x0129)]
+    Idempotent(IdempotentReq),
+
+    /// Long-running request executed in background; replied immediately
+    /// with `Reply::JobQueued`. The request result is provided by
+    /// `Request::JobStatus` and published with `Event::JobCompleted`.
+    #[api(type = 0x012B)]
+    Async(AsyncReq),
+
+    /// Status of the request queued with `Request::Async`
+    #[api(type = 0x012D)]
+    #[display("job_status({0})")]
+    JobStatus(u64),
+
+    /// Events published starting from the given sequence number and still
+    /// kept in the daemon event log
+    #[api(type = 0x012F)]
+    #[display("replay_events(from: {0})")]
+    ReplayEvents(u64),
+
+    /// Reserves outpoint for a protocol run outside of the node (like
+    /// channel funding or a swap), so transfers can't spend it until the
+    /// reservation is released or expires
+    #[api(type = 0x0133)]
+    ReserveOutpoint(ReserveOutpointReq),
+
+    /// Releases outpoint reserved with `Request::ReserveOutpoint`
+    #[api(type = 0x0135)]
+    #[display("release_outpoint({0})")]
+    ReleaseOutpoint(OutPoint),
+
+    /// Prepares own leg of an atomic swap: state transition sending the
+    /// asset to the counterparty together with the contract history and
+    /// the bitcoin inputs and outputs of the node. The leg is replied with
+    /// `Reply::SwapLeg` and has to be passed to the counterparty.
+    #[api(type = 0x0137)]
+    SwapOffer(SwapOfferReq),
+
+    /// Completes atomic swap offered by the counterparty: verifies the
+    /// offered leg, adds own leg into the same witness transaction and
+    /// anchors both transitions in it, replying with `Reply::Swap`
+    #[api(type = 0x0139)]
+    SwapComplete(SwapCompleteReq),
+
+    /// Verifies atomic swap completed by the counterparty before the node
+    /// signs the witness transaction
+    #[api(type = 0x013B)]
+    SwapVerify(SwapVerifyReq),
+
+    /// Prepares transfer like [`Request::PrepareTransfer`], but fills the
+    /// witness PSBT with all data required by hardware wallets and other
+    /// external signers: spent transactions and outputs, key derivation
+    /// paths from the registered descriptors and RGB proprietary keys. The
+    /// signed PSBT must be returned with [`Request::FinalizeTransfer`].
+    #[api(type = 0x013D)]
+    #[display("prepare_signing_{0}")]
+    PrepareSigning(TransferReq),
+
+    /// Accepts signed witness PSBT of the pending transfer: verifies that it
+    /// keeps the anchors of the transfer, finalizes and broadcasts the
+    /// witness transaction and commits the transfer
+    #[api(type = 0x013F)]
+    #[display("finalize_transfer(...)")]
+    FinalizeTransfer(PartiallySignedTransaction),
+
+    /// Approves transfer which was held for the second operator approval
+    /// since it exceeds the approval threshold; replied with
+    /// `Reply::Transfer` carrying the released consignment
+    #[api(type = 0x0141)]
+    ApproveTransfer(ApproveTransferReq),
+
+    /// Exports ledger of the changes of all known allocations mined at or
+    /// below the given block height, for accounting and tax reporting;
+    /// replied with `Reply::Ledger`
+    #[api(type = 0x0143)]
+    ExportLedger(ExportLedgerReq),
+
+    /// Exports records of the audit log made within the given time range;
+    /// replied with `Reply::AuditLog`
+    #[api(type = 0x0145)]
+    AuditLog(AuditLogReq),
+
+    /// Sets or clears administrative hold of the asset or of the outpoint,
+    /// preventing transfers of the held allocations; the request must be
+    /// signed with one of the registered admin keys
+    #[api(type = 0x0147)]
+    SetHold(SetHoldReq),
+
+    /// Request made on behalf of the account (tenant) of the node and signed
+    /// with the account key: the request sees and uses only the assets and
+    /// allocations registered under the account
+    #[api(type = 0x0149)]
+    Account(AccountReq),
+
+    /// Imports allocations of the known assets from the snapshot file
+    /// located on the daemon host. Allocations are applied to the cache in
+    /// batches; after each batch the progress is published with
+    /// `Event::BulkImportProgress`. Replied with `Reply::BulkImport`.
+    #[api(type = 0x014B)]
+    BulkImport(BulkImportReq),
+
+    /// Consignment relayed by a peer node, which is validated and accepted
+    /// if its contract is subscribed and it assigns assets to the seals
+    /// blinded by this node; otherwise replied with `Reply::Nothing`. The
+    /// outcome is published with `Event::ConsignmentAccepted`,
+    /// `Event::ConsignmentPending` or `Event::ConsignmentRejected`.
+    #[api(type = 0x014D)]
+    #[display("auto_accept(...)")]
+    AutoAccept(Consignment),
+
+    /// Sets or removes client-provided metadata entry of the cached asset;
+    /// replied with `Reply::Nothing` if the metadata were not changed
+    #[api(type = 0x014F)]
+    SetAssetMeta(SetAssetMetaReq),
+
+    /// Returns client-provided metadata of the cached asset with
+    /// `Reply::AssetMeta`
+    #[api(type = 0x0151)]
+    #[display("get_asset_meta({0})")]
+    GetAssetMeta(ContractId),
+
+    /// Adds or removes bookkeeping label of the outpoint or the asset
+    /// allocation; replied with `Reply::Nothing` if the labels were not
+    /// changed
+    #[api(type = 0x0153)]
+    SetLabel(SetLabelReq),
+
+    /// Recomputes issued, burned, replaced and circulating supply of the
+    /// asset from the contract data kept by the stash and compares them with
+    /// the cached values; replied with `Reply::SupplyReport`
+    #[api(type = 0x0155)]
+    #[display("supply_report({0})")]
+    SupplyReport(ContractId),
+
+    /// Lists unspent epoch and burn-and-replace seals of the contract with
+    /// `Reply::EpochSeals`
+    #[api(type = 0x0157)]
+    #[display("epoch_seals({0})")]
+    EpochSeals(ContractId),
+
+    /// Constructs epoch-opening transition spending the current epoch seal
+    /// and anchors it into the witness transaction; replied with
+    /// `Reply::Transfer`
+    #[api(type = 0x0159)]
+    OpenEpoch(OpenEpochReq),
+
+    /// Reports operations permitted by the schema and genesis of the
+    /// contract kept by the stash with `Reply::ContractCapabilities`
+    #[api(type = 0x015B)]
+    #[display("contract_capabilities({0})")]
+    ContractCapabilities(ContractId),
+
+    /// Composes witness transaction spending the asset inputs together with
+    /// the bitcoin outputs of the registered descriptors paying the fee, and
+    /// adding the change output; replied with `Reply::WitnessTemplate`
+    #[api(type = 0x015D)]
+    ComposeWitness(ComposeWitnessReq),
+
+    /// Reports bitcoin balance of the registered descriptors available for
+    /// paying witness transaction fees with `Reply::FundingBalance`
+    #[api(type = 0x015F)]
+    #[display("funding_balance()")]
+    FundingBalance,
+
+    /// Returns unused receive address of the registered descriptors for
+    /// topping up the funds with `Reply::FundingAddress`
+    #[api(type = 0x0161)]
+    #[display("new_funding_address()")]
+    NewFundingAddress,
+
+    /// Blinds the receiving outpoint, keeping the blinding factor for
+    /// accepting the transfer, and creates invoice for the payer; replied
+    /// with `Reply::Receive`. If the outpoint is not given, one of the
+    /// confirmed funding outputs of the descriptors is reserved for it.
+    #[api(type = 0x0163)]
+    ReceiveAsset(ReceiveAssetReq),
+
+    /// Lists receives created with `Request::ReceiveAsset` which are
+    /// neither fulfilled nor expired with `Reply::PendingReceives`
+    #[api(type = 0x0165)]
+    #[display("list_pending_receives()")]
+    ListPendingReceives,
+
+    /// Cancels pending receive to the blinded outpoint, releasing the
+    /// funding output reserved for it; replied with `Reply::Nothing` if
+    /// there is no such receive
+    #[api(type = 0x0167)]
+    #[display("cancel_receive({0})")]
+    CancelReceive(OutpointHash),
+
+    /// Lists outgoing transfers known to the node with `Reply::Transfers`
+    #[api(type = 0x0169)]
+    #[display("list_transfers()")]
+    ListTransfers,
+
+    /// Returns stage of the outgoing transfer with the given witness
+    /// transaction id with `Reply::TransferStatus`
+    #[api(type = 0x016B)]
+    #[display("transfer_status({0})")]
+    TransferStatus(Txid),
+
+    /// Exports consignment accepted by the node within the retention window
+    /// with `Reply::Consignment`, so it can be presented as a proof of the
+    /// payment; replied with `Reply::Nothing` if the consignment is unknown
+    /// or has already expired
+    #[api(type = 0x016D)]
+    #[display("export_accepted_consignment({0})")]
+    ExportAcceptedConsignment(sha256::Hash),
+
+    /// Rebuilds consignment of the earlier outgoing transfer with the given
+    /// witness transaction id from the stash data, for the recipient who has
+    /// lost the original consignment. Replied with `Reply::Consignment`.
+    #[api(type = 0x016F)]
+    #[display("reconsign({0})")]
+    Reconsign(Txid),
+
+    /// Exports bundle with everything required to claim the allocations
+    /// owned by the node from scratch on a fresh node, encrypted with the
+    /// given passphrase. Replied with `Reply::Claims`.
+    #[api(type = 0x0171)]
+    #[display("export_claims(...)")]
+    ExportClaims(Passphrase),
+
+    /// Imports claims bundle produced by `Request::ExportClaims`, replied
+    /// with `Reply::ClaimsImport`
+    #[api(type = 0x0173)]
+    ImportClaims(ImportClaimsReq),
+
+    /// Backs up cache and stash into the configured backup target right
+    /// away, rotating the older backups; replied with `Reply::Backup`
+    #[api(type = 0x0175)]
+    #[display("backup()")]
+    Backup,
+
     #[api(type = 0xFF01)]
     #[display("sync(using: {0})")]
-    Sync(FileFormat),
+    Sync(DataFormat),
 
     #[api(type = 0xFF02)]
     #[display("assets(on: {0})")]
     Assets(OutPoint),
 
     #[api(type = 0xFF03)]
-    #[display("allocations({0})")]
-    Allocations(ContractId),
+    Allocations(AllocationsReq),
+
+    /// Liveness check, replied with `Reply::Success` without touching any of
+    /// the daemon dependencies
+    #[api(type = 0xFF04)]
+    #[display("ping()")]
+    Ping,
+
+    /// Readiness check of the daemon and the services it depends on
+    #[api(type = 0xFF05)]
+    #[display("status()")]
+    Status,
+
+    /// Re-reads tunable settings from the daemon configuration file
+    #[api(type = 0xFF06)]
+    #[display("reload_config()")]
+    ReloadConfig,
 }
 
 #[derive(
@@ -100,8 +441,10 @@ pub struct IssueReq {
     #[clap(short, long, default_value = "0")]
     pub precision: u8,
 
-    /// Asset allocation, in form of <amount>@<txid>:<vout>
-    pub allocation: Vec<OutpointCoins>,
+    /// Asset allocation, in form of <amount>@<txid>:<vout> for a revealed
+    /// outpoint, <amount>@<utxob...> for a blinded outpoint or
+    /// <amount>@<vout> for an output of the witness transaction
+    pub allocation: Vec<IssueAllocation>,
 
     /// Outputs controlling inflation (secondary issue);
     /// in form of <amount>@<txid>:<vout>
@@ -118,8 +461,320 @@ pub struct IssueReq {
     /// right of opening the first epoch
     #[clap(short, long)]
     pub epoch: Option<OutPoint>,
+
+    /// Disable secondary issuance, omitting inflation rights from the
+    /// genesis even if inflation outputs are given
+    #[clap(long)]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub no_inflation: bool,
+
+    /// Disable renomination, omitting renomination right from the genesis
+    #[clap(long)]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub no_renomination: bool,
+
+    /// Disable burn & replacement procedure, omitting epoch right from the
+    /// genesis
+    #[clap(long)]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub no_burn: bool,
+}
+
+impl IssueReq {
+    /// Removes rights of the capabilities disabled with the issue flags, so
+    /// the schema processor does not create their genesis assignments
+    pub fn restrict(mut self) -> Self {
+        if self.no_inflation {
+            self.inflation = vec![];
+        }
+        if self.no_renomination {
+            self.renomination = None;
+        }
+        if self.no_burn {
+            self.epoch = None;
+        }
+        self
+    }
+
+    /// Validates issue parameters before they are passed to RGB20 schema
+    /// processor, checking asset precision and that neither the issued amount
+    /// nor total supply (including inflation) overflows `u64` or exceeds the
+    /// provided supply cap. Returns total issued amount on success.
+    pub fn validate(
+        &self,
+        supply_cap: Option<AtomicValue>,
+    ) -> Result<AtomicValue, IssueError> {
+        if self.precision > RGB20_MAX_PRECISION {
+            Err(IssueError::PrecisionTooLarge(self.precision))?
+        }
+        let issued = self
+            .allocation
+            .iter()
+            .try_fold(0u64, |sum, IssueAllocation { coins, .. }| {
+                sum.checked_add(*coins)
+            })
+            .ok_or(IssueError::IssueOverflow)?;
+        let supply = self
+            .inflation
+            .iter()
+            .try_fold(issued, |sum, OutpointCoins { coins, .. }| {
+                sum.checked_add(*coins)
+            })
+            .ok_or(IssueError::SupplyOverflow)?;
+        match supply_cap {
+            Some(cap) if supply > cap => {
+                Err(IssueError::SupplyCapExceeded { supply, cap })
+            }
+            _ => Ok(issued),
+        }
+    }
+}
+
+/// Errors detected during validation of asset issue parameters
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum IssueError {
+    /// asset precision {0} exceeds maximum allowed value of 19 decimal digits
+    PrecisionTooLarge(u8),
+
+    /// sum of the issued allocations overflows 64-bit atomic value
+    IssueOverflow,
+
+    /// sum of the issued amount and inflation allowance overflows 64-bit
+    /// atomic value
+    SupplyOverflow,
+
+    /// total asset supply {supply} exceeds supply cap of {cap} configured for
+    /// the node
+    SupplyCapExceeded {
+        supply: AtomicValue,
+        cap: AtomicValue,
+    },
+}
+
+/// Seal receiving assets at issue
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Display,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize,),
+    serde(crate = "serde_crate")
+)]
+pub enum IssueSeal {
+    /// Revealed transaction output, which is put into the genesis as is
+    #[display(inner)]
+    Revealed(OutPoint),
+
+    /// Blinded transaction output, provided by the receiver which does not
+    /// want to reveal the outpoint in the (publicly broadcasted) genesis
+    #[display(inner)]
+    Concealed(OutpointHash),
+
+    /// Output of the witness transaction, identified by its number
+    #[display(inner)]
+    WitnessVout(u32),
+}
+
+impl FromStr for IssueSeal {
+    type Err = AllocationParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(':') {
+            s.parse()
+                .map(IssueSeal::Revealed)
+                .map_err(|_| AllocationParseError)
+        } else if s.chars().all(|c| c.is_ascii_digit()) {
+            s.parse()
+                .map(IssueSeal::WitnessVout)
+                .map_err(|_| AllocationParseError)
+        } else {
+            s.parse()
+                .map(IssueSeal::Concealed)
+                .map_err(|_| AllocationParseError)
+        }
+    }
+}
+
+/// Amount of the asset allocated at issue to a given seal
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize,),
+    serde(crate = "serde_crate")
+)]
+pub struct IssueAllocation {
+    #[cfg_attr(feature = "serde", serde(with = "As::<AtomicValueString>"))]
+    pub coins: AtomicValue,
+    pub seal: IssueSeal,
+}
+
+impl From<OutpointCoins> for IssueAllocation {
+    fn from(outpoint_coins: OutpointCoins) -> Self {
+        Self {
+            coins: outpoint_coins.coins,
+            seal: IssueSeal::Revealed(outpoint_coins.outpoint),
+        }
+    }
+}
+
+impl Display for IssueAllocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.coins, self.seal)
+    }
+}
+
+impl FromStr for IssueAllocation {
+    type Err = AllocationParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut iter = s.split('@');
+        match (iter.next(), iter.next(), iter.next()) {
+            (Some(coins), Some(seal), None) => Ok(Self {
+                coins: coins.parse().map_err(|_| AllocationParseError)?,
+                seal: seal.parse()?,
+            }),
+            _ => Err(AllocationParseError),
+        }
+    }
+}
+
+/// Amount of the asset assigned to an output of the witness transaction
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Display,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("{coins}@{vout}")]
+pub struct VoutCoins {
+    pub coins: AtomicValue,
+    pub vout: u32,
+}
+
+impl FromStr for VoutCoins {
+    type Err = AllocationParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match IssueAllocation::from_str(s)? {
+            IssueAllocation {
+                coins,
+                seal: IssueSeal::WitnessVout(vout),
+            } => Ok(VoutCoins { coins, vout }),
+            _ => Err(AllocationParseError),
+        }
+    }
+}
+
+/// Request for the asset payment, given by the receiver to the payer
+#[derive(
+    Clone, Copy, PartialEq, Eq, Hash, Debug, StrictEncode, StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Invoice {
+    pub contract_id: ContractId,
+    pub amount: AtomicValue,
+    /// Blinded outpoint receiving the asset
+    pub endpoint: OutpointHash,
+    /// Unix timestamp after which the receiver does not accept the payment
+    pub expiry: Option<i64>,
+}
+
+impl Display for Invoice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rgb20:{}?amount={}&endpoint={}",
+            self.contract_id, self.amount, self.endpoint
+        )?;
+        if let Some(expiry) = self.expiry {
+            write!(f, "&expiry={}", expiry)?;
+        }
+        Ok(())
+    }
 }
 
+/// Invoice must be in form of
+/// `rgb20:<contract_id>?amount=<amount>&endpoint=<blinded outpoint>` with
+/// optional `&expiry=<timestamp>`
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub struct InvoiceParseError;
+
+impl FromStr for Invoice {
+    type Err = InvoiceParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s.strip_prefix("rgb20:").ok_or(InvoiceParseError)?;
+        let mut split = rest.splitn(2, '?');
+        let contract_id = split
+            .next()
+            .and_then(|id| ContractId::from_str(id).ok())
+            .ok_or(InvoiceParseError)?;
+        let (mut amount, mut endpoint, mut expiry) = (None, None, None);
+        for param in split.next().ok_or(InvoiceParseError)?.split('&') {
+            match param.split_at(param.find('=').ok_or(InvoiceParseError)?) {
+                ("amount", value) => {
+                    amount = AtomicValue::from_str(&value[1..]).ok()
+                }
+                ("endpoint", value) => {
+                    endpoint = OutpointHash::from_str(&value[1..]).ok()
+                }
+                ("expiry", value) => {
+                    expiry = Some(
+                        i64::from_str(&value[1..])
+                            .map_err(|_| InvoiceParseError)?,
+                    )
+                }
+                _ => Err(InvoiceParseError)?,
+            }
+        }
+        Ok(Invoice {
+            contract_id,
+            amount: amount.ok_or(InvoiceParseError)?,
+            endpoint: endpoint.ok_or(InvoiceParseError)?,
+            expiry,
+        })
+    }
+}
+
+/// Issue allocation must be in form of <amount>@<txid>:<vout>,
+/// <amount>@<blinded outpoint> or <amount>@<vout>
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub struct AllocationParseError;
+
 #[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
 #[strict_encoding_crate(lnpbp::strict_encoding)]
 #[display("transfer({contract_id}, ...)")]
@@ -140,10 +795,569 @@ pub struct TransferReq {
     /// blinding entropy.
     pub payment: BTreeMap<SealEndpoint, AtomicValue>,
 
+    /// Receiver's allocations on the outputs of the witness transaction
+    /// itself, by the output number.
+    ///
+    /// Blinding of these seals is generated by the node and revealed to the
+    /// receiver with the consignment endpoints.
+    pub theirs_witness: BTreeMap<u32, AtomicValue>,
+
     /// Asset change allocations
     ///
     /// Here we always know an explicit outpoint that will contain the assets
     pub change: BTreeMap<SealDefinition, AtomicValue>,
+
+    /// Reject the transfer if any of the inputs is not confirmed yet
+    pub confirmed_only: bool,
+
+    /// Witness transaction output type holding the anchor commitment;
+    /// defaults to the node configuration
+    pub container: Option<AnchorContainer>,
+}
+
+impl TransferReq {
+    /// Total amount paid by the transfer, not counting the change
+    pub fn payment_amount(&self) -> AtomicValue {
+        self.payment
+            .values()
+            .chain(self.theirs_witness.values())
+            .fold(0u64, |sum, amount| sum.saturating_add(*amount))
+    }
+
+    /// Moves allocations on the witness transaction outputs to the payment
+    /// seals with newly generated blinding
+    pub fn reveal_witness_payments(&mut self) {
+        let mut rng = thread_rng();
+        for (vout, amount) in std::mem::take(&mut self.theirs_witness) {
+            self.payment
+                .insert(SealEndpoint::with_vout(vout, &mut rng), amount);
+        }
+    }
+
+    /// Total amount leaving the seals controlled by the node: the payments
+    /// together with the change assigned to the `foreign` seals, which are
+    /// not controlled by the node
+    pub fn outgoing_amount(
+        &self,
+        foreign: &BTreeSet<SealDefinition>,
+    ) -> AtomicValue {
+        self.change
+            .iter()
+            .filter(|(seal, _)| foreign.contains(seal))
+            .fold(self.payment_amount(), |sum, (_, amount)| {
+                sum.saturating_add(*amount)
+            })
+    }
+
+    /// Enforces dust limit on the transfer outputs.
+    ///
+    /// Payments below the dust limit are rejected with
+    /// [`TransferError::DustOutput`] naming the receiver. Change allocations
+    /// below the limit are merged into the largest change allocation; if the
+    /// merged change is still below the limit the transfer is rejected.
+    pub fn apply_dust_limit(
+        &mut self,
+        dust_limit: AtomicValue,
+    ) -> Result<(), TransferError> {
+        if let Some((endpoint, amount)) = self
+            .payment
+            .iter()
+            .find(|(_, amount)| **amount < dust_limit)
+        {
+            Err(TransferError::DustOutput {
+                endpoint: *endpoint,
+                amount: *amount,
+                dust_limit,
+            })?
+        }
+
+        let dust = self
+            .change
+            .iter()
+            .filter(|(_, amount)| **amount < dust_limit)
+            .map(|(seal, _)| *seal)
+            .collect::<Vec<_>>();
+        if dust.is_empty() {
+            return Ok(());
+        }
+        let target = *self
+            .change
+            .iter()
+            .max_by_key(|(_, amount)| **amount)
+            .map(|(seal, _)| seal)
+            .expect("change can't be empty when it contains dust");
+        let mut merged = self.change[&target];
+        for seal in dust.into_iter().filter(|seal| *seal != target) {
+            merged += self.change.remove(&seal).unwrap_or_default();
+        }
+        if merged < dust_limit {
+            Err(TransferError::DustOutput {
+                endpoint: target.into(),
+                amount: merged,
+                dust_limit,
+            })?
+        }
+        self.change.insert(target, merged);
+        Ok(())
+    }
+}
+
+/// Errors detected during validation of asset transfer parameters
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum TransferError {
+    /// output {endpoint} is assigned {amount} atomic units, which is below
+    /// the dust limit of {dust_limit}
+    DustOutput {
+        endpoint: SealEndpoint,
+        amount: AtomicValue,
+        dust_limit: AtomicValue,
+    },
+
+    /// there is no pending transfer with witness transaction id {0}
+    UnknownTransfer(Txid),
+
+    /// input {outpoint} is already reserved by pending transfer with witness
+    /// transaction id {txid}
+    InputReserved { outpoint: OutPoint, txid: Txid },
+
+    /// input {outpoint} has status {status}, while only confirmed inputs
+    /// are allowed for the transfer
+    UnconfirmedInput {
+        outpoint: OutPoint,
+        status: AllocationStatus,
+    },
+
+    /// input {outpoint} is already spent on-chain by transaction {txid}
+    SealAlreadySpent { outpoint: OutPoint, txid: Txid },
+
+    /// outpoint {0} is reserved for a protocol run outside of the node
+    OutpointReserved(OutPoint),
+
+    /// signed witness transaction {0} does not match anchors of the pending
+    /// transfer
+    AnchorMismatch(Txid),
+
+    /// transfer with witness transaction id {0} is waiting for the operator
+    /// approval
+    ApprovalPending(Txid),
+
+    /// {0} is not a registered transfer approver
+    UnknownApprover(PublicKey),
+
+    /// approval signature for the transfer with witness transaction id {0}
+    /// is invalid
+    InvalidApproval(Txid),
+
+    /// input #{input} of witness transaction {txid} is not signed or can't
+    /// be finalized
+    UnsignedInput { txid: Txid, input: usize },
+
+    /// allocations of {0} are on administrative hold
+    AllocationOnHold(HoldTarget),
+
+    /// {0} is not a registered admin
+    UnknownAdmin(PublicKey),
+
+    /// admin signature is invalid or has expired
+    InvalidAdminSignature,
+
+    /// anchor and state transition of the witness transaction {0} are not
+    /// known to the stash, so the transfer consignment can't be rebuilt
+    WitnessUnknown(Txid),
+
+    /// transfer inputs hold allocations of asset {0}, while none of the
+    /// change seals is controlled by the node, so they can't be moved
+    NoOwnedChange(ContractId),
+}
+
+/// Asset sent by the node within an atomic swap
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("send({contract_id}, ...)")]
+pub struct SwapSend {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Asset input: unspent transaction outputs
+    pub inputs: BTreeSet<OutPoint>,
+
+    /// Allocations of the counterparty
+    pub payment: BTreeMap<SealEndpoint, AtomicValue>,
+
+    /// Asset change allocations
+    pub change: BTreeMap<SealDefinition, AtomicValue>,
+}
+
+impl SwapSend {
+    /// Shifts witness transaction output numbers used by the seals by the
+    /// given offset
+    pub fn shift_vouts(&mut self, offset: u32) {
+        self.payment = self
+            .payment
+            .iter()
+            .map(|(endpoint, amount)| match endpoint {
+                SealEndpoint::WitnessVout { vout, blinding } => (
+                    SealEndpoint::WitnessVout {
+                        vout: vout + offset,
+                        blinding: *blinding,
+                    },
+                    *amount,
+                ),
+                _ => (*endpoint, *amount),
+            })
+            .collect();
+        self.change = self
+            .change
+            .iter()
+            .map(|(seal, amount)| match seal {
+                SealDefinition::WitnessVout { vout, blinding } => (
+                    SealDefinition::WitnessVout {
+                        vout: vout + offset,
+                        blinding: *blinding,
+                    },
+                    *amount,
+                ),
+                _ => (*seal, *amount),
+            })
+            .collect();
+    }
+
+    /// Transfer request with the same parameters, used for applying the
+    /// checks common with the ordinary transfers
+    pub fn transfer_req(
+        &self,
+        witness: &PartiallySignedTransaction,
+    ) -> TransferReq {
+        TransferReq {
+            contract_id: self.contract_id,
+            witness: witness.clone(),
+            inputs: self.inputs.clone(),
+            payment: self.payment.clone(),
+            theirs_witness: empty!(),
+            change: self.change.clone(),
+            confirmed_only: false,
+            container: None,
+        }
+    }
+}
+
+/// What the node expects to receive from the counterparty in an atomic swap
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub enum SwapReceive {
+    /// Asset allocations assigned to the node seals by the counterparty
+    /// state transition
+    #[display("receive({contract_id}, ...)")]
+    Asset {
+        contract_id: ContractId,
+        allocations: BTreeMap<SealEndpoint, AtomicValue>,
+    },
+
+    /// Witness transaction output paying at least the given amount of
+    /// satoshis to the script
+    #[display("receive({amount} sat)")]
+    Bitcoin { script_pubkey: Script, amount: u64 },
+}
+
+/// Asset part of an atomic swap leg
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("{contract_id}")]
+pub struct AssetLeg {
+    pub contract_id: ContractId,
+
+    /// Outpoints spent by the transition
+    pub inputs: BTreeSet<OutPoint>,
+
+    /// State transition which is not anchored yet
+    pub transition: Transition,
+
+    /// Partial consignment with the contract history preceding the
+    /// transition
+    pub history: Consignment,
+}
+
+/// Part of an atomic swap contributed by one of the parties
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("swap_leg(...)")]
+pub struct SwapLeg {
+    /// Asset sent by the party; absent if the party pays with bitcoins only
+    pub asset: Option<AssetLeg>,
+
+    /// Bitcoin inputs and outputs of the party
+    pub witness: PartiallySignedTransaction,
+}
+
+impl SwapLeg {
+    /// Leg of the party paying with bitcoins only, which does not require
+    /// participation of the node
+    pub fn bitcoin(witness: PartiallySignedTransaction) -> Self {
+        Self {
+            asset: None,
+            witness,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("swap_offer({send}, ...)")]
+pub struct SwapOfferReq {
+    pub send: SwapSend,
+
+    /// Bitcoin inputs and outputs of the node; witness output numbers used
+    /// by `send` seals refer to this transaction
+    pub witness: PartiallySignedTransaction,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("swap_complete({receive}, ...)")]
+pub struct SwapCompleteReq {
+    /// Leg offered by the counterparty
+    pub offer: SwapLeg,
+
+    /// Asset sent by the node in exchange; absent if the node pays with
+    /// bitcoins only
+    pub send: Option<SwapSend>,
+
+    /// Bitcoin inputs and outputs of the node. They are appended to the
+    /// ones of the offer, so the node shifts witness output numbers used by
+    /// `send` seals by the number of the offer outputs.
+    pub witness: PartiallySignedTransaction,
+
+    pub receive: SwapReceive,
+}
+
+#[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("swap_verify({receive}, ...)")]
+pub struct SwapVerifyReq {
+    /// Leg offered by the node
+    pub offer: SwapLeg,
+
+    /// Swap completed by the counterparty
+    pub swap: Swap,
+
+    pub receive: SwapReceive,
+}
+
+/// Request made with client-supplied idempotency key. Only the requests
+/// changing node state (issue, transfer, accept and forget requests) may
+/// be made with the key.
+#[derive(Clone, Debug, Display)]
+#[display("{request} with key {key}")]
+pub struct IdempotentReq {
+    /// Key identifying the operation; retries of the operation must use the
+    /// same key
+    pub key: String,
+
+    pub request: Box<Request>,
+}
+
+impl StrictEncode for IdempotentReq {
+    fn strict_encode<E: io::Write>(
+        &self,
+        mut e: E,
+    ) -> Result<usize, strict_encoding::Error> {
+        Ok(self.key.strict_encode(&mut e)?
+            + self.request.serialize().strict_encode(&mut e)?)
+    }
+}
+
+impl StrictDecode for IdempotentReq {
+    fn strict_decode<D: io::Read>(
+        mut d: D,
+    ) -> Result<Self, strict_encoding::Error> {
+        let key = String::strict_decode(&mut d)?;
+        let data = Vec::<u8>::strict_decode(&mut d)?;
+        let request =
+            super::batch::unmarshall(&Request::create_unmarshaller(), &data)?;
+        Ok(Self {
+            key,
+            request: Box::new(request),
+        })
+    }
+}
+
+/// Request which has to be executed in background. Only the requests which
+/// may take long time (issue, transfer, validate, accept, sync, import and
+/// forget requests) may be executed in background.
+#[derive(Clone, Debug, Display)]
+#[display("{request} in background")]
+pub struct AsyncReq {
+    pub request: Box<Request>,
+}
+
+impl StrictEncode for AsyncReq {
+    fn strict_encode<E: io::Write>(
+        &self,
+        e: E,
+    ) -> Result<usize, strict_encoding::Error> {
+        self.request.serialize().strict_encode(e)
+    }
+}
+
+impl StrictDecode for AsyncReq {
+    fn strict_decode<D: io::Read>(
+        d: D,
+    ) -> Result<Self, strict_encoding::Error> {
+        let data = Vec::<u8>::strict_decode(d)?;
+        let request =
+            super::batch::unmarshall(&Request::create_unmarshaller(), &data)?;
+        Ok(Self {
+            request: Box::new(request),
+        })
+    }
+}
+
+/// Tag prefixing account request in the message signed by the account key
+pub const ACCOUNT_TAG: &[u8] = b"RGB:account:";
+
+/// Request made on behalf of the account. Only the requests which can be
+/// scoped to the account assets and allocations (issue, transfer, validate,
+/// accept, blind, forget and asset queries) may be made by accounts.
+#[derive(Clone, Debug, Display)]
+#[display("{request} by account {account}")]
+pub struct AccountReq {
+    /// Public key identifying the account
+    pub account: PublicKey,
+
+    /// Unix timestamp of the request signing; the daemon rejects requests
+    /// signed too long ago, so the request can't be replayed later
+    pub timestamp: i64,
+
+    pub request: Box<Request>,
+
+    /// Account signature over [`AccountReq::message`]
+    pub signature: secp256k1::Signature,
+}
+
+impl AccountReq {
+    /// Message signed by the account: SHA256 hash of [`ACCOUNT_TAG`]
+    /// followed by the timestamp and the serialized request
+    pub fn message(timestamp: i64, request: &Request) -> Message {
+        let mut engine = sha256::Hash::engine();
+        engine.input(ACCOUNT_TAG);
+        engine.input(&timestamp.to_le_bytes());
+        engine.input(&request.serialize());
+        Message::from_slice(&sha256::Hash::from_engine(engine)[..])
+            .expect("SHA256 hash is always a valid message")
+    }
+
+    /// Constructs request signed with the account key
+    pub fn sign(
+        request: Request,
+        timestamp: i64,
+        secret_key: &SecretKey,
+    ) -> Self {
+        let secp = Secp256k1::signing_only();
+        AccountReq {
+            account: PublicKey::from_secret_key(&secp, secret_key),
+            timestamp,
+            signature: secp
+                .sign(&Self::message(timestamp, &request), secret_key),
+            request: Box::new(request),
+        }
+    }
+
+    /// Checks account signature
+    pub fn verify(&self) -> bool {
+        Secp256k1::verification_only()
+            .verify(
+                &Self::message(self.timestamp, &self.request),
+                &self.signature,
+                &self.account,
+            )
+            .is_ok()
+    }
+}
+
+impl StrictEncode for AccountReq {
+    fn strict_encode<E: io::Write>(
+        &self,
+        mut e: E,
+    ) -> Result<usize, strict_encoding::Error> {
+        Ok(self.account.strict_encode(&mut e)?
+            + self.timestamp.strict_encode(&mut e)?
+            + self.request.serialize().strict_encode(&mut e)?
+            + self.signature.strict_encode(&mut e)?)
+    }
+}
+
+impl StrictDecode for AccountReq {
+    fn strict_decode<D: io::Read>(
+        mut d: D,
+    ) -> Result<Self, strict_encoding::Error> {
+        let account = PublicKey::strict_decode(&mut d)?;
+        let timestamp = i64::strict_decode(&mut d)?;
+        let data = Vec::<u8>::strict_decode(&mut d)?;
+        let request =
+            super::batch::unmarshall(&Request::create_unmarshaller(), &data)?;
+        Ok(Self {
+            account,
+            timestamp,
+            request: Box::new(request),
+            signature: secp256k1::Signature::strict_decode(&mut d)?,
+        })
+    }
+}
+
+/// Errors of the background jobs
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum JobError {
+    /// {0} can't be executed in background
+    NotSupported(String),
+
+    /// background job #{0} is unknown or was already pruned
+    UnknownJob(u64),
+}
+
+/// Errors of the requests made on behalf of the accounts
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum AccountError {
+    /// {0} is not a registered account
+    UnknownAccount(PublicKey),
+
+    /// account signature is invalid or has expired
+    InvalidSignature,
+
+    /// {0} can't be made on behalf of an account
+    NotSupported(String),
+
+    /// {0} is not registered under the account
+    NotOwned(String),
+
+    /// {0} must be made on behalf of one of the registered accounts
+    Required(String),
+}
+
+/// Errors of the requests made with idempotency key
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum IdempotencyError {
+    /// idempotency key {0} was already used for a different request
+    KeyReused(String),
+
+    /// {0} does not change node state and can't be made with idempotency
+    /// key
+    NotStateChanging(String),
+}
+
+#[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("validate(...)")]
+pub struct ValidateReq {
+    /// Raw consignment data
+    pub consignment: Consignment,
+
+    /// Number of confirmations required for the consignment witness
+    /// transactions; overrides node configuration if given
+    pub min_confirmations: Option<u32>,
 }
 
 #[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
@@ -155,6 +1369,279 @@ pub struct AcceptReq {
 
     /// Reveal outpoints data used during invoice creation
     pub reveal_outpoints: Vec<OutpointReveal>,
+
+    /// Number of confirmations required for the consignment witness
+    /// transactions; overrides node configuration if given
+    pub min_confirmations: Option<u32>,
+}
+
+#[derive(
+    Clone, Copy, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("forget_contract({contract_id}, dry_run: {dry_run})")]
+pub struct ForgetContractReq {
+    /// Asset to forget
+    pub contract_id: ContractId,
+
+    /// Only list data which would be removed, without removing them
+    pub dry_run: bool,
+}
+
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("set_asset_meta({contract_id}, {key})")]
+pub struct SetAssetMetaReq {
+    pub contract_id: ContractId,
+
+    /// Metadata key, like `label` or `icon`
+    pub key: String,
+
+    /// New value of the key; the key is removed if the value is not given
+    pub value: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("allocations({contract_id})")]
+pub struct AllocationsReq {
+    pub contract_id: ContractId,
+
+    /// If not empty, only allocations having all of the labels are returned
+    pub labels: BTreeSet<String>,
+}
+
+impl From<ContractId> for AllocationsReq {
+    fn from(contract_id: ContractId) -> Self {
+        Self {
+            contract_id,
+            labels: bset![],
+        }
+    }
+}
+
+/// Outpoint or asset allocation labeled for bookkeeping; outpoint labels
+/// apply to allocations of all assets on the outpoint
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    StrictEncode,
+    StrictDecode,
+    Debug,
+    Display,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub enum LabelTarget {
+    #[display("outpoint {0}")]
+    Outpoint(OutPoint),
+
+    /// Allocation of the asset on the outpoint
+    #[display("allocation {0}@{1}")]
+    Allocation(ContractId, OutPoint),
+}
+
+impl FromStr for LabelTarget {
+    type Err = String;
+
+    /// Parses `<outpoint>` or `<contract_id>@<outpoint>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || {
+            format!("`{}` is neither an outpoint nor an asset allocation", s)
+        };
+        let mut iter = s.split('@');
+        match (iter.next(), iter.next(), iter.next()) {
+            (Some(outpoint), None, None) => OutPoint::from_str(outpoint)
+                .map(LabelTarget::Outpoint)
+                .map_err(|_| err()),
+            (Some(contract_id), Some(outpoint), None) => {
+                Ok(LabelTarget::Allocation(
+                    ContractId::from_str(contract_id).map_err(|_| err())?,
+                    OutPoint::from_str(outpoint).map_err(|_| err())?,
+                ))
+            }
+            _ => Err(err()),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("open_epoch({contract_id}, {epoch}, ...)")]
+pub struct OpenEpochReq {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Base layer transaction structure to use
+    pub witness: PartiallySignedTransaction,
+
+    /// Outpoint of the current epoch seal, which is spent by the witness
+    /// transaction
+    pub epoch: OutPoint,
+
+    /// Seal receiving the right to open the next epoch; if not given, no
+    /// further epochs can be opened
+    pub next_epoch: Option<SealDefinition>,
+
+    /// Seal receiving the right to burn and replace assets within the
+    /// opened epoch
+    pub burn_replace: Option<SealDefinition>,
+}
+
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("compose_witness({fee_rate} sat/vB, ...)")]
+pub struct ComposeWitnessReq {
+    /// Outpoints holding the assets which will be transferred; they must be
+    /// unspent outputs of the registered descriptors
+    pub inputs: BTreeSet<OutPoint>,
+
+    /// Fee rate in sats per virtual byte
+    pub fee_rate: u64,
+
+    /// Witness transaction output type holding the anchor commitment;
+    /// defaults to the node configuration
+    pub container: Option<AnchorContainer>,
+}
+
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("set_label({target}, {label}, set: {set})")]
+pub struct SetLabelReq {
+    pub target: LabelTarget,
+
+    /// Label text, like `cold storage` or `customer #123`
+    pub label: String,
+
+    /// Whether the label is added or removed
+    pub set: bool,
+}
+
+#[derive(
+    Clap, Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("reserve_outpoint({outpoint}, {purpose}, {duration} s)")]
+pub struct ReserveOutpointReq {
+    /// Outpoint to reserve
+    pub outpoint: OutPoint,
+
+    /// Protocol the outpoint is reserved for; repeated reservation for the
+    /// same purpose extends the reservation
+    #[clap(short, long, default_value = "external")]
+    pub purpose: String,
+
+    /// Time (in seconds) after which the reservation is released
+    #[clap(short, long, default_value = "3600")]
+    pub duration: u32,
+}
+
+#[derive(
+    Clap, Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("receive_asset({contract_id}, {amount}, ...)")]
+pub struct ReceiveAssetReq {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Amount to receive, in atomic (non-float) units
+    pub amount: AtomicValue,
+
+    /// Locally-controlled outpoint receiving the asset [default: one of the
+    /// funding outputs of the node]
+    #[clap(short, long)]
+    pub utxo: Option<OutPoint>,
+
+    /// Time (in seconds) after which the receive expires [default: depends
+    /// on the node reservation timeout]
+    #[clap(short, long)]
+    pub expiry: Option<u32>,
+}
+
+/// Tag prefixing witness transaction id in the message signed by the
+/// transfer approver
+pub const TRANSFER_APPROVAL_TAG: &[u8] = b"RGB:approve-transfer:";
+
+#[derive(
+    Clone, Copy, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("approve_transfer({txid}, by: {approver})")]
+pub struct ApproveTransferReq {
+    /// Witness transaction id of the transfer held for approval
+    pub txid: Txid,
+
+    /// Public key of the approving operator
+    pub approver: PublicKey,
+
+    /// Approver signature over [`ApproveTransferReq::message`]
+    pub signature: secp256k1::Signature,
+}
+
+impl ApproveTransferReq {
+    /// Message signed by the approver: SHA256 hash of
+    /// [`TRANSFER_APPROVAL_TAG`] followed by the witness transaction id
+    pub fn message(txid: Txid) -> Message {
+        let mut engine = sha256::Hash::engine();
+        engine.input(TRANSFER_APPROVAL_TAG);
+        engine.input(&txid[..]);
+        Message::from_slice(&sha256::Hash::from_engine(engine)[..])
+            .expect("SHA256 hash is always a valid message")
+    }
+
+    /// Constructs approval signed with the operator key
+    pub fn sign(txid: Txid, secret_key: &SecretKey) -> Self {
+        let secp = Secp256k1::signing_only();
+        ApproveTransferReq {
+            txid,
+            approver: PublicKey::from_secret_key(&secp, secret_key),
+            signature: secp.sign(&Self::message(txid), secret_key),
+        }
+    }
+
+    /// Checks approver signature
+    pub fn verify(&self) -> bool {
+        Secp256k1::verification_only()
+            .verify(&Self::message(self.txid), &self.signature, &self.approver)
+            .is_ok()
+    }
+}
+
+#[derive(
+    Clone, Copy, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("channel_funded({funding}, local: {local_funded})")]
+pub struct ChannelFundedReq {
+    /// Channel funding outpoint
+    pub funding: OutPoint,
+
+    /// Whether the channel was funded by the local node
+    pub local_funded: bool,
+}
+
+#[derive(
+    Clone, Copy, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("channel_updated({contract_id}@{funding}, {local}/{remote})")]
+pub struct ChannelUpdateReq {
+    /// Channel funding outpoint
+    pub funding: OutPoint,
+
+    /// Asset which distribution is updated
+    pub contract_id: ContractId,
+
+    /// Amount of the asset belonging to the local node
+    pub local: AtomicValue,
+
+    /// Amount of the asset belonging to the remote peer
+    pub remote: AtomicValue,
 }
 
 fn ticker_validator(name: &str) -> Result<(), String> {
@@ -172,3 +1659,603 @@ fn ticker_validator(name: &str) -> Result<(), String> {
         Ok(())
     }
 }
+
+/// Format of the allocation ledger exported with `Request::ExportLedger`
+#[derive(
+    Clone, Copy, PartialEq, Eq, Hash, Debug, Display, StrictEncode, StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[repr(u8)]
+pub enum LedgerFormat {
+    /// Comma-separated values with a header row
+    #[display("csv")]
+    Csv = 1,
+
+    /// JSON array of ledger entries
+    #[display("json")]
+    Json = 2,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display("unknown ledger format `{0}`; it must be either `csv` or `json`")]
+pub struct LedgerFormatError(String);
+
+impl FromStr for LedgerFormat {
+    type Err = LedgerFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(LedgerFormat::Csv),
+            "json" => Ok(LedgerFormat::Json),
+            _ => Err(LedgerFormatError(s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("bulk_import({filename}, batch: {batch_size})")]
+pub struct BulkImportReq {
+    /// Snapshot file with the allocation records; the file is read by the
+    /// daemon, so the path must be valid on the daemon host
+    pub filename: String,
+
+    /// Number of records applied to the cache at once; zero selects the
+    /// daemon default
+    pub batch_size: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("import_claims(...)")]
+pub struct ImportClaimsReq {
+    /// Encrypted claims bundle
+    pub data: Vec<u8>,
+
+    pub passphrase: Passphrase,
+}
+
+/// Passphrase encrypting the claims bundle; it is redacted from the debug
+/// output, so the requests carrying it may be logged safely
+#[derive(Clone, PartialEq, Eq)]
+pub struct Passphrase(String);
+
+impl Passphrase {
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Passphrase {
+    #[inline]
+    fn from(passphrase: String) -> Self {
+        Passphrase(passphrase)
+    }
+}
+
+impl fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("Passphrase(...)")
+    }
+}
+
+impl StrictEncode for Passphrase {
+    fn strict_encode<E: io::Write>(
+        &self,
+        e: E,
+    ) -> Result<usize, strict_encoding::Error> {
+        self.0.strict_encode(e)
+    }
+}
+
+impl StrictDecode for Passphrase {
+    fn strict_decode<D: io::Read>(
+        d: D,
+    ) -> Result<Self, strict_encoding::Error> {
+        Ok(Passphrase(String::strict_decode(d)?))
+    }
+}
+
+#[derive(
+    Clone, Copy, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("export_ledger({height}, using: {format})")]
+pub struct ExportLedgerReq {
+    /// Block height of the ledger snapshot: allocation changes mined in
+    /// the later blocks or not mined yet are not included
+    pub height: u32,
+
+    pub format: LedgerFormat,
+}
+
+/// Subject of the administrative hold
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    StrictEncode,
+    StrictDecode,
+    Debug,
+    Display,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub enum HoldTarget {
+    /// All allocations of the asset
+    #[display("contract {0}")]
+    Contract(ContractId),
+
+    /// Allocations of all assets on the outpoint
+    #[display("outpoint {0}")]
+    Outpoint(OutPoint),
+}
+
+impl FromStr for HoldTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ContractId::from_str(s)
+            .map(HoldTarget::Contract)
+            .or_else(|_| OutPoint::from_str(s).map(HoldTarget::Outpoint))
+            .map_err(|_| {
+                format!("`{}` is neither a contract id nor an outpoint", s)
+            })
+    }
+}
+
+/// Tag prefixing hold parameters in the message signed by the admin
+pub const HOLD_TAG: &[u8] = b"RGB:set-hold:";
+
+#[derive(
+    Clone, Copy, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("set_hold({target}, held: {held}, by: {admin})")]
+pub struct SetHoldReq {
+    pub target: HoldTarget,
+
+    /// Whether the hold is set or cleared
+    pub held: bool,
+
+    /// Unix timestamp of the request signing; the daemon rejects requests
+    /// signed too long ago, so the request can't be replayed later
+    pub timestamp: i64,
+
+    /// Public key of the admin
+    pub admin: PublicKey,
+
+    /// Admin signature over [`SetHoldReq::message`]
+    pub signature: secp256k1::Signature,
+}
+
+impl SetHoldReq {
+    /// Message signed by the admin: SHA256 hash of [`HOLD_TAG`] followed by
+    /// the strict-encoded hold target, hold flag and timestamp
+    pub fn message(target: HoldTarget, held: bool, timestamp: i64) -> Message {
+        let mut engine = sha256::Hash::engine();
+        engine.input(HOLD_TAG);
+        target
+            .strict_encode(&mut engine)
+            .expect("hash engines do not fail");
+        engine.input(&[held as u8]);
+        engine.input(&timestamp.to_le_bytes());
+        Message::from_slice(&sha256::Hash::from_engine(engine)[..])
+            .expect("SHA256 hash is always a valid message")
+    }
+
+    /// Constructs request signed with the admin key
+    pub fn sign(
+        target: HoldTarget,
+        held: bool,
+        timestamp: i64,
+        secret_key: &SecretKey,
+    ) -> Self {
+        let secp = Secp256k1::signing_only();
+        SetHoldReq {
+            target,
+            held,
+            timestamp,
+            admin: PublicKey::from_secret_key(&secp, secret_key),
+            signature: secp
+                .sign(&Self::message(target, held, timestamp), secret_key),
+        }
+    }
+
+    /// Checks admin signature
+    pub fn verify(&self) -> bool {
+        Secp256k1::verification_only()
+            .verify(
+                &Self::message(self.target, self.held, self.timestamp),
+                &self.signature,
+                &self.admin,
+            )
+            .is_ok()
+    }
+}
+
+#[derive(
+    Clone, Copy, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("audit_log({from}..{to})")]
+pub struct AuditLogReq {
+    /// Unix timestamp of the earliest exported record
+    pub from: i64,
+
+    /// Unix timestamp following the latest exported record
+    pub to: i64,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lnpbp::client_side_validation::CommitConceal;
+
+    fn issue_req(
+        precision: u8,
+        allocation: &[AtomicValue],
+        inflation: &[AtomicValue],
+    ) -> IssueReq {
+        let coins = |values: &[AtomicValue]| {
+            values
+                .iter()
+                .map(|coins| OutpointCoins {
+                    coins: *coins,
+                    outpoint: OutPoint::default(),
+                })
+                .collect::<Vec<_>>()
+        };
+        IssueReq {
+            ticker: s!("TEST"),
+            name: s!("Test asset"),
+            description: None,
+            precision,
+            allocation: coins(allocation)
+                .into_iter()
+                .map(IssueAllocation::from)
+                .collect(),
+            inflation: coins(inflation),
+            renomination: None,
+            epoch: None,
+            no_inflation: false,
+            no_renomination: false,
+            no_burn: false,
+        }
+    }
+
+    #[test]
+    fn test_issue_precision() {
+        assert_eq!(issue_req(0, &[1], &[]).validate(None), Ok(1));
+        assert_eq!(
+            issue_req(RGB20_MAX_PRECISION, &[1], &[]).validate(None),
+            Ok(1)
+        );
+        assert_eq!(
+            issue_req(RGB20_MAX_PRECISION + 1, &[1], &[]).validate(None),
+            Err(IssueError::PrecisionTooLarge(RGB20_MAX_PRECISION + 1))
+        );
+        assert_eq!(
+            issue_req(u8::MAX, &[1], &[]).validate(None),
+            Err(IssueError::PrecisionTooLarge(u8::MAX))
+        );
+    }
+
+    #[test]
+    fn test_issue_boundary_supply() {
+        assert_eq!(issue_req(8, &[], &[]).validate(None), Ok(0));
+        assert_eq!(issue_req(8, &[u64::MAX], &[]).validate(None), Ok(u64::MAX));
+        assert_eq!(
+            issue_req(8, &[u64::MAX - 1, 1], &[]).validate(None),
+            Ok(u64::MAX)
+        );
+        assert_eq!(
+            issue_req(8, &[u64::MAX, 1], &[]).validate(None),
+            Err(IssueError::IssueOverflow)
+        );
+        assert_eq!(
+            issue_req(8, &[u64::MAX - 1], &[1]).validate(None),
+            Ok(u64::MAX - 1)
+        );
+        assert_eq!(
+            issue_req(8, &[u64::MAX - 1], &[1, 1]).validate(None),
+            Err(IssueError::SupplyOverflow)
+        );
+        assert_eq!(
+            issue_req(8, &[1], &[u64::MAX]).validate(None),
+            Err(IssueError::SupplyOverflow)
+        );
+    }
+
+    #[test]
+    fn test_issue_supply_cap() {
+        assert_eq!(
+            issue_req(2, &[500, 500], &[]).validate(Some(1000)),
+            Ok(1000)
+        );
+        assert_eq!(
+            issue_req(2, &[500, 500], &[1]).validate(Some(1000)),
+            Err(IssueError::SupplyCapExceeded {
+                supply: 1001,
+                cap: 1000
+            })
+        );
+        assert_eq!(
+            issue_req(2, &[1001], &[]).validate(Some(1000)),
+            Err(IssueError::SupplyCapExceeded {
+                supply: 1001,
+                cap: 1000
+            })
+        );
+        assert_eq!(issue_req(2, &[0], &[]).validate(Some(0)), Ok(0));
+
+        let mut issue = issue_req(2, &[500, 500], &[1]);
+        issue.no_inflation = true;
+        assert_eq!(issue.restrict().validate(Some(1000)), Ok(1000));
+    }
+
+    fn transfer_req(
+        payment: &[(u32, AtomicValue)],
+        change: &[(u32, AtomicValue)],
+    ) -> TransferReq {
+        let tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        };
+        TransferReq {
+            contract_id: ContractId::default(),
+            witness: PartiallySignedTransaction::from_unsigned_tx(tx).unwrap(),
+            inputs: bset![],
+            payment: payment
+                .iter()
+                .map(|(vout, amount)| {
+                    (
+                        SealEndpoint::WitnessVout {
+                            vout: *vout,
+                            blinding: 0,
+                        },
+                        *amount,
+                    )
+                })
+                .collect(),
+            theirs_witness: bmap! {},
+            change: change
+                .iter()
+                .map(|(vout, amount)| {
+                    (
+                        SealDefinition::WitnessVout {
+                            vout: *vout,
+                            blinding: 0,
+                        },
+                        *amount,
+                    )
+                })
+                .collect(),
+            confirmed_only: false,
+            container: None,
+        }
+    }
+
+    #[test]
+    fn test_dust_payment() {
+        let mut transfer = transfer_req(&[(0, 546), (1, 545)], &[(2, 1000)]);
+        assert_eq!(
+            transfer.apply_dust_limit(546),
+            Err(TransferError::DustOutput {
+                endpoint: SealEndpoint::WitnessVout {
+                    vout: 1,
+                    blinding: 0
+                },
+                amount: 545,
+                dust_limit: 546,
+            })
+        );
+
+        let mut transfer = transfer_req(&[(0, 546)], &[(1, 546)]);
+        let expected = transfer.clone();
+        assert_eq!(transfer.apply_dust_limit(546), Ok(()));
+        assert_eq!(transfer, expected);
+    }
+
+    #[test]
+    fn test_outgoing_amount() {
+        let transfer = transfer_req(&[(0, 1000)], &[(1, 300), (2, 200)]);
+        assert_eq!(transfer.outgoing_amount(&bset![]), 1000);
+        let foreign = bset![SealDefinition::WitnessVout {
+            vout: 2,
+            blinding: 0
+        }];
+        assert_eq!(transfer.outgoing_amount(&foreign), 1200);
+
+        let transfer = transfer_req(&[], &[(1, 5000)]);
+        assert_eq!(transfer.payment_amount(), 0);
+        let foreign = bset![SealDefinition::WitnessVout {
+            vout: 1,
+            blinding: 0
+        }];
+        assert_eq!(transfer.outgoing_amount(&foreign), 5000);
+    }
+
+    #[test]
+    fn test_dust_change_merge() {
+        let mut transfer =
+            transfer_req(&[(0, 1000)], &[(1, 10), (2, 2000), (3, 20)]);
+        assert_eq!(transfer.apply_dust_limit(546), Ok(()));
+        assert_eq!(transfer, transfer_req(&[(0, 1000)], &[(2, 2030)]));
+
+        let mut transfer = transfer_req(&[(0, 1000)], &[(1, 300), (2, 300)]);
+        assert_eq!(transfer.apply_dust_limit(546), Ok(()));
+        assert_eq!(transfer.change.values().sum::<AtomicValue>(), 600);
+        assert_eq!(transfer.change.len(), 1);
+
+        let mut transfer = transfer_req(&[(0, 1000)], &[(1, 100), (2, 200)]);
+        assert_eq!(
+            transfer.apply_dust_limit(546),
+            Err(TransferError::DustOutput {
+                endpoint: SealEndpoint::WitnessVout {
+                    vout: 2,
+                    blinding: 0
+                },
+                amount: 300,
+                dust_limit: 546,
+            })
+        );
+    }
+
+    #[test]
+    fn test_issue_allocation_parse() {
+        let outpoint = OutPoint::default();
+        let hash = OutpointReveal::from(outpoint).outpoint_hash();
+        for seal in &[
+            IssueSeal::Revealed(outpoint),
+            IssueSeal::Concealed(hash),
+            IssueSeal::WitnessVout(1),
+        ] {
+            let allocation = IssueAllocation {
+                coins: 100,
+                seal: *seal,
+            };
+            assert_eq!(allocation.to_string().parse(), Ok(allocation));
+        }
+        assert_eq!("100".parse::<IssueAllocation>(), Err(AllocationParseError));
+        assert_eq!(
+            "100@1@2".parse::<IssueAllocation>(),
+            Err(AllocationParseError)
+        );
+        assert_eq!(
+            "100@utxob1invalid".parse::<IssueAllocation>(),
+            Err(AllocationParseError)
+        );
+        assert_eq!(
+            "100@1".parse::<VoutCoins>(),
+            Ok(VoutCoins {
+                coins: 100,
+                vout: 1
+            })
+        );
+        assert_eq!(
+            format!("100@{}", outpoint).parse::<VoutCoins>(),
+            Err(AllocationParseError)
+        );
+    }
+
+    #[test]
+    fn test_batch_encoding() {
+        use internet2::{CreateUnmarshaller, TypedEnum, Unmarshall};
+
+        let batch = Request::Batch(Batch(vec![
+            Request::Ping,
+            Request::Batch(Batch(vec![Request::Status])),
+            Request::Idempotent(IdempotentReq {
+                key: s!("payout-1"),
+                request: Box::new(Request::ForgetAll(true)),
+            }),
+            Request::Async(AsyncReq {
+                request: Box::new(Request::Sync(DataFormat::Json)),
+            }),
+        ]));
+        let message = match Request::create_unmarshaller()
+            .unmarshall(&batch.serialize())
+        {
+            Ok(message) => message,
+            Err(_) => panic!("batch request can't be decoded"),
+        };
+        assert_eq!(message.serialize(), batch.serialize());
+        match &*message {
+            Request::Batch(Batch(requests)) => assert_eq!(
+                requests.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                vec![
+                    "ping()",
+                    "batch(...)",
+                    "forget_all(dry_run: true) with key payout-1",
+                    "sync(using: json) in background"
+                ]
+            ),
+            _ => panic!("batch request is decoded as {}", message),
+        }
+    }
+
+    #[test]
+    fn test_transfer_approval() {
+        let txid = Txid::from_inner([7u8; 32]);
+        let secret_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let approval = ApproveTransferReq::sign(txid, &secret_key);
+        assert!(approval.verify());
+        assert!(!ApproveTransferReq {
+            txid: Txid::from_inner([8u8; 32]),
+            ..approval
+        }
+        .verify());
+    }
+
+    #[test]
+    fn test_hold_request() {
+        let outpoint = OutPoint::new(Txid::from_inner([7u8; 32]), 1);
+        let target = HoldTarget::from_str(&outpoint.to_string()).unwrap();
+        assert_eq!(target, HoldTarget::Outpoint(outpoint));
+        let secret_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let hold = SetHoldReq::sign(target, true, 1620000000, &secret_key);
+        assert!(hold.verify());
+        assert!(!SetHoldReq {
+            held: false,
+            ..hold
+        }
+        .verify());
+    }
+
+    #[test]
+    fn test_account_request() {
+        use internet2::{CreateUnmarshaller, TypedEnum, Unmarshall};
+
+        let secret_key = SecretKey::from_slice(&[2u8; 32]).unwrap();
+        let request = Request::Account(AccountReq::sign(
+            Request::Sync(DataFormat::Json),
+            1620000000,
+            &secret_key,
+        ));
+        let message = match Request::create_unmarshaller()
+            .unmarshall(&request.serialize())
+        {
+            Ok(message) => message,
+            Err(_) => panic!("account request can't be decoded"),
+        };
+        match &*message {
+            Request::Account(req) => {
+                assert!(req.verify());
+                assert!(!AccountReq {
+                    request: Box::new(Request::Sync(DataFormat::Yaml)),
+                    ..req.clone()
+                }
+                .verify());
+            }
+            _ => panic!("account request is decoded as {}", message),
+        }
+    }
+
+    #[test]
+    fn test_invoice() {
+        let invoice = Invoice {
+            contract_id: ContractId::default(),
+            amount: 100,
+            endpoint: OutpointReveal::from(OutPoint::default())
+                .commit_conceal(),
+            expiry: None,
+        };
+        assert_eq!(Invoice::from_str(&invoice.to_string()), Ok(invoice));
+        let invoice = Invoice {
+            expiry: Some(1_600_000_000),
+            ..invoice
+        };
+        assert_eq!(Invoice::from_str(&invoice.to_string()), Ok(invoice));
+        assert_eq!(
+            Invoice::from_str("rgb20:?amount=1"),
+            Err(InvoiceParseError)
+        );
+    }
+}
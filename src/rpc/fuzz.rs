@@ -0,0 +1,113 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Entry points for fuzzing the parsing of the untrusted RPC data, to be
+//! called from the fuzzer harness (like `cargo fuzz` targets). Each of them
+//! runs the same checks the daemons perform before processing the message;
+//! they must not panic or allocate unbounded memory on any input.
+
+use internet2::{CreateUnmarshaller, TypedEnum, Unmarshall};
+use lnpbp::strict_encoding::StrictDecode;
+use rgb::Consignment;
+
+use super::limits::{self, Limited};
+use super::{fungible, stash};
+use crate::error::ServiceErrorDomain;
+use crate::util::compression::decompress;
+
+fn parse<T>(data: &[u8]) -> Result<T, ServiceErrorDomain>
+where
+    T: TypedEnum + CreateUnmarshaller + Clone + Limited,
+{
+    limits::check_size(data)?;
+    let raw = decompress(data.to_vec())?;
+    let message = super::take(T::create_unmarshaller().unmarshall(&raw)?);
+    message.check_limits()?;
+    Ok(message)
+}
+
+/// Fuzz target for the requests received by the fungible contract daemon
+pub fn fungible_request(data: &[u8]) {
+    let _ = parse::<fungible::Request>(data);
+}
+
+/// Fuzz target for the requests received by the stash daemon
+pub fn stash_request(data: &[u8]) {
+    let _ = parse::<stash::Request>(data);
+}
+
+/// Fuzz target for the consignments read from files or received from the
+/// counterparties
+pub fn consignment(data: &[u8]) {
+    if limits::check_size(data).is_ok() {
+        if let Ok(consignment) = Consignment::strict_decode(data) {
+            let _ = consignment.check_limits();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constants::{RPC_MAX_MESSAGE_SIZE, RPC_MAX_NESTING_DEPTH};
+    use crate::rpc::fungible::{AsyncReq, Request};
+    use crate::rpc::limits::LimitError;
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth: usize| {
+            (0..depth).fold(Request::Ping, |request, _| {
+                Request::Async(AsyncReq {
+                    request: Box::new(request),
+                })
+            })
+        };
+        let data = nested(RPC_MAX_NESTING_DEPTH).serialize();
+        assert!(parse::<Request>(&data).is_ok());
+        let data = nested(RPC_MAX_NESTING_DEPTH + 1).serialize();
+        assert!(parse::<Request>(&data).is_err());
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let data = vec![0u8; RPC_MAX_MESSAGE_SIZE + 1];
+        // NB: Other errors are not printed, since their debug representation
+        // requires ZMQ library to be linked
+        match parse::<Request>(&data) {
+            Err(ServiceErrorDomain::Limit(LimitError::MessageTooLarge {
+                size,
+                ..
+            })) => assert_eq!(size, RPC_MAX_MESSAGE_SIZE + 1),
+            _ => panic!("oversized message is not rejected"),
+        }
+    }
+
+    #[test]
+    fn test_fuzz_targets() {
+        // Deterministic pseudo-random input, so the failures are reproducible
+        let mut state = 0x2545F4914F6CDD1Du64;
+        for len in 0..512 {
+            let data = (0..len)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect::<Vec<_>>();
+            fungible_request(&data);
+            stash_request(&data);
+            consignment(&data);
+        }
+    }
+}
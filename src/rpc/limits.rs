@@ -0,0 +1,152 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Limits on the size and structure of the RPC messages received from the
+//! untrusted parties. Message size is checked before the message is
+//! decompressed and unmarshalled, nesting depth of the embedded messages is
+//! checked during unmarshalling, and the number of items in the decoded
+//! data, which affects the cost of their processing, right after it.
+
+use rgb::Consignment;
+
+use super::{fungible, stash};
+use crate::constants::{
+    RPC_MAX_BATCH_SIZE, RPC_MAX_CONSIGNMENT_ENDPOINTS,
+    RPC_MAX_CONSIGNMENT_NODES, RPC_MAX_MESSAGE_SIZE,
+};
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum LimitError {
+    /// message of {size} bytes exceeds maximum allowed size of {limit} bytes
+    MessageTooLarge { size: usize, limit: usize },
+
+    /// {what} has {count} items, which exceeds the limit of {limit}
+    TooManyItems {
+        what: &'static str,
+        count: usize,
+        limit: usize,
+    },
+}
+
+/// Checks size of the raw message data
+pub fn check_size(data: &[u8]) -> Result<(), LimitError> {
+    if data.len() > RPC_MAX_MESSAGE_SIZE {
+        Err(LimitError::MessageTooLarge {
+            size: data.len(),
+            limit: RPC_MAX_MESSAGE_SIZE,
+        })?
+    }
+    Ok(())
+}
+
+fn check_count(
+    what: &'static str,
+    count: usize,
+    limit: usize,
+) -> Result<(), LimitError> {
+    if count > limit {
+        Err(LimitError::TooManyItems { what, count, limit })?
+    }
+    Ok(())
+}
+
+/// Data decoded from the RPC message, whose structure has to be checked
+/// before processing
+pub trait Limited {
+    fn check_limits(&self) -> Result<(), LimitError>;
+}
+
+impl Limited for Consignment {
+    fn check_limits(&self) -> Result<(), LimitError> {
+        check_count(
+            "consignment",
+            self.state_transitions.len() + self.state_extensions.len(),
+            RPC_MAX_CONSIGNMENT_NODES,
+        )?;
+        check_count(
+            "consignment endpoints",
+            self.endpoints.len(),
+            RPC_MAX_CONSIGNMENT_ENDPOINTS,
+        )
+    }
+}
+
+impl Limited for fungible::SwapLeg {
+    fn check_limits(&self) -> Result<(), LimitError> {
+        match self.asset {
+            Some(ref asset) => asset.history.check_limits(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Limited for fungible::Request {
+    fn check_limits(&self) -> Result<(), LimitError> {
+        use fungible::Request;
+        match self {
+            Request::Validate(fungible::ValidateReq {
+                consignment, ..
+            })
+            | Request::Accept(fungible::AcceptReq { consignment, .. })
+            | Request::AutoAccept(consignment)
+            | Request::ImportFromConsignment(consignment) => {
+                consignment.check_limits()
+            }
+            Request::SwapComplete(fungible::SwapCompleteReq {
+                offer, ..
+            }) => offer.check_limits(),
+            Request::SwapVerify(fungible::SwapVerifyReq {
+                offer,
+                swap,
+                ..
+            }) => {
+                offer.check_limits()?;
+                swap.outgoing
+                    .iter()
+                    .chain(swap.incoming.iter())
+                    .try_for_each(Consignment::check_limits)
+            }
+            Request::Batch(batch) => {
+                check_count("batch", batch.0.len(), RPC_MAX_BATCH_SIZE)?;
+                batch.0.iter().try_for_each(Request::check_limits)
+            }
+            Request::Idempotent(fungible::IdempotentReq {
+                request, ..
+            })
+            | Request::Async(fungible::AsyncReq { request, .. })
+            | Request::Account(fungible::AccountReq { request, .. }) => {
+                request.check_limits()
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Limited for stash::Request {
+    fn check_limits(&self) -> Result<(), LimitError> {
+        use stash::Request;
+        match self {
+            Request::Validate(consignment)
+            | Request::ExplainFailure(consignment) => {
+                consignment.check_limits()
+            }
+            Request::Accept(stash::AcceptRequest { consignment, .. })
+            | Request::InspectConsignment(stash::AcceptRequest {
+                consignment,
+                ..
+            }) => consignment.check_limits(),
+            _ => Ok(()),
+        }
+    }
+}
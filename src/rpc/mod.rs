@@ -11,8 +11,19 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+pub(crate) mod batch;
 pub mod fungible;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzz;
+pub mod limits;
+#[cfg(feature = "prost")]
+pub mod proto;
+pub mod relay;
 pub mod reply;
+mod shared;
 pub mod stash;
+pub mod supervisor;
 
+pub use batch::Batch;
 pub use reply::Reply;
+pub use shared::{take, Shared};
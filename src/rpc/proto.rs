@@ -0,0 +1,175 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Protobuf messages for RGB20 asset data returned by `Reply::Sync`.
+//!
+//! Definitions here must match `proto/rgb20.proto`, which is the source for
+//! non-Rust backends generating their own decoders.
+
+use std::iter::FromIterator;
+
+use bitcoin::hashes::hex::ToHex;
+use rgb::ToBech32;
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AssetList {
+    #[prost(message, repeated, tag = "1")]
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Asset {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub ticker: String,
+    #[prost(string, tag = "3")]
+    pub name: String,
+    #[prost(string, optional, tag = "4")]
+    pub description: Option<String>,
+    #[prost(message, optional, tag = "5")]
+    pub supply: Option<Supply>,
+    #[prost(string, tag = "6")]
+    pub chain: String,
+    #[prost(uint32, tag = "7")]
+    pub decimal_precision: u32,
+    #[prost(int64, tag = "8")]
+    pub date: i64,
+    #[prost(string, tag = "9")]
+    pub genesis: String,
+    #[prost(message, repeated, tag = "10")]
+    pub known_issues: Vec<Issue>,
+    #[prost(message, repeated, tag = "11")]
+    pub known_inflation: Vec<Inflation>,
+    #[prost(message, repeated, tag = "12")]
+    pub known_allocations: Vec<Allocation>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Supply {
+    #[prost(uint64, tag = "1")]
+    pub known_circulating: u64,
+    #[prost(bool, optional, tag = "2")]
+    pub is_issued_known: Option<bool>,
+    #[prost(uint64, tag = "3")]
+    pub issue_limit: u64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Issue {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(uint64, tag = "2")]
+    pub amount: u64,
+    #[prost(string, optional, tag = "3")]
+    pub origin: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Inflation {
+    #[prost(string, tag = "1")]
+    pub outpoint: String,
+    #[prost(uint64, tag = "2")]
+    pub amount: u64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Allocation {
+    #[prost(string, tag = "1")]
+    pub node_id: String,
+    #[prost(uint32, tag = "2")]
+    pub index: u32,
+    #[prost(string, tag = "3")]
+    pub outpoint: String,
+    #[prost(uint64, tag = "4")]
+    pub amount: u64,
+    #[prost(bytes, tag = "5")]
+    pub blinding: Vec<u8>,
+}
+
+impl From<&rgb20::Asset> for Asset {
+    fn from(asset: &rgb20::Asset) -> Self {
+        Asset {
+            id: asset.id().to_bech32_string(),
+            ticker: asset.ticker().clone(),
+            name: asset.name().clone(),
+            description: asset.description().clone(),
+            supply: Some(Supply::from(asset.supply())),
+            chain: asset.chain().to_string(),
+            decimal_precision: *asset.decimal_precision() as u32,
+            date: asset.date().timestamp(),
+            genesis: asset.genesis().clone(),
+            known_issues: asset
+                .known_issues()
+                .iter()
+                .map(Issue::from)
+                .collect(),
+            known_inflation: asset
+                .known_inflation()
+                .iter()
+                .map(|(outpoint, amount)| Inflation {
+                    outpoint: outpoint.to_string(),
+                    amount: *amount,
+                })
+                .collect(),
+            known_allocations: asset
+                .known_allocations()
+                .iter()
+                .map(Allocation::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<&rgb20::Supply> for Supply {
+    fn from(supply: &rgb20::Supply) -> Self {
+        Supply {
+            known_circulating: *supply.known_circulating(),
+            is_issued_known: *supply.is_issued_known(),
+            issue_limit: *supply.issue_limit(),
+        }
+    }
+}
+
+impl From<&rgb20::Issue> for Issue {
+    fn from(issue: &rgb20::Issue) -> Self {
+        Issue {
+            id: issue.id().to_hex(),
+            amount: *issue.amount(),
+            origin: issue.origin().map(|outpoint| outpoint.to_string()),
+        }
+    }
+}
+
+impl From<&rgb20::Allocation> for Allocation {
+    fn from(allocation: &rgb20::Allocation) -> Self {
+        Allocation {
+            node_id: allocation.node_id().to_hex(),
+            index: *allocation.index() as u32,
+            outpoint: allocation.outpoint().to_string(),
+            amount: allocation.revealed_amount().value,
+            blinding: AsRef::<[u8]>::as_ref(
+                &allocation.revealed_amount().blinding,
+            )
+            .to_vec(),
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a rgb20::Asset> for AssetList {
+    fn from_iter<I: IntoIterator<Item = &'a rgb20::Asset>>(iter: I) -> Self {
+        AssetList {
+            assets: iter.into_iter().map(Asset::from).collect(),
+        }
+    }
+}
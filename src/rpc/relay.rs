@@ -0,0 +1,150 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Messages relayed between the stash daemons of different nodes. Each
+//! message is published under id of the contract it belongs to, so the peers
+//! receive only the data of the contracts they are subscribed to. Messages
+//! are authenticated with the secret shared by the relay peers.
+
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+
+use bitcoin::hashes::hex::{self, FromHex, ToHex};
+use bitcoin::hashes::{hmac, sha256, Hash, HashEngine};
+use internet2::TypedEnum;
+use lnpbp::strict_encoding::{StrictDecode, StrictEncode};
+use rgb::{Consignment, ContractId, Disclosure, Genesis, Schema};
+
+use super::Shared;
+use crate::util::compression::{compress, RPC_COMPRESSION_THRESHOLD};
+
+/// Minimal length (in bytes) of the relay key
+pub const RELAY_KEY_MIN_LEN: usize = 16;
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum RelayError {
+    /// relay key must be a hex string encoding at least 16 bytes
+    #[from(hex::Error)]
+    InvalidKey,
+
+    /// relay frame is truncated
+    Truncated,
+
+    /// relay frame has invalid authentication code
+    Unauthenticated,
+}
+
+/// Secret shared by the relay peers, authenticating relayed messages
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RelayKey(Vec<u8>);
+
+impl Display for RelayKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.to_hex())
+    }
+}
+
+impl FromStr for RelayKey {
+    type Err = RelayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = Vec::<u8>::from_hex(s)?;
+        if key.len() < RELAY_KEY_MIN_LEN {
+            Err(RelayError::InvalidKey)?
+        }
+        Ok(RelayKey(key))
+    }
+}
+
+impl RelayKey {
+    fn mac(&self, data: &[u8]) -> hmac::Hmac<sha256::Hash> {
+        let mut engine = hmac::HmacEngine::<sha256::Hash>::new(&self.0);
+        engine.input(data);
+        hmac::Hmac::from_engine(engine)
+    }
+}
+
+#[derive(Clone, Debug, Display, Api)]
+#[api(encoding = "strict")]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display(inner)]
+#[non_exhaustive]
+pub enum Message {
+    /// Schema of the contract, published together with its genesis
+    #[api(type = 0x0901)]
+    #[display("schema({0})")]
+    Schema(Schema),
+
+    #[api(type = 0x0903)]
+    #[display("genesis({0})")]
+    Genesis(Genesis),
+
+    /// Anchored state transitions and extensions of the contract
+    #[api(type = 0x0905)]
+    #[display("disclosure(...)")]
+    Disclosure(Disclosure),
+
+    /// Consignment accepted by the publishing node; it is validated by the
+    /// peers before being accepted
+    #[api(type = 0x0907)]
+    #[display("consignment(...)")]
+    Consignment(Shared<Consignment>),
+}
+
+/// Serializes relayed message prefixed with the contract id, which is used
+/// as ZMQ subscription topic, and followed by HMAC-SHA256 of both keyed with
+/// the relay key
+pub fn frame(
+    key: &RelayKey,
+    contract_id: ContractId,
+    message: &Message,
+) -> Vec<u8> {
+    let mut frame = topic(contract_id);
+    frame.extend(compress(message.serialize(), RPC_COMPRESSION_THRESHOLD));
+    let mac = key.mac(&frame);
+    frame.extend(&mac[..]);
+    frame
+}
+
+/// Checks authentication code of the relay frame and splits it into the
+/// contract id and message data
+pub fn unframe<'a>(
+    key: &RelayKey,
+    frame: &'a [u8],
+) -> Result<(ContractId, &'a [u8]), RelayError> {
+    let mac_len = sha256::Hash::LEN;
+    if frame.len() < 32 + mac_len {
+        Err(RelayError::Truncated)?
+    }
+    let (data, mac) = frame.split_at(frame.len() - mac_len);
+    // Constant-time comparison, not leaking the matching prefix length
+    let diff = key.mac(data)[..]
+        .iter()
+        .zip(mac)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        Err(RelayError::Unauthenticated)?
+    }
+    let (topic, data) = data.split_at(32);
+    let contract_id =
+        ContractId::strict_decode(topic).map_err(|_| RelayError::Truncated)?;
+    Ok((contract_id, data))
+}
+
+/// ZMQ subscription topic for the updates of the contract
+pub fn topic(contract_id: ContractId) -> Vec<u8> {
+    contract_id
+        .strict_serialize()
+        .expect("Memory encoders do not fail")
+}
@@ -11,18 +11,33 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use std::collections::BTreeMap;
+use core::fmt::{self, Display, Formatter};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::io;
 
+use bitcoin::hashes::{sha256, Hash};
 use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
-use bitcoin::OutPoint;
-use microservices::FileFormat;
-use rgb::{AtomicValue, Consignment, ContractId, Disclosure};
+use bitcoin::{OutPoint, Txid};
+use internet2::{CreateUnmarshaller, TypedEnum};
+use lnpbp::seals::OutpointReveal;
+use lnpbp::strict_encoding::{self, StrictDecode, StrictEncode};
+use rgb::{
+    AtomicValue, Consignment, ContractId, Disclosure, Genesis, Node, NodeId,
+    SchemaId, SealEndpoint,
+};
+use rgb20::schema::OwnedRightsType;
 use rgb20::Asset;
 
 #[cfg(feature = "node")]
 use crate::error::RuntimeError;
 #[cfg(any(feature = "node", feature = "client"))]
 use crate::error::ServiceError;
+use crate::error::{ErrorCategory, ErrorCode, UnknownErrorCode};
+use crate::rpc::fungible::{Invoice, IssueSeal, LedgerFormat};
+use crate::util::DataFormat;
 
 #[derive(Clone, Debug, Display, Api)]
 #[api(encoding = "strict")]
@@ -42,8 +57,14 @@ pub enum Reply {
     #[display("noop()")]
     Nothing,
 
+    /// Consignment with the given id was already accepted before, so its
+    /// data were not merged for the second time
+    #[api(type = 0x0007)]
+    #[display("already_accepted({0})")]
+    AlreadyAccepted(::bitcoin::hashes::sha256::Hash),
+
     #[api(type = 0xFF00)]
-    Sync(crate::rpc::reply::SyncFormat),
+    Sync(crate::rpc::reply::AssetSync),
 
     #[api(type = 0xFF01)]
     #[display("asset({0})")]
@@ -51,11 +72,11 @@ pub enum Reply {
 
     #[api(type = 0xFF02)]
     #[display("outpoint_assets(...)")]
-    OutpointAssets(BTreeMap<ContractId, Vec<AtomicValue>>),
+    OutpointAssets(crate::rpc::reply::OutpointAssets),
 
     #[api(type = 0xFF03)]
     #[display("asset_allocations(...)")]
-    AssetAllocations(BTreeMap<OutPoint, Vec<AtomicValue>>),
+    AssetAllocations(BTreeMap<OutPoint, AllocatedAmounts>),
 
     #[api(type = 0xFF04)]
     #[display("schema_ids(...)")]
@@ -80,6 +101,180 @@ pub enum Reply {
     #[api(type = 0xFF0C)]
     Transfer(crate::rpc::reply::Transfer),
 
+    #[api(type = 0xFF0D)]
+    #[display("contract_state(...)")]
+    ContractState(crate::rpc::reply::SyncFormat),
+
+    #[api(type = 0xFF0E)]
+    #[display("anchors(...)")]
+    Anchors(Vec<::rgb::Anchor>),
+
+    #[api(type = 0xFF0F)]
+    #[display("gc_stats({0})")]
+    GcStats(crate::rpc::reply::GcStats),
+
+    #[api(type = 0xFF11)]
+    #[display("forgotten({0})")]
+    Forgotten(crate::rpc::reply::ForgetReport),
+
+    #[api(type = 0xFF10)]
+    #[display("consignment_summary(...)")]
+    ConsignmentSummary(crate::rpc::reply::ConsignmentSummary),
+
+    #[api(type = 0xFF13)]
+    IssuePreview(crate::rpc::reply::IssuePreview),
+
+    /// Consignment is valid, but it will be accepted only after its witness
+    /// transactions get the required number of confirmations
+    #[api(type = 0xFF12)]
+    Pending(crate::rpc::reply::PendingConsignment),
+
+    #[api(type = 0xFF14)]
+    #[display("health({0})")]
+    Health(crate::rpc::reply::HealthStatus),
+
+    /// Blinded outpoint data; the blinding factor is not displayed since it
+    /// must be kept secret by the receiver
+    #[api(type = 0xFF15)]
+    #[display("outpoint_reveal(...)")]
+    OutpointReveal(::lnpbp::seals::OutpointReveal),
+
+    /// Replies to the batched requests, in the same order as the requests
+    #[api(type = 0xFF16)]
+    #[display("batch(...)")]
+    Batch(crate::rpc::Batch<Reply>),
+
+    /// Request is rejected since the client has exceeded its request quota
+    #[api(type = 0xFF17)]
+    #[display("rate_limited(retry_after: {0}s)")]
+    RateLimited(u32),
+
+    /// Request was queued for background execution as a job with the given
+    /// id
+    #[api(type = 0xFF18)]
+    #[display("job_queued({0})")]
+    JobQueued(u64),
+
+    #[api(type = 0xFF19)]
+    Job(crate::rpc::reply::JobStatus),
+
+    #[api(type = 0xFF1A)]
+    EventReplay(crate::rpc::reply::EventReplay),
+
+    #[api(type = 0xFF1B)]
+    #[display("consignment(...)")]
+    Consignment(::rgb::Consignment),
+
+    #[api(type = 0xFF1C)]
+    #[display("swap_leg(...)")]
+    SwapLeg(crate::rpc::fungible::SwapLeg),
+
+    #[api(type = 0xFF1D)]
+    Swap(crate::rpc::reply::Swap),
+
+    /// Transfer exceeds the approval threshold and its consignment is held
+    /// until `Request::ApproveTransfer` for the witness transaction id is
+    /// received
+    #[api(type = 0xFF1E)]
+    #[display("approval_required({0})")]
+    ApprovalRequired(bitcoin::Txid),
+
+    #[api(type = 0xFF1F)]
+    Ledger(crate::rpc::reply::Ledger),
+
+    #[api(type = 0xFF20)]
+    #[display("audit_log(...)")]
+    AuditLog(Vec<crate::rpc::reply::AuditRecord>),
+
+    #[api(type = 0xFF21)]
+    #[display("bulk_import({0})")]
+    BulkImport(crate::rpc::reply::BulkImportReport),
+
+    /// Status of the daemons managed by `rgbd` supervisor
+    #[api(type = 0xFF22)]
+    #[display("daemons(...)")]
+    Daemons(Vec<crate::rpc::reply::DaemonStatus>),
+
+    /// Anchored transitions and extensions of a contract known to the stash
+    #[api(type = 0xFF23)]
+    #[display("disclosure(...)")]
+    Disclosure(::rgb::Disclosure),
+
+    #[api(type = 0xFF24)]
+    #[display("checkpoint_import({0})")]
+    CheckpointImport(crate::rpc::reply::CheckpointImport),
+
+    /// Client-provided metadata of the cached asset
+    #[api(type = 0xFF25)]
+    #[display("asset_meta(...)")]
+    AssetMeta(crate::rpc::reply::AssetMeta),
+
+    #[api(type = 0xFF26)]
+    #[display("supply_report({0})")]
+    SupplyReport(crate::rpc::reply::SupplyReport),
+
+    /// Unspent epoch and burn-and-replace seals of the contract
+    #[api(type = 0xFF27)]
+    #[display("epoch_seals(...)")]
+    EpochSeals(Vec<crate::rpc::reply::EpochSeal>),
+
+    #[api(type = 0xFF28)]
+    #[display("contract_capabilities({0})")]
+    ContractCapabilities(crate::rpc::reply::ContractCapabilities),
+
+    /// Contract node graph in Graphviz DOT format
+    #[api(type = 0xFF29)]
+    #[display("graph(...)")]
+    Graph(String),
+
+    /// Consignment validation status with step-by-step explanation
+    #[api(type = 0xFF2A)]
+    #[display("validation_report({0})")]
+    ValidationReport(crate::rpc::reply::ValidationReport),
+
+    /// Witness transaction composed by the node from the descriptor outputs
+    #[api(type = 0xFF2B)]
+    #[display("witness_template({0})")]
+    WitnessTemplate(crate::rpc::reply::WitnessTemplate),
+
+    #[api(type = 0xFF2C)]
+    #[display("funding_balance({0})")]
+    FundingBalance(crate::rpc::reply::FundingBalance),
+
+    #[api(type = 0xFF2D)]
+    #[display("funding_address({0})")]
+    FundingAddress(String),
+
+    /// Blinded receiving outpoint with the invoice for the payer
+    #[api(type = 0xFF2E)]
+    #[display("receive({0})")]
+    Receive(crate::rpc::reply::Receive),
+
+    #[api(type = 0xFF2F)]
+    #[display("pending_receives(...)")]
+    PendingReceives(Vec<crate::rpc::reply::PendingReceive>),
+
+    #[api(type = 0xFF30)]
+    #[display("transfers(...)")]
+    Transfers(Vec<crate::rpc::reply::TransferStatus>),
+
+    #[api(type = 0xFF31)]
+    #[display("transfer_status({0})")]
+    TransferStatus(crate::rpc::reply::TransferStatus),
+
+    /// Encrypted claims bundle
+    #[api(type = 0xFF32)]
+    #[display("claims(...)")]
+    Claims(Vec<u8>),
+
+    #[api(type = 0xFF33)]
+    #[display("claims_import({0})")]
+    ClaimsImport(crate::rpc::reply::ClaimsImport),
+
+    #[api(type = 0xFF34)]
+    #[display("backup({0})")]
+    Backup(crate::rpc::reply::Backup),
+
     #[api(type = 0xFF0B)]
     #[display("validation_status({0})")]
     ValidationStatus(::rgb::validation::Status),
@@ -114,68 +309,979 @@ impl From<ServiceError> for Reply {
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Error)]
 #[strict_encoding_crate(lnpbp::strict_encoding)]
 #[display("sync(using: {0}, ...)")]
-pub struct SyncFormat(pub FileFormat, pub Vec<u8>);
+pub struct SyncFormat(pub DataFormat, pub Vec<u8>);
+
+/// Client-provided key-value metadata of a cached asset, which are not part
+/// of the contract data
+pub type AssetMeta = BTreeMap<String, String>;
+
+/// Cached assets serialized in the requested format, together with the
+/// metadata of the assets which have them
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("sync(using: {format}, ...)")]
+pub struct AssetSync {
+    pub format: DataFormat,
+    pub data: Vec<u8>,
+    pub meta: BTreeMap<ContractId, AssetMeta>,
+    pub capabilities: BTreeMap<ContractId, AssetCapabilities>,
+}
+
+/// Capabilities of the asset enabled at the issuance, defined by the owned
+/// rights assigned by the contract genesis
+#[derive(
+    Clone, Copy, PartialEq, Eq, Hash, Debug, Default, StrictEncode, StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct AssetCapabilities {
+    /// Secondary issuance
+    pub inflation: bool,
+    pub renomination: bool,
+    /// Epoch-based burn & replacement procedure
+    pub burn: bool,
+}
+
+impl AssetCapabilities {
+    /// Capabilities defined by the genesis of the cached asset, unless the
+    /// genesis data can't be parsed
+    pub fn with_asset(asset: &Asset) -> Option<Self> {
+        asset
+            .genesis()
+            .parse::<Genesis>()
+            .ok()
+            .as_ref()
+            .map(AssetCapabilities::from)
+    }
+
+    /// Capabilities of each of the assets whose genesis can be parsed
+    pub fn with_assets(
+        assets: &[Asset],
+    ) -> BTreeMap<ContractId, AssetCapabilities> {
+        assets
+            .iter()
+            .filter_map(|asset| {
+                Some((*asset.id(), AssetCapabilities::with_asset(asset)?))
+            })
+            .collect()
+    }
+}
+
+impl From<&Genesis> for AssetCapabilities {
+    fn from(genesis: &Genesis) -> Self {
+        let has =
+            |ty: OwnedRightsType| genesis.owned_rights_by_type(*ty).is_some();
+        AssetCapabilities {
+            inflation: has(OwnedRightsType::Inflation),
+            renomination: has(OwnedRightsType::Renomination),
+            burn: has(OwnedRightsType::Epoch)
+                || has(OwnedRightsType::BurnReplace),
+        }
+    }
+}
+
+impl Display for AssetCapabilities {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let enabled = [
+            (self.inflation, "inflation"),
+            (self.renomination, "renomination"),
+            (self.burn, "burn"),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>();
+        if enabled.is_empty() {
+            f.write_str("none")
+        } else {
+            f.write_str(&enabled.join(", "))
+        }
+    }
+}
+
+/// Operations permitted by the contract schema and genesis
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("{contract_id}: {capabilities}, max supply {max_supply}")]
+pub struct ContractCapabilities {
+    pub contract_id: ContractId,
+    pub schema_id: SchemaId,
+    /// Types of the state transitions closing any of the owned rights
+    /// assigned by the genesis
+    pub transitions: BTreeSet<u16>,
+    pub capabilities: AssetCapabilities,
+    /// Amount issued at genesis
+    pub issued: AtomicValue,
+    /// Amount issued at genesis together with the maximum amount of the
+    /// secondary issuance permitted by the genesis
+    pub max_supply: AtomicValue,
+}
+
+/// Consignment validation status explained step by step
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("{status}")]
+pub struct ValidationReport {
+    pub status: rgb::validation::Status,
+    /// Validated nodes, their anchors and closed seals in the validation
+    /// order, each followed by the failures and warnings it has caused
+    pub steps: Vec<String>,
+}
+
+/// Witness transaction skeleton composed by the node, ready to be used for
+/// the transfer and signed
+#[derive(Clone, PartialEq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("fee {fee}, change at vout {change_vout}")]
+pub struct WitnessTemplate {
+    pub witness: Psbt,
+    /// Bitcoin-only outputs of the descriptors spent to pay the fee
+    pub funding: BTreeSet<OutPoint>,
+    /// Transaction fee in sats
+    pub fee: u64,
+    /// Output receiving the change, which may be used for the witness
+    /// transaction seals
+    pub change_vout: u32,
+}
+
+/// Everything required to receive the asset: blinding of the receiving
+/// outpoint, kept by the node, and the invoice to be sent to the payer
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("{invoice}")]
+pub struct Receive {
+    pub reveal: OutpointReveal,
+    pub invoice: Invoice,
+}
+
+/// Stage of the outgoing transfer; transfers move only forward through the
+/// stages, except that the ones which are not broadcast yet may fail
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Display,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[repr(u8)]
+pub enum TransferState {
+    /// State transition and witness transaction are prepared, but the
+    /// consignment is held by the node until the operator approval
+    #[display("created")]
+    Created = 0,
+
+    /// Consignment is given out to the wallet
+    #[display("consigned")]
+    Consigned = 1,
+
+    /// Witness transaction is signed by the co-signing service or by the
+    /// wallet
+    #[display("signed")]
+    Signed = 2,
+
+    /// Witness transaction is broadcast or is seen in mempool
+    #[display("broadcast")]
+    Broadcast = 3,
+
+    /// Witness transaction is mined
+    #[display("confirmed")]
+    Confirmed = 4,
+
+    /// Consignment was accepted by the payee and relayed back to the node
+    #[display("accepted-by-peer")]
+    AcceptedByPeer = 5,
+
+    /// Transfer is cancelled or has timed out before its witness
+    /// transaction was broadcast
+    #[display("failed")]
+    Failed = 6,
+}
+
+/// Outgoing transfer tracked by the node, identified by its witness
+/// transaction id
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("{txid}: {state}")]
+pub struct TransferStatus {
+    pub txid: Txid,
+    pub contract_id: ContractId,
+    /// Amount paid to the beneficiaries, not including the change
+    pub amount: AtomicValue,
+    pub state: TransferState,
+    pub created: i64,
+    /// Unix timestamp of the last state change
+    pub updated: i64,
+    /// Reason of the transfer failure
+    pub reason: Option<String>,
+}
+
+/// Receive created by the node which is neither fulfilled by an accepted
+/// consignment nor expired yet
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("{invoice}")]
+pub struct PendingReceive {
+    pub invoice: Invoice,
+    /// Outpoint receiving the asset
+    pub outpoint: OutPoint,
+    pub created: i64,
+    pub expires: i64,
+    /// Whether the outpoint is one of the funding outputs reserved for the
+    /// receive by the node
+    pub reserved: bool,
+}
+
+/// Bitcoin funds of the registered descriptors, in sats
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+    Default,
+    Display,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("{confirmed} confirmed, {unconfirmed} unconfirmed, {locked} locked")]
+pub struct FundingBalance {
+    /// Confirmed outputs which can be spent to pay the fees
+    pub confirmed: u64,
+    pub unconfirmed: u64,
+    /// Outputs holding assets or reserved for transfers and external
+    /// protocols
+    pub locked: u64,
+    /// Number of the confirmed spendable outputs
+    pub outputs: u32,
+}
 
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Error)]
 #[strict_encoding_crate(lnpbp::strict_encoding)]
 #[display("transfer(...)")]
 pub struct Transfer {
     pub consignment: Consignment,
+    /// Consignments for the other contracts anchored in the same witness
+    /// transaction, created for the requested endpoints
+    pub other_consignments: BTreeMap<ContractId, Consignment>,
     pub disclosure: Disclosure,
     pub witness: Psbt,
+    /// Results of the size optimization for each of the consignments
+    pub optimization: BTreeMap<ContractId, ConsignmentStats>,
+}
+
+/// Allocation ledger in the format requested by the client
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("ledger({height}, using: {format}, ...)")]
+pub struct Ledger {
+    pub format: LedgerFormat,
+
+    /// Block height of the ledger snapshot
+    pub height: u32,
+
+    pub data: Vec<u8>,
+}
+
+/// Outcome of the request recorded in the audit log
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub enum AuditOutcome {
+    #[display("success")]
+    Success,
+
+    #[display("failure({code:#06x}, {info})")]
+    Failure { code: u16, info: String },
+}
+
+/// Record of the append-only audit log. Each record commits to the hash of
+/// the preceding one, so any removed or modified record breaks the chain of
+/// all records following it.
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("audit_record(#{seq}, {request}, {outcome})")]
+pub struct AuditRecord {
+    /// Number of the records preceding this one
+    pub seq: u64,
+
+    /// Unix timestamp of the request completion
+    pub timestamp: i64,
+
+    /// Identity of the party which has made the request
+    pub caller: String,
+
+    /// Summary of the request
+    pub request: String,
+
+    /// Hash of the serialized request, committing to all of its parameters
+    pub params_digest: sha256::Hash,
+
+    pub outcome: AuditOutcome,
+
+    /// Hash of the preceding record; zero hash for the first record
+    pub prev_hash: sha256::Hash,
 }
 
+impl AuditRecord {
+    /// Hash of the record, committing to all records preceding it
+    pub fn hash(&self) -> sha256::Hash {
+        sha256::Hash::hash(
+            &strict_encoding::strict_serialize(self)
+                .expect("in-memory encoding of audit record can't fail"),
+        )
+    }
+}
+
+/// Atomic swap with both legs anchored in the same witness transaction,
+/// which has to be signed by both parties
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("swap(...)")]
+pub struct Swap {
+    /// Consignment for the asset sent by the party completing the swap
+    pub outgoing: Option<Consignment>,
+
+    /// Consignment for the asset received by the party completing the swap,
+    /// already verified by it
+    pub incoming: Option<Consignment>,
+
+    pub disclosure: Disclosure,
+    pub witness: Psbt,
+}
+
+/// Bitcoin status of the outpoint holding asset allocations
+#[derive(
+    Clone, Copy, PartialEq, Eq, Hash, Debug, Display, StrictEncode, StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub enum AllocationStatus {
+    /// Transaction creating the outpoint is not mined yet or is not known
+    #[display("unconfirmed")]
+    Unconfirmed,
+
+    /// Transaction creating the outpoint is mined at the given block height
+    #[display("confirmed({0})")]
+    Confirmed(u32),
+
+    /// Outpoint is already spent by some transaction
+    #[display("spent")]
+    Spent,
+
+    /// Transaction creating the outpoint was mined, but has left the chain
+    /// after a reorganization
+    #[display("reorged")]
+    Reorged,
+
+    /// Outpoint is reserved for a protocol run outside of the node and
+    /// can't be spent by transfers
+    #[display("reserved")]
+    Reserved,
+
+    /// Allocation is on administrative hold and can't be transferred
+    #[display("held")]
+    Held,
+}
+
+impl Default for AllocationStatus {
+    #[inline]
+    fn default() -> Self {
+        AllocationStatus::Unconfirmed
+    }
+}
+
+/// Amounts of the asset allocated to an outpoint together with the outpoint
+/// status
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display(Debug)]
+pub struct AllocatedAmounts {
+    pub status: AllocationStatus,
+    pub amounts: Vec<AtomicValue>,
+    /// Bookkeeping labels of the allocation and its outpoint
+    pub labels: BTreeSet<String>,
+}
+
+/// Assets allocated to an outpoint together with the metadata of the assets
+/// which have them
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display(Debug)]
+pub struct OutpointAssets {
+    pub assets: BTreeMap<ContractId, AllocatedAmounts>,
+    pub meta: BTreeMap<ContractId, AssetMeta>,
+}
+
+/// Summary of the consignment data, produced without consignment validation
+#[derive(Clone, PartialEq, Eq, Debug, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct ConsignmentSummary {
+    /// Consignment contract id and ids of other contracts revealed by the
+    /// consignment anchors
+    pub contract_ids: BTreeSet<ContractId>,
+    pub schema_id: SchemaId,
+    pub transitions: u32,
+    pub extensions: u32,
+    pub endpoints: Vec<(NodeId, SealEndpoint)>,
+    /// Witness transaction ids of all consignment transitions
+    pub txids: BTreeSet<Txid>,
+    /// Total revealed value assigned to the outpoints provided with the
+    /// request
+    pub received_value: AtomicValue,
+}
+
+/// Asset which would be created by the issue request
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("issue_preview({contract_id}, ...)")]
+pub struct IssuePreview {
+    pub contract_id: ContractId,
+    /// Size of the strict-encoded genesis
+    pub genesis_size: u32,
+    /// Amount issued at genesis, in atomic units
+    pub issued: AtomicValue,
+    /// Maximum amount of the secondary issuance, in atomic units
+    pub inflation: AtomicValue,
+    /// Issued amounts per allocation seal
+    pub allocations: BTreeMap<IssueSeal, AtomicValue>,
+    /// Result of the genesis validation against RGB20 schema
+    pub status: ::rgb::validation::Status,
+}
+
+/// Consignment whose witness transactions do not have the required number of
+/// confirmations yet
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("pending({consignment_id}, required: {required})")]
+pub struct PendingConsignment {
+    pub consignment_id: ::bitcoin::hashes::sha256::Hash,
+    pub contract_id: ContractId,
+    /// Number of confirmations required for the acceptance
+    pub required: u32,
+    /// Current number of confirmations of the witness transactions which are
+    /// not deep enough yet
+    pub confirmations: BTreeMap<Txid, u32>,
+}
+
+/// Asset allocations removed (or, for a dry run, which would be removed) by
+/// the forget request
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("forget_report(dry_run: {dry_run}, ...)")]
+pub struct ForgetReport {
+    pub dry_run: bool,
+    pub assets: BTreeMap<ContractId, BTreeMap<OutPoint, Vec<AtomicValue>>>,
+}
+
+/// Asset supply recomputed from the contract genesis and the state
+/// transitions kept by the stash, with the values cached by the node which
+/// do not match the recomputed ones
+#[derive(
+    Clone, PartialEq, Eq, Debug, Display, Default, StrictEncode, StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display(
+    "{contract_id}: issued {issued}, burned {burned}, replaced {replaced}, \
+     circulating {circulating}"
+)]
+pub struct SupplyReport {
+    pub contract_id: ContractId,
+    /// Amount issued at genesis and with the secondary issues
+    pub issued: AtomicValue,
+    /// Number of the secondary issue transitions
+    pub secondary_issues: u32,
+    pub burned: AtomicValue,
+    /// Revealed amount assigned by the burn-and-replace transitions
+    pub replaced: AtomicValue,
+    /// Number of the replacement assignments whose amount is not revealed to
+    /// the node and which is not included into the replaced amount
+    pub confidential_replacements: u32,
+    pub circulating: AtomicValue,
+    pub mismatches: Vec<SupplyMismatch>,
+}
+
+/// Supply value cached by the node which differs from the recomputed one
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("{value}: cached {cached}, computed {computed}")]
+pub struct SupplyMismatch {
+    pub value: String,
+    pub cached: AtomicValue,
+    pub computed: AtomicValue,
+}
+
+/// Owned right of the RGB20 epoch-based burn and replacement procedure
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Display,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub enum EpochRight {
+    /// Right to open the next epoch
+    #[display("epoch")]
+    Epoch,
+
+    /// Right to burn and replace assets within the epoch
+    #[display("burn_replace")]
+    BurnReplace,
+}
+
+/// Unspent seal of the contract node assignment of an epoch right
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("{right}({node_id}:{index})")]
+pub struct EpochSeal {
+    pub right: EpochRight,
+    pub node_id: NodeId,
+    pub index: u16,
+    /// Outpoint of the seal, unless the seal is confidential
+    pub outpoint: Option<OutPoint>,
+}
+
+/// Consignment size optimization report
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+    Display,
+    Default,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display(
+    "{original_size} -> {optimized_size} bytes ({deduplicated} duplicated \
+     nodes removed, {concealed} items concealed)"
+)]
+pub struct ConsignmentStats {
+    /// Size of the strict-encoded consignment before the optimization
+    pub original_size: u32,
+    /// Size of the strict-encoded consignment after the optimization
+    pub optimized_size: u32,
+    /// Number of removed duplicated ancestor nodes
+    pub deduplicated: u32,
+    /// Number of concealed state assignments and anchor commitments
+    pub concealed: u32,
+}
+
+/// Number of stash entries removed by the garbage collection
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+    Display,
+    Default,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display(
+    "{transitions} transitions, {extensions} extensions, {anchors} anchors"
+)]
+pub struct GcStats {
+    pub transitions: u32,
+    pub extensions: u32,
+    pub anchors: u32,
+}
+
+/// Readiness of the daemon and the services it depends on
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+    Display,
+    Default,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(Debug)]
+pub struct HealthStatus {
+    /// Stash daemon has replied to the request
+    pub stash_connected: bool,
+    /// Asset cache storage is available for writing
+    pub cache_available: bool,
+    /// Schema of the contracts served by the daemon is registered with the
+    /// stash daemon
+    pub schema_registered: bool,
+    /// Height of the chain tip known to the chain backend, or `None` if the
+    /// backend is not reachable
+    pub chain_height: Option<u32>,
+    /// Time (in seconds) passed since the chain tip block timestamp
+    pub chain_lag: Option<u64>,
+    /// Memory usage of the asset cache
+    pub cache: CacheStats,
+}
+
+impl HealthStatus {
+    /// Whether the daemon is able to serve requests
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.stash_connected
+            && self.cache_available
+            && self.schema_registered
+            && self.chain_height.is_some()
+    }
+}
+
+/// Memory usage of the asset cache and its efficiency
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+    Display,
+    Default,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display("{hot} assets in memory, {cold} on disk, {memory_used} bytes used")]
+pub struct CacheStats {
+    /// Number of assets kept in memory
+    pub hot: u32,
+    /// Number of assets evicted to disk
+    pub cold: u32,
+    /// Size of the assets kept in memory, in bytes of their encoded data
+    pub memory_used: u64,
+    /// Memory budget, if the cache is limited
+    pub memory_budget: Option<u64>,
+    /// Number of accesses to assets kept in memory
+    pub hits: u64,
+    /// Number of accesses to assets which had to be read from disk
+    pub misses: u64,
+    /// Number of assets moved from memory to disk
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    /// Share of asset accesses served from memory, or `None` if no assets
+    /// were accessed yet
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return None;
+        }
+        Some(self.hits as f64 / total as f64)
+    }
+}
+
+/// Events replayed from the daemon event log
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("event_replay({next_seq}, ...)")]
+pub struct EventReplay {
+    /// Some of the requested events were evicted from the log and can't be
+    /// replayed
+    pub truncated: bool,
+    /// Sequence number which will be assigned to the next published event
+    pub next_seq: u64,
+    /// Serialized events, indexed by their sequence numbers
+    pub events: BTreeMap<u64, Vec<u8>>,
+}
+
+/// Progress of the allocation import from the snapshot file
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+    Display,
+    Default,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("{records} records: {imported} imported, {duplicates} known")]
+pub struct BulkImportReport {
+    /// Number of allocation records processed so far
+    pub records: u64,
+    /// Number of allocations added to the cache
+    pub imported: u64,
+    /// Number of allocations which were already known to the node
+    pub duplicates: u64,
+}
+
+/// Result of the claims bundle import
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "kebab-case")
+)]
+#[display("{contracts} contracts and {reveals} reveals imported")]
+pub struct ClaimsImport {
+    /// Number of contracts added to the stash and cache
+    pub contracts: u32,
+    /// Number of the outpoint reveals added to the cache
+    pub reveals: u32,
+    /// Output descriptors of the funding wallet keys, which must be added
+    /// to the node configuration to spend the funding outputs
+    pub descriptors: Vec<String>,
+}
+
+/// Completed backup of the node data
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "kebab-case")
+)]
+#[display("backup {name} of {cache_files} cache files, {removed} rotated")]
+pub struct Backup {
+    /// Name of the backup in the backup target
+    pub name: String,
+    /// Number of the cache files copied into the backup
+    pub cache_files: u32,
+    /// Number of the older backups removed by the rotation
+    pub removed: u32,
+}
+
+/// Result of the trusted checkpoint import
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+    Display,
+    Default,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "kebab-case")
+)]
+#[display(
+    "height {height}: {schemata} schemata and {geneses} contracts added, \
+     {anchors} anchors"
+)]
+pub struct CheckpointImport {
+    /// Height of the block up to which the checkpoint data are known
+    pub height: u32,
+    /// Number of schemata added to the stash
+    pub schemata: u32,
+    /// Number of contracts added to the stash; their history is backfilled
+    /// later
+    pub geneses: u32,
+    /// Number of the anchor headers in the checkpoint
+    pub anchors: u32,
+}
+
+/// State of the daemon managed by `rgbd` supervisor
+#[derive(
+    Clone, Copy, PartialEq, Eq, Hash, Debug, Display, StrictEncode, StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "kebab-case")
+)]
+pub enum DaemonState {
+    /// Daemon process or thread is running
+    #[display("running")]
+    Running,
+
+    /// Daemon has exited and is waiting for the restart delay to pass
+    #[display("restarting")]
+    Restarting,
+
+    /// Daemon has exited and will not be restarted since the supervisor is
+    /// shutting down
+    #[display("stopped")]
+    Stopped,
+}
+
+/// Status of the daemon managed by `rgbd` supervisor
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "kebab-case")
+)]
+#[display("{name} ({network}): {state}")]
+pub struct DaemonStatus {
+    /// Daemon name, like `stashd` or `fungibled`
+    pub name: String,
+    /// Bitcoin network served by the daemon
+    pub network: String,
+    pub state: DaemonState,
+    /// Process id, if the daemon is run as a separate process
+    pub pid: Option<u32>,
+    /// Time (in seconds) since the last (re)start of the daemon
+    pub uptime: u64,
+    /// Number of times the daemon was restarted after it has exited
+    pub restarts: u32,
+    /// Description of the last daemon exit
+    pub last_exit: Option<String>,
+}
+
+/// Status of the request executed in background
+#[derive(Clone, Debug, Display)]
+#[display("job({id}, {request})")]
+pub struct JobStatus {
+    /// Job id assigned when the request was queued
+    pub id: u64,
+    /// Queued request, in its display form
+    pub request: String,
+    /// Time when the request was queued
+    pub queued: i64,
+    /// Time when the request execution was completed, if it was
+    pub completed: Option<i64>,
+    /// Reply to the request, if it was already executed
+    pub result: Option<Box<Reply>>,
+}
+
+impl JobStatus {
+    /// Whether the request was already executed
+    #[inline]
+    pub fn is_completed(&self) -> bool {
+        self.result.is_some()
+    }
+}
+
+impl StrictEncode for JobStatus {
+    fn strict_encode<E: io::Write>(
+        &self,
+        mut e: E,
+    ) -> Result<usize, strict_encoding::Error> {
+        Ok(self.id.strict_encode(&mut e)?
+            + self.request.strict_encode(&mut e)?
+            + self.queued.strict_encode(&mut e)?
+            + self.completed.strict_encode(&mut e)?
+            + self
+                .result
+                .as_ref()
+                .map(|reply| reply.serialize())
+                .strict_encode(&mut e)?)
+    }
+}
+
+impl StrictDecode for JobStatus {
+    fn strict_decode<D: io::Read>(
+        mut d: D,
+    ) -> Result<Self, strict_encoding::Error> {
+        Ok(Self {
+            id: StrictDecode::strict_decode(&mut d)?,
+            request: StrictDecode::strict_decode(&mut d)?,
+            queued: StrictDecode::strict_decode(&mut d)?,
+            completed: StrictDecode::strict_decode(&mut d)?,
+            result: Option::<Vec<u8>>::strict_decode(&mut d)?
+                .map(|data| {
+                    super::batch::unmarshall(
+                        &Reply::create_unmarshaller(),
+                        &data,
+                    )
+                })
+                .transpose()?
+                .map(Box::new),
+        })
+    }
+}
+
+/// Error reply. Clients should branch on the error [`Failure::code`], since
+/// the human-readable `info` message may change between the daemon versions.
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Error)]
 #[strict_encoding_crate(lnpbp::strict_encoding)]
-#[display("failure({code}, {info})")]
+#[display("failure({category}, {code:#06x}, {info})")]
 #[non_exhaustive]
 pub struct Failure {
+    pub category: ErrorCategory,
+    /// Numeric value of the [`ErrorCode`]; it is kept as a number, so the
+    /// clients can receive codes introduced in the newer daemon versions
     pub code: u16,
+    /// Human-readable error message
     pub info: String,
+    /// Machine-readable parameters of the error
+    pub details: BTreeMap<String, String>,
 }
 
-impl From<internet2::presentation::Error> for Failure {
-    fn from(err: internet2::presentation::Error) -> Self {
-        // TODO #61: Save error code taken from `Error::to_value()` after
-        //       implementation of `ToValue` trait and derive macro for enums
+impl Failure {
+    pub fn with(code: ErrorCode, info: impl ToString) -> Self {
         Failure {
-            code: 0,
-            info: format!("{}", err),
+            category: code.category(),
+            code: code as u16,
+            info: info.to_string(),
+            details: bmap! {},
         }
     }
+
+    /// Error code, if it is known to this version of the library
+    #[inline]
+    pub fn error_code(&self) -> Result<ErrorCode, UnknownErrorCode> {
+        ErrorCode::try_from(self.code)
+    }
+}
+
+impl From<internet2::presentation::Error> for Failure {
+    fn from(err: internet2::presentation::Error) -> Self {
+        Failure::with(ErrorCode::Encoding, err)
+    }
 }
 
 impl From<internet2::transport::Error> for Failure {
     fn from(err: internet2::transport::Error) -> Self {
-        // TODO #61: Save error code taken from `Error::to_value()` after
-        //       implementation of `ToValue` trait and derive macro for enums
-        Failure {
-            code: 1,
-            info: format!("{}", err),
-        }
+        Failure::with(ErrorCode::Transport, err)
     }
 }
 
 #[cfg(feature = "node")]
 impl From<RuntimeError> for Failure {
     fn from(err: RuntimeError) -> Self {
-        // TODO #61: Save error code taken from `Error::to_value()` after
-        //       implementation of `ToValue` trait and derive macro for enums
-        Failure {
-            code: 2,
-            info: format!("{}", err),
-        }
+        Failure::with(ErrorCode::Runtime, err)
     }
 }
 
 #[cfg(any(feature = "node", feature = "client"))]
 impl From<ServiceError> for Failure {
     fn from(err: ServiceError) -> Self {
-        // TODO #61: Save error code taken from `Error::to_value()` after
-        //       implementation of `ToValue` trait and derive macro for enums
         Failure {
-            code: 3,
-            info: format!("{}", err),
+            details: err.domain.details(),
+            ..Failure::with(err.domain.code(), err)
         }
     }
 }
@@ -0,0 +1,137 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use lnpbp::strict_encoding::{self, StrictDecode, StrictEncode};
+
+/// Reference-counted data embedded into the API messages. Large data, like
+/// consignments, are wrapped into it, so they can be passed between the
+/// runtime components and into the outgoing messages without being copied.
+/// Strict encoding of the wrapper is the same as of the wrapped data.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Shared<T>(Arc<T>);
+
+impl<T> Shared<T> {
+    /// Returns wrapped data, copying them only if they are still shared
+    pub fn into_inner(self) -> T
+    where
+        T: Clone,
+    {
+        take(self.0)
+    }
+}
+
+/// Takes data out of [`Arc`], copying them only if they are still shared.
+/// Used to get ownership over the unmarshalled API messages, which are
+/// returned by the unmarshaller wrapped into [`Arc`].
+pub fn take<T>(data: Arc<T>) -> T
+where
+    T: Clone,
+{
+    Arc::try_unwrap(data).unwrap_or_else(|data| (*data).clone())
+}
+
+impl<T> Deref for Shared<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> AsRef<T> for Shared<T> {
+    #[inline]
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Shared<T> {
+    #[inline]
+    fn from(data: T) -> Self {
+        Shared(Arc::new(data))
+    }
+}
+
+impl<T> From<Arc<T>> for Shared<T> {
+    #[inline]
+    fn from(data: Arc<T>) -> Self {
+        Shared(data)
+    }
+}
+
+impl<T> Display for Shared<T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&*self.0, f)
+    }
+}
+
+impl<T> StrictEncode for Shared<T>
+where
+    T: StrictEncode,
+{
+    #[inline]
+    fn strict_encode<E: io::Write>(
+        &self,
+        e: E,
+    ) -> Result<usize, strict_encoding::Error> {
+        self.0.strict_encode(e)
+    }
+}
+
+impl<T> StrictDecode for Shared<T>
+where
+    T: StrictDecode,
+{
+    #[inline]
+    fn strict_decode<D: io::Read>(
+        d: D,
+    ) -> Result<Self, strict_encoding::Error> {
+        T::strict_decode(d).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use lnpbp::strict_encoding::{strict_deserialize, strict_serialize};
+
+    use super::*;
+
+    #[test]
+    fn test_shared_encoding() {
+        let data = vec![1u8, 2, 3];
+        let shared = Shared::from(data.clone());
+        let copy = shared.clone();
+        assert_eq!(
+            strict_serialize(&shared).unwrap(),
+            strict_serialize(&data).unwrap()
+        );
+        assert_eq!(
+            strict_deserialize::<Shared<Vec<u8>>>(
+                &strict_serialize(&data).unwrap()
+            )
+            .unwrap(),
+            shared
+        );
+        drop(shared);
+        assert_eq!(copy.into_inner(), data);
+    }
+}
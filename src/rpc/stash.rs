@@ -14,13 +14,16 @@
 use std::collections::{BTreeMap, BTreeSet};
 
 use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
-use bitcoin::OutPoint;
+use bitcoin::{OutPoint, Txid};
 use lnpbp::seals::OutpointReveal;
 use rgb::{
     Consignment, ContractId, Disclosure, Genesis, NodeId, Schema, SchemaId,
     SealEndpoint, Transition,
 };
 
+use super::Shared;
+use crate::util::{AnchorContainer, DataFormat};
+
 #[derive(Clone, Debug, Display, Api)]
 #[api(encoding = "strict")]
 #[strict_encoding_crate(lnpbp::strict_encoding)]
@@ -51,6 +54,24 @@ pub enum Request {
     #[display("read_genesis({0})")]
     ReadGenesis(ContractId),
 
+    /// Exports snapshot of the contract state known to the stash in the
+    /// requested data format (YAML or JSON)
+    #[api(type = 0x0207)]
+    ContractState(ContractStateRequest),
+
+    /// Returns all anchored transitions and extensions of the contract
+    /// known to the stash; used by the nodes backfilling history of the
+    /// contracts imported from a checkpoint
+    #[api(type = 0x0209)]
+    #[display("contract_history({0})")]
+    ContractHistory(ContractId),
+
+    /// Exports graph of the contract nodes known to the stash in Graphviz
+    /// DOT format, replied with `Reply::Graph`
+    #[api(type = 0x020b)]
+    #[display("export_graph({0})")]
+    ExportGraph(ContractId),
+
     #[api(type = 0x0301)]
     #[display("read_transitions(...)")]
     ReadTransitions(Vec<NodeId>),
@@ -58,13 +79,53 @@ pub enum Request {
     #[api(type = 0x0401)]
     Transfer(TransferRequest),
 
+    /// Returns consignment with the contract history preceding the state
+    /// transition which is not anchored yet, for the cases when the
+    /// transition is anchored by some other party
+    #[api(type = 0x040b)]
+    History(HistoryRequest),
+
+    /// Rebuilds consignment of an earlier transfer from the anchor and the
+    /// state transition committed by its witness transaction, which must be
+    /// already enclosed into the stash. Endpoints of the consignment are the
+    /// payees recorded with the transfer or, if there are none, the blinded
+    /// and witness output seals of the transition. Replied with
+    /// `Reply::Nothing` if the stash does not know the witness transaction.
+    #[api(type = 0x0411)]
+    Reconsign(ReconsignRequest),
+
+    /// Returns all known anchors committing to the witness transaction with
+    /// the given txid
+    #[api(type = 0x0501)]
+    #[display("anchors_by_txid({0})")]
+    AnchorsByTxid(Txid),
+
     #[api(type = 0x0403)]
     #[display("validate({0})")]
-    Validate(Consignment),
+    Validate(Shared<Consignment>),
+
+    /// Validates consignment file located on the stash daemon host without
+    /// loading the whole consignment into memory
+    #[api(type = 0x040d)]
+    #[display("validate_file({0})")]
+    ValidateFile(String),
+
+    /// Re-runs consignment validation and explains each of its steps, with
+    /// the failures put next to the nodes, anchors and witness transactions
+    /// they concern; replied with `Reply::ValidationReport`
+    #[api(type = 0x040f)]
+    #[display("explain_failure({0})")]
+    ExplainFailure(Shared<Consignment>),
 
     #[api(type = 0x0405)]
     Accept(AcceptRequest),
 
+    /// Parses consignment without validating or accepting it and returns
+    /// its summary
+    #[api(type = 0x0409)]
+    #[display("inspect_consignment(...)")]
+    InspectConsignment(AcceptRequest),
+
     #[api(type = 0x0406)]
     #[display("enclose({0})")]
     Enclose(Disclosure),
@@ -72,6 +133,43 @@ pub enum Request {
     #[api(type = 0x0407)]
     #[display("forget(...)")]
     Forget(Vec<(NodeId, u16)>),
+
+    /// Removes transitions, extensions and anchors which are not connected
+    /// to any of the known contract geneses
+    #[api(type = 0x0601)]
+    #[display("gc()")]
+    Gc(),
+
+    /// Pushes all of the stash data to the standby node, bringing its stash
+    /// in sync with the primary one
+    #[api(type = 0x0603)]
+    #[display("resync()")]
+    Resync(),
+
+    /// Imports signed checkpoint file located on the stash daemon host,
+    /// adding its schemata and contract geneses to the stash
+    #[api(type = 0x0605)]
+    #[display("import_checkpoint({0})")]
+    ImportCheckpoint(String),
+
+    /// Copies all stash data into the backup component `stash` of the given
+    /// backup; the data are kept in the `disk` storage layout regardless of
+    /// the storage backend used by the stash
+    #[api(type = 0x0607)]
+    Backup(BackupRequest),
+
+    /// Re-reads tunable settings from the daemon configuration file
+    #[api(type = 0x0701)]
+    #[display("reload_config()")]
+    ReloadConfig(),
+}
+
+#[derive(Clone, Copy, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("contract_state({contract_id}, using: {format})")]
+pub struct ContractStateRequest {
+    pub contract_id: ContractId,
+    pub format: DataFormat,
 }
 
 #[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
@@ -83,13 +181,50 @@ pub struct TransferRequest {
     pub transition: Transition,
     pub other_transitions: BTreeMap<ContractId, Transition>,
     pub endpoints: BTreeSet<SealEndpoint>,
+    /// Endpoints for the transitions of other contracts anchored in the same
+    /// witness transaction. Consignments are created only for the contracts
+    /// listed here, each of which must have transition in
+    /// `other_transitions`.
+    pub other_endpoints: BTreeMap<ContractId, BTreeSet<SealEndpoint>>,
     pub psbt: Psbt,
+    /// Witness transaction output type holding the anchor commitment
+    pub container: AnchorContainer,
+}
+
+#[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("history({contract_id}, ...)")]
+pub struct HistoryRequest {
+    pub contract_id: ContractId,
+    pub transition: Transition,
+    pub endpoints: BTreeSet<SealEndpoint>,
+}
+
+#[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("backup({backup} to {target})")]
+pub struct BackupRequest {
+    /// Directory or S3 bucket URL keeping the backups; it must be accessible
+    /// from the stash daemon host
+    pub target: String,
+    pub backup: String,
+}
+
+#[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display("reconsign({contract_id}, {txid})")]
+pub struct ReconsignRequest {
+    pub contract_id: ContractId,
+    pub txid: Txid,
+    /// Payee endpoints recorded when the transfer was created; may be empty
+    /// for the transfers recorded before the payees were kept
+    pub payees: Vec<SealEndpoint>,
 }
 
 #[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
 #[strict_encoding_crate(lnpbp::strict_encoding)]
 #[display("accept(...)")]
 pub struct AcceptRequest {
-    pub consignment: Consignment,
+    pub consignment: Shared<Consignment>,
     pub reveal_outpoints: Vec<OutpointReveal>,
 }
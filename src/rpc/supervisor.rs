@@ -0,0 +1,33 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! API of `rgbd` supervisor launching and restarting stash and contract
+//! daemons
+
+#[derive(Clone, Debug, Display, Api)]
+#[api(encoding = "strict")]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[display(inner)]
+#[non_exhaustive]
+pub enum Request {
+    /// Liveness check, replied with `Reply::Success`
+    #[api(type = 0x0801)]
+    #[display("ping()")]
+    Ping,
+
+    /// Status of all of the daemons managed by the supervisor, replied with
+    /// `Reply::Daemons`
+    #[api(type = 0x0803)]
+    #[display("status()")]
+    Status,
+}
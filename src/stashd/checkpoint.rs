@@ -0,0 +1,515 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Trusted checkpoints bootstrapping fresh nodes. Checkpoint is a bundle of
+//! contract schemata and geneses together with the headers of the blocks
+//! mining witness transactions of the contract anchors up to some height,
+//! signed by a party trusted by the node operator (like an asset registry).
+//! Once the checkpoint is imported, the node serves the known assets
+//! immediately; contract history is backfilled lazily, when it is first
+//! required, from the peers configured with `backfill` option. Only the
+//! backfilled anchors which are listed in the checkpoint and are committed
+//! to by their witness transactions are accepted.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use bitcoin::consensus::{Decodable, Encodable};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{self, Message, PublicKey, Secp256k1, SecretKey};
+use bitcoin::{BlockHeader, Txid};
+use internet2::zmqsocket::{ZmqSocketAddr, ZmqType};
+use internet2::{
+    session, transport, CreateUnmarshaller, TypedEnum, Unmarshall,
+};
+use lnpbp::strict_encoding::{
+    self, strict_serialize, StrictDecode, StrictEncode,
+};
+use lnpbp::Chain;
+use rgb::{
+    Anchor, ContractId, Disclosure, Genesis, Node, NodeId, Schema, Stash,
+};
+use wallet::resolvers::{ElectrumTxResolver, TxResolver};
+
+use super::index::Index;
+use super::storage::Store;
+use super::Runtime;
+use crate::constants::STASHD_BACKFILL_TIMEOUT;
+use crate::error::{ApiErrorType, ServiceErrorDomain};
+use crate::rpc::reply::CheckpointImport;
+use crate::rpc::stash::Request;
+use crate::rpc::Reply;
+use crate::util::compression::req_rep;
+
+/// Tag prefixing checkpoint data in the message signed by the checkpoint key
+pub const CHECKPOINT_TAG: &[u8] = b"RGB:checkpoint:";
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum CheckpointError {
+    /// I/O error accessing checkpoint file: {0}
+    #[from]
+    Io(io::Error),
+
+    /// malformed checkpoint data: {0}
+    #[from]
+    Encoding(strict_encoding::Error),
+
+    /// checkpoint signature is invalid
+    InvalidSignature,
+
+    /// checkpoint is signed with key {0}, which is not trusted
+    UntrustedSigner(PublicKey),
+
+    /// anchor witness transaction {txid} is mined at height {height}, above
+    /// the checkpoint height
+    AboveCheckpoint { txid: Txid, height: u32 },
+}
+
+/// Header of the block mining witness transaction of a contract anchor
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AnchorHeader {
+    pub txid: Txid,
+    pub height: u32,
+    pub header: BlockHeader,
+}
+
+impl StrictEncode for AnchorHeader {
+    fn strict_encode<E: io::Write>(
+        &self,
+        mut e: E,
+    ) -> Result<usize, strict_encoding::Error> {
+        Ok(self.txid.strict_encode(&mut e)?
+            + self.height.strict_encode(&mut e)?
+            + self.header.consensus_encode(&mut e)?)
+    }
+}
+
+impl StrictDecode for AnchorHeader {
+    fn strict_decode<D: io::Read>(
+        mut d: D,
+    ) -> Result<Self, strict_encoding::Error> {
+        Ok(AnchorHeader {
+            txid: Txid::strict_decode(&mut d)?,
+            height: u32::strict_decode(&mut d)?,
+            header: BlockHeader::consensus_decode(&mut d).map_err(|err| {
+                strict_encoding::Error::DataIntegrityError(err.to_string())
+            })?,
+        })
+    }
+}
+
+/// Contract data known at some block height
+#[derive(Clone, Debug, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct Checkpoint {
+    pub network: Chain,
+    pub height: u32,
+    pub schemata: Vec<Schema>,
+    pub geneses: Vec<Genesis>,
+    pub anchors: Vec<AnchorHeader>,
+}
+
+/// Checkpoint signed by the party vouching for its data
+#[derive(Clone, Debug, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct SignedCheckpoint {
+    pub checkpoint: Checkpoint,
+
+    /// Public key of the checkpoint signer
+    pub signer: PublicKey,
+
+    /// Signature over [`SignedCheckpoint::message`]
+    pub signature: secp256k1::Signature,
+}
+
+impl SignedCheckpoint {
+    /// Message signed by the checkpoint key: SHA256 hash of
+    /// [`CHECKPOINT_TAG`] followed by the serialized checkpoint
+    pub fn message(
+        checkpoint: &Checkpoint,
+    ) -> Result<Message, strict_encoding::Error> {
+        let mut engine = sha256::Hash::engine();
+        engine.input(CHECKPOINT_TAG);
+        engine.input(&strict_serialize(checkpoint)?);
+        Ok(Message::from_slice(&sha256::Hash::from_engine(engine)[..])
+            .expect("SHA256 hash is always a valid message"))
+    }
+
+    /// Constructs checkpoint signed with the key
+    pub fn sign(
+        checkpoint: Checkpoint,
+        secret_key: &SecretKey,
+    ) -> Result<Self, strict_encoding::Error> {
+        let secp = Secp256k1::signing_only();
+        Ok(SignedCheckpoint {
+            signer: PublicKey::from_secret_key(&secp, secret_key),
+            signature: secp.sign(&Self::message(&checkpoint)?, secret_key),
+            checkpoint,
+        })
+    }
+
+    /// Checks that the checkpoint is signed with one of the trusted keys
+    /// and that its anchors are not above the checkpoint height. Block
+    /// headers are vouched by the checkpoint signer: they carry no merkle
+    /// proofs, so their proof of work does not prove anything about the
+    /// anchors.
+    pub fn verify(&self, trusted: &[PublicKey]) -> Result<(), CheckpointError> {
+        if !trusted.contains(&self.signer) {
+            Err(CheckpointError::UntrustedSigner(self.signer))?
+        }
+        Secp256k1::verification_only()
+            .verify(
+                &Self::message(&self.checkpoint)?,
+                &self.signature,
+                &self.signer,
+            )
+            .map_err(|_| CheckpointError::InvalidSignature)?;
+        for anchor in &self.checkpoint.anchors {
+            if anchor.height > self.checkpoint.height {
+                Err(CheckpointError::AboveCheckpoint {
+                    txid: anchor.txid,
+                    height: anchor.height,
+                })?
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, CheckpointError> {
+        let file = BufReader::new(fs::File::open(path)?);
+        Ok(SignedCheckpoint::strict_decode(file)?)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let file = BufWriter::new(fs::File::create(path)?);
+        self.strict_encode(file)?;
+        Ok(())
+    }
+}
+
+impl Runtime {
+    /// Adds schemata and geneses from the checkpoint file located on the
+    /// stash daemon host; history of the newly added contracts will be
+    /// backfilled once it is required
+    pub(super) fn import_checkpoint(
+        &mut self,
+        filename: &str,
+    ) -> Result<CheckpointImport, ServiceErrorDomain> {
+        let malformed = |err: CheckpointError| {
+            ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+                request: s!("import_checkpoint"),
+                argument: err.to_string(),
+            })
+        };
+        let signed = SignedCheckpoint::read(filename).map_err(malformed)?;
+        match signed.verify(&self.config.checkpoint_keys) {
+            Err(err @ CheckpointError::UntrustedSigner(_)) => {
+                Err(ServiceErrorDomain::Config(err.to_string()))?
+            }
+            result => result.map_err(malformed)?,
+        }
+        let checkpoint = signed.checkpoint;
+        if checkpoint.network != self.config.network {
+            Err(ServiceErrorDomain::WrongNetwork {
+                expected: self.config.network.clone(),
+                found: checkpoint.network.clone(),
+            })?
+        }
+        for genesis in &checkpoint.geneses {
+            self.check_network(genesis)?;
+        }
+
+        let mut report = CheckpointImport {
+            height: checkpoint.height,
+            anchors: checkpoint.anchors.len() as u32,
+            ..default!()
+        };
+        let known_schemata = self
+            .storage
+            .schema_ids()?
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+        for schema in &checkpoint.schemata {
+            if known_schemata.contains(&schema.schema_id()) {
+                continue;
+            }
+            self.storage.add_schema(schema)?;
+            self.replicate(Request::AddSchema(schema.clone()));
+            report.schemata += 1;
+        }
+        let known_contracts = self
+            .storage
+            .contract_ids()?
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+        let mut added = BTreeSet::new();
+        for genesis in &checkpoint.geneses {
+            let contract_id = genesis.contract_id();
+            if known_contracts.contains(&contract_id) {
+                continue;
+            }
+            self.storage.add_genesis(genesis)?;
+            self.index_node_seals(genesis, None)
+                .map_err(|_| ServiceErrorDomain::Stash)?;
+            self.replicate(Request::AddGenesis(genesis.clone()));
+            added.insert(contract_id);
+        }
+        report.geneses = added.len() as u32;
+        let anchors = checkpoint
+            .anchors
+            .iter()
+            .map(|anchor| (anchor.txid, anchor.height))
+            .collect();
+        self.indexer.index_checkpoint(&added, &anchors)?;
+
+        info!(
+            "Imported checkpoint at height {} signed by {}: {}",
+            checkpoint.height, signed.signer, report
+        );
+        Ok(report)
+    }
+
+    /// Disclosure with all of the anchored transitions and extensions of the
+    /// contract known to the stash
+    pub(super) fn contract_disclosure(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<Disclosure, ServiceErrorDomain> {
+        // Fails for unknown contracts
+        self.storage.genesis(&contract_id)?;
+
+        let mut disclosure = Disclosure::default();
+        for node_id in self.storage.transition_ids()? {
+            let anchor_id = self.indexer.anchor_id_by_transition_id(node_id)?;
+            let anchor = self.storage.anchor(&anchor_id)?;
+            if !anchor.validate(&contract_id, &node_id) {
+                continue;
+            }
+            let transition = self.storage.transition(&node_id)?;
+            disclosure.insert_anchored_transitions(
+                anchor,
+                bmap! { contract_id => transition },
+            );
+        }
+        let mut extensions = vec![];
+        for node_id in self.storage.extension_ids()? {
+            let extension = self.storage.extension(&node_id)?;
+            if extension.contract_id() == Some(contract_id) {
+                extensions.push(extension);
+            }
+        }
+        if !extensions.is_empty() {
+            disclosure.insert_extensions(contract_id, extensions);
+        }
+        Ok(disclosure)
+    }
+
+    /// Fetches history of the contract imported from the checkpoint from the
+    /// backfill peers, if it was not backfilled yet. Failures are logged:
+    /// the request requiring the history is served with the data known to
+    /// the stash.
+    pub(super) fn backfill(&mut self, contract_id: ContractId) {
+        match self.indexer.requires_backfill(contract_id) {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(err) => {
+                error!("Unable to read backfill index: {}", err);
+                return;
+            }
+        }
+        for endpoint in self.config.backfill.clone() {
+            debug!("Backfilling contract {} from {}", contract_id, endpoint);
+            match self.backfill_from(&endpoint, contract_id) {
+                Ok(count) => {
+                    info!(
+                        "Contract {} history is backfilled from {}: {} \
+                         anchored transitions",
+                        contract_id, endpoint, count
+                    );
+                    return;
+                }
+                Err(err) => warn!(
+                    "Unable to backfill contract {} from {}: {}",
+                    contract_id, endpoint, err
+                ),
+            }
+        }
+        warn!("History of contract {} is not backfilled yet", contract_id);
+    }
+
+    fn backfill_from(
+        &mut self,
+        endpoint: &ZmqSocketAddr,
+        contract_id: ContractId,
+    ) -> Result<usize, ServiceErrorDomain> {
+        let peer = fetch_history(endpoint, contract_id)?;
+        let electrum = ElectrumTxResolver::new(&self.config.electrum_server)
+            .map_err(|_| ServiceErrorDomain::Electrum)?;
+
+        let mut disclosure = Disclosure::default();
+        let mut count = 0usize;
+        for (anchor, transitions) in peer.transitions().values() {
+            let transition = match transitions.get(&contract_id) {
+                Some(transition) => transition,
+                None => continue,
+            };
+            if self.indexer.checkpoint_height(anchor.txid)?.is_none() {
+                warn!(
+                    "Skipping backfilled anchor {} which is not listed in \
+                     the checkpoint",
+                    anchor.txid
+                );
+                continue;
+            }
+            // Peer may provide anchor listed in the checkpoint, but committing
+            // to a transition it has invented, so the commitment is checked
+            // against the witness transaction
+            verify_anchor(
+                &electrum,
+                anchor,
+                contract_id,
+                transition.node_id(),
+            )?;
+            disclosure.insert_anchored_transitions(
+                anchor.clone(),
+                bmap! { contract_id => transition.clone() },
+            );
+            count += 1;
+        }
+        if let Some(extensions) = peer.extensions().get(&contract_id) {
+            let extensions = extensions
+                .iter()
+                .filter(|extension| {
+                    extension.contract_id() == Some(contract_id)
+                })
+                .cloned()
+                .collect();
+            disclosure.insert_extensions(contract_id, extensions);
+        }
+
+        self.know_about(disclosure.clone())
+            .map_err(|_| ServiceErrorDomain::Stash)?;
+        self.replicate(Request::Enclose(disclosure));
+        self.indexer.mark_backfilled(contract_id)?;
+        Ok(count)
+    }
+}
+
+/// Checks that the anchor commits to the contract node and that the
+/// commitment is contained in the witness transaction
+pub(super) fn verify_anchor(
+    electrum: &ElectrumTxResolver,
+    anchor: &Anchor,
+    contract_id: ContractId,
+    node_id: NodeId,
+) -> Result<(), ServiceErrorDomain> {
+    if !anchor.validate(&contract_id, &node_id) {
+        Err(ServiceErrorDomain::Anchor(format!(
+            "anchor {} does not commit to transition {}",
+            anchor.txid, node_id
+        )))?
+    }
+    let (tx, fee) = electrum
+        .resolve(&anchor.txid)
+        .map_err(|_| ServiceErrorDomain::Electrum)?
+        .ok_or_else(|| {
+            ServiceErrorDomain::Anchor(format!(
+                "witness transaction {} is not known",
+                anchor.txid
+            ))
+        })?;
+    if !anchor.verify(&contract_id, &tx, fee) {
+        Err(ServiceErrorDomain::Anchor(format!(
+            "witness transaction {} does not contain anchor commitment",
+            anchor.txid
+        )))?
+    }
+    Ok(())
+}
+
+fn fetch_history(
+    endpoint: &ZmqSocketAddr,
+    contract_id: ContractId,
+) -> Result<Disclosure, ServiceErrorDomain> {
+    let mut session =
+        session::Raw::with_zmq_unencrypted(ZmqType::Req, endpoint, None, None)?;
+    let socket = session.as_socket();
+    socket
+        .set_rcvtimeo(STASHD_BACKFILL_TIMEOUT as i32 * 1000)
+        .map_err(transport::Error::from)?;
+    socket.set_linger(0).map_err(transport::Error::from)?;
+
+    let raw = req_rep(
+        &mut session,
+        Request::ContractHistory(contract_id).serialize(),
+    )?;
+    match &*Reply::create_unmarshaller().unmarshall(&raw)? {
+        Reply::Disclosure(disclosure) => Ok(disclosure.clone()),
+        Reply::Failure(failure) => {
+            Err(ServiceErrorDomain::Internal(failure.to_string()))
+        }
+        _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::blockdata::constants::genesis_block;
+
+    #[test]
+    fn test_checkpoint_signature() {
+        let secret_key = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let header = genesis_block(bitcoin::Network::Bitcoin).header;
+        let anchor = AnchorHeader {
+            txid: Txid::default(),
+            height: 0,
+            header,
+        };
+        let checkpoint = Checkpoint {
+            network: Chain::Mainnet,
+            height: 100,
+            schemata: vec![],
+            geneses: vec![],
+            anchors: vec![anchor],
+        };
+        let signed = SignedCheckpoint::sign(checkpoint, &secret_key).unwrap();
+        let decoded = SignedCheckpoint::strict_decode(
+            &strict_serialize(&signed).unwrap()[..],
+        )
+        .unwrap();
+        assert_eq!(decoded.checkpoint.anchors, vec![anchor]);
+        decoded.verify(&[signed.signer]).unwrap();
+        assert!(matches!(
+            decoded.verify(&[]),
+            Err(CheckpointError::UntrustedSigner(_))
+        ));
+
+        let mut forged = decoded.clone();
+        forged.checkpoint.height = 0;
+        forged.checkpoint.anchors[0].height = 1;
+        assert!(matches!(
+            forged.verify(&[signed.signer]),
+            Err(CheckpointError::InvalidSignature)
+        ));
+        let forged =
+            SignedCheckpoint::sign(forged.checkpoint, &secret_key).unwrap();
+        assert!(matches!(
+            forged.verify(&[signed.signer]),
+            Err(CheckpointError::AboveCheckpoint { height: 1, .. })
+        ));
+    }
+}
@@ -13,14 +13,23 @@
 
 use core::fmt::Display;
 use core::str::FromStr;
+use serde::Deserialize;
 use std::path::PathBuf;
 
+use bitcoin::secp256k1::PublicKey;
 use internet2::zmqsocket::ZmqSocketAddr;
 use internet2::LocalNode;
 use lnpbp::Chain;
 use microservices::FileFormat;
+use rgb::ContractId;
 
+use super::{ReplicationKey, StoreType};
 use crate::constants::*;
+use crate::rpc::relay::RelayKey;
+use crate::util::config::{self, LayeredOpts};
+#[cfg(feature = "server")]
+use crate::util::daemon::{Credentials, LifecycleError};
+use crate::util::logging::{LogFilter, LogTarget};
 
 #[derive(Clap)]
 #[clap(
@@ -39,10 +48,16 @@ pub struct Opts {
     pub data_dir: String,
 
     /// Connection string to stash (exact format depends on used storage
-    /// engine)
+    /// engine); for `s3` storage it is the bucket URL
+    /// `s3://<host>[:<port>]/<bucket>[/<prefix>][?region=<region>]`
     #[clap(short, long, default_value = STASHD_STASH, env = "RGB_STASHD_STASH")]
     pub stash: String,
 
+    /// Stash storage backend to use (`disk`, `s3`, or `hammersbald` and
+    /// `sled` if compiled with `nosql` feature)
+    #[clap(long, default_value = "disk", env = "RGB_STASHD_STORE")]
+    pub store: StoreType,
+
     /// Connection string to indexing service
     #[clap(short, long, default_value = STASHD_INDEX, env = "RGB_STASHD_INDEX")]
     pub index: String,
@@ -55,17 +70,175 @@ pub struct Opts {
     )]
     pub rpc_endpoint: String,
 
-    /// Bitcoin network to use
+    /// Bitcoin network to use (`bitcoin`, `testnet`, `signet` or `regtest`);
+    /// private signet and regtest networks are given as
+    /// `signet:<genesis hash>` and `regtest:<genesis hash>`
     #[clap(short, long, default_value = RGB_NETWORK, env = "RGB_NETWORK")]
     pub network: Chain,
 
-    /// Electrum server to use to fecth Bitcoin transactions
+    /// Electrum server to use to fecth Bitcoin transactions [default: depends
+    /// on the network]
+    #[clap(long = "electrum", env = "RGB_ELECTRUM_SERVER")]
+    pub electrum_server: Option<String>,
+
+    /// Interval (in seconds) for running stash garbage collection; if not
+    /// given, garbage is collected only on `gc` request
+    #[clap(long, env = "RGB_STASHD_GC_INTERVAL")]
+    pub gc_interval: Option<u32>,
+
+    /// ZMQ socket address of the standby node to which all stash updates are
+    /// replicated
+    #[clap(long, env = "RGB_STASHD_REPLICA")]
+    pub replica: Option<String>,
+
+    /// ZMQ socket address on which the node, running as a standby, receives
+    /// stash updates from the primary node; updates are applied once the
+    /// primary, after its start or the start of the standby, has performed
+    /// the full resync
+    #[clap(long, env = "RGB_STASHD_STANDBY")]
+    pub standby: Option<String>,
+
+    /// Hex-encoded secret shared by the primary and standby nodes, which
+    /// authenticates replicated stash updates; required if either `replica`
+    /// or `standby` is given
+    #[clap(long, env = "RGB_STASHD_REPLICATION_KEY")]
+    pub replication_key: Option<String>,
+
+    /// Public keys (hex-encoded) of the parties (like asset registries)
+    /// trusted to sign checkpoints with contract data
     #[clap(
-        long = "electrum",
-        default_value = DEFAULT_ELECTRUM_ENDPOINT,
-        env = "RGB_ELECTRUM_SERVER"
+        long = "checkpoint-key",
+        env = "RGB_STASHD_CHECKPOINT_KEYS",
+        use_delimiter = true
     )]
-    pub electrum_server: String,
+    pub checkpoint_keys: Vec<String>,
+
+    /// ZMQ socket addresses of the peer stash daemons providing history of
+    /// the contracts imported from a checkpoint
+    #[clap(long, env = "RGB_STASHD_BACKFILL", use_delimiter = true)]
+    pub backfill: Vec<String>,
+
+    /// ZMQ socket address on which the node publishes updates of the stash
+    /// contracts to the relay peers
+    #[clap(long, env = "RGB_STASHD_RELAY")]
+    pub relay: Option<String>,
+
+    /// ZMQ socket addresses of the relay peers publishing contract updates
+    #[clap(
+        long = "relay-peer",
+        env = "RGB_STASHD_RELAY_PEERS",
+        use_delimiter = true
+    )]
+    pub relay_peers: Vec<String>,
+
+    /// Contracts which updates are received from the relay peers [default:
+    /// all contracts published by the peers]
+    #[clap(
+        long = "relay-contract",
+        env = "RGB_STASHD_RELAY_CONTRACTS",
+        use_delimiter = true
+    )]
+    pub relay_contracts: Vec<String>,
+
+    /// Hex-encoded secret shared by the relay peers, which authenticates
+    /// relayed contract updates; required if either `relay` or `relay-peer`
+    /// is given
+    #[clap(long, env = "RGB_STASHD_RELAY_KEY")]
+    pub relay_key: Option<String>,
+
+    /// Log target: `stderr`, `syslog`, `journald` or `json:<path>` for a
+    /// file with one JSON object per log record
+    #[clap(long, default_value = "stderr", env = "RGB_LOG")]
+    pub log: LogTarget,
+
+    /// Per-module log levels overriding the verbosity, as comma-separated
+    /// `<module>=<level>` directives (like `rgb_node::fungibled=debug`)
+    #[clap(long, env = "RGB_LOG_FILTER")]
+    pub log_filter: Option<LogFilter>,
+
+    /// Run in background, detached from the terminal
+    #[clap(long)]
+    pub daemon: bool,
+
+    /// Path to the file receiving id of the daemon process
+    #[clap(long, env = "RGB_STASHD_PID_FILE")]
+    pub pid_file: Option<String>,
+
+    /// Confine the daemon to the data directory, making it the filesystem
+    /// root (requires root privileges); all of the files used by the daemon
+    /// must be located in the data directory
+    #[clap(long)]
+    pub chroot: bool,
+
+    /// Unprivileged user the daemon switches to once it has bound its
+    /// sockets
+    #[clap(long, env = "RGB_USER")]
+    pub user: Option<String>,
+
+    /// Path to the configuration file; tunable settings are re-read from it
+    /// on SIGHUP or `reload-config` request [default:
+    /// {data_dir}/rgb-node.toml, if exists]
+    #[clap(long = "config", env = "RGB_CONFIG")]
+    pub config_file: Option<String>,
+
+    /// Print effective configuration in the configuration file format and
+    /// exit
+    #[clap(long)]
+    pub dump_config: bool,
+}
+
+impl LayeredOpts for Opts {
+    const DAEMON: &'static str = "stashd";
+    const KEYS: &'static [(&'static str, &'static str)] = &[
+        ("data-dir", "RGB_DATA_DIR"),
+        ("log", "RGB_LOG"),
+        ("log-filter", "RGB_LOG_FILTER"),
+        ("pid-file", "RGB_STASHD_PID_FILE"),
+        ("user", "RGB_USER"),
+        ("stash", "RGB_STASHD_STASH"),
+        ("store", "RGB_STASHD_STORE"),
+        ("index", "RGB_STASHD_INDEX"),
+        ("rpc", "RGB_STASHD_RPC"),
+        ("network", "RGB_NETWORK"),
+        ("electrum", "RGB_ELECTRUM_SERVER"),
+        ("gc-interval", "RGB_STASHD_GC_INTERVAL"),
+        ("replica", "RGB_STASHD_REPLICA"),
+        ("standby", "RGB_STASHD_STANDBY"),
+        ("replication-key", "RGB_STASHD_REPLICATION_KEY"),
+        ("checkpoint-key", "RGB_STASHD_CHECKPOINT_KEYS"),
+        ("backfill", "RGB_STASHD_BACKFILL"),
+        ("relay", "RGB_STASHD_RELAY"),
+        ("relay-peer", "RGB_STASHD_RELAY_PEERS"),
+        ("relay-contract", "RGB_STASHD_RELAY_CONTRACTS"),
+        ("relay-key", "RGB_STASHD_RELAY_KEY"),
+    ];
+
+    fn data_dir(&self) -> &str {
+        &self.data_dir
+    }
+
+    fn config_file(&self) -> Option<&str> {
+        self.config_file.as_deref()
+    }
+
+    fn verbose_mut(&mut self) -> &mut u8 {
+        &mut self.verbose
+    }
+}
+
+/// Settings which can be changed without restarting the daemon; connection
+/// endpoints, network and storage settings are not among them. Absent
+/// fields keep their current values.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize)]
+#[serde(crate = "serde_crate", rename_all = "kebab-case")]
+pub struct Tunables {
+    pub verbose: Option<u8>,
+    pub log_filter: Option<String>,
+    #[serde(rename = "electrum")]
+    pub electrum_server: Option<String>,
+    pub gc_interval: Option<u32>,
+    #[serde(rename = "checkpoint-key")]
+    pub checkpoint_keys: Option<Vec<String>>,
 }
 
 // We need config structure since not all of the parameters can be specified
@@ -76,27 +249,82 @@ pub struct Opts {
 pub struct Config {
     pub node_auth: LocalNode,
     pub verbose: u8,
+    pub log: LogTarget,
+    pub log_filter: LogFilter,
+    pub user: Option<String>,
+    /// User resolved before the daemon was confined to the data directory
+    #[cfg(feature = "server")]
+    pub credentials: Option<Credentials>,
     pub data_dir: PathBuf,
     pub stash: String,
+    pub store: StoreType,
     pub index: String,
     pub format: FileFormat,
     pub rpc_endpoint: ZmqSocketAddr,
     pub network: Chain,
     pub electrum_server: String,
+    pub gc_interval: Option<u32>,
+    pub replica: Option<ZmqSocketAddr>,
+    pub standby: Option<ZmqSocketAddr>,
+    pub replication_key: Option<ReplicationKey>,
+    pub checkpoint_keys: Vec<PublicKey>,
+    pub backfill: Vec<ZmqSocketAddr>,
+    pub relay: Option<ZmqSocketAddr>,
+    pub relay_peers: Vec<ZmqSocketAddr>,
+    pub relay_contracts: Vec<ContractId>,
+    pub relay_key: Option<RelayKey>,
+    pub config_file: Option<PathBuf>,
 }
 
 impl From<Opts> for Config {
     fn from(opts: Opts) -> Self {
         let mut me = Self {
             verbose: opts.verbose,
+            log: opts.log,
+            log_filter: opts.log_filter.unwrap_or_default(),
+            user: opts.user,
+            #[cfg(feature = "server")]
+            credentials: None,
             network: opts.network,
+            store: opts.store,
+            gc_interval: opts.gc_interval,
             ..Config::default()
         };
         me.data_dir = me.parse_param(opts.data_dir);
         me.stash = me.parse_param(opts.stash);
         me.index = me.parse_param(opts.index);
         me.rpc_endpoint = me.parse_param(opts.rpc_endpoint);
-        me.electrum_server = me.parse_param(opts.electrum_server);
+        me.electrum_server = match opts.electrum_server {
+            Some(server) => me.parse_param(server),
+            None => default_electrum_endpoint(&me.network).to_string(),
+        };
+        me.replica = opts.replica.map(|addr| me.parse_param(addr));
+        me.standby = opts.standby.map(|addr| me.parse_param(addr));
+        me.replication_key =
+            opts.replication_key.map(|key| me.parse_param(key));
+        me.checkpoint_keys = opts
+            .checkpoint_keys
+            .into_iter()
+            .map(|key| me.parse_param(key))
+            .collect();
+        me.backfill = opts
+            .backfill
+            .into_iter()
+            .map(|addr| me.parse_param(addr))
+            .collect();
+        me.relay = opts.relay.map(|addr| me.parse_param(addr));
+        me.relay_peers = opts
+            .relay_peers
+            .into_iter()
+            .map(|addr| me.parse_param(addr))
+            .collect();
+        me.relay_contracts = opts
+            .relay_contracts
+            .into_iter()
+            .map(|contract_id| me.parse_param(contract_id))
+            .collect();
+        me.relay_key = opts.relay_key.map(|key| me.parse_param(key));
+        me.config_file = opts.config_file.map(|path| me.parse_param(path));
         me
     }
 }
@@ -106,10 +334,16 @@ impl Default for Config {
         Self {
             node_auth: LocalNode::new(),
             verbose: 0,
+            log: LogTarget::Stderr,
+            log_filter: LogFilter::default(),
+            user: None,
+            #[cfg(feature = "server")]
+            credentials: None,
             data_dir: RGB_DATA_DIR
                 .parse()
                 .expect("Error in RGB_DATA_DIR constant value"),
             stash: STASHD_STASH.to_string(),
+            store: StoreType::default(),
             index: STASHD_INDEX.to_string(),
             format: FileFormat::Yaml,
             rpc_endpoint: STASHD_RPC_ENDPOINT
@@ -121,11 +355,123 @@ impl Default for Config {
             electrum_server: DEFAULT_ELECTRUM_ENDPOINT
                 .parse()
                 .expect("Error in DEFAULT_ELECTRUM_ENDPOINT constant value"),
+            gc_interval: None,
+            replica: None,
+            standby: None,
+            replication_key: None,
+            checkpoint_keys: vec![],
+            backfill: vec![],
+            relay: None,
+            relay_peers: vec![],
+            relay_contracts: vec![],
+            relay_key: None,
+            config_file: None,
         }
     }
 }
 
 impl Config {
+    /// Unprivileged user the daemon switches to, unless it was resolved
+    /// before confinement
+    #[cfg(feature = "server")]
+    pub fn credentials(&self) -> Result<Option<Credentials>, LifecycleError> {
+        match self.credentials {
+            Some(ref credentials) => Ok(Some(credentials.clone())),
+            None => self.user.as_deref().map(Credentials::resolve).transpose(),
+        }
+    }
+
+    /// Effective configuration with the configuration file keys
+    pub fn dump(&self) -> toml::value::Table {
+        let mut table = toml::value::Table::new();
+        table.insert(s!("verbose"), config::integer(self.verbose));
+        table.insert(s!("log"), config::value(&self.log));
+        if !self.log_filter.is_empty() {
+            table.insert(s!("log-filter"), config::value(&self.log_filter));
+        }
+        if let Some(ref user) = self.user {
+            table.insert(s!("user"), config::value(user));
+        }
+        table.insert(s!("data-dir"), config::value(self.data_dir.display()));
+        table.insert(s!("stash"), config::value(&self.stash));
+        table.insert(s!("store"), config::value(self.store));
+        table.insert(s!("index"), config::value(&self.index));
+        table.insert(s!("rpc"), config::value(&self.rpc_endpoint));
+        table.insert(s!("network"), config::value(&self.network));
+        table.insert(s!("electrum"), config::value(&self.electrum_server));
+        if let Some(interval) = self.gc_interval {
+            table.insert(s!("gc-interval"), config::integer(interval));
+        }
+        if let Some(ref addr) = self.replica {
+            table.insert(s!("replica"), config::value(addr));
+        }
+        if let Some(ref addr) = self.standby {
+            table.insert(s!("standby"), config::value(addr));
+        }
+        if let Some(ref key) = self.replication_key {
+            table.insert(s!("replication-key"), config::value(key));
+        }
+        if !self.checkpoint_keys.is_empty() {
+            table.insert(
+                s!("checkpoint-key"),
+                toml::Value::Array(
+                    self.checkpoint_keys.iter().map(config::value).collect(),
+                ),
+            );
+        }
+        if !self.backfill.is_empty() {
+            table.insert(
+                s!("backfill"),
+                toml::Value::Array(
+                    self.backfill.iter().map(config::value).collect(),
+                ),
+            );
+        }
+        if let Some(ref addr) = self.relay {
+            table.insert(s!("relay"), config::value(addr));
+        }
+        if !self.relay_peers.is_empty() {
+            table.insert(
+                s!("relay-peer"),
+                toml::Value::Array(
+                    self.relay_peers.iter().map(config::value).collect(),
+                ),
+            );
+        }
+        if !self.relay_contracts.is_empty() {
+            table.insert(
+                s!("relay-contract"),
+                toml::Value::Array(
+                    self.relay_contracts.iter().map(config::value).collect(),
+                ),
+            );
+        }
+        if let Some(ref key) = self.relay_key {
+            table.insert(s!("relay-key"), config::value(key));
+        }
+        table
+    }
+
+    /// Updates tunable settings with the values from the configuration file
+    pub fn apply(&mut self, tunables: Tunables) {
+        if let Some(verbose) = tunables.verbose {
+            self.verbose = verbose;
+        }
+        if let Some(filter) = tunables.log_filter {
+            self.log_filter = self.parse_param(filter);
+        }
+        if let Some(server) = tunables.electrum_server {
+            self.electrum_server = self.parse_param(server);
+        }
+        if let Some(interval) = tunables.gc_interval {
+            self.gc_interval = Some(interval);
+        }
+        if let Some(keys) = tunables.checkpoint_keys {
+            self.checkpoint_keys =
+                keys.into_iter().map(|key| self.parse_param(key)).collect();
+        }
+    }
+
     pub fn parse_param<T>(&self, param: String) -> T
     where
         T: FromStr,
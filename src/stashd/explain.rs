@@ -0,0 +1,319 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Step-by-step explanation of the consignment validation: the consignment
+//! nodes are walked in the order they are validated, and each of the
+//! validation failures and warnings is put next to the node, ancestor or
+//! witness transaction it concerns.
+
+use std::collections::BTreeSet;
+
+use bitcoin::Txid;
+use rgb::validation::{Failure, Status, Warning};
+use rgb::{Consignment, Node, NodeId};
+
+/// Explains validation status of the consignment as a list of steps
+pub fn explain(consignment: &Consignment, status: &Status) -> Vec<String> {
+    let genesis = &consignment.genesis;
+    let mut known = bset! { genesis.node_id() };
+    known.extend(
+        consignment
+            .state_transitions
+            .iter()
+            .map(|(_, t)| t.node_id()),
+    );
+    known.extend(consignment.state_extensions.iter().map(Node::node_id));
+
+    let mut explained = BTreeSet::new();
+    let mut steps = vec![format!(
+        "Genesis {} of contract {} using schema {}",
+        genesis.node_id(),
+        genesis.contract_id(),
+        genesis.schema_id()
+    )];
+    explain_node(&mut steps, &mut explained, status, genesis, None, &known);
+
+    for (anchor, transition) in &consignment.state_transitions {
+        steps.push(format!(
+            "Transition {} of type #{} anchored by {} in witness \
+             transaction {}",
+            transition.node_id(),
+            transition.transition_type().unwrap_or_default(),
+            anchor.anchor_id(),
+            anchor.txid
+        ));
+        explain_node(
+            &mut steps,
+            &mut explained,
+            status,
+            transition,
+            Some(anchor.txid),
+            &known,
+        );
+    }
+
+    for extension in &consignment.state_extensions {
+        steps.push(format!(
+            "Extension {} of type #{}",
+            extension.node_id(),
+            extension.extension_type().unwrap_or_default()
+        ));
+        explain_node(
+            &mut steps,
+            &mut explained,
+            status,
+            extension,
+            None,
+            &known,
+        );
+    }
+
+    for (node_id, endpoint) in &consignment.endpoints {
+        steps.push(format!("Endpoint {} assigned by {}", endpoint, node_id));
+        if !known.contains(node_id) {
+            steps.push(format!(
+                "  failed: endpoint node {} is absent from the consignment",
+                node_id
+            ));
+        }
+    }
+
+    for (index, failure) in status.failures.iter().enumerate() {
+        if !explained.contains(&index) {
+            steps.push(format!("Failure: {}", describe(failure)));
+        }
+    }
+    for warning in &status.warnings {
+        if let Warning::ExcessiveTransition(_) = warning {
+            steps.push(format!("Warning: {}", warning));
+        }
+    }
+    for info in &status.info {
+        steps.push(format!("Info: {}", info));
+    }
+    steps.push(format!(
+        "Result: {:?} with {} failures and {} warnings",
+        status.validity(),
+        status.failures.len(),
+        status.warnings.len()
+    ));
+    steps
+}
+
+fn explain_node(
+    steps: &mut Vec<String>,
+    explained: &mut BTreeSet<usize>,
+    status: &Status,
+    node: &impl Node,
+    witness: Option<Txid>,
+    known: &BTreeSet<NodeId>,
+) {
+    let node_id = node.node_id();
+    for (ancestor_id, rights) in node.parent_owned_rights() {
+        for (right_type, indexes) in rights {
+            for index in indexes {
+                let absent = if known.contains(ancestor_id) {
+                    ""
+                } else {
+                    " (absent from the consignment)"
+                };
+                steps.push(format!(
+                    "  closes right #{}:{} of {}{}",
+                    right_type, index, ancestor_id, absent
+                ));
+            }
+        }
+    }
+    if let Some(txid) = witness {
+        if status.unresolved_txids.contains(&txid) {
+            steps.push(format!(
+                "  witness transaction {} is not known to the resolver",
+                txid
+            ));
+        }
+    }
+    for (index, failure) in status.failures.iter().enumerate() {
+        if concerns(failure, node_id, witness) {
+            explained.insert(index);
+            steps.push(format!("  failed: {}", describe(failure)));
+        }
+    }
+    for warning in &status.warnings {
+        match warning {
+            Warning::EndpointDuplication(id, _)
+            | Warning::EndpointTransitionSealNotFound(id, _)
+                if *id == node_id =>
+            {
+                steps.push(format!("  warning: {}", warning))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Detects whether the failure is caused by the node or its witness
+/// transaction
+fn concerns(failure: &Failure, node_id: NodeId, witness: Option<Txid>) -> bool {
+    match failure {
+        Failure::SchemaUnknownExtensionType(id, _)
+        | Failure::SchemaUnknownTransitionType(id, _)
+        | Failure::SchemaUnknownFieldType(id, _)
+        | Failure::SchemaUnknownOwnedRightType(id, _)
+        | Failure::SchemaUnknownPublicRightType(id, _)
+        | Failure::SchemaDeniedScriptExtension(id)
+        | Failure::SchemaMetaOccurencesError(id, _, _)
+        | Failure::SchemaParentOwnedRightOccurencesError(id, _, _)
+        | Failure::SchemaOwnedRightOccurencesError(id, _, _)
+        | Failure::TransitionNotAnchored(id)
+        | Failure::TransitionNotInAnchor(id, _)
+        | Failure::TransitionParentWrongSealType { node_id: id, .. }
+        | Failure::TransitionParentWrongSeal { node_id: id, .. }
+        | Failure::TransitionParentConfidentialSeal { node_id: id, .. }
+        | Failure::TransitionParentIsNotWitnessInput { node_id: id, .. }
+        | Failure::ExtensionParentWrongValenciesType { node_id: id, .. }
+        | Failure::WitnessNoCommitment(id, _, _)
+        | Failure::ScriptFailure(id, _) => *id == node_id,
+        Failure::WitnessTransactionMissed(txid) => witness == Some(*txid),
+        _ => false,
+    }
+}
+
+/// Describes validation failure in human-readable form
+fn describe(failure: &Failure) -> String {
+    match failure {
+        Failure::SchemaUnknown(schema_id) => {
+            format!("schema {} is not known", schema_id)
+        }
+        Failure::SchemaUnknownTransitionType(_, ty) => {
+            format!("schema does not define transition type #{}", ty)
+        }
+        Failure::SchemaUnknownExtensionType(_, ty) => {
+            format!("schema does not define extension type #{}", ty)
+        }
+        Failure::SchemaUnknownFieldType(_, ty) => {
+            format!("schema does not define metadata field #{}", ty)
+        }
+        Failure::SchemaUnknownOwnedRightType(_, ty) => {
+            format!("schema does not define owned right #{}", ty)
+        }
+        Failure::SchemaMetaOccurencesError(_, ty, err) => {
+            format!("metadata field #{} violates schema: {:?}", ty, err)
+        }
+        Failure::SchemaParentOwnedRightOccurencesError(_, ty, err) => {
+            format!("closed rights #{} violate schema: {:?}", ty, err)
+        }
+        Failure::SchemaOwnedRightOccurencesError(_, ty, err) => {
+            format!("assigned rights #{} violate schema: {:?}", ty, err)
+        }
+        Failure::TransitionAbsent(id) => {
+            format!("ancestor transition {} is absent from the consignment", id)
+        }
+        Failure::TransitionNotAnchored(id) => {
+            format!("transition {} is not anchored", id)
+        }
+        Failure::TransitionNotInAnchor(id, anchor_id) => {
+            format!("anchor {} does not commit to transition {}", anchor_id, id)
+        }
+        Failure::TransitionParentWrongSealType {
+            ancestor_id,
+            assignment_type,
+            ..
+        } => format!(
+            "seals of right #{} of ancestor {} have wrong type",
+            assignment_type, ancestor_id
+        ),
+        Failure::TransitionParentWrongSeal {
+            ancestor_id,
+            assignment_type,
+            seal_index,
+            ..
+        } => format!(
+            "ancestor {} has no seal for right #{}:{}",
+            ancestor_id, assignment_type, seal_index
+        ),
+        Failure::TransitionParentConfidentialSeal {
+            ancestor_id,
+            assignment_type,
+            seal_index,
+            ..
+        } => format!(
+            "seal of right #{}:{} of ancestor {} is confidential and can't \
+             be checked against the witness transaction inputs",
+            assignment_type, seal_index, ancestor_id
+        ),
+        Failure::TransitionParentIsNotWitnessInput {
+            ancestor_id,
+            assignment_type,
+            seal_index,
+            outpoint,
+            ..
+        } => format!(
+            "seal {} of right #{}:{} of ancestor {} is not spent by the \
+             witness transaction",
+            outpoint, assignment_type, seal_index, ancestor_id
+        ),
+        Failure::ExtensionAbsent(id) => {
+            format!("ancestor extension {} is absent from the consignment", id)
+        }
+        Failure::WitnessTransactionMissed(txid) => {
+            format!("witness transaction {} is not found", txid)
+        }
+        Failure::WitnessNoCommitment(id, anchor_id, txid) => format!(
+            "witness transaction {} does not contain commitment of anchor {} \
+             to {}",
+            txid, anchor_id, id
+        ),
+        Failure::EndpointTransitionNotFound(id) => {
+            format!("endpoint node {} is absent from the consignment", id)
+        }
+        Failure::ScriptFailure(id, code) => {
+            format!("validation script of {} has failed with code {}", id, code)
+        }
+        failure => failure.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::OutPoint;
+    use lnpbp::Chain;
+
+    #[test]
+    fn test_explain_genesis() {
+        let (_, genesis) = rgb20::issue(
+            Chain::Regtest(Default::default()),
+            s!("TEST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(OutPoint::default(), 100)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let consignment = Consignment::with(genesis, vec![], vec![], vec![]);
+        let mut status = Status::default();
+        status
+            .failures
+            .push(Failure::TransitionAbsent(NodeId::default()));
+
+        let steps = explain(&consignment, &status);
+        assert!(steps[0].starts_with("Genesis "));
+        assert!(steps
+            .iter()
+            .any(|step| step.starts_with("Failure: ancestor")));
+        assert!(steps.last().unwrap().starts_with("Result: Invalid"));
+    }
+}
@@ -0,0 +1,127 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Mark-and-sweep garbage collection of the stash entries which are not
+//! connected to any of the known contract geneses.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use amplify::Wrapper;
+use bitcoin::hashes::Hash;
+use rgb::{Anchor, Node, NodeId};
+
+use super::index::Index;
+use super::storage::Store;
+use super::Runtime;
+use crate::error::ServiceErrorDomain;
+use crate::rpc::reply::GcStats;
+
+/// Ids of all parent nodes (both for owned and public rights)
+fn parent_ids(node: &impl Node) -> BTreeSet<NodeId> {
+    node.parent_owned_rights()
+        .keys()
+        .chain(node.parent_public_rights().keys())
+        .copied()
+        .collect()
+}
+
+/// Ids of the nodes committed to by the anchor
+fn committed_ids(anchor: &Anchor) -> BTreeSet<NodeId> {
+    anchor
+        .commitment
+        .commitments
+        .iter()
+        .filter(|commitment| commitment.protocol.is_some())
+        .map(|commitment| {
+            NodeId::from_inner(<NodeId as Wrapper>::Inner::from_inner(
+                commitment.commitment.into_inner(),
+            ))
+        })
+        .collect()
+}
+
+impl Runtime {
+    /// Removes all transitions and extensions which have no path through
+    /// their parents to any of the known geneses, and all anchors which do not
+    /// commit to any of the remaining transitions
+    pub(super) fn collect_garbage(
+        &mut self,
+    ) -> Result<GcStats, ServiceErrorDomain> {
+        debug!("Collecting stash garbage");
+
+        trace!("Reading stash node graph");
+        let mut parents = BTreeMap::<NodeId, BTreeSet<NodeId>>::new();
+        let transition_ids = self.storage.transition_ids()?;
+        for node_id in &transition_ids {
+            let transition = self.storage.transition(node_id)?;
+            parents.insert(*node_id, parent_ids(&transition));
+        }
+        let extension_ids = self.storage.extension_ids()?;
+        for node_id in &extension_ids {
+            let extension = self.storage.extension(node_id)?;
+            parents.insert(*node_id, parent_ids(&extension));
+        }
+
+        trace!("Marking nodes reachable from contract geneses");
+        let mut live = self
+            .storage
+            .contract_ids()?
+            .into_iter()
+            .map(|contract_id| NodeId::from_inner(contract_id.into_inner()))
+            .collect::<BTreeSet<_>>();
+        loop {
+            let marked = parents
+                .iter()
+                .filter(|(node_id, _)| !live.contains(*node_id))
+                .filter(|(_, parent_ids)| {
+                    parent_ids.iter().any(|id| live.contains(id))
+                })
+                .map(|(node_id, _)| *node_id)
+                .collect::<Vec<_>>();
+            if marked.is_empty() {
+                break;
+            }
+            live.extend(marked);
+        }
+
+        trace!("Sweeping unreachable nodes");
+        let mut stats = GcStats::default();
+        for node_id in transition_ids.iter().filter(|id| !live.contains(*id)) {
+            trace!("Removing orphaned transition {}", node_id);
+            self.storage.remove_transition(node_id)?;
+            self.indexer.remove_seals(*node_id)?;
+            stats.transitions += 1;
+        }
+        for node_id in extension_ids.iter().filter(|id| !live.contains(*id)) {
+            trace!("Removing orphaned extension {}", node_id);
+            self.storage.remove_extension(node_id)?;
+            self.indexer.remove_seals(*node_id)?;
+            stats.extensions += 1;
+        }
+
+        trace!("Sweeping anchors not committing to the remaining nodes");
+        for anchor_id in self.storage.anchor_ids()? {
+            let anchor = self.storage.anchor(&anchor_id)?;
+            if committed_ids(&anchor).iter().any(|id| live.contains(id)) {
+                continue;
+            }
+            trace!("Removing orphaned anchor {}", anchor_id);
+            self.storage.remove_anchor(&anchor_id)?;
+            self.indexer.remove_anchor(&anchor)?;
+            stats.anchors += 1;
+        }
+
+        info!("Stash garbage collected: removed {}", stats);
+        Ok(stats)
+    }
+}
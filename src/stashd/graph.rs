@@ -0,0 +1,136 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Export of the contract node graph in Graphviz DOT format for debugging:
+//! each genesis, state transition and extension is a graph node annotated
+//! with its type, witness txid and the number of owned rights assignments
+//! per right type; edges go from the node assigning an owned or public right
+//! to the node closing it.
+
+use core::fmt::Write;
+
+use bitcoin::Txid;
+use rgb::{Disclosure, Genesis, Node, NodeId};
+
+/// Number of node id characters shown in the node labels
+const LABEL_ID_LEN: usize = 16;
+
+/// Renders contract genesis, transitions and extensions from the disclosure
+/// as Graphviz DOT digraph
+pub fn dot(genesis: &Genesis, history: &Disclosure) -> String {
+    let contract_id = genesis.contract_id();
+    let mut dot = String::new();
+    // Writing to a string never fails
+    let _ = writeln!(dot, "digraph \"{}\" {{", contract_id);
+    let _ = writeln!(dot, "    rankdir=LR;");
+    let _ = writeln!(dot, "    node [shape=box, fontname=\"monospace\"];");
+
+    write_node(&mut dot, genesis, s!("genesis"), None);
+    for (anchor, transitions) in history.transitions().values() {
+        if let Some(transition) = transitions.get(&contract_id) {
+            let ty = transition
+                .transition_type()
+                .map(|ty| format!("transition #{}", ty))
+                .unwrap_or_else(|| s!("transition"));
+            write_node(&mut dot, transition, ty, Some(anchor.txid));
+        }
+    }
+    for extension in
+        history.extensions().get(&contract_id).into_iter().flatten()
+    {
+        let ty = extension
+            .extension_type()
+            .map(|ty| format!("extension #{}", ty))
+            .unwrap_or_else(|| s!("extension"));
+        write_node(&mut dot, extension, ty, None);
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+fn write_node(
+    dot: &mut String,
+    node: &impl Node,
+    ty: String,
+    witness: Option<Txid>,
+) {
+    let node_id = node.node_id();
+    let mut label = format!("{}\\n{}", ty, short_id(node_id));
+    if let Some(txid) = witness {
+        label.push_str(&format!("\\ntxid {}", txid));
+    }
+    for (right_type, assignments) in node.owned_rights().iter() {
+        label.push_str(&format!(
+            "\\nright #{}: {}",
+            right_type,
+            assignments.len()
+        ));
+    }
+    let _ = writeln!(dot, "    \"{}\" [label=\"{}\"];", node_id, label);
+
+    for (parent_id, rights) in node.parent_owned_rights() {
+        for (right_type, indexes) in rights {
+            for index in indexes {
+                let _ = writeln!(
+                    dot,
+                    "    \"{}\" -> \"{}\" [label=\"#{}:{}\"];",
+                    parent_id, node_id, right_type, index
+                );
+            }
+        }
+    }
+    for (parent_id, rights) in node.parent_public_rights() {
+        for right_type in rights {
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [label=\"#{}\", style=dashed];",
+                parent_id, node_id, right_type
+            );
+        }
+    }
+}
+
+fn short_id(node_id: NodeId) -> String {
+    node_id.to_string().chars().take(LABEL_ID_LEN).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::OutPoint;
+    use lnpbp::Chain;
+
+    #[test]
+    fn test_genesis_dot() {
+        let (_, genesis) = rgb20::issue(
+            Chain::Regtest(Default::default()),
+            s!("TEST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(OutPoint::default(), 100)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let dot = dot(&genesis, &Disclosure::default());
+        assert!(dot
+            .starts_with(&format!("digraph \"{}\" {{", genesis.contract_id())));
+        assert!(dot
+            .contains(&format!("\"{}\" [label=\"genesis", genesis.node_id())));
+        assert!(!dot.contains("->"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
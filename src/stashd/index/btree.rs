@@ -11,18 +11,19 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::io::{self, Read, Write};
 use std::path::PathBuf;
 
 use amplify::{IoError, Wrapper};
-use bitcoin::hashes::Hash;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{OutPoint, Txid};
 use lnpbp::strict_encoding::{StrictDecode, StrictEncode};
 use microservices::FileFormat;
-use rgb::{Anchor, AnchorId, NodeId};
+use rgb::{seal, Anchor, AnchorId, ContractId, NodeId};
 
-use super::Index;
+use super::{Index, SealRef, SealTransitions};
 use crate::error::{BootstrapError, ServiceErrorDomain};
 use crate::util::file::{file, FileMode};
 
@@ -50,6 +51,32 @@ struct BTreeIndexData {
     // #[cfg_attr(feature = "serde", serde(with =
     // "As::<BTreeMap<DisplayFromStr, DisplayFromStr>>"))]
     node_anchors: BTreeMap<NodeId, AnchorId>,
+
+    /// Anchors committing to each of the witness transactions
+    #[cfg_attr(feature = "serde", serde(default))]
+    txid_anchors: BTreeMap<Txid, BTreeSet<AnchorId>>,
+
+    /// Nodes defining and spending each of the seals
+    #[cfg_attr(feature = "serde", serde(default))]
+    seal_transitions: BTreeMap<seal::Confidential, SealTransitions>,
+
+    /// Nodes defining and spending each of the revealed seal outpoints
+    #[cfg_attr(feature = "serde", serde(default))]
+    outpoint_transitions: BTreeMap<OutPoint, SealTransitions>,
+
+    /// Ids of consignments accepted by the stash
+    #[cfg_attr(feature = "serde", serde(default))]
+    accepted: BTreeSet<sha256::Hash>,
+
+    /// Heights of the blocks mining witness transactions of the anchors
+    /// listed in the imported checkpoints
+    #[cfg_attr(feature = "serde", serde(default))]
+    checkpoint_anchors: BTreeMap<Txid, u32>,
+
+    /// Contracts imported from the checkpoints, which history is not
+    /// backfilled yet
+    #[cfg_attr(feature = "serde", serde(default))]
+    backfill: BTreeSet<ContractId>,
 }
 
 #[derive(Debug, Display, Error, From)]
@@ -176,6 +203,20 @@ impl BTreeIndex {
         Ok(())
     }
 
+    /// Detects index created before anchors were indexed by their txid, which
+    /// requires re-indexing of all stash anchors
+    pub fn requires_txid_reindex(&self) -> bool {
+        self.index.txid_anchors.is_empty()
+            && !self.index.node_anchors.is_empty()
+    }
+
+    /// Detects index created before seals were indexed, which requires
+    /// re-indexing of all stash transitions
+    pub fn requires_seal_reindex(&self) -> bool {
+        self.index.seal_transitions.is_empty()
+            && !self.index.node_anchors.is_empty()
+    }
+
     pub fn store(&self) -> Result<(), BTreeIndexError> {
         trace!("Saving index information ...");
         let _ = fs::remove_file(&self.config.index_filename());
@@ -210,6 +251,18 @@ impl Index for BTreeIndex {
             .ok_or(BTreeIndexError::AnchorNotFound)
     }
 
+    fn anchor_ids_by_txid(
+        &self,
+        txid: Txid,
+    ) -> Result<BTreeSet<AnchorId>, Self::Error> {
+        Ok(self
+            .index
+            .txid_anchors
+            .get(&txid)
+            .cloned()
+            .unwrap_or_default())
+    }
+
     fn index_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error> {
         for commitment in anchor
             .commitment
@@ -225,7 +278,166 @@ impl Index for BTreeIndex {
                 anchor.anchor_id(),
             );
         }
+        self.index
+            .txid_anchors
+            .entry(anchor.txid)
+            .or_default()
+            .insert(anchor.anchor_id());
+        self.store()?;
+        Ok(true)
+    }
+
+    fn remove_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error> {
+        let anchor_id = anchor.anchor_id();
+        let count = self.index.node_anchors.len();
+        self.index.node_anchors.retain(|_, id| *id != anchor_id);
+        let mut removed = count != self.index.node_anchors.len();
+        if let Some(anchors) = self.index.txid_anchors.get_mut(&anchor.txid) {
+            removed |= anchors.remove(&anchor_id);
+            if anchors.is_empty() {
+                self.index.txid_anchors.remove(&anchor.txid);
+            }
+        }
+        self.store()?;
+        Ok(removed)
+    }
+
+    fn is_accepted(&self, id: sha256::Hash) -> Result<bool, Self::Error> {
+        Ok(self.index.accepted.contains(&id))
+    }
+
+    fn mark_accepted(&mut self, id: sha256::Hash) -> Result<bool, Self::Error> {
+        let inserted = self.index.accepted.insert(id);
+        self.store()?;
+        Ok(inserted)
+    }
+
+    fn transitions_by_seal(
+        &self,
+        seal: seal::Confidential,
+    ) -> Result<SealTransitions, Self::Error> {
+        Ok(self
+            .index
+            .seal_transitions
+            .get(&seal)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn transitions_by_outpoint(
+        &self,
+        outpoint: OutPoint,
+    ) -> Result<SealTransitions, Self::Error> {
+        Ok(self
+            .index
+            .outpoint_transitions
+            .get(&outpoint)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn index_seals(
+        &mut self,
+        node_id: NodeId,
+        defines: &[SealRef],
+        spends: &[SealRef],
+    ) -> Result<bool, Self::Error> {
+        let index = &mut self.index;
+        for seal_ref in defines {
+            index
+                .seal_transitions
+                .entry(seal_ref.seal)
+                .or_default()
+                .defined_by
+                .insert(node_id);
+            if let Some(outpoint) = seal_ref.outpoint {
+                index
+                    .outpoint_transitions
+                    .entry(outpoint)
+                    .or_default()
+                    .defined_by
+                    .insert(node_id);
+            }
+        }
+        for seal_ref in spends {
+            index
+                .seal_transitions
+                .entry(seal_ref.seal)
+                .or_default()
+                .spent_by
+                .insert(node_id);
+            if let Some(outpoint) = seal_ref.outpoint {
+                index
+                    .outpoint_transitions
+                    .entry(outpoint)
+                    .or_default()
+                    .spent_by
+                    .insert(node_id);
+            }
+        }
         self.store()?;
         Ok(true)
     }
+
+    fn remove_seals(&mut self, node_id: NodeId) -> Result<bool, Self::Error> {
+        let mut removed = false;
+        for transitions in self
+            .index
+            .seal_transitions
+            .values_mut()
+            .chain(self.index.outpoint_transitions.values_mut())
+        {
+            removed |= transitions.defined_by.remove(&node_id);
+            removed |= transitions.spent_by.remove(&node_id);
+        }
+        self.index.seal_transitions.retain(|_, transitions| {
+            !transitions.defined_by.is_empty()
+                || !transitions.spent_by.is_empty()
+        });
+        self.index.outpoint_transitions.retain(|_, transitions| {
+            !transitions.defined_by.is_empty()
+                || !transitions.spent_by.is_empty()
+        });
+        self.store()?;
+        Ok(removed)
+    }
+
+    fn index_checkpoint(
+        &mut self,
+        contracts: &BTreeSet<ContractId>,
+        anchors: &BTreeMap<Txid, u32>,
+    ) -> Result<bool, Self::Error> {
+        let count =
+            self.index.backfill.len() + self.index.checkpoint_anchors.len();
+        self.index.backfill.extend(contracts);
+        self.index.checkpoint_anchors.extend(anchors);
+        self.store()?;
+        Ok(
+            self.index.backfill.len() + self.index.checkpoint_anchors.len()
+                > count,
+        )
+    }
+
+    fn checkpoint_height(
+        &self,
+        txid: Txid,
+    ) -> Result<Option<u32>, Self::Error> {
+        Ok(self.index.checkpoint_anchors.get(&txid).copied())
+    }
+
+    fn requires_backfill(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<bool, Self::Error> {
+        Ok(self.index.backfill.contains(&contract_id))
+    }
+
+    fn mark_backfilled(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<bool, Self::Error> {
+        let removed = self.index.backfill.remove(&contract_id);
+        self.store()?;
+        Ok(removed)
+    }
 }
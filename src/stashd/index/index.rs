@@ -11,10 +11,51 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use rgb::{Anchor, AnchorId, NodeId};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::hashes::sha256;
+use bitcoin::{OutPoint, Txid};
+use rgb::{seal, Anchor, AnchorId, ContractId, NodeId};
 
 use crate::error::ServiceErrorDomain;
 
+/// Reference to a single-use seal assigned or closed by a node; outpoint is
+/// present only if the seal is revealed and the witness transaction is known
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SealRef {
+    pub seal: seal::Confidential,
+    pub outpoint: Option<OutPoint>,
+}
+
+/// Nodes related to a single-use seal
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "kebab-case")
+)]
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Default,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+pub struct SealTransitions {
+    /// Genesis or state transitions assigning state to the seal
+    pub defined_by: BTreeSet<NodeId>,
+
+    /// State transitions closing the seal
+    pub spent_by: BTreeSet<NodeId>,
+}
+
 pub trait Index {
     type Error: ::std::error::Error + Into<ServiceErrorDomain>;
 
@@ -23,5 +64,68 @@ pub trait Index {
         tsid: NodeId,
     ) -> Result<AnchorId, Self::Error>;
 
+    fn anchor_ids_by_txid(
+        &self,
+        txid: Txid,
+    ) -> Result<BTreeSet<AnchorId>, Self::Error>;
+
     fn index_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error>;
+
+    /// Removes all index records referencing the anchor
+    fn remove_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error>;
+
+    fn transitions_by_seal(
+        &self,
+        seal: seal::Confidential,
+    ) -> Result<SealTransitions, Self::Error>;
+
+    fn transitions_by_outpoint(
+        &self,
+        outpoint: OutPoint,
+    ) -> Result<SealTransitions, Self::Error>;
+
+    /// Checks whether the consignment was already accepted by the stash
+    fn is_accepted(&self, id: sha256::Hash) -> Result<bool, Self::Error>;
+
+    /// Records consignment as accepted by the stash
+    fn mark_accepted(&mut self, id: sha256::Hash) -> Result<bool, Self::Error>;
+
+    /// Indexes seals defined and spent by the node (genesis or state
+    /// transition)
+    fn index_seals(
+        &mut self,
+        node_id: NodeId,
+        defines: &[SealRef],
+        spends: &[SealRef],
+    ) -> Result<bool, Self::Error>;
+
+    /// Removes the node from all seal index records
+    fn remove_seals(&mut self, node_id: NodeId) -> Result<bool, Self::Error>;
+
+    /// Records contracts imported from the trusted checkpoint, which history
+    /// has to be backfilled, and the heights of the blocks mining witness
+    /// transactions of the checkpoint anchors
+    fn index_checkpoint(
+        &mut self,
+        contracts: &BTreeSet<ContractId>,
+        anchors: &BTreeMap<Txid, u32>,
+    ) -> Result<bool, Self::Error>;
+
+    /// Height of the block mining the witness transaction, if the
+    /// transaction is listed in one of the imported checkpoints
+    fn checkpoint_height(&self, txid: Txid)
+        -> Result<Option<u32>, Self::Error>;
+
+    /// Checks whether the contract was imported from the checkpoint and its
+    /// history was not backfilled yet
+    fn requires_backfill(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<bool, Self::Error>;
+
+    /// Records contract history as backfilled
+    fn mark_backfilled(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<bool, Self::Error>;
 }
@@ -15,6 +15,6 @@ mod index;
 
 mod btree;
 
-pub(super) use index::Index;
+pub(super) use index::{Index, SealRef, SealTransitions};
 
 pub(super) use btree::{BTreeIndex, BTreeIndexConfig, BTreeIndexError};
@@ -0,0 +1,114 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeSet;
+
+use amplify::Wrapper;
+use bitcoin::hashes::Hash;
+use bitcoin::{OutPoint, Txid};
+use lnpbp::client_side_validation::CommitConceal;
+use lnpbp::seals::OutpointReveal;
+use rgb::{seal, Assignments, AtomicValue, Consignment, ContractId, Node};
+
+use crate::rpc::reply::ConsignmentSummary;
+
+/// Sums up revealed amounts assigned by the node to the given seals
+fn received_value(
+    node: &impl Node,
+    witness_txid: Option<Txid>,
+    seals: &BTreeSet<seal::Confidential>,
+    outpoints: &BTreeSet<OutPoint>,
+) -> AtomicValue {
+    node.owned_rights()
+        .values()
+        .filter_map(|assignments| match assignments {
+            Assignments::DiscreteFiniteField(set) => Some(set),
+            _ => None,
+        })
+        .flatten()
+        .filter(|state| {
+            seals.contains(&state.seal_definition_confidential())
+                || state
+                    .seal_definition()
+                    .and_then(|revealed| match revealed {
+                        seal::Revealed::TxOutpoint(reveal) => {
+                            Some(OutPoint::from(reveal))
+                        }
+                        seal::Revealed::WitnessVout { .. } => witness_txid
+                            .map(|txid| revealed.outpoint_reveal(txid).into()),
+                    })
+                    .map(|outpoint| outpoints.contains(&outpoint))
+                    .unwrap_or_default()
+        })
+        .filter_map(|state| state.assigned_state())
+        .fold(0, |sum, revealed| sum.saturating_add(revealed.value))
+}
+
+/// Summarizes consignment data without validating it. Value received by the
+/// caller is computed for the seals matching `reveal_outpoints`, if their
+/// amounts are revealed.
+pub fn inspect(
+    consignment: &Consignment,
+    reveal_outpoints: &[OutpointReveal],
+) -> ConsignmentSummary {
+    let seals = reveal_outpoints
+        .iter()
+        .map(CommitConceal::commit_conceal)
+        .collect::<BTreeSet<_>>();
+    let outpoints = reveal_outpoints
+        .iter()
+        .copied()
+        .map(OutPoint::from)
+        .collect::<BTreeSet<_>>();
+
+    let genesis = &consignment.genesis;
+    let mut contract_ids = bset! { genesis.contract_id() };
+    let mut value = received_value(genesis, None, &seals, &outpoints);
+    for (anchor, transition) in &consignment.state_transitions {
+        contract_ids.extend(
+            anchor
+                .commitment
+                .commitments
+                .iter()
+                .filter_map(|commitment| commitment.protocol)
+                .map(|protocol| {
+                    ContractId::from_inner(
+                        <ContractId as Wrapper>::Inner::from_inner(
+                            protocol.into_inner(),
+                        ),
+                    )
+                }),
+        );
+        value = value.saturating_add(received_value(
+            transition,
+            Some(anchor.txid),
+            &seals,
+            &outpoints,
+        ));
+    }
+    for extension in &consignment.state_extensions {
+        value = value.saturating_add(received_value(
+            extension, None, &seals, &outpoints,
+        ));
+    }
+
+    ConsignmentSummary {
+        contract_ids,
+        schema_id: genesis.schema_id(),
+        transitions: consignment.state_transitions.len() as u32,
+        extensions: consignment.state_extensions.len() as u32,
+        endpoints: consignment.endpoints.clone(),
+        txids: consignment.txids(),
+        received_value: value,
+    }
+}
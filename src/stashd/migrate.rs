@@ -0,0 +1,87 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use lnpbp::Chain;
+
+use super::storage::{migrate, MigrationStats, Storage, StoreType};
+use super::Config;
+use crate::constants::*;
+use crate::error::BootstrapError;
+
+#[derive(Clap)]
+#[clap(
+    name = "stash-migrate",
+    version = "0.1.0",
+    author = "Dr Maxim Orlovsky <orlovsky@pandoracore.com>",
+    about = "RGB stash migration tool copying stash data between storage backends; part of RGB suite"
+)]
+pub struct MigrateOpts {
+    /// Sets verbosity level; can be used multiple times to increase verbosity
+    #[clap(short, long, global = true, parse(from_occurrences))]
+    pub verbose: u8,
+
+    /// Data directory path
+    #[clap(short, long, default_value = RGB_DATA_DIR, env = "RGB_DATA_DIR")]
+    pub data_dir: String,
+
+    /// Bitcoin network to use (`bitcoin`, `testnet`, `signet` or `regtest`);
+    /// private signet and regtest networks are given as
+    /// `signet:<genesis hash>` and `regtest:<genesis hash>`
+    #[clap(short, long, default_value = RGB_NETWORK, env = "RGB_NETWORK")]
+    pub network: Chain,
+
+    /// Location of the source stash
+    #[clap(short, long, default_value = STASHD_STASH, env = "RGB_STASHD_STASH")]
+    pub stash: String,
+
+    /// Storage backend of the source stash
+    #[clap(long)]
+    pub from: StoreType,
+
+    /// Location of the target stash; defaults to the source stash location
+    #[clap(short, long)]
+    pub target: Option<String>,
+
+    /// Storage backend for the target stash
+    #[clap(long)]
+    pub to: StoreType,
+}
+
+impl MigrateOpts {
+    /// Copies all data from the source stash into the target one
+    pub fn exec(self) -> Result<MigrationStats, BootstrapError> {
+        let mut config = Config {
+            verbose: self.verbose,
+            network: self.network,
+            ..Config::default()
+        };
+        config.data_dir = config.parse_param(self.data_dir);
+        let source: String = config.parse_param(self.stash);
+        let target = self
+            .target
+            .map(|target| config.parse_param(target))
+            .unwrap_or_else(|| source.clone());
+        if self.from == self.to && source == target {
+            Err(BootstrapError::ArgParseError(s!(
+                "source and target stashes must differ"
+            )))?
+        }
+
+        let from = Storage::with(self.from, source.into())?;
+        let mut to = Storage::with(self.to, target.into())?;
+        migrate(&from, &mut to).map_err(|err| {
+            error!("Error migrating stash data: {}", err);
+            BootstrapError::StorageError
+        })
+    }
+}
@@ -11,12 +11,34 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+mod checkpoint;
 mod config;
+mod explain;
+mod gc;
+mod graph;
+mod inspect;
+mod migrate;
+mod optimize;
+mod relay;
+mod replication;
 mod runtime;
+mod snapshot;
 mod stash;
+mod streaming;
 
 pub(self) mod index;
 pub(self) mod storage;
 
-pub use config::{Config, Opts};
+pub use checkpoint::{
+    AnchorHeader, Checkpoint, CheckpointError, SignedCheckpoint, CHECKPOINT_TAG,
+};
+pub use config::{Config, Opts, Tunables};
+pub use migrate::MigrateOpts;
+pub use relay::{Relay, RELAY_SEEN_FRAMES};
+pub use replication::{ReplicationError, ReplicationKey, Replicator};
 pub use runtime::{main_with_config, Runtime};
+pub use snapshot::{ContractState, OwnedRightState, SupplyCounter};
+pub use storage::{
+    migrate, MigrationStats, Storage, StoreType, StoreTypeParseError,
+};
+pub use streaming::{ConsignmentFile, StreamingValidator};
@@ -0,0 +1,76 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Consignment size optimization pass, applied to consignments before they
+//! are returned to the client.
+
+use std::collections::BTreeSet;
+
+use lnpbp::client_side_validation::CommitConceal;
+use lnpbp::strict_encoding::strict_serialize;
+use rgb::{ConcealState, Consignment, ContractId, Node, NodeId};
+
+use crate::rpc::reply::ConsignmentStats;
+
+/// Size of the strict-encoded consignment
+fn consignment_size(consignment: &Consignment) -> u32 {
+    strict_serialize(consignment)
+        .map(|data| data.len() as u32)
+        .unwrap_or_default()
+}
+
+/// Removes duplicated ancestor nodes from the consignment and conceals all
+/// state which is not assigned to the consignment endpoints, as well as
+/// anchor commitments to the other contracts
+pub fn optimize(
+    consignment: &mut Consignment,
+    contract_id: ContractId,
+) -> ConsignmentStats {
+    let original_size = consignment_size(consignment);
+
+    let mut known = BTreeSet::<NodeId>::new();
+    let mut deduplicated = 0u32;
+    consignment.state_transitions.retain(|(_, transition)| {
+        let unique = known.insert(transition.node_id());
+        deduplicated += !unique as u32;
+        unique
+    });
+    consignment.state_extensions.retain(|extension| {
+        let unique = known.insert(extension.node_id());
+        deduplicated += !unique as u32;
+        unique
+    });
+
+    let endpoint_seals = consignment
+        .endpoints
+        .iter()
+        .map(|(_, endpoint)| endpoint.commit_conceal())
+        .collect::<Vec<_>>();
+    let mut concealed = 0usize;
+    for (anchor, transition) in &mut consignment.state_transitions {
+        concealed += anchor.conceal_except(contract_id);
+        concealed += transition.conceal_state_except(&endpoint_seals);
+    }
+    for extension in &mut consignment.state_extensions {
+        concealed += extension.conceal_state_except(&endpoint_seals);
+    }
+
+    let stats = ConsignmentStats {
+        original_size,
+        optimized_size: consignment_size(consignment),
+        deduplicated,
+        concealed: concealed as u32,
+    };
+    debug!("Consignment optimized: {}", stats);
+    stats
+}
@@ -0,0 +1,344 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Peer-to-peer relay of contract data between the stash daemons of
+//! different nodes, allowing e.g. issuer node to push contract updates to
+//! the distributor nodes. The publishing node sends each contract genesis,
+//! disclosure and accepted consignment over ZMQ PUB socket as a frame
+//! `contract_id || message || HMAC-SHA256(contract_id || message)`, where the
+//! HMAC is keyed with the relay secret shared by the nodes; the subscribing
+//! nodes filter the frames by the ids of the contracts they follow, drop the
+//! frames failing authentication and apply the rest through their own RPC
+//! socket. Consignments are validated before being accepted; anchors of the
+//! disclosed state transitions are checked against their witness
+//! transactions.
+//!
+//! Nodes may subscribe to each other: every node publishes the relayed
+//! update once more, but does not publish the same frame twice, so the
+//! update is not bounced between the nodes forever.
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::thread;
+
+use bitcoin::hashes::{sha256, Hash};
+use internet2::zmqsocket::{ZmqSocketAddr, ZmqType};
+use internet2::{
+    session, transport, CreateUnmarshaller, Session, TypedEnum, Unmarshall,
+    ZMQ_CONTEXT,
+};
+use rgb::validation::Validity;
+use rgb::{ContractId, Disclosure, Node};
+use wallet::resolvers::ElectrumTxResolver;
+
+use super::checkpoint::verify_anchor;
+use super::storage::Store;
+use super::Runtime;
+use crate::error::{BootstrapError, ServiceErrorDomain};
+use crate::rpc::relay::{frame, topic, unframe, Message, RelayKey};
+use crate::rpc::stash::{AcceptRequest, Request};
+use crate::rpc::Reply;
+use crate::util::compression::{decompress, req_rep};
+
+/// Number of the recently published frames remembered for suppressing
+/// repeated publication of the same update
+pub const RELAY_SEEN_FRAMES: usize = 1024;
+
+/// Publishing side of the relay
+pub struct Relay {
+    session: Box<dyn Session>,
+    key: RelayKey,
+    seen: HashSet<sha256::Hash>,
+    order: VecDeque<sha256::Hash>,
+}
+
+impl Relay {
+    /// Binds socket publishing contract updates, authenticated with the
+    /// `key`, to the relay peers
+    pub fn bind(
+        endpoint: &ZmqSocketAddr,
+        key: RelayKey,
+    ) -> Result<Self, BootstrapError> {
+        let socket = ZMQ_CONTEXT
+            .socket(ZmqType::Pub.socket_type())
+            .map_err(transport::Error::from)?;
+        socket
+            .bind(&endpoint.zmq_socket_string())
+            .map_err(transport::Error::from)?;
+        info!("Relaying contract updates to the peers on {}", endpoint);
+        Ok(Self {
+            session: Box::new(session::Raw::from_zmq_socket_unencrypted(
+                ZmqType::Pub,
+                socket,
+            )),
+            key,
+            seen: HashSet::new(),
+            order: VecDeque::with_capacity(RELAY_SEEN_FRAMES),
+        })
+    }
+
+    /// Publishes message under the contract id topic. Failures are only
+    /// logged, since the peers can't affect serving of the node clients.
+    pub fn publish(&mut self, contract_id: ContractId, message: &Message) {
+        let frame = frame(&self.key, contract_id, message);
+        let hash = sha256::Hash::hash(&frame);
+        if !self.seen.insert(hash) {
+            trace!("Relay frame {} is already published", hash);
+            return;
+        }
+        self.order.push_back(hash);
+        if self.order.len() > RELAY_SEEN_FRAMES {
+            if let Some(old) = self.order.pop_front() {
+                self.seen.remove(&old);
+            }
+        }
+        match self.session.send_raw_message(&frame) {
+            Ok(_) => trace!("Relayed {} for contract {}", message, contract_id),
+            Err(err) => warn!(
+                "Unable to relay {} for contract {}: {}",
+                message, contract_id, err
+            ),
+        }
+    }
+}
+
+/// Starts receiving updates of the contracts from the relay peers on a
+/// separate thread; each update authenticated with the `key` is applied by
+/// sending it to the stash RPC endpoint. If no contracts are given, updates
+/// of all contracts published by the peers are received.
+pub fn subscribe(
+    peers: &[ZmqSocketAddr],
+    contracts: &[ContractId],
+    key: RelayKey,
+    electrum_server: String,
+    rpc_endpoint: &ZmqSocketAddr,
+) -> Result<(), BootstrapError> {
+    let socket = ZMQ_CONTEXT
+        .socket(ZmqType::Sub.socket_type())
+        .map_err(transport::Error::from)?;
+    for peer in peers {
+        socket
+            .connect(&peer.zmq_socket_string())
+            .map_err(transport::Error::from)?;
+        info!("Receiving contract updates from relay peer {}", peer);
+    }
+    if contracts.is_empty() {
+        socket.set_subscribe(b"").map_err(transport::Error::from)?;
+    }
+    for contract_id in contracts {
+        socket
+            .set_subscribe(&topic(*contract_id))
+            .map_err(transport::Error::from)?;
+    }
+    let mut receiver =
+        session::Raw::from_zmq_socket_unencrypted(ZmqType::Sub, socket);
+    let mut stash = session::Raw::with_zmq_unencrypted(
+        ZmqType::Req,
+        rpc_endpoint,
+        None,
+        None,
+    )?;
+
+    thread::spawn(move || {
+        let unmarshaller = Message::create_unmarshaller();
+        loop {
+            let frame = match receiver.recv_raw_message() {
+                Ok(frame) => frame,
+                Err(err) => {
+                    error!("Error receiving relayed update: {}", err);
+                    continue;
+                }
+            };
+            let (contract_id, data) = match unframe(&key, &frame) {
+                Ok(update) => update,
+                Err(err) => {
+                    warn!("Dropping relay frame: {}", err);
+                    continue;
+                }
+            };
+            let message = match decompress(data.to_vec())
+                .map_err(ServiceErrorDomain::from)
+                .and_then(|raw| Ok(unmarshaller.unmarshall(&raw)?))
+            {
+                Ok(message) => message,
+                Err(err) => {
+                    warn!("Dropping relayed update: {}", err);
+                    continue;
+                }
+            };
+            debug!("Relayed {} for contract {}", message, contract_id);
+            if let Err(err) = apply(&mut stash, &message, &electrum_server) {
+                error!(
+                    "Relayed {} for contract {} is not applied: {}",
+                    message, contract_id, err
+                );
+            }
+        }
+    });
+    Ok(())
+}
+
+fn apply(
+    stash: &mut impl Session,
+    message: &Message,
+    electrum_server: &str,
+) -> Result<(), ServiceErrorDomain> {
+    let request = match message {
+        Message::Schema(schema) => Request::AddSchema(schema.clone()),
+        Message::Genesis(genesis) => Request::AddGenesis(genesis.clone()),
+        Message::Disclosure(disclosure) => {
+            let electrum = ElectrumTxResolver::new(electrum_server)
+                .map_err(|_| ServiceErrorDomain::Electrum)?;
+            for (anchor, transitions) in disclosure.transitions().values() {
+                for (contract_id, transition) in transitions {
+                    verify_anchor(
+                        &electrum,
+                        anchor,
+                        *contract_id,
+                        transition.node_id(),
+                    )?;
+                }
+            }
+            Request::Enclose(disclosure.clone())
+        }
+        Message::Consignment(consignment) => {
+            match stash_request(stash, Request::Validate(consignment.clone()))?
+            {
+                Reply::ValidationStatus(status)
+                    if status.validity() == Validity::Valid => {}
+                Reply::ValidationStatus(status) => {
+                    Err(ServiceErrorDomain::Internal(format!(
+                        "consignment validity is {:?}",
+                        status.validity()
+                    )))?
+                }
+                _ => Err(ServiceErrorDomain::Internal(s!(
+                    "unexpected reply to the consignment validation"
+                )))?,
+            }
+            Request::Accept(AcceptRequest {
+                consignment: consignment.clone(),
+                reveal_outpoints: vec![],
+            })
+        }
+    };
+    stash_request(stash, request).map(|_| ())
+}
+
+fn stash_request(
+    stash: &mut impl Session,
+    request: Request,
+) -> Result<Reply, ServiceErrorDomain> {
+    let raw = req_rep(stash, request.serialize())?;
+    match &*Reply::create_unmarshaller().unmarshall(&raw)? {
+        Reply::Failure(failure) => {
+            Err(ServiceErrorDomain::Internal(failure.to_string()))
+        }
+        reply => Ok(reply.clone()),
+    }
+}
+
+impl Runtime {
+    /// Publishes stash update, which was successfully applied, to the relay
+    /// peers
+    pub(super) fn relay_update(&mut self, request: &Request, reply: &Reply) {
+        let mut messages = vec![];
+        match (request, reply) {
+            (Request::AddGenesis(genesis), _) => {
+                let contract_id = genesis.contract_id();
+                match self.storage.schema(&genesis.schema_id()) {
+                    Ok(schema) => {
+                        messages.push((contract_id, Message::Schema(schema)))
+                    }
+                    Err(err) => warn!(
+                        "Schema of contract {} is not relayed: {}",
+                        contract_id, err
+                    ),
+                }
+                messages.push((contract_id, Message::Genesis(genesis.clone())));
+            }
+            (Request::Enclose(disclosure), _) => {
+                for (contract_id, disclosure) in split_disclosure(disclosure) {
+                    messages
+                        .push((contract_id, Message::Disclosure(disclosure)))
+                }
+            }
+            (Request::Accept(request), Reply::Success) => messages.push((
+                request.consignment.genesis.contract_id(),
+                Message::Consignment(request.consignment.clone()),
+            )),
+            _ => {}
+        }
+        if let Some(ref mut relay) = self.relay {
+            for (contract_id, message) in messages {
+                relay.publish(contract_id, &message);
+            }
+        }
+    }
+}
+
+/// Splits disclosure into the disclosures of the individual contracts, so
+/// the peers receive data only of the contracts they are subscribed to
+fn split_disclosure(
+    disclosure: &Disclosure,
+) -> BTreeMap<ContractId, Disclosure> {
+    let mut split = BTreeMap::<ContractId, Disclosure>::new();
+    for (anchor, transitions) in disclosure.transitions().values() {
+        for (contract_id, transition) in transitions {
+            split
+                .entry(*contract_id)
+                .or_default()
+                .insert_anchored_transitions(
+                    anchor.clone(),
+                    bmap! { *contract_id => transition.clone() },
+                );
+        }
+    }
+    for (contract_id, extensions) in disclosure.extensions() {
+        split
+            .entry(*contract_id)
+            .or_default()
+            .insert_extensions(*contract_id, extensions.clone());
+    }
+    split
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::rpc::relay::RelayError;
+
+    #[test]
+    fn test_relay_frame() {
+        let key = RelayKey::from_str(&"11".repeat(16)).unwrap();
+        let contract_id = ContractId::default();
+        let message = Message::Disclosure(Disclosure::default());
+        let frame = frame(&key, contract_id, &message);
+        let (id, data) = unframe(&key, &frame).unwrap();
+        assert_eq!(id, contract_id);
+        let raw = decompress(data.to_vec()).unwrap();
+        assert!(matches!(
+            &*Message::create_unmarshaller().unmarshall(&raw).unwrap(),
+            Message::Disclosure(_)
+        ));
+        assert_eq!(unframe(&key, &frame[..63]), Err(RelayError::Truncated));
+
+        let mut forged = frame.clone();
+        forged[0] ^= 1;
+        assert_eq!(unframe(&key, &forged), Err(RelayError::Unauthenticated));
+        let other = RelayKey::from_str(&"ff".repeat(16)).unwrap();
+        assert_eq!(unframe(&other, &frame), Err(RelayError::Unauthenticated));
+        assert_eq!(RelayKey::from_str("0011"), Err(RelayError::InvalidKey));
+    }
+}
@@ -0,0 +1,509 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Stash replication for warm-failover deployments. The primary stashd pushes
+//! each stash update it has applied to the standby node over ZMQ PUSH/PULL
+//! channel; the standby applies the update through its own RPC socket, so the
+//! standby stash is modified by exactly the same code as the primary one.
+//!
+//! Updates are framed as `epoch || seq || request || HMAC-SHA256(epoch || seq
+//! || request)`, where `epoch` is a random number chosen by the primary each
+//! time it starts, `seq` is a little-endian 64-bit sequence number within the
+//! epoch and the HMAC is keyed with the secret shared by both nodes. The
+//! standby drops frames failing authentication, replayed frames with already
+//! seen sequence numbers and frames of the epochs it has already left; gaps
+//! in the sequence numbers are reported. The stash of the standby missing
+//! some updates is restored with the full resync, which pushes all of the
+//! primary stash data preceded by a frame without request. Once the primary
+//! starts a new epoch, the standby drops its updates until the full resync.
+//! The standby keeps its replication progress in the data directory, so the
+//! frames it has already seen are rejected after its restart as well.
+
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use bitcoin::hashes::hex::{self, FromHex, ToHex};
+use bitcoin::hashes::{hmac, sha256, Hash, HashEngine};
+use bitcoin::secp256k1::rand::{thread_rng, RngCore};
+use internet2::zmqsocket::{ZmqSocketAddr, ZmqType};
+use internet2::{
+    session, transport, CreateUnmarshaller, Session, Unmarshall, ZMQ_CONTEXT,
+};
+use lnpbp::strict_encoding::{self, StrictDecode, StrictEncode};
+use rgb::{ContractId, Disclosure, Node};
+
+use super::index::Index;
+use super::storage::Store;
+use super::Runtime;
+use crate::constants::STASHD_REPLICATION_TIMEOUT;
+use crate::error::{BootstrapError, ServiceErrorDomain};
+use crate::rpc::stash::Request;
+use crate::rpc::Reply;
+use crate::util::compression::{compress, req_rep, RPC_COMPRESSION_THRESHOLD};
+use crate::util::file::{file, FileMode};
+
+/// Minimal length (in bytes) of the replication key
+pub const REPLICATION_KEY_MIN_LEN: usize = 16;
+
+/// Name of the file in the standby data directory keeping the replication
+/// progress
+pub const PROGRESS_FILENAME: &str = "replication.se";
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ReplicationError {
+    /// replication key must be a hex string encoding at least 16 bytes
+    #[from(hex::Error)]
+    InvalidKey,
+
+    /// replicated stash update is truncated
+    Truncated,
+
+    /// replicated stash update has invalid authentication code
+    Unauthenticated,
+}
+
+/// Secret shared by the primary and standby nodes, authenticating replicated
+/// stash updates
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ReplicationKey(Vec<u8>);
+
+impl Display for ReplicationKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.to_hex())
+    }
+}
+
+impl FromStr for ReplicationKey {
+    type Err = ReplicationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = Vec::<u8>::from_hex(s)?;
+        if key.len() < REPLICATION_KEY_MIN_LEN {
+            Err(ReplicationError::InvalidKey)?
+        }
+        Ok(ReplicationKey(key))
+    }
+}
+
+impl ReplicationKey {
+    fn mac(&self, data: &[u8]) -> hmac::Hmac<sha256::Hash> {
+        let mut engine = hmac::HmacEngine::<sha256::Hash>::new(&self.0);
+        engine.input(data);
+        hmac::Hmac::from_engine(engine)
+    }
+
+    /// Wraps serialized stash request into authenticated replication frame
+    pub fn seal(&self, epoch: u64, seq: u64, request: &[u8]) -> Vec<u8> {
+        let mut frame = epoch.to_le_bytes().to_vec();
+        frame.extend(&seq.to_le_bytes());
+        frame.extend(request);
+        let mac = self.mac(&frame);
+        frame.extend(&mac[..]);
+        frame
+    }
+
+    /// Checks authentication code of the replication frame and returns its
+    /// epoch, sequence number and serialized stash request
+    pub fn open<'a>(
+        &self,
+        frame: &'a [u8],
+    ) -> Result<(u64, u64, &'a [u8]), ReplicationError> {
+        let mac_len = sha256::Hash::LEN;
+        if frame.len() < 16 + mac_len {
+            Err(ReplicationError::Truncated)?
+        }
+        let (data, mac) = frame.split_at(frame.len() - mac_len);
+        // Constant-time comparison, not leaking the matching prefix length
+        let diff = self.mac(data)[..]
+            .iter()
+            .zip(mac)
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        if diff != 0 {
+            Err(ReplicationError::Unauthenticated)?
+        }
+        let mut epoch = [0u8; 8];
+        epoch.copy_from_slice(&data[..8]);
+        let mut seq = [0u8; 8];
+        seq.copy_from_slice(&data[8..16]);
+        Ok((
+            u64::from_le_bytes(epoch),
+            u64::from_le_bytes(seq),
+            &data[16..],
+        ))
+    }
+}
+
+/// Primary side of the replication channel
+pub struct Replicator {
+    session: Box<dyn Session>,
+    key: ReplicationKey,
+    epoch: u64,
+    seq: u64,
+}
+
+impl Replicator {
+    /// Connects to the standby node receiving stash updates at `endpoint`
+    pub fn with(
+        endpoint: &ZmqSocketAddr,
+        key: ReplicationKey,
+    ) -> Result<Self, BootstrapError> {
+        let socket = ZMQ_CONTEXT
+            .socket(ZmqType::Push.socket_type())
+            .map_err(transport::Error::from)?;
+        socket
+            .set_sndtimeo(STASHD_REPLICATION_TIMEOUT as i32 * 1000)
+            .map_err(transport::Error::from)?;
+        socket
+            .connect(&endpoint.zmq_socket_string())
+            .map_err(transport::Error::from)?;
+        let epoch = thread_rng().next_u64();
+        info!(
+            "Replicating stash updates to the standby node {} in epoch {:016x}",
+            endpoint, epoch
+        );
+        Ok(Self {
+            session: Box::new(session::Raw::from_zmq_socket_unencrypted(
+                ZmqType::Push,
+                socket,
+            )),
+            key,
+            epoch,
+            seq: 0,
+        })
+    }
+
+    /// Sends serialized stash request to the standby node. Failures are only
+    /// logged, since the primary must keep serving its clients when the
+    /// standby is unavailable.
+    pub fn push(&mut self, request: Vec<u8>) {
+        self.send(&compress(request, RPC_COMPRESSION_THRESHOLD));
+    }
+
+    /// Notifies the standby node that the full resync follows, so it resumes
+    /// applying the updates after the primary has started a new epoch
+    pub fn start_resync(&mut self) {
+        self.send(&[]);
+    }
+
+    fn send(&mut self, data: &[u8]) {
+        self.seq += 1;
+        let frame = self.key.seal(self.epoch, self.seq, data);
+        match self.session.send_raw_message(&frame) {
+            Ok(_) => trace!("Stash update #{} is replicated", self.seq),
+            Err(err) => warn!(
+                "Stash update #{} is not replicated: {}; standby node \
+                 requires full resync",
+                self.seq, err
+            ),
+        }
+    }
+}
+
+/// Replication epoch of the primary node followed by the standby
+#[derive(Clone, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+struct Progress {
+    epoch: Option<u64>,
+    last_seq: u64,
+    /// Updates are dropped since the primary has started a new epoch and
+    /// until it starts the full resync
+    awaiting_resync: bool,
+    /// Epochs left by the primary; their frames are never accepted again
+    retired: BTreeSet<u64>,
+}
+
+impl Progress {
+    /// Reads progress saved before the standby restart; starts from scratch
+    /// if nothing was saved yet
+    fn load(filename: &Path) -> Result<Self, strict_encoding::Error> {
+        if !filename.exists() {
+            return Ok(Self::default());
+        }
+        Self::strict_decode(file(filename, FileMode::Read)?)
+    }
+
+    /// Saves progress; the file is replaced atomically, so it is never left
+    /// half-written
+    fn save(&self, filename: &Path) -> Result<(), strict_encoding::Error> {
+        let staging = filename.with_extension("tmp");
+        self.strict_encode(file(&staging, FileMode::Create)?)?;
+        fs::rename(&staging, filename)?;
+        Ok(())
+    }
+
+    /// Checks authenticated frame against the replication progress, returning
+    /// whether its stash request has to be applied. Resync frames do not
+    /// carry any request.
+    fn accept(&mut self, epoch: u64, seq: u64, resync: bool) -> bool {
+        if self.retired.contains(&epoch) {
+            warn!(
+                "Dropping stash update #{} replayed from epoch {:016x}",
+                seq, epoch
+            );
+            return false;
+        }
+        if self.epoch != Some(epoch) {
+            if let Some(last_epoch) = self.epoch {
+                self.retired.insert(last_epoch);
+            }
+            warn!(
+                "Primary node has started replication epoch {:016x}; stash \
+                 updates are dropped until full resync",
+                epoch
+            );
+            self.epoch = Some(epoch);
+            self.awaiting_resync = true;
+        } else if seq <= self.last_seq {
+            warn!("Dropping replayed stash update #{}", seq);
+            return false;
+        } else if seq != self.last_seq + 1 && !self.awaiting_resync {
+            warn!(
+                "Stash updates #{}..#{} are lost; full resync is required",
+                self.last_seq + 1,
+                seq - 1
+            );
+        }
+        self.last_seq = seq;
+        if resync {
+            info!("Primary node has started full resync");
+            self.awaiting_resync = false;
+            return false;
+        }
+        if self.awaiting_resync {
+            trace!("Dropping stash update #{} preceding full resync", seq);
+        }
+        !self.awaiting_resync
+    }
+}
+
+/// Starts receiving stash updates from the primary node at `endpoint` on a
+/// separate thread. Each authenticated update is applied by sending it to
+/// the stash RPC endpoint. Replication progress is kept in `progress_file`.
+pub fn serve_standby(
+    endpoint: &ZmqSocketAddr,
+    key: ReplicationKey,
+    rpc_endpoint: &ZmqSocketAddr,
+    progress_file: PathBuf,
+) -> Result<(), BootstrapError> {
+    let mut progress = Progress::load(&progress_file).map_err(|err| {
+        error!(
+            "Unable to read replication progress from {}: {}",
+            progress_file.display(),
+            err
+        );
+        BootstrapError::StorageError
+    })?;
+    if let Some(epoch) = progress.epoch {
+        info!(
+            "Resuming replication epoch {:016x} after stash update #{}",
+            epoch, progress.last_seq
+        );
+    }
+    let socket = ZMQ_CONTEXT
+        .socket(ZmqType::Pull.socket_type())
+        .map_err(transport::Error::from)?;
+    socket
+        .bind(&endpoint.zmq_socket_string())
+        .map_err(transport::Error::from)?;
+    let mut receiver =
+        session::Raw::from_zmq_socket_unencrypted(ZmqType::Pull, socket);
+    let mut stash = session::Raw::with_zmq_unencrypted(
+        ZmqType::Req,
+        rpc_endpoint,
+        None,
+        None,
+    )?;
+    info!(
+        "Receiving stash updates from the primary node on {}",
+        endpoint
+    );
+
+    thread::spawn(move || {
+        loop {
+            let frame = match receiver.recv_raw_message() {
+                Ok(frame) => frame,
+                Err(err) => {
+                    error!("Error receiving stash update: {}", err);
+                    continue;
+                }
+            };
+            let (epoch, seq, data) = match key.open(&frame) {
+                Ok(update) => update,
+                Err(err) => {
+                    warn!("Dropping stash update: {}", err);
+                    continue;
+                }
+            };
+            let seen = progress.clone();
+            let accepted = progress.accept(epoch, seq, data.is_empty());
+            // Progress is saved before the update is applied, so the update
+            // is never applied twice, even if the standby restarts
+            if progress != seen {
+                if let Err(err) = progress.save(&progress_file) {
+                    error!(
+                        "Stash update #{} is not applied: unable to save \
+                         replication progress: {}",
+                        seq, err
+                    );
+                    continue;
+                }
+            }
+            if !accepted {
+                continue;
+            }
+            if let Err(err) = apply(&mut stash, data) {
+                error!("Stash update #{} is not applied: {}", seq, err);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn apply(
+    stash: &mut impl Session,
+    data: &[u8],
+) -> Result<(), ServiceErrorDomain> {
+    let raw = req_rep(stash, data.to_vec())?;
+    match &*Reply::create_unmarshaller().unmarshall(&raw)? {
+        Reply::Failure(failure) => {
+            Err(ServiceErrorDomain::Internal(failure.to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Runtime {
+    /// Stash requests recreating all of the stash data on the standby node
+    pub(super) fn resync_updates(
+        &self,
+    ) -> Result<Vec<Request>, ServiceErrorDomain> {
+        let mut updates = vec![];
+        for schema_id in self.storage.schema_ids()? {
+            updates.push(Request::AddSchema(self.storage.schema(&schema_id)?));
+        }
+        let contract_ids = self.storage.contract_ids()?;
+        for contract_id in &contract_ids {
+            updates
+                .push(Request::AddGenesis(self.storage.genesis(contract_id)?));
+        }
+
+        let mut anchored = BTreeMap::new();
+        for node_id in self.storage.transition_ids()? {
+            let anchor_id = self.indexer.anchor_id_by_transition_id(node_id)?;
+            let anchor = self.storage.anchor(&anchor_id)?;
+            let contract_id = contract_ids
+                .iter()
+                .find(|contract_id| anchor.validate(contract_id, &node_id))
+                .ok_or(ServiceErrorDomain::Stash)?;
+            anchored
+                .entry(anchor_id)
+                .or_insert_with(|| (anchor, BTreeMap::new()))
+                .1
+                .insert(*contract_id, self.storage.transition(&node_id)?);
+        }
+        let mut extensions = BTreeMap::<ContractId, Vec<_>>::new();
+        for node_id in self.storage.extension_ids()? {
+            let extension = self.storage.extension(&node_id)?;
+            let contract_id =
+                extension.contract_id().ok_or(ServiceErrorDomain::Stash)?;
+            extensions.entry(contract_id).or_default().push(extension);
+        }
+
+        for (_, (anchor, transitions)) in anchored {
+            let mut disclosure = Disclosure::default();
+            disclosure.insert_anchored_transitions(anchor, transitions);
+            updates.push(Request::Enclose(disclosure));
+        }
+        for (contract_id, extensions) in extensions {
+            let mut disclosure = Disclosure::default();
+            disclosure.insert_extensions(contract_id, extensions);
+            updates.push(Request::Enclose(disclosure));
+        }
+        Ok(updates)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_replication_frame() {
+        let key = ReplicationKey::from_str(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+        )
+        .unwrap();
+        let frame = key.seal(3, 7, b"update");
+        assert_eq!(key.open(&frame).unwrap(), (3, 7, &b"update"[..]));
+
+        let mut forged = frame.clone();
+        forged[8] = 8;
+        assert_eq!(key.open(&forged), Err(ReplicationError::Unauthenticated));
+        let other = ReplicationKey::from_str(&"ff".repeat(16)).unwrap();
+        assert_eq!(other.open(&frame), Err(ReplicationError::Unauthenticated));
+        assert_eq!(key.open(&frame[..40]), Err(ReplicationError::Truncated));
+        assert_eq!(
+            ReplicationKey::from_str("0011"),
+            Err(ReplicationError::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_replication_replay() {
+        let mut progress = Progress::default();
+        // Updates of the new epoch are applied only after the full resync
+        assert!(!progress.accept(1, 1, false));
+        assert!(!progress.accept(1, 2, true));
+        assert!(progress.accept(1, 3, false));
+        assert!(progress.accept(1, 5, false));
+        assert!(!progress.accept(1, 5, false));
+        assert!(!progress.accept(1, 4, false));
+        assert!(!progress.accept(1, 1, false));
+
+        // Primary restart
+        assert!(!progress.accept(2, 1, false));
+        assert!(!progress.accept(1, 6, false));
+        assert!(!progress.accept(2, 2, true));
+        assert!(progress.accept(2, 3, false));
+        assert!(!progress.accept(1, 7, true));
+        assert!(!progress.accept(2, 3, false));
+    }
+
+    #[test]
+    fn test_replication_progress_restart() {
+        let filename =
+            std::env::temp_dir().join("rgb-test-replication-progress.se");
+        let _ = fs::remove_file(&filename);
+        assert_eq!(Progress::load(&filename).unwrap(), Progress::default());
+
+        let mut progress = Progress::default();
+        assert!(!progress.accept(1, 1, true));
+        assert!(progress.accept(1, 2, false));
+        assert!(!progress.accept(2, 1, true));
+        assert!(progress.accept(2, 2, false));
+        progress.save(&filename).unwrap();
+
+        // Standby restart
+        let mut progress = Progress::load(&filename).unwrap();
+        assert!(!progress.accept(2, 2, false));
+        assert!(!progress.accept(2, 1, true));
+        assert!(!progress.accept(1, 3, false));
+        assert!(progress.accept(2, 3, false));
+        fs::remove_file(&filename).unwrap();
+    }
+}
@@ -11,40 +11,66 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use core::str::FromStr;
 use std::collections::BTreeSet;
+use std::fs;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
+use amplify::Wrapper;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::Txid;
 use internet2::zmqsocket::ZmqType;
 use internet2::{
-    session, transport, CreateUnmarshaller, PlainTranscoder, Session,
-    TypedEnum, Unmarshall, Unmarshaller,
+    session, CreateUnmarshaller, Session, TypedEnum, Unmarshall, Unmarshaller,
 };
 use microservices::node::TryService;
 use rgb::{
-    Anchor, Consignment, ContractId, Disclosure, Genesis, Node, NodeId, Schema,
+    Consignment, ContractId, Disclosure, Genesis, Node, NodeId, Schema,
     SchemaId, Stash,
 };
 use wallet::resolvers::ElectrumTxResolver;
 
+use super::explain::explain;
+use super::graph;
 use super::index::{BTreeIndex, Index};
-#[cfg(not(store_hammersbald))] // Default store
-use super::storage::{DiskStorage, DiskStorageConfig, Store};
-use super::Config;
+use super::inspect::inspect;
+use super::optimize::optimize;
+use super::relay::{self, Relay};
+use super::replication::{self, Replicator};
+use super::stash::reconsign_endpoints;
+use super::storage::{migrate, Storage, Store, StoreType};
+use super::{Config, ConsignmentFile, ContractState, StreamingValidator};
 use crate::error::{
-    BootstrapError, RuntimeError, ServiceError, ServiceErrorDomain,
-    ServiceErrorSource,
+    ApiErrorType, BootstrapError, RuntimeError, ServiceError,
+    ServiceErrorDomain, ServiceErrorSource,
+};
+use crate::rpc::limits::{self, Limited};
+use crate::rpc::stash::{
+    AcceptRequest, BackupRequest, ContractStateRequest, HistoryRequest,
+    ReconsignRequest, Request, TransferRequest,
 };
-use crate::rpc::stash::{AcceptRequest, Request, TransferRequest};
 use crate::rpc::{reply, Reply};
 use crate::stashd::index::BTreeIndexConfig;
-use crate::util::ToBech32Data;
+use crate::util::backup::BackupTarget;
+use crate::util::compression::{
+    compress, decompress, RPC_COMPRESSION_THRESHOLD,
+};
+#[cfg(feature = "server")]
+use crate::util::daemon;
+use crate::util::psbt::embed_transfer;
+use crate::util::{
+    anchor, config, logging, reload, shutdown, DataFormat, ToBech32Data,
+};
+use crate::validator;
 
 pub struct Runtime {
     /// Original configuration object
-    config: Config,
+    pub(super) config: Config,
 
-    /// Request-response API socket
-    rpc_server: session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
+    /// Request-response API session: ZMQ socket or in-memory channel for the
+    /// embedded mode
+    rpc_server: Box<dyn Session>,
 
     /// RGB Index: fast, mostly in-memory key-value indexing service.
     /// Must be exclusive for the current service
@@ -57,14 +83,23 @@ pub struct Runtime {
     /// large binary blob values. Fast read, slow write, no delete db.
     /// Must be exclusive for the current service and must not be used
     /// from anywhere else. The disk storage must be locked for exclusive
-    /// access.
-    #[cfg(not(store_hammersbald))] // Default store
-    pub(super) storage: DiskStorage,
-    #[cfg(all(store_hammersbald, not(any(store_disk))))]
-    pub(super) storage: HammersbaldStore,
+    /// access. Storage backend is selected with the config `store` option.
+    pub(super) storage: Storage,
+
+    /// Channel replicating stash updates to the standby node, if configured
+    replicator: Option<Replicator>,
+
+    /// Socket publishing contract updates to the relay peers, if configured
+    pub(super) relay: Option<Relay>,
 
     /// Unmarshaller instance used for parsing RPC request
     unmarshaller: Unmarshaller<Request>,
+
+    /// Time of the last garbage collection run
+    last_gc: Instant,
+
+    /// Reload generation of the current configuration
+    reload_generation: usize,
 }
 
 impl Runtime {
@@ -81,30 +116,107 @@ impl Runtime {
     }
 
     pub fn init(config: Config) -> Result<Self, BootstrapError> {
-        #[cfg(not(store_hammersbald))] // Default store
-        let storage = DiskStorage::new(DiskStorageConfig {
-            data_dir: PathBuf::from(config.stash.clone()),
-        })?;
-
-        let indexer = BTreeIndex::new(BTreeIndexConfig {
-            index_dir: PathBuf::from(config.index.clone()),
-            data_format: config.format,
-        })?;
-
         let session_rpc = session::Raw::with_zmq_unencrypted(
             ZmqType::Rep,
             &config.rpc_endpoint,
             None,
             None,
         )?;
+        Self::with_session(config, Box::new(session_rpc))
+    }
+
+    /// Constructs runtime serving requests from the provided session instead
+    /// of the ZMQ socket specified in the configuration
+    pub fn with_session(
+        config: Config,
+        rpc_server: Box<dyn Session>,
+    ) -> Result<Self, BootstrapError> {
+        let storage =
+            Storage::with(config.store, PathBuf::from(config.stash.clone()))?;
+        storage.check_integrity().map_err(|err| {
+            error!("Stash data are damaged: {}", err);
+            BootstrapError::StorageError
+        })?;
 
-        Ok(Self {
+        let mut indexer = BTreeIndex::new(BTreeIndexConfig {
+            index_dir: PathBuf::from(config.index.clone()),
+            data_format: config.format,
+        })?;
+        if indexer.requires_txid_reindex() {
+            info!("Re-indexing stash anchors by their witness txid");
+            let anchors = storage.anchor_ids().map_err(|err| {
+                error!("Unable to read stash anchors: {}", err);
+                BootstrapError::StorageError
+            })?;
+            for anchor_id in anchors {
+                let anchor = storage.anchor(&anchor_id).map_err(|err| {
+                    error!("Unable to read anchor {}: {}", anchor_id, err);
+                    BootstrapError::StorageError
+                })?;
+                indexer.index_anchor(&anchor)?;
+            }
+        }
+
+        let replicator = match (&config.replica, &config.replication_key) {
+            (Some(endpoint), Some(key)) => {
+                Some(Replicator::with(endpoint, key.clone())?)
+            }
+            (None, _) => None,
+            (Some(_), None) => Err(BootstrapError::ArgParseError(s!(
+                "stash replication requires `replication-key` to be set"
+            )))?,
+        };
+
+        let relay = match (&config.relay, &config.relay_key) {
+            (Some(endpoint), Some(key)) => {
+                Some(Relay::bind(endpoint, key.clone())?)
+            }
+            (None, _) => None,
+            (Some(_), None) => Err(BootstrapError::ArgParseError(s!(
+                "contract relay requires `relay-key` to be set"
+            )))?,
+        };
+
+        let mut runtime = Self {
             config,
-            rpc_server: session_rpc,
+            rpc_server,
             indexer,
             storage,
+            replicator,
+            relay,
             unmarshaller: Request::create_unmarshaller(),
-        })
+            last_gc: Instant::now(),
+            reload_generation: reload::generation(),
+        };
+        if runtime.indexer.requires_seal_reindex() {
+            info!("Re-indexing seals defined and spent by stash transitions");
+            runtime.reindex_seals().map_err(|err| {
+                error!("Unable to re-index stash seals: {}", err);
+                BootstrapError::StorageError
+            })?;
+        }
+
+        Ok(runtime)
+    }
+
+    fn reindex_seals(&mut self) -> Result<(), ServiceErrorDomain> {
+        for contract_id in self.storage.contract_ids()? {
+            let genesis = self.storage.genesis(&contract_id)?;
+            self.index_node_seals(&genesis, None)
+                .map_err(|_| ServiceErrorDomain::Stash)?;
+        }
+        for node_id in self.storage.transition_ids()? {
+            let transition = self.storage.transition(&node_id)?;
+            let witness_txid = self.witness_txid(node_id);
+            self.index_node_seals(&transition, witness_txid)
+                .map_err(|_| ServiceErrorDomain::Stash)?;
+        }
+        for node_id in self.storage.extension_ids()? {
+            let extension = self.storage.extension(&node_id)?;
+            self.index_node_seals(&extension, None)
+                .map_err(|_| ServiceErrorDomain::Stash)?;
+        }
+        Ok(())
     }
 }
 
@@ -112,7 +224,7 @@ impl TryService for Runtime {
     type ErrorType = RuntimeError;
 
     fn try_run_loop(mut self) -> Result<(), Self::ErrorType> {
-        loop {
+        while !shutdown::is_requested() {
             match self.run() {
                 Ok(_) => debug!("API request processing complete"),
                 Err(err) => {
@@ -120,23 +232,88 @@ impl TryService for Runtime {
                     Err(err)?;
                 }
             }
+            if self.reload_generation != reload::generation() {
+                if let Err(err) = self.reload() {
+                    error!("Unable to reload configuration: {}", err);
+                }
+            }
         }
+        self.shutdown()
     }
 }
 
 impl Runtime {
+    /// Re-reads tunable settings from the configuration file; if it fails
+    /// to load, the current configuration is kept
+    fn reload(&mut self) -> Result<(), ServiceErrorDomain> {
+        self.reload_generation = reload::generation();
+        let path = match self.config.config_file {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+        debug!("Reading configuration file {:?}", path);
+        let tunables = config::tunables(path, "stashd")
+            .map_err(|err| ServiceErrorDomain::Config(err.to_string()))?;
+        let verbose = self.config.verbose;
+        let log_filter = self.config.log_filter.clone();
+        self.config.apply(tunables);
+        if self.config.verbose != verbose
+            || self.config.log_filter != log_filter
+        {
+            logging::set_filter(self.config.verbose, &self.config.log_filter);
+        }
+        info!("Configuration reloaded");
+        Ok(())
+    }
+
+    /// Rejects contract data which belong to another bitcoin network
+    pub(super) fn check_network(
+        &self,
+        genesis: &Genesis,
+    ) -> Result<(), ServiceErrorDomain> {
+        if genesis.chain() != &self.config.network {
+            Err(ServiceErrorDomain::WrongNetwork {
+                expected: self.config.network.clone(),
+                found: genesis.chain().clone(),
+            })?
+        }
+        Ok(())
+    }
+
+    /// Persists the index and flushes the storage; sessions are closed when
+    /// the runtime is dropped
+    fn shutdown(self) -> Result<(), RuntimeError> {
+        info!("Stash runtime is shutting down");
+        self.indexer
+            .store()
+            .map_err(|err| RuntimeError::Internal(err.to_string()))?;
+        self.storage
+            .flush()
+            .map_err(|err| RuntimeError::Internal(err.to_string()))
+    }
+
     fn run(&mut self) -> Result<(), RuntimeError> {
         trace!("Awaiting for ZMQ RPC requests...");
         let raw = self.rpc_server.recv_raw_message()?;
         let reply = self.rpc_process(raw).unwrap_or_else(|err| err);
         trace!("Preparing ZMQ RPC reply: {:?}", reply);
-        let data = reply.serialize();
+        let data = compress(reply.serialize(), RPC_COMPRESSION_THRESHOLD);
         trace!(
             "Sending {} bytes back to the client over ZMQ RPC: {}",
             data.len(),
             data.to_bech32data()
         );
         self.rpc_server.send_raw_message(&data)?;
+
+        // Scheduled garbage collection is performed in between of the
+        // requests, since the API socket is processed in a blocking way
+        if let Some(interval) = self.config.gc_interval {
+            if self.last_gc.elapsed() >= Duration::from_secs(interval as u64) {
+                if let Err(err) = self.rpc_gc() {
+                    error!("Error collecting stash garbage: {}", err);
+                }
+            }
+        }
         Ok(())
     }
 
@@ -146,11 +323,26 @@ impl Runtime {
             raw.len(),
             raw.to_bech32data()
         );
-        let message = &*self.unmarshaller.unmarshall(&raw).map_err(|err| {
+        limits::check_size(&raw).map_err(|err| ServiceError {
+            domain: err.into(),
+            service: ServiceErrorSource::Stash,
+        })?;
+        let raw = decompress(raw).map_err(|err| {
+            ServiceError::from_rpc(ServiceErrorSource::Stash, err)
+        })?;
+        let message = self.unmarshaller.unmarshall(&raw).map_err(|err| {
             ServiceError::from_rpc(ServiceErrorSource::Stash, err)
         })?;
+        // Raw message data are kept only if they have to be replicated, so
+        // large consignments are not held in memory twice
+        let raw = self.replicator.as_ref().map(|_| raw);
+        let message = &*message;
+        message.check_limits().map_err(|err| ServiceError {
+            domain: err.into(),
+            service: ServiceErrorSource::Stash,
+        })?;
         debug!("Received ZMQ RPC request: {:?}", message);
-        Ok(match message {
+        let reply = match message {
             Request::ListSchemata() => self.rpc_list_schemata(),
             Request::ListGeneses() => self.rpc_list_geneses(),
             Request::AddGenesis(genesis) => self.rpc_add_genesis(genesis),
@@ -159,17 +351,98 @@ impl Runtime {
                 self.rpc_read_genesis(contract_id)
             }
             Request::ReadSchema(schema_id) => self.rpc_read_schema(schema_id),
+            Request::AnchorsByTxid(txid) => self.rpc_anchors_by_txid(*txid),
+            Request::ContractState(request) => {
+                self.rpc_contract_state(*request)
+            }
+            Request::ContractHistory(contract_id) => {
+                self.rpc_contract_history(*contract_id)
+            }
+            Request::ExportGraph(contract_id) => {
+                self.rpc_export_graph(*contract_id)
+            }
             Request::ReadTransitions(_) => unimplemented!(),
             Request::Transfer(consign) => self.rpc_transfer(consign),
+            Request::History(request) => self.rpc_history(request),
+            Request::Reconsign(request) => self.rpc_reconsign(request),
             Request::Validate(consign) => self.rpc_validate(consign),
+            Request::ValidateFile(filename) => self.rpc_validate_file(filename),
+            Request::ExplainFailure(consign) => {
+                self.rpc_explain_failure(consign)
+            }
             Request::Accept(merge) => self.rpc_accept(merge),
             Request::Enclose(disclosure) => self.rpc_enclose(disclosure),
+            Request::InspectConsignment(request) => {
+                self.rpc_inspect_consignment(request)
+            }
+            Request::Gc() => self.rpc_gc(),
+            Request::Resync() => self.rpc_resync(),
+            Request::ImportCheckpoint(filename) => {
+                self.rpc_import_checkpoint(filename)
+            }
+            Request::Backup(request) => self.rpc_backup(request),
+            Request::ReloadConfig() => self.rpc_reload_config(),
             Request::Forget(removal_list) => self.rpc_forget(removal_list),
         }
         .map_err(|err| ServiceError {
             domain: err,
             service: ServiceErrorSource::Stash,
-        })?)
+        })?;
+
+        // Only the requests which have been successfully applied to the stash
+        // are replicated
+        if let (Some(replicator), Some(raw)) = (self.replicator.as_mut(), raw) {
+            match message {
+                Request::AddSchema(_)
+                | Request::AddGenesis(_)
+                | Request::Accept(_)
+                | Request::Enclose(_)
+                | Request::Forget(_)
+                | Request::Gc() => replicator.push(raw),
+                // Standby does not need to re-validate the consignment, it
+                // only keeps its genesis
+                Request::Validate(consignment) => replicator.push(
+                    Request::AddGenesis(consignment.genesis.clone())
+                        .serialize(),
+                ),
+                _ => {}
+            }
+        }
+        if self.relay.is_some() {
+            self.relay_update(message, &reply);
+        }
+        Ok(reply)
+    }
+
+    /// Pushes stash update to the standby node, if one is configured
+    pub(super) fn replicate(&mut self, request: Request) {
+        if let Some(ref mut replicator) = self.replicator {
+            replicator.push(request.serialize());
+        }
+    }
+
+    fn rpc_reload_config(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got RELOAD_CONFIG");
+        self.reload()?;
+        Ok(Reply::Success)
+    }
+
+    fn rpc_resync(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got RESYNC");
+        if self.replicator.is_none() {
+            Err(ServiceErrorDomain::Config(s!(
+                "no standby node is configured for the stash replication"
+            )))?
+        }
+        let updates = self.resync_updates()?;
+        info!("Pushing {} updates to the standby node", updates.len());
+        if let Some(ref mut replicator) = self.replicator {
+            replicator.start_resync();
+            for update in updates {
+                replicator.push(update.serialize());
+            }
+        }
+        Ok(Reply::Success)
     }
 
     fn rpc_list_schemata(&mut self) -> Result<Reply, ServiceErrorDomain> {
@@ -198,7 +471,10 @@ impl Runtime {
         genesis: &Genesis,
     ) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got ADD_GENESIS {}", genesis);
+        self.check_network(genesis)?;
         self.storage.add_genesis(genesis)?;
+        self.index_node_seals(genesis, None)
+            .map_err(|_| ServiceErrorDomain::Stash)?;
         Ok(Reply::Success)
     }
 
@@ -211,6 +487,126 @@ impl Runtime {
         Ok(Reply::Genesis(genesis))
     }
 
+    fn rpc_gc(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got GC");
+        let stats = self.collect_garbage()?;
+        self.last_gc = Instant::now();
+        Ok(Reply::GcStats(stats))
+    }
+
+    fn rpc_import_checkpoint(
+        &mut self,
+        filename: &str,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got IMPORT_CHECKPOINT {}", filename);
+        let report = self.import_checkpoint(filename)?;
+        Ok(Reply::CheckpointImport(report))
+    }
+
+    fn rpc_backup(
+        &mut self,
+        request: &BackupRequest,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got {}", request);
+        let target = BackupTarget::from_str(&request.target)?;
+        // Stash data are copied into a staging storage first, so the backup
+        // has the same layout for all storage backends
+        let staging = self.config.data_dir.join("backup").join(&request.backup);
+        let _ = fs::remove_dir_all(&staging);
+        let mut copy = Storage::with(StoreType::Disk, staging.clone())
+            .map_err(|err| ServiceErrorDomain::Storage(err.to_string()))?;
+        let stats = migrate(&self.storage, &mut copy);
+        drop(copy);
+        let stored = stats.and_then(|stats| {
+            target
+                .store_dir(&request.backup, "stash", &staging)
+                .map(|_| stats)
+                .map_err(ServiceErrorDomain::from)
+        });
+        if let Err(err) = fs::remove_dir_all(&staging) {
+            warn!("Unable to remove backup staging directory: {}", err);
+        }
+        info!("Stash backup {} is created: {}", request.backup, stored?);
+        Ok(Reply::Success)
+    }
+
+    fn rpc_contract_history(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CONTRACT_HISTORY {}", contract_id);
+        let disclosure = self.contract_disclosure(contract_id)?;
+        Ok(Reply::Disclosure(disclosure))
+    }
+
+    fn rpc_export_graph(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got EXPORT_GRAPH {}", contract_id);
+        self.backfill(contract_id);
+        let genesis = self.storage.genesis(&contract_id)?;
+        let disclosure = self.contract_disclosure(contract_id)?;
+        Ok(Reply::Graph(graph::dot(&genesis, &disclosure)))
+    }
+
+    fn rpc_anchors_by_txid(
+        &mut self,
+        txid: Txid,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got ANCHORS_BY_TXID {}", txid);
+        let anchors = self
+            .indexer
+            .anchor_ids_by_txid(txid)?
+            .iter()
+            .map(|anchor_id| self.storage.anchor(anchor_id))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Reply::Anchors(anchors))
+    }
+
+    fn rpc_contract_state(
+        &mut self,
+        request: ContractStateRequest,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CONTRACT_STATE {}", request);
+        self.backfill(request.contract_id);
+        let genesis = self.storage.genesis(&request.contract_id)?;
+        let transitions = self
+            .storage
+            .transition_ids()?
+            .iter()
+            .map(|node_id| self.storage.transition(node_id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let extensions = self
+            .storage
+            .extension_ids()?
+            .iter()
+            .map(|node_id| self.storage.extension(node_id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let state = ContractState::with(&genesis, transitions, extensions);
+        trace!(
+            "Contract {} has {} unspent owned rights",
+            request.contract_id,
+            state.owned_rights.len()
+        );
+        let data = match request.format {
+            DataFormat::Yaml => serde_yaml::to_vec(&state)
+                .map_err(|err| ServiceErrorDomain::Internal(err.to_string()))?,
+            DataFormat::Json => serde_json::to_vec(&state)
+                .map_err(|err| ServiceErrorDomain::Internal(err.to_string()))?,
+            format => {
+                Err(ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+                    request: s!("contract_state"),
+                    argument: format.to_string(),
+                }))?
+            }
+        };
+        Ok(Reply::ContractState(reply::SyncFormat(
+            request.format,
+            data,
+        )))
+    }
+
     fn rpc_read_schema(
         &mut self,
         schema_id: &SchemaId,
@@ -228,25 +624,38 @@ impl Runtime {
 
         let mut transitions = request.other_transitions.clone();
         transitions.insert(request.contract_id, request.transition.clone());
+        for contract_id in transitions.keys() {
+            self.backfill(*contract_id);
+        }
 
         // Construct anchor
         let mut psbt = request.psbt.clone();
-        let (anchors, map) = Anchor::commit(
+        let (anchors, map) = anchor::commit(
             transitions
                 .iter()
                 .map(|(contract_id, ts)| (*contract_id, ts.node_id()))
                 .collect(),
             &mut psbt,
+            request.container,
         )
         .map_err(|err| ServiceErrorDomain::Anchor(format!("{}", err)))?;
         let anchor = anchors[*map
             .get(&request.contract_id)
             .expect("Core LNP/BP anchor commitment procedure is broken")]
         .clone();
+        embed_transfer(
+            &mut psbt,
+            &transitions,
+            &map.iter()
+                .map(|(contract_id, index)| {
+                    (*contract_id, anchors[*index].clone())
+                })
+                .collect(),
+        );
 
         // Prepare consignments: extract from stash storage the required data
         // and assemble them into a consignment
-        let consignment = self
+        let mut consignment = self
             .consign(
                 request.contract_id,
                 &request.transition,
@@ -254,6 +663,35 @@ impl Runtime {
                 &request.endpoints,
             )
             .map_err(|_| ServiceErrorDomain::Stash)?;
+        let mut optimization = bmap! {
+            request.contract_id => optimize(&mut consignment, request.contract_id)
+        };
+
+        // Consignments for other contracts sharing the same witness
+        // transaction, so the owner of multi-asset outputs can receive all of
+        // them at once
+        let mut other_consignments = bmap! {};
+        for (contract_id, endpoints) in &request.other_endpoints {
+            let transition = request
+                .other_transitions
+                .get(contract_id)
+                .ok_or_else(|| {
+                    ServiceErrorDomain::Api(ApiErrorType::MissedArgument {
+                        request: s!("transfer"),
+                        argument: format!("other_transitions[{}]", contract_id),
+                    })
+                })?;
+            let anchor = anchors[*map
+                .get(contract_id)
+                .expect("Core LNP/BP anchor commitment procedure is broken")]
+            .clone();
+            let mut consignment = self
+                .consign(*contract_id, transition, Some(&anchor), endpoints)
+                .map_err(|_| ServiceErrorDomain::Stash)?;
+            optimization
+                .insert(*contract_id, optimize(&mut consignment, *contract_id));
+            other_consignments.insert(*contract_id, consignment);
+        }
 
         // Prepare disclosure
         let mut disclosure = Disclosure::default();
@@ -279,16 +717,70 @@ impl Runtime {
 
         Ok(Reply::Transfer(reply::Transfer {
             consignment,
+            other_consignments,
             disclosure,
             witness: psbt,
+            optimization,
         }))
     }
 
+    fn rpc_history(
+        &mut self,
+        request: &HistoryRequest,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got HISTORY {}", request);
+        self.backfill(request.contract_id);
+        let consignment = self
+            .history(
+                request.contract_id,
+                &request.transition,
+                &request.endpoints,
+            )
+            .map_err(|_| ServiceErrorDomain::Stash)?;
+        Ok(Reply::Consignment(consignment))
+    }
+
+    fn rpc_reconsign(
+        &mut self,
+        request: &ReconsignRequest,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got RECONSIGN {}", request);
+        let contract_id = request.contract_id;
+        self.backfill(contract_id);
+        for anchor_id in self.indexer.anchor_ids_by_txid(request.txid)? {
+            let anchor = self.storage.anchor(&anchor_id)?;
+            let node_id =
+                match anchor.commitment.commitments.iter().find(|commitment| {
+                    commitment.protocol.map(|protocol| protocol.into_inner())
+                        == Some(contract_id.into_inner().into_inner())
+                }) {
+                    Some(commitment) => NodeId::from_inner(
+                        <NodeId as Wrapper>::Inner::from_inner(
+                            commitment.commitment.into_inner(),
+                        ),
+                    ),
+                    None => continue,
+                };
+            if !self.storage.has_transition(&node_id)? {
+                continue;
+            }
+            let transition = self.storage.transition(&node_id)?;
+            let endpoints = reconsign_endpoints(&transition, &request.payees);
+            let mut consignment = self
+                .consign(contract_id, &transition, Some(&anchor), &endpoints)
+                .map_err(|_| ServiceErrorDomain::Stash)?;
+            optimize(&mut consignment, contract_id);
+            return Ok(Reply::Consignment(consignment));
+        }
+        Ok(Reply::Nothing)
+    }
+
     fn rpc_validate(
         &mut self,
         consignment: &Consignment,
     ) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got VALIDATE CONSIGNMENT");
+        self.check_network(&consignment.genesis)?;
 
         let schema = self
             .storage()
@@ -298,13 +790,79 @@ impl Runtime {
         // [VALIDATION]: Validate genesis node against the scheme
         let electrum = ElectrumTxResolver::new(&self.config.electrum_server)
             .map_err(|_| ServiceErrorDomain::Electrum)?;
-        let validation_status = consignment.validate(&schema, &electrum);
+        let validation_status =
+            validator::validate_with_schema(consignment, &schema, &electrum);
 
         self.storage.add_genesis(&consignment.genesis)?;
 
         Ok(Reply::ValidationStatus(validation_status))
     }
 
+    fn rpc_explain_failure(
+        &mut self,
+        consignment: &Consignment,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got EXPLAIN_FAILURE");
+        self.check_network(&consignment.genesis)?;
+
+        let schema = self
+            .storage()
+            .schema(&consignment.genesis.schema_id())
+            .map_err(|err| ServiceErrorDomain::Storage(err.to_string()))?;
+        let electrum = ElectrumTxResolver::new(&self.config.electrum_server)
+            .map_err(|_| ServiceErrorDomain::Electrum)?;
+        let status =
+            validator::validate_with_schema(consignment, &schema, &electrum);
+
+        // Unlike `Validate`, the stash is left untouched: the consignment
+        // is only diagnosed
+        let steps = explain(consignment, &status);
+        for step in &steps {
+            trace!("{}", step);
+        }
+        Ok(Reply::ValidationReport(reply::ValidationReport {
+            status,
+            steps,
+        }))
+    }
+
+    fn rpc_validate_file(
+        &mut self,
+        filename: &str,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got VALIDATE_FILE {}", filename);
+        let malformed = |err: lnpbp::strict_encoding::Error| {
+            ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+                request: s!("validate_file"),
+                argument: err.to_string(),
+            })
+        };
+        let consignment = ConsignmentFile::open(filename).map_err(malformed)?;
+        let genesis = consignment.genesis();
+        self.check_network(genesis)?;
+        debug!(
+            "Consignment file contains {} state transitions and extensions",
+            consignment.len()
+        );
+
+        let schema = self
+            .storage()
+            .schema(&genesis.schema_id())
+            .map_err(|err| ServiceErrorDomain::Storage(err.to_string()))?;
+        let electrum = ElectrumTxResolver::new(&self.config.electrum_server)
+            .map_err(|_| ServiceErrorDomain::Electrum)?;
+        let validation_status =
+            StreamingValidator::validate(&schema, &consignment, &electrum)
+                .map_err(malformed)?;
+
+        self.storage.add_genesis(genesis)?;
+        // Standby node can't access the file, so only the genesis is
+        // replicated, the same way as for the `Validate` request
+        self.replicate(Request::AddGenesis(genesis.clone()));
+
+        Ok(Reply::ValidationStatus(validation_status))
+    }
+
     fn rpc_accept(
         &mut self,
         accept_req: &AcceptRequest,
@@ -313,13 +871,32 @@ impl Runtime {
 
         let known_seals = &accept_req.reveal_outpoints;
         let consignment = &accept_req.consignment;
+        self.check_network(&consignment.genesis)?;
+
+        let consignment_id = sha256::Hash::from_inner(
+            consignment.id().into_inner().into_inner(),
+        );
+        if self.indexer.is_accepted(consignment_id)? {
+            debug!("Consignment {} is already accepted", consignment_id);
+            return Ok(Reply::AlreadyAccepted(consignment_id));
+        }
 
         self.accept(consignment, known_seals)
             .map_err(|_| ServiceErrorDomain::Stash)?;
+        self.indexer.mark_accepted(consignment_id)?;
 
         Ok(Reply::Success)
     }
 
+    fn rpc_inspect_consignment(
+        &mut self,
+        request: &AcceptRequest,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got INSPECT CONSIGNMENT");
+        let summary = inspect(&request.consignment, &request.reveal_outpoints);
+        Ok(Reply::ConsignmentSummary(summary))
+    }
+
     fn rpc_enclose(
         &mut self,
         disclosure: &Disclosure,
@@ -348,8 +925,56 @@ impl Runtime {
 }
 
 pub fn main_with_config(config: Config) -> Result<(), BootstrapError> {
+    #[cfg(feature = "server")]
+    shutdown::install(
+        config.rpc_endpoint.clone(),
+        Request::ListSchemata().serialize(),
+    )?;
+    let standby = match (&config.standby, &config.replication_key) {
+        (Some(endpoint), Some(key)) => Some((endpoint.clone(), key.clone())),
+        (None, _) => None,
+        (Some(_), None) => Err(BootstrapError::ArgParseError(s!(
+            "stash replication requires `replication-key` to be set"
+        )))?,
+    };
+    let relay_key = match (config.relay_peers.is_empty(), &config.relay_key) {
+        (true, _) => None,
+        (false, Some(key)) => Some(key.clone()),
+        (false, None) => Err(BootstrapError::ArgParseError(s!(
+            "contract relay requires `relay-key` to be set"
+        )))?,
+    };
+    let rpc_endpoint = config.rpc_endpoint.clone();
+    let progress_file = config.data_dir.join(replication::PROGRESS_FILENAME);
+    let electrum_server = config.electrum_server.clone();
+    let relay_peers = config.relay_peers.clone();
+    let relay_contracts = config.relay_contracts.clone();
+    #[cfg(feature = "server")]
+    let credentials = config.credentials()?;
     let runtime = Runtime::init(config)?;
-    runtime.run_or_panic("Stashd runtime");
-
-    unreachable!()
+    if let Some((endpoint, key)) = standby {
+        replication::serve_standby(
+            &endpoint,
+            key,
+            &rpc_endpoint,
+            progress_file,
+        )?;
+    }
+    if let Some(key) = relay_key {
+        relay::subscribe(
+            &relay_peers,
+            &relay_contracts,
+            key,
+            electrum_server,
+            &rpc_endpoint,
+        )?;
+    }
+    #[cfg(feature = "server")]
+    if let Some(ref credentials) = credentials {
+        daemon::drop_privileges(credentials)?;
+    }
+    if let Err(err) = runtime.try_run_loop() {
+        panic!("Stashd runtime has failed with error {}", err);
+    }
+    Ok(())
 }
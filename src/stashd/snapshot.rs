@@ -0,0 +1,228 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Snapshots of the contract state known to the stash, which may be used by
+//! block explorers and other services presenting contract data.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_with::As;
+use std::collections::{BTreeMap, BTreeSet};
+
+use rgb::schema::OwnedRightType;
+use rgb::{
+    seal, Assignments, AtomicValue, ContractId, Extension, Genesis, Node,
+    NodeId, SchemaId, Transition,
+};
+
+#[cfg(feature = "serde")]
+use crate::util::AtomicValueString;
+
+/// Owned right which is not spent by any of the state transitions known to
+/// the stash
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "kebab-case")
+)]
+#[display("{node_id}/{owned_right_type}/{index}@{seal}")]
+pub struct OwnedRightState {
+    /// Node defining the owned right
+    pub node_id: NodeId,
+
+    /// Type of the owned right according to the contract schema
+    pub owned_right_type: OwnedRightType,
+
+    /// Index of the assignment within the node owned rights of this type
+    pub index: u16,
+
+    /// Current holder of the right; always concealed
+    pub seal: seal::Confidential,
+
+    /// Assigned amount, if the right has discrete state revealed to the stash
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "As::<Option<AtomicValueString>>")
+    )]
+    pub amount: Option<AtomicValue>,
+}
+
+/// Supply counters for a single owned right type
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "kebab-case")
+)]
+pub struct SupplyCounter {
+    /// Number of unspent assignments
+    pub assignments: usize,
+
+    /// Total of the revealed amounts of unspent assignments
+    #[cfg_attr(feature = "serde", serde(with = "As::<AtomicValueString>"))]
+    pub known_amount: AtomicValue,
+
+    /// Number of unspent assignments with amounts concealed from the stash
+    pub confidential_amounts: usize,
+}
+
+/// Full state of a contract known to the stash
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "kebab-case")
+)]
+#[display("contract_state({contract_id}, ...)")]
+pub struct ContractState {
+    pub contract_id: ContractId,
+    pub schema_id: SchemaId,
+
+    /// Number of known state transitions of the contract
+    pub transitions: usize,
+
+    /// Number of known state extensions of the contract
+    pub extensions: usize,
+
+    /// Unspent owned rights
+    pub owned_rights: Vec<OwnedRightState>,
+
+    /// Supply counters per owned right type
+    pub supply: BTreeMap<OwnedRightType, SupplyCounter>,
+}
+
+impl ContractState {
+    /// Constructs contract state from the genesis and stash nodes. Nodes
+    /// which are not connected to the genesis through their parents (i.e.
+    /// belonging to other contracts) are ignored.
+    pub fn with(
+        genesis: &Genesis,
+        transitions: Vec<Transition>,
+        extensions: Vec<Extension>,
+    ) -> Self {
+        let mut known = bset! { genesis.node_id() };
+        let mut transitions = transitions;
+        let mut extensions = extensions;
+        let mut nodes: Vec<&dyn Node> = vec![genesis];
+        let mut contract_transitions = vec![];
+        let mut contract_extensions = vec![];
+        loop {
+            let count = known.len();
+            let is_connected = |node: &dyn Node| {
+                node.parent_owned_rights()
+                    .keys()
+                    .chain(node.parent_public_rights().keys())
+                    .any(|id| known.contains(id))
+            };
+            let (connected, rest): (Vec<_>, Vec<_>) = transitions
+                .into_iter()
+                .partition(|transition| is_connected(transition));
+            transitions = rest;
+            contract_transitions.extend(connected);
+            let (connected, rest): (Vec<_>, Vec<_>) = extensions
+                .into_iter()
+                .partition(|extension| is_connected(extension));
+            extensions = rest;
+            contract_extensions.extend(connected);
+            known.extend(contract_transitions.iter().map(Transition::node_id));
+            known.extend(contract_extensions.iter().map(Extension::node_id));
+            if known.len() == count {
+                break;
+            }
+        }
+        nodes.extend(contract_transitions.iter().map(|node| node as &dyn Node));
+        nodes.extend(contract_extensions.iter().map(|node| node as &dyn Node));
+
+        let spent = nodes
+            .iter()
+            .flat_map(|node| node.parent_owned_rights().iter())
+            .flat_map(|(node_id, rights)| {
+                rights.iter().flat_map(move |(owned_right_type, indexes)| {
+                    indexes
+                        .iter()
+                        .map(move |index| (*node_id, *owned_right_type, *index))
+                })
+            })
+            .collect::<BTreeSet<_>>();
+
+        let mut owned_rights = vec![];
+        let mut supply = BTreeMap::<OwnedRightType, SupplyCounter>::new();
+        for node in &nodes {
+            let node_id = node.node_id();
+            for (owned_right_type, assignments) in node.owned_rights() {
+                for (index, (seal, amount)) in
+                    owned_states(assignments).into_iter().enumerate()
+                {
+                    let index = index as u16;
+                    if spent.contains(&(node_id, *owned_right_type, index)) {
+                        continue;
+                    }
+                    let counter = supply.entry(*owned_right_type).or_default();
+                    counter.assignments += 1;
+                    match amount {
+                        Some(amount) => {
+                            counter.known_amount =
+                                counter.known_amount.saturating_add(amount)
+                        }
+                        None if assignments.is_discrete_state() => {
+                            counter.confidential_amounts += 1
+                        }
+                        None => {}
+                    }
+                    owned_rights.push(OwnedRightState {
+                        node_id,
+                        owned_right_type: *owned_right_type,
+                        index,
+                        seal,
+                        amount,
+                    });
+                }
+            }
+        }
+
+        Self {
+            contract_id: genesis.contract_id(),
+            schema_id: genesis.schema_id(),
+            transitions: contract_transitions.len(),
+            extensions: contract_extensions.len(),
+            owned_rights,
+            supply,
+        }
+    }
+}
+
+fn owned_states(
+    assignments: &Assignments,
+) -> Vec<(seal::Confidential, Option<AtomicValue>)> {
+    match assignments {
+        Assignments::Declarative(set) => set
+            .iter()
+            .map(|assignment| (assignment.seal_definition_confidential(), None))
+            .collect(),
+        Assignments::DiscreteFiniteField(set) => set
+            .iter()
+            .map(|assignment| {
+                (
+                    assignment.seal_definition_confidential(),
+                    assignment.assigned_state().map(|state| state.value),
+                )
+            })
+            .collect(),
+        Assignments::CustomData(set) => set
+            .iter()
+            .map(|assignment| (assignment.seal_definition_confidential(), None))
+            .collect(),
+    }
+}
@@ -11,18 +11,22 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use std::collections::{BTreeSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
+use amplify::Wrapper;
 use bitcoin::hashes::Hash;
+use bitcoin::{OutPoint, Txid};
+use lnpbp::client_side_validation::CommitConceal;
 use lnpbp::lnpbp4::ProtocolId;
 use lnpbp::seals::OutpointReveal;
+use rgb::schema::OwnedRightType;
 use rgb::{
-    Anchor, Assignments, ConcealState, Consignment, ContractId, Disclosure,
-    Extension, Genesis, IntoRevealed, Node, NodeId, SchemaId, SealEndpoint,
-    Stash, Transition,
+    seal, Anchor, Assignments, ConcealState, Consignment, ContractId,
+    Disclosure, Extension, ExtensionData, Genesis, IntoRevealed, Node, NodeId,
+    SchemaId, SealDefinition, SealEndpoint, Stash, Transition, TransitionData,
 };
 
-use super::index::Index;
+use super::index::{Index, SealRef};
 use super::storage::Store;
 use super::Runtime;
 
@@ -31,6 +35,7 @@ use super::Runtime;
 pub enum Error {
     /// Storage error
     #[from(super::storage::DiskStorageError)]
+    #[from(crate::error::ServiceErrorDomain)]
     StorageError,
 
     /// Index error
@@ -48,6 +53,10 @@ pub enum Error {
     /// Trying to import data related to an unknown contract {0}. Please import
     /// genesis for that contract first.
     UnknownContract(ContractId),
+
+    /// Seal closed by the state transition is already closed by another
+    /// transition {0} known to the stash
+    SealAlreadyClosed(NodeId),
 }
 
 pub struct DumbIter<T>(std::marker::PhantomData<T>);
@@ -59,6 +68,243 @@ impl<T> Iterator for DumbIter<T> {
     }
 }
 
+/// Collects seals assigned by the node. If `filter` is given, only the
+/// assignments with the listed owned right types and indexes are used.
+fn node_seals(
+    node: &impl Node,
+    witness_txid: Option<Txid>,
+    filter: Option<&BTreeMap<OwnedRightType, Vec<u16>>>,
+) -> Vec<SealRef> {
+    let mut seals = vec![];
+    for (right_type, assignments) in node.owned_rights() {
+        let indexes = match filter.map(|filter| filter.get(right_type)) {
+            None => None,
+            Some(None) => continue,
+            Some(Some(indexes)) => Some(indexes),
+        };
+        for (index, seal) in
+            assignments.all_seal_definitions().into_iter().enumerate()
+        {
+            let index = index as u16;
+            if indexes.map(|i| !i.contains(&index)).unwrap_or_default() {
+                continue;
+            }
+            let outpoint = match assignments.seal_definition(index) {
+                Ok(Some(seal::Revealed::TxOutpoint(reveal))) => {
+                    Some(OutPoint::from(reveal))
+                }
+                Ok(Some(revealed)) => witness_txid
+                    .map(|txid| OutPoint::from(revealed.outpoint_reveal(txid))),
+                _ => None,
+            };
+            seals.push(SealRef { seal, outpoint });
+        }
+    }
+    seals
+}
+
+impl Runtime {
+    /// Returns witness transaction id for the node, if the node anchor is
+    /// known to the stash
+    pub(super) fn witness_txid(&self, node_id: NodeId) -> Option<Txid> {
+        let anchor_id =
+            self.indexer.anchor_id_by_transition_id(node_id).ok()?;
+        self.storage
+            .anchor(&anchor_id)
+            .ok()
+            .map(|anchor| anchor.txid)
+    }
+
+    /// Adds seals defined by the node and seals of its parent nodes closed by
+    /// it to the stash index. Parent nodes unknown to the stash are skipped.
+    pub(super) fn index_node_seals(
+        &mut self,
+        node: &impl Node,
+        witness_txid: Option<Txid>,
+    ) -> Result<(), Error> {
+        let defines = node_seals(node, witness_txid, None);
+        let spends = self.closed_seals(node);
+        self.indexer
+            .index_seals(node.node_id(), &defines, &spends)?;
+        Ok(())
+    }
+
+    /// Seals of the parent nodes closed by the node; parent nodes unknown to
+    /// the stash are skipped
+    fn closed_seals(&self, node: &impl Node) -> Vec<SealRef> {
+        let mut spends = vec![];
+        for (parent_id, rights) in node.parent_owned_rights() {
+            let parent_txid = self.witness_txid(*parent_id);
+            if let Ok(transition) = self.storage.transition(parent_id) {
+                spends.extend(node_seals(
+                    &transition,
+                    parent_txid,
+                    Some(rights),
+                ));
+            } else if let Ok(extension) = self.storage.extension(parent_id) {
+                spends.extend(node_seals(
+                    &extension,
+                    parent_txid,
+                    Some(rights),
+                ));
+            } else if let Ok(genesis) = self
+                .storage
+                .genesis(&ContractId::from_inner(parent_id.into_inner()))
+            {
+                spends.extend(node_seals(&genesis, None, Some(rights)));
+            }
+        }
+        spends
+    }
+
+    /// Checks with the seal index that none of the seals closed by the
+    /// transition was already closed by another transition known to the
+    /// stash, and that none of their outpoints is spent by another witness
+    /// transaction. The stash learns about the transitions once their
+    /// witness transactions are published, so the conflicting consignment
+    /// would be invalid.
+    fn check_double_spend(
+        &self,
+        transition: &Transition,
+        witness_txid: Txid,
+    ) -> Result<(), Error> {
+        let node_id = transition.node_id();
+        for seal_ref in self.closed_seals(transition) {
+            let spent_by = self.indexer.transitions_by_seal(seal_ref.seal)?;
+            if let Some(other) =
+                spent_by.spent_by.into_iter().find(|id| *id != node_id)
+            {
+                error!("Seal {} is already closed by {}", seal_ref.seal, other);
+                Err(Error::SealAlreadyClosed(other))?
+            }
+            let outpoint = match seal_ref.outpoint {
+                Some(outpoint) => outpoint,
+                None => continue,
+            };
+            let spent_by = self.indexer.transitions_by_outpoint(outpoint)?;
+            for other in spent_by.spent_by {
+                match self.witness_txid(other) {
+                    Some(txid) if other != node_id && txid != witness_txid => {
+                        error!(
+                            "Outpoint {} is already spent by {} in {}",
+                            outpoint, other, txid
+                        );
+                        Err(Error::SealAlreadyClosed(other))?
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Collects anchored state transitions and extensions of the contract
+    /// preceding the node, down to the contract genesis, which is not
+    /// included
+    pub(super) fn ancestors(
+        &self,
+        genesis: &Genesis,
+        node: &impl Node,
+    ) -> Result<(TransitionData, ExtensionData), Error> {
+        trace!("Collecting other involved nodes");
+        let mut state_transitions = vec![];
+        let mut state_extensions = vec![];
+        let mut sources = VecDeque::<NodeId>::new();
+        sources
+            .extend(node.parent_owned_rights().into_iter().map(|(id, _)| id));
+        sources
+            .extend(node.parent_public_rights().into_iter().map(|(id, _)| id));
+        trace!("Node list for consignment: {:#?}", sources);
+        let mut visited = BTreeSet::<NodeId>::new();
+        while let Some(node_id) = sources.pop_front() {
+            if node_id.into_inner() == genesis.contract_id().into_inner() {
+                continue;
+            }
+            // Ancestors shared by several nodes are added only once
+            if !visited.insert(node_id) {
+                continue;
+            }
+            trace!(
+                "Getting anchor id for node/protocol id {} from the index",
+                &ProtocolId::from((*node_id).into_inner())
+            );
+            let anchor_id = self.indexer.anchor_id_by_transition_id(node_id)?;
+            trace!("Retrieving anchor with id {}", anchor_id);
+            let anchor = self.storage.anchor(&anchor_id)?;
+            trace!("Anchor data: {:#?}", anchor);
+
+            trace!("Extending source data with the ancestors");
+            // TODO #162: (new) Improve this logic
+            match (
+                self.storage.transition(&node_id),
+                self.storage.extension(&node_id),
+            ) {
+                (Ok(mut transition), Err(_)) => {
+                    transition.conceal_state();
+                    state_transitions.push((anchor, transition.clone()));
+                    sources.extend(
+                        transition
+                            .parent_owned_rights()
+                            .into_iter()
+                            .map(|(id, _)| id),
+                    );
+                    sources.extend(
+                        transition
+                            .parent_public_rights()
+                            .into_iter()
+                            .map(|(id, _)| id),
+                    );
+                }
+                (Err(_), Ok(mut extension)) => {
+                    extension.conceal_state();
+                    state_extensions.push(extension.clone());
+                    sources.extend(
+                        extension
+                            .parent_owned_rights()
+                            .into_iter()
+                            .map(|(id, _)| id),
+                    );
+                    sources.extend(
+                        extension
+                            .parent_public_rights()
+                            .into_iter()
+                            .map(|(id, _)| id),
+                    );
+                }
+                _ => Err(Error::StorageError)?,
+            }
+        }
+        Ok((state_transitions, state_extensions))
+    }
+
+    /// Creates consignment with the contract history preceding the state
+    /// transition, which is not anchored yet. Such partial consignment is
+    /// completed by the party anchoring the transition.
+    pub(super) fn history(
+        &self,
+        contract_id: ContractId,
+        transition: &Transition,
+        endpoints: &BTreeSet<SealEndpoint>,
+    ) -> Result<Consignment, Error> {
+        debug!(
+            "Preparing history of contract {} for node {}",
+            contract_id,
+            transition.node_id()
+        );
+        let genesis = self.storage.genesis(&contract_id)?;
+        let (state_transitions, state_extensions) =
+            self.ancestors(&genesis, transition)?;
+        let node_id = transition.node_id();
+        let endpoints = endpoints.iter().map(|op| (node_id, *op)).collect();
+        Ok(Consignment::with(
+            genesis,
+            endpoints,
+            state_transitions,
+            state_extensions,
+        ))
+    }
+}
+
 impl Stash for Runtime {
     type Error = Error;
     type GenesisIterator = DumbIter<Genesis>;
@@ -142,6 +388,7 @@ impl Stash for Runtime {
             node.as_any().downcast_ref::<Transition>().clone()
         {
             let anchor = anchor.ok_or(Error::AnchorParameterIsRequired)?;
+            self.check_double_spend(transition, anchor.txid)?;
             state_transitions.push((anchor.clone(), transition.clone()));
         } else if let Some(extension) =
             node.as_any().downcast_ref::<Extension>().clone()
@@ -151,67 +398,9 @@ impl Stash for Runtime {
             Err(Error::GenesisNode)?;
         }
 
-        trace!("Collecting other involved nodes");
-        let mut sources = VecDeque::<NodeId>::new();
-        sources
-            .extend(node.parent_owned_rights().into_iter().map(|(id, _)| id));
-        sources
-            .extend(node.parent_public_rights().into_iter().map(|(id, _)| id));
-        trace!("Node list for consignment: {:#?}", sources);
-        while let Some(node_id) = sources.pop_front() {
-            if node_id.into_inner() == genesis.contract_id().into_inner() {
-                continue;
-            }
-            trace!(
-                "Getting anchor id for node/protocol id {} from the index",
-                &ProtocolId::from((*node_id).into_inner())
-            );
-            let anchor_id = self.indexer.anchor_id_by_transition_id(node_id)?;
-            trace!("Retrieving anchor with id {}", anchor_id);
-            let anchor = self.storage.anchor(&anchor_id)?;
-            trace!("Anchor data: {:#?}", anchor);
-
-            trace!("Extending source data with the ancestors");
-            // TODO #162: (new) Improve this logic
-            match (
-                self.storage.transition(&node_id),
-                self.storage.extension(&node_id),
-            ) {
-                (Ok(mut transition), Err(_)) => {
-                    transition.conceal_state();
-                    state_transitions.push((anchor, transition.clone()));
-                    sources.extend(
-                        transition
-                            .parent_owned_rights()
-                            .into_iter()
-                            .map(|(id, _)| id),
-                    );
-                    sources.extend(
-                        transition
-                            .parent_public_rights()
-                            .into_iter()
-                            .map(|(id, _)| id),
-                    );
-                }
-                (Err(_), Ok(mut extension)) => {
-                    extension.conceal_state();
-                    state_extensions.push(extension.clone());
-                    sources.extend(
-                        extension
-                            .parent_owned_rights()
-                            .into_iter()
-                            .map(|(id, _)| id),
-                    );
-                    sources.extend(
-                        extension
-                            .parent_public_rights()
-                            .into_iter()
-                            .map(|(id, _)| id),
-                    );
-                }
-                _ => Err(Error::StorageError)?,
-            }
-        }
+        let (ancestors, extensions) = self.ancestors(&genesis, node)?;
+        state_transitions.extend(ancestors);
+        state_extensions.extend(extensions);
 
         let node_id = node.node_id();
         let endpoints = endpoints.iter().map(|op| (node_id, *op)).collect();
@@ -261,6 +450,7 @@ impl Stash for Runtime {
         // [PRIVACY] [SECURITY]:
         // Update all data with the previously known revealed information in the
         // stash
+        let mut indexed = vec![];
         for (mut anchor, mut transition) in
             consignment.state_transitions.into_iter()
         {
@@ -284,6 +474,7 @@ impl Stash for Runtime {
             self.storage.add_anchor(&anchor)?;
             self.indexer.index_anchor(&anchor)?;
             self.storage.add_transition(&transition)?;
+            indexed.push((transition.node_id(), Some(anchor.txid)));
         }
 
         for mut extension in consignment.state_extensions.into_iter() {
@@ -299,6 +490,22 @@ impl Stash for Runtime {
                 );
             }
             self.storage.add_extension(&extension)?;
+            indexed.push((extension.node_id(), None));
+        }
+
+        // Seals are indexed once all nodes are stored, since the consignment
+        // nodes are not ordered and parent nodes are required to resolve the
+        // spent seals
+        for (node_id, witness_txid) in indexed {
+            match self.storage.transition(&node_id) {
+                Ok(transition) => {
+                    self.index_node_seals(&transition, witness_txid)?
+                }
+                Err(_) => {
+                    let extension = self.storage.extension(&node_id)?;
+                    self.index_node_seals(&extension, witness_txid)?
+                }
+            }
         }
 
         Ok(())
@@ -338,21 +545,23 @@ impl Stash for Runtime {
             self.indexer.index_anchor(&anchor)?;
         }
 
-        for transition in disclosure
-            .transitions()
-            .values()
-            .map(|(_, map)| map.values())
-            .flatten()
-        {
-            let mut transition: Transition = transition.clone();
-            if let Ok(other_transition) =
-                self.storage.transition(&transition.node_id())
-            {
-                transition = transition
-                    .into_revealed(other_transition)
-                    .expect("RGB commitment procedure is broken");
+        let mut indexed = vec![];
+        for (anchor, transitions) in disclosure.transitions().values() {
+            for transition in transitions.values() {
+                let mut transition: Transition = transition.clone();
+                if let Ok(other_transition) =
+                    self.storage.transition(&transition.node_id())
+                {
+                    transition = transition
+                        .into_revealed(other_transition)
+                        .expect("RGB commitment procedure is broken");
+                }
+                self.storage.add_transition(&transition)?;
+                indexed.push((transition, anchor.txid));
             }
-            self.storage.add_transition(&transition)?;
+        }
+        for (transition, witness_txid) in indexed {
+            self.index_node_seals(&transition, Some(witness_txid))?;
         }
 
         for extension in disclosure.extensions().values().flatten() {
@@ -365,6 +574,7 @@ impl Stash for Runtime {
                     .expect("RGB commitment procedure is broken");
             }
             self.storage.add_extension(&extension)?;
+            self.index_node_seals(&extension, None)?;
         }
 
         Ok(())
@@ -381,3 +591,129 @@ impl Stash for Runtime {
         unimplemented!()
     }
 }
+
+/// Endpoints of the payees to which the outgoing state transition is
+/// re-consigned. Payees recorded by the sender when the transfer was created
+/// are used when present (keeping only the ones actually assigned by the
+/// transition); for the transfers recorded without payees all blinded seals
+/// and all seals defined on the witness transaction outputs are used.
+pub(super) fn reconsign_endpoints(
+    transition: &Transition,
+    payees: &[SealEndpoint],
+) -> BTreeSet<SealEndpoint> {
+    let assignments = transition.owned_rights().values();
+    if !payees.is_empty() {
+        let assigned = assignments
+            .flat_map(Assignments::all_seal_definitions)
+            .collect::<BTreeSet<_>>();
+        return payees
+            .iter()
+            .copied()
+            .filter(|payee| {
+                assigned.contains(&match *payee {
+                    SealEndpoint::TxOutpoint(hash) => hash,
+                    SealEndpoint::WitnessVout { vout, blinding } => {
+                        SealDefinition::WitnessVout { vout, blinding }
+                            .commit_conceal()
+                    }
+                })
+            })
+            .collect();
+    }
+    assignments
+        .flat_map(|assignments| {
+            let known = assignments.known_seal_definitions();
+            let revealed = known
+                .iter()
+                .map(CommitConceal::commit_conceal)
+                .collect::<BTreeSet<_>>();
+            let witness = known.into_iter().filter_map(|seal| match seal {
+                SealDefinition::WitnessVout { vout, blinding } => {
+                    Some(SealEndpoint::WitnessVout { vout, blinding })
+                }
+                SealDefinition::TxOutpoint(_) => None,
+            });
+            assignments
+                .all_seal_definitions()
+                .into_iter()
+                .filter(move |seal| !revealed.contains(seal))
+                .map(SealEndpoint::TxOutpoint)
+                .chain(witness)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lnpbp::Chain;
+
+    fn transition(payee: SealEndpoint) -> Transition {
+        let outpoint = OutPoint::default();
+        let (asset, _) = rgb20::issue(
+            Chain::Regtest(Default::default()),
+            s!("TEST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint, 100)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let change = SealDefinition::TxOutpoint(OutpointReveal {
+            blinding: 1,
+            txid: Txid::default(),
+            vout: 1,
+        });
+        rgb20::transfer(
+            &asset,
+            bset![outpoint],
+            bmap! { payee => 60 },
+            bmap! { change => 40 },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reconsign_witness_vout_payee() {
+        let payee = SealEndpoint::WitnessVout {
+            vout: 0,
+            blinding: 42,
+        };
+        let transition = transition(payee);
+
+        assert_eq!(reconsign_endpoints(&transition, &[payee]), bset![payee]);
+        assert!(reconsign_endpoints(&transition, &[]).contains(&payee));
+
+        let foreign = SealEndpoint::TxOutpoint(
+            OutpointReveal {
+                blinding: 3,
+                txid: Txid::default(),
+                vout: 3,
+            }
+            .commit_conceal(),
+        );
+        assert_eq!(
+            reconsign_endpoints(&transition, &[payee, foreign]),
+            bset![payee]
+        );
+    }
+
+    #[test]
+    fn test_reconsign_blinded_payee() {
+        let payee = SealEndpoint::TxOutpoint(
+            OutpointReveal {
+                blinding: 2,
+                txid: Txid::default(),
+                vout: 2,
+            }
+            .commit_conceal(),
+        );
+        let transition = transition(payee);
+
+        assert_eq!(reconsign_endpoints(&transition, &[]), bset![payee]);
+    }
+}
@@ -0,0 +1,354 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use core::str::FromStr;
+use std::path::PathBuf;
+
+use rgb::prelude::*;
+
+use super::{
+    DiskStorage, DiskStorageConfig, S3Storage, S3StorageConfig, Store,
+};
+#[cfg(feature = "hammersbald")]
+use super::{HammersbaldConfig, HammersbaldStorage};
+#[cfg(feature = "sled")]
+use super::{SledStorage, SledStorageConfig};
+use crate::constants::STASHD_S3_CACHED_ITEMS;
+use crate::error::{BootstrapError, ServiceErrorDomain};
+use crate::util::migration::{self, Migration};
+use crate::util::s3::S3Url;
+
+/// Version of the stash data layout, common for all storage backends
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Migrations upgrading older stash layouts to the current one
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "marking stash created before format versioning",
+    migrate: migration::unversioned,
+}];
+
+/// Stash storage backend type
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum StoreType {
+    /// File-per-object storage
+    #[display("disk")]
+    Disk,
+
+    /// Object-per-key storage in S3-compatible bucket
+    #[display("s3")]
+    S3,
+
+    /// Embedded hammersbald key-value database
+    #[cfg(feature = "hammersbald")]
+    #[display("hammersbald")]
+    Hammersbald,
+
+    /// Embedded sled key-value database
+    #[cfg(feature = "sled")]
+    #[display("sled")]
+    Sled,
+}
+
+impl Default for StoreType {
+    fn default() -> Self {
+        StoreType::Disk
+    }
+}
+
+/// Error parsing stash storage type
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display("unknown or unsupported stash storage type `{_0}`")]
+pub struct StoreTypeParseError(String);
+
+impl FromStr for StoreType {
+    type Err = StoreTypeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "disk" | "file" => StoreType::Disk,
+            "s3" => StoreType::S3,
+            #[cfg(feature = "hammersbald")]
+            "hammersbald" => StoreType::Hammersbald,
+            #[cfg(feature = "sled")]
+            "sled" => StoreType::Sled,
+            _ => Err(StoreTypeParseError(s.to_owned()))?,
+        })
+    }
+}
+
+/// Stash storage with the backend selected at runtime
+pub enum Storage {
+    Disk(DiskStorage),
+    S3(S3Storage),
+    #[cfg(feature = "hammersbald")]
+    Hammersbald(HammersbaldStorage),
+    #[cfg(feature = "sled")]
+    Sled(SledStorage),
+}
+
+impl Storage {
+    /// Opens storage of the given type located in the `data_dir`; for the
+    /// S3 storage `data_dir` is the bucket URL
+    pub fn with(
+        store_type: StoreType,
+        data_dir: PathBuf,
+    ) -> Result<Self, BootstrapError> {
+        debug!("Opening {} stash storage at {:?}", store_type, data_dir);
+        // S3 storage keeps its format version in the bucket itself
+        if store_type != StoreType::S3 {
+            migration::upgrade(&data_dir, FORMAT_VERSION, MIGRATIONS).map_err(
+                |err| {
+                    error!("Unable to open stash at {:?}: {}", data_dir, err);
+                    BootstrapError::StorageError
+                },
+            )?;
+        }
+        Ok(match store_type {
+            StoreType::Disk => {
+                Storage::Disk(DiskStorage::new(DiskStorageConfig { data_dir })?)
+            }
+            StoreType::S3 => {
+                let url = S3Url::from_str(&data_dir.to_string_lossy())
+                    .map_err(|err| {
+                        BootstrapError::ArgParseError(err.to_string())
+                    })?;
+                Storage::S3(S3Storage::new(S3StorageConfig {
+                    url,
+                    cached_items: STASHD_S3_CACHED_ITEMS,
+                })?)
+            }
+            #[cfg(feature = "hammersbald")]
+            StoreType::Hammersbald => Storage::Hammersbald(
+                HammersbaldStorage::new(HammersbaldConfig {
+                    data_dir,
+                    cached_pages: 100,
+                    bucket_fill_targes: 2,
+                })?,
+            ),
+            #[cfg(feature = "sled")]
+            StoreType::Sled => {
+                Storage::Sled(SledStorage::new(SledStorageConfig { data_dir })?)
+            }
+        })
+    }
+
+    /// Writes all pending changes to disk
+    pub fn flush(&self) -> Result<(), ServiceErrorDomain> {
+        match self {
+            #[cfg(feature = "sled")]
+            Storage::Sled(storage) => {
+                storage.flush().map_err(ServiceErrorDomain::from)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that all schemata and geneses kept in the stash can be read
+    pub fn check_integrity(&self) -> Result<(), ServiceErrorDomain> {
+        for schema_id in self.schema_ids()? {
+            self.schema(&schema_id)?;
+        }
+        for contract_id in self.contract_ids()? {
+            self.genesis(&contract_id)?;
+        }
+        Ok(())
+    }
+
+    pub fn store_type(&self) -> StoreType {
+        match self {
+            Storage::Disk(_) => StoreType::Disk,
+            Storage::S3(_) => StoreType::S3,
+            #[cfg(feature = "hammersbald")]
+            Storage::Hammersbald(_) => StoreType::Hammersbald,
+            #[cfg(feature = "sled")]
+            Storage::Sled(_) => StoreType::Sled,
+        }
+    }
+}
+
+macro_rules! dispatch {
+    ($self:ident, $storage:ident => $call:expr) => {
+        match $self {
+            Storage::Disk($storage) => $call.map_err(ServiceErrorDomain::from),
+            Storage::S3($storage) => $call.map_err(ServiceErrorDomain::from),
+            #[cfg(feature = "hammersbald")]
+            Storage::Hammersbald($storage) => {
+                $call.map_err(ServiceErrorDomain::from)
+            }
+            #[cfg(feature = "sled")]
+            Storage::Sled($storage) => $call.map_err(ServiceErrorDomain::from),
+        }
+    };
+}
+
+impl Store for Storage {
+    type Error = ServiceErrorDomain;
+
+    fn schema_ids(&self) -> Result<Vec<SchemaId>, Self::Error> {
+        dispatch!(self, s => s.schema_ids())
+    }
+
+    fn schema(&self, id: &SchemaId) -> Result<Schema, Self::Error> {
+        dispatch!(self, s => s.schema(id))
+    }
+
+    fn has_schema(&self, id: &SchemaId) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.has_schema(id))
+    }
+
+    fn add_schema(&mut self, schema: &Schema) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.add_schema(schema))
+    }
+
+    fn remove_schema(&mut self, id: &SchemaId) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.remove_schema(id))
+    }
+
+    fn contract_ids(&self) -> Result<Vec<ContractId>, Self::Error> {
+        dispatch!(self, s => s.contract_ids())
+    }
+
+    fn genesis(&self, id: &ContractId) -> Result<Genesis, Self::Error> {
+        dispatch!(self, s => s.genesis(id))
+    }
+
+    fn has_genesis(&self, id: &ContractId) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.has_genesis(id))
+    }
+
+    fn add_genesis(&mut self, genesis: &Genesis) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.add_genesis(genesis))
+    }
+
+    fn remove_genesis(&mut self, id: &ContractId) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.remove_genesis(id))
+    }
+
+    fn anchor_ids(&self) -> Result<Vec<AnchorId>, Self::Error> {
+        dispatch!(self, s => s.anchor_ids())
+    }
+
+    fn anchor(&self, id: &AnchorId) -> Result<Anchor, Self::Error> {
+        dispatch!(self, s => s.anchor(id))
+    }
+
+    fn has_anchor(&self, id: &AnchorId) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.has_anchor(id))
+    }
+
+    fn add_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.add_anchor(anchor))
+    }
+
+    fn remove_anchor(&mut self, id: &AnchorId) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.remove_anchor(id))
+    }
+
+    fn transition_ids(&self) -> Result<Vec<NodeId>, Self::Error> {
+        dispatch!(self, s => s.transition_ids())
+    }
+
+    fn transition(&self, id: &NodeId) -> Result<Transition, Self::Error> {
+        dispatch!(self, s => s.transition(id))
+    }
+
+    fn has_transition(&self, id: &NodeId) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.has_transition(id))
+    }
+
+    fn add_transition(
+        &mut self,
+        transition: &Transition,
+    ) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.add_transition(transition))
+    }
+
+    fn remove_transition(&mut self, id: &NodeId) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.remove_transition(id))
+    }
+
+    fn extension_ids(&self) -> Result<Vec<NodeId>, Self::Error> {
+        dispatch!(self, s => s.extension_ids())
+    }
+
+    fn extension(&self, id: &NodeId) -> Result<Extension, Self::Error> {
+        dispatch!(self, s => s.extension(id))
+    }
+
+    fn has_extension(&self, id: &NodeId) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.has_extension(id))
+    }
+
+    fn add_extension(
+        &mut self,
+        extension: &Extension,
+    ) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.add_extension(extension))
+    }
+
+    fn remove_extension(&mut self, id: &NodeId) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.remove_extension(id))
+    }
+}
+
+/// Statistics of the data copied by [`migrate`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Display)]
+#[display(
+    "{schemata} schemata, {geneses} geneses, {anchors} anchors, \
+     {transitions} transitions, {extensions} extensions"
+)]
+pub struct MigrationStats {
+    pub schemata: usize,
+    pub geneses: usize,
+    pub anchors: usize,
+    pub transitions: usize,
+    pub extensions: usize,
+}
+
+/// Copies all stash data from one storage to another. Data already present
+/// in the target storage are overwritten; the source storage is not
+/// modified.
+pub fn migrate(
+    from: &impl Store,
+    to: &mut impl Store,
+) -> Result<MigrationStats, ServiceErrorDomain> {
+    let mut stats = MigrationStats::default();
+    for id in from.schema_ids().map_err(Into::into)? {
+        let schema = from.schema(&id).map_err(Into::into)?;
+        to.add_schema(&schema).map_err(Into::into)?;
+        stats.schemata += 1;
+    }
+    for id in from.contract_ids().map_err(Into::into)? {
+        let genesis = from.genesis(&id).map_err(Into::into)?;
+        to.add_genesis(&genesis).map_err(Into::into)?;
+        stats.geneses += 1;
+    }
+    for id in from.anchor_ids().map_err(Into::into)? {
+        let anchor = from.anchor(&id).map_err(Into::into)?;
+        to.add_anchor(&anchor).map_err(Into::into)?;
+        stats.anchors += 1;
+    }
+    for id in from.transition_ids().map_err(Into::into)? {
+        let transition = from.transition(&id).map_err(Into::into)?;
+        to.add_transition(&transition).map_err(Into::into)?;
+        stats.transitions += 1;
+    }
+    for id in from.extension_ids().map_err(Into::into)? {
+        let extension = from.extension(&id).map_err(Into::into)?;
+        to.add_extension(&extension).map_err(Into::into)?;
+        stats.extensions += 1;
+    }
+    Ok(stats)
+}
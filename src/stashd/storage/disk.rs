@@ -14,7 +14,8 @@
 use std::path::PathBuf;
 use std::{fs, io};
 
-use bitcoin::hashes::hex::ToHex;
+use amplify::Wrapper;
+use bitcoin::hashes::hex::{FromHex, ToHex};
 use rgb::prelude::*;
 
 use super::Store;
@@ -138,6 +139,44 @@ impl DiskStorageConfig {
                 .collect(),
         )
     }
+
+    #[inline]
+    pub fn anchor_names(&self) -> Result<Vec<String>, io::Error> {
+        Ok(
+            read_dir_filenames(self.anchors_dir(), Some(Self::RGB_FILE_EXT))?
+                .into_iter()
+                .map(|name| String::from(name))
+                .collect(),
+        )
+    }
+
+    #[inline]
+    pub fn transition_names(&self) -> Result<Vec<String>, io::Error> {
+        Ok(read_dir_filenames(
+            self.transitions_dir(),
+            Some(Self::RGB_FILE_EXT),
+        )?
+        .into_iter()
+        .map(|name| String::from(name))
+        .collect())
+    }
+
+    #[inline]
+    pub fn extension_names(&self) -> Result<Vec<String>, io::Error> {
+        // Extensions directory is not created on storage initialization
+        if !self.extensions_dir().exists() {
+            return Ok(vec![]);
+        }
+        Ok(
+            read_dir_filenames(
+                self.extensions_dir(),
+                Some(Self::RGB_FILE_EXT),
+            )?
+            .into_iter()
+            .map(|name| String::from(name))
+            .collect(),
+        )
+    }
 }
 
 /// Keeps all source/binary RGB contract data, stash etc
@@ -272,6 +311,17 @@ impl Store for DiskStorage {
         Ok(existed)
     }
 
+    fn anchor_ids(&self) -> Result<Vec<AnchorId>, Self::Error> {
+        self.config.anchor_names()?.into_iter().try_fold(
+            vec![],
+            |mut list, name| {
+                let name = name.replace(".rgb", "");
+                list.push(AnchorId::from_inner(FromHex::from_hex(&name)?));
+                Ok(list)
+            },
+        )
+    }
+
     fn anchor(&self, id: &AnchorId) -> Result<Anchor, Self::Error> {
         Ok(Anchor::read_file(self.config.anchor_filename(id))?)
     }
@@ -294,6 +344,17 @@ impl Store for DiskStorage {
         Ok(existed)
     }
 
+    fn transition_ids(&self) -> Result<Vec<NodeId>, Self::Error> {
+        self.config.transition_names()?.into_iter().try_fold(
+            vec![],
+            |mut list, name| {
+                let name = name.replace(".rgb", "");
+                list.push(NodeId::from_inner(FromHex::from_hex(&name)?));
+                Ok(list)
+            },
+        )
+    }
+
     fn transition(&self, id: &NodeId) -> Result<Transition, Self::Error> {
         Ok(Transition::read_file(self.config.transition_filename(id))?)
     }
@@ -319,6 +380,17 @@ impl Store for DiskStorage {
         Ok(existed)
     }
 
+    fn extension_ids(&self) -> Result<Vec<NodeId>, Self::Error> {
+        self.config.extension_names()?.into_iter().try_fold(
+            vec![],
+            |mut list, name| {
+                let name = name.replace(".rgb", "");
+                list.push(NodeId::from_inner(FromHex::from_hex(&name)?));
+                Ok(list)
+            },
+        )
+    }
+
     fn extension(&self, id: &NodeId) -> Result<Extension, Self::Error> {
         Ok(Extension::read_file(self.config.extension_filename(id))?)
     }
@@ -248,6 +248,14 @@ impl Store for HammersbaldStorage {
         Ok(true)
     }
 
+    fn anchor_ids(&self) -> Result<Vec<AnchorId>, Self::Error> {
+        let mut result = vec![];
+        for item in self.anchors_db.iter() {
+            result.push(AnchorId::strict_decode(&item.1[..])?);
+        }
+        Ok(result)
+    }
+
     fn anchor(&self, id: &AnchorId) -> Result<Anchor, Self::Error> {
         let key = strict_serialize(id)?;
         let value = self
@@ -281,6 +289,14 @@ impl Store for HammersbaldStorage {
         Ok(true)
     }
 
+    fn transition_ids(&self) -> Result<Vec<NodeId>, Self::Error> {
+        let mut result = vec![];
+        for item in self.transitions_db.iter() {
+            result.push(NodeId::strict_decode(&item.1[..])?);
+        }
+        Ok(result)
+    }
+
     fn transition(&self, id: &NodeId) -> Result<Transition, Self::Error> {
         let key = strict_serialize(id)?;
         let value = self
@@ -317,6 +333,14 @@ impl Store for HammersbaldStorage {
         Ok(true)
     }
 
+    fn extension_ids(&self) -> Result<Vec<NodeId>, Self::Error> {
+        let mut result = vec![];
+        for item in self.extensions_db.iter() {
+            result.push(NodeId::strict_decode(&item.1[..])?);
+        }
+        Ok(result)
+    }
+
     fn extension(&self, id: &NodeId) -> Result<Extension, Self::Error> {
         let key = strict_serialize(id)?;
         let value = self
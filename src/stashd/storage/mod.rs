@@ -11,12 +11,22 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+mod backend;
 mod disk;
 #[cfg(feature = "hammersbald")]
 mod hammersbald;
+mod s3;
+#[cfg(feature = "sled")]
+mod sled;
 mod store;
 
 #[cfg(feature = "hammersbald")]
-pub use self::hammersbald::HammersbaldStorage;
+pub use self::hammersbald::{HammersbaldConfig, HammersbaldStorage};
+#[cfg(feature = "sled")]
+pub use self::sled::{SledStorage, SledStorageConfig};
+pub use backend::{
+    migrate, MigrationStats, Storage, StoreType, StoreTypeParseError,
+};
 pub use disk::{DiskStorage, DiskStorageConfig, DiskStorageError};
+pub use s3::{S3Storage, S3StorageConfig};
 pub use store::Store;
@@ -0,0 +1,421 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use core::cell::RefCell;
+use core::str::FromStr;
+use std::collections::BTreeMap;
+
+use amplify::Wrapper;
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use lnpbp::strict_encoding::{
+    strict_deserialize, strict_serialize, StrictDecode, StrictEncode,
+};
+use rgb::prelude::*;
+
+use super::backend::FORMAT_VERSION;
+use super::Store;
+use crate::error::{BootstrapError, ServiceErrorDomain};
+use crate::util::migration::FORMAT_FILENAME;
+use crate::util::s3::{S3Error, S3Url};
+
+const RGB_FILE_EXT: &str = "rgb";
+
+#[derive(Debug, Display, Error, From)]
+#[display(Debug)]
+pub enum S3StorageError {
+    #[from]
+    S3(S3Error),
+
+    #[from]
+    Encoding(lnpbp::strict_encoding::Error),
+
+    #[from(bitcoin::hashes::Error)]
+    HashName,
+
+    #[from(bitcoin::hashes::hex::Error)]
+    #[from(rgb::bech32::Error)]
+    BrokenFilenames,
+
+    DataNotFound,
+
+    UnsupportedFormat(String),
+}
+
+impl From<S3StorageError> for ServiceErrorDomain {
+    fn from(err: S3StorageError) -> Self {
+        ServiceErrorDomain::Storage(err.to_string())
+    }
+}
+
+impl From<S3StorageError> for BootstrapError {
+    fn from(_: S3StorageError) -> Self {
+        BootstrapError::StorageError
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct S3StorageConfig {
+    pub url: S3Url,
+    /// Maximum number of anchors and of transitions kept in memory
+    pub cached_items: usize,
+}
+
+/// Bounded in-memory cache evicting the least recently used items
+struct HotCache<K, V> {
+    capacity: usize,
+    tick: u64,
+    items: BTreeMap<K, (V, u64)>,
+    usage: BTreeMap<u64, K>,
+}
+
+impl<K, V> HotCache<K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            items: bmap! {},
+            usage: bmap! {},
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<V> {
+        self.tick += 1;
+        let (value, used) = self.items.get_mut(key)?;
+        self.usage.remove(used);
+        *used = self.tick;
+        self.usage.insert(self.tick, key.clone());
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, used)) =
+            self.items.insert(key.clone(), (value, self.tick))
+        {
+            self.usage.remove(&used);
+        }
+        self.usage.insert(self.tick, key);
+        while self.items.len() > self.capacity {
+            let oldest = match self.usage.keys().next() {
+                Some(used) => *used,
+                None => break,
+            };
+            if let Some(key) = self.usage.remove(&oldest) {
+                self.items.remove(&key);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some((_, used)) = self.items.remove(key) {
+            self.usage.remove(&used);
+        }
+    }
+}
+
+/// Keeps all RGB contract data as objects in S3-compatible bucket, using the
+/// same layout as the disk storage (`anchors/<id>.rgb` etc), so the stash
+/// may be moved between the bucket and a local directory by plain copying.
+/// Anchors and state transitions are frequently re-read during consignment
+/// composing and validation, so the recently used ones are kept in memory;
+/// all writes go to the bucket right away.
+pub struct S3Storage {
+    url: S3Url,
+    anchors: RefCell<HotCache<AnchorId, Anchor>>,
+    transitions: RefCell<HotCache<NodeId, Transition>>,
+}
+
+impl S3Storage {
+    pub fn new(config: S3StorageConfig) -> Result<Self, S3StorageError> {
+        debug!("Instantiating RGB storage (S3 bucket {}) ...", config.url);
+        let storage = Self {
+            anchors: RefCell::new(HotCache::new(config.cached_items)),
+            transitions: RefCell::new(HotCache::new(config.cached_items)),
+            url: config.url,
+        };
+        storage.check_version()?;
+        Ok(storage)
+    }
+
+    /// Checks version of the stash data layout kept in the bucket, marking
+    /// new stash with the current version
+    fn check_version(&self) -> Result<(), S3StorageError> {
+        match self.url.get(FORMAT_FILENAME)? {
+            None => {
+                debug!("Marking new S3 stash with format {}", FORMAT_VERSION);
+                let version = format!("{}\n", FORMAT_VERSION);
+                self.url.put(FORMAT_FILENAME, version.as_bytes())?;
+            }
+            Some(data) => {
+                let data = String::from_utf8_lossy(&data).trim().to_string();
+                match u16::from_str(&data) {
+                    Ok(version) if version == FORMAT_VERSION => {}
+                    _ => Err(S3StorageError::UnsupportedFormat(data))?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn key(dir: &str, name: impl AsRef<str>) -> String {
+        format!("{}/{}.{}", dir, name.as_ref(), RGB_FILE_EXT)
+    }
+
+    fn names(&self, dir: &str) -> Result<Vec<String>, S3StorageError> {
+        let prefix = format!("{}/", dir);
+        let suffix = format!(".{}", RGB_FILE_EXT);
+        Ok(self
+            .url
+            .list(&prefix)?
+            .into_iter()
+            .filter_map(|key| {
+                key.strip_prefix(&prefix)?
+                    .strip_suffix(&suffix)
+                    .map(str::to_string)
+            })
+            .collect())
+    }
+
+    fn get<T>(&self, key: &str) -> Result<T, S3StorageError>
+    where
+        T: StrictDecode,
+    {
+        let data = self.url.get(key)?.ok_or(S3StorageError::DataNotFound)?;
+        Ok(strict_deserialize(&data)?)
+    }
+
+    fn has(&self, key: &str) -> Result<bool, S3StorageError> {
+        Ok(self.url.list(key)?.iter().any(|found| found == key))
+    }
+
+    /// Writes the object, returning whether it has already existed
+    fn put<T>(&self, key: &str, value: &T) -> Result<bool, S3StorageError>
+    where
+        T: StrictEncode,
+    {
+        let exists = self.has(key)?;
+        self.url.put(key, &strict_serialize(value)?)?;
+        Ok(exists)
+    }
+
+    fn remove(&self, key: &str) -> Result<bool, S3StorageError> {
+        let existed = self.has(key)?;
+        self.url.delete(key)?;
+        Ok(existed)
+    }
+}
+
+impl Store for S3Storage {
+    type Error = S3StorageError;
+
+    fn schema_ids(&self) -> Result<Vec<SchemaId>, Self::Error> {
+        self.names("schemata")?.into_iter().try_fold(
+            vec![],
+            |mut list, name| {
+                list.push(SchemaId::from_bech32_str(&name)?);
+                Ok(list)
+            },
+        )
+    }
+
+    fn schema(&self, id: &SchemaId) -> Result<Schema, Self::Error> {
+        self.get(&Self::key("schemata", id.to_bech32().to_string()))
+    }
+
+    fn has_schema(&self, id: &SchemaId) -> Result<bool, Self::Error> {
+        self.has(&Self::key("schemata", id.to_bech32().to_string()))
+    }
+
+    fn add_schema(&mut self, schema: &Schema) -> Result<bool, Self::Error> {
+        let id = schema.schema_id().to_bech32().to_string();
+        self.put(&Self::key("schemata", id), schema)
+    }
+
+    fn remove_schema(&mut self, id: &SchemaId) -> Result<bool, Self::Error> {
+        self.remove(&Self::key("schemata", id.to_bech32().to_string()))
+    }
+
+    fn contract_ids(&self) -> Result<Vec<ContractId>, Self::Error> {
+        self.names("geneses")?
+            .into_iter()
+            .try_fold(vec![], |mut list, name| {
+                list.push(ContractId::from_bech32_str(&name)?);
+                Ok(list)
+            })
+    }
+
+    fn genesis(&self, id: &ContractId) -> Result<Genesis, Self::Error> {
+        self.get(&Self::key("geneses", id.to_bech32().to_string()))
+    }
+
+    fn has_genesis(&self, id: &ContractId) -> Result<bool, Self::Error> {
+        self.has(&Self::key("geneses", id.to_bech32().to_string()))
+    }
+
+    fn add_genesis(&mut self, genesis: &Genesis) -> Result<bool, Self::Error> {
+        let id = genesis.contract_id().to_bech32().to_string();
+        self.put(&Self::key("geneses", id), genesis)
+    }
+
+    fn remove_genesis(&mut self, id: &ContractId) -> Result<bool, Self::Error> {
+        self.remove(&Self::key("geneses", id.to_bech32().to_string()))
+    }
+
+    fn anchor_ids(&self) -> Result<Vec<AnchorId>, Self::Error> {
+        self.names("anchors")?
+            .into_iter()
+            .try_fold(vec![], |mut list, name| {
+                list.push(AnchorId::from_inner(FromHex::from_hex(&name)?));
+                Ok(list)
+            })
+    }
+
+    fn anchor(&self, id: &AnchorId) -> Result<Anchor, Self::Error> {
+        if let Some(anchor) = self.anchors.borrow_mut().get(id) {
+            return Ok(anchor);
+        }
+        let anchor: Anchor = self.get(&Self::key("anchors", id.to_hex()))?;
+        self.anchors.borrow_mut().insert(*id, anchor.clone());
+        Ok(anchor)
+    }
+
+    fn has_anchor(&self, id: &AnchorId) -> Result<bool, Self::Error> {
+        if self.anchors.borrow_mut().get(id).is_some() {
+            return Ok(true);
+        }
+        self.has(&Self::key("anchors", id.to_hex()))
+    }
+
+    fn add_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error> {
+        let id = anchor.anchor_id();
+        let exists = self.put(&Self::key("anchors", id.to_hex()), anchor)?;
+        self.anchors.borrow_mut().insert(id, anchor.clone());
+        Ok(exists)
+    }
+
+    fn remove_anchor(&mut self, id: &AnchorId) -> Result<bool, Self::Error> {
+        self.anchors.borrow_mut().remove(id);
+        self.remove(&Self::key("anchors", id.to_hex()))
+    }
+
+    fn transition_ids(&self) -> Result<Vec<NodeId>, Self::Error> {
+        self.names("transitions")?.into_iter().try_fold(
+            vec![],
+            |mut list, name| {
+                list.push(NodeId::from_inner(FromHex::from_hex(&name)?));
+                Ok(list)
+            },
+        )
+    }
+
+    fn transition(&self, id: &NodeId) -> Result<Transition, Self::Error> {
+        if let Some(transition) = self.transitions.borrow_mut().get(id) {
+            return Ok(transition);
+        }
+        let transition: Transition =
+            self.get(&Self::key("transitions", id.to_hex()))?;
+        self.transitions
+            .borrow_mut()
+            .insert(*id, transition.clone());
+        Ok(transition)
+    }
+
+    fn has_transition(&self, id: &NodeId) -> Result<bool, Self::Error> {
+        if self.transitions.borrow_mut().get(id).is_some() {
+            return Ok(true);
+        }
+        self.has(&Self::key("transitions", id.to_hex()))
+    }
+
+    fn add_transition(
+        &mut self,
+        transition: &Transition,
+    ) -> Result<bool, Self::Error> {
+        let id = transition.node_id();
+        let exists =
+            self.put(&Self::key("transitions", id.to_hex()), transition)?;
+        self.transitions.borrow_mut().insert(id, transition.clone());
+        Ok(exists)
+    }
+
+    fn remove_transition(&mut self, id: &NodeId) -> Result<bool, Self::Error> {
+        self.transitions.borrow_mut().remove(id);
+        self.remove(&Self::key("transitions", id.to_hex()))
+    }
+
+    fn extension_ids(&self) -> Result<Vec<NodeId>, Self::Error> {
+        self.names("extensions")?.into_iter().try_fold(
+            vec![],
+            |mut list, name| {
+                list.push(NodeId::from_inner(FromHex::from_hex(&name)?));
+                Ok(list)
+            },
+        )
+    }
+
+    fn extension(&self, id: &NodeId) -> Result<Extension, Self::Error> {
+        self.get(&Self::key("extensions", id.to_hex()))
+    }
+
+    fn has_extension(&self, id: &NodeId) -> Result<bool, Self::Error> {
+        self.has(&Self::key("extensions", id.to_hex()))
+    }
+
+    fn add_extension(
+        &mut self,
+        extension: &Extension,
+    ) -> Result<bool, Self::Error> {
+        let id = extension.node_id().to_hex();
+        self.put(&Self::key("extensions", id), extension)
+    }
+
+    fn remove_extension(&mut self, id: &NodeId) -> Result<bool, Self::Error> {
+        self.remove(&Self::key("extensions", id.to_hex()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hot_cache_eviction() {
+        let mut cache = HotCache::new(2);
+        cache.insert(1u8, "one");
+        cache.insert(2u8, "two");
+        // Reading the oldest item makes it the most recently used one
+        assert_eq!(cache.get(&1), Some("one"));
+        cache.insert(3u8, "three");
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some("one"));
+        assert_eq!(cache.get(&3), Some("three"));
+
+        cache.insert(3u8, "THREE");
+        assert_eq!(cache.items.len(), 2);
+        assert_eq!(cache.usage.len(), 2);
+        cache.remove(&1);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&3), Some("THREE"));
+
+        let mut disabled = HotCache::new(0);
+        disabled.insert(1u8, "one");
+        assert_eq!(disabled.get(&1), None);
+    }
+}
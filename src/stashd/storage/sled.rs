@@ -0,0 +1,299 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::path::PathBuf;
+
+use lnpbp::strict_encoding::{
+    strict_deserialize, strict_serialize, StrictDecode, StrictEncode,
+};
+use rgb::prelude::*;
+
+use super::Store;
+use crate::error::{BootstrapError, ServiceErrorDomain};
+
+#[derive(Debug, Display, Error, From)]
+#[display(Debug)]
+pub enum SledStorageError {
+    #[from]
+    Sled(::sled::Error),
+
+    #[from]
+    Encoding(lnpbp::strict_encoding::Error),
+
+    DataNotFound,
+}
+
+impl From<SledStorageError> for ServiceErrorDomain {
+    fn from(err: SledStorageError) -> Self {
+        ServiceErrorDomain::Storage(err.to_string())
+    }
+}
+
+impl From<SledStorageError> for BootstrapError {
+    fn from(_: SledStorageError) -> Self {
+        BootstrapError::StorageError
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display(Debug)]
+pub struct SledStorageConfig {
+    pub data_dir: PathBuf,
+}
+
+impl SledStorageConfig {
+    #[inline]
+    pub fn db_dir(&self) -> PathBuf {
+        self.data_dir.join("sled")
+    }
+}
+
+/// Keeps all RGB contract data in an embedded sled key-value database, using
+/// a separate tree for each of the data types. Suitable for large contract
+/// graphs, where the file-per-object disk storage becomes slow.
+pub struct SledStorage {
+    db: ::sled::Db,
+    schemata: ::sled::Tree,
+    geneses: ::sled::Tree,
+    anchors: ::sled::Tree,
+    transitions: ::sled::Tree,
+    extensions: ::sled::Tree,
+}
+
+impl SledStorage {
+    pub fn new(config: SledStorageConfig) -> Result<Self, SledStorageError> {
+        debug!("Instantiating RGB storage (sled database) ...");
+        let db = ::sled::open(config.db_dir())?;
+        Ok(Self {
+            schemata: db.open_tree("schemata")?,
+            geneses: db.open_tree("geneses")?,
+            anchors: db.open_tree("anchors")?,
+            transitions: db.open_tree("transitions")?,
+            extensions: db.open_tree("extensions")?,
+            db,
+        })
+    }
+
+    /// Writes all pending changes to disk
+    pub fn flush(&self) -> Result<(), SledStorageError> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn ids<T>(tree: &::sled::Tree) -> Result<Vec<T>, SledStorageError>
+    where
+        T: StrictDecode,
+    {
+        tree.iter().keys().try_fold(vec![], |mut list, key| {
+            list.push(strict_deserialize(&key?)?);
+            Ok(list)
+        })
+    }
+
+    fn get<K, V>(tree: &::sled::Tree, id: &K) -> Result<V, SledStorageError>
+    where
+        K: StrictEncode,
+        V: StrictDecode,
+    {
+        let value = tree
+            .get(strict_serialize(id)?)?
+            .ok_or(SledStorageError::DataNotFound)?;
+        Ok(strict_deserialize(&value)?)
+    }
+
+    fn has<K>(tree: &::sled::Tree, id: &K) -> Result<bool, SledStorageError>
+    where
+        K: StrictEncode,
+    {
+        Ok(tree.contains_key(strict_serialize(id)?)?)
+    }
+
+    fn put<K, V>(
+        tree: &::sled::Tree,
+        id: &K,
+        value: &V,
+    ) -> Result<bool, SledStorageError>
+    where
+        K: StrictEncode,
+        V: StrictEncode,
+    {
+        Ok(tree
+            .insert(strict_serialize(id)?, strict_serialize(value)?)?
+            .is_some())
+    }
+
+    fn remove<K>(tree: &::sled::Tree, id: &K) -> Result<bool, SledStorageError>
+    where
+        K: StrictEncode,
+    {
+        Ok(tree.remove(strict_serialize(id)?)?.is_some())
+    }
+}
+
+impl Store for SledStorage {
+    type Error = SledStorageError;
+
+    fn schema_ids(&self) -> Result<Vec<SchemaId>, Self::Error> {
+        Self::ids(&self.schemata)
+    }
+
+    fn schema(&self, id: &SchemaId) -> Result<Schema, Self::Error> {
+        Self::get(&self.schemata, id)
+    }
+
+    fn has_schema(&self, id: &SchemaId) -> Result<bool, Self::Error> {
+        Self::has(&self.schemata, id)
+    }
+
+    fn add_schema(&mut self, schema: &Schema) -> Result<bool, Self::Error> {
+        Self::put(&self.schemata, &schema.schema_id(), schema)
+    }
+
+    fn remove_schema(&mut self, id: &SchemaId) -> Result<bool, Self::Error> {
+        Self::remove(&self.schemata, id)
+    }
+
+    fn contract_ids(&self) -> Result<Vec<ContractId>, Self::Error> {
+        Self::ids(&self.geneses)
+    }
+
+    fn genesis(&self, id: &ContractId) -> Result<Genesis, Self::Error> {
+        Self::get(&self.geneses, id)
+    }
+
+    fn has_genesis(&self, id: &ContractId) -> Result<bool, Self::Error> {
+        Self::has(&self.geneses, id)
+    }
+
+    fn add_genesis(&mut self, genesis: &Genesis) -> Result<bool, Self::Error> {
+        Self::put(&self.geneses, &genesis.contract_id(), genesis)
+    }
+
+    fn remove_genesis(&mut self, id: &ContractId) -> Result<bool, Self::Error> {
+        Self::remove(&self.geneses, id)
+    }
+
+    fn anchor_ids(&self) -> Result<Vec<AnchorId>, Self::Error> {
+        Self::ids(&self.anchors)
+    }
+
+    fn anchor(&self, id: &AnchorId) -> Result<Anchor, Self::Error> {
+        Self::get(&self.anchors, id)
+    }
+
+    fn has_anchor(&self, id: &AnchorId) -> Result<bool, Self::Error> {
+        Self::has(&self.anchors, id)
+    }
+
+    fn add_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error> {
+        Self::put(&self.anchors, &anchor.anchor_id(), anchor)
+    }
+
+    fn remove_anchor(&mut self, id: &AnchorId) -> Result<bool, Self::Error> {
+        Self::remove(&self.anchors, id)
+    }
+
+    fn transition_ids(&self) -> Result<Vec<NodeId>, Self::Error> {
+        Self::ids(&self.transitions)
+    }
+
+    fn transition(&self, id: &NodeId) -> Result<Transition, Self::Error> {
+        Self::get(&self.transitions, id)
+    }
+
+    fn has_transition(&self, id: &NodeId) -> Result<bool, Self::Error> {
+        Self::has(&self.transitions, id)
+    }
+
+    fn add_transition(
+        &mut self,
+        transition: &Transition,
+    ) -> Result<bool, Self::Error> {
+        Self::put(&self.transitions, &transition.node_id(), transition)
+    }
+
+    fn remove_transition(&mut self, id: &NodeId) -> Result<bool, Self::Error> {
+        Self::remove(&self.transitions, id)
+    }
+
+    fn extension_ids(&self) -> Result<Vec<NodeId>, Self::Error> {
+        Self::ids(&self.extensions)
+    }
+
+    fn extension(&self, id: &NodeId) -> Result<Extension, Self::Error> {
+        Self::get(&self.extensions, id)
+    }
+
+    fn has_extension(&self, id: &NodeId) -> Result<bool, Self::Error> {
+        Self::has(&self.extensions, id)
+    }
+
+    fn add_extension(
+        &mut self,
+        extension: &Extension,
+    ) -> Result<bool, Self::Error> {
+        Self::put(&self.extensions, &extension.node_id(), extension)
+    }
+
+    fn remove_extension(&mut self, id: &NodeId) -> Result<bool, Self::Error> {
+        Self::remove(&self.extensions, id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_sled_db() {
+        let genesis = Genesis::default();
+        let contract_id = genesis.contract_id();
+        let transition = Transition::default();
+        let transition_node_id = transition.node_id();
+        let extension = Extension::default();
+        let extension_node_id = extension.node_id();
+
+        let data_dir = env::temp_dir().join("rgb-test-sled-storage");
+        let _ = std::fs::remove_dir_all(&data_dir);
+        let mut database =
+            SledStorage::new(SledStorageConfig { data_dir }).unwrap();
+
+        assert!(!database.add_genesis(&genesis).unwrap());
+        assert!(database.add_genesis(&genesis).unwrap());
+        assert!(database.has_genesis(&contract_id).unwrap());
+        assert_eq!(database.genesis(&contract_id).unwrap(), genesis);
+        assert_eq!(vec![contract_id], database.contract_ids().unwrap());
+        assert!(database.remove_genesis(&contract_id).unwrap());
+        assert!(!database.has_genesis(&contract_id).unwrap());
+
+        assert!(!database.add_transition(&transition).unwrap());
+        assert!(database.has_transition(&transition_node_id).unwrap());
+        assert_eq!(
+            database.transition(&transition_node_id).unwrap(),
+            transition
+        );
+        assert_eq!(
+            vec![transition_node_id],
+            database.transition_ids().unwrap()
+        );
+        assert!(database.remove_transition(&transition_node_id).unwrap());
+
+        assert!(!database.add_extension(&extension).unwrap());
+        assert!(database.has_extension(&extension_node_id).unwrap());
+        assert_eq!(database.extension(&extension_node_id).unwrap(), extension);
+        assert_eq!(vec![extension_node_id], database.extension_ids().unwrap());
+        assert!(database.remove_extension(&extension_node_id).unwrap());
+        assert!(database.extension_ids().unwrap().is_empty());
+    }
+}
@@ -30,11 +30,13 @@ pub trait Store {
     fn add_genesis(&mut self, genesis: &Genesis) -> Result<bool, Self::Error>;
     fn remove_genesis(&mut self, id: &ContractId) -> Result<bool, Self::Error>;
 
+    fn anchor_ids(&self) -> Result<Vec<AnchorId>, Self::Error>;
     fn anchor(&self, id: &AnchorId) -> Result<Anchor, Self::Error>;
     fn has_anchor(&self, id: &AnchorId) -> Result<bool, Self::Error>;
     fn add_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error>;
     fn remove_anchor(&mut self, id: &AnchorId) -> Result<bool, Self::Error>;
 
+    fn transition_ids(&self) -> Result<Vec<NodeId>, Self::Error>;
     fn transition(&self, id: &NodeId) -> Result<Transition, Self::Error>;
     fn has_transition(&self, id: &NodeId) -> Result<bool, Self::Error>;
     fn add_transition(
@@ -43,6 +45,7 @@ pub trait Store {
     ) -> Result<bool, Self::Error>;
     fn remove_transition(&mut self, id: &NodeId) -> Result<bool, Self::Error>;
 
+    fn extension_ids(&self) -> Result<Vec<NodeId>, Self::Error>;
     fn extension(&self, id: &NodeId) -> Result<Extension, Self::Error>;
     fn has_extension(&self, id: &NodeId) -> Result<bool, Self::Error>;
     fn add_extension(
@@ -0,0 +1,532 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Validation of the consignments stored in files, which does not load the
+//! whole consignment into memory. The file is scanned once to build an index
+//! of node positions; after that each state transition is decoded from the
+//! file only for the time of its own validation and the validation of its
+//! direct descendants. Memory use is bounded by the size of the index and
+//! does not depend on the size of the state data, which makes possible to
+//! validate airdrop-scale consignments.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use bitcoin::{OutPoint, Transaction};
+use lnpbp::client_side_validation::CommitConceal;
+use lnpbp::strict_encoding::{Error, StrictDecode};
+use rgb::schema::OwnedRightType;
+use rgb::stash::RGB_CONSIGNMENT_VERSION;
+use rgb::validation::{Failure, Status, Warning};
+use rgb::{
+    seal, Anchor, Assignments, ConsignmentEndpoints, ContractId, Extension,
+    Genesis, Node, NodeId, Schema, Transition,
+};
+use wallet::resolvers::TxResolver;
+
+use crate::constants::RPC_MAX_CONSIGNMENT_ENDPOINTS;
+use crate::util::file::consignment_file;
+
+/// Reader keeping track of the number of bytes consumed from the file, so
+/// the positions of the decoded nodes can be recorded
+struct Tracker<R> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R> Read for Tracker<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Position {
+    Transition(u64),
+    Extension(u64),
+}
+
+/// Consignment node decoded from the file
+enum Loaded {
+    Genesis,
+    Transition(Anchor, Transition),
+    Extension(Extension),
+}
+
+/// Consignment file with the index of its state transitions and extensions.
+/// Only genesis and endpoints are kept in memory.
+pub struct ConsignmentFile {
+    file: fs::File,
+    genesis: Genesis,
+    endpoints: ConsignmentEndpoints,
+    index: BTreeMap<NodeId, Position>,
+}
+
+impl ConsignmentFile {
+    /// Opens consignment file and scans it, indexing positions of all state
+    /// transitions and extensions
+    pub fn open(filename: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = consignment_file(filename)?;
+        let mut reader = Tracker {
+            inner: BufReader::new(&file),
+            pos: (&file).seek(SeekFrom::Current(0))?,
+        };
+
+        let version = u16::strict_decode(&mut reader)?;
+        if version != RGB_CONSIGNMENT_VERSION {
+            Err(Error::UnsupportedDataStructure(
+                "consignment of unknown version",
+            ))?
+        }
+        let genesis = Genesis::strict_decode(&mut reader)?;
+        let count = usize::strict_decode(&mut reader)?;
+        if count > RPC_MAX_CONSIGNMENT_ENDPOINTS {
+            Err(Error::DataIntegrityError(format!(
+                "consignment has {} endpoints, while no more than {} are \
+                 allowed",
+                count, RPC_MAX_CONSIGNMENT_ENDPOINTS
+            )))?
+        }
+        let mut endpoints = Vec::with_capacity(count);
+        for _ in 0..count {
+            endpoints.push(StrictDecode::strict_decode(&mut reader)?);
+        }
+
+        let mut index = BTreeMap::new();
+        for _ in 0..usize::strict_decode(&mut reader)? {
+            let pos = reader.pos;
+            Anchor::strict_decode(&mut reader)?;
+            let transition = Transition::strict_decode(&mut reader)?;
+            index.insert(transition.node_id(), Position::Transition(pos));
+        }
+        for _ in 0..usize::strict_decode(&mut reader)? {
+            let pos = reader.pos;
+            let extension = Extension::strict_decode(&mut reader)?;
+            index.insert(extension.node_id(), Position::Extension(pos));
+        }
+
+        Ok(Self {
+            file,
+            genesis,
+            endpoints,
+            index,
+        })
+    }
+
+    #[inline]
+    pub fn genesis(&self) -> &Genesis {
+        &self.genesis
+    }
+
+    #[inline]
+    pub fn endpoints(&self) -> &ConsignmentEndpoints {
+        &self.endpoints
+    }
+
+    /// Number of state transitions and extensions in the consignment
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    fn load(&self, node_id: NodeId) -> Result<Option<Loaded>, Error> {
+        if node_id == self.genesis.node_id() {
+            return Ok(Some(Loaded::Genesis));
+        }
+        let (pos, is_transition) = match self.index.get(&node_id) {
+            None => return Ok(None),
+            Some(Position::Transition(pos)) => (*pos, true),
+            Some(Position::Extension(pos)) => (*pos, false),
+        };
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(pos))?;
+        let mut reader = BufReader::new(file);
+        Ok(Some(if is_transition {
+            Loaded::Transition(
+                Anchor::strict_decode(&mut reader)?,
+                Transition::strict_decode(&mut reader)?,
+            )
+        } else {
+            Loaded::Extension(Extension::strict_decode(&mut reader)?)
+        }))
+    }
+
+    fn node<'a>(&'a self, loaded: &'a Loaded) -> &'a dyn Node {
+        match loaded {
+            Loaded::Genesis => &self.genesis,
+            Loaded::Transition(_, transition) => transition,
+            Loaded::Extension(extension) => extension,
+        }
+    }
+}
+
+/// Validates consignment file against the schema. Performs the same checks
+/// as [`rgb::Validator`], but each node is validated only once, even if it
+/// is an ancestor of multiple endpoints.
+pub struct StreamingValidator<'file, R: TxResolver> {
+    consignment: &'file ConsignmentFile,
+    contract_id: ContractId,
+    status: Status,
+    validation_index: BTreeSet<NodeId>,
+    resolver: R,
+}
+
+impl<'file, R> StreamingValidator<'file, R>
+where
+    R: TxResolver,
+{
+    pub fn validate(
+        schema: &Schema,
+        consignment: &'file ConsignmentFile,
+        resolver: R,
+    ) -> Result<Status, Error> {
+        let mut validator = StreamingValidator {
+            consignment,
+            contract_id: consignment.genesis.contract_id(),
+            status: Status::default(),
+            validation_index: BTreeSet::new(),
+            resolver,
+        };
+
+        // [VALIDATION]: Schema must be the one used by the contract genesis;
+        //               otherwise there is no point in the rest of checks
+        let schema_id = consignment.genesis.schema_id();
+        if schema.schema_id() != schema_id {
+            validator
+                .status
+                .add_failure(Failure::SchemaUnknown(schema_id));
+            return Ok(validator.status);
+        }
+
+        validator.validate_branch(schema, consignment.genesis.node_id())?;
+        for node_id in validator.end_transitions()? {
+            validator.validate_branch(schema, node_id)?;
+        }
+
+        for node_id in consignment.index.keys() {
+            if !validator.validation_index.contains(node_id) {
+                validator
+                    .status
+                    .add_warning(Warning::ExcessiveTransition(*node_id));
+            }
+        }
+        Ok(validator.status)
+    }
+
+    fn end_transitions(&mut self) -> Result<Vec<NodeId>, Error> {
+        let mut end_transitions = Vec::<NodeId>::new();
+        for (node_id, seal_endpoint) in &self.consignment.endpoints {
+            let loaded = match self.consignment.load(*node_id)? {
+                Some(loaded) => loaded,
+                None => {
+                    self.status.add_failure(
+                        Failure::EndpointTransitionNotFound(*node_id),
+                    );
+                    continue;
+                }
+            };
+            let node = self.consignment.node(&loaded);
+            if !node
+                .all_seal_definitions()
+                .contains(&seal_endpoint.commit_conceal())
+            {
+                self.status.add_warning(
+                    Warning::EndpointTransitionSealNotFound(
+                        *node_id,
+                        *seal_endpoint,
+                    ),
+                );
+            } else if end_transitions.contains(node_id) {
+                self.status.add_warning(Warning::EndpointDuplication(
+                    *node_id,
+                    *seal_endpoint,
+                ));
+            } else {
+                end_transitions.push(*node_id);
+            }
+        }
+        Ok(end_transitions)
+    }
+
+    fn validate_branch(
+        &mut self,
+        schema: &Schema,
+        node_id: NodeId,
+    ) -> Result<(), Error> {
+        let mut queue = VecDeque::<NodeId>::new();
+        queue.push_back(node_id);
+        while let Some(node_id) = queue.pop_front() {
+            if !self.validation_index.insert(node_id) {
+                continue;
+            }
+            let loaded = match self.consignment.load(node_id)? {
+                Some(loaded) => loaded,
+                None => {
+                    self.status.add_failure(Failure::TransitionAbsent(node_id));
+                    continue;
+                }
+            };
+            let node = self.consignment.node(&loaded);
+
+            // Only the direct parents are required by the schema validation
+            // and by the check of the closed seals
+            let parent_ids = node
+                .parent_owned_rights()
+                .keys()
+                .chain(node.parent_public_rights().keys())
+                .copied()
+                .collect::<BTreeSet<_>>();
+            let mut parents = BTreeMap::new();
+            for parent_id in &parent_ids {
+                if let Some(parent) = self.consignment.load(*parent_id)? {
+                    parents.insert(*parent_id, parent);
+                }
+            }
+            let mut nodes = parents
+                .iter()
+                .map(|(id, parent)| (*id, self.consignment.node(parent)))
+                .collect::<BTreeMap<_, _>>();
+            nodes.insert(node_id, node);
+
+            // [VALIDATION]: Verify node against the schema
+            self.status += schema.validate(&nodes, node);
+
+            if let Loaded::Transition(ref anchor, _) = loaded {
+                // [VALIDATION]: Check that transition is committed into the
+                //               anchor
+                if !anchor.validate(&self.contract_id, &node_id) {
+                    self.status.add_failure(Failure::TransitionNotInAnchor(
+                        node_id,
+                        anchor.anchor_id(),
+                    ));
+                }
+                self.validate_graph_node(node, anchor, &parents);
+            }
+
+            queue.extend(parent_ids);
+        }
+        Ok(())
+    }
+
+    fn validate_graph_node(
+        &mut self,
+        node: &dyn Node,
+        anchor: &Anchor,
+        parents: &BTreeMap<NodeId, Loaded>,
+    ) {
+        let txid = anchor.txid;
+        let node_id = node.node_id();
+
+        let (witness_tx, fee) = match self.resolver.resolve(&txid) {
+            Err(_) => {
+                self.status.unresolved_txids.push(txid);
+                return;
+            }
+            Ok(None) => {
+                self.status
+                    .add_failure(Failure::WitnessTransactionMissed(txid));
+                return;
+            }
+            Ok(Some(resolved)) => resolved,
+        };
+
+        // [VALIDATION]: Checking anchor deterministic bitcoin commitment
+        if !anchor.verify(&self.contract_id, &witness_tx, fee) {
+            self.status.add_failure(Failure::WitnessNoCommitment(
+                node_id,
+                anchor.anchor_id(),
+                txid,
+            ));
+        }
+
+        // [VALIDATION]: Witness transaction must close the seals defined by
+        //               the transition ancestors
+        for (ancestor_id, assignments) in node.parent_owned_rights() {
+            let ancestor_id = *ancestor_id;
+            let ancestor = match parents.get(&ancestor_id) {
+                Some(ancestor) => ancestor,
+                None => {
+                    self.status
+                        .add_failure(Failure::TransitionAbsent(ancestor_id));
+                    continue;
+                }
+            };
+            let ancestor_txid = match ancestor {
+                Loaded::Transition(anchor, _) => Some(anchor.txid),
+                _ => None,
+            };
+            let ancestor_node = self.consignment.node(ancestor);
+
+            for (assignment_type, seal_indexes) in assignments {
+                let assignment_type = *assignment_type;
+                let variant =
+                    match ancestor_node.owned_rights_by_type(assignment_type) {
+                        Some(variant) => variant,
+                        None => {
+                            self.status.add_failure(
+                                Failure::TransitionParentWrongSealType {
+                                    node_id,
+                                    ancestor_id,
+                                    assignment_type,
+                                },
+                            );
+                            continue;
+                        }
+                    };
+                for seal_index in seal_indexes {
+                    self.validate_witness_input(
+                        &witness_tx,
+                        node_id,
+                        (ancestor_id, ancestor_txid),
+                        assignment_type,
+                        variant,
+                        *seal_index,
+                    );
+                }
+            }
+        }
+    }
+
+    fn validate_witness_input(
+        &mut self,
+        witness_tx: &Transaction,
+        node_id: NodeId,
+        (ancestor_id, ancestor_txid): (NodeId, Option<bitcoin::Txid>),
+        assignment_type: OwnedRightType,
+        variant: &Assignments,
+        seal_index: u16,
+    ) {
+        let outpoint =
+            match (variant.seal_definition(seal_index), ancestor_txid) {
+                (Ok(Some(seal::Revealed::TxOutpoint(outpoint))), None) => {
+                    OutPoint::from(outpoint)
+                }
+                (Ok(Some(seal)), Some(txid)) => {
+                    OutPoint::from(seal.outpoint_reveal(txid))
+                }
+                (Ok(None), _) => {
+                    self.status.add_failure(
+                        Failure::TransitionParentConfidentialSeal {
+                            node_id,
+                            ancestor_id,
+                            assignment_type,
+                            seal_index,
+                        },
+                    );
+                    return;
+                }
+                // Witness vout seals can't be defined by the nodes which do not
+                // have a witness transaction
+                (Err(_), _) | (Ok(Some(_)), None) => {
+                    self.status.add_failure(
+                        Failure::TransitionParentWrongSeal {
+                            node_id,
+                            ancestor_id,
+                            assignment_type,
+                            seal_index,
+                        },
+                    );
+                    return;
+                }
+            };
+
+        if !witness_tx
+            .input
+            .iter()
+            .any(|txin| txin.previous_output == outpoint)
+        {
+            self.status.add_failure(
+                Failure::TransitionParentIsNotWitnessInput {
+                    node_id,
+                    ancestor_id,
+                    assignment_type,
+                    seal_index,
+                    outpoint,
+                },
+            );
+        }
+    }
+}
+
+#[cfg(all(test, feature = "fungibles"))]
+mod test {
+    use std::env;
+
+    use bitcoin::Txid;
+    use lnpbp::Chain;
+    use rgb::{Consignment, SealEndpoint};
+    use rgb20::schema::OwnedRightsType;
+
+    use super::*;
+    use crate::util::file::ReadWrite;
+    use crate::validator::FnResolver;
+
+    #[test]
+    fn test_streaming_validation() {
+        let outpoint = OutPoint {
+            txid: Default::default(),
+            vout: 0,
+        };
+        let (_, genesis) = rgb20::issue(
+            Chain::Regtest(Default::default()),
+            s!("TEST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint, 100)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let seal = genesis
+            .owned_rights_by_type(*OwnedRightsType::Assets)
+            .unwrap()
+            .seal_definition(0)
+            .unwrap()
+            .unwrap();
+        let endpoints = vec![
+            (genesis.node_id(), SealEndpoint::from(seal)),
+            (NodeId::default(), SealEndpoint::from(seal)),
+        ];
+        let consignment = Consignment::with(genesis, endpoints, vec![], vec![]);
+        let filename = env::temp_dir().join("rgb-test-streaming.rgbc");
+        consignment.write_file(&filename).unwrap();
+
+        let schema = rgb20::schema::schema();
+        let resolver = || FnResolver(|_: &Txid| Ok(None));
+        let file = ConsignmentFile::open(&filename).unwrap();
+        assert!(file.is_empty());
+        let status =
+            StreamingValidator::validate(&schema, &file, resolver()).unwrap();
+        let expected = consignment.validate(&schema, resolver());
+        assert_eq!(status.failures, expected.failures);
+        assert_eq!(status.warnings, expected.warnings);
+        assert_eq!(
+            status.failures,
+            vec![Failure::EndpointTransitionNotFound(NodeId::default())]
+        );
+    }
+}
@@ -0,0 +1,454 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Deterministic harness for the integration tests of the applications
+//! built on top of the node. Fungible contract runtime is run in-process
+//! against [`MockStash`], which validates consignments with the
+//! [`ScriptedResolver`], so transfer and accept flows can be tested without
+//! a real stash daemon or a regtest chain.
+
+mod resolver;
+mod stash;
+
+pub use resolver::ScriptedResolver;
+pub use stash::{MockStash, StashHandler};
+
+use std::path::Path;
+
+use crate::error::BootstrapError;
+use crate::fungibled;
+use crate::i9n::{Embedded, Error};
+use crate::rpc::fungible::Request;
+use crate::rpc::Reply;
+
+/// Electrum server address which is never reachable, so the runtime does
+/// not try to query the chain
+const UNREACHABLE_ELECTRUM: &'static str = "127.0.0.1:1";
+
+/// Fungible runtime configuration storing its cache in `data_dir` and not
+/// requiring any confirmations or chain backend
+pub fn config(data_dir: impl AsRef<Path>) -> fungibled::Config {
+    let data_dir = data_dir.as_ref();
+    fungibled::Config {
+        data_dir: data_dir.to_path_buf(),
+        cache: data_dir.join("cache").display().to_string(),
+        electrum_server: UNREACHABLE_ELECTRUM.to_string(),
+        min_confirmations: 0,
+        ..fungibled::Config::default()
+    }
+}
+
+/// Fungible contract runtime run in-process against the mock stash
+pub struct TestNode {
+    node: Embedded,
+    stash: MockStash,
+}
+
+impl TestNode {
+    pub fn start(
+        config: fungibled::Config,
+        stash: MockStash,
+    ) -> Result<Self, BootstrapError> {
+        let node = Embedded::with_stash_session(stash.spawn(), config)?;
+        Ok(Self { node, stash })
+    }
+
+    /// Embedded node providing asynchronous API to the runtime
+    #[inline]
+    pub fn node(&self) -> &Embedded {
+        &self.node
+    }
+
+    #[inline]
+    pub fn stash(&self) -> &MockStash {
+        &self.stash
+    }
+
+    /// Sends request to the runtime and waits for the reply
+    pub fn request(&self, request: Request) -> Result<Reply, Error> {
+        self.node.request_blocking(request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+    use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+    use bitcoin::{OutPoint, Transaction};
+    use chrono::Utc;
+    use lnpbp::seals::OutpointReveal;
+    use rgb::{Node, SealDefinition, SealEndpoint};
+    use rgb20::{Asset, OutpointCoins};
+
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::fungibled::SpendPolicy;
+    use crate::rpc::fungible::{
+        HoldTarget, IssueAllocation, IssueReq, SetHoldReq, TransferReq,
+    };
+    use crate::rpc::stash;
+
+    fn data_dir(name: &str) -> std::path::PathBuf {
+        env::temp_dir().join(format!(
+            "rgb-node-testing-{}-{}",
+            name,
+            std::process::id()
+        ))
+    }
+
+    fn issue(node: &TestNode, outpoint: OutPoint) -> Asset {
+        issue_coins(node, outpoint, 100)
+    }
+
+    fn issue_coins(node: &TestNode, outpoint: OutPoint, coins: u64) -> Asset {
+        let issue = IssueReq {
+            ticker: s!("TEST"),
+            name: s!("Test asset"),
+            description: None,
+            precision: 0,
+            allocation: vec![IssueAllocation::from(OutpointCoins {
+                coins,
+                outpoint,
+            })],
+            inflation: vec![],
+            renomination: None,
+            epoch: None,
+            no_inflation: false,
+            no_renomination: false,
+            no_burn: false,
+        };
+        match node.request(Request::Issue(issue)).unwrap() {
+            Reply::Asset(asset) => asset,
+            reply => panic!("unexpected reply {}", reply),
+        }
+    }
+
+    #[test]
+    fn test_mock_stash_issue() {
+        let node = TestNode::start(
+            config(data_dir("issue")),
+            MockStash::new(Default::default()),
+        )
+        .unwrap();
+        let asset = issue(&node, OutPoint::default());
+
+        let geneses = node.stash().geneses();
+        assert_eq!(geneses.len(), 1);
+        assert_eq!(geneses[0].contract_id(), *asset.id());
+        assert!(node
+            .stash()
+            .requests()
+            .iter()
+            .any(|request| matches!(request, stash::Request::AddSchema(_))));
+    }
+
+    #[test]
+    fn test_held_allocation_transfer() {
+        let secp = Secp256k1::new();
+        let admin_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let mut config = config(data_dir("hold"));
+        config.admins = vec![PublicKey::from_secret_key(&secp, &admin_key)];
+        let node = TestNode::start(config, MockStash::new(Default::default()))
+            .unwrap();
+        let outpoint = OutPoint::default();
+        let asset = issue(&node, outpoint);
+
+        let hold = SetHoldReq::sign(
+            HoldTarget::Outpoint(outpoint),
+            true,
+            Utc::now().timestamp(),
+            &admin_key,
+        );
+        match node.request(Request::SetHold(hold)).unwrap() {
+            Reply::Success => {}
+            reply => panic!("unexpected reply {}", reply),
+        }
+
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        };
+        let transfer = TransferReq {
+            contract_id: *asset.id(),
+            witness: Psbt::from_unsigned_tx(tx).unwrap(),
+            inputs: bset![outpoint],
+            payment: bmap! {
+                SealEndpoint::WitnessVout { vout: 0, blinding: 0 } => 100
+            },
+            theirs_witness: bmap! {},
+            change: bmap! {},
+            confirmed_only: false,
+            container: None,
+        };
+        match node.request(Request::Transfer(transfer)).unwrap() {
+            Reply::Failure(failure) => {
+                assert_eq!(failure.code, ErrorCode::AllocationOnHold as u16)
+            }
+            reply => panic!("unexpected reply {}", reply),
+        }
+    }
+
+    #[test]
+    fn test_hold_of_other_asset() {
+        let secp = Secp256k1::new();
+        let admin_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let mut config = config(data_dir("hold-other"));
+        config.admins = vec![PublicKey::from_secret_key(&secp, &admin_key)];
+        let node = TestNode::start(config, MockStash::new(Default::default()))
+            .unwrap();
+        let held = issue(&node, OutPoint::new(Default::default(), 1));
+        let outpoint = OutPoint::new(Default::default(), 2);
+        let asset = issue(&node, outpoint);
+
+        let hold = SetHoldReq::sign(
+            HoldTarget::Contract(*held.id()),
+            true,
+            Utc::now().timestamp(),
+            &admin_key,
+        );
+        match node.request(Request::SetHold(hold)).unwrap() {
+            Reply::Success => {}
+            reply => panic!("unexpected reply {}", reply),
+        }
+
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        };
+        let transfer = TransferReq {
+            contract_id: *asset.id(),
+            witness: Psbt::from_unsigned_tx(tx).unwrap(),
+            inputs: bset![outpoint],
+            payment: bmap! {
+                SealEndpoint::WitnessVout { vout: 0, blinding: 0 } => 100
+            },
+            theirs_witness: bmap! {},
+            change: bmap! {},
+            confirmed_only: false,
+            container: None,
+        };
+        // Hold of the asset not allocated on the input does not block the
+        // transfer, which reaches the stash
+        if let Reply::Failure(failure) =
+            node.request(Request::Transfer(transfer)).unwrap()
+        {
+            assert_ne!(failure.code, ErrorCode::AllocationOnHold as u16);
+        }
+        assert!(node
+            .stash()
+            .requests()
+            .iter()
+            .any(|request| matches!(request, stash::Request::Transfer(_))));
+    }
+
+    #[test]
+    fn test_foreign_change_with_other_assets() {
+        let dir = data_dir("foreign-change");
+        std::fs::create_dir_all(&dir).unwrap();
+        let policy = dir.join("policy.json");
+        std::fs::write(&policy, "{}").unwrap();
+        let mut config = config(&dir);
+        config.policy = Some(policy.clone());
+        let node = TestNode::start(config, MockStash::new(Default::default()))
+            .unwrap();
+        // Other asset is cached, but has no allocations on the change seal
+        issue(&node, OutPoint::new(Default::default(), 1));
+        let outpoint = OutPoint::new(Default::default(), 2);
+        let asset = issue(&node, outpoint);
+
+        let payee = SealEndpoint::WitnessVout {
+            vout: 0,
+            blinding: 0,
+        };
+        let policies = bmap! {
+            *asset.id() => SpendPolicy {
+                allowed_recipients: Some(bset![payee]),
+                ..SpendPolicy::default()
+            }
+        };
+        serde_json::to_writer(
+            std::fs::File::create(&policy).unwrap(),
+            &policies,
+        )
+        .unwrap();
+        match node.request(Request::ReloadConfig).unwrap() {
+            Reply::Success => {}
+            reply => panic!("unexpected reply {}", reply),
+        }
+
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        };
+        let foreign = SealDefinition::TxOutpoint(OutpointReveal {
+            blinding: 7,
+            txid: Default::default(),
+            vout: 9,
+        });
+        let transfer = TransferReq {
+            contract_id: *asset.id(),
+            witness: Psbt::from_unsigned_tx(tx).unwrap(),
+            inputs: bset![outpoint],
+            payment: bmap! { payee => 60 },
+            theirs_witness: bmap! {},
+            change: bmap! { foreign => 40 },
+            confirmed_only: false,
+            container: None,
+        };
+        match node.request(Request::Transfer(transfer)).unwrap() {
+            Reply::Failure(failure) => {
+                assert_eq!(failure.code, ErrorCode::ChangeNotOwned as u16)
+            }
+            reply => panic!("unexpected reply {}", reply),
+        }
+    }
+
+    #[test]
+    fn test_blank_transition_change() {
+        let node = TestNode::start(
+            config(data_dir("blank-change")),
+            MockStash::new(Default::default()),
+        )
+        .unwrap();
+        let outpoint = OutPoint::new(Default::default(), 1);
+        let asset = issue(&node, outpoint);
+        let other = issue_coins(&node, outpoint, 50);
+
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        };
+        let payee = SealEndpoint::WitnessVout {
+            vout: 0,
+            blinding: 0,
+        };
+        let foreign = SealDefinition::TxOutpoint(OutpointReveal {
+            blinding: 7,
+            txid: Default::default(),
+            vout: 9,
+        });
+        let mut transfer = TransferReq {
+            contract_id: *asset.id(),
+            witness: Psbt::from_unsigned_tx(tx).unwrap(),
+            inputs: bset![outpoint],
+            payment: bmap! { payee => 60 },
+            theirs_witness: bmap! {},
+            change: bmap! { foreign => 40 },
+            confirmed_only: false,
+            container: None,
+        };
+        // Other asset on the input can't be moved to the seal controlled by
+        // the payee
+        match node.request(Request::Transfer(transfer.clone())).unwrap() {
+            Reply::Failure(failure) => {
+                assert_eq!(failure.code, ErrorCode::NoOwnedChange as u16)
+            }
+            reply => panic!("unexpected reply {}", reply),
+        }
+
+        let own = match node
+            .request(Request::Blind(OutPoint::new(Default::default(), 2)))
+            .unwrap()
+        {
+            Reply::OutpointReveal(reveal) => SealDefinition::TxOutpoint(reveal),
+            reply => panic!("unexpected reply {}", reply),
+        };
+        transfer.change = bmap! { foreign => 20, own => 20 };
+        let _ = node.request(Request::Transfer(transfer));
+        let requests = node.stash().requests();
+        let request = requests
+            .iter()
+            .find_map(|request| match request {
+                stash::Request::Transfer(request)
+                    if request.other_transitions.contains_key(other.id()) =>
+                {
+                    Some(request)
+                }
+                _ => None,
+            })
+            .expect("blank transition is not requested");
+        // Consignment of the other asset is requested for the own seal
+        assert_eq!(
+            request.other_endpoints.get(other.id()),
+            Some(&bset![SealEndpoint::from(own)])
+        );
+        let seals = request.other_transitions[other.id()]
+            .owned_rights()
+            .values()
+            .flat_map(|assignments| assignments.known_seal_definitions())
+            .collect::<Vec<_>>();
+        assert_eq!(seals, vec![own]);
+    }
+
+    #[test]
+    fn test_witness_payment_endpoint() {
+        let node = TestNode::start(
+            config(data_dir("witness-payment")),
+            MockStash::new(Default::default()),
+        )
+        .unwrap();
+        let outpoint = OutPoint::new(Default::default(), 1);
+        let asset = issue_coins(&node, outpoint, 100);
+
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        };
+        let transfer = TransferReq {
+            contract_id: *asset.id(),
+            witness: Psbt::from_unsigned_tx(tx).unwrap(),
+            inputs: bset![outpoint],
+            payment: bmap! {},
+            theirs_witness: bmap! { 1 => 100 },
+            change: bmap! {},
+            confirmed_only: false,
+            container: None,
+        };
+        let _ = node.request(Request::Transfer(transfer));
+        let request = node
+            .stash()
+            .requests()
+            .into_iter()
+            .find_map(|request| match request {
+                stash::Request::Transfer(request) => Some(request),
+                _ => None,
+            })
+            .expect("consignment is not requested");
+        // Witness output seal is assigned by the node and its blinding is
+        // passed to the stash as a consignment endpoint
+        let seals = request
+            .transition
+            .owned_rights()
+            .values()
+            .flat_map(|assignments| assignments.known_seal_definitions())
+            .collect::<Vec<_>>();
+        match seals.as_slice() {
+            [seal @ SealDefinition::WitnessVout { vout: 1, .. }] => {
+                assert_eq!(request.endpoints, bset![SealEndpoint::from(*seal)])
+            }
+            _ => panic!("unexpected transition seals {:?}", seals),
+        }
+    }
+}
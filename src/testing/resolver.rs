@@ -0,0 +1,67 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use bitcoin::{Transaction, Txid};
+use wallet::resolvers::{TxResolver, TxResolverError};
+
+/// Transaction resolver answering from the set of transactions provided by
+/// the test instead of a chain backend. Clones share the same set, so the
+/// transactions may be added after the resolver was passed to the
+/// [`super::MockStash`].
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedResolver {
+    transactions: Arc<Mutex<BTreeMap<Txid, (Transaction, u64)>>>,
+}
+
+impl ScriptedResolver {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes transaction known to the resolver, together with the fee paid
+    /// by it
+    pub fn add(&self, tx: Transaction, fee: u64) {
+        self.transactions
+            .lock()
+            .expect("scripted resolver lock is poisoned")
+            .insert(tx.txid(), (tx, fee));
+    }
+
+    /// Makes transaction unknown to the resolver, as if it was not mined or
+    /// was evicted from mempool
+    pub fn remove(&self, txid: Txid) -> Option<Transaction> {
+        self.transactions
+            .lock()
+            .expect("scripted resolver lock is poisoned")
+            .remove(&txid)
+            .map(|(tx, _)| tx)
+    }
+}
+
+impl TxResolver for ScriptedResolver {
+    fn resolve(
+        &self,
+        txid: &Txid,
+    ) -> Result<Option<(Transaction, u64)>, TxResolverError> {
+        Ok(self
+            .transactions
+            .lock()
+            .map_err(|_| TxResolverError)?
+            .get(txid)
+            .cloned())
+    }
+}
@@ -0,0 +1,191 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use internet2::{CreateUnmarshaller, Session, TypedEnum, Unmarshall};
+use rgb::{ContractId, Genesis, Schema, SchemaId};
+
+use super::ScriptedResolver;
+use crate::error::{ServiceError, ServiceErrorDomain, ServiceErrorSource};
+use crate::i9n::ChannelSession;
+use crate::rpc::stash::Request;
+use crate::rpc::Reply;
+use crate::util::compression::{
+    compress, decompress, RPC_COMPRESSION_THRESHOLD,
+};
+use crate::validator;
+
+/// Function scripting stash replies. Returning `None` falls back to the
+/// default behaviour of [`MockStash`].
+pub type StashHandler = Box<dyn FnMut(&Request) -> Option<Reply> + Send>;
+
+#[derive(Default)]
+struct State {
+    handler: Option<StashHandler>,
+    schemata: BTreeMap<SchemaId, Schema>,
+    geneses: BTreeMap<ContractId, Genesis>,
+    requests: Vec<Request>,
+}
+
+/// In-memory replacement of the stash daemon.
+///
+/// Schemata and geneses are kept in memory, consignments are validated with
+/// the [`ScriptedResolver`] and the rest of the state-changing requests are
+/// acknowledged without doing anything. Requests which require the real
+/// stash data (like transfer consignments or contract history) fail unless
+/// their replies are scripted with [`MockStash::with_handler`]. All received
+/// requests are recorded and can be inspected with [`MockStash::requests`].
+#[derive(Clone)]
+pub struct MockStash {
+    resolver: ScriptedResolver,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockStash {
+    pub fn new(resolver: ScriptedResolver) -> Self {
+        Self {
+            resolver,
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// Sets function providing replies for the requests before the default
+    /// processing
+    pub fn with_handler(
+        self,
+        handler: impl FnMut(&Request) -> Option<Reply> + Send + 'static,
+    ) -> Self {
+        self.state().handler = Some(Box::new(handler));
+        self
+    }
+
+    #[inline]
+    pub fn resolver(&self) -> &ScriptedResolver {
+        &self.resolver
+    }
+
+    /// Requests received by the stash so far, in the order of their arrival
+    pub fn requests(&self) -> Vec<Request> {
+        self.state().requests.clone()
+    }
+
+    /// Contract geneses known to the stash
+    pub fn geneses(&self) -> Vec<Genesis> {
+        self.state().geneses.values().cloned().collect()
+    }
+
+    /// Starts serving stash requests on a separate thread; the thread stops
+    /// once the returned session is dropped
+    pub fn spawn(&self) -> ChannelSession {
+        let (client, mut server) = ChannelSession::pair();
+        let stash = self.clone();
+        thread::spawn(move || {
+            while let Ok(raw) = server.recv_raw_message() {
+                let reply = stash.process(raw).unwrap_or_else(|err| err);
+                let data =
+                    compress(reply.serialize(), RPC_COMPRESSION_THRESHOLD);
+                if server.send_raw_message(&data).is_err() {
+                    break;
+                }
+            }
+        });
+        client
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("mock stash lock is poisoned")
+    }
+
+    fn process(&self, raw: Vec<u8>) -> Result<Reply, Reply> {
+        let raw = decompress(raw).map_err(|err| {
+            ServiceError::from_rpc(ServiceErrorSource::Stash, err)
+        })?;
+        let request =
+            &*Request::create_unmarshaller().unmarshall(&raw).map_err(
+                |err| ServiceError::from_rpc(ServiceErrorSource::Stash, err),
+            )?;
+        debug!("Mock stash received {}", request);
+
+        let mut state = self.state();
+        state.requests.push(request.clone());
+        if let Some(reply) =
+            state.handler.as_mut().and_then(|handler| handler(request))
+        {
+            return Ok(reply);
+        }
+
+        match request {
+            Request::AddSchema(schema) => {
+                state.schemata.insert(schema.schema_id(), schema.clone());
+                Ok(Reply::Success)
+            }
+            Request::ListSchemata() => {
+                Ok(Reply::SchemaIds(state.schemata.keys().cloned().collect()))
+            }
+            Request::ReadSchema(schema_id) => state
+                .schemata
+                .get(schema_id)
+                .cloned()
+                .map(Reply::Schema)
+                .ok_or(ServiceErrorDomain::Storage(format!(
+                    "unknown schema {}",
+                    schema_id
+                ))),
+            Request::AddGenesis(genesis) => {
+                state.geneses.insert(genesis.contract_id(), genesis.clone());
+                Ok(Reply::Success)
+            }
+            Request::ListGeneses() => {
+                Ok(Reply::ContractIds(state.geneses.keys().cloned().collect()))
+            }
+            Request::ReadGenesis(contract_id) => state
+                .geneses
+                .get(contract_id)
+                .cloned()
+                .map(Reply::Genesis)
+                .ok_or(ServiceErrorDomain::Storage(format!(
+                    "unknown contract {}",
+                    contract_id
+                ))),
+            Request::Validate(consignment) => {
+                validator::validate(consignment, self.resolver.clone())
+                    .map(Reply::ValidationStatus)
+                    .map_err(|err| ServiceErrorDomain::Schema(err.to_string()))
+            }
+            Request::Accept(accept) => {
+                let genesis = &accept.consignment.genesis;
+                state.geneses.insert(genesis.contract_id(), genesis.clone());
+                Ok(Reply::Success)
+            }
+            Request::Enclose(_)
+            | Request::Forget(_)
+            | Request::Gc()
+            | Request::Resync()
+            | Request::Backup(_)
+            | Request::ReloadConfig() => Ok(Reply::Success),
+            _ => Err(ServiceErrorDomain::Internal(format!(
+                "request {} is not scripted for the mock stash",
+                request
+            ))),
+        }
+        .map_err(|err| {
+            Reply::from(ServiceError {
+                domain: err,
+                service: ServiceErrorSource::Stash,
+            })
+        })
+    }
+}
@@ -0,0 +1,252 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Conversion of asset amounts between atomic units and decimal strings.
+//!
+//! Decimal strings always use `.` as the decimal separator and contain no
+//! digit grouping, sign or exponent, so they do not depend on the user locale
+//! and are parsed back into exactly the same atomic value.
+
+#[cfg(feature = "serde")]
+use core::convert::TryFrom;
+#[cfg(feature = "serde")]
+use core::fmt;
+#[cfg(feature = "serde")]
+use serde::{de, Deserializer, Serializer};
+#[cfg(feature = "serde")]
+use serde_with::{DeserializeAs, SerializeAs};
+
+use rgb::AtomicValue;
+
+/// Error parsing decimal asset amount
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum AmountParseError {
+    /// amount is not given
+    Empty,
+
+    /// amount contains invalid character '{0}'; only decimal digits and a
+    /// single '.' separator are allowed
+    InvalidCharacter(char),
+
+    /// amount has {found} significant fractional digits, while the asset
+    /// precision is {precision}
+    ExcessPrecision { found: usize, precision: u8 },
+
+    /// amount does not fit into 64-bit atomic value
+    Overflow,
+}
+
+/// Renders atomic value as a decimal number with the asset precision,
+/// keeping all fractional digits
+pub fn format_amount(value: AtomicValue, precision: u8) -> String {
+    if precision == 0 {
+        return value.to_string();
+    }
+    let precision = precision as usize;
+    let digits = format!("{:0>width$}", value, width = precision + 1);
+    let (int, fract) = digits.split_at(digits.len() - precision);
+    format!("{}.{}", int, fract)
+}
+
+/// Parses decimal number into atomic value using the asset precision.
+/// Fractional digits beyond the precision are allowed only if they are
+/// zeros.
+pub fn parse_amount(
+    s: &str,
+    precision: u8,
+) -> Result<AtomicValue, AmountParseError> {
+    let (int, fract) = match s.find('.') {
+        Some(pos) => (&s[..pos], &s[pos + 1..]),
+        None => (s, ""),
+    };
+    if int.is_empty() && fract.is_empty() {
+        Err(AmountParseError::Empty)?
+    }
+    if let Some(ch) = int
+        .chars()
+        .chain(fract.chars())
+        .find(|ch| !ch.is_ascii_digit())
+    {
+        Err(AmountParseError::InvalidCharacter(ch))?
+    }
+    let fract = fract.trim_end_matches('0');
+    if fract.len() > precision as usize {
+        Err(AmountParseError::ExcessPrecision {
+            found: fract.len(),
+            precision,
+        })?
+    }
+    format!("{}{:0<width$}", int, fract, width = precision as usize)
+        .chars()
+        .try_fold(0 as AtomicValue, |value, digit| {
+            value
+                .checked_mul(10)?
+                .checked_add(digit.to_digit(10)? as AtomicValue)
+        })
+        .ok_or(AmountParseError::Overflow)
+}
+
+/// Serde adaptor representing atomic values as decimal strings, since JSON
+/// numbers are parsed into floating-point values by many clients, losing
+/// precision for the amounts above 2^53. For compatibility with the older
+/// clients the amounts are also accepted as JSON numbers, as long as they are
+/// non-negative and have no fractional part.
+///
+/// Use with `#[serde(with = "As::<AtomicValueString>")]`.
+#[cfg(feature = "serde")]
+pub struct AtomicValueString;
+
+#[cfg(feature = "serde")]
+impl SerializeAs<AtomicValue> for AtomicValueString {
+    fn serialize_as<S>(
+        value: &AtomicValue,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&value.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> DeserializeAs<'de, AtomicValue> for AtomicValueString {
+    fn deserialize_as<D>(deserializer: D) -> Result<AtomicValue, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = AtomicValue;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("atomic value as a string of decimal digits")
+            }
+
+            fn visit_str<E: de::Error>(
+                self,
+                v: &str,
+            ) -> Result<Self::Value, E> {
+                if v.is_empty() || !v.chars().all(|ch| ch.is_ascii_digit()) {
+                    Err(E::invalid_value(de::Unexpected::Str(v), &self))?
+                }
+                v.parse().map_err(|_| E::custom(AmountParseError::Overflow))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(v)
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                AtomicValue::try_from(v).map_err(|_| {
+                    E::invalid_value(de::Unexpected::Signed(v), &self)
+                })
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                // Floats above 2^53 can't be trusted to keep the exact value
+                const MAX_EXACT: f64 = (1u64 << 53) as f64;
+                if v < 0.0 || v.fract() != 0.0 || v > MAX_EXACT {
+                    Err(E::invalid_value(de::Unexpected::Float(v), &self))?
+                }
+                Ok(v as AtomicValue)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "serde")]
+    use serde::{Deserialize, Serialize};
+    #[cfg(feature = "serde")]
+    use serde_with::As;
+
+    #[test]
+    fn test_amount_roundtrip() {
+        assert_eq!(format_amount(5, 0), "5");
+        assert_eq!(format_amount(5, 2), "0.05");
+        assert_eq!(format_amount(123456, 3), "123.456");
+        assert_eq!(
+            format_amount(AtomicValue::MAX, 19),
+            "1.8446744073709551615"
+        );
+
+        assert_eq!(parse_amount("0.05", 2), Ok(5));
+        assert_eq!(parse_amount(".05", 2), Ok(5));
+        assert_eq!(parse_amount("123.", 3), Ok(123000));
+        assert_eq!(parse_amount("1.500", 1), Ok(15));
+        assert_eq!(
+            parse_amount("1.8446744073709551615", 19),
+            Ok(AtomicValue::MAX)
+        );
+        assert_eq!(
+            parse_amount("1.8446744073709551616", 19),
+            Err(AmountParseError::Overflow)
+        );
+        assert_eq!(
+            parse_amount("1.05", 1),
+            Err(AmountParseError::ExcessPrecision {
+                found: 2,
+                precision: 1
+            })
+        );
+        assert_eq!(
+            parse_amount("1,5", 1),
+            Err(AmountParseError::InvalidCharacter(','))
+        );
+        assert_eq!(
+            parse_amount("-1", 1),
+            Err(AmountParseError::InvalidCharacter('-'))
+        );
+        assert_eq!(parse_amount(".", 1), Err(AmountParseError::Empty));
+
+        for value in &[0, 1, 10, 999, 1_000_000, AtomicValue::MAX] {
+            for precision in 0..=19 {
+                let s = format_amount(*value, precision);
+                assert_eq!(parse_amount(&s, precision), Ok(*value));
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_atomic_value_string() {
+        #[derive(PartialEq, Debug, Serialize, Deserialize)]
+        #[serde(crate = "serde_crate")]
+        struct Params {
+            #[serde(with = "As::<AtomicValueString>")]
+            amount: AtomicValue,
+        }
+
+        let params = Params {
+            amount: AtomicValue::MAX,
+        };
+        let json = serde_json::to_string(&params).unwrap();
+        assert_eq!(json, r#"{"amount":"18446744073709551615"}"#);
+        assert_eq!(serde_json::from_str::<Params>(&json).unwrap(), params);
+
+        let parse = |json: &str| serde_json::from_str::<Params>(json).ok();
+        assert_eq!(parse(r#"{"amount":10}"#), Some(Params { amount: 10 }));
+        assert_eq!(parse(r#"{"amount":10.0}"#), Some(Params { amount: 10 }));
+        assert_eq!(parse(r#"{"amount":10.5}"#), None);
+        assert_eq!(parse(r#"{"amount":-10}"#), None);
+        assert_eq!(parse(r#"{"amount":"1e3"}"#), None);
+        assert_eq!(parse(r#"{"amount":""}"#), None);
+    }
+}
@@ -0,0 +1,290 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Anchoring of the state transitions in the witness transaction. Besides the
+//! pay-to-contract tweak of the existing output key done by RGB Core, the
+//! commitment may be put into an OP_RETURN output appended to the witness
+//! transaction, for the wallets which are unable to sign for tweaked keys.
+//!
+//! The output holding the commitment is selected deterministically from the
+//! contract id, number of the transaction outputs and the transaction fee.
+//! To keep the indexes of the existing outputs (which may be used by the
+//! witness transaction seals) the OP_RETURN output is always appended, and
+//! its value (which is taken from the fee) is selected to make it the
+//! commitment output.
+
+use core::str::FromStr;
+use std::collections::{BTreeMap, HashMap};
+
+use amplify::Wrapper;
+use bitcoin::hashes::{sha256, sha256d, Hash, HashEngine};
+use bitcoin::secp256k1;
+use bitcoin::util::psbt::raw::ProprietaryKey;
+use bitcoin::util::psbt::{self, PartiallySignedTransaction as Psbt};
+use bitcoin::util::uint::Uint256;
+use lnpbp::commit_verify::{CommitVerify, EmbedCommitVerify, TryCommitVerify};
+use lnpbp::dbc::{
+    Container, ScriptEncodeData, ScriptEncodeMethod, SpkContainer,
+    TxoutCommitment, TxoutContainer,
+};
+use lnpbp::lnpbp4::{MessageMap, MultimsgCommitment};
+use rgb::{
+    Anchor, ContractId, NodeId, PSBT_OUT_PUBKEY, PSBT_OUT_TWEAK, PSBT_PREFIX,
+};
+use wallet::psbt::Fee;
+use wallet::SECP256K1;
+
+/// Number of attempts to find OP_RETURN commitment key; each of the attempts
+/// succeeds with 1/2 probability
+const OP_RETURN_KEY_ATTEMPTS: u32 = 128;
+
+/// Witness transaction output type holding the anchor commitment
+#[derive(
+    Copy, Clone, PartialEq, Eq, Hash, Debug, Display, StrictEncode, StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[repr(u8)]
+pub enum AnchorContainer {
+    /// Pay-to-contract tweak of the existing output key, which must be
+    /// provided with `RGB` proprietary PSBT output key
+    #[display("tweak")]
+    Tweak = 1,
+
+    /// OP_RETURN output appended to the witness transaction
+    #[display("op-return")]
+    OpReturn = 2,
+}
+
+impl Default for AnchorContainer {
+    fn default() -> Self {
+        AnchorContainer::Tweak
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(
+    "unknown anchor container `{0}`; it must be either `tweak` or `op-return`"
+)]
+pub struct AnchorContainerError(String);
+
+impl FromStr for AnchorContainer {
+    type Err = AnchorContainerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tweak" | "p2c" | "pay-to-contract" => Ok(AnchorContainer::Tweak),
+            "op-return" | "opret" => Ok(AnchorContainer::OpReturn),
+            _ => Err(AnchorContainerError(s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum Error {
+    /// {0}
+    Commitment(String),
+
+    /// Unable to compute witness transaction fee: {0}
+    Fee(String),
+
+    /// Contracts {0} and {1} are committed to different outputs of the
+    /// witness transaction, while OP_RETURN anchor has a single output
+    OutputMismatch(ContractId, ContractId),
+
+    /// Witness transaction fee of {fee} sats is too small to place OP_RETURN
+    /// commitment, which requires at least {required} sats
+    InsufficientFee { fee: u64, required: u64 },
+
+    /// Too many state transitions for commitment; can't fit into a single
+    /// anchor
+    SizeLimit,
+}
+
+/// Commits to the state transitions in the witness transaction, returning
+/// anchors and the index of the anchor for each of the contracts
+pub fn commit(
+    transitions: BTreeMap<ContractId, NodeId>,
+    psbt: &mut Psbt,
+    container: AnchorContainer,
+) -> Result<(Vec<Anchor>, HashMap<ContractId, usize>), Error> {
+    match container {
+        AnchorContainer::Tweak => Anchor::commit(transitions, psbt)
+            .map_err(|err| Error::Commitment(err.to_string())),
+        AnchorContainer::OpReturn => commit_op_return(transitions, psbt),
+    }
+}
+
+fn commit_op_return(
+    transitions: BTreeMap<ContractId, NodeId>,
+    psbt: &mut Psbt,
+) -> Result<(Vec<Anchor>, HashMap<ContractId, usize>), Error> {
+    if transitions.is_empty() {
+        return Ok((vec![], HashMap::new()));
+    }
+
+    let fee = psbt.fee().map_err(|err| Error::Fee(err.to_string()))?;
+    let num_outs = psbt.global.unsigned_tx.output.len() as u64 + 1;
+
+    let mut factor = None;
+    for contract_id in transitions.keys() {
+        let id = Uint256::from_be_bytes(contract_id.into_inner().into_inner());
+        let protocol_factor =
+            (id % Uint256::from_u64(num_outs).unwrap()).low_u64();
+        match factor {
+            None => factor = Some((*contract_id, protocol_factor)),
+            Some((other_id, other_factor))
+                if other_factor != protocol_factor =>
+            {
+                Err(Error::OutputMismatch(other_id, *contract_id))?
+            }
+            _ => {}
+        }
+    }
+    let (_, factor) = factor.expect("transitions are not empty");
+
+    // Commitment goes to the output `(factor + fee) % num_outs`; we need it
+    // to be the last one, so the output value reduces the fee accordingly
+    let value = (factor + fee % num_outs + 1) % num_outs;
+    if value > fee {
+        Err(Error::InsufficientFee {
+            fee,
+            required: value,
+        })?
+    }
+
+    let multimsg = transitions
+        .iter()
+        .map(|(contract_id, node_id)| {
+            (
+                (contract_id.into_inner().into_inner()).into(),
+                sha256d::Hash::from_inner(node_id.into_inner().into_inner()),
+            )
+        })
+        .collect::<MessageMap>();
+    let mm_commitment = MultimsgCommitment::try_commit(&multimsg)
+        .map_err(|_| Error::SizeLimit)?;
+    let mm_buffer = mm_commitment
+        .commitments
+        .iter()
+        .flat_map(|item| item.commitment.into_inner().to_vec())
+        .collect::<Vec<u8>>();
+    let mm_digest = sha256::Hash::commit(&mm_buffer);
+
+    // OP_RETURN output is not spendable, so the commitment key is derived
+    // from the commitment itself; only the keys which are even after the
+    // tweak may be put into OP_RETURN
+    let (commitment, container) = (0..OP_RETURN_KEY_ATTEMPTS)
+        .find_map(|attempt| {
+            let mut engine = sha256::Hash::engine();
+            engine.input(&mm_digest[..]);
+            engine.input(&attempt.to_be_bytes());
+            let seckey = secp256k1::SecretKey::from_slice(
+                &sha256::Hash::from_engine(engine)[..],
+            )
+            .ok()?;
+            let mut container = TxoutContainer {
+                value,
+                script_container: SpkContainer {
+                    pubkey: secp256k1::PublicKey::from_secret_key(
+                        &SECP256K1, &seckey,
+                    ),
+                    method: ScriptEncodeMethod::OpReturn,
+                    source: ScriptEncodeData::SinglePubkey,
+                    tag: sha256::Hash::hash(b"LNPBP4"),
+                    tweaking_factor: None,
+                },
+                tweaking_factor: None,
+            };
+            TxoutCommitment::embed_commit(&mut container, &mm_digest)
+                .ok()
+                .map(|commitment| (commitment, container))
+        })
+        .ok_or_else(|| {
+            Error::Commitment(
+                "unable to find OP_RETURN commitment key".to_string(),
+            )
+        })?;
+
+    let proprietary_key = |subtype| ProprietaryKey {
+        prefix: PSBT_PREFIX.to_vec(),
+        subtype,
+        key: vec![],
+    };
+    let mut output = psbt::Output::default();
+    output.proprietary.insert(
+        proprietary_key(PSBT_OUT_PUBKEY),
+        container.script_container.pubkey.serialize().to_vec(),
+    );
+    output.proprietary.insert(
+        proprietary_key(PSBT_OUT_TWEAK),
+        container
+            .tweaking_factor
+            .expect("Tweaking factor always present after commitment")[..]
+            .to_vec(),
+    );
+    psbt.global.unsigned_tx.output.push(commitment.into_inner());
+    psbt.outputs.push(output);
+
+    let anchor = Anchor {
+        txid: psbt.global.unsigned_tx.txid(),
+        commitment: mm_commitment,
+        proof: container.into_proof(),
+    };
+    let map = transitions.keys().map(|id| (*id, 0)).collect();
+    Ok((vec![anchor], map))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::{OutPoint, Script, Transaction, TxIn, TxOut};
+
+    #[test]
+    fn test_op_return_anchor() {
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::new(),
+                sequence: 0xFFFFFFFF,
+                witness: vec![],
+            }],
+            output: vec![TxOut {
+                value: 9_000,
+                script_pubkey: Script::new(),
+            }],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: 10_000,
+            script_pubkey: Script::new(),
+        });
+        let contract_id = ContractId::default();
+
+        let (anchors, map) = commit(
+            bmap! { contract_id => NodeId::default() },
+            &mut psbt,
+            AnchorContainer::OpReturn,
+        )
+        .unwrap();
+        let tx = &psbt.global.unsigned_tx;
+        assert_eq!(tx.output.len(), 2);
+        assert_eq!(psbt.outputs.len(), 2);
+        assert!(tx.output[1].script_pubkey.is_op_return());
+        assert_eq!(tx.output[0].value, 9_000);
+        assert_eq!(map[&contract_id], 0);
+        assert!(anchors[0].verify(&contract_id, tx, psbt.fee().unwrap()));
+    }
+}
@@ -0,0 +1,256 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Backup targets keeping the snapshots of the daemon data. Each backup is
+//! named after its creation time (`rgb-backup-<YYYYMMDD>T<HHMMSS>Z`) and
+//! consists of the components (like `cache` or `stash`), which are copies of
+//! the daemon data directories. Backups are kept either in a local directory
+//! as `<dir>/<backup>/<component>/...` or in S3-compatible bucket under the
+//! `<prefix>/<backup>/<component>/...` keys.
+
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use chrono::{DateTime, Utc};
+
+use super::s3::{S3Error, S3Url};
+use crate::error::ServiceErrorDomain;
+
+/// Prefix of the backup names
+pub const BACKUP_PREFIX: &str = "rgb-backup-";
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum BackupError {
+    /// backup target must be either a directory or S3 bucket URL
+    NoTarget,
+
+    /// `{0}` is not a valid backup or component name
+    InvalidName(String),
+
+    /// I/O error accessing backup directory: {0}
+    Io(String),
+
+    /// {0}
+    #[from]
+    S3(S3Error),
+}
+
+impl From<io::Error> for BackupError {
+    fn from(err: io::Error) -> Self {
+        BackupError::Io(err.to_string())
+    }
+}
+
+impl From<BackupError> for ServiceErrorDomain {
+    fn from(err: BackupError) -> Self {
+        ServiceErrorDomain::Storage(err.to_string())
+    }
+}
+
+/// Location where the backups are kept
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BackupTarget {
+    Dir(PathBuf),
+    S3(S3Url),
+}
+
+impl FromStr for BackupTarget {
+    type Err = BackupError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("s3://") || s.starts_with("s3+http://") {
+            Ok(BackupTarget::S3(S3Url::from_str(s)?))
+        } else if s.is_empty() {
+            Err(BackupError::NoTarget)
+        } else {
+            Ok(BackupTarget::Dir(PathBuf::from(s)))
+        }
+    }
+}
+
+impl Display for BackupTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupTarget::Dir(dir) => write!(f, "{}", dir.display()),
+            BackupTarget::S3(url) => Display::fmt(url, f),
+        }
+    }
+}
+
+/// Name of the backup created at the given time
+pub fn backup_name(time: DateTime<Utc>) -> String {
+    format!("{}{}", BACKUP_PREFIX, time.format("%Y%m%dT%H%M%SZ"))
+}
+
+/// Checks that the name may be safely used as a path component
+fn check_name(name: &str) -> Result<(), BackupError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(BackupError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Paths of all files in the directory, relative to it
+fn files(dir: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut files = vec![];
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(subdir) = dirs.pop() {
+        for entry in fs::read_dir(dir.join(&subdir))? {
+            let entry = entry?;
+            let path = subdir.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+impl BackupTarget {
+    /// Copies all files of the directory into the backup component,
+    /// returning number of the copied files
+    pub fn store_dir(
+        &self,
+        backup: &str,
+        component: &str,
+        dir: &Path,
+    ) -> Result<usize, BackupError> {
+        check_name(backup)?;
+        check_name(component)?;
+        let files = files(dir)?;
+        for file in &files {
+            match self {
+                BackupTarget::Dir(target) => {
+                    let dest = target.join(backup).join(component).join(file);
+                    if let Some(parent) = dest.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::copy(dir.join(file), dest)?;
+                }
+                BackupTarget::S3(url) => {
+                    let key = format!(
+                        "{}/{}/{}",
+                        backup,
+                        component,
+                        file.to_string_lossy().replace('\\', "/")
+                    );
+                    url.put(&key, &fs::read(dir.join(file))?)?;
+                }
+            }
+        }
+        Ok(files.len())
+    }
+
+    /// Names of the backups kept in the target, from the oldest to the
+    /// newest one
+    pub fn backups(&self) -> Result<Vec<String>, BackupError> {
+        let mut backups = match self {
+            BackupTarget::Dir(dir) if !dir.exists() => vec![],
+            BackupTarget::Dir(dir) => fs::read_dir(dir)?
+                .filter_map(Result::ok)
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|name| name.starts_with(BACKUP_PREFIX))
+                .collect(),
+            BackupTarget::S3(url) => url
+                .list(BACKUP_PREFIX)?
+                .into_iter()
+                .filter_map(|key| key.split('/').next().map(str::to_string))
+                .collect(),
+        };
+        backups.sort();
+        backups.dedup();
+        Ok(backups)
+    }
+
+    /// Removes backup with all its components
+    pub fn remove(&self, backup: &str) -> Result<(), BackupError> {
+        check_name(backup)?;
+        match self {
+            BackupTarget::Dir(dir) => fs::remove_dir_all(dir.join(backup))?,
+            BackupTarget::S3(url) => {
+                for key in url.list(&format!("{}/", backup))? {
+                    url.delete(&key)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the oldest backups, keeping the given number of the newest
+    /// ones; returns names of the removed backups
+    pub fn rotate(&self, keep: usize) -> Result<Vec<String>, BackupError> {
+        let mut backups = self.backups()?;
+        let excess = backups.len().saturating_sub(keep);
+        backups.truncate(excess);
+        for backup in &backups {
+            self.remove(backup)?;
+        }
+        Ok(backups)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_backup_rotation() {
+        let root = env::temp_dir().join("rgb-test-backup");
+        let _ = fs::remove_dir_all(&root);
+        let data_dir = root.join("data");
+        fs::create_dir_all(data_dir.join("assets")).unwrap();
+        fs::write(data_dir.join("FORMAT"), "1\n").unwrap();
+        fs::write(data_dir.join("assets").join("asset"), "data").unwrap();
+
+        let target =
+            BackupTarget::from_str(&root.join("backups").to_string_lossy())
+                .unwrap();
+        assert_eq!(target.backups(), Ok(vec![]));
+        let names = (1..=3)
+            .map(|day| backup_name(Utc.ymd(2021, 3, day).and_hms(12, 0, 0)))
+            .collect::<Vec<_>>();
+        assert_eq!(names[0], "rgb-backup-20210301T120000Z");
+        for name in names.iter().rev() {
+            assert_eq!(target.store_dir(name, "cache", &data_dir), Ok(2));
+        }
+        assert_eq!(target.backups().unwrap(), names);
+        assert_eq!(
+            target.store_dir("../escape", "cache", &data_dir),
+            Err(BackupError::InvalidName(s!("../escape")))
+        );
+
+        assert_eq!(target.rotate(2).unwrap(), vec![names[0].clone()]);
+        assert_eq!(target.backups().unwrap(), names[1..].to_vec());
+        let restored = root
+            .join("backups")
+            .join(&names[2])
+            .join("cache")
+            .join("assets")
+            .join("asset");
+        assert_eq!(fs::read_to_string(restored).unwrap(), "data");
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,121 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Optional zstd compression of RPC payloads.
+//!
+//! Compression is flagged per-message: a compressed payload is a plain zstd
+//! frame, which always starts with [`ZSTD_MAGIC`]. Since the first two bytes of
+//! an uncompressed API message hold its type and `0xB528` is not used by any
+//! of the RGB APIs, the receiving side can always tell compressed messages
+//! from uncompressed ones, so no session-level negotiation is required.
+
+#[cfg(feature = "zstd")]
+use std::io::{self, Read};
+
+use internet2::{presentation, Session};
+
+#[cfg(feature = "zstd")]
+use crate::constants::RPC_MAX_MESSAGE_SIZE;
+
+/// Magic number starting each zstd frame
+pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Default size (in bytes) of the messages below which they are sent
+/// uncompressed
+pub const RPC_COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Compression level used for RPC payloads: we favour speed here since most
+/// of the messages are sent over local sockets
+#[cfg(feature = "zstd")]
+const RPC_COMPRESSION_LEVEL: i32 = 3;
+
+/// Detects whether the raw message data are zstd-compressed
+#[inline]
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(&ZSTD_MAGIC)
+}
+
+/// Compresses serialized RPC message if its size is not less than
+/// `threshold`. If compression does not reduce the message size, the data are
+/// already compressed or the crate is compiled without `zstd` feature,
+/// returns the original data.
+#[cfg(feature = "zstd")]
+pub fn compress(data: Vec<u8>, threshold: usize) -> Vec<u8> {
+    if data.len() < threshold || is_compressed(&data) {
+        return data;
+    }
+    match zstd::encode_all(&data[..], RPC_COMPRESSION_LEVEL) {
+        Ok(compressed) if compressed.len() < data.len() => compressed,
+        _ => data,
+    }
+}
+
+/// Compresses serialized RPC message if its size is not less than
+/// `threshold`. If compression does not reduce the message size, the data are
+/// already compressed or the crate is compiled without `zstd` feature,
+/// returns the original data.
+#[cfg(not(feature = "zstd"))]
+#[inline]
+pub fn compress(data: Vec<u8>, _threshold: usize) -> Vec<u8> {
+    data
+}
+
+/// Decompresses raw RPC message data if they are zstd-compressed; otherwise
+/// returns them unmodified. Decompressed data are not allowed to exceed
+/// [`RPC_MAX_MESSAGE_SIZE`], so a small malicious frame can't exhaust the
+/// memory.
+#[cfg(feature = "zstd")]
+pub fn decompress(data: Vec<u8>) -> Result<Vec<u8>, presentation::Error> {
+    if !is_compressed(&data) {
+        return Ok(data);
+    }
+    let mut decompressed = vec![];
+    zstd::stream::read::Decoder::new(&data[..])?
+        .take(RPC_MAX_MESSAGE_SIZE as u64 + 1)
+        .read_to_end(&mut decompressed)?;
+    if decompressed.len() > RPC_MAX_MESSAGE_SIZE {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "decompressed message exceeds maximum size of {} bytes",
+                RPC_MAX_MESSAGE_SIZE
+            ),
+        ))?
+    }
+    Ok(decompressed)
+}
+
+/// Decompresses raw RPC message data if they are zstd-compressed; otherwise
+/// returns them unmodified.
+#[cfg(not(feature = "zstd"))]
+pub fn decompress(data: Vec<u8>) -> Result<Vec<u8>, presentation::Error> {
+    if is_compressed(&data) {
+        return Err(presentation::Error::NoEncoder);
+    }
+    Ok(data)
+}
+
+/// Sends serialized RPC request over the session, compressing it if its size
+/// reaches [`RPC_COMPRESSION_THRESHOLD`], and returns raw data of the reply
+/// after their decompression
+pub fn req_rep<S>(
+    session: &mut S,
+    request: Vec<u8>,
+) -> Result<Vec<u8>, presentation::Error>
+where
+    S: Session + ?Sized,
+{
+    let data = compress(request, RPC_COMPRESSION_THRESHOLD);
+    session.send_raw_message(&data)?;
+    decompress(session.recv_raw_message()?)
+}
@@ -0,0 +1,233 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Layered daemon configuration. Default values of the daemon parameters are
+//! overridden by the values from `rgb-node.toml` configuration file, which
+//! are overridden by environment variables, which are overridden by
+//! command-line arguments.
+//!
+//! Configuration file is shared by all daemons: top-level keys apply to all
+//! of them, while keys from a section named after a daemon (like `[stashd]`)
+//! apply only to that daemon. Keys are named after the long command-line
+//! arguments. Values from the file are exported as the environment variables
+//! read by the daemon command-line parser, unless the variable is already
+//! set, so the rest of the precedence rules are handled by the parser.
+
+#[cfg(feature = "node")]
+use core::convert::TryFrom;
+#[cfg(feature = "node")]
+use core::fmt::Display;
+#[cfg(feature = "node")]
+use std::env;
+#[cfg(all(feature = "node", feature = "clap"))]
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[cfg(all(feature = "node", feature = "clap"))]
+use clap::Clap;
+#[cfg(feature = "node")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "node")]
+use toml::value::{Table, Value};
+
+/// Name of the configuration file looked up in the data directory
+pub const CONFIG_FILE_NAME: &str = "rgb-node.toml";
+
+/// Environment variable holding path to the configuration file
+pub const CONFIG_FILE_ENV: &str = "RGB_CONFIG";
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// I/O error reading configuration file: {0}
+    #[from]
+    Io(io::Error),
+
+    /// error parsing configuration file: {0}
+    Parse(String),
+
+    /// configuration files are not supported by this build
+    Unsupported,
+}
+
+/// Command-line options of a daemon reading the configuration file
+#[cfg(all(feature = "node", feature = "clap"))]
+pub trait LayeredOpts: Clap {
+    /// Name of the daemon, which is also the name of its section in the
+    /// configuration file
+    const DAEMON: &'static str;
+
+    /// Configuration file keys and environment variables they are exported
+    /// to. Verbosity is not in the list, since it can't be set via
+    /// environment; it is taken from the `verbose` key if no `-v` flags were
+    /// given.
+    const KEYS: &'static [(&'static str, &'static str)];
+
+    fn data_dir(&self) -> &str;
+
+    fn config_file(&self) -> Option<&str>;
+
+    fn verbose_mut(&mut self) -> &mut u8;
+}
+
+/// Path to the configuration file: the explicitly given one, or
+/// `rgb-node.toml` from the data directory, if it exists
+pub fn locate(config_file: Option<&str>, data_dir: &str) -> Option<PathBuf> {
+    match config_file {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Some(Path::new(data_dir).join(CONFIG_FILE_NAME))
+            .filter(|path| path.is_file()),
+    }
+}
+
+/// Reads keys applying to the daemon from the configuration file
+#[cfg(feature = "node")]
+pub fn read(
+    path: impl AsRef<Path>,
+    daemon: &str,
+) -> Result<Table, ConfigError> {
+    let data = std::fs::read_to_string(path)?;
+    let mut file: Table = toml::from_str(&data)
+        .map_err(|err| ConfigError::Parse(err.to_string()))?;
+    let section = match file.remove(daemon) {
+        Some(Value::Table(section)) => section,
+        Some(_) => Err(ConfigError::Parse(format!(
+            "`{}` must be a section",
+            daemon
+        )))?,
+        None => Table::new(),
+    };
+    let mut layer = file
+        .into_iter()
+        .filter(|(_, value)| !value.is_table())
+        .collect::<Table>();
+    layer.extend(section);
+    Ok(layer)
+}
+
+/// Reads tunable settings of the daemon from the configuration file
+#[cfg(feature = "node")]
+pub fn tunables<T>(
+    path: impl AsRef<Path>,
+    daemon: &str,
+) -> Result<T, ConfigError>
+where
+    T: DeserializeOwned,
+{
+    Value::Table(read(path, daemon)?)
+        .try_into()
+        .map_err(|err| ConfigError::Parse(err.to_string()))
+}
+
+/// Configuration files can't be read without `node` feature
+#[cfg(not(feature = "node"))]
+pub fn tunables<T>(
+    _path: impl AsRef<Path>,
+    _daemon: &str,
+) -> Result<T, ConfigError> {
+    Err(ConfigError::Unsupported)
+}
+
+/// Exports configuration file values as environment variables which are not
+/// set yet
+#[cfg(feature = "node")]
+fn export(layer: &Table, keys: &[(&str, &str)]) {
+    for (key, var) in keys {
+        let value = match layer.get(*key) {
+            Some(value) if env::var_os(var).is_none() => value,
+            _ => continue,
+        };
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Array(values) => values
+                .iter()
+                .map(|value| match value {
+                    Value::String(s) => s.clone(),
+                    value => value.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            value => value.to_string(),
+        };
+        env::set_var(var, value);
+    }
+}
+
+/// Parses daemon command-line arguments, using values from the configuration
+/// file for the arguments which were given neither in the command line nor
+/// in the environment
+#[cfg(all(feature = "node", feature = "clap"))]
+pub fn parse_from<O, I, T>(args: I) -> Result<O, ConfigError>
+where
+    O: LayeredOpts,
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args = args.into_iter().collect::<Vec<_>>();
+    let opts = O::parse_from(args.clone());
+    let path = match locate(opts.config_file(), opts.data_dir()) {
+        Some(path) => path,
+        None => return Ok(opts),
+    };
+    let layer = read(&path, O::DAEMON)?;
+    export(&layer, O::KEYS);
+    // Makes the file known to the daemons launched by this one
+    if env::var_os(CONFIG_FILE_ENV).is_none() {
+        env::set_var(CONFIG_FILE_ENV, &path);
+    }
+
+    let mut opts = O::parse_from(args);
+    if *opts.verbose_mut() == 0 {
+        if let Some(verbose) = layer.get("verbose").and_then(Value::as_integer)
+        {
+            *opts.verbose_mut() = verbose as u8;
+        }
+    }
+    Ok(opts)
+}
+
+/// Parses command-line arguments of the current process with the
+/// configuration file
+#[cfg(all(feature = "node", feature = "clap"))]
+#[inline]
+pub fn parse<O>() -> Result<O, ConfigError>
+where
+    O: LayeredOpts,
+{
+    parse_from(env::args_os())
+}
+
+/// Configuration value for the effective configuration dump
+#[cfg(feature = "node")]
+pub fn value(value: impl Display) -> Value {
+    Value::String(value.to_string())
+}
+
+/// Integer configuration value for the effective configuration dump
+#[cfg(feature = "node")]
+pub fn integer(value: impl Into<u64>) -> Value {
+    let value = value.into();
+    i64::try_from(value)
+        .map(Value::Integer)
+        .unwrap_or_else(|_| Value::String(value.to_string()))
+}
+
+/// Formats effective daemon configuration as a configuration file section
+#[cfg(feature = "node")]
+pub fn dump(daemon: &str, config: Table) -> String {
+    let mut file = Table::new();
+    file.insert(daemon.to_string(), Value::Table(config));
+    toml::to_string(&file).unwrap_or_else(|err| err.to_string())
+}
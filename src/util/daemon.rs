@@ -0,0 +1,222 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Traditional daemon lifecycle for the operators running the node under
+//! init systems without service supervision: detaching from the terminal,
+//! PID file, confinement to the data directory and switching to an
+//! unprivileged user once the daemon has bound its sockets.
+
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use nix::errno::Errno;
+use nix::sys::signal;
+use nix::unistd::{self, Gid, Pid, Uid, User};
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum LifecycleError {
+    /// I/O error accessing PID file: {0}
+    #[from]
+    Io(io::Error),
+
+    /// system call has failed: {0}
+    #[from]
+    Sys(nix::Error),
+
+    /// daemon is already running with PID {0}
+    AlreadyRunning(i32),
+
+    /// unknown user `{0}`
+    UnknownUser(String),
+}
+
+/// Detaches the process from the terminal, continuing its execution in
+/// background with standard streams redirected to `/dev/null`. Must be
+/// called before any threads are started. Current directory is kept, so
+/// relative paths in the configuration remain valid.
+pub fn detach() -> Result<(), LifecycleError> {
+    unistd::daemon(true, false)?;
+    Ok(())
+}
+
+/// PID file of the running daemon, removed once dropped. The file can't be
+/// removed if the daemon was confined to the data directory or has switched
+/// to a user without write access to the file.
+#[derive(Debug)]
+pub struct PidFile(PathBuf);
+
+impl PidFile {
+    /// Writes id of the current process to the file, unless the file holds
+    /// id of another running process
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, LifecycleError> {
+        let path = path.as_ref();
+        let running = fs::read_to_string(path)
+            .ok()
+            .and_then(|data| data.trim().parse::<i32>().ok())
+            .filter(|pid| *pid != unistd::getpid().as_raw())
+            // Process owned by another user can't be signalled, but exists
+            .filter(|pid| {
+                !matches!(
+                    signal::kill(Pid::from_raw(*pid), None),
+                    Err(nix::Error::Sys(Errno::ESRCH))
+                )
+            });
+        if let Some(pid) = running {
+            Err(LifecycleError::AlreadyRunning(pid))?
+        }
+        fs::write(path, format!("{}\n", unistd::getpid()))?;
+        Ok(PidFile(path.to_path_buf()))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.0) {
+            warn!("Unable to remove PID file {:?}: {}", self.0, err);
+        }
+    }
+}
+
+/// Makes the directory the filesystem root of the process
+pub fn confine(dir: impl AsRef<Path>) -> Result<(), LifecycleError> {
+    unistd::chroot(dir.as_ref())?;
+    unistd::chdir("/")?;
+    info!("Confined to {:?}", dir.as_ref());
+    Ok(())
+}
+
+/// Path of the file inside the directory to which the process was confined
+/// with [`confine`]; paths outside of it are returned unchanged
+pub fn confined_path(path: &str, dir: impl AsRef<Path>) -> String {
+    match Path::new(path).strip_prefix(dir) {
+        Ok(relative) => Path::new("/").join(relative).display().to_string(),
+        Err(_) => path.to_string(),
+    }
+}
+
+/// Unprivileged user with its groups, looked up in the user and group
+/// databases. Must be resolved before the process is confined with
+/// [`confine`], since the databases are not reachable afterwards.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Credentials {
+    pub name: String,
+    pub uid: Uid,
+    pub gid: Gid,
+    pub groups: Vec<Gid>,
+}
+
+impl Credentials {
+    pub fn resolve(name: &str) -> Result<Self, LifecycleError> {
+        let user = User::from_name(name)?
+            .ok_or_else(|| LifecycleError::UnknownUser(name.to_string()))?;
+        let c_name = CString::new(name)
+            .map_err(|_| LifecycleError::UnknownUser(name.to_string()))?;
+        let groups = unistd::getgrouplist(&c_name, user.gid)?;
+        Ok(Credentials {
+            name: user.name,
+            uid: user.uid,
+            gid: user.gid,
+            groups,
+        })
+    }
+}
+
+/// Switches the process to the user and its groups. Does nothing if the
+/// process is already run by the user, so the daemons launched by the
+/// supervisor which has switched to the user are not affected.
+pub fn drop_privileges(
+    credentials: &Credentials,
+) -> Result<(), LifecycleError> {
+    if unistd::geteuid() == credentials.uid {
+        return Ok(());
+    }
+    unistd::setgroups(&credentials.groups)?;
+    unistd::setgid(credentials.gid)?;
+    unistd::setuid(credentials.uid)?;
+    info!(
+        "Switched to user {} (uid {})",
+        credentials.name, credentials.uid
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use nix::sys::wait::{self, WaitStatus};
+    use nix::unistd::ForkResult;
+
+    use super::*;
+
+    #[test]
+    fn test_pid_file() {
+        let path = std::env::temp_dir()
+            .join(format!("rgb-test-{}.pid", unistd::getpid()));
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{}\n", unistd::getpid())
+        );
+        drop(pid_file);
+        assert!(!path.exists());
+
+        // PID 1 is always running
+        fs::write(&path, "1\n").unwrap();
+        assert!(matches!(
+            PidFile::create(&path),
+            Err(LifecycleError::AlreadyRunning(1))
+        ));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            confined_path("/var/lib/rgb/rgb-node.toml", "/var/lib/rgb"),
+            "/rgb-node.toml"
+        );
+        assert_eq!(
+            confined_path("/etc/rgb.toml", "/var/lib/rgb"),
+            "/etc/rgb.toml"
+        );
+    }
+
+    #[test]
+    fn test_confined_drop_privileges() {
+        // Both confinement and switching the user require root privileges
+        if !unistd::geteuid().is_root() {
+            return;
+        }
+        let credentials = Credentials::resolve("nobody").unwrap();
+        let dir = std::env::temp_dir()
+            .join(format!("rgb-test-chroot-{}", unistd::getpid()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // Confinement can't be undone, so it is done by the child process
+        match unsafe { unistd::fork() }.unwrap() {
+            ForkResult::Child => {
+                let code = match confine(&dir)
+                    .and_then(|_| drop_privileges(&credentials))
+                {
+                    Ok(()) if unistd::geteuid() == credentials.uid => 0,
+                    _ => 1,
+                };
+                unsafe { nix::libc::_exit(code) }
+            }
+            ForkResult::Parent { child } => {
+                let status = wait::waitpid(child, None).unwrap();
+                fs::remove_dir(&dir).unwrap();
+                assert_eq!(status, WaitStatus::Exited(child, 0));
+            }
+        }
+    }
+}
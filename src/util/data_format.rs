@@ -0,0 +1,123 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use core::str::FromStr;
+
+use microservices::{FileFormat, FormatParseError};
+
+/// Data formats supported for asset cache storage, export and `Sync` replies.
+///
+/// Extends [`FileFormat`] from `microservices` crate with binary
+/// serialization formats used natively by some downstream wallets. Numeric
+/// values of the variants shared with [`FileFormat`] are kept the same, so the
+/// strict-encoded representation of those formats does not change.
+#[derive(
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Display,
+    StrictEncode,
+    StrictDecode,
+)]
+#[strict_encoding_crate(lnpbp::strict_encoding)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum DataFormat {
+    /// JSON
+    #[display("json")]
+    Json = 10,
+
+    /// YAML
+    #[display("yaml")]
+    Yaml = 11,
+
+    /// TOML
+    #[display("toml")]
+    Toml = 12,
+
+    /// CBOR (RFC 7049)
+    #[display("cbor")]
+    Cbor = 20,
+
+    /// MessagePack
+    #[display("msgpack")]
+    MsgPack = 21,
+
+    /// Protocol buffers (see `proto/rgb20.proto` for message definitions)
+    #[display("protobuf")]
+    Protobuf = 22,
+
+    /// Strict encoding
+    #[display("strict-encode")]
+    StrictEncode = 0,
+}
+
+impl DataFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DataFormat::Yaml => "yaml",
+            DataFormat::Json => "json",
+            DataFormat::Toml => "toml",
+            DataFormat::Cbor => "cbor",
+            DataFormat::MsgPack => "msgpack",
+            DataFormat::Protobuf => "pb",
+            DataFormat::StrictEncode => "se",
+        }
+    }
+
+    /// Parses data format for the file storage. Protocol buffers are used
+    /// only for the data exchange and can't be used to store the data.
+    pub fn parse_storage(s: &str) -> Result<Self, String> {
+        match s.parse::<DataFormat>().map_err(|err| err.to_string())? {
+            DataFormat::Protobuf => Err(format!(
+                "data format `{}` can't be used for the file storage",
+                DataFormat::Protobuf
+            )),
+            format => Ok(format),
+        }
+    }
+}
+
+impl From<FileFormat> for DataFormat {
+    fn from(format: FileFormat) -> Self {
+        match format {
+            FileFormat::Json => DataFormat::Json,
+            FileFormat::Yaml => DataFormat::Yaml,
+            FileFormat::Toml => DataFormat::Toml,
+            _ => DataFormat::StrictEncode,
+        }
+    }
+}
+
+impl FromStr for DataFormat {
+    type Err = FormatParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match &s.to_lowercase() {
+            s if s.starts_with("cbor") => Self::Cbor,
+            s if s.starts_with("msgpack")
+                || s.starts_with("messagepack")
+                || s.starts_with("mpk") =>
+            {
+                Self::MsgPack
+            }
+            s if s.starts_with("proto") || s == "pb" => Self::Protobuf,
+            s => FileFormat::from_str(s)?.into(),
+        })
+    }
+}
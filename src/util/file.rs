@@ -212,25 +212,31 @@ impl ReadWrite for Extension {
     }
 }
 
+/// Opens consignment file and checks its magic number; the returned file is
+/// positioned at the beginning of the strict-encoded consignment data
+pub fn consignment_file(filename: impl AsRef<Path>) -> Result<fs::File, Error> {
+    let mut file = file(filename, FileMode::Read)?;
+    let mut magic_buf = [0u8; 4];
+    file.read_exact(&mut magic_buf)?;
+    let magic = u32::from_be_bytes(magic_buf);
+    let magic = MagicNumber::try_from(magic).map_err(|detected| {
+        Error::DataIntegrityError(format!(
+            "Wrong file type: expected consignment file, got unknown magic number {}",
+            detected
+        ))
+    })?;
+    if magic != MagicNumber::Consignment {
+        Err(Error::DataIntegrityError(format!(
+            "Wrong file type: expected consignment file, got {}",
+            magic
+        )))?
+    }
+    Ok(file)
+}
+
 impl ReadWrite for Consignment {
     fn read_file(filename: impl AsRef<Path>) -> Result<Self, Error> {
-        let mut file = file(filename, FileMode::Read)?;
-        let mut magic_buf = [0u8; 4];
-        file.read_exact(&mut magic_buf)?;
-        let magic = u32::from_be_bytes(magic_buf);
-        let magic = MagicNumber::try_from(magic).map_err(|detected| {
-            Error::DataIntegrityError(format!(
-                "Wrong file type: expected consignment file, got unknown magic number {}",
-                detected
-            ))
-        })?;
-        if magic != MagicNumber::Consignment {
-            Err(Error::DataIntegrityError(format!(
-                "Wrong file type: expected consignment file, got {}",
-                magic
-            )))?
-        }
-        Consignment::strict_decode(file)
+        Consignment::strict_decode(consignment_file(filename)?)
     }
 
     fn write_file(&self, filename: impl AsRef<Path>) -> Result<usize, Error> {
@@ -0,0 +1,472 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Daemon logging. Log records are written to one of the targets: standard
+//! error (the default), a file with one JSON object per line, syslog or
+//! journald. Records are filtered by the daemon verbosity and per-module
+//! level overrides given in `RUST_LOG` syntax (like
+//! `rgb_node::fungibled=debug,internet2=warn`); both can be changed at
+//! runtime on the configuration reload.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter, Write as _};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Mutex, RwLock};
+
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use super::reload;
+
+/// Path of the syslog socket
+#[cfg(unix)]
+const SYSLOG_SOCKET: &str = "/dev/log";
+
+/// Path of the journald native protocol socket
+#[cfg(unix)]
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Syslog facility used for the log records (`daemon`)
+#[cfg(unix)]
+const SYSLOG_FACILITY: u8 = 3;
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum LoggingError {
+    /// I/O error opening log target: {0}
+    #[from]
+    Io(io::Error),
+
+    /// logger is already initialized
+    #[from(SetLoggerError)]
+    AlreadyInitialized,
+
+    /// log target is not supported on this platform
+    Unsupported,
+}
+
+/// Destination of the log records
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum LogTarget {
+    /// Standard error, formatted for humans
+    Stderr,
+
+    /// File with one JSON object per log record
+    Json(PathBuf),
+
+    /// Local syslog daemon
+    Syslog,
+
+    /// Systemd journal
+    Journald,
+}
+
+impl Default for LogTarget {
+    fn default() -> Self {
+        LogTarget::Stderr
+    }
+}
+
+impl Display for LogTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LogTarget::Stderr => f.write_str("stderr"),
+            LogTarget::Json(path) => write!(f, "json:{}", path.display()),
+            LogTarget::Syslog => f.write_str("syslog"),
+            LogTarget::Journald => f.write_str("journald"),
+        }
+    }
+}
+
+impl FromStr for LogTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stderr" => Ok(LogTarget::Stderr),
+            "syslog" => Ok(LogTarget::Syslog),
+            "journald" => Ok(LogTarget::Journald),
+            s if s.starts_with("json:") && s.len() > 5 => {
+                Ok(LogTarget::Json(PathBuf::from(&s[5..])))
+            }
+            _ => Err(format!(
+                "unknown log target `{}`; expected `stderr`, `syslog`, \
+                 `journald` or `json:<path>`",
+                s
+            )),
+        }
+    }
+}
+
+/// Log levels: the default one and the overrides for modules, given as
+/// comma-separated `RUST_LOG`-style directives (`<level>` for the default
+/// level and `<module>=<level>` for the module and its submodules)
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct LogFilter {
+    /// Level used for the modules without overrides; if absent, the level
+    /// matching daemon verbosity is used
+    pub default: Option<LevelFilter>,
+
+    /// Levels for the modules, by module path
+    pub modules: BTreeMap<String, LevelFilter>,
+}
+
+impl LogFilter {
+    /// Whether the filter has neither the default level nor overrides
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.modules.is_empty()
+    }
+
+    /// Adds directives from the other filter, replacing the existing ones
+    pub fn extend(&mut self, other: LogFilter) {
+        if other.default.is_some() {
+            self.default = other.default;
+        }
+        self.modules.extend(other.modules);
+    }
+
+    /// Level for the given module path: the level of the longest module
+    /// path prefix having an override, or the default level
+    pub fn level(&self, target: &str, default: LevelFilter) -> LevelFilter {
+        self.modules
+            .iter()
+            .rev()
+            .find(|(module, _)| {
+                target.starts_with(module.as_str())
+                    && (target.len() == module.len()
+                        || target[module.len()..].starts_with("::"))
+            })
+            .map(|(_, level)| *level)
+            .or(self.default)
+            .unwrap_or(default)
+    }
+
+    /// Most verbose level enabled by the filter
+    pub fn max_level(&self, default: LevelFilter) -> LevelFilter {
+        self.modules
+            .values()
+            .copied()
+            .chain(Some(self.default.unwrap_or(default)))
+            .max()
+            .unwrap_or(default)
+    }
+}
+
+impl Display for LogFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut directives = self
+            .default
+            .iter()
+            .map(|level| level.to_string().to_lowercase())
+            .chain(self.modules.iter().map(|(module, level)| {
+                format!("{}={}", module, level.to_string().to_lowercase())
+            }));
+        if let Some(directive) = directives.next() {
+            f.write_str(&directive)?;
+        }
+        for directive in directives {
+            write!(f, ",{}", directive)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for LogFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = LogFilter::default();
+        for directive in s.split(',').map(str::trim) {
+            if directive.is_empty() {
+                continue;
+            }
+            let mut split = directive.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some(module), Some(level)) => {
+                    let level = LevelFilter::from_str(level).map_err(|_| {
+                        format!("unknown log level in `{}`", directive)
+                    })?;
+                    filter.modules.insert(module.to_string(), level);
+                }
+                (Some(directive), None) => {
+                    match LevelFilter::from_str(directive) {
+                        Ok(level) => filter.default = Some(level),
+                        // Module name without level enables all records
+                        Err(_) => {
+                            filter.modules.insert(
+                                directive.to_string(),
+                                LevelFilter::Trace,
+                            );
+                        }
+                    }
+                }
+                _ => unreachable!("splitn always returns first item"),
+            }
+        }
+        Ok(filter)
+    }
+}
+
+struct FilterState {
+    default: LevelFilter,
+    filter: LogFilter,
+}
+
+lazy_static::lazy_static! {
+    static ref FILTER: RwLock<FilterState> = RwLock::new(FilterState {
+        default: LevelFilter::Trace,
+        filter: LogFilter::default(),
+    });
+}
+
+enum Backend {
+    Stderr(env_logger::Logger),
+    Json(Mutex<BufWriter<fs::File>>),
+    #[cfg(unix)]
+    Syslog(UnixDatagram),
+    #[cfg(unix)]
+    Journald(UnixDatagram),
+}
+
+struct Logger {
+    ident: String,
+    backend: Backend,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let state = FILTER.read().unwrap_or_else(|err| err.into_inner());
+        metadata.level() <= state.filter.level(metadata.target(), state.default)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // Logging must not fail the daemon, so write errors are ignored
+        let _ = match self.backend {
+            Backend::Stderr(ref logger) => {
+                logger.log(record);
+                Ok(())
+            }
+            Backend::Json(ref file) => {
+                let line = json_record(record);
+                let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+                file.write_all(line.as_bytes()).and_then(|_| file.flush())
+            }
+            #[cfg(unix)]
+            Backend::Syslog(ref socket) => {
+                let message = format!(
+                    "<{}>{}[{}]: {}",
+                    SYSLOG_FACILITY * 8 + syslog_severity(record.level()),
+                    self.ident,
+                    std::process::id(),
+                    record.args()
+                );
+                socket.send(message.as_bytes()).map(|_| ())
+            }
+            #[cfg(unix)]
+            Backend::Journald(ref socket) => {
+                let message = journald_record(&self.ident, record);
+                socket.send(&message).map(|_| ())
+            }
+        };
+    }
+
+    fn flush(&self) {
+        if let Backend::Json(ref file) = self.backend {
+            let _ = file.lock().unwrap_or_else(|e| e.into_inner()).flush();
+        }
+    }
+}
+
+/// Installs the process logger writing to the given target. Directives from
+/// `RUST_LOG` environment variable, if present, are added to the filter.
+pub fn init(
+    ident: &str,
+    target: &LogTarget,
+    verbose: u8,
+    filter: &LogFilter,
+) -> Result<(), LoggingError> {
+    let backend = match target {
+        LogTarget::Stderr => Backend::Stderr(
+            env_logger::Builder::new()
+                .filter_level(LevelFilter::Trace)
+                .build(),
+        ),
+        LogTarget::Json(path) => Backend::Json(Mutex::new(BufWriter::new(
+            fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?,
+        ))),
+        #[cfg(unix)]
+        LogTarget::Syslog => Backend::Syslog(connect(SYSLOG_SOCKET)?),
+        #[cfg(unix)]
+        LogTarget::Journald => Backend::Journald(connect(JOURNALD_SOCKET)?),
+        #[cfg(not(unix))]
+        _ => Err(LoggingError::Unsupported)?,
+    };
+    log::set_boxed_logger(Box::new(Logger {
+        ident: ident.to_string(),
+        backend,
+    }))?;
+    set_filter(verbose, filter);
+    Ok(())
+}
+
+/// Changes levels of the log records written by the process logger
+pub fn set_filter(verbose: u8, filter: &LogFilter) {
+    let mut filter = filter.clone();
+    if let Some(env) = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|env| LogFilter::from_str(&env).ok())
+    {
+        filter.extend(env);
+    }
+    let default = reload::verbosity(verbose);
+    log::set_max_level(filter.max_level(default));
+    let mut state = FILTER.write().unwrap_or_else(|err| err.into_inner());
+    *state = FilterState { default, filter };
+}
+
+#[cfg(unix)]
+fn connect(path: &str) -> Result<UnixDatagram, io::Error> {
+    let socket = UnixDatagram::unbound()?;
+    socket.connect(path)?;
+    Ok(socket)
+}
+
+#[cfg(unix)]
+fn syslog_severity(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+fn json_record(record: &Record) -> String {
+    let mut line = format!(
+        "{{\"timestamp\":\"{}\",\"level\":\"{}\",\"target\":",
+        chrono::Utc::now().to_rfc3339(),
+        record.level()
+    );
+    json_string(&mut line, record.target());
+    if let Some(module) = record.module_path() {
+        line.push_str(",\"module\":");
+        json_string(&mut line, module);
+    }
+    if let Some(file) = record.file() {
+        line.push_str(",\"file\":");
+        json_string(&mut line, file);
+    }
+    if let Some(no) = record.line() {
+        let _ = write!(line, ",\"line\":{}", no);
+    }
+    line.push_str(",\"message\":");
+    json_string(&mut line, &record.args().to_string());
+    line.push_str("}\n");
+    line
+}
+
+fn json_string(buf: &mut String, s: &str) {
+    buf.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(buf, "\\u{:04x}", c as u32);
+            }
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+}
+
+/// Formats record in journald native protocol: `KEY=value` lines, with the
+/// values containing newlines given as the key, newline, 64-bit
+/// little-endian value length and the value itself
+#[cfg(unix)]
+fn journald_record(ident: &str, record: &Record) -> Vec<u8> {
+    let mut data = vec![];
+    let mut field = |key: &str, value: &str| {
+        data.extend_from_slice(key.as_bytes());
+        if value.contains('\n') {
+            data.push(b'\n');
+            data.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            data.push(b'=');
+        }
+        data.extend_from_slice(value.as_bytes());
+        data.push(b'\n');
+    };
+    field("PRIORITY", &syslog_severity(record.level()).to_string());
+    field("SYSLOG_IDENTIFIER", ident);
+    field("MESSAGE", &record.args().to_string());
+    field("TARGET", record.target());
+    if let Some(module) = record.module_path() {
+        field("CODE_MODULE", module);
+    }
+    if let Some(file) = record.file() {
+        field("CODE_FILE", file);
+    }
+    if let Some(no) = record.line() {
+        field("CODE_LINE", &no.to_string());
+    }
+    data
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_log_filter() {
+        let filter = LogFilter::from_str(
+            "info, rgb_node::fungibled=debug,rgb_node::fungibled::cache=off",
+        )
+        .unwrap();
+        assert_eq!(filter.default, Some(LevelFilter::Info));
+        assert_eq!(
+            filter.level("rgb_node::fungibled::runtime", LevelFilter::Error),
+            LevelFilter::Debug
+        );
+        assert_eq!(
+            filter.level("rgb_node::fungibled::cache::sql", LevelFilter::Error),
+            LevelFilter::Off
+        );
+        assert_eq!(
+            filter.level("rgb_node::fungibledx", LevelFilter::Error),
+            LevelFilter::Info
+        );
+        assert_eq!(filter.max_level(LevelFilter::Error), LevelFilter::Debug);
+        assert_eq!(filter.to_string().parse::<LogFilter>(), Ok(filter));
+        assert!(LogFilter::from_str("internet2=loud").is_err());
+
+        assert_eq!(
+            LogTarget::from_str("json:/var/log/rgb.json"),
+            Ok(LogTarget::Json(PathBuf::from("/var/log/rgb.json")))
+        );
+        assert!(LogTarget::from_str("json:").is_err());
+    }
+}
@@ -0,0 +1,181 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Versioning of the on-disk data layouts.
+//!
+//! Each data directory keeps the version of its layout in a small text file.
+//! On startup the version is compared against the one supported by the
+//! daemon: older layouts are upgraded by the registered migrations one
+//! version at a time, while newer layouts are refused, since they may be
+//! damaged by an outdated daemon.
+
+use std::path::Path;
+use std::{fs, io};
+
+/// Name of the file keeping data format version inside the data directory
+pub const FORMAT_FILENAME: &str = "FORMAT";
+
+/// Errors checking and upgrading data format version
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum MigrationError {
+    /// I/O error accessing data directory: {0}
+    #[from]
+    Io(io::Error),
+
+    /// data format version file `{0}` is corrupted
+    BrokenVersion(String),
+
+    /// data are stored in format version {found}, while this release
+    /// supports versions up to {supported}; please upgrade rgb-node
+    Unsupported { found: u16, supported: u16 },
+
+    /// no migration from data format version {0} is known
+    NoMigration(u16),
+
+    /// migration from data format version {from} has failed: {details}
+    Failed { from: u16, details: String },
+}
+
+/// Step upgrading data layout from version `from` to the next one
+#[derive(Clone, Copy)]
+pub struct Migration {
+    pub from: u16,
+    pub description: &'static str,
+    pub migrate: fn(&Path) -> Result<(), MigrationError>,
+}
+
+/// Migration for the layouts which were created before format versioning
+/// was introduced and are identical to the first versioned layout
+pub fn unversioned(_: &Path) -> Result<(), MigrationError> {
+    Ok(())
+}
+
+/// Reads data format version from the data directory, if it was written
+pub fn read_version(dir: &Path) -> Result<Option<u16>, MigrationError> {
+    let filename = dir.join(FORMAT_FILENAME);
+    if !filename.exists() {
+        return Ok(None);
+    }
+    let data = fs::read_to_string(&filename)?;
+    data.trim().parse().map(Some).map_err(|_| {
+        MigrationError::BrokenVersion(filename.display().to_string())
+    })
+}
+
+pub fn write_version(dir: &Path, version: u16) -> Result<(), MigrationError> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(FORMAT_FILENAME), format!("{}\n", version))?;
+    Ok(())
+}
+
+/// Brings data in the directory to the `current` format version, applying
+/// migrations in order. Directories without data are marked with the
+/// current version; directories with data but without version file are
+/// considered to have version 0. Returns the version the data were found
+/// in.
+pub fn upgrade(
+    dir: &Path,
+    current: u16,
+    migrations: &[Migration],
+) -> Result<u16, MigrationError> {
+    let found = match read_version(dir)? {
+        Some(version) => version,
+        None if !dir.exists() || fs::read_dir(dir)?.next().is_none() => {
+            write_version(dir, current)?;
+            return Ok(current);
+        }
+        None => 0,
+    };
+    if found > current {
+        Err(MigrationError::Unsupported {
+            found,
+            supported: current,
+        })?
+    }
+
+    let mut version = found;
+    while version < current {
+        let migration = migrations
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or(MigrationError::NoMigration(version))?;
+        info!(
+            "Upgrading data in {:?} from format version {}: {}",
+            dir, version, migration.description
+        );
+        (migration.migrate)(dir)?;
+        version += 1;
+        // Version is written after each step, so an interrupted upgrade
+        // continues from the last completed migration
+        write_version(dir, version)?;
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    fn rename(dir: &Path) -> Result<(), MigrationError> {
+        fs::rename(dir.join("old"), dir.join("new"))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let migrations = [
+            Migration {
+                from: 0,
+                description: "versioning",
+                migrate: unversioned,
+            },
+            Migration {
+                from: 1,
+                description: "renaming",
+                migrate: rename,
+            },
+        ];
+
+        let dir = env::temp_dir()
+            .join(format!("rgb-node-migration-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(upgrade(&dir, 2, &migrations).unwrap(), 2);
+        assert_eq!(read_version(&dir).unwrap(), Some(2));
+
+        fs::remove_file(dir.join(FORMAT_FILENAME)).unwrap();
+        fs::write(dir.join("old"), b"data").unwrap();
+        assert_eq!(upgrade(&dir, 2, &migrations).unwrap(), 0);
+        assert!(dir.join("new").exists());
+        assert_eq!(read_version(&dir).unwrap(), Some(2));
+
+        match upgrade(&dir, 1, &migrations) {
+            Err(MigrationError::Unsupported {
+                found: 2,
+                supported: 1,
+            }) => {}
+            other => panic!("unexpected upgrade result {:?}", other),
+        }
+        match upgrade(&dir, 3, &migrations) {
+            Err(MigrationError::NoMigration(2)) => {}
+            other => panic!("unexpected upgrade result {:?}", other),
+        }
+
+        fs::write(dir.join(FORMAT_FILENAME), b"v2").unwrap();
+        assert!(read_version(&dir).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -13,11 +13,34 @@
 
 #[macro_use]
 mod macros;
+mod amount;
+pub mod anchor;
+#[cfg(feature = "_rpc")]
+pub mod backup;
 mod bech32data;
+pub mod compression;
+pub mod config;
+#[cfg(feature = "server")]
+pub mod daemon;
+mod data_format;
 pub mod file;
+pub mod logging;
 mod magic_numbers;
+pub mod migration;
+pub mod psbt;
+pub mod reload;
+#[cfg(feature = "_rpc")]
+pub mod s3;
 mod seal_spec;
+pub mod shutdown;
+mod spawned;
 
+#[cfg(feature = "serde")]
+pub use amount::AtomicValueString;
+pub use amount::{format_amount, parse_amount, AmountParseError};
+pub use anchor::AnchorContainer;
 pub use bech32data::{FromBech32Data, ToBech32Data};
+pub use data_format::DataFormat;
 pub use magic_numbers::MagicNumber;
 pub use seal_spec::SealSpec;
+pub use spawned::{block_on, Spawned};
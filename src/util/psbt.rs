@@ -0,0 +1,197 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Proprietary PSBT key-value pairs carrying RGB data of the transfer, so
+//! external signers and coordinators are able to recognize RGB witness
+//! transactions and to preserve RGB data while manipulating them. All keys
+//! use `RGB` prefix, shared with the output keys defined by RGB Core
+//! ([`rgb::PSBT_OUT_PUBKEY`] and [`rgb::PSBT_OUT_TWEAK`]); all key data and
+//! values are strict-encoded.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::util::psbt::raw::ProprietaryKey;
+use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+use bitcoin::OutPoint;
+use lnpbp::strict_encoding::{
+    self, strict_deserialize, strict_serialize, StrictDecode, StrictEncode,
+};
+use rgb::{Anchor, ContractId, Transition, PSBT_PREFIX};
+
+/// Global key marking contract which has state transition anchored in the
+/// transaction. Key data is the contract id, value is empty.
+pub const PSBT_GLOBAL_CONTRACT: u8 = 0x10;
+
+/// Global key with the state transition anchored in the transaction. Key
+/// data is the contract id, value is the transition.
+pub const PSBT_GLOBAL_TRANSITION: u8 = 0x11;
+
+/// Global key with the anchor committing to the contract state transition.
+/// Key data is the contract id, value is the anchor.
+pub const PSBT_GLOBAL_ANCHOR: u8 = 0x12;
+
+/// Input key marking input which spends allocations of the contract. Key
+/// data is the contract id, value is empty.
+pub const PSBT_IN_CONTRACT: u8 = 0x20;
+
+fn key(subtype: u8, contract_id: ContractId) -> ProprietaryKey {
+    ProprietaryKey {
+        prefix: PSBT_PREFIX.to_vec(),
+        subtype,
+        key: strict_serialize(&contract_id)
+            .expect("Strict encoding of contract id can't fail"),
+    }
+}
+
+/// Contract ids from the data of the keys of the given subtype; keys with
+/// malformed data are ignored
+fn contract_ids(
+    map: &BTreeMap<ProprietaryKey, Vec<u8>>,
+    subtype: u8,
+) -> BTreeSet<ContractId> {
+    map.keys()
+        .filter(|key| key.prefix == PSBT_PREFIX && key.subtype == subtype)
+        .filter_map(|key| strict_deserialize(&key.key).ok())
+        .collect()
+}
+
+/// Decodes values of the keys of the given subtype, indexed by the contract
+/// id taken from the key data
+fn values<T: StrictDecode>(
+    map: &BTreeMap<ProprietaryKey, Vec<u8>>,
+    subtype: u8,
+) -> Result<BTreeMap<ContractId, T>, strict_encoding::Error> {
+    map.iter()
+        .filter(|(key, _)| key.prefix == PSBT_PREFIX && key.subtype == subtype)
+        .map(|(key, value)| {
+            Ok((strict_deserialize(&key.key)?, strict_deserialize(value)?))
+        })
+        .collect()
+}
+
+fn insert(
+    map: &mut BTreeMap<ProprietaryKey, Vec<u8>>,
+    subtype: u8,
+    contract_id: ContractId,
+    value: &impl StrictEncode,
+) {
+    map.insert(
+        key(subtype, contract_id),
+        strict_serialize(value).expect("In-memory encoding can't fail"),
+    );
+}
+
+/// Adds contract ids, state transitions and anchors of the transfer to the
+/// global map of the PSBT
+pub fn embed_transfer(
+    psbt: &mut Psbt,
+    transitions: &BTreeMap<ContractId, Transition>,
+    anchors: &BTreeMap<ContractId, Anchor>,
+) {
+    let map = &mut psbt.global.proprietary;
+    for (contract_id, transition) in transitions {
+        map.insert(key(PSBT_GLOBAL_CONTRACT, *contract_id), vec![]);
+        insert(map, PSBT_GLOBAL_TRANSITION, *contract_id, transition);
+    }
+    for (contract_id, anchor) in anchors {
+        insert(map, PSBT_GLOBAL_ANCHOR, *contract_id, anchor);
+    }
+}
+
+/// Marks PSBT inputs spending the outpoints with allocations of the
+/// contract; returns number of the marked inputs
+pub fn mark_inputs(
+    psbt: &mut Psbt,
+    contract_id: ContractId,
+    outpoints: &BTreeSet<OutPoint>,
+) -> usize {
+    let mut count = 0;
+    for (txin, input) in psbt
+        .global
+        .unsigned_tx
+        .input
+        .iter()
+        .zip(psbt.inputs.iter_mut())
+    {
+        if outpoints.contains(&txin.previous_output) {
+            input
+                .proprietary
+                .insert(key(PSBT_IN_CONTRACT, contract_id), vec![]);
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Contracts which have state transitions anchored in the transaction
+pub fn contracts(psbt: &Psbt) -> BTreeSet<ContractId> {
+    contract_ids(&psbt.global.proprietary, PSBT_GLOBAL_CONTRACT)
+}
+
+/// State transitions anchored in the transaction
+pub fn transitions(
+    psbt: &Psbt,
+) -> Result<BTreeMap<ContractId, Transition>, strict_encoding::Error> {
+    values(&psbt.global.proprietary, PSBT_GLOBAL_TRANSITION)
+}
+
+/// Anchors of the contract state transitions
+pub fn anchors(
+    psbt: &Psbt,
+) -> Result<BTreeMap<ContractId, Anchor>, strict_encoding::Error> {
+    values(&psbt.global.proprietary, PSBT_GLOBAL_ANCHOR)
+}
+
+/// Contracts whose allocations are spent by the PSBT input with the given
+/// index
+pub fn input_contracts(psbt: &Psbt, index: usize) -> BTreeSet<ContractId> {
+    psbt.inputs
+        .get(index)
+        .map(|input| contract_ids(&input.proprietary, PSBT_IN_CONTRACT))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Script, Transaction, TxIn, Txid};
+
+    #[test]
+    fn test_transfer_keys() {
+        let outpoint = OutPoint::new(Txid::from_inner([1u8; 32]), 0);
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                script_sig: Script::new(),
+                sequence: 0xFFFFFFFF,
+                witness: vec![],
+            }],
+            output: vec![],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        let contract_id = ContractId::default();
+        let bundle = bmap! { contract_id => Transition::default() };
+
+        embed_transfer(&mut psbt, &bundle, &bmap! {});
+        assert_eq!(contracts(&psbt), bset! { contract_id });
+        assert_eq!(transitions(&psbt).unwrap(), bundle);
+        assert!(anchors(&psbt).unwrap().is_empty());
+
+        assert_eq!(mark_inputs(&mut psbt, contract_id, &bset! {outpoint}), 1);
+        assert_eq!(input_contracts(&psbt, 0), bset! { contract_id });
+        assert!(input_contracts(&psbt, 1).is_empty());
+    }
+}
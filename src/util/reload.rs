@@ -0,0 +1,48 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Reload of the daemon tunable settings without restart. Each SIGHUP
+//! increases process-wide reload generation; daemon run loops compare it
+//! with the generation of their current configuration and re-read the
+//! configuration file once it changes. `ReloadConfig` RPC request reloads
+//! configuration of the daemon receiving it only.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use log::LevelFilter;
+
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Current reload generation
+#[inline]
+pub fn generation() -> usize {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+/// Requests all daemons run by the process to reload their configuration,
+/// returning the new reload generation
+#[inline]
+pub fn request() -> usize {
+    GENERATION.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+/// Maximum log level matching the daemon verbosity setting
+pub fn verbosity(verbose: u8) -> LevelFilter {
+    match verbose {
+        0 => LevelFilter::Error,
+        1 => LevelFilter::Warn,
+        2 => LevelFilter::Info,
+        3 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
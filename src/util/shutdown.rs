@@ -0,0 +1,131 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Graceful daemon shutdown. Daemon run loops check the shutdown flag after
+//! each processed request and, once it is set, persist their state and exit
+//! instead of waiting for the next request.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "server")]
+use std::sync::{Mutex, Once};
+#[cfg(feature = "server")]
+use std::thread;
+
+#[cfg(feature = "server")]
+use internet2::zmqsocket::{ZmqSocketAddr, ZmqType};
+#[cfg(feature = "server")]
+use internet2::{session, transport, Session};
+#[cfg(feature = "server")]
+use nix::sys::signal::{SigSet, Signal};
+
+#[cfg(feature = "server")]
+use super::reload;
+#[cfg(feature = "server")]
+use crate::error::BootstrapError;
+
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "server")]
+lazy_static::lazy_static! {
+    /// RPC endpoints of the daemons run by the process together with the
+    /// messages waking them up
+    static ref WAKEUPS: Mutex<Vec<(ZmqSocketAddr, Vec<u8>)>> =
+        Mutex::new(vec![]);
+}
+
+/// Whether the process was requested to shut down
+#[inline]
+pub fn is_requested() -> bool {
+    SHUTDOWN.load(Ordering::SeqCst)
+}
+
+/// Requests all daemons run by the process to shut down after they finish
+/// processing of the current request
+#[inline]
+pub fn request() {
+    SHUTDOWN.store(true, Ordering::SeqCst)
+}
+
+/// Makes daemon with the given RPC endpoint to shut down on SIGTERM or
+/// SIGINT and to reload its configuration on SIGHUP. The signals are blocked for the calling thread and all threads
+/// spawned by it afterwards, so the function must be called before the
+/// daemon starts any threads; the signals are handled by a dedicated thread,
+/// which sets the shutdown flag (or requests configuration reload) and sends
+/// the `wakeup` request to the daemon, so the daemon run loop waiting for
+/// requests can notice it.
+#[cfg(feature = "server")]
+pub fn install(
+    rpc_endpoint: ZmqSocketAddr,
+    wakeup: Vec<u8>,
+) -> Result<(), BootstrapError> {
+    static START: Once = Once::new();
+
+    let mut signals = SigSet::empty();
+    signals.add(Signal::SIGTERM);
+    signals.add(Signal::SIGINT);
+    signals.add(Signal::SIGHUP);
+    signals.thread_block().map_err(|err| err.to_string())?;
+
+    WAKEUPS
+        .lock()
+        .map_err(|_| BootstrapError::Other)?
+        .push((rpc_endpoint, wakeup));
+
+    START.call_once(|| {
+        thread::spawn(move || loop {
+            let shutdown = match signals.wait() {
+                Ok(Signal::SIGHUP) => {
+                    info!("Received SIGHUP, reloading configuration");
+                    reload::request();
+                    false
+                }
+                Ok(signal) => {
+                    info!("Received {}, shutting down", signal);
+                    request();
+                    true
+                }
+                Err(err) => {
+                    error!("Unable to wait for signals: {}", err);
+                    return;
+                }
+            };
+            let wakeups = WAKEUPS
+                .lock()
+                .map(|wakeups| wakeups.clone())
+                .unwrap_or_default();
+            for (endpoint, message) in wakeups {
+                if let Err(err) = wake(&endpoint, &message) {
+                    warn!("Unable to wake up daemon at {}: {}", endpoint, err);
+                }
+            }
+            if shutdown {
+                return;
+            }
+        });
+    });
+    Ok(())
+}
+
+/// Sends request to the daemon without waiting for the reply
+#[cfg(feature = "server")]
+fn wake(
+    endpoint: &ZmqSocketAddr,
+    message: &[u8],
+) -> Result<(), transport::Error> {
+    let mut session =
+        session::Raw::with_zmq_unencrypted(ZmqType::Req, endpoint, None, None)?;
+    // Give the message some time to be delivered before the socket is closed
+    session.as_socket().set_linger(1000)?;
+    session.send_raw_message(message)?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::sync::{Arc, Mutex};
+use std::task::Wake;
+use std::thread::{self, Thread};
+
+struct State<T> {
+    output: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// Future resolved with the result of a blocking operation, which is run on
+/// a separate thread, so the future does not block the executor
+pub struct Spawned<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T> Spawned<T>
+where
+    T: Send + 'static,
+{
+    pub fn spawn(operation: impl FnOnce() -> T + Send + 'static) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            output: None,
+            waker: None,
+        }));
+        let shared = state.clone();
+        thread::spawn(move || {
+            let output = operation();
+            let mut state = shared.lock().expect("spawned state lock poisoned");
+            state.output = Some(output);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        Self { state }
+    }
+}
+
+impl<T> Future for Spawned<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().expect("spawned state lock poisoned");
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs the future to completion on the current thread. Intended for the
+/// synchronous callers (like FFI bindings) which do not run any executor.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+// RGB standard library
+// Written in 2021 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Consignment validation which does not depend on the node daemons and their
+//! ZMQ transport, so it can be used by `wasm32` builds of the crate (for
+//! instance by web wallets verifying incoming transfers locally). Bitcoin
+//! transactions are resolved by the caller, which may use any available
+//! source like a block explorer API.
+
+use bitcoin::{Transaction, Txid};
+use rgb::{validation, Consignment, Schema, SchemaId};
+use wallet::resolvers::{TxResolver, TxResolverError};
+
+/// Transaction resolver calling the provided function
+pub struct FnResolver<F>(pub F)
+where
+    F: Fn(&Txid) -> Result<Option<(Transaction, u64)>, TxResolverError>;
+
+impl<F> TxResolver for FnResolver<F>
+where
+    F: Fn(&Txid) -> Result<Option<(Transaction, u64)>, TxResolverError>,
+{
+    fn resolve(
+        &self,
+        txid: &Txid,
+    ) -> Result<Option<(Transaction, u64)>, TxResolverError> {
+        (self.0)(txid)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum Error {
+    /// consignment uses schema {0} which is not known to the library; it
+    /// must be provided explicitly
+    UnknownSchema(SchemaId),
+}
+
+/// Returns schema with the given id if it is one of the standard schemata
+/// compiled into the library
+#[cfg_attr(not(feature = "fungibles"), allow(unused_variables))]
+pub fn known_schema(schema_id: SchemaId) -> Option<Schema> {
+    #[cfg(feature = "fungibles")]
+    {
+        let schema = rgb20::schema::schema();
+        if schema.schema_id() == schema_id {
+            return Some(schema);
+        }
+    }
+    None
+}
+
+/// Validates consignment against the given schema. This is the same
+/// procedure which is used by the stash daemon serving validation requests.
+pub fn validate_with_schema(
+    consignment: &Consignment,
+    schema: &Schema,
+    resolver: impl TxResolver,
+) -> validation::Status {
+    consignment.validate(schema, resolver)
+}
+
+/// Validates consignment against one of the standard schemata compiled into
+/// the library
+pub fn validate(
+    consignment: &Consignment,
+    resolver: impl TxResolver,
+) -> Result<validation::Status, Error> {
+    let schema_id = consignment.genesis.schema_id();
+    let schema =
+        known_schema(schema_id).ok_or(Error::UnknownSchema(schema_id))?;
+    Ok(validate_with_schema(consignment, &schema, resolver))
+}